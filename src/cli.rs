@@ -0,0 +1,23 @@
+//! Value parsers for command line arguments.
+use std::time::Duration;
+
+/// Parse a duration given in seconds, fractions are allowed, e.g. `1.5`.
+pub(crate) fn parse_secs(s: &str) -> Result<Duration, String> {
+    let secs = s.parse::<f64>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_secs() {
+        assert_eq!(parse_secs("2").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_secs("1.5").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_secs("0").unwrap(), Duration::ZERO);
+        assert_eq!(parse_secs("x").unwrap_err(), "invalid float literal");
+        assert!(parse_secs("-1").is_err());
+        assert!(parse_secs("inf").is_err());
+    }
+}
@@ -0,0 +1,554 @@
+//! Per-workspace configuration file.
+//!
+//! Projects can ship a [`CONFIG_FILE_NAME`] in the workspace root, so project-specific settings travel with the
+//! repository instead of the MCP client config. Keys are the long names of the CLI arguments, e.g.
+//! `lsp-ready-timeout = 60` or `rust-features = ["serde"]`. Values passed via CLI or environment take precedence.
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::{ArgAction, ArgMatches, Parser, parser::ValueSource};
+use itertools::Itertools;
+use rmcp::schemars;
+use toml::{Table, Value};
+
+/// Replaces values of secrets, e.g. auth tokens, in the effective configuration.
+const REDACTED: &str = "<redacted>";
+
+/// Name of the config file within the workspace root.
+pub(crate) const CONFIG_FILE_NAME: &str = ".common-sense-coder.toml";
+
+/// Arguments that cannot be set by the config file.
+///
+/// The repository may not be trusted, so it must not run commands, expose the server, change where data is written, or
+/// widen which files are shown.
+const CLI_ONLY: &[&str] = &[
+    "args",
+    "batch",
+    "call",
+    "custom-lsp-arg",
+    "custom-lsp-command",
+    "custom-lsp-config",
+    "custom-lsp-init-options",
+    "daemon",
+    "daemon-dir",
+    "daemon-grace-period",
+    "dependency-source-root",
+    "enable-lsp-request-tool",
+    "enable-run-check-tool",
+    "enable-run-test-tool",
+    "help",
+    "install-missing-lsp",
+    "intercept-io",
+    "intercept-io-format",
+    "intercept-io-redact",
+    "listen",
+    "listen-allowed-host",
+    "listen-auth-token",
+    "listen-without-auth",
+    "lsp-arg",
+    "lsp-cache-dir",
+    "lsp-command",
+    "lsp-cwd",
+    "lsp-env",
+    "lsp-init-options",
+    "lsp-replay",
+    "read-only",
+    "redact-secrets",
+    "rust-check-command",
+    "rust-check-override-command",
+    "version",
+    "workspace",
+    "workspace-jail",
+    "workspace-jail-allow",
+];
+
+/// CLI arguments and the config file of the workspace that they are merged with.
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigFile {
+    argv: Vec<OsString>,
+
+    /// Location of the config file, which may not exist (yet). `None` without a workspace root.
+    path: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    /// Parse CLI arguments and merge the config file of the workspace, if there is one.
+    ///
+    /// `root` picks the workspace root from the CLI arguments.
+    pub(crate) fn parse<P>(root: impl FnOnce(&P) -> Option<PathBuf>) -> Result<(P, Self)>
+    where
+        P: Parser,
+    {
+        let argv = std::env::args_os().collect::<Vec<_>>();
+        let matches = P::command().get_matches_from(&argv);
+        let args = P::from_arg_matches(&matches).context("parse CLI arguments")?;
+
+        let this = Self {
+            path: root(&args).map(|root| root.join(CONFIG_FILE_NAME)),
+            argv,
+        };
+        let args = match this.merge(&matches)? {
+            Some(args) => args,
+            None => args,
+        };
+        Ok((args, this))
+    }
+
+    /// Location of the config file, which may not exist.
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Parse the CLI arguments again and merge the current content of the config file.
+    pub(crate) fn reparse<P>(&self) -> Result<P>
+    where
+        P: Parser,
+    {
+        let matches = P::command()
+            .try_get_matches_from(&self.argv)
+            .context("parse CLI arguments")?;
+        match self.merge(&matches)? {
+            Some(args) => Ok(args),
+            None => P::from_arg_matches(&matches).context("parse CLI arguments"),
+        }
+    }
+
+    /// Current content of the config file, empty if it does not exist.
+    pub(crate) fn table(&self) -> Result<Table> {
+        match self.existing_path() {
+            Some(path) => load(path).with_context(|| format!("config file: {}", path.display())),
+            None => Ok(Table::new()),
+        }
+    }
+
+    /// Effective configuration, i.e. CLI arguments merged with the current content of the config file and defaults.
+    pub(crate) fn effective<P>(&self) -> Result<EffectiveConfig>
+    where
+        P: Parser,
+    {
+        let command = P::command();
+        let cli = command
+            .clone()
+            .try_get_matches_from(&self.argv)
+            .context("parse CLI arguments")?;
+        let path = self.existing_path();
+        let merged = match path {
+            Some(path) => command
+                .clone()
+                .try_get_matches_from(self.merged_argv::<P>(path, &cli)?)
+                .with_context(|| format!("apply config file: {}", path.display()))?,
+            None => cli.clone(),
+        };
+
+        let settings = command
+            .get_arguments()
+            .filter_map(|arg| {
+                let name = arg.get_long()?;
+                let id = arg.get_id().as_str();
+                let source = match merged.value_source(id) {
+                    Some(ValueSource::CommandLine)
+                        if cli.value_source(id) == Some(ValueSource::CommandLine) =>
+                    {
+                        Some(Source::Cli)
+                    }
+                    Some(ValueSource::CommandLine) => Some(Source::ConfigFile),
+                    Some(ValueSource::EnvVariable) => Some(Source::Env),
+                    Some(ValueSource::DefaultValue) => Some(Source::Default),
+                    Some(_) | None => None,
+                };
+                let values = match arg.get_action() {
+                    ArgAction::SetTrue | ArgAction::SetFalse => {
+                        vec![merged.get_flag(id).to_string()]
+                    }
+                    ArgAction::Count => vec![merged.get_count(id).to_string()],
+                    _ => merged
+                        .get_raw(id)
+                        .into_iter()
+                        .flatten()
+                        .map(|value| value.to_string_lossy().into_owned())
+                        .collect(),
+                };
+                let values = if arg.is_hide_env_values_set() && !values.is_empty() {
+                    vec![REDACTED.to_owned()]
+                } else {
+                    values
+                };
+                Some(Setting {
+                    name: name.to_owned(),
+                    values,
+                    source,
+                })
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+
+        Ok(EffectiveConfig {
+            config_file: path.map(|path| path.display().to_string()),
+            settings,
+        })
+    }
+
+    /// Report the effective configuration of `P` on demand, see [`effective`](Self::effective).
+    pub(crate) fn reporter<P>(&self) -> ConfigReporter
+    where
+        P: Parser,
+    {
+        ConfigReporter {
+            config_file: self.clone(),
+            effective: Self::effective::<P>,
+        }
+    }
+
+    /// Location of the config file, `None` if it does not exist.
+    fn existing_path(&self) -> Option<&Path> {
+        self.path.as_deref().filter(|path| path.is_file())
+    }
+
+    /// Parse CLI arguments merged with the config file, `None` if there is no config file.
+    fn merge<P>(&self, matches: &ArgMatches) -> Result<Option<P>>
+    where
+        P: Parser,
+    {
+        let Some(path) = self.existing_path() else {
+            return Ok(None);
+        };
+
+        let args = P::try_parse_from(self.merged_argv::<P>(path, matches)?)
+            .with_context(|| format!("apply config file: {}", path.display()))?;
+        Ok(Some(args))
+    }
+
+    /// CLI arguments with those of the config file in front, so that the CLI arguments take precedence.
+    fn merged_argv<P>(&self, path: &Path, matches: &ArgMatches) -> Result<Vec<OsString>>
+    where
+        P: Parser,
+    {
+        let file_args = load(path)
+            .and_then(|table| file_args(&P::command(), matches, &table))
+            .with_context(|| format!("config file: {}", path.display()))?;
+        Ok(self
+            .argv
+            .iter()
+            .take(1)
+            .cloned()
+            .chain(file_args.into_iter().map(OsString::from))
+            .chain(self.argv.iter().skip(1).cloned())
+            .collect())
+    }
+}
+
+/// Computes the effective configuration on demand, so it reflects changes of the config file.
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigReporter {
+    config_file: ConfigFile,
+    effective: fn(&ConfigFile) -> Result<EffectiveConfig>,
+}
+
+impl ConfigReporter {
+    pub(crate) fn effective(&self) -> Result<EffectiveConfig> {
+        (self.effective)(&self.config_file)
+    }
+}
+
+/// Where the value of a setting comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Source {
+    Cli,
+    Env,
+    ConfigFile,
+    Default,
+}
+
+/// Effective value of a setting.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub(crate) struct Setting {
+    /// long name of the CLI argument, also the key within the config file
+    name: String,
+
+    /// values, secrets are redacted
+    values: Vec<String>,
+
+    /// where the value comes from, missing if the setting is not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Source>,
+}
+
+/// Effective configuration, see [`ConfigFile::effective`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub(crate) struct EffectiveConfig {
+    /// config file that was merged, missing if there is none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_file: Option<String>,
+
+    settings: Vec<Setting>,
+}
+
+/// Keys whose values differ between two versions of the config file.
+pub(crate) fn changed_keys<'a>(old: &'a Table, new: &'a Table) -> BTreeSet<&'a str> {
+    old.keys()
+        .chain(new.keys())
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| key.as_str())
+        .collect()
+}
+
+fn load(path: &Path) -> Result<Table> {
+    let s = std::fs::read_to_string(path).context("read")?;
+    toml::from_str(&s).context("parse")
+}
+
+/// Turn config file into CLI arguments, skipping those that were passed via CLI or environment.
+fn file_args(command: &clap::Command, matches: &ArgMatches, table: &Table) -> Result<Vec<String>> {
+    let mut args = vec![];
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+        ensure!(
+            !CLI_ONLY.contains(&long.as_str()),
+            "`{key}` can only be passed via CLI or environment"
+        );
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            bail!("unknown key `{key}`, use the long name of a CLI argument");
+        };
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, Value::Boolean(b)) | (ArgAction::SetFalse, Value::Boolean(b)) => {
+                if *b == matches!(arg.get_action(), ArgAction::SetTrue) {
+                    args.push(format!("--{long}"));
+                }
+            }
+            (ArgAction::Count, Value::Integer(n)) => {
+                for _ in 0..*n {
+                    args.push(format!("--{long}"));
+                }
+            }
+            (ArgAction::Append, Value::Array(values)) => {
+                for value in values {
+                    args.push(format!("--{long}={}", scalar(key, value)?));
+                }
+            }
+            (_, value) => {
+                args.push(format!("--{long}={}", scalar(key, value)?));
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Render value as it would be passed via CLI.
+///
+/// Tables are passed as JSON.
+fn scalar(key: &str, value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::String(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(d) => d.to_string(),
+        Value::Table(table) => serde_json::to_string(table).context("serialize table")?,
+        Value::Array(_) => bail!("`{key}` cannot be repeated"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestArgs {
+        #[clap(long)]
+        workspace: Vec<PathBuf>,
+
+        #[clap(long)]
+        timeout: Option<u64>,
+
+        #[clap(long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        #[clap(long)]
+        flag: bool,
+
+        #[clap(short, long, action = ArgAction::Count)]
+        verbose: u8,
+
+        #[clap(long)]
+        options: Option<String>,
+
+        #[clap(long, default_value = "relative")]
+        style: String,
+
+        #[clap(long, env = "COMMON_SENSE_CODER_TEST_SECRET", hide_env_values = true)]
+        secret: Option<String>,
+    }
+
+    fn file_args_for(cli: &[&str], file: &str) -> Result<Vec<String>> {
+        let matches = TestArgs::command()
+            .get_matches_from(std::iter::once("test").chain(cli.iter().copied()));
+        file_args(
+            &TestArgs::command(),
+            &matches,
+            &toml::from_str(file).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_file_args() {
+        assert_eq!(
+            file_args_for(
+                &[],
+                r#"
+                timeout = 10
+                features = ["a", "b"]
+                flag = true
+                verbose = 2
+                options = {cargo = {features = "all"}}
+                "#,
+            )
+            .unwrap(),
+            [
+                "--features=a",
+                "--features=b",
+                "--flag",
+                r#"--options={"cargo":{"features":"all"}}"#,
+                "--timeout=10",
+                "--verbose",
+                "--verbose",
+            ],
+        );
+        assert_eq!(
+            file_args_for(&[], "flag = false").unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            file_args_for(&[], "features = \"a\"").unwrap(),
+            ["--features=a"],
+        );
+    }
+
+    #[test]
+    fn test_cli_takes_precedence() {
+        assert_eq!(
+            file_args_for(
+                &["--timeout=1", "--features=c"],
+                "timeout = 10\nfeatures = [\"a\"]\nflag = true"
+            )
+            .unwrap(),
+            ["--flag"],
+        );
+    }
+
+    #[test]
+    fn test_reparse() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = ConfigFile {
+            argv: ["test", "--features=c"].map(OsString::from).to_vec(),
+            path: Some(dir.path().join(CONFIG_FILE_NAME)),
+        };
+        let args = config_file.reparse::<TestArgs>().unwrap();
+        assert_eq!((args.timeout, args.features), (None, vec!["c".to_owned()]));
+        assert!(config_file.table().unwrap().is_empty());
+
+        std::fs::write(
+            config_file.path().unwrap(),
+            "timeout = 10\nfeatures = [\"a\"]",
+        )
+        .unwrap();
+        let args = config_file.reparse::<TestArgs>().unwrap();
+        assert_eq!(
+            (args.timeout, args.features),
+            (Some(10), vec!["c".to_owned()])
+        );
+        assert_eq!(config_file.table().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_effective() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = ConfigFile {
+            argv: ["test", "--features=c", "--secret=s", "-v"]
+                .map(OsString::from)
+                .to_vec(),
+            path: Some(dir.path().join(CONFIG_FILE_NAME)),
+        };
+        std::fs::write(
+            config_file.path().unwrap(),
+            "timeout = 10\nfeatures = [\"a\"]",
+        )
+        .unwrap();
+
+        let config = config_file.reporter::<TestArgs>().effective().unwrap();
+        assert_eq!(
+            config.config_file,
+            Some(config_file.path().unwrap().display().to_string()),
+        );
+        let settings = config
+            .settings
+            .iter()
+            .map(|setting| {
+                (
+                    setting.name.as_str(),
+                    setting.values.join(","),
+                    setting.source,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            settings,
+            [
+                ("features", "c".to_owned(), Some(Source::Cli)),
+                ("flag", "false".to_owned(), Some(Source::Default)),
+                ("options", "".to_owned(), None),
+                ("secret", REDACTED.to_owned(), Some(Source::Cli)),
+                ("style", "relative".to_owned(), Some(Source::Default)),
+                ("timeout", "10".to_owned(), Some(Source::ConfigFile)),
+                ("verbose", "1".to_owned(), Some(Source::Cli)),
+                ("workspace", "".to_owned(), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let old = toml::from_str::<Table>("a = 1\nb = 2\nc = 3").unwrap();
+        let new = toml::from_str::<Table>("a = 1\nb = 3\nd = 4").unwrap();
+        assert_eq!(
+            changed_keys(&old, &new).into_iter().collect::<Vec<_>>(),
+            ["b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert_eq!(
+            file_args_for(&[], "nope = 1").unwrap_err().to_string(),
+            "unknown key `nope`, use the long name of a CLI argument",
+        );
+        assert_eq!(
+            file_args_for(&[], "workspace = [\"/\"]")
+                .unwrap_err()
+                .to_string(),
+            "`workspace` can only be passed via CLI or environment",
+        );
+        assert_eq!(
+            file_args_for(&[], "timeout = [1, 2]")
+                .unwrap_err()
+                .to_string(),
+            "`timeout` cannot be repeated",
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Correlate tool calls with the language server traffic they cause.
+//!
+//! Every tool call gets a request ID. It is attached to the tracing span of the call, returned in error payloads, and
+//! added to the interception dump entries of all language server messages that are exchanged while the call runs.
+use std::{
+    collections::BTreeSet,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// Tool calls that are currently running, shared by all clones.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActiveRequests {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next: AtomicU64,
+    active: Mutex<BTreeSet<u64>>,
+}
+
+impl ActiveRequests {
+    /// Allocate request ID for a new tool call, which is active until the guard is dropped.
+    pub(crate) fn start(&self) -> RequestGuard {
+        let id = self.inner.next.fetch_add(1, Ordering::Relaxed) + 1;
+        self.inner.active.lock().expect("not poisoned").insert(id);
+        RequestGuard {
+            id,
+            requests: self.clone(),
+        }
+    }
+
+    /// IDs of the running tool calls, ascending.
+    pub(crate) fn active(&self) -> Vec<u64> {
+        self.inner
+            .active
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .copied()
+            .collect()
+    }
+}
+
+/// Running tool call, see [`ActiveRequests::start`].
+#[derive(Debug)]
+pub(crate) struct RequestGuard {
+    id: u64,
+    requests: ActiveRequests,
+}
+
+impl RequestGuard {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.requests
+            .inner
+            .active
+            .lock()
+            .expect("not poisoned")
+            .remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_active_requests() {
+        let requests = ActiveRequests::default();
+        assert_eq!(requests.active(), Vec::<u64>::new());
+
+        let a = requests.start();
+        let b = requests.clone().start();
+        assert_eq!((a.id(), b.id()), (1, 2));
+        assert_eq!(requests.active(), [1, 2]);
+
+        drop(a);
+        assert_eq!(requests.active(), [2]);
+        assert_eq!(requests.start().id(), 3);
+        assert_eq!(requests.active(), [2]);
+    }
+}
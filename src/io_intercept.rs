@@ -1,18 +1,29 @@
 //! Tooling to intercept IO streams to/from external sources for debugging.
 use std::{
+    collections::{BTreeMap, VecDeque},
     io::Error,
-    path::Path,
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::Context as _;
+use anyhow::{Context as _, ensure};
+use clap::{Parser, ValueEnum};
+use lsp_types::ServerInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use tokio::{
     io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
     sync::mpsc::UnboundedSender,
 };
+use tracing::{info, warn};
 
-use crate::TaskManager;
+use crate::{
+    TaskManager, constants::VERSION_STRING, correlation::ActiveRequests, lsp::workspace::Workspace,
+};
 
 /// Dyn-typed [`AsyncWrite`].
 pub(crate) type BoxWrite = Pin<Box<dyn AsyncWrite + Send>>;
@@ -20,29 +31,487 @@ pub(crate) type BoxWrite = Pin<Box<dyn AsyncWrite + Send>>;
 /// Dyn-typed [`AsyncRead`]
 pub(crate) type BoxRead = Pin<Box<dyn AsyncRead + Send>>;
 
-/// Dumps [`AsyncWrite`] data to a file.
-pub(crate) struct WriteFork {
-    inner: BoxWrite,
-    tx: UnboundedSender<Message>,
-}
+/// File name of the JSONL dump.
+const JSONL_FILE: &str = "io.jsonl";
+
+/// File name of the session metadata.
+const SESSION_FILE: &str = "session.json";
 
-impl WriteFork {
-    /// Create new fork in given directory path.
+/// Keys of JSON-RPC messages whose strings contain source code or documentation.
+///
+/// Covers document text (`text`), hover bodies (`contents`, `value`), edits (`newText`), completion documentation, and
+/// MCP resources (`blob`).
+const REDACTED_KEYS: &[&str] = &[
+    "blob",
+    "contents",
+    "documentation",
+    "newText",
+    "text",
+    "value",
+];
+
+/// Number of characters that [`Redaction::Truncate`] keeps.
+const TRUNCATE_CHARS: usize = 16;
+
+/// CLI config for IO interception.
+#[derive(Debug, Clone, Default, Parser)]
+pub(crate) struct InterceptIoCLIConfig {
+    /// Intercept IO to/from language server and MCP client for debugging.
+    ///
+    /// Every run creates a timestamped subdirectory in the provided directory, which holds the dumps (see
+    /// `--intercept-io-format`) and a `session.json` with metadata like version, arguments, and exit status.
+    #[clap(long, env = "COMMON_SENSE_CODER_INTERCEPT_IO")]
+    intercept_io: Option<PathBuf>,
+
+    /// Format of the IO dumps.
+    #[clap(
+        long,
+        default_value = "raw",
+        env = "COMMON_SENSE_CODER_INTERCEPT_IO_FORMAT"
+    )]
+    intercept_io_format: InterceptIoFormat,
+
+    /// Redact file contents, hover bodies, and document text in the JSONL dump, so it can be shared.
     ///
-    /// The directory MUST exist.
+    /// The message structure is kept. Requires `--intercept-io-format=jsonl`. Language server stderr is not captured
+    /// in this mode.
+    #[clap(long, env = "COMMON_SENSE_CODER_INTERCEPT_IO_REDACT")]
+    intercept_io_redact: Option<Redaction>,
+
+    /// Keep the last N messages exchanged with every language server in memory and offer the `debug_tail` tool.
     ///
-    /// `what` is used for logging but also as a filename.
-    pub(crate) async fn new(
-        inner: BoxWrite,
-        directory: &Path,
-        what: &'static str,
+    /// Works without `--intercept-io` and respects `--intercept-io-redact`. Use 0 to disable.
+    #[clap(long, default_value_t = 0, env = "COMMON_SENSE_CODER_DEBUG_TAIL")]
+    debug_tail: usize,
+
+    /// Session of this run, set up by [`init`](Self::init).
+    #[clap(skip)]
+    session: Option<Arc<Session>>,
+
+    /// Running tool calls, recorded with the language server messages.
+    #[clap(skip)]
+    active_requests: ActiveRequests,
+}
+
+impl InterceptIoCLIConfig {
+    /// Directory that holds the dumps of this run, if IO is intercepted.
+    pub(crate) fn directory(&self) -> Option<&Path> {
+        self.session
+            .as_ref()
+            .map(|session| session.directory.as_path())
+    }
+
+    /// Directory for the raw stderr dump of language servers, `None` if they must not be captured.
+    pub(crate) fn stderr_directory(&self) -> Option<&Path> {
+        self.intercept_io_redact
+            .is_none()
+            .then_some(self.directory())
+            .flatten()
+    }
+
+    /// Running tool calls, which are recorded with the language server messages.
+    pub(crate) fn active_requests(&self) -> ActiveRequests {
+        self.active_requests.clone()
+    }
+
+    /// Whether the recent messages are kept in memory, see [`tail`](Self::tail).
+    pub(crate) fn debug_tail_enabled(&self) -> bool {
+        self.debug_tail > 0
+    }
+
+    /// Create in-memory tail for a new connection, if enabled.
+    pub(crate) fn tail(&self) -> Option<Arc<TrafficTail>> {
+        self.debug_tail_enabled()
+            .then(|| Arc::new(TrafficTail::new(self.debug_tail)))
+    }
+
+    /// Create dump directory for this run.
+    pub(crate) async fn init(&mut self) -> anyhow::Result<()> {
+        ensure!(
+            self.intercept_io_redact.is_none()
+                || self.intercept_io_format == InterceptIoFormat::Jsonl
+                || self.intercept_io.is_none(),
+            "`--intercept-io-redact` requires `--intercept-io-format=jsonl`",
+        );
+
+        if let Some(directory) = &self.intercept_io {
+            let started = format_timestamp(SystemTime::now());
+            let directory = directory.join(format!("{started}-{}", std::process::id()));
+            info!(
+                path=%directory.display(),
+                format=?self.intercept_io_format,
+                redact=?self.intercept_io_redact,
+                "interception IO",
+            );
+
+            tokio::fs::create_dir_all(&directory)
+                .await
+                .context("create directories for IO interception")?;
+
+            let session = Session {
+                directory,
+                metadata: Mutex::new(SessionMetadata {
+                    version: VERSION_STRING,
+                    args: mask_secrets(std::env::args()),
+                    started,
+                    workspace: vec![],
+                    servers: BTreeMap::new(),
+                    exit: None,
+                }),
+            };
+            session.update(|_| {});
+            self.session = Some(Arc::new(session));
+        }
+        Ok(())
+    }
+
+    /// Record workspace in the session metadata.
+    pub(crate) fn record_workspace(&self, workspace: &Workspace) {
+        if let Some(session) = &self.session {
+            session.update(|metadata| {
+                metadata.workspace = workspace
+                    .folders()
+                    .map(|folder| folder.display().to_string())
+                    .collect();
+            });
+        }
+    }
+
+    /// Record name and version of a language server in the session metadata.
+    pub(crate) fn record_server(&self, name: &str, server_info: Option<&ServerInfo>) {
+        if let Some(session) = &self.session {
+            session.update(|metadata| {
+                metadata
+                    .servers
+                    .insert(name.to_owned(), server_info.cloned());
+            });
+        }
+    }
+
+    /// Record exit status in the session metadata.
+    pub(crate) fn record_exit(&self, res: &anyhow::Result<()>) {
+        if let Some(session) = &self.session {
+            session.update(|metadata| {
+                metadata.exit = Some(SessionExit {
+                    success: res.is_ok(),
+                    error: res.as_ref().err().map(|e| format!("{e:#}")),
+                });
+            });
+        }
+    }
+
+    /// Intercept both directions of the connection to a peer.
+    ///
+    /// Messages are also added to the `tail`, if any. Returns the streams unchanged if IO is neither intercepted nor
+    /// tailed.
+    pub(crate) async fn fork(
+        &self,
+        read: BoxRead,
+        write: BoxWrite,
+        peer: Peer<'_>,
+        tail: Option<&Arc<TrafficTail>>,
         tasks: &mut TaskManager,
-    ) -> anyhow::Result<Self> {
-        let tx = spawn_writer(directory, what, tasks).await?;
-        Ok(Self { inner, tx })
+    ) -> anyhow::Result<(BoxRead, BoxWrite)> {
+        let directory = self.directory();
+        if directory.is_none() && tail.is_none() {
+            return Ok((read, write));
+        }
+
+        let sink = |what: &str, direction: Direction| {
+            let file = directory.map(|directory| match self.intercept_io_format {
+                InterceptIoFormat::Raw => (directory.join(what), InterceptIoFormat::Raw),
+                InterceptIoFormat::Jsonl => (directory.join(JSONL_FILE), InterceptIoFormat::Jsonl),
+            });
+            let framed = tail.is_some()
+                || file
+                    .as_ref()
+                    .is_some_and(|(_, format)| *format == InterceptIoFormat::Jsonl);
+            Sink {
+                file,
+                stream: framed.then(|| {
+                    let mut stream = JsonlStream::new(peer, direction, self.intercept_io_redact);
+                    if let Peer::Lsp(_) = peer {
+                        stream.active_requests = Some(self.active_requests.clone());
+                    }
+                    stream
+                }),
+                tail: tail.cloned(),
+            }
+        };
+
+        let (read_what, write_what) = peer.raw_files();
+        let read_tx =
+            spawn_writer(sink(&read_what, Direction::Received), &read_what, tasks).await?;
+        let write_tx = spawn_writer(sink(&write_what, Direction::Sent), &write_what, tasks).await?;
+
+        Ok((
+            Box::pin(ReadFork {
+                inner: read,
+                tx: read_tx,
+            }),
+            Box::pin(WriteFork {
+                inner: write,
+                tx: write_tx,
+            }),
+        ))
     }
 }
 
+/// Format of the IO dumps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum InterceptIoFormat {
+    /// Raw bytes, one file per stream.
+    #[default]
+    Raw,
+
+    /// A single `io.jsonl` file with one entry per JSON-RPC message, tagged with timestamp, peer, and direction.
+    Jsonl,
+}
+
+/// In-memory ring buffer of the last messages of a connection, see `--debug-tail`.
+///
+/// Entries have the same shape as in the JSONL dump.
+#[derive(Debug)]
+pub(crate) struct TrafficTail {
+    capacity: usize,
+    entries: Mutex<VecDeque<Value>>,
+}
+
+impl TrafficTail {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Add entry, evicting the oldest one if full.
+    fn push(&self, entry: Value) {
+        let mut entries = self.entries.lock().expect("not poisoned");
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Last `n` entries, oldest first.
+    pub(crate) fn last(&self, n: usize) -> Vec<Value> {
+        let entries = self.entries.lock().expect("not poisoned");
+        entries
+            .iter()
+            .skip(entries.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Directory and metadata of an intercepted run.
+#[derive(Debug)]
+struct Session {
+    directory: PathBuf,
+    metadata: Mutex<SessionMetadata>,
+}
+
+impl Session {
+    /// Change metadata and write it to disk.
+    fn update(&self, f: impl FnOnce(&mut SessionMetadata)) {
+        let mut metadata = self.metadata.lock().expect("not poisoned");
+        f(&mut metadata);
+
+        let path = self.directory.join(SESSION_FILE);
+        let res = serde_json::to_vec_pretty(&*metadata)
+            .map_err(Error::from)
+            .and_then(|data| std::fs::write(&path, data));
+        if let Err(e) = res {
+            warn!(%e, path=%path.display(), "cannot write session metadata");
+        }
+    }
+}
+
+/// Content of `session.json`.
+#[derive(Debug, Serialize)]
+struct SessionMetadata {
+    version: &'static str,
+    args: Vec<String>,
+
+    /// UTC timestamp.
+    started: String,
+
+    /// Workspace folders, empty until the workspace is known.
+    workspace: Vec<String>,
+
+    /// Info reported by the language servers, keyed by programming language.
+    servers: BTreeMap<String, Option<ServerInfo>>,
+
+    /// `None` while running or if the run was aborted.
+    exit: Option<SessionExit>,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionExit {
+    success: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Arguments that must not end up in dumps.
+const SECRET_ARGS: &[&str] = &["--listen-auth-token"];
+
+/// Replace values of [`SECRET_ARGS`].
+fn mask_secrets(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut masked = vec![];
+    let mut mask_next = false;
+    for arg in args {
+        if std::mem::take(&mut mask_next) {
+            masked.push("<redacted>".to_owned());
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((key, _value)) if SECRET_ARGS.contains(&key) => {
+                masked.push(format!("{key}=<redacted>"));
+            }
+            _ => {
+                mask_next = SECRET_ARGS.contains(&arg.as_str());
+                masked.push(arg);
+            }
+        }
+    }
+    masked
+}
+
+/// Format as compact UTC timestamp, e.g. `20261015T063900Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // see https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    )
+}
+
+/// How content is redacted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Redaction {
+    /// Replace with length and hash, so equal contents can still be recognized.
+    Hash,
+
+    /// Keep the first few characters.
+    Truncate,
+}
+
+impl Redaction {
+    fn apply(self, s: &str) -> String {
+        match self {
+            Self::Hash => {
+                let hash = Sha256::digest(s.as_bytes())
+                    .iter()
+                    .take(8)
+                    .map(|b| format!("{b:02x}"))
+                    .collect::<String>();
+                format!("<redacted {} bytes, sha256:{hash}>", s.len())
+            }
+            Self::Truncate => {
+                let prefix = s.chars().take(TRUNCATE_CHARS).collect::<String>();
+                if prefix.len() == s.len() {
+                    prefix
+                } else {
+                    format!("{prefix}<redacted {} bytes>", s.len() - prefix.len())
+                }
+            }
+        }
+    }
+
+    /// Redact strings of [`REDACTED_KEYS`] in a message.
+    fn redact(self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if REDACTED_KEYS.contains(&key.as_str()) {
+                        self.redact_strings(value);
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+            _ => {}
+        }
+    }
+
+    /// Redact strings and strings in arrays, objects are handled by [`redact`](Self::redact).
+    fn redact_strings(self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.apply(s),
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.redact_strings(value)),
+            value => self.redact(value),
+        }
+    }
+}
+
+/// Other side of an intercepted connection.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Peer<'a> {
+    /// MCP client, talks newline-delimited JSON-RPC via our stdin/stdout.
+    Mcp,
+
+    /// Language server with the given name, talks JSON-RPC with `Content-Length` headers via its stdin/stdout.
+    Lsp(&'a str),
+}
+
+impl Peer<'_> {
+    /// Name used in JSONL entries.
+    fn name(&self) -> String {
+        match self {
+            Self::Mcp => "mcp".to_owned(),
+            Self::Lsp(name) => format!("lsp.{name}"),
+        }
+    }
+
+    /// File names of the raw dumps of the read and the written data.
+    fn raw_files(&self) -> (String, String) {
+        match self {
+            Self::Mcp => ("mcp.stdin.txt".to_owned(), "mcp.stdout.txt".to_owned()),
+            Self::Lsp(name) => (
+                format!("lsp.{name}.stdout.txt"),
+                format!("lsp.{name}.stdin.txt"),
+            ),
+        }
+    }
+
+    fn framing(&self) -> Framing {
+        match self {
+            Self::Mcp => Framing::Lines,
+            Self::Lsp(_) => Framing::ContentLength,
+        }
+    }
+}
+
+/// Dumps [`AsyncWrite`] data to a file.
+struct WriteFork {
+    inner: BoxWrite,
+    tx: UnboundedSender<Message>,
+}
+
 impl AsyncWrite for WriteFork {
     fn poll_write(
         mut self: Pin<&mut Self>,
@@ -69,28 +538,11 @@ impl AsyncWrite for WriteFork {
 }
 
 /// Dumps [`AsyncRead`] data to a file.
-pub(crate) struct ReadFork {
+struct ReadFork {
     inner: BoxRead,
     tx: UnboundedSender<Message>,
 }
 
-impl ReadFork {
-    /// Create new fork in given directory path.
-    ///
-    /// The directory MUST exist.
-    ///
-    /// `what` is used for logging but also as a filename.
-    pub(crate) async fn new(
-        inner: BoxRead,
-        directory: &Path,
-        what: &'static str,
-        tasks: &mut TaskManager,
-    ) -> anyhow::Result<Self> {
-        let tx = spawn_writer(directory, what, tasks).await?;
-        Ok(Self { inner, tx })
-    }
-}
-
 impl AsyncRead for ReadFork {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -127,26 +579,63 @@ enum Message {
     Shutdown,
 }
 
+/// Destinations of one direction of an intercepted connection.
+#[derive(Debug)]
+struct Sink {
+    /// Dump file and its format.
+    file: Option<(PathBuf, InterceptIoFormat)>,
+
+    /// Splits data into entries, required for JSONL dumps and the tail.
+    stream: Option<JsonlStream>,
+
+    tail: Option<Arc<TrafficTail>>,
+}
+
+impl Sink {
+    /// Handle complete entry.
+    async fn entry(&self, file: Option<&mut tokio::fs::File>, entry: Value) -> anyhow::Result<()> {
+        if let Some(file) = file
+            && let Some((_, InterceptIoFormat::Jsonl)) = &self.file
+        {
+            // multiple JSONL streams share a file, so every entry must be appended at once
+            let mut line = serde_json::to_vec(&entry).expect("entry is serializable");
+            line.push(b'\n');
+            file.write_all(&line).await.context("write entry")?;
+        }
+        if let Some(tail) = &self.tail {
+            tail.push(entry);
+        }
+        Ok(())
+    }
+}
+
 /// Spawn background writer task.
 ///
 /// The task will finish after sending [`Message::Shutdown`] or after all [senders](UnboundedSender) are dropped.
 async fn spawn_writer(
-    directory: &Path,
-    what: &'static str,
+    sink: Sink,
+    what: &str,
     tasks: &mut TaskManager,
 ) -> anyhow::Result<UnboundedSender<Message>> {
-    let file = tokio::fs::File::options()
-        .append(true)
-        .create(true)
-        .open(directory.join(what))
-        .await
-        .with_context(|| format!("open {what} interception file"))?;
+    let file = match &sink.file {
+        Some((path, _)) => Some(
+            tokio::fs::File::options()
+                .append(true)
+                .create(true)
+                .open(path)
+                .await
+                .with_context(|| format!("open {what} interception file"))?,
+        ),
+        None => None,
+    };
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
     tasks.spawn(
         async move |cancel| {
             let mut file = file;
             let mut rx = rx;
+            let mut sink = sink;
+            let raw = matches!(sink.file, Some((_, InterceptIoFormat::Raw)));
 
             while let Some(msg) = tokio::select! {
                 biased;
@@ -155,10 +644,29 @@ async fn spawn_writer(
             } {
                 match msg {
                     Message::Data(data) => {
-                        file.write_all(&data).await.context("write data")?;
+                        if raw && let Some(file) = &mut file {
+                            file.write_all(&data).await.context("write data")?;
+                        }
+                        let entries = sink
+                            .stream
+                            .as_mut()
+                            .map(|stream| {
+                                stream
+                                    .framer
+                                    .push(&data)
+                                    .into_iter()
+                                    .map(|frame| stream.entry(&frame))
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or_default();
+                        for entry in entries {
+                            sink.entry(file.as_mut(), entry).await?;
+                        }
                     }
                     Message::Flush => {
-                        file.flush().await.context("flush file")?;
+                        if let Some(file) = &mut file {
+                            file.flush().await.context("flush file")?;
+                        }
                     }
                     Message::Shutdown => {
                         break;
@@ -166,8 +674,17 @@ async fn spawn_writer(
                 }
             }
 
-            file.flush().await.context("flush file")?;
-            file.shutdown().await.context("shut down file")?;
+            if let Some(stream) = &mut sink.stream
+                && let Some(rest) = stream.framer.finish()
+            {
+                let entry = stream.entry(&rest);
+                sink.entry(file.as_mut(), entry).await?;
+            }
+
+            if let Some(file) = &mut file {
+                file.flush().await.context("flush file")?;
+                file.shutdown().await.context("shut down file")?;
+            }
             Ok(())
         },
         what,
@@ -175,3 +692,373 @@ async fn spawn_writer(
 
     Ok(tx)
 }
+
+/// Direction of an intercepted message, from our point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+/// Entry of the JSONL dump.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    /// Microseconds since the UNIX epoch.
+    timestamp_us: u128,
+
+    peer: &'a str,
+
+    direction: Direction,
+
+    /// Parsed JSON-RPC message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<Value>,
+
+    /// Data that is not valid JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<String>,
+
+    /// Tool calls that were running when the message was exchanged.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    request_ids: Vec<u64>,
+}
+
+/// One direction of a connection in the JSONL dump.
+#[derive(Debug)]
+struct JsonlStream {
+    peer: String,
+    direction: Direction,
+    redaction: Option<Redaction>,
+    framer: Framer,
+
+    /// Running tool calls, only tracked for language servers.
+    active_requests: Option<ActiveRequests>,
+}
+
+impl JsonlStream {
+    fn new(peer: Peer<'_>, direction: Direction, redaction: Option<Redaction>) -> Self {
+        Self {
+            peer: peer.name(),
+            direction,
+            redaction,
+            framer: Framer::new(peer.framing()),
+            active_requests: None,
+        }
+    }
+
+    /// Convert frame to JSONL entry.
+    fn entry(&self, frame: &[u8]) -> Value {
+        let (mut message, mut raw) = match serde_json::from_slice::<Value>(frame) {
+            Ok(message) => (Some(message), None),
+            Err(_) => (None, Some(String::from_utf8_lossy(frame).into_owned())),
+        };
+        if let Some(redaction) = self.redaction {
+            if let Some(message) = &mut message {
+                redaction.redact(message);
+            }
+            if let Some(raw) = &mut raw {
+                *raw = redaction.apply(raw);
+            }
+        }
+        let entry = Entry {
+            timestamp_us: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_micros(),
+            peer: &self.peer,
+            direction: self.direction,
+            message,
+            raw,
+            request_ids: self
+                .active_requests
+                .as_ref()
+                .map(|requests| requests.active())
+                .unwrap_or_default(),
+        };
+        serde_json::to_value(&entry).expect("entry is serializable")
+    }
+}
+
+/// How messages are delimited within a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// One message per line.
+    Lines,
+
+    /// Headers, including `Content-Length`, followed by the message.
+    ContentLength,
+}
+
+/// Splits a stream into messages.
+#[derive(Debug)]
+struct Framer {
+    framing: Framing,
+    buf: Vec<u8>,
+}
+
+impl Framer {
+    fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            buf: vec![],
+        }
+    }
+
+    /// Add data and return all messages that are complete now.
+    fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(data);
+        std::iter::from_fn(|| self.next_frame())
+            .filter(|frame| !frame.is_empty())
+            .collect()
+    }
+
+    /// Remaining data of an incomplete message.
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        (!self.buf.is_empty()).then(|| std::mem::take(&mut self.buf))
+    }
+
+    fn next_frame(&mut self) -> Option<Vec<u8>> {
+        match self.framing {
+            Framing::Lines => {
+                let pos = self.buf.iter().position(|b| *b == b'\n')?;
+                let mut line = self.buf.drain(..=pos).collect::<Vec<_>>();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                Some(line)
+            }
+            Framing::ContentLength => {
+                let header_end = self.buf.windows(4).position(|w| w == b"\r\n\r\n")?;
+                let body_start = header_end + 4;
+                match content_length(&self.buf[..header_end]) {
+                    Some(len) => {
+                        let end = body_start.checked_add(len)?;
+                        if self.buf.len() < end {
+                            return None;
+                        }
+                        let frame = self.buf[body_start..end].to_vec();
+                        self.buf.drain(..end);
+                        Some(frame)
+                    }
+                    // garbage, report headers as they are
+                    None => Some(self.buf.drain(..body_start).collect()),
+                }
+            }
+        }
+    }
+}
+
+/// Parse `Content-Length` header.
+fn content_length(headers: &[u8]) -> Option<usize> {
+    std::str::from_utf8(headers)
+        .ok()?
+        .split("\r\n")
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse().ok())
+                .flatten()
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_framer_lines() {
+        let mut framer = Framer::new(Framing::Lines);
+        assert_eq!(framer.push(b"{\"a\":"), Vec::<Vec<u8>>::new());
+        assert_eq!(
+            framer.push(b"1}\n\n{\"b\":2}\r\n{"),
+            vec![b"{\"a\":1}".to_vec(), b"{\"b\":2}".to_vec()],
+        );
+        assert_eq!(framer.finish(), Some(b"{".to_vec()));
+        assert_eq!(framer.finish(), None);
+    }
+
+    #[test]
+    fn test_framer_content_length() {
+        let mut framer = Framer::new(Framing::ContentLength);
+        assert_eq!(
+            framer.push(b"Content-Length: 7\r\n\r\n{\"a\":"),
+            Vec::<Vec<u8>>::new(),
+        );
+        assert_eq!(
+            framer.push(b"1}content-length:7\r\nContent-Type: x\r\n\r\n{\"b\":2}Foo: bar\r\n\r\n"),
+            vec![
+                b"{\"a\":1}".to_vec(),
+                b"{\"b\":2}".to_vec(),
+                b"Foo: bar\r\n\r\n".to_vec(),
+            ],
+        );
+        assert_eq!(framer.finish(), None);
+    }
+
+    #[test]
+    fn test_entry() {
+        let stream = JsonlStream::new(Peer::Lsp("rust"), Direction::Sent, None);
+        let parse = |frame: &[u8]| {
+            let mut entry = stream.entry(frame);
+            assert!(entry["timestamp_us"].as_u64().unwrap() > 0);
+            entry.as_object_mut().unwrap().remove("timestamp_us");
+            entry
+        };
+
+        assert_eq!(
+            parse(br#"{"jsonrpc":"2.0","method":"exit"}"#),
+            serde_json::json!({
+                "peer": "lsp.rust",
+                "direction": "sent",
+                "message": {"jsonrpc": "2.0", "method": "exit"},
+            }),
+        );
+        assert_eq!(
+            parse(b"not json"),
+            serde_json::json!({
+                "peer": "lsp.rust",
+                "direction": "sent",
+                "raw": "not json",
+            }),
+        );
+    }
+
+    #[test]
+    fn test_entry_request_ids() {
+        let requests = ActiveRequests::default();
+        let mut stream = JsonlStream::new(Peer::Lsp("rust"), Direction::Received, None);
+        stream.active_requests = Some(requests.clone());
+
+        assert!(stream.entry(b"{}").get("request_ids").is_none());
+
+        let _a = requests.start();
+        let _b = requests.start();
+        assert_eq!(
+            stream.entry(b"{}")["request_ids"],
+            serde_json::json!([1, 2])
+        );
+    }
+
+    #[test]
+    fn test_redaction() {
+        let mut message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": {
+                    "uri": "file:///ws/src/lib.rs",
+                    "languageId": "rust",
+                    "version": 1,
+                    "text": "fn secret() {}",
+                },
+                "contents": [
+                    "plain",
+                    {"language": "rust", "value": "struct Secret;"},
+                ],
+                "value": {"kind": "begin", "title": "Indexing"},
+            },
+        });
+        Redaction::Hash.redact(&mut message);
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {
+                    "textDocument": {
+                        "uri": "file:///ws/src/lib.rs",
+                        "languageId": "rust",
+                        "version": 1,
+                        "text": "<redacted 14 bytes, sha256:cc3c71ff8840257c>",
+                    },
+                    "contents": [
+                        "<redacted 5 bytes, sha256:a116c9ed46d62077>",
+                        {"language": "rust", "value": "<redacted 14 bytes, sha256:8b6a5832eda23bc5>"},
+                    ],
+                    "value": {"kind": "begin", "title": "Indexing"},
+                },
+            }),
+        );
+
+        assert_eq!(Redaction::Truncate.apply("short"), "short");
+        assert_eq!(
+            Redaction::Truncate.apply("fn main() { println!(\"secret\"); }"),
+            "fn main() { prin<redacted 17 bytes>",
+        );
+    }
+
+    #[test]
+    fn test_redacted_entry() {
+        let stream = JsonlStream::new(Peer::Mcp, Direction::Received, Some(Redaction::Truncate));
+        let mut entry = stream.entry(
+            br#"{"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"pub struct VerySecretStruct;"}]}}"#,
+        );
+        entry.as_object_mut().unwrap().remove("timestamp_us");
+        assert_eq!(
+            entry,
+            serde_json::json!({
+                "peer": "mcp",
+                "direction": "received",
+                "message": {
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": {"content": [{"type": "text", "text": "pub struct VeryS<redacted 12 bytes>"}]},
+                },
+            }),
+        );
+
+        let mut entry = stream.entry(b"not json at all, maybe code");
+        assert_eq!(
+            entry["raw"].take(),
+            Value::from("not json at all,<redacted 11 bytes>")
+        );
+    }
+
+    #[test]
+    fn test_traffic_tail() {
+        let tail = TrafficTail::new(3);
+        assert_eq!(tail.last(10), Vec::<Value>::new());
+
+        for i in 0..5 {
+            tail.push(Value::from(i));
+        }
+        assert_eq!(
+            tail.last(10),
+            [Value::from(2), Value::from(3), Value::from(4)]
+        );
+        assert_eq!(tail.last(2), [Value::from(3), Value::from(4)]);
+        assert_eq!(tail.last(0), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let at = |secs: u64| format_timestamp(UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        assert_eq!(at(0), "19700101T000000Z");
+        assert_eq!(at(951_782_400), "20000229T000000Z");
+        assert_eq!(at(1_792_045_804), "20261015T063004Z");
+    }
+
+    #[test]
+    fn test_mask_secrets() {
+        let args = [
+            "common-sense-coder",
+            "--listen-auth-token=secret",
+            "--listen-auth-token",
+            "secret",
+            "--workspace=.",
+        ];
+        assert_eq!(
+            mask_secrets(args.map(ToOwned::to_owned)),
+            [
+                "common-sense-coder",
+                "--listen-auth-token=<redacted>",
+                "--listen-auth-token",
+                "<redacted>",
+                "--workspace=.",
+            ],
+        );
+    }
+}
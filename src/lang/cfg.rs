@@ -0,0 +1,367 @@
+//! `cfg` conditions that guard a Rust item, parsed from the attributes in the source.
+//!
+//! Symbols behind a disabled feature or in `#[cfg(test)]` modules are "missing" in other builds, so the conditions of
+//! the item itself and of all enclosing items (modules, impl blocks, functions) are collected.
+use lsp_types::Position;
+
+/// Conditions that apply at the position, outermost first, e.g. `test` or `feature = "serde"`.
+///
+/// Attributes of the item at the position are included, as long as they precede the position.
+pub(super) fn conditions(content: &str, position: Position) -> Vec<String> {
+    let mut parser = Parser::default();
+    let mut chars = content.char_indices().peekable();
+    let mut line = 0;
+    let mut line_start = 0;
+
+    while let Some((i, c)) = chars.next() {
+        if (line, (i - line_start) as u32) >= (position.line, position.character) {
+            break;
+        }
+        match c {
+            '\n' => {
+                line += 1;
+                line_start = i + 1;
+            }
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                // line comment, incl. docs
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (j, c) in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = j + 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' | 'r' => {
+                if let Some(len) = string_len(content, i) {
+                    while let Some((j, c)) = chars.next_if(|(j, _)| *j < i + len) {
+                        if c == '\n' {
+                            line += 1;
+                            line_start = j + 1;
+                        }
+                    }
+                }
+            }
+            // char literals, lifetimes do not have a closing quote
+            '\'' => {
+                let rest = &content[i + 1..];
+                let len = match rest.strip_prefix('\\') {
+                    Some(escaped) => escaped
+                        .get(1..)
+                        .and_then(|escaped| escaped.find('\''))
+                        .map(|end| end + 3),
+                    None => rest
+                        .chars()
+                        .next()
+                        .filter(|c| rest[c.len_utf8()..].starts_with('\''))
+                        .map(|c| c.len_utf8() + 1),
+                };
+                if let Some(len) = len {
+                    while chars.next_if(|(j, _)| *j <= i + len).is_some() {}
+                }
+            }
+            '#' => {
+                let rest = &content[i + 1..];
+                let (inner, rest) = match rest.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, rest),
+                };
+                let Some(body) = rest.trim_start().strip_prefix('[') else {
+                    continue;
+                };
+                let Some(end) = closing(body, '[', ']') else {
+                    continue;
+                };
+                let attribute = &body[..end];
+                if let Some(condition) = attribute
+                    .trim_start()
+                    .strip_prefix("cfg")
+                    .map(str::trim_start)
+                    .and_then(|args| args.strip_prefix('('))
+                    .and_then(|args| closing(args, '(', ')').map(|end| &args[..end]))
+                {
+                    let condition = normalize(condition);
+                    if inner {
+                        parser.inner(condition);
+                    } else {
+                        parser.pending.push(condition);
+                    }
+                }
+
+                // skip the attribute, it may contain strings and brackets
+                let end = content.len() - body.len() + end;
+                for (j, c) in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = j + 1;
+                    }
+                    if j >= end {
+                        break;
+                    }
+                }
+            }
+            '{' => parser.open(),
+            '}' => parser.close(),
+            '(' | '[' => parser.nesting += 1,
+            ')' | ']' => parser.nesting = parser.nesting.saturating_sub(1),
+            // e.g. the end of a field or enum variant, but not of a parameter
+            ';' | ',' if parser.nesting == 0 => parser.pending.clear(),
+            _ => {}
+        }
+    }
+
+    parser
+        .blocks
+        .into_iter()
+        .flat_map(|block| block.conditions)
+        .chain(parser.pending)
+        .collect()
+}
+
+/// Condition on a single line, e.g. for attributes that span several lines.
+fn normalize(condition: &str) -> String {
+    condition
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(",)", ")")
+}
+
+/// Length of the string literal that starts at byte `start`, incl. raw strings like `r#"..."#`.
+///
+/// Prefixes of byte and C strings (`b`, `c`) are part of the preceding text. `None` if there is no string literal, an
+/// unterminated literal spans the rest of the content.
+fn string_len(content: &str, start: usize) -> Option<usize> {
+    let text = &content[start..];
+    let (raw, rest) = match text.strip_prefix('r') {
+        Some(rest) => {
+            // end of an identifier, e.g. `for`
+            let before = &content[..start];
+            let before = before.strip_suffix(['b', 'c']).unwrap_or(before);
+            if before.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+            (true, rest)
+        }
+        None => (false, text),
+    };
+    let quoted = rest.trim_start_matches('#');
+    let hashes = rest.len() - quoted.len();
+    let body = quoted.strip_prefix('"')?;
+    let prefix = text.len() - body.len();
+
+    let end = if raw {
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        body.find(&terminator).map(|end| end + terminator.len())
+    } else {
+        let mut escaped = false;
+        body.char_indices().find_map(|(i, c)| {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => return Some(i + 1),
+                _ => {}
+            }
+            None
+        })
+    };
+    Some(prefix + end.unwrap_or(body.len()))
+}
+
+/// Position of the bracket that closes the one right before `text`.
+fn closing(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | 'r' if let Some(len) = string_len(text, i) => {
+                while chars.next_if(|(j, _)| *j < i + len).is_some() {}
+            }
+            _ if c == open => depth += 1,
+            _ if c == close => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return Some(i),
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Debug, Default)]
+struct Block {
+    conditions: Vec<String>,
+
+    /// Nesting of parentheses and brackets outside of this block.
+    nesting: usize,
+}
+
+#[derive(Debug)]
+struct Parser {
+    /// Open blocks, the first one is the file.
+    blocks: Vec<Block>,
+
+    /// Conditions of the next item.
+    pending: Vec<String>,
+
+    /// Nesting of parentheses and brackets within the current block.
+    nesting: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self {
+            blocks: vec![Block::default()],
+            pending: vec![],
+            nesting: 0,
+        }
+    }
+}
+
+impl Parser {
+    /// Inner attribute, applies to the enclosing block.
+    fn inner(&mut self, condition: String) {
+        self.blocks
+            .last_mut()
+            .expect("file block")
+            .conditions
+            .push(condition);
+    }
+
+    fn open(&mut self) {
+        self.blocks.push(Block {
+            conditions: std::mem::take(&mut self.pending),
+            nesting: self.nesting,
+        });
+        self.nesting = 0;
+    }
+
+    fn close(&mut self) {
+        self.pending.clear();
+        // unbalanced braces, e.g. within macros
+        if self.blocks.len() > 1 {
+            let block = self.blocks.pop().expect("checked length");
+            self.nesting = block.nesting;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONTENT: &str = r##"#![cfg(unix)]
+//! #[cfg(comment)]
+
+#[cfg(feature = "serde")]
+pub struct Config {
+    #[cfg(feature = "tls")]
+    pub cert: String,
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    const S: &str = "}#[cfg(string)]{";
+    const C: char = '}';
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        not(miri)
+    ))]
+    fn check<'a>(a: &'a str, b: u8) {
+        let x = 1;
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "x")))]
+pub fn plain() {}
+"##;
+
+    fn at(line: u32, character: u32) -> Vec<String> {
+        conditions(CONTENT, Position::new(line, character))
+    }
+
+    #[test]
+    fn test_conditions() {
+        assert_eq!(at(4, 11), ["unix", "feature = \"serde\""]);
+        assert_eq!(
+            at(6, 8),
+            ["unix", "feature = \"serde\"", "feature = \"tls\""]
+        );
+        assert_eq!(at(7, 8), ["unix", "feature = \"serde\""]);
+        assert_eq!(at(11, 4), ["unix", "test"]);
+        assert_eq!(at(12, 10), ["unix", "test"]);
+        assert_eq!(at(13, 10), ["unix", "test"]);
+        assert_eq!(
+            at(20, 7),
+            ["unix", "test", "all(target_os = \"linux\", not(miri))"]
+        );
+        assert_eq!(
+            at(21, 12),
+            ["unix", "test", "all(target_os = \"linux\", not(miri))"]
+        );
+        assert_eq!(at(26, 7), ["unix"]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("any(\n    unix,\n    windows,\n)"),
+            "any(unix, windows)"
+        );
+        assert_eq!(normalize("feature = \"a\""), "feature = \"a\"");
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let content = "mod m {\n    const A: char = '\\'';\n    const B: char = '\\u{7D}';\n    #[cfg(x)]\n    fn f() {}\n}\n";
+        assert_eq!(conditions(content, Position::new(4, 7)), ["x"]);
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let content = r###"mod m {
+    const P: &str = r"C:\";
+    #[cfg(x)]
+    mod n {
+        const Q: &[u8] = br#"}"\"#;
+        const r#type: &str = r#""#;
+        #[doc = r"\"]
+        #[cfg(y)]
+        fn f() {}
+    }
+}
+"###;
+        assert_eq!(conditions(content, Position::new(8, 11)), ["x", "y"]);
+    }
+
+    #[test]
+    fn test_string_len() {
+        assert_eq!(string_len(r#"x = "a\"b" + 1"#, 4), Some(6));
+        assert_eq!(string_len(r#"x = r"a\" + 1"#, 4), Some(5));
+        assert_eq!(string_len(r##"x = br#"a"b"# + 1"##, 5), Some(8));
+        assert_eq!(string_len(r#"x = "a"#, 4), Some(2));
+        assert_eq!(string_len("for x", 2), None);
+        assert_eq!(string_len("r#type", 0), None);
+    }
+
+    #[test]
+    fn test_closing() {
+        assert_eq!(closing("a(b)c)d", '(', ')'), Some(5));
+        assert_eq!(closing("a = \")\")", '(', ')'), Some(7));
+        assert_eq!(closing(r#"doc = r"\")"#, '(', ')'), Some(10));
+        assert_eq!(closing("a(b", '(', ')'), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! C# via [csharp-ls](https://github.com/razzmatazz/csharp-language-server), which is based on Roslyn.
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::Result;
+use lsp_types::Position;
+use serde_json::json;
+
+use crate::lsp::version::MinimumVersion;
+
+use super::{
+    DependencySymbol, LanguageServerCommand, ProgrammingLanguageQuirks, Readiness,
+    install::LanguageServerRelease,
+};
+
+#[derive(Debug)]
+pub(crate) struct CSharp;
+
+impl ProgrammingLanguageQuirks for CSharp {
+    fn language_server(&self) -> LanguageServerCommand {
+        LanguageServerCommand::binary("csharp-ls")
+    }
+
+    fn language_server_env(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    fn release(&self) -> Option<LanguageServerRelease> {
+        // distributed as .NET tool, see https://github.com/razzmatazz/csharp-language-server#installation
+        None
+    }
+
+    fn install_hint(&self) -> Option<String> {
+        Some("`dotnet tool install --global csharp-ls`".to_owned())
+    }
+
+    fn file_extensions(&self) -> HashSet<String> {
+        HashSet::from(["cs".to_owned()])
+    }
+
+    fn initialization_options(&self, workspace: &Path) -> Result<Option<serde_json::Value>> {
+        // The server picks a solution/project on its own if none is given, but that may be some nested test
+        // project. So pick one deterministically if the workspace root contains an unambiguous candidate.
+        let Some(solution) = find_csharp_solution(workspace) else {
+            return Ok(None);
+        };
+
+        Ok(Some(json!({
+            "csharp": {
+                "solution": solution,
+            },
+        })))
+    }
+
+    fn minimum_version(&self) -> Option<MinimumVersion> {
+        None
+    }
+
+    fn readiness(&self) -> Readiness {
+        // the server uses random tokens, so we need to match the title of its background project load
+        Readiness::progress(["Loading workspace"])
+    }
+
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64> {
+        HashMap::from([("static".to_owned(), -1)])
+    }
+
+    fn docs_url(&self, _symbol: &DependencySymbol<'_>) -> Option<String> {
+        None
+    }
+
+    fn is_package_manifest(&self, file_name: &str) -> bool {
+        file_name.ends_with(".csproj")
+    }
+
+    fn flycheck_progress_prefix(&self) -> Option<String> {
+        None
+    }
+
+    fn supports_runnables(&self) -> bool {
+        false
+    }
+
+    fn supports_parent_module(&self) -> bool {
+        false
+    }
+
+    fn supports_syntax_trees(&self) -> bool {
+        false
+    }
+
+    fn cfg_conditions(&self, _content: &str, _position: Position) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Find C# solution or -- if there is none -- project file in the workspace root.
+///
+/// Returns the file name if there is exactly one candidate.
+fn find_csharp_solution(workspace: &Path) -> Option<String> {
+    let mut solutions = vec![];
+    let mut projects = vec![];
+
+    for entry in std::fs::read_dir(workspace).ok()? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("sln" | "slnx") => solutions.push(name.to_owned()),
+            Some("csproj") => projects.push(name.to_owned()),
+            _ => {}
+        }
+    }
+
+    let candidates = if solutions.is_empty() {
+        projects
+    } else {
+        solutions
+    };
+    match <[String; 1]>::try_from(candidates) {
+        Ok([candidate]) => Some(candidate),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_find_csharp_solution() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(find_csharp_solution(dir.path()), None);
+
+        std::fs::write(dir.path().join("a.csproj"), "").unwrap();
+        assert_eq!(
+            find_csharp_solution(dir.path()).as_deref(),
+            Some("a.csproj")
+        );
+
+        std::fs::write(dir.path().join("b.csproj"), "").unwrap();
+        assert_eq!(find_csharp_solution(dir.path()), None);
+
+        std::fs::write(dir.path().join("c.sln"), "").unwrap();
+        assert_eq!(find_csharp_solution(dir.path()).as_deref(), Some("c.sln"));
+
+        // directories are ignored
+        std::fs::create_dir(dir.path().join("d.sln")).unwrap();
+        assert_eq!(find_csharp_solution(dir.path()).as_deref(), Some("c.sln"));
+
+        std::fs::write(dir.path().join("e.slnx"), "").unwrap();
+        assert_eq!(find_csharp_solution(dir.path()), None);
+    }
+}
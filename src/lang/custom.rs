@@ -0,0 +1,501 @@
+//! Language server that is configured by the user.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, ensure};
+use clap::Parser;
+use lsp_types::Position;
+use serde::Deserialize;
+
+use crate::{cli::parse_secs, lsp::version::MinimumVersion};
+
+use super::{
+    DependencySymbol, LanguageServerCommand, ProgrammingLanguageQuirks, Readiness,
+    install::LanguageServerRelease,
+};
+
+/// CLI config for the custom programming language.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct CustomLanguageCLIConfig {
+    /// TOML config file for the custom programming language.
+    ///
+    /// Supports the keys `command`, `args`, `env`, `file_extensions`, `initialization_options`, `init_progress`,
+    /// `quiescence_secs`, `probe_query`, `minimum_version` and `semantic_token_modifier_scores`. Values passed via CLI
+    /// take precedence.
+    #[clap(long, env = "COMMON_SENSE_CODER_CUSTOM_LSP_CONFIG")]
+    custom_lsp_config: Option<PathBuf>,
+
+    /// Language server binary for the custom programming language.
+    #[clap(long, env = "COMMON_SENSE_CODER_CUSTOM_LSP_COMMAND")]
+    custom_lsp_command: Option<String>,
+
+    /// Argument passed to the language server of the custom programming language.
+    ///
+    /// Can be repeated.
+    #[clap(long, allow_hyphen_values = true)]
+    custom_lsp_arg: Vec<String>,
+
+    /// File extension (without leading dot) handled by the language server of the custom programming language.
+    ///
+    /// Can be repeated.
+    #[clap(long)]
+    custom_lsp_file_extension: Vec<String>,
+
+    /// Initialization options (as JSON) for the language server of the custom programming language.
+    #[clap(long, env = "COMMON_SENSE_CODER_CUSTOM_LSP_INIT_OPTIONS", value_parser = parse_json)]
+    custom_lsp_init_options: Option<serde_json::Value>,
+
+    /// Progress report (token or title) that is expected before the language server of the custom programming
+    /// language is ready.
+    ///
+    /// Can be repeated. If no readiness strategy is given, the language server is ready as soon as there is no
+    /// progress in flight.
+    ///
+    /// Conflicts with `--custom-lsp-quiescence` and `--custom-lsp-probe-query`.
+    #[clap(long, conflicts_with_all = ["custom_lsp_quiescence", "custom_lsp_probe_query"])]
+    custom_lsp_init_progress: Vec<String>,
+
+    /// Seconds without any progress report after which the language server of the custom programming language is
+    /// considered ready.
+    ///
+    /// Conflicts with `--custom-lsp-init-progress` and `--custom-lsp-probe-query`.
+    #[clap(long, value_parser = parse_secs, conflicts_with = "custom_lsp_probe_query")]
+    custom_lsp_quiescence: Option<Duration>,
+
+    /// Workspace symbol query that is repeated until it returns results, after which the language server of the
+    /// custom programming language is considered ready.
+    ///
+    /// Conflicts with `--custom-lsp-init-progress` and `--custom-lsp-quiescence`.
+    #[clap(long)]
+    custom_lsp_probe_query: Option<String>,
+
+    /// Minimum version (`<major>.<minor>.<patch>`) of the language server of the custom programming language.
+    #[clap(long, value_parser = MinimumVersion::parse_release)]
+    custom_lsp_minimum_version: Option<MinimumVersion>,
+}
+
+fn parse_json(s: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
+
+/// Interval for [`Readiness::Probe`].
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pick readiness strategy, if any was configured.
+fn readiness(
+    init_progress: HashSet<String>,
+    quiescence: Option<Duration>,
+    probe_query: Option<String>,
+) -> Result<Option<Readiness>> {
+    let mut strategies = vec![];
+    if !init_progress.is_empty() {
+        strategies.push(Readiness::Progress {
+            parts: init_progress,
+        });
+    }
+    if let Some(duration) = quiescence {
+        strategies.push(Readiness::Quiescence { duration });
+    }
+    if let Some(query) = probe_query {
+        strategies.push(Readiness::Probe {
+            query,
+            interval: PROBE_INTERVAL,
+        });
+    }
+    ensure!(
+        strategies.len() <= 1,
+        "only one readiness strategy can be configured",
+    );
+    Ok(strategies.pop())
+}
+
+/// Content of [`CustomLanguageCLIConfig::custom_lsp_config`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomLanguageFileConfig {
+    command: Option<String>,
+
+    #[serde(default)]
+    args: Vec<String>,
+
+    #[serde(default)]
+    env: HashMap<String, String>,
+
+    #[serde(default)]
+    file_extensions: HashSet<String>,
+
+    initialization_options: Option<serde_json::Value>,
+
+    #[serde(default)]
+    init_progress: HashSet<String>,
+
+    quiescence_secs: Option<f64>,
+
+    probe_query: Option<String>,
+
+    minimum_version: Option<String>,
+
+    #[serde(default)]
+    semantic_token_modifier_scores: HashMap<String, i64>,
+}
+
+impl CustomLanguageFileConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("read config file: {}", path.display()))?;
+        toml::from_str(&s).with_context(|| format!("parse config file: {}", path.display()))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Custom {
+    command: LanguageServerCommand,
+    env: HashMap<String, String>,
+    file_extensions: HashSet<String>,
+    initialization_options: Option<serde_json::Value>,
+    readiness: Readiness,
+    minimum_version: Option<MinimumVersion>,
+    semantic_token_modifier_scores: HashMap<String, i64>,
+}
+
+impl Custom {
+    pub(crate) fn try_new(cfg: &CustomLanguageCLIConfig) -> Result<Self> {
+        let CustomLanguageCLIConfig {
+            custom_lsp_config,
+            custom_lsp_command,
+            custom_lsp_arg,
+            custom_lsp_file_extension,
+            custom_lsp_init_options,
+            custom_lsp_init_progress,
+            custom_lsp_quiescence,
+            custom_lsp_probe_query,
+            custom_lsp_minimum_version,
+        } = cfg;
+
+        let file_cfg = match custom_lsp_config {
+            Some(path) => CustomLanguageFileConfig::load(path)?,
+            None => CustomLanguageFileConfig::default(),
+        };
+
+        let binary = custom_lsp_command
+            .clone()
+            .or(file_cfg.command)
+            .context("custom programming language requires a language server command")?;
+        let args = if custom_lsp_arg.is_empty() {
+            file_cfg.args
+        } else {
+            custom_lsp_arg.clone()
+        };
+        let file_extensions = if custom_lsp_file_extension.is_empty() {
+            file_cfg.file_extensions
+        } else {
+            custom_lsp_file_extension.iter().cloned().collect()
+        };
+        let cli_readiness = readiness(
+            custom_lsp_init_progress.iter().cloned().collect(),
+            *custom_lsp_quiescence,
+            custom_lsp_probe_query.clone(),
+        )?;
+        let file_readiness = readiness(
+            file_cfg.init_progress,
+            file_cfg
+                .quiescence_secs
+                .map(Duration::try_from_secs_f64)
+                .transpose()
+                .context("invalid `quiescence_secs`")?,
+            file_cfg.probe_query,
+        )
+        .context("invalid config file")?;
+        let minimum_version = match custom_lsp_minimum_version {
+            Some(version) => Some(version.clone()),
+            None => file_cfg
+                .minimum_version
+                .as_deref()
+                .map(MinimumVersion::parse_release)
+                .transpose()
+                .map_err(anyhow::Error::msg)
+                .context("invalid config file")?,
+        };
+        let readiness = cli_readiness
+            .or(file_readiness)
+            .unwrap_or_else(|| Readiness::progress([]));
+
+        Ok(Self {
+            command: LanguageServerCommand { binary, args },
+            env: file_cfg.env,
+            file_extensions,
+            initialization_options: custom_lsp_init_options
+                .clone()
+                .or(file_cfg.initialization_options),
+            readiness,
+            minimum_version,
+            semantic_token_modifier_scores: file_cfg.semantic_token_modifier_scores,
+        })
+    }
+}
+
+impl ProgrammingLanguageQuirks for Custom {
+    fn language_server(&self) -> LanguageServerCommand {
+        self.command.clone()
+    }
+
+    fn language_server_env(&self) -> HashMap<String, String> {
+        self.env.clone()
+    }
+
+    fn release(&self) -> Option<LanguageServerRelease> {
+        None
+    }
+
+    fn install_hint(&self) -> Option<String> {
+        None
+    }
+
+    fn file_extensions(&self) -> HashSet<String> {
+        self.file_extensions.clone()
+    }
+
+    fn initialization_options(&self, _workspace: &Path) -> Result<Option<serde_json::Value>> {
+        Ok(self.initialization_options.clone())
+    }
+
+    fn minimum_version(&self) -> Option<MinimumVersion> {
+        self.minimum_version.clone()
+    }
+
+    fn readiness(&self) -> Readiness {
+        self.readiness.clone()
+    }
+
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64> {
+        self.semantic_token_modifier_scores.clone()
+    }
+
+    fn docs_url(&self, _symbol: &DependencySymbol<'_>) -> Option<String> {
+        None
+    }
+
+    fn is_package_manifest(&self, _file_name: &str) -> bool {
+        false
+    }
+
+    fn flycheck_progress_prefix(&self) -> Option<String> {
+        None
+    }
+
+    fn supports_runnables(&self) -> bool {
+        false
+    }
+
+    fn supports_parent_module(&self) -> bool {
+        false
+    }
+
+    fn supports_syntax_trees(&self) -> bool {
+        false
+    }
+
+    fn cfg_conditions(&self, _content: &str, _position: Position) -> Vec<String> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_cli_only() {
+        let cfg = CustomLanguageCLIConfig::try_parse_from([
+            "",
+            "--custom-lsp-command=my-lsp",
+            "--custom-lsp-arg=--stdio",
+            "--custom-lsp-arg",
+            "-v",
+            "--custom-lsp-file-extension=foo",
+            r#"--custom-lsp-init-options={"a": 1}"#,
+            "--custom-lsp-init-progress=indexing",
+        ])
+        .unwrap();
+        let custom = Custom::try_new(&cfg).unwrap();
+
+        assert_eq!(
+            custom.language_server(),
+            LanguageServerCommand {
+                binary: "my-lsp".to_owned(),
+                args: vec!["--stdio".to_owned(), "-v".to_owned()],
+            },
+        );
+        assert_eq!(
+            custom.initialization_options(Path::new("/")).unwrap(),
+            Some(json!({"a": 1})),
+        );
+        assert_eq!(custom.readiness(), Readiness::progress(["indexing"]));
+        assert_eq!(custom.file_extensions(), HashSet::from(["foo".to_owned()]));
+        assert!(custom.language_server_env().is_empty());
+        assert!(custom.semantic_token_modifier_scores().is_empty());
+    }
+
+    #[test]
+    fn test_file_with_cli_override() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lsp.toml");
+        std::fs::write(
+            &path,
+            r#"
+            command = "file-lsp"
+            args = ["--stdio"]
+            file_extensions = ["foo", "bar"]
+            init_progress = ["loading"]
+            minimum_version = "1.2.3"
+
+            [env]
+            LOG = "debug"
+
+            [initialization_options]
+            x = { y = true }
+
+            [semantic_token_modifier_scores]
+            declaration = 10
+            "#,
+        )
+        .unwrap();
+
+        let cfg = CustomLanguageCLIConfig {
+            custom_lsp_config: Some(path.clone()),
+            ..Default::default()
+        };
+        let custom = Custom::try_new(&cfg).unwrap();
+        assert_eq!(
+            custom.language_server(),
+            LanguageServerCommand {
+                binary: "file-lsp".to_owned(),
+                args: vec!["--stdio".to_owned()],
+            },
+        );
+        assert_eq!(
+            custom.language_server_env(),
+            HashMap::from([("LOG".to_owned(), "debug".to_owned())]),
+        );
+        assert_eq!(
+            custom.file_extensions(),
+            HashSet::from(["foo".to_owned(), "bar".to_owned()]),
+        );
+        assert_eq!(
+            custom.initialization_options(Path::new("/")).unwrap(),
+            Some(json!({"x": {"y": true}})),
+        );
+        assert_eq!(custom.readiness(), Readiness::progress(["loading"]));
+        assert_eq!(
+            custom.minimum_version(),
+            Some(MinimumVersion::Release([1, 2, 3])),
+        );
+        assert_eq!(
+            custom.semantic_token_modifier_scores(),
+            HashMap::from([("declaration".to_owned(), 10)]),
+        );
+
+        let cfg = CustomLanguageCLIConfig {
+            custom_lsp_config: Some(path),
+            custom_lsp_command: Some("cli-lsp".to_owned()),
+            custom_lsp_arg: vec!["--cli".to_owned()],
+            custom_lsp_file_extension: vec![],
+            custom_lsp_init_options: Some(json!(null)),
+            custom_lsp_init_progress: vec![],
+            custom_lsp_quiescence: None,
+            custom_lsp_probe_query: Some("main".to_owned()),
+            custom_lsp_minimum_version: None,
+        };
+        let custom = Custom::try_new(&cfg).unwrap();
+        assert_eq!(
+            custom.language_server(),
+            LanguageServerCommand {
+                binary: "cli-lsp".to_owned(),
+                args: vec!["--cli".to_owned()],
+            },
+        );
+        assert_eq!(
+            custom.initialization_options(Path::new("/")).unwrap(),
+            Some(json!(null)),
+        );
+        assert_eq!(
+            custom.readiness(),
+            Readiness::Probe {
+                query: "main".to_owned(),
+                interval: PROBE_INTERVAL,
+            },
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = Custom::try_new(&CustomLanguageCLIConfig::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "custom programming language requires a language server command",
+        );
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lsp.toml");
+        std::fs::write(&path, "foo = 1").unwrap();
+        let err = Custom::try_new(&CustomLanguageCLIConfig {
+            custom_lsp_config: Some(path.clone()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().starts_with("parse config file: "), "{err}");
+
+        std::fs::write(
+            &path,
+            "command = \"lsp\"\ninit_progress = [\"a\"]\nquiescence_secs = 1",
+        )
+        .unwrap();
+        let err = Custom::try_new(&CustomLanguageCLIConfig {
+            custom_lsp_config: Some(path),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "invalid config file: only one readiness strategy can be configured",
+        );
+
+        let err = CustomLanguageCLIConfig::try_parse_from([
+            "",
+            "--custom-lsp-command=lsp",
+            "--custom-lsp-quiescence=1",
+            "--custom-lsp-probe-query=main",
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_quiescence() {
+        let cfg = CustomLanguageCLIConfig::try_parse_from([
+            "",
+            "--custom-lsp-command=lsp",
+            "--custom-lsp-quiescence=1.5",
+        ])
+        .unwrap();
+        assert_eq!(
+            Custom::try_new(&cfg).unwrap().readiness(),
+            Readiness::Quiescence {
+                duration: Duration::from_millis(1500),
+            },
+        );
+
+        CustomLanguageCLIConfig::try_parse_from([
+            "",
+            "--custom-lsp-command=lsp",
+            "--custom-lsp-quiescence=-1",
+        ])
+        .unwrap_err();
+    }
+}
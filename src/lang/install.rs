@@ -0,0 +1,263 @@
+//! Installation of missing language servers.
+//!
+//! Downloading requires the `install` cargo feature.
+#[cfg(feature = "install")]
+use std::io::Read;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "install")]
+use anyhow::ensure;
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+#[cfg(feature = "install")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "install")]
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::lsp::spawn_error::{SpawnError, SpawnProblem};
+
+use super::{LanguageServerCommand, ProgrammingLanguageQuirks, overrides::Overridden};
+
+/// CLI config for the installation of missing language servers.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct InstallCLIConfig {
+    /// Download a pinned release of the language server if its binary cannot be found.
+    ///
+    /// The download is verified against a pinned checksum and stored in `--lsp-cache-dir`.
+    /// Requires the `install` cargo feature.
+    #[clap(long, env = "COMMON_SENSE_CODER_INSTALL_MISSING_LSP")]
+    install_missing_lsp: bool,
+
+    /// Cache directory for downloaded language servers.
+    ///
+    /// Defaults to `$XDG_CACHE_HOME/common-sense-coder` or `$HOME/.cache/common-sense-coder`.
+    #[clap(long, env = "COMMON_SENSE_CODER_LSP_CACHE_DIR")]
+    lsp_cache_dir: Option<PathBuf>,
+}
+
+impl InstallCLIConfig {
+    /// Make sure that the language server binary exists, installing it if requested.
+    pub(crate) async fn ensure_installed(
+        &self,
+        name: &str,
+        quirks: Arc<dyn ProgrammingLanguageQuirks>,
+    ) -> Result<Arc<dyn ProgrammingLanguageQuirks>> {
+        let LanguageServerCommand { binary, args } = quirks.language_server();
+        if find_binary(&binary) {
+            return Ok(quirks);
+        }
+        let Some(release) = quirks.release().filter(|_| self.install_missing_lsp) else {
+            return Err(SpawnError::new(name, quirks.as_ref(), SpawnProblem::NotFound).into());
+        };
+
+        let dir = self.cache_dir()?.join(name).join(&release.version);
+        let path = dir.join(&binary);
+        if path.is_file() {
+            info!(path=%path.display(), "use cached language server");
+        } else {
+            info!(url=release.url.as_str(), path=%path.display(), "install language server");
+            install(release, &dir, &path).await?;
+        }
+
+        let binary = path
+            .into_os_string()
+            .into_string()
+            .map_err(|path| anyhow::anyhow!("cache path is not UTF-8: {}", path.display()))?;
+        Ok(Arc::new(Overridden::with_command(
+            quirks,
+            LanguageServerCommand { binary, args },
+        )))
+    }
+
+    fn cache_dir(&self) -> Result<PathBuf> {
+        if let Some(dir) = &self.lsp_cache_dir {
+            return Ok(dir.clone());
+        }
+
+        let base = match std::env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(
+                std::env::var_os("HOME")
+                    .context("cannot determine cache directory, use `--lsp-cache-dir`")?,
+            )
+            .join(".cache"),
+        };
+        Ok(base.join("common-sense-coder"))
+    }
+}
+
+/// Pinned language server release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LanguageServerRelease {
+    /// Version, used as a cache key.
+    pub(crate) version: String,
+
+    /// URL of a gzip-compressed tar archive.
+    pub(crate) url: String,
+
+    /// Hex-encoded SHA256 checksum of the archive.
+    ///
+    /// Pinned instead of downloaded next to the archive, so a compromised download server cannot swap the binary.
+    pub(crate) sha256: String,
+
+    /// Path of the binary within the archive.
+    pub(crate) archive_path: String,
+}
+
+/// Checks if the binary exists, either as a path or within `PATH`.
+fn find_binary(binary: &str) -> bool {
+    let path = Path::new(binary);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(binary).is_file()))
+        .unwrap_or_default()
+}
+
+/// Download release, verify it and extract the binary to the given path.
+#[cfg(feature = "install")]
+async fn install(release: LanguageServerRelease, dir: &Path, path: &Path) -> Result<()> {
+    let data = download(&release.url).await?;
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("create cache directory: {}", dir.display()))?;
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || verify_and_extract(&data, &release, &path))
+        .await
+        .context("join extraction task")?
+}
+
+#[cfg(not(feature = "install"))]
+async fn install(release: LanguageServerRelease, _dir: &Path, _path: &Path) -> Result<()> {
+    bail!(
+        "cannot download {}: built without the `install` feature",
+        release.url
+    )
+}
+
+#[cfg(feature = "install")]
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let data = reqwest::get(url)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .with_context(|| format!("download {url}"))?
+        .bytes()
+        .await
+        .with_context(|| format!("download {url}"))?;
+    Ok(data.to_vec())
+}
+
+/// Verify checksum of archive and extract binary to the given path.
+#[cfg(feature = "install")]
+fn verify_and_extract(data: &[u8], release: &LanguageServerRelease, path: &Path) -> Result<()> {
+    let checksum = Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    ensure!(
+        checksum == release.sha256,
+        "checksum mismatch for {}: expected {}, got {checksum}",
+        release.url,
+        release.sha256,
+    );
+
+    let mut archive = tar::Archive::new(GzDecoder::new(data));
+    for entry in archive.entries().context("read archive")? {
+        let mut entry = entry.context("read archive entry")?;
+        if entry.path().context("read archive entry path")? != Path::new(&release.archive_path) {
+            continue;
+        }
+
+        let mut content = vec![];
+        entry
+            .read_to_end(&mut content)
+            .context("read binary from archive")?;
+
+        // write to temporary file first so that we never end up with a partial binary
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content).with_context(|| format!("write {}", tmp.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("make executable: {}", tmp.display()))?;
+        }
+        std::fs::rename(&tmp, path).with_context(|| format!("move to {}", path.display()))?;
+        return Ok(());
+    }
+
+    bail!("archive does not contain {}", release.archive_path)
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "install")]
+    use flate2::{Compression, write::GzEncoder};
+    #[cfg(feature = "install")]
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[cfg(feature = "install")]
+    fn archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (path, content) in [("foo/README", "readme"), ("foo/bin/lsp", "binary")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[cfg(feature = "install")]
+    fn release(data: &[u8], archive_path: &str) -> LanguageServerRelease {
+        LanguageServerRelease {
+            version: "1.0".to_owned(),
+            url: "https://example.com/lsp.tar.gz".to_owned(),
+            sha256: Sha256::digest(data)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+            archive_path: archive_path.to_owned(),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "install")]
+    fn test_verify_and_extract() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lsp");
+        let data = archive();
+
+        verify_and_extract(&data, &release(&data, "foo/bin/lsp"), &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "binary");
+        assert!(find_binary(path.to_str().unwrap()));
+
+        let err = verify_and_extract(&data, &release(&data, "foo/bin/other"), &path).unwrap_err();
+        assert_eq!(err.to_string(), "archive does not contain foo/bin/other");
+
+        let err = verify_and_extract(&data, &release(b"other", "foo/bin/lsp"), &path).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("checksum mismatch for https://example.com/lsp.tar.gz"),
+            "{err}",
+        );
+    }
+
+    #[test]
+    fn test_find_binary() {
+        assert!(find_binary("cargo"));
+        assert!(!find_binary("this-binary-does-not-exist"));
+        assert!(!find_binary("/this/path/does/not/exist"));
+    }
+}
@@ -0,0 +1,425 @@
+use clap::{Parser, ValueEnum};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::lsp::{dependency::Dependency, version::MinimumVersion};
+use anyhow::{Context, Result, bail};
+use csharp::CSharp;
+use custom::{Custom, CustomLanguageCLIConfig};
+use install::LanguageServerRelease;
+use itertools::Itertools;
+use lsp_types::Position;
+use rust::{Rust, RustCLIConfig};
+use serde_json::Value;
+use toml::Toml;
+use tracing::info;
+use yaml::Yaml;
+
+mod cfg;
+pub(crate) mod csharp;
+pub(crate) mod custom;
+pub(crate) mod install;
+pub(crate) mod overrides;
+pub(crate) mod rust;
+pub(crate) mod toml;
+pub(crate) mod yaml;
+
+/// CLI config for the programming languages.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct LanguageCLIConfig {
+    /// Rust config.
+    #[clap(flatten)]
+    rust: RustCLIConfig,
+
+    /// Custom programming language config.
+    #[clap(flatten)]
+    custom: CustomLanguageCLIConfig,
+}
+
+/// Code programming language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub(crate) enum ProgrammingLanguage {
+    Rust,
+    #[value(name = "csharp")]
+    CSharp,
+    /// TOML files like `Cargo.toml`, usually used as secondary language.
+    Toml,
+    /// YAML files like CI configs, usually used as secondary language.
+    Yaml,
+    /// Language server configured via `--custom-lsp-*` flags or config file.
+    Custom,
+    /// Detect language from workspace content.
+    Auto,
+}
+
+impl ProgrammingLanguage {
+    /// Resolve requested languages -- including auto-detection -- and get their quirks.
+    ///
+    /// Duplicates are removed, the order is preserved.
+    pub(crate) fn resolve(
+        languages: &[Self],
+        workspace: &Path,
+        cfg: &LanguageCLIConfig,
+    ) -> Result<Vec<(Self, Arc<dyn ProgrammingLanguageQuirks>)>> {
+        let mut resolved = vec![];
+        for lang in languages {
+            match lang {
+                Self::Auto => {
+                    let detected = Self::detect(workspace)?;
+                    info!(
+                        langs = %detected.iter().map(|lang| lang.name()).join(","),
+                        "detected programming languages",
+                    );
+                    resolved.extend(detected);
+                }
+                lang => resolved.push(*lang),
+            }
+        }
+
+        resolved
+            .into_iter()
+            .unique()
+            .map(|lang| Ok((lang, lang.quirks(cfg)?)))
+            .collect()
+    }
+
+    /// Get quirks for respective language.
+    ///
+    /// Fails for [`Auto`](Self::Auto), which has to be [resolved](Self::resolve) first.
+    fn quirks(&self, cfg: &LanguageCLIConfig) -> Result<Arc<dyn ProgrammingLanguageQuirks>> {
+        Ok(match self {
+            Self::Rust => Arc::new(Rust::new(cfg.rust.clone())),
+            Self::CSharp => Arc::new(CSharp),
+            Self::Toml => Arc::new(Toml),
+            Self::Yaml => Arc::new(Yaml),
+            Self::Custom => Arc::new(
+                Custom::try_new(&cfg.custom).context("set up custom programming language")?,
+            ),
+            Self::Auto => bail!("`auto` must be resolved to concrete programming languages first"),
+        })
+    }
+
+    /// Name as used on the CLI.
+    pub(crate) fn name(&self) -> String {
+        self.to_possible_value()
+            .expect("no skipped variants")
+            .get_name()
+            .to_owned()
+    }
+
+    /// Detect programming languages based on marker files in the workspace root.
+    fn detect(workspace: &Path) -> Result<Vec<Self>> {
+        let mut supported = vec![];
+        let mut unsupported = vec![];
+
+        let entries = std::fs::read_dir(workspace)
+            .with_context(|| format!("list workspace: {}", workspace.display()))?;
+        for entry in entries {
+            let entry = entry.context("list workspace entry")?;
+            let Some(name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+                continue;
+            };
+            let extension = Path::new(&name).extension().and_then(|ext| ext.to_str());
+
+            match (name.as_str(), extension) {
+                ("Cargo.toml", _) => supported.push((Self::Rust, name)),
+                (_, Some("sln" | "slnx" | "csproj")) => supported.push((Self::CSharp, name)),
+                ("package.json" | "tsconfig.json", _) => {
+                    unsupported.push(("JavaScript/TypeScript", name))
+                }
+                ("go.mod", _) => unsupported.push(("Go", name)),
+                ("pyproject.toml" | "setup.py" | "requirements.txt", _) => {
+                    unsupported.push(("Python", name))
+                }
+                ("pom.xml" | "build.gradle" | "build.gradle.kts", _) => {
+                    unsupported.push(("Java/Kotlin", name))
+                }
+                _ => {}
+            }
+        }
+
+        if supported.is_empty() {
+            if unsupported.is_empty() {
+                bail!(
+                    "cannot detect programming language of workspace {}, use `--programming-language` to set it",
+                    workspace.display(),
+                );
+            }
+
+            unsupported.sort_unstable();
+            bail!(
+                "detected unsupported programming language(s) in workspace {}: {}; use `--programming-language=custom` to configure a language server",
+                workspace.display(),
+                unsupported
+                    .iter()
+                    .map(|(lang, name)| format!("{lang} ({name})"))
+                    .join(", "),
+            )
+        }
+
+        Ok(supported
+            .into_iter()
+            .map(|(lang, _name)| lang)
+            .sorted()
+            .dedup()
+            .collect())
+    }
+}
+
+/// Merge `overlay` into `base`.
+///
+/// Objects are merged recursively, all other values are replaced.
+pub(crate) fn deep_merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (k, v) in overlay {
+                match base.get_mut(&k) {
+                    Some(base_v) => deep_merge(base_v, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            *base = overlay;
+        }
+    }
+}
+
+/// Command to launch a language server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LanguageServerCommand {
+    /// Binary name or path.
+    pub(crate) binary: String,
+
+    /// Arguments passed to the binary.
+    pub(crate) args: Vec<String>,
+}
+
+impl LanguageServerCommand {
+    /// Command without any arguments.
+    pub(crate) fn binary(binary: &str) -> Self {
+        Self {
+            binary: binary.to_owned(),
+            args: vec![],
+        }
+    }
+}
+
+/// Strategy to detect that a language server is ready after startup.
+///
+/// Independent of the strategy, the language server is only considered ready if it has no progress in flight.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Readiness {
+    /// Ready once all given progress reports were started.
+    ///
+    /// A part is matched against the progress token as well as against the title of the progress report. An empty set
+    /// means that the language server is ready as soon as there is no progress in flight.
+    Progress { parts: HashSet<String> },
+
+    /// Ready once there was no progress event for the given duration.
+    Quiescence { duration: Duration },
+
+    /// Ready once a `workspace/symbol` request for the given query returns at least one symbol.
+    ///
+    /// The request is repeated with the given interval.
+    Probe { query: String, interval: Duration },
+}
+
+impl Readiness {
+    /// [`Progress`](Self::Progress) strategy with given parts.
+    pub(crate) fn progress<const N: usize>(parts: [&str; N]) -> Self {
+        Self::Progress {
+            parts: parts.into_iter().map(|p| p.to_owned()).collect(),
+        }
+    }
+}
+
+/// Symbol that is defined in a dependency.
+#[derive(Debug)]
+pub(crate) struct DependencySymbol<'a> {
+    /// Package that defines the symbol.
+    pub(crate) dependency: &'a Dependency,
+
+    /// Symbol name.
+    pub(crate) name: &'a str,
+
+    /// Semantic token type.
+    pub(crate) token_type: &'a str,
+
+    /// Code blocks of the hover response.
+    pub(crate) hover: Vec<&'a str>,
+}
+
+/// Quirks for the respective [`ProgrammingLanguage`].
+pub(crate) trait ProgrammingLanguageQuirks: Debug + Send + Sync + 'static {
+    /// Command that starts the language server.
+    fn language_server(&self) -> LanguageServerCommand;
+
+    /// Language server environment variables.
+    fn language_server_env(&self) -> HashMap<String, String>;
+
+    /// Pinned release of the language server for the current platform that can be installed if the binary is missing.
+    fn release(&self) -> Option<LanguageServerRelease>;
+
+    /// How users can install the language server themselves, e.g. a shell command.
+    fn install_hint(&self) -> Option<String>;
+
+    /// File extensions (without leading dot) handled by the language server.
+    ///
+    /// Tool calls for other files are rejected. An empty set means that the language server handles all files.
+    fn file_extensions(&self) -> HashSet<String>;
+
+    /// Language server initialization options.
+    ///
+    /// These are also reported as settings via `workspace/configuration` and are re-evaluated when the configuration
+    /// is reloaded.
+    fn initialization_options(&self, workspace: &Path) -> Result<Option<serde_json::Value>>;
+
+    /// Minimum language server version that supports all features we rely on.
+    fn minimum_version(&self) -> Option<MinimumVersion>;
+
+    /// Strategy to detect when the language server finished its initial work.
+    fn readiness(&self) -> Readiness;
+
+    /// Sets score for each semantic token modifier.
+    ///
+    /// Defaults to zero for unspecified modifiers. Scores of multiple modifiers on a token will be added.
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64>;
+
+    /// Best-effort documentation URL of a symbol that is defined in a dependency.
+    fn docs_url(&self, symbol: &DependencySymbol<'_>) -> Option<String>;
+
+    /// The file (given by name) marks the root directory of a package, e.g. `Cargo.toml` for a Rust crate.
+    fn is_package_manifest(&self, file_name: &str) -> bool;
+
+    /// Prefix of the progress tokens of on-demand checks (see `rust-analyzer/runFlycheck`).
+    ///
+    /// `None` if the language server does not support them.
+    fn flycheck_progress_prefix(&self) -> Option<String>;
+
+    /// The language server lists tests and how to run them (see `experimental/runnables`).
+    fn supports_runnables(&self) -> bool;
+
+    /// The language server finds the declaration of the module that contains a position (see
+    /// `experimental/parentModule`).
+    fn supports_parent_module(&self) -> bool;
+
+    /// The language server shows its syntax and item trees of a document (see `rust-analyzer/viewSyntaxTree` and
+    /// `rust-analyzer/viewItemTree`).
+    fn supports_syntax_trees(&self) -> bool;
+
+    /// Conditions that guard the item at the position of the file content, outermost first, e.g. `feature = "serde"`
+    /// for Rust.
+    fn cfg_conditions(&self, content: &str, position: Position) -> Vec<String>;
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let dir = TempDir::new().unwrap();
+        let err = ProgrammingLanguage::detect(dir.path()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("cannot detect programming language of workspace"),
+            "{err}",
+        );
+
+        std::fs::write(dir.path().join("go.mod"), "").unwrap();
+        std::fs::write(dir.path().join("package.json"), "").unwrap();
+        let err = ProgrammingLanguage::detect(dir.path()).unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                ": Go (go.mod), JavaScript/TypeScript (package.json); use `--programming-language=custom` to configure a language server"
+            ),
+            "{err}",
+        );
+
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(
+            ProgrammingLanguage::detect(dir.path()).unwrap(),
+            vec![ProgrammingLanguage::Rust],
+        );
+
+        std::fs::write(dir.path().join("b.csproj"), "").unwrap();
+        std::fs::write(dir.path().join("a.sln"), "").unwrap();
+        assert_eq!(
+            ProgrammingLanguage::detect(dir.path()).unwrap(),
+            vec![ProgrammingLanguage::Rust, ProgrammingLanguage::CSharp],
+        );
+
+        std::fs::remove_file(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            ProgrammingLanguage::detect(dir.path()).unwrap(),
+            vec![ProgrammingLanguage::CSharp],
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.sln"), "").unwrap();
+
+        let resolved = ProgrammingLanguage::resolve(
+            &[
+                ProgrammingLanguage::Rust,
+                ProgrammingLanguage::Auto,
+                ProgrammingLanguage::Rust,
+            ],
+            dir.path(),
+            &LanguageCLIConfig::default(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(lang, _quirks)| lang)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            resolved,
+            vec![ProgrammingLanguage::Rust, ProgrammingLanguage::CSharp],
+        );
+
+        let err = ProgrammingLanguage::Auto
+            .quirks(&LanguageCLIConfig::default())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`auto` must be resolved to concrete programming languages first"
+        );
+    }
+
+    #[test]
+    fn test_deep_merge() {
+        let mut base = json!({
+            "a": {"b": 1, "c": [1, 2]},
+            "d": "x",
+        });
+        deep_merge(
+            &mut base,
+            json!({
+                "a": {"c": [3], "e": null},
+                "d": {"f": true},
+                "g": 2,
+            }),
+        );
+        assert_eq!(
+            base,
+            json!({
+                "a": {"b": 1, "c": [3], "e": null},
+                "d": {"f": true},
+                "g": 2,
+            }),
+        );
+    }
+}
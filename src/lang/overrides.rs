@@ -0,0 +1,505 @@
+//! User-provided overrides for the quirks of a programming language.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::Parser;
+use lsp_types::Position;
+use serde_json::Value;
+
+use crate::lsp::version::MinimumVersion;
+
+use super::{
+    DependencySymbol, LanguageServerCommand, ProgrammingLanguage, ProgrammingLanguageQuirks,
+    Readiness, deep_merge, install::LanguageServerRelease,
+};
+
+/// CLI config to override the language server setup.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct LspOverridesCLIConfig {
+    /// Language server binary, overrides the default of the programming language.
+    ///
+    /// Can be combined with `--lsp-arg` to use wrapper commands, e.g. `--lsp-command=rustup --lsp-arg=run
+    /// --lsp-arg=nightly --lsp-arg=rust-analyzer`. Only supported if a single programming language is used.
+    #[clap(long, env = "COMMON_SENSE_CODER_LSP_COMMAND")]
+    lsp_command: Option<String>,
+
+    /// Argument passed to the language server, overrides the default arguments of the programming language.
+    ///
+    /// Can be repeated. Arguments are comma-separated, which is mostly useful for the environment variable. Only
+    /// supported if a single programming language is used.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LSP_ARGS",
+        value_delimiter = ',',
+        allow_hyphen_values = true
+    )]
+    lsp_arg: Vec<String>,
+
+    /// Initialization options (as JSON) for the language server.
+    ///
+    /// Use `@<path>` to read them from a file, which is re-read when the configuration is reloaded. Deep-merged over
+    /// the defaults of the programming language, i.e. objects are merged recursively and all other values are
+    /// replaced. Only supported if a single programming language is used.
+    #[clap(long, env = "COMMON_SENSE_CODER_LSP_INIT_OPTIONS", value_parser = InitOptions::parse)]
+    lsp_init_options: Option<InitOptions>,
+
+    /// Progress report (token or title) that is expected before the language server is ready, replaces the defaults
+    /// of the programming language.
+    ///
+    /// Can be repeated. Parts are comma-separated when passed via environment variable. Only supported if a single
+    /// programming language is used.
+    ///
+    /// Conflicts with `--lsp-init-progress-extra`.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LSP_INIT_PROGRESS",
+        value_delimiter = ',',
+        conflicts_with = "lsp_init_progress_extra"
+    )]
+    lsp_init_progress: Vec<String>,
+
+    /// Progress report (token or title) that is expected before the language server is ready, in addition to the
+    /// defaults of the programming language.
+    ///
+    /// Can be repeated. Parts are comma-separated when passed via environment variable. Only supported if a single
+    /// programming language is used.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LSP_INIT_PROGRESS_EXTRA",
+        value_delimiter = ','
+    )]
+    lsp_init_progress_extra: Vec<String>,
+}
+
+/// Source of [`LspOverridesCLIConfig::lsp_init_options`].
+#[derive(Debug, Clone)]
+enum InitOptions {
+    Inline(Value),
+    File(PathBuf),
+}
+
+impl InitOptions {
+    fn parse(s: &str) -> Result<Self> {
+        let this = match s.strip_prefix('@') {
+            Some(path) => Self::File(PathBuf::from(path)),
+            None => Self::Inline(serde_json::from_str(s).context("parse JSON")?),
+        };
+
+        // validate early
+        this.load()?;
+
+        Ok(this)
+    }
+
+    fn load(&self) -> Result<Value> {
+        match self {
+            Self::Inline(value) => Ok(value.clone()),
+            Self::File(path) => {
+                let s = std::fs::read_to_string(path)
+                    .with_context(|| format!("read file: {}", path.display()))?;
+                serde_json::from_str(&s)
+                    .with_context(|| format!("parse JSON file: {}", path.display()))
+            }
+        }
+    }
+}
+
+impl LspOverridesCLIConfig {
+    /// File that the initialization options are read from, if any.
+    pub(crate) fn init_options_file(&self) -> Option<&Path> {
+        match &self.lsp_init_options {
+            Some(InitOptions::File(path)) => Some(path),
+            Some(InitOptions::Inline(_)) | None => None,
+        }
+    }
+
+    /// Apply overrides to resolved languages.
+    pub(crate) fn apply(
+        &self,
+        languages: Vec<(ProgrammingLanguage, Arc<dyn ProgrammingLanguageQuirks>)>,
+    ) -> Result<Vec<(ProgrammingLanguage, Arc<dyn ProgrammingLanguageQuirks>)>> {
+        let Self {
+            lsp_command,
+            lsp_arg,
+            lsp_init_options,
+            lsp_init_progress,
+            lsp_init_progress_extra,
+        } = self;
+
+        if lsp_command.is_none()
+            && lsp_arg.is_empty()
+            && lsp_init_options.is_none()
+            && lsp_init_progress.is_empty()
+            && lsp_init_progress_extra.is_empty()
+        {
+            return Ok(languages);
+        }
+        ensure!(
+            languages.len() == 1,
+            "language server overrides require exactly one programming language, got {}",
+            languages.len(),
+        );
+
+        languages
+            .into_iter()
+            .map(|(lang, inner)| {
+                let LanguageServerCommand { binary, args } = inner.language_server();
+                let command = match lsp_command {
+                    Some(binary) => LanguageServerCommand {
+                        binary: binary.clone(),
+                        args: lsp_arg.clone(),
+                    },
+                    None => LanguageServerCommand {
+                        binary,
+                        args: if lsp_arg.is_empty() {
+                            args
+                        } else {
+                            lsp_arg.clone()
+                        },
+                    },
+                };
+
+                let readiness = if !lsp_init_progress.is_empty() {
+                    Some(Readiness::Progress {
+                        parts: lsp_init_progress.iter().cloned().collect(),
+                    })
+                } else if !lsp_init_progress_extra.is_empty() {
+                    let Readiness::Progress { mut parts } = inner.readiness() else {
+                        bail!(
+                            "`--lsp-init-progress-extra` requires a language server that waits for progress reports"
+                        );
+                    };
+                    parts.extend(lsp_init_progress_extra.iter().cloned());
+                    Some(Readiness::Progress { parts })
+                } else {
+                    None
+                };
+
+                Ok((
+                    lang,
+                    Arc::new(Overridden {
+                        inner,
+                        command,
+                        initialization_options: lsp_init_options.clone(),
+                        readiness,
+                    }) as Arc<dyn ProgrammingLanguageQuirks>,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Quirks with user-provided overrides.
+#[derive(Debug)]
+pub(super) struct Overridden {
+    inner: Arc<dyn ProgrammingLanguageQuirks>,
+    command: LanguageServerCommand,
+    initialization_options: Option<InitOptions>,
+    readiness: Option<Readiness>,
+}
+
+impl Overridden {
+    /// Override only the language server command.
+    pub(super) fn with_command(
+        inner: Arc<dyn ProgrammingLanguageQuirks>,
+        command: LanguageServerCommand,
+    ) -> Self {
+        Self {
+            inner,
+            command,
+            initialization_options: None,
+            readiness: None,
+        }
+    }
+}
+
+impl ProgrammingLanguageQuirks for Overridden {
+    fn language_server(&self) -> LanguageServerCommand {
+        self.command.clone()
+    }
+
+    fn language_server_env(&self) -> HashMap<String, String> {
+        self.inner.language_server_env()
+    }
+
+    fn release(&self) -> Option<LanguageServerRelease> {
+        // a pinned release is only meaningful for the default binary
+        if self.command.binary == self.inner.language_server().binary {
+            self.inner.release()
+        } else {
+            None
+        }
+    }
+
+    fn install_hint(&self) -> Option<String> {
+        // the hint installs the default binary
+        if self.command.binary == self.inner.language_server().binary {
+            self.inner.install_hint()
+        } else {
+            None
+        }
+    }
+
+    fn file_extensions(&self) -> HashSet<String> {
+        self.inner.file_extensions()
+    }
+
+    fn initialization_options(&self, workspace: &Path) -> Result<Option<Value>> {
+        let base = self.inner.initialization_options(workspace)?;
+        let overlay = self
+            .initialization_options
+            .as_ref()
+            .map(InitOptions::load)
+            .transpose()?;
+        Ok(match (base, overlay) {
+            (Some(mut base), Some(overlay)) => {
+                deep_merge(&mut base, overlay);
+                Some(base)
+            }
+            (base, overlay) => overlay.or(base),
+        })
+    }
+
+    fn minimum_version(&self) -> Option<MinimumVersion> {
+        self.inner.minimum_version()
+    }
+
+    fn readiness(&self) -> Readiness {
+        self.readiness
+            .clone()
+            .unwrap_or_else(|| self.inner.readiness())
+    }
+
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64> {
+        self.inner.semantic_token_modifier_scores()
+    }
+
+    fn docs_url(&self, symbol: &DependencySymbol<'_>) -> Option<String> {
+        self.inner.docs_url(symbol)
+    }
+
+    fn is_package_manifest(&self, file_name: &str) -> bool {
+        self.inner.is_package_manifest(file_name)
+    }
+
+    fn flycheck_progress_prefix(&self) -> Option<String> {
+        self.inner.flycheck_progress_prefix()
+    }
+
+    fn supports_runnables(&self) -> bool {
+        self.inner.supports_runnables()
+    }
+
+    fn supports_parent_module(&self) -> bool {
+        self.inner.supports_parent_module()
+    }
+
+    fn supports_syntax_trees(&self) -> bool {
+        self.inner.supports_syntax_trees()
+    }
+
+    fn cfg_conditions(&self, content: &str, position: Position) -> Vec<String> {
+        self.inner.cfg_conditions(content, position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use crate::lang::{csharp::CSharp, rust::Rust};
+
+    use super::*;
+
+    fn apply(args: &[&str]) -> Result<Vec<LanguageServerCommand>> {
+        let cfg =
+            LspOverridesCLIConfig::try_parse_from(std::iter::once("").chain(args.iter().copied()))
+                .unwrap();
+        Ok(cfg
+            .apply(vec![(
+                ProgrammingLanguage::Rust,
+                Arc::new(Rust::default()) as Arc<dyn ProgrammingLanguageQuirks>,
+            )])?
+            .into_iter()
+            .map(|(_lang, quirks)| quirks.language_server())
+            .collect())
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(
+            apply(&[]).unwrap(),
+            vec![LanguageServerCommand::binary("rust-analyzer")],
+        );
+        assert_eq!(
+            apply(&["--lsp-arg=-v"]).unwrap(),
+            vec![LanguageServerCommand {
+                binary: "rust-analyzer".to_owned(),
+                args: vec!["-v".to_owned()],
+            }],
+        );
+        assert_eq!(
+            apply(&[
+                "--lsp-command=rustup",
+                "--lsp-arg=run",
+                "--lsp-arg",
+                "nightly",
+                "--lsp-arg=rust-analyzer",
+            ])
+            .unwrap(),
+            vec![LanguageServerCommand {
+                binary: "rustup".to_owned(),
+                args: vec![
+                    "run".to_owned(),
+                    "nightly".to_owned(),
+                    "rust-analyzer".to_owned(),
+                ],
+            }],
+        );
+        assert_eq!(
+            apply(&[
+                "--lsp-arg=run,nightly",
+                "--lsp-arg=--log-file=/tmp/ra log.txt"
+            ])
+            .unwrap(),
+            vec![LanguageServerCommand {
+                binary: "rust-analyzer".to_owned(),
+                args: vec![
+                    "run".to_owned(),
+                    "nightly".to_owned(),
+                    "--log-file=/tmp/ra log.txt".to_owned(),
+                ],
+            }],
+        );
+        assert_eq!(
+            apply(&["--lsp-command=/opt/ra"]).unwrap(),
+            vec![LanguageServerCommand::binary("/opt/ra")],
+        );
+    }
+
+    #[test]
+    fn test_multiple_languages() {
+        let cfg = LspOverridesCLIConfig::try_parse_from(["", "--lsp-command=foo"]).unwrap();
+        let err = cfg
+            .apply(vec![
+                (ProgrammingLanguage::Rust, Arc::new(Rust::default()) as _),
+                (ProgrammingLanguage::CSharp, Arc::new(CSharp) as _),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "language server overrides require exactly one programming language, got 2",
+        );
+
+        let cfg = LspOverridesCLIConfig::default();
+        assert_eq!(
+            cfg.apply(vec![
+                (ProgrammingLanguage::Rust, Arc::new(Rust::default()) as _),
+                (ProgrammingLanguage::CSharp, Arc::new(CSharp) as _),
+            ])
+            .unwrap()
+            .len(),
+            2,
+        );
+    }
+
+    #[test]
+    fn test_init_progress() {
+        let readiness = |args: &[&str]| {
+            let cfg = LspOverridesCLIConfig::try_parse_from(
+                std::iter::once("").chain(args.iter().copied()),
+            )
+            .unwrap();
+            let (_lang, quirks) = cfg
+                .apply(vec![(
+                    ProgrammingLanguage::Rust,
+                    Arc::new(Rust::default()) as _,
+                )])
+                .unwrap()
+                .pop()
+                .unwrap();
+            quirks.readiness()
+        };
+
+        assert_eq!(readiness(&[]), Rust::default().readiness());
+        assert_eq!(
+            readiness(&["--lsp-init-progress=a", "--lsp-init-progress=b"]),
+            Readiness::progress(["a", "b"]),
+        );
+        assert_eq!(
+            readiness(&["--lsp-init-progress-extra=a"]),
+            Readiness::progress([
+                "rustAnalyzer/Building CrateGraph",
+                "rustAnalyzer/Roots Scanned",
+                "rustAnalyzer/cachePriming",
+                "rust-analyzer/flycheck/0",
+                "a",
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_init_options() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("init.json");
+        std::fs::write(&path, r#"{"cargo": {"features": "all"}}"#).unwrap();
+
+        for arg in [
+            r#"--lsp-init-options={"cargo": {"features": "all"}}"#.to_owned(),
+            format!("--lsp-init-options=@{}", path.display()),
+        ] {
+            let cfg = LspOverridesCLIConfig::try_parse_from(["", &arg]).unwrap();
+            let (_lang, quirks) = cfg
+                .apply(vec![(
+                    ProgrammingLanguage::Rust,
+                    Arc::new(Rust::default()) as _,
+                )])
+                .unwrap()
+                .pop()
+                .unwrap();
+            let options = quirks
+                .initialization_options(Path::new("/"))
+                .unwrap()
+                .unwrap();
+            assert_eq!(options["cargo"]["features"], json!("all"));
+            assert_eq!(options["files"]["watcher"], json!("server"));
+        }
+
+        // file is re-read
+        let cfg = LspOverridesCLIConfig::try_parse_from([
+            "",
+            &format!("--lsp-init-options=@{}", path.display()),
+        ])
+        .unwrap();
+        let (_lang, quirks) = cfg
+            .apply(vec![(
+                ProgrammingLanguage::Rust,
+                Arc::new(Rust::default()) as _,
+            )])
+            .unwrap()
+            .pop()
+            .unwrap();
+        std::fs::write(&path, r#"{"cargo": {"features": ["foo"]}}"#).unwrap();
+        let options = quirks
+            .initialization_options(Path::new("/"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options["cargo"]["features"], json!(["foo"]));
+        std::fs::write(&path, "{").unwrap();
+        let err = quirks.initialization_options(Path::new("/")).unwrap_err();
+        assert!(err.to_string().starts_with("parse JSON file: "), "{err}");
+
+        let err =
+            LspOverridesCLIConfig::try_parse_from(["", "--lsp-init-options=@/does/not/exist"])
+                .unwrap_err();
+        assert!(
+            err.to_string().contains("read file: /does/not/exist"),
+            "{err}"
+        );
+    }
+}
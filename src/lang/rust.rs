@@ -0,0 +1,425 @@
+//! Rust via [rust-analyzer](https://rust-analyzer.github.io/).
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use lsp_types::Position;
+use serde_json::{Value, json};
+
+use crate::lsp::{
+    dependency::{Dependency, Origin},
+    version::MinimumVersion,
+};
+
+use super::{
+    DependencySymbol, LanguageServerCommand, ProgrammingLanguageQuirks, Readiness, cfg, deep_merge,
+    install::LanguageServerRelease,
+};
+
+/// CLI config for rust-analyzer.
+#[derive(Debug, Clone, Default, Parser)]
+pub(crate) struct RustCLIConfig {
+    /// Cargo features to activate, sets `cargo.features`.
+    ///
+    /// Can be repeated or comma-separated. Use `all` to activate all features.
+    #[clap(long, env = "COMMON_SENSE_CODER_RUST_FEATURES", value_delimiter = ',')]
+    rust_features: Vec<String>,
+
+    /// Target triple used for analysis, sets `cargo.target`.
+    #[clap(long, env = "COMMON_SENSE_CODER_RUST_TARGET")]
+    rust_target: Option<String>,
+
+    /// Cargo subcommand used for checks, e.g. `clippy`, sets `check.command`.
+    #[clap(long, env = "COMMON_SENSE_CODER_RUST_CHECK_COMMAND")]
+    rust_check_command: Option<String>,
+
+    /// Command (and its arguments) used for checks instead of `cargo check`, sets `check.overrideCommand`.
+    ///
+    /// Can be repeated to pass multiple arguments.
+    #[clap(long, allow_hyphen_values = true)]
+    rust_check_override_command: Vec<String>,
+
+    /// Expand procedural macros, sets `procMacro.enable`.
+    #[clap(long, env = "COMMON_SENSE_CODER_RUST_PROC_MACRO")]
+    rust_proc_macro: Option<bool>,
+}
+
+impl RustCLIConfig {
+    /// Initialization options set by this config.
+    fn initialization_options(&self) -> Value {
+        let Self {
+            rust_features,
+            rust_target,
+            rust_check_command,
+            rust_check_override_command,
+            rust_proc_macro,
+        } = self;
+
+        let mut options = json!({});
+        match rust_features.as_slice() {
+            [] => {}
+            [all] if all == "all" => {
+                deep_merge(&mut options, json!({"cargo": {"features": "all"}}))
+            }
+            features => deep_merge(&mut options, json!({"cargo": {"features": features}})),
+        }
+        if let Some(target) = rust_target {
+            deep_merge(&mut options, json!({"cargo": {"target": target}}));
+        }
+        if let Some(command) = rust_check_command {
+            deep_merge(&mut options, json!({"check": {"command": command}}));
+        }
+        if !rust_check_override_command.is_empty() {
+            deep_merge(
+                &mut options,
+                json!({"check": {"overrideCommand": rust_check_override_command}}),
+            );
+        }
+        if let Some(enable) = rust_proc_macro {
+            deep_merge(&mut options, json!({"procMacro": {"enable": enable}}));
+        }
+        options
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Rust {
+    cfg: RustCLIConfig,
+}
+
+impl Rust {
+    pub(crate) fn new(cfg: RustCLIConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+impl ProgrammingLanguageQuirks for Rust {
+    fn language_server(&self) -> LanguageServerCommand {
+        LanguageServerCommand::binary("rust-analyzer")
+    }
+
+    fn language_server_env(&self) -> HashMap<String, String> {
+        HashMap::from([("RA_LOG".to_owned(), "info".to_owned())])
+    }
+
+    fn release(&self) -> Option<LanguageServerRelease> {
+        // Taken from the manifest of the respective Rust release, which only ships rust-analyzer for the listed targets,
+        // see https://static.rust-lang.org/dist/channel-rust-1.90.0.toml
+        let (target, sha256) = match (std::env::consts::ARCH, std::env::consts::OS) {
+            ("x86_64", "linux") => (
+                "x86_64-unknown-linux-gnu",
+                "327e1c7dca7172f12c736b897ce5f01e47f675b6609b99be268cfbff5c4ff01c",
+            ),
+            _ => {
+                return None;
+            }
+        };
+        let version = "1.90.0";
+        Some(LanguageServerRelease {
+            version: version.to_owned(),
+            url: format!(
+                "https://static.rust-lang.org/dist/2025-09-18/rust-analyzer-{version}-{target}.tar.gz"
+            ),
+            sha256: sha256.to_owned(),
+            archive_path: format!(
+                "rust-analyzer-{version}-{target}/rust-analyzer-preview/bin/rust-analyzer"
+            ),
+        })
+    }
+
+    fn install_hint(&self) -> Option<String> {
+        Some("`rustup component add rust-analyzer`".to_owned())
+    }
+
+    fn file_extensions(&self) -> HashSet<String> {
+        HashSet::from(["rs".to_owned()])
+    }
+
+    fn initialization_options(&self, _workspace: &Path) -> Result<Option<Value>> {
+        let mut options = json!({
+            "files": {
+                "watcher": "server",
+            },
+            "hover": {
+                "dropGlue": {
+                    "enable": false,
+                },
+                "memoryLayout": {
+                    "enable": false,
+                },
+                "show": {
+                    "enumVariants": 100,
+                    "fields": 100,
+                    "traitAssocItems": 100,
+                },
+            },
+            "workspace": {
+                "symbol": {
+                    "search": {
+                        "limit": 10_000,
+                    },
+                },
+            },
+        });
+        deep_merge(&mut options, self.cfg.initialization_options());
+        Ok(Some(options))
+    }
+
+    fn minimum_version(&self) -> Option<MinimumVersion> {
+        // roughly when UTF-8 position encoding negotiation (LSP 3.17) landed, workspace symbol filtering and semantic
+        // token deltas are older
+        Some(MinimumVersion::Date("2023-01-01"))
+    }
+
+    fn readiness(&self) -> Readiness {
+        Readiness::progress([
+            "rustAnalyzer/Building CrateGraph",
+            "rustAnalyzer/Roots Scanned",
+            "rustAnalyzer/cachePriming",
+            "rust-analyzer/flycheck/0",
+        ])
+    }
+
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64> {
+        HashMap::from([
+            ("declaration".to_owned(), 10),
+            ("injected".to_owned(), -100),
+            ("library".to_owned(), -1),
+            ("public".to_owned(), 10),
+        ])
+    }
+
+    fn docs_url(&self, symbol: &DependencySymbol<'_>) -> Option<String> {
+        let Dependency {
+            name,
+            version,
+            origin,
+            ..
+        } = symbol.dependency;
+        let base = match (origin, version) {
+            (Origin::Registry, Some(version)) => format!("https://docs.rs/{name}/{version}"),
+            (Origin::Std, _) => "https://doc.rust-lang.org/stable".to_owned(),
+            // git and path dependencies are not published
+            _ => return None,
+        };
+
+        // rust-analyzer starts the hover with the path of the parent item, e.g. `serde::de`
+        let parent = symbol.hover.first().copied().filter(|s| is_rust_path(s));
+        let item = parent.and_then(|parent| {
+            let dir = parent.replace("::", "/");
+            let file = match symbol.token_type {
+                "namespace" => return Some(format!("{dir}/{}/index.html", symbol.name)),
+                "enumMember" => {
+                    // parent is the enum
+                    let (module, enum_name) = dir.rsplit_once('/')?;
+                    return Some(format!(
+                        "{module}/enum.{enum_name}.html#variant.{}",
+                        symbol.name
+                    ));
+                }
+                "enum" => "enum",
+                "function" => "fn",
+                "interface" => "trait",
+                "macro" => "macro",
+                "struct" => "struct",
+                "typeAlias" => "type",
+                "union" => "union",
+                _ => return None,
+            };
+            Some(format!("{dir}/{file}.{}.html", symbol.name))
+        });
+
+        Some(match item {
+            Some(item) => format!("{base}/{item}"),
+            None => {
+                let lib = parent
+                    .and_then(|parent| parent.split("::").next())
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_else(|| name.replace('-', "_"));
+                format!("{base}/{lib}/?search={}", symbol.name)
+            }
+        })
+    }
+
+    fn is_package_manifest(&self, file_name: &str) -> bool {
+        file_name == "Cargo.toml"
+    }
+
+    fn flycheck_progress_prefix(&self) -> Option<String> {
+        Some("rust-analyzer/flycheck/".to_owned())
+    }
+
+    fn supports_runnables(&self) -> bool {
+        true
+    }
+
+    fn supports_parent_module(&self) -> bool {
+        true
+    }
+
+    fn supports_syntax_trees(&self) -> bool {
+        true
+    }
+
+    fn cfg_conditions(&self, content: &str, position: Position) -> Vec<String> {
+        cfg::conditions(content, position)
+    }
+}
+
+/// Check if the string is a path like `std::collections`.
+fn is_rust_path(s: &str) -> bool {
+    s.split("::").all(|segment| {
+        !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(args: &[&str]) -> Value {
+        let cfg =
+            RustCLIConfig::try_parse_from(std::iter::once("").chain(args.iter().copied())).unwrap();
+        Rust::new(cfg)
+            .initialization_options(Path::new("/"))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_initialization_options() {
+        let default = options(&[]);
+        assert_eq!(
+            default,
+            Rust::default()
+                .initialization_options(Path::new("/"))
+                .unwrap()
+                .unwrap()
+        );
+        assert!(default.get("cargo").is_none());
+
+        let options_all = options(&["--rust-features=all"]);
+        assert_eq!(options_all["cargo"], json!({"features": "all"}));
+
+        let options = options(&[
+            "--rust-features=a,b",
+            "--rust-target=wasm32-unknown-unknown",
+            "--rust-check-command=clippy",
+            "--rust-check-override-command=cargo",
+            "--rust-check-override-command=check",
+            "--rust-check-override-command=--message-format=json",
+            "--rust-proc-macro=false",
+        ]);
+        assert_eq!(
+            options["cargo"],
+            json!({"features": ["a", "b"], "target": "wasm32-unknown-unknown"}),
+        );
+        assert_eq!(
+            options["check"],
+            json!({
+                "command": "clippy",
+                "overrideCommand": ["cargo", "check", "--message-format=json"],
+            }),
+        );
+        assert_eq!(options["procMacro"], json!({"enable": false}));
+        assert_eq!(options["files"], default["files"]);
+    }
+
+    #[test]
+    fn test_docs_url() {
+        let serde = Dependency {
+            name: "serde".to_owned(),
+            version: Some("1.0.200".to_owned()),
+            path: "src/de/mod.rs".to_owned(),
+            root: "/cargo/registry/src/index/serde-1.0.200".into(),
+            origin: Origin::Registry,
+        };
+        let core = Dependency {
+            name: "core".to_owned(),
+            version: None,
+            path: "src/option.rs".to_owned(),
+            root: "/rust/lib/rustlib/src/rust/library/core".into(),
+            origin: Origin::Std,
+        };
+        let local = Dependency {
+            name: "my_lib".to_owned(),
+            version: Some("0.1.0".to_owned()),
+            path: "src/lib.rs".to_owned(),
+            root: "/libs/my_lib".into(),
+            origin: Origin::Path,
+        };
+        let docs_url = |dependency, name, token_type, hover: &[&str]| {
+            Rust::default().docs_url(&DependencySymbol {
+                dependency,
+                name,
+                token_type,
+                hover: hover.to_vec(),
+            })
+        };
+
+        assert_eq!(
+            docs_url(
+                &serde,
+                "Deserialize",
+                "interface",
+                &["serde::de", "pub trait Deserialize<'de>"]
+            )
+            .as_deref(),
+            Some("https://docs.rs/serde/1.0.200/serde/de/trait.Deserialize.html"),
+        );
+        assert_eq!(
+            docs_url(&serde, "de", "namespace", &["serde", "pub mod de"]).as_deref(),
+            Some("https://docs.rs/serde/1.0.200/serde/de/index.html"),
+        );
+        assert_eq!(
+            docs_url(
+                &core,
+                "Option",
+                "enum",
+                &["core::option", "pub enum Option<T>"]
+            )
+            .as_deref(),
+            Some("https://doc.rust-lang.org/stable/core/option/enum.Option.html"),
+        );
+        assert_eq!(
+            docs_url(
+                &core,
+                "Some",
+                "enumMember",
+                &["core::option::Option", "Some(T)"]
+            )
+            .as_deref(),
+            Some("https://doc.rust-lang.org/stable/core/option/enum.Option.html#variant.Some"),
+        );
+        assert_eq!(
+            docs_url(
+                &core,
+                "map",
+                "method",
+                &[
+                    "core::option::Option",
+                    "pub fn map<U, F>(self, f: F) -> Option<U>"
+                ]
+            )
+            .as_deref(),
+            Some("https://doc.rust-lang.org/stable/core/?search=map"),
+        );
+        assert_eq!(
+            docs_url(
+                &serde,
+                "Deserialize",
+                "interface",
+                &["pub trait Deserialize<'de>"]
+            )
+            .as_deref(),
+            Some("https://docs.rs/serde/1.0.200/serde/?search=Deserialize"),
+        );
+        assert_eq!(
+            docs_url(&local, "Foo", "struct", &["my_lib", "pub struct Foo"]),
+            None
+        );
+    }
+}
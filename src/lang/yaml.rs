@@ -0,0 +1,89 @@
+//! YAML (e.g. CI configs) via [yaml-language-server](https://github.com/redhat-developer/yaml-language-server).
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::Result;
+use lsp_types::Position;
+
+use crate::lsp::version::MinimumVersion;
+
+use super::{
+    DependencySymbol, LanguageServerCommand, ProgrammingLanguageQuirks, Readiness,
+    install::LanguageServerRelease,
+};
+
+#[derive(Debug)]
+pub(crate) struct Yaml;
+
+impl ProgrammingLanguageQuirks for Yaml {
+    fn language_server(&self) -> LanguageServerCommand {
+        LanguageServerCommand {
+            binary: "yaml-language-server".to_owned(),
+            args: vec!["--stdio".to_owned()],
+        }
+    }
+
+    fn language_server_env(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    fn release(&self) -> Option<LanguageServerRelease> {
+        // distributed via npm
+        None
+    }
+
+    fn install_hint(&self) -> Option<String> {
+        Some("`npm install --global yaml-language-server`".to_owned())
+    }
+
+    fn file_extensions(&self) -> HashSet<String> {
+        HashSet::from(["yaml".to_owned(), "yml".to_owned()])
+    }
+
+    fn initialization_options(&self, _workspace: &Path) -> Result<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    fn minimum_version(&self) -> Option<MinimumVersion> {
+        None
+    }
+
+    fn readiness(&self) -> Readiness {
+        // only parses the files that are queried, there is no initial indexing
+        Readiness::progress([])
+    }
+
+    fn semantic_token_modifier_scores(&self) -> HashMap<String, i64> {
+        HashMap::new()
+    }
+
+    fn docs_url(&self, _symbol: &DependencySymbol<'_>) -> Option<String> {
+        None
+    }
+
+    fn is_package_manifest(&self, _file_name: &str) -> bool {
+        false
+    }
+
+    fn flycheck_progress_prefix(&self) -> Option<String> {
+        None
+    }
+
+    fn supports_runnables(&self) -> bool {
+        false
+    }
+
+    fn supports_parent_module(&self) -> bool {
+        false
+    }
+
+    fn supports_syntax_trees(&self) -> bool {
+        false
+    }
+
+    fn cfg_conditions(&self, _content: &str, _position: Position) -> Vec<String> {
+        vec![]
+    }
+}
@@ -1,8 +1,8 @@
 //! Logging setup.
-use std::io::IsTerminal;
+use std::{fmt, io::IsTerminal};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use tracing_log::LogTracer;
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
@@ -31,32 +31,94 @@ pub(crate) struct LoggingCLIConfig {
         action = clap::ArgAction::Count,
     )]
     log_verbose_count: u8,
+
+    /// Log output format.
+    #[clap(long, default_value = "text", env = "COMMON_SENSE_CODER_LOG_FORMAT")]
+    log_format: LogFormat,
+}
+
+/// Log output format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LogFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+
+    /// One JSON object per line with timestamp, level, target, fields, and spans, e.g. for log aggregation.
+    Json,
 }
 
 /// Setup process-wide logging.
-pub(crate) fn setup_logging(config: LoggingCLIConfig) -> Result<()> {
+///
+/// Returns a handle to change the log filter at runtime.
+pub(crate) fn setup_logging(config: LoggingCLIConfig) -> Result<LogFilterHandle> {
     LogTracer::init().context("init log tracer")?;
 
-    let filter = match config.log_filter {
-        Some(filter) => filter,
-        None => match config.log_verbose_count {
-            0 => "warn".to_owned(),
-            1 => "info".to_owned(),
-            2 => format!("info,{}=debug", env!("CARGO_CRATE_NAME")),
-            3 => "debug".to_owned(),
-            _ => "trace".to_owned(),
-        },
+    let writer = std::io::stderr;
+    let builder = FmtSubscriber::builder()
+        .with_env_filter(config.filter()?)
+        .with_writer(writer);
+    let handle = match config.log_format {
+        LogFormat::Text => {
+            let builder = builder
+                .with_ansi(writer().is_terminal())
+                .with_filter_reloading();
+            let handle = builder.reload_handle();
+            tracing::subscriber::set_global_default(builder.finish())
+                .context("install tracinng subscriber")?;
+            LogFilterHandle(Box::new(move |filter| {
+                handle.reload(filter).context("reload log filter")
+            }))
+        }
+        LogFormat::Json => {
+            // the span list already contains the current span
+            let builder = builder
+                .json()
+                .with_current_span(false)
+                .with_span_list(true)
+                .with_filter_reloading();
+            let handle = builder.reload_handle();
+            tracing::subscriber::set_global_default(builder.finish())
+                .context("install tracinng subscriber")?;
+            LogFilterHandle(Box::new(move |filter| {
+                handle.reload(filter).context("reload log filter")
+            }))
+        }
     };
-    let filter = EnvFilter::try_new(filter).context("set up log env filter")?;
 
-    let writer = std::io::stderr;
-    let subscriber = FmtSubscriber::builder()
-        .with_ansi(writer().is_terminal())
-        .with_env_filter(filter)
-        .with_writer(writer)
-        .finish();
+    Ok(handle)
+}
+
+impl LoggingCLIConfig {
+    fn filter(&self) -> Result<EnvFilter> {
+        let filter = match &self.log_filter {
+            Some(filter) => filter.clone(),
+            None => match self.log_verbose_count {
+                0 => "warn".to_owned(),
+                1 => "info".to_owned(),
+                2 => format!("info,{}=debug", env!("CARGO_CRATE_NAME")),
+                3 => "debug".to_owned(),
+                _ => "trace".to_owned(),
+            },
+        };
+        EnvFilter::try_new(filter).context("set up log env filter")
+    }
+}
 
-    tracing::subscriber::set_global_default(subscriber).context("install tracinng subscriber")?;
+/// Changes the log filter at runtime, see [`setup_logging`].
+///
+/// The log format cannot be changed at runtime.
+pub(crate) struct LogFilterHandle(Box<dyn Fn(EnvFilter) -> Result<()> + Send + Sync>);
+
+impl LogFilterHandle {
+    /// Use the log filter of the given config from now on.
+    pub(crate) fn reload(&self, config: &LoggingCLIConfig) -> Result<()> {
+        (self.0)(config.filter()?)
+    }
+}
 
-    Ok(())
+impl fmt::Debug for LogFilterHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LogFilterHandle").finish_non_exhaustive()
+    }
 }
@@ -0,0 +1,438 @@
+//! Memory budget that all caches share.
+//!
+//! Every cache of every language server registers at the [`CacheRegistry`]. Once the summed up size of all entries
+//! exceeds the budget, the least recently used entries are evicted first, regardless of the cache they belong to. This
+//! keeps long-running servers (e.g. in daemon mode) from growing without bounds.
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use lsp_types::SymbolInformation;
+use rmcp::schemars;
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct CacheCLIConfig {
+    /// Upper limit (in MiB) for the memory of all caches combined, e.g. hover results and semantic tokens.
+    ///
+    /// The least recently used entries are evicted first.
+    #[clap(long, default_value = "64", env = "COMMON_SENSE_CODER_CACHE_MAX_MIB")]
+    cache_max_mib: usize,
+}
+
+impl CacheCLIConfig {
+    pub(crate) fn registry(&self) -> CacheRegistry {
+        CacheRegistry::new(self.cache_max_mib.saturating_mul(1024 * 1024))
+    }
+}
+
+/// Cache whose entries count towards the budget.
+trait Evict: Debug + Send + Sync {
+    /// Summed up size of all entries.
+    fn bytes(&self) -> usize;
+
+    /// Logical time of the least recently used entry, `None` if the cache is empty.
+    fn oldest(&self) -> Option<u64>;
+
+    /// Evict the least recently used entry and return its size.
+    fn evict_oldest(&self) -> usize;
+}
+
+/// Shared by all caches of all language servers.
+#[derive(Debug, Clone)]
+pub(crate) struct CacheRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+#[derive(Debug)]
+struct RegistryInner {
+    max_bytes: usize,
+
+    /// Increased with every use, orders the entries of all caches by recency.
+    clock: AtomicU64,
+
+    /// Registered caches, also serializes evictions.
+    caches: Mutex<Vec<Weak<dyn Evict>>>,
+
+    evictions: AtomicU64,
+}
+
+impl CacheRegistry {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(RegistryInner {
+                max_bytes,
+                clock: AtomicU64::new(0),
+                caches: Default::default(),
+                evictions: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Create cache that counts towards the budget.
+    pub(crate) fn lru<K, V>(&self) -> Arc<Lru<K, V>>
+    where
+        K: Clone + Debug + Eq + Hash + Send + 'static,
+        V: Clone + Debug + Send + 'static,
+    {
+        let lru = Arc::new(Lru {
+            registry: self.clone(),
+            state: Mutex::new(LruState {
+                entries: HashMap::new(),
+                bytes: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        });
+        let weak = Arc::downgrade(&lru) as Weak<dyn Evict>;
+        self.inner.caches.lock().expect("not poisoned").push(weak);
+        lru
+    }
+
+    fn tick(&self) -> u64 {
+        self.inner.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Evict the least recently used entries until all caches fit into the budget.
+    fn enforce(&self) {
+        let mut caches = self.inner.caches.lock().expect("not poisoned");
+        caches.retain(|cache| cache.strong_count() > 0);
+        let caches = caches.iter().filter_map(Weak::upgrade).collect::<Vec<_>>();
+
+        let mut bytes = caches.iter().map(|cache| cache.bytes()).sum::<usize>();
+        while bytes > self.inner.max_bytes {
+            let Some(cache) = caches
+                .iter()
+                .filter_map(|cache| cache.oldest().map(|last_used| (last_used, cache)))
+                .min_by_key(|(last_used, _cache)| *last_used)
+                .map(|(_last_used, cache)| cache)
+            else {
+                break;
+            };
+            bytes -= cache.evict_oldest();
+            self.inner.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CacheBudgetStats {
+        let bytes = self
+            .inner
+            .caches
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|cache| cache.bytes())
+            .sum();
+        CacheBudgetStats {
+            max_bytes: self.inner.max_bytes,
+            bytes,
+            evictions: self.inner.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Memory usage of all caches combined.
+#[derive(Debug, Clone, Copy, serde::Serialize, schemars::JsonSchema)]
+pub(crate) struct CacheBudgetStats {
+    /// budget in bytes, see `--cache-max-mib`
+    max_bytes: usize,
+
+    /// approximate memory footprint of all cached entries in bytes
+    bytes: usize,
+
+    /// entries that were evicted to stay within the budget
+    evictions: u64,
+}
+
+#[derive(Debug)]
+struct LruEntry<V> {
+    value: V,
+    size: usize,
+
+    /// Logical time of the last use, see [`RegistryInner::clock`].
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct LruState<K, V> {
+    entries: HashMap<K, LruEntry<V>>,
+
+    /// Summed up size of all entries.
+    bytes: usize,
+}
+
+/// Cache with entries that count towards the budget of the [`CacheRegistry`].
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    registry: CacheRegistry,
+    state: Mutex<LruState<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Clone + Debug + Eq + Hash + Send + 'static,
+    V: Clone + Debug + Send + 'static,
+{
+    /// Cached value, `None` if there is none or it is no longer `valid`.
+    pub(crate) fn get(&self, key: &K, valid: impl FnOnce(&V) -> bool) -> Option<V> {
+        let now = self.registry.tick();
+        let value = self
+            .state
+            .lock()
+            .expect("not poisoned")
+            .entries
+            .get_mut(key)
+            .filter(|entry| valid(&entry.value))
+            .map(|entry| {
+                entry.last_used = now;
+                entry.value.clone()
+            });
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Remember value with the given size in bytes, evicting other entries if the budget is exhausted.
+    ///
+    /// Values that are larger than the entire budget are not cached.
+    pub(crate) fn insert(&self, key: K, value: V, size: usize) {
+        if size > self.registry.inner.max_bytes {
+            self.remove(&key);
+            return;
+        }
+
+        let last_used = self.registry.tick();
+        {
+            let mut state = self.state.lock().expect("not poisoned");
+            let previous = state.entries.insert(
+                key,
+                LruEntry {
+                    value,
+                    size,
+                    last_used,
+                },
+            );
+            state.bytes += size;
+            if let Some(previous) = previous {
+                state.bytes -= previous.size;
+            }
+        }
+        self.registry.enforce();
+    }
+
+    pub(crate) fn remove(&self, key: &K) {
+        let mut state = self.state.lock().expect("not poisoned");
+        if let Some(entry) = state.entries.remove(key) {
+            state.bytes -= entry.size;
+        }
+    }
+
+    /// Only keep entries for which `f` returns `true`.
+    pub(crate) fn retain(&self, mut f: impl FnMut(&K, &V) -> bool) {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.entries.retain(|key, entry| f(key, &entry.value));
+        state.bytes = state.entries.values().map(|entry| entry.size).sum();
+    }
+
+    pub(crate) fn clear(&self) {
+        self.retain(|_key, _value| false);
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        let (entries, bytes) = {
+            let state = self.state.lock().expect("not poisoned");
+            (state.entries.len(), state.bytes)
+        };
+        CacheStats::new(
+            entries,
+            bytes,
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+            self.evictions.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl<K, V> Evict for Lru<K, V>
+where
+    K: Clone + Debug + Eq + Hash + Send + 'static,
+    V: Clone + Debug + Send + 'static,
+{
+    fn bytes(&self) -> usize {
+        self.state.lock().expect("not poisoned").bytes
+    }
+
+    fn oldest(&self) -> Option<u64> {
+        self.state
+            .lock()
+            .expect("not poisoned")
+            .entries
+            .values()
+            .map(|entry| entry.last_used)
+            .min()
+    }
+
+    fn evict_oldest(&self) -> usize {
+        let mut state = self.state.lock().expect("not poisoned");
+        let Some(key) = state
+            .entries
+            .iter()
+            .min_by_key(|(_key, entry)| entry.last_used)
+            .map(|(key, _entry)| key.clone())
+        else {
+            return 0;
+        };
+        let entry = state.entries.remove(&key).expect("just found");
+        state.bytes -= entry.size;
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+        entry.size
+    }
+}
+
+/// Effectiveness of a cache.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub(crate) struct CacheStats {
+    /// number of cached entries
+    entries: usize,
+
+    /// approximate memory footprint of the cached entries in bytes
+    bytes: usize,
+
+    /// lookups that were answered from the cache
+    hits: u64,
+
+    /// lookups that needed a request to the language server
+    misses: u64,
+
+    /// share of lookups that were answered from the cache, `null` if there were none
+    hit_rate: Option<f64>,
+
+    /// entries that were evicted to stay within `--cache-max-mib`
+    evictions: u64,
+}
+
+impl CacheStats {
+    fn new(entries: usize, bytes: usize, hits: u64, misses: u64, evictions: u64) -> Self {
+        Self {
+            entries,
+            bytes,
+            hits,
+            misses,
+            hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+            evictions,
+        }
+    }
+}
+
+impl std::ops::Add for CacheStats {
+    type Output = Self;
+
+    /// Stats of two caches combined.
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.entries + other.entries,
+            self.bytes + other.bytes,
+            self.hits + other.hits,
+            self.misses + other.misses,
+            self.evictions + other.evictions,
+        )
+    }
+}
+
+/// Approximate memory footprint of the symbols in bytes.
+pub(crate) fn symbols_size(symbols: &[SymbolInformation]) -> usize {
+    symbols
+        .iter()
+        .map(|symbol| {
+            size_of::<SymbolInformation>()
+                + symbol.name.len()
+                + symbol
+                    .container_name
+                    .as_ref()
+                    .map(String::len)
+                    .unwrap_or_default()
+                + symbol.location.uri.as_str().len()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let registry = CacheRegistry::new(10);
+        let lru = registry.lru::<u32, &'static str>();
+        assert_eq!(lru.get(&1, |_| true), None);
+
+        lru.insert(1, "1234", 4);
+        lru.insert(2, "1234", 4);
+        assert_eq!(lru.get(&1, |_| true), Some("1234"));
+        lru.insert(3, "1234", 4);
+        assert_eq!(lru.get(&1, |_| true), Some("1234"));
+        assert_eq!(lru.get(&2, |_| true), None);
+        assert_eq!(lru.get(&3, |_| true), Some("1234"));
+
+        // invalid entries are misses
+        assert_eq!(lru.get(&3, |_| false), None);
+
+        // too large
+        lru.insert(4, "12345678901", 11);
+        assert_eq!(lru.get(&4, |_| true), None);
+        assert_eq!(lru.get(&1, |_| true), Some("1234"));
+
+        assert_eq!(
+            serde_json::to_value(lru.stats()).unwrap(),
+            serde_json::json!({
+                "entries": 2,
+                "bytes": 8,
+                "hits": 4,
+                "misses": 4,
+                "hit_rate": 0.5,
+                "evictions": 1,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_shared_budget() {
+        let registry = CacheRegistry::new(10);
+        let a = registry.lru::<u32, ()>();
+        let b = registry.lru::<u32, ()>();
+
+        a.insert(1, (), 4);
+        b.insert(1, (), 4);
+        a.get(&1, |_| true);
+
+        // least recently used entry of any cache is evicted first
+        b.insert(2, (), 4);
+        assert_eq!(a.get(&1, |_| true), Some(()));
+        assert_eq!(b.get(&1, |_| true), None);
+        assert_eq!(b.get(&2, |_| true), Some(()));
+
+        // dropped caches free their budget
+        drop(a);
+        b.insert(3, (), 4);
+        assert_eq!(b.get(&2, |_| true), Some(()));
+
+        assert_eq!(
+            serde_json::to_value(registry.stats()).unwrap(),
+            serde_json::json!({"max_bytes": 10, "bytes": 8, "evictions": 1}),
+        );
+    }
+}
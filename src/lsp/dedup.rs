@@ -0,0 +1,290 @@
+//! Coalesce identical in-flight requests.
+//!
+//! Concurrent tool calls (e.g. from batch mode or multiple MCP clients) often issue the very same requests, like
+//! semantic tokens for the same file. Those share a single request to the language server.
+use std::{
+    any::Any,
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use futures::{
+    FutureExt,
+    future::{BoxFuture, Shared},
+};
+use lsp_client::{LspClient, LspError};
+use lsp_types::request::Request;
+use tracing::debug;
+
+use super::{
+    metrics::RequestMetrics,
+    partial::{PartialResultRequest, PartialResults},
+    retry::RetryCLIConfig,
+};
+
+/// Response that is shared by all callers.
+type SharedResponse<T> = Shared<BoxFuture<'static, Result<T, Arc<LspError>>>>;
+
+/// In-flight requests, keyed by method and serialized parameters.
+///
+/// The values are [`SharedResponse`]s of the respective result type.
+type InFlight = HashMap<(&'static str, String), Box<dyn Any + Send>>;
+
+/// Language server client that coalesces identical in-flight requests.
+///
+/// Everything but [`send_request`](Self::send_request) and [`send_request_partial`](Self::send_request_partial) is
+/// passed through to the [`LspClient`].
+#[derive(Debug, Clone)]
+pub(crate) struct DedupClient {
+    client: Arc<LspClient>,
+    in_flight: Arc<Mutex<InFlight>>,
+    partial_results: PartialResults,
+    retry_cfg: RetryCLIConfig,
+    metrics: RequestMetrics,
+}
+
+impl DedupClient {
+    pub(crate) fn new(
+        client: Arc<LspClient>,
+        partial_results: PartialResults,
+        retry_cfg: RetryCLIConfig,
+    ) -> Self {
+        Self {
+            client,
+            in_flight: Default::default(),
+            partial_results,
+            retry_cfg,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Statistics of the requests sent to the server.
+    ///
+    /// Coalesced requests are only counted once.
+    pub(crate) fn metrics(&self) -> &RequestMetrics {
+        &self.metrics
+    }
+
+    /// Send an LSP request to the server, or join an identical request that is already in flight.
+    ///
+    /// Transient errors are retried.
+    pub(crate) async fn send_request<R>(
+        &self,
+        params: R::Params,
+    ) -> Result<R::Result, Arc<LspError>>
+    where
+        R: Request,
+        R::Params: Clone,
+        R::Result: Clone,
+    {
+        self.coalesce::<R, _, _>(params, |client, _partial_results, params| async move {
+            client.send_request::<R>(params).await
+        })
+        .await
+    }
+
+    /// Same as [`send_request`](Self::send_request), but assemble partial results that the server streams.
+    pub(crate) async fn send_request_partial<R>(
+        &self,
+        params: R::Params,
+    ) -> Result<R::Result, Arc<LspError>>
+    where
+        R: PartialResultRequest,
+        R::Params: Clone,
+        R::Result: Clone,
+    {
+        self.coalesce::<R, _, _>(params, |client, partial_results, params| async move {
+            partial_results.send_request::<R>(&client, params).await
+        })
+        .await
+    }
+
+    async fn coalesce<R, F, Fut>(
+        &self,
+        params: R::Params,
+        send: F,
+    ) -> Result<R::Result, Arc<LspError>>
+    where
+        R: Request,
+        R::Params: Clone,
+        R::Result: Clone,
+        F: Fn(Arc<LspClient>, PartialResults, R::Params) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R::Result, LspError>> + Send + 'static,
+    {
+        let key = serde_json::to_string(&params).map(|params| (R::METHOD, params));
+
+        let client = Arc::clone(&self.client);
+        let partial_results = self.partial_results.clone();
+        let retry_cfg = self.retry_cfg;
+        let metrics = self.metrics.clone();
+        let fut = async move {
+            let start = Instant::now();
+            let res = retry_cfg
+                .run(R::METHOD, || {
+                    send(Arc::clone(&client), partial_results.clone(), params.clone())
+                })
+                .await;
+            metrics.record(R::METHOD, start.elapsed(), res.is_ok());
+            res.map_err(Arc::new)
+        };
+
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                debug!(method = R::METHOD, %e, "cannot serialize params, do not coalesce");
+                return fut.await;
+            }
+        };
+
+        let fut = {
+            let mut in_flight = self.in_flight.lock().expect("not poisoned");
+            let existing = in_flight
+                .get(&key)
+                .and_then(|fut| fut.downcast_ref::<SharedResponse<R::Result>>())
+                .cloned();
+            match existing {
+                Some(fut) => {
+                    debug!(method = R::METHOD, "coalesce request");
+                    fut
+                }
+                None => {
+                    let fut: SharedResponse<R::Result> = fut.boxed().shared();
+                    in_flight.insert(key.clone(), Box::new(fut.clone()));
+                    fut
+                }
+            }
+        };
+
+        // also clean up if the caller is cancelled
+        let _cleanup = Cleanup::<R::Result> {
+            in_flight: &self.in_flight,
+            key,
+            fut: fut.clone(),
+        };
+        fut.await
+    }
+}
+
+impl Deref for DedupClient {
+    type Target = LspClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+/// Removes in-flight request once a caller is done with it.
+///
+/// Callers that still wait for the response keep it alive, only new callers issue a new request.
+struct Cleanup<'a, T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    in_flight: &'a Mutex<InFlight>,
+    key: (&'static str, String),
+    fut: SharedResponse<T>,
+}
+
+impl<T> Drop for Cleanup<'_, T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().expect("not poisoned");
+        let same = in_flight
+            .get(&self.key)
+            .and_then(|fut| fut.downcast_ref::<SharedResponse<T>>())
+            .is_some_and(|fut| fut.ptr_eq(&self.fut));
+        // a newer request for the same key may have replaced ours
+        if same {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use clap::Parser;
+    use lsp_client::transport::io_transport;
+    use lsp_types::{
+        HoverParams, TextDocumentIdentifier, TextDocumentPositionParams, request::HoverRequest,
+    };
+    use serde_json::{Value, json};
+    use tokio::io::{BufReader, duplex};
+
+    use crate::lsp::transport::{read_message, write_messages};
+
+    use super::*;
+
+    /// Answers every request with `null` and counts them.
+    async fn fake_server(
+        input: impl tokio::io::AsyncRead + Unpin,
+        mut output: impl tokio::io::AsyncWrite + Unpin,
+        count: Arc<AtomicUsize>,
+    ) {
+        let mut input = BufReader::new(input);
+        while let Ok(buf) = read_message(&mut input).await {
+            let msg: Value = serde_json::from_slice(&buf).unwrap();
+            count.fetch_add(1, Ordering::SeqCst);
+
+            let resp = json!({"jsonrpc": "2.0", "id": msg["id"], "result": null});
+            write_messages(&mut output, &[resp]).await.unwrap();
+        }
+    }
+
+    fn hover(file: &str) -> HoverParams {
+        HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: format!("file:///ws/{file}").parse().unwrap(),
+                },
+                position: Default::default(),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_coalesce() {
+        let (client_out, server_in) = duplex(1024);
+        let (server_out, client_in) = duplex(1024);
+        let count = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(fake_server(server_in, server_out, Arc::clone(&count)));
+        let (sender, receiver) = io_transport(client_out, client_in);
+        let client = DedupClient::new(
+            Arc::new(LspClient::new(sender, receiver)),
+            PartialResults::default(),
+            RetryCLIConfig::parse_from([""]),
+        );
+
+        let (a1, a2, b) = tokio::join!(
+            client.send_request::<HoverRequest>(hover("a.rs")),
+            client.send_request::<HoverRequest>(hover("a.rs")),
+            client.send_request::<HoverRequest>(hover("b.rs")),
+        );
+        assert_eq!(a1.unwrap(), None);
+        assert_eq!(a2.unwrap(), None);
+        assert_eq!(b.unwrap(), None);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert!(client.in_flight.lock().unwrap().is_empty());
+
+        // finished requests are not cached
+        client
+            .send_request::<HoverRequest>(hover("a.rs"))
+            .await
+            .unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+
+        let metrics = client.metrics().snapshot();
+        assert_eq!(
+            metrics.keys().copied().collect::<Vec<_>>(),
+            ["textDocument/hover"]
+        );
+        assert_eq!(metrics["textDocument/hover"].count, 3);
+        assert_eq!(metrics["textDocument/hover"].errors, 0);
+    }
+}
@@ -0,0 +1,400 @@
+//! Recognize files of dependencies outside the workspace.
+//!
+//! Raw paths like `~/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f/serde-1.0.200/src/lib.rs` are noisy and
+//! differ between machines, the crate name and version are what matters. Tool results use virtual paths like
+//! `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs` instead, which tools accept as input again.
+//!
+//! Only files within known [source roots](SourceRoot) are recognized, see
+//! [`dependency_sources`](super::dependency_sources) for how they are found.
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use clap::ValueEnum;
+use rmcp::schemars;
+use serde::Serialize;
+
+/// Package a dependency file belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, schemars::JsonSchema)]
+pub(crate) struct Dependency {
+    /// Crate name.
+    #[serde(rename = "crate")]
+    pub(crate) name: String,
+
+    /// Crate version, unknown for the standard library.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<String>,
+
+    /// File path, relative to the crate root.
+    pub(crate) path: String,
+
+    /// Absolute path of the crate root.
+    #[serde(skip)]
+    pub(crate) root: PathBuf,
+
+    /// Where the crate comes from.
+    #[serde(skip)]
+    pub(crate) origin: Origin,
+}
+
+impl Dependency {
+    /// Virtual path of the crate root, e.g. `dep://serde@1.0.200` or `std://core`.
+    fn virtual_root(&self) -> String {
+        match (self.origin, &self.version) {
+            (Origin::Std, _) => format!("{STD_SCHEME}{}", self.name),
+            (_, Some(version)) => format!("{DEP_SCHEME}{}@{version}", self.name),
+            (_, None) => format!("{DEP_SCHEME}{}", self.name),
+        }
+    }
+}
+
+/// Kind of a [`SourceRoot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum Origin {
+    /// Crates of registries, e.g. `~/.cargo/registry/src` which contains `<index>/<name>-<version>/`.
+    Registry,
+
+    /// Git checkouts, e.g. `~/.cargo/git/checkouts` which contains `<name>-<hash>/<revision>/`.
+    Git,
+
+    /// Standard library, e.g. `<sysroot>/lib/rustlib/src/rust/library` which contains `<name>/`.
+    Std,
+
+    /// A single package, e.g. a path dependency outside the workspace.
+    Path,
+}
+
+/// Directory that contains sources of dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceRoot {
+    pub(crate) origin: Origin,
+
+    /// Absolute path.
+    pub(crate) path: PathBuf,
+
+    /// Name and version of a [`Origin::Path`] package, the directory name is used if unknown.
+    pub(crate) package: Option<(String, Option<String>)>,
+}
+
+impl SourceRoot {
+    pub(crate) fn new(origin: Origin, path: impl Into<PathBuf>) -> Self {
+        Self {
+            origin,
+            path: path.into(),
+            package: None,
+        }
+    }
+
+    /// Parse path of a dependency file within this directory.
+    ///
+    /// Returns `None` if the path is outside of this directory or does not match its layout.
+    fn parse(&self, path: &Path) -> Option<Dependency> {
+        let components = path
+            .strip_prefix(&self.path)
+            .ok()?
+            .components()
+            .map(|c| match c {
+                Component::Normal(s) => s.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let (name, version, root, rest) = match (self.origin, components.as_slice()) {
+            (Origin::Registry, [index, dir, rest @ ..]) => {
+                let (name, version) = split_name_version(dir)?;
+                let root = self.path.join(index).join(dir);
+                (name.to_owned(), Some(version.to_owned()), root, rest)
+            }
+            (Origin::Git, [dir, rev, rest @ ..]) => {
+                let (name, _hash) = dir.rsplit_once('-')?;
+                let root = self.path.join(dir).join(rev);
+                (name.to_owned(), Some((*rev).to_owned()), root, rest)
+            }
+            (Origin::Std, [name, rest @ ..]) => {
+                ((*name).to_owned(), None, self.path.join(name), rest)
+            }
+            (Origin::Path, rest) => {
+                let (name, version) = match &self.package {
+                    Some((name, version)) => (name.clone(), version.clone()),
+                    None => (self.path.file_name()?.to_str()?.to_owned(), None),
+                };
+                (name, version, self.path.clone(), rest)
+            }
+            _ => return None,
+        };
+
+        Some(Dependency {
+            name,
+            version,
+            path: join(rest)?,
+            root,
+            origin: self.origin,
+        })
+    }
+}
+
+/// Prefix of virtual paths of dependencies.
+const DEP_SCHEME: &str = "dep://";
+
+/// Prefix of virtual paths of the standard library.
+const STD_SCHEME: &str = "std://";
+
+/// Checks if the path is a virtual path of a dependency, no matter if the crate is known.
+pub(crate) fn is_virtual(path: &str) -> bool {
+    path.starts_with(DEP_SCHEME) || path.starts_with(STD_SCHEME)
+}
+
+/// Known source roots and the crate roots of the virtual paths that were handed out.
+///
+/// Shared by all clones. It is derived from the workspace and hence ignored when comparing workspaces.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DependencyRoots {
+    /// Crate roots, keyed by virtual root.
+    roots: Arc<Mutex<HashMap<String, PathBuf>>>,
+
+    /// See [`set_sources`](Self::set_sources).
+    sources: Arc<RwLock<Vec<SourceRoot>>>,
+}
+
+impl DependencyRoots {
+    /// Set directories that contain sources of dependencies.
+    ///
+    /// Files outside of these directories are not recognized as dependency files.
+    pub(crate) fn set_sources(&self, sources: Vec<SourceRoot>) {
+        *self.sources.write().expect("not poisoned") = sources;
+    }
+
+    /// Parse path of a dependency file.
+    ///
+    /// Returns `None` if the path does not belong to a known source root. Nested roots, e.g. a path dependency
+    /// within a git checkout, take precedence.
+    pub(crate) fn parse(&self, path: &Path) -> Option<Dependency> {
+        self.sources
+            .read()
+            .expect("not poisoned")
+            .iter()
+            .filter_map(|source| source.parse(path))
+            .max_by_key(|dep| dep.root.components().count())
+    }
+
+    /// Directories that contain sources of dependencies, see [`set_sources`](Self::set_sources).
+    pub(crate) fn source_paths(&self) -> Vec<PathBuf> {
+        self.sources
+            .read()
+            .expect("not poisoned")
+            .iter()
+            .map(|source| source.path.clone())
+            .collect()
+    }
+
+    /// Get virtual path of a dependency file and remember its crate root.
+    pub(crate) fn virtualize(&self, dependency: &Dependency) -> String {
+        let root = dependency.virtual_root();
+        let path = format!("{root}/{}", dependency.path);
+        self.roots
+            .lock()
+            .expect("not poisoned")
+            .insert(root, dependency.root.clone());
+        path
+    }
+
+    /// Get absolute path of a virtual path.
+    ///
+    /// Returns `None` for other paths and unknown crates.
+    pub(crate) fn resolve(&self, path: &str) -> Option<PathBuf> {
+        if !is_virtual(path) {
+            return None;
+        }
+        let (root, rest) = path
+            .match_indices('/')
+            .nth(2)
+            .map(|(pos, _)| (&path[..pos], &path[pos + 1..]))
+            .unwrap_or((path, ""));
+        let roots = self.roots.lock().expect("not poisoned");
+        let root = roots.get(root)?;
+        Some(rest.split('/').fold(root.clone(), |path, c| path.join(c)))
+    }
+}
+
+impl PartialEq for DependencyRoots {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DependencyRoots {}
+
+impl PartialOrd for DependencyRoots {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DependencyRoots {
+    fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+/// Split `<name>-<version>` directory name, e.g. `windows-sys-0.52.0`.
+fn split_name_version(dir: &str) -> Option<(&str, &str)> {
+    dir.match_indices('-').find_map(|(pos, _)| {
+        let version = &dir[pos + 1..];
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        // patch version may have a suffix, e.g. `1.0.0-rc.1`
+        let mut parts = version.splitn(3, '.');
+        let is_version = parts.next().is_some_and(is_number)
+            && parts.next().is_some_and(is_number)
+            && parts.next().is_some_and(|patch| {
+                is_number(
+                    patch
+                        .split(|c: char| !c.is_ascii_digit())
+                        .next()
+                        .unwrap_or_default(),
+                )
+            });
+        is_version.then(|| (&dir[..pos], version))
+    })
+}
+
+fn join(components: &[&str]) -> Option<String> {
+    (!components.is_empty()).then(|| components.join("/"))
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    fn roots() -> DependencyRoots {
+        let roots = DependencyRoots::default();
+        roots.set_sources(vec![
+            SourceRoot::new(Origin::Registry, "/cargo/registry/src"),
+            SourceRoot::new(Origin::Git, "/cargo/git/checkouts"),
+            SourceRoot::new(Origin::Std, "/rust/lib/rustlib/src/rust/library"),
+            SourceRoot {
+                origin: Origin::Path,
+                path: PathBuf::from("/libs/my-lib"),
+                package: Some(("my_lib".to_owned(), Some("0.1.0".to_owned()))),
+            },
+            SourceRoot::new(Origin::Path, "/cargo/git/checkouts/repo-1a2b/f00/vendored"),
+        ]);
+        roots
+    }
+
+    #[test]
+    fn test_parse() {
+        let roots = roots();
+        let parse = |path: &str| roots.parse(Path::new(path));
+
+        assert_eq!(
+            parse(
+                "/cargo/registry/src/index.crates.io-1949cf8c6b5b557f/windows-sys-0.52.0-rc.1/src/lib.rs"
+            ),
+            Some(Dependency {
+                name: "windows-sys".to_owned(),
+                version: Some("0.52.0-rc.1".to_owned()),
+                path: "src/lib.rs".to_owned(),
+                root: PathBuf::from(
+                    "/cargo/registry/src/index.crates.io-1949cf8c6b5b557f/windows-sys-0.52.0-rc.1"
+                ),
+                origin: Origin::Registry,
+            })
+        );
+        assert_eq!(
+            parse("/cargo/git/checkouts/my-repo-1a2b3c4d/f00ba12/src/lib.rs"),
+            Some(Dependency {
+                name: "my-repo".to_owned(),
+                version: Some("f00ba12".to_owned()),
+                path: "src/lib.rs".to_owned(),
+                root: PathBuf::from("/cargo/git/checkouts/my-repo-1a2b3c4d/f00ba12"),
+                origin: Origin::Git,
+            })
+        );
+        assert_eq!(
+            parse("/rust/lib/rustlib/src/rust/library/core/src/option.rs"),
+            Some(Dependency {
+                name: "core".to_owned(),
+                version: None,
+                path: "src/option.rs".to_owned(),
+                root: PathBuf::from("/rust/lib/rustlib/src/rust/library/core"),
+                origin: Origin::Std,
+            })
+        );
+        assert_eq!(
+            parse("/libs/my-lib/src/lib.rs"),
+            Some(Dependency {
+                name: "my_lib".to_owned(),
+                version: Some("0.1.0".to_owned()),
+                path: "src/lib.rs".to_owned(),
+                root: PathBuf::from("/libs/my-lib"),
+                origin: Origin::Path,
+            })
+        );
+        assert_eq!(
+            parse("/cargo/git/checkouts/repo-1a2b/f00/vendored/src/lib.rs").map(|dep| dep.name),
+            Some("vendored".to_owned())
+        );
+
+        // same layout, but not a known source root
+        assert_eq!(
+            parse("/home/me/.cargo/registry/src/index/serde-1.0.200/src/lib.rs"),
+            None
+        );
+        assert_eq!(parse("/fixtures/dependency_lib/src/lib.rs"), None);
+        assert_eq!(parse("/cargo/registry/src/index/serde/src/lib.rs"), None);
+        assert_eq!(parse("/cargo/registry/src/index/serde-1.0.200"), None);
+        assert_eq!(parse("/cargo/registry/src/../../etc/passwd"), None);
+        assert_eq!(
+            DependencyRoots::default().parse(Path::new("/libs/my-lib/src/lib.rs")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_virtual_paths() {
+        let roots = roots();
+        let parse = |path: &str| roots.parse(Path::new(path)).unwrap();
+        let dep = parse("/cargo/registry/src/index/serde-1.0.200/src/de/mod.rs");
+        let std = parse("/rust/lib/rustlib/src/rust/library/core/src/option.rs");
+        let unversioned = parse("/cargo/git/checkouts/repo-1a2b/f00/vendored/src/lib.rs");
+
+        assert_eq!(roots.resolve("dep://serde@1.0.200/src/de/mod.rs"), None);
+        assert_eq!(roots.virtualize(&dep), "dep://serde@1.0.200/src/de/mod.rs");
+        assert_eq!(roots.clone().virtualize(&std), "std://core/src/option.rs");
+        assert_eq!(roots.virtualize(&unversioned), "dep://vendored/src/lib.rs");
+
+        assert_eq!(
+            roots.resolve("dep://serde@1.0.200/src/de/mod.rs"),
+            Some(PathBuf::from(
+                "/cargo/registry/src/index/serde-1.0.200/src/de/mod.rs"
+            ))
+        );
+        assert_eq!(
+            roots.resolve("std://core/src/option.rs"),
+            Some(PathBuf::from(
+                "/rust/lib/rustlib/src/rust/library/core/src/option.rs"
+            ))
+        );
+        assert_eq!(
+            roots.resolve("dep://vendored/src/lib.rs"),
+            Some(PathBuf::from(
+                "/cargo/git/checkouts/repo-1a2b/f00/vendored/src/lib.rs"
+            ))
+        );
+        assert_eq!(roots.resolve("dep://serde@1.0.201/src/lib.rs"), None);
+        assert_eq!(roots.resolve("src/lib.rs"), None);
+    }
+
+    #[test]
+    fn test_split_name_version() {
+        assert_eq!(
+            split_name_version("serde-1.0.200"),
+            Some(("serde", "1.0.200"))
+        );
+        assert_eq!(split_name_version("x-2d-0.1.0"), Some(("x-2d", "0.1.0")));
+        assert_eq!(split_name_version("serde"), None);
+        assert_eq!(split_name_version("serde-1.0"), None);
+    }
+}
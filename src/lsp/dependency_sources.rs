@@ -0,0 +1,222 @@
+//! Find the directories that contain sources of dependencies.
+//!
+//! Files of dependencies are only shown if they are within one of these directories. By default, they are detected
+//! via the cargo home (registry crates and git checkouts), `rustc --print sysroot` (standard library), and
+//! `cargo metadata` (path dependencies outside the workspace).
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, ensure};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+use super::{
+    dependency::{Origin, SourceRoot},
+    workspace::normalize,
+};
+
+/// How long detection commands may run.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, clap::Parser)]
+pub(crate) struct DependencySourcesCLIConfig {
+    /// Directory that contains sources of dependencies, as `<kind>=<path>`.
+    ///
+    /// Kinds are `registry` (contains `<index>/<name>-<version>/`), `git` (contains `<name>-<hash>/<revision>/`),
+    /// `std` (contains `<name>/`), and `path` (a single package). Relative paths are resolved against the workspace
+    /// root. Disables the auto-detection via the cargo home, `rustc --print sysroot`, and `cargo metadata`. Files
+    /// outside the workspace and these directories are dropped from tool results.
+    ///
+    /// Can be repeated or comma-separated.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_DEPENDENCY_SOURCE_ROOT",
+        value_delimiter = ',',
+        value_parser = parse_source_root
+    )]
+    dependency_source_root: Vec<SourceRoot>,
+}
+
+fn parse_source_root(s: &str) -> Result<SourceRoot, String> {
+    let (origin, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `<kind>=<path>`, got `{s}`"))?;
+    let origin = Origin::from_str(origin, true)?;
+    Ok(SourceRoot::new(origin, path))
+}
+
+impl DependencySourcesCLIConfig {
+    /// Source roots for the workspace with the given root.
+    pub(crate) async fn detect(&self, workspace_root: &Path) -> Vec<SourceRoot> {
+        if !self.dependency_source_root.is_empty() {
+            return self
+                .dependency_source_root
+                .iter()
+                .map(|source| SourceRoot {
+                    path: canonicalize(&normalize(&workspace_root.join(&source.path))),
+                    ..source.clone()
+                })
+                .collect();
+        }
+
+        let mut sources = vec![];
+        match cargo_home() {
+            Some(home) => {
+                sources.push(SourceRoot::new(Origin::Registry, home.join("registry/src")));
+                sources.push(SourceRoot::new(Origin::Git, home.join("git/checkouts")));
+            }
+            None => debug!("cannot determine cargo home"),
+        }
+        match rustc_sysroot(workspace_root).await {
+            Ok(sysroot) => sources.push(SourceRoot::new(
+                Origin::Std,
+                sysroot.join("lib/rustlib/src/rust/library"),
+            )),
+            Err(e) => debug!(%e, "cannot determine rust sysroot"),
+        }
+        match path_packages(workspace_root).await {
+            Ok(packages) => sources.extend(packages),
+            Err(e) => debug!(%e, "cannot list path dependencies via cargo metadata"),
+        }
+
+        let sources = sources
+            .into_iter()
+            .filter(|source| source.path.is_dir())
+            .map(|source| SourceRoot {
+                path: canonicalize(&source.path),
+                ..source
+            })
+            .collect::<Vec<_>>();
+        info!(sources = sources.len(), "detected dependency source roots");
+        sources
+    }
+}
+
+/// Canonicalize path if it exists, because language servers report canonical paths.
+fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::home_dir().map(|home| home.join(".cargo")))
+}
+
+async fn rustc_sysroot(workspace_root: &Path) -> Result<PathBuf> {
+    let stdout = run(
+        Command::new("rustc").args(["--print", "sysroot"]),
+        workspace_root,
+    )
+    .await?;
+    Ok(PathBuf::from(stdout.trim()))
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    version: String,
+
+    /// `None` for path dependencies and workspace members.
+    source: Option<String>,
+
+    manifest_path: PathBuf,
+}
+
+/// Path dependencies outside the workspace.
+async fn path_packages(workspace_root: &Path) -> Result<Vec<SourceRoot>> {
+    // `--frozen` neither touches the network nor the lock file
+    let stdout = run(
+        Command::new("cargo").args(["metadata", "--format-version=1", "--frozen"]),
+        workspace_root,
+    )
+    .await?;
+    let metadata =
+        serde_json::from_str::<Metadata>(&stdout).context("parse cargo metadata output")?;
+
+    Ok(metadata
+        .packages
+        .into_iter()
+        .filter(|package| package.source.is_none())
+        .filter_map(|package| {
+            let dir = package.manifest_path.parent()?;
+            (!dir.starts_with(workspace_root)).then(|| SourceRoot {
+                origin: Origin::Path,
+                path: dir.to_owned(),
+                package: Some((package.name, Some(package.version))),
+            })
+        })
+        .collect())
+}
+
+/// Run command and return its stdout.
+async fn run(command: &mut Command, cwd: &Path) -> Result<String> {
+    let output = tokio::time::timeout(
+        DETECT_TIMEOUT,
+        command
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .context("timeout")?
+    .context("run command")?;
+    ensure!(
+        output.status.success(),
+        "command failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    String::from_utf8(output.stdout).context("output is not UTF-8")
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_source_root() {
+        assert_eq!(
+            parse_source_root("registry=/cargo/registry/src"),
+            Ok(SourceRoot::new(Origin::Registry, "/cargo/registry/src"))
+        );
+        assert_eq!(
+            parse_source_root("Path=../lib"),
+            Ok(SourceRoot::new(Origin::Path, "../lib"))
+        );
+        parse_source_root("/cargo/registry/src").unwrap_err();
+        parse_source_root("vendor=/vendor").unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("lib")).unwrap();
+
+        let cfg = DependencySourcesCLIConfig {
+            dependency_source_root: vec![
+                SourceRoot::new(Origin::Path, "../lib/../lib"),
+                SourceRoot::new(Origin::Registry, "/registry"),
+            ],
+        };
+        let ws = root.join("ws");
+        assert_eq!(
+            cfg.detect(&ws).await,
+            vec![
+                SourceRoot::new(Origin::Path, root.join("lib")),
+                SourceRoot::new(Origin::Registry, "/registry"),
+            ]
+        );
+    }
+}
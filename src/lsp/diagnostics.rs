@@ -0,0 +1,194 @@
+//! Collect diagnostics that the language server publishes via `textDocument/publishDiagnostics`.
+//!
+//! Language servers push diagnostics whenever they are (re-)computed, e.g. after `cargo check` finished or for
+//! documents that we opened. Only the latest diagnostics per document are kept.
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use lsp_client::LspClient;
+use lsp_types::{Diagnostic, PublishDiagnosticsParams, Uri, notification::PublishDiagnostics};
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::{TaskManager, tasks::RestartPolicy};
+
+/// Diagnostics are only used on demand, so a broken subscription is re-established instead of stopping the server.
+const RESTART_POLICY: RestartPolicy = RestartPolicy::OnFailure {
+    backoff: Duration::from_secs(1),
+    max_restarts: 5,
+};
+
+/// Diagnostics keyed by URI string, which sorts the documents.
+type Documents = BTreeMap<String, (Uri, Vec<Diagnostic>)>;
+
+/// Latest diagnostics of a language server, per document.
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostics {
+    /// Documents without diagnostics are not stored.
+    documents: Arc<Mutex<Documents>>,
+
+    /// Number of received notifications.
+    received: watch::Sender<u64>,
+}
+
+impl Diagnostics {
+    /// Start listening for diagnostics.
+    pub(crate) fn start(tasks: &mut TaskManager, name: &str, client: Arc<LspClient>) -> Self {
+        let this = Self {
+            documents: Default::default(),
+            received: watch::Sender::new(0),
+        };
+
+        let this_captured = this.clone();
+        let name = name.to_owned();
+        tasks.spawn_supervised(
+            move |cancel| {
+                let this = this_captured.clone();
+                let client = Arc::clone(&client);
+                let name = name.clone();
+                async move {
+                    let mut subscription = client
+                        .subscribe_to_method::<PublishDiagnostics>()
+                        .await
+                        .context("subscribe to 'publishDiagnostics'")?;
+
+                    loop {
+                        let params = tokio::select! {
+                            next = subscription.next() => match next {
+                                Some(res) => res.context("receive diagnostics")?,
+                                None => break,
+                            },
+                            _ = cancel.cancelled() => break,
+                        };
+                        debug!(
+                            lang = name.as_str(),
+                            uri = params.uri.as_str(),
+                            n = params.diagnostics.len(),
+                            "diagnostics",
+                        );
+                        this.publish(params);
+                    }
+
+                    subscription
+                        .unsubscribe()
+                        .await
+                        .context("unsubscribe diagnostics")?;
+
+                    Ok(())
+                }
+            },
+            "diagnostics",
+            RESTART_POLICY,
+        );
+
+        this
+    }
+
+    fn publish(&self, params: PublishDiagnosticsParams) {
+        let PublishDiagnosticsParams {
+            uri, diagnostics, ..
+        } = params;
+        {
+            let mut documents = self.documents.lock().expect("not poisoned");
+            if diagnostics.is_empty() {
+                documents.remove(uri.as_str());
+            } else {
+                documents.insert(uri.as_str().to_owned(), (uri, diagnostics));
+            }
+        }
+        self.received.send_modify(|n| *n += 1);
+    }
+
+    /// Current diagnostics, sorted by document.
+    pub(crate) fn snapshot(&self) -> Vec<(Uri, Vec<Diagnostic>)> {
+        self.documents
+            .lock()
+            .expect("not poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Wait until no diagnostics were published for the given duration.
+    pub(crate) async fn settle(&self, quiet: Duration) {
+        let mut rx = self.received.subscribe();
+        while let Ok(Ok(())) = tokio::time::timeout(quiet, rx.changed()).await {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{DiagnosticSeverity, Range};
+
+    use super::*;
+
+    fn diagnostic(message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::default(),
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: message.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn messages(diagnostics: &Diagnostics) -> Vec<(String, Vec<String>)> {
+        diagnostics
+            .snapshot()
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                (
+                    uri.as_str().to_owned(),
+                    diagnostics.into_iter().map(|d| d.message).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let diagnostics = Diagnostics {
+            documents: Default::default(),
+            received: watch::Sender::new(0),
+        };
+        let publish = |uri: &str, messages: &[&str]| {
+            diagnostics.publish(PublishDiagnosticsParams {
+                uri: uri.parse().unwrap(),
+                diagnostics: messages.iter().map(|m| diagnostic(m)).collect(),
+                version: None,
+            })
+        };
+
+        publish("file:///b.rs", &["b1"]);
+        publish("file:///a.rs", &["a1", "a2"]);
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (
+                    "file:///a.rs".to_owned(),
+                    vec!["a1".to_owned(), "a2".to_owned()]
+                ),
+                ("file:///b.rs".to_owned(), vec!["b1".to_owned()]),
+            ]
+        );
+
+        // replaced and cleared
+        publish("file:///a.rs", &["a3"]);
+        publish("file:///b.rs", &[]);
+        assert_eq!(
+            messages(&diagnostics),
+            [("file:///a.rs".to_owned(), vec!["a3".to_owned()])]
+        );
+
+        // nothing published in the meantime
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            diagnostics.settle(Duration::from_millis(10)),
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! Results of per-document requests for files that are queried often.
+//!
+//! Warm-up fetches the document symbols and semantic tokens of core files once the language server is ready, so the
+//! first tool calls for these files do not have to wait for the language server. Only pinned documents are cached,
+//! all others are fetched for every tool call. Results are reused as long as neither the document nor the workspace
+//! changed.
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use lsp_types::{SemanticToken, SymbolInformation, Uri};
+
+use super::{
+    cache::{CacheRegistry, CacheStats, Lru, symbols_size},
+    documents::Fingerprint,
+    symbol_cache::Revision,
+};
+
+/// State that the results of a document depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DocumentState {
+    /// Content of the document.
+    pub(crate) fingerprint: Fingerprint,
+
+    /// Rest of the workspace, which e.g. determines the semantic tokens of imported items.
+    pub(crate) revision: Revision,
+}
+
+/// Results per document, keyed by URI.
+type Slots<T> = Arc<Lru<Uri, (DocumentState, T)>>;
+
+/// Cached results of pinned documents of a single language server.
+#[derive(Debug)]
+pub(crate) struct DocumentCache {
+    pinned: Mutex<HashSet<Uri>>,
+
+    /// Document symbols, including variables that are derived from the semantic tokens.
+    symbols: Slots<Vec<SymbolInformation>>,
+
+    semantic_tokens: Slots<Arc<[SemanticToken]>>,
+}
+
+impl DocumentCache {
+    pub(crate) fn new(registry: &CacheRegistry) -> Self {
+        Self {
+            pinned: Default::default(),
+            symbols: registry.lru(),
+            semantic_tokens: registry.lru(),
+        }
+    }
+
+    /// Cache results of the document from now on.
+    pub(crate) fn pin(&self, uri: Uri) {
+        self.pinned.lock().expect("not poisoned").insert(uri);
+    }
+
+    pub(crate) fn symbols(
+        &self,
+        uri: &Uri,
+        state: DocumentState,
+    ) -> Option<Vec<SymbolInformation>> {
+        self.get(&self.symbols, uri, state)
+    }
+
+    /// Remember symbols if the document is pinned, `state` is the one from before the request started.
+    pub(crate) fn store_symbols(
+        &self,
+        uri: &Uri,
+        state: DocumentState,
+        symbols: &[SymbolInformation],
+    ) {
+        if self.is_pinned(uri) {
+            self.symbols.insert(
+                uri.clone(),
+                (state, symbols.to_vec()),
+                symbols_size(symbols),
+            );
+        }
+    }
+
+    pub(crate) fn semantic_tokens(
+        &self,
+        uri: &Uri,
+        state: DocumentState,
+    ) -> Option<Arc<[SemanticToken]>> {
+        self.get(&self.semantic_tokens, uri, state)
+    }
+
+    /// Remember semantic tokens if the document is pinned, `state` is the one from before the request started.
+    pub(crate) fn store_semantic_tokens(
+        &self,
+        uri: &Uri,
+        state: DocumentState,
+        data: &Arc<[SemanticToken]>,
+    ) {
+        if self.is_pinned(uri) {
+            self.semantic_tokens.insert(
+                uri.clone(),
+                (state, Arc::clone(data)),
+                size_of_val(&**data),
+            );
+        }
+    }
+
+    /// Symbols and semantic tokens combined.
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.symbols.stats() + self.semantic_tokens.stats()
+    }
+
+    fn is_pinned(&self, uri: &Uri) -> bool {
+        self.pinned.lock().expect("not poisoned").contains(uri)
+    }
+
+    /// Only pinned documents are ever stored, so lookups of other documents are not counted as misses.
+    fn get<T: Clone + std::fmt::Debug + Send + 'static>(
+        &self,
+        slots: &Slots<T>,
+        uri: &Uri,
+        state: DocumentState,
+    ) -> Option<T> {
+        if !self.is_pinned(uri) {
+            return None;
+        }
+        slots
+            .get(uri, |(cached_state, _value)| *cached_state == state)
+            .map(|(_state, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use crate::lsp::cache::CacheCLIConfig;
+
+    use super::*;
+
+    fn state(content: &str, files: u64) -> DocumentState {
+        DocumentState {
+            fingerprint: Fingerprint::of(content),
+            revision: Revision { files, work: 0 },
+        }
+    }
+
+    fn tokens(n: u32) -> Arc<[SemanticToken]> {
+        (0..n)
+            .map(|delta_start| SemanticToken {
+                delta_line: 0,
+                delta_start,
+                length: 1,
+                token_type: 0,
+                token_modifiers_bitset: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pinned() {
+        let registry = CacheCLIConfig::parse_from([""]).registry();
+        let cache = DocumentCache::new(&registry);
+        let pinned = "file:///ws/src/lib.rs".parse::<Uri>().unwrap();
+        let other = "file:///ws/src/main.rs".parse::<Uri>().unwrap();
+        cache.pin(pinned.clone());
+
+        cache.store_semantic_tokens(&pinned, state("a", 1), &tokens(2));
+        cache.store_semantic_tokens(&other, state("a", 1), &tokens(2));
+        assert_eq!(
+            cache.semantic_tokens(&pinned, state("a", 1)),
+            Some(tokens(2))
+        );
+        assert_eq!(cache.semantic_tokens(&other, state("a", 1)), None);
+        assert_eq!(cache.symbols(&pinned, state("a", 1)), None);
+
+        // changed document or workspace
+        assert_eq!(cache.semantic_tokens(&pinned, state("b", 1)), None);
+        assert_eq!(cache.semantic_tokens(&pinned, state("a", 2)), None);
+
+        // refreshed by the next request
+        cache.store_semantic_tokens(&pinned, state("b", 2), &tokens(3));
+        assert_eq!(
+            cache.semantic_tokens(&pinned, state("b", 2)),
+            Some(tokens(3))
+        );
+    }
+}
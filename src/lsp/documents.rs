@@ -0,0 +1,288 @@
+//! Keep files that are actively queried open in the language server.
+//!
+//! Many language servers return fresher and more complete results (e.g. hover) for open documents than for files
+//! that they only know from disk. Documents are opened when a tool call reads them and closed after they were not used
+//! for a while. Open documents are synced with the content on disk, since the language server ignores the disk
+//! content for them.
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use lsp_types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Uri,
+    VersionedTextDocumentIdentifier,
+    notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument},
+};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{debug, warn};
+
+use super::dedup::DedupClient;
+
+/// Documents that were not used for this long are closed.
+const IDLE_TTL: Duration = Duration::from_secs(60);
+
+/// Open documents of a single language server.
+#[derive(Debug)]
+pub(crate) struct Documents {
+    /// Language ID that is reported to the language server.
+    language_id: String,
+
+    client: DedupClient,
+
+    /// Open documents, keyed by URI.
+    ///
+    /// This is an async lock, so notifications for the same document are sent in order.
+    open: Arc<Mutex<HashMap<Uri, OpenDocument>>>,
+
+    /// Closes idle documents, started with the first document.
+    closer: OnceLock<DropGuard>,
+}
+
+/// Document that is open in the language server.
+#[derive(Debug)]
+struct OpenDocument {
+    version: i32,
+
+    /// Content that the language server knows.
+    fingerprint: Fingerprint,
+
+    last_used: Instant,
+}
+
+impl Documents {
+    pub(crate) fn new(language_id: String, client: DedupClient) -> Self {
+        Self {
+            language_id,
+            client,
+            open: Default::default(),
+            closer: OnceLock::new(),
+        }
+    }
+
+    /// Open document with the given content, or sync it if it is already open.
+    pub(crate) async fn open(&self, uri: &Uri, text: &str) -> Result<()> {
+        let fingerprint = Fingerprint::of(text);
+
+        let mut open = self.open.lock().await;
+        match open.get_mut(uri) {
+            Some(doc) => {
+                doc.last_used = Instant::now();
+                self.change(uri, doc, text).await?;
+            }
+            None => {
+                debug!(uri = uri.as_str(), "open document");
+                self.client
+                    .send_notification::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                        text_document: TextDocumentItem {
+                            uri: uri.clone(),
+                            language_id: self.language_id.clone(),
+                            version: 0,
+                            text: text.to_owned(),
+                        },
+                    })
+                    .await
+                    .context("DidOpenTextDocument")?;
+                open.insert(
+                    uri.clone(),
+                    OpenDocument {
+                        version: 0,
+                        fingerprint,
+                        last_used: Instant::now(),
+                    },
+                );
+            }
+        }
+        drop(open);
+
+        self.closer.get_or_init(|| {
+            let ct = CancellationToken::new();
+            tokio::spawn(close_idle_loop(
+                Arc::clone(&self.open),
+                self.client.clone(),
+                ct.clone(),
+            ));
+            ct.drop_guard()
+        });
+
+        Ok(())
+    }
+
+    /// Sync document with the given content if it is open, closed documents stay closed.
+    ///
+    /// The language server ignores changes on disk for open documents.
+    pub(crate) async fn sync(&self, uri: &Uri, text: &str) -> Result<()> {
+        let mut open = self.open.lock().await;
+        if let Some(doc) = open.get_mut(uri) {
+            self.change(uri, doc, text).await?;
+        }
+        Ok(())
+    }
+
+    /// Send the new content of an open document, if it changed.
+    async fn change(&self, uri: &Uri, doc: &mut OpenDocument, text: &str) -> Result<()> {
+        let fingerprint = Fingerprint::of(text);
+        if doc.fingerprint == fingerprint {
+            return Ok(());
+        }
+
+        debug!(uri = uri.as_str(), "sync document");
+        doc.version += 1;
+        doc.fingerprint = fingerprint;
+        self.client
+            .send_notification::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier {
+                    uri: uri.clone(),
+                    version: doc.version,
+                },
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: text.to_owned(),
+                }],
+            })
+            .await
+            .context("DidChangeTextDocument")
+    }
+}
+
+/// Hash of a file content, used to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Fingerprint(u64);
+
+impl Fingerprint {
+    pub(crate) fn of(text: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// Periodically close idle documents.
+async fn close_idle_loop(
+    open: Arc<Mutex<HashMap<Uri, OpenDocument>>>,
+    client: DedupClient,
+    ct: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(IDLE_TTL / 2);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ct.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        if let Err(e) = close_idle(&open, &client, IDLE_TTL).await {
+            warn!(%e, "cannot close idle documents");
+        }
+    }
+}
+
+/// Close documents that were not used within the given TTL.
+async fn close_idle(
+    open: &Mutex<HashMap<Uri, OpenDocument>>,
+    client: &DedupClient,
+    ttl: Duration,
+) -> Result<()> {
+    let mut open = open.lock().await;
+    let idle = open
+        .iter()
+        .filter(|(_uri, doc)| doc.last_used.elapsed() >= ttl)
+        .map(|(uri, _doc)| uri.clone())
+        .collect::<Vec<_>>();
+
+    for uri in idle {
+        debug!(uri = uri.as_str(), "close idle document");
+        open.remove(&uri);
+        client
+            .send_notification::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier { uri },
+            })
+            .await
+            .context("DidCloseTextDocument")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use lsp_client::{LspClient, transport::io_transport};
+    use serde_json::Value;
+    use tokio::{
+        io::{BufReader, duplex},
+        sync::mpsc,
+    };
+
+    use crate::lsp::{partial::PartialResults, retry::RetryCLIConfig, transport::read_message};
+
+    use super::*;
+
+    /// Forwards method and version of all notifications.
+    async fn fake_server(
+        input: impl tokio::io::AsyncRead + Unpin,
+        tx: mpsc::UnboundedSender<(String, Value)>,
+    ) {
+        let mut input = BufReader::new(input);
+        while let Ok(buf) = read_message(&mut input).await {
+            let msg: Value = serde_json::from_slice(&buf).unwrap();
+            tx.send((
+                msg["method"].as_str().unwrap().to_owned(),
+                msg["params"]["textDocument"]["version"].clone(),
+            ))
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_sync_close() {
+        let (client_out, server_in) = duplex(1024);
+        let (_server_out, client_in) = duplex(1024);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::spawn(fake_server(server_in, tx));
+        let (sender, receiver) = io_transport(client_out, client_in);
+        let client = DedupClient::new(
+            Arc::new(LspClient::new(sender, receiver)),
+            PartialResults::default(),
+            RetryCLIConfig::parse_from([""]),
+        );
+        let documents = Documents::new("rust".to_owned(), client.clone());
+        let uri = "file:///ws/lib.rs".parse::<Uri>().unwrap();
+
+        // not open yet
+        documents.sync(&uri, "fn foo() {}").await.unwrap();
+        documents.open(&uri, "fn foo() {}").await.unwrap();
+        documents.open(&uri, "fn foo() {}").await.unwrap();
+        documents.open(&uri, "fn bar() {}").await.unwrap();
+        documents.sync(&uri, "fn bar() {}").await.unwrap();
+        documents.sync(&uri, "fn baz() {}").await.unwrap();
+        close_idle(&documents.open, &client, IDLE_TTL)
+            .await
+            .unwrap();
+        close_idle(&documents.open, &client, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(documents.open.lock().await.is_empty());
+
+        let mut notifications = vec![];
+        for _ in 0..4 {
+            notifications.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            notifications,
+            vec![
+                ("textDocument/didOpen".to_owned(), Value::from(0)),
+                ("textDocument/didChange".to_owned(), Value::from(1)),
+                ("textDocument/didChange".to_owned(), Value::from(2)),
+                ("textDocument/didClose".to_owned(), Value::Null),
+            ],
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Watch workspace files and report changes to the language servers.
+//!
+//! Files that are edited between tool calls (by the agent or the user) would otherwise leave the language servers and
+//! our caches with stale state. Files are listed via `git ls-files`, so ignored files like build output are skipped,
+//! and polled for changes. Outside of git repositories, the folder is walked: `.gitignore` and `.ignore` files are
+//! still honoured, and hidden files as well as cache directories (tagged via `CACHEDIR.TAG`, e.g. cargo's `target`) are
+//! skipped.
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, ensure};
+use ignore::WalkBuilder;
+use lsp_types::{
+    DidChangeWatchedFilesParams, FileChangeType, FileEvent, notification::DidChangeWatchedFiles,
+};
+use tokio::time::MissedTickBehavior;
+use tokio_util::task::AbortOnDropHandle;
+use tracing::{debug, info, warn};
+
+use crate::cli::parse_secs;
+
+use super::{server::LanguageServer, uri::path_to_file_uri, workspace::SharedWorkspace};
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct FileWatcherCLIConfig {
+    /// Interval (in seconds) in which workspace files are checked for changes.
+    ///
+    /// Changes are reported to the language servers. Use `0` to disable the file watcher.
+    #[clap(
+        long,
+        default_value = "2",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_FILE_WATCH_INTERVAL"
+    )]
+    file_watch_interval: Duration,
+}
+
+impl FileWatcherCLIConfig {
+    /// Start watching the workspace, stops when the returned handle is dropped.
+    ///
+    /// Returns `None` if the file watcher is disabled.
+    pub(crate) fn start(
+        &self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+    ) -> Option<AbortOnDropHandle<()>> {
+        if self.file_watch_interval.is_zero() {
+            info!("file watcher disabled");
+            return None;
+        }
+
+        Some(AbortOnDropHandle::new(tokio::spawn(watch(
+            servers,
+            workspace,
+            self.file_watch_interval,
+        ))))
+    }
+}
+
+/// File metadata used to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl From<Metadata> for FileState {
+    fn from(metadata: Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+}
+
+/// State of all watched files within a workspace folder.
+type Snapshot = HashMap<PathBuf, FileState>;
+
+/// Poll workspace files and notify language servers about changes.
+async fn watch(servers: Arc<[LanguageServer]>, workspace: SharedWorkspace, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // keyed by workspace folder
+    let mut snapshots = HashMap::<PathBuf, Snapshot>::new();
+
+    loop {
+        interval.tick().await;
+
+        let folders = workspace
+            .get()
+            .folders()
+            .map(Path::to_owned)
+            .collect::<Vec<_>>();
+        // removed folders are announced to the language servers separately
+        snapshots.retain(|folder, _| folders.contains(folder));
+
+        let mut changes = vec![];
+        for folder in folders {
+            let res = tokio::task::spawn_blocking({
+                let folder = folder.clone();
+                move || scan(&folder)
+            })
+            .await
+            .context("join scan");
+            let snapshot = match res.and_then(|res| res) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    warn!(folder=%folder.display(), %e, "cannot scan workspace folder");
+                    continue;
+                }
+            };
+
+            // new folders are indexed by the language servers anyways
+            if let Some(previous) = snapshots.get(&folder) {
+                changes.extend(diff(previous, &snapshot));
+            }
+            snapshots.insert(folder, snapshot);
+        }
+        if changes.is_empty() {
+            continue;
+        }
+
+        debug!(n = changes.len(), "files changed");
+        workspace.files_changed();
+
+        let changes = changes
+            .into_iter()
+            .filter_map(|(path, typ)| match path_to_file_uri(&path) {
+                Ok(uri) => Some(FileEvent { uri, typ }),
+                Err(e) => {
+                    debug!(path=%path.display(), %e, "cannot convert path to URI");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        for server in servers.iter() {
+            if let Err(e) = server
+                .progress_guard()
+                .client()
+                .send_notification::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+                    changes: changes.clone(),
+                })
+                .await
+            {
+                warn!(lang = server.name(), %e, "cannot notify language server about changed files");
+            }
+        }
+    }
+}
+
+/// Changes between two snapshots of the same folder, sorted by path.
+fn diff(previous: &Snapshot, current: &Snapshot) -> Vec<(PathBuf, FileChangeType)> {
+    let mut changes = current
+        .iter()
+        .filter_map(|(path, state)| match previous.get(path) {
+            None => Some((path.clone(), FileChangeType::CREATED)),
+            Some(previous) if previous != state => Some((path.clone(), FileChangeType::CHANGED)),
+            Some(_) => None,
+        })
+        .chain(
+            previous
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .map(|path| (path.clone(), FileChangeType::DELETED)),
+        )
+        .collect::<Vec<_>>();
+    changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    changes
+}
+
+/// Get state of all watched files within the folder.
+fn scan(folder: &Path) -> Result<Snapshot> {
+    Ok(list_files(folder)?
+        .into_iter()
+        .filter_map(|path| {
+            // tracked files may be deleted from the working tree
+            let metadata = std::fs::metadata(&path).ok()?;
+            metadata.is_file().then(|| (path, metadata.into()))
+        })
+        .collect())
+}
+
+/// Files within the folder that are watched, see module docs.
+///
+/// This blocks, so use it via [`spawn_blocking`](tokio::task::spawn_blocking) in async contexts.
+pub(crate) fn list_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    match git_files(folder) {
+        Ok(files) => Ok(files),
+        Err(e) => {
+            debug!(folder=%folder.display(), %e, "cannot list files via git, walk folder");
+            walk(folder).context("walk folder")
+        }
+    }
+}
+
+/// Tracked and untracked files that are not ignored by git.
+fn git_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .current_dir(folder)
+        .output()
+        .context("run git")?;
+    ensure!(
+        output.status.success(),
+        "git failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let path = std::str::from_utf8(path).context("path is not UTF-8")?;
+            Ok(folder.join(path))
+        })
+        .collect()
+}
+
+/// Files within the folder that are not ignored, see module docs.
+fn walk(folder: &Path) -> Result<Vec<PathBuf>> {
+    WalkBuilder::new(folder)
+        .require_git(false)
+        .filter_entry(|entry| {
+            !entry.file_type().is_some_and(|t| t.is_dir())
+                || !entry.path().join("CACHEDIR.TAG").exists()
+        })
+        .build()
+        .filter_map(|entry| match entry {
+            Ok(entry) => entry
+                .file_type()
+                .is_some_and(|t| t.is_file())
+                .then(|| Ok(entry.into_path())),
+            Err(e) => Some(Err(e).context("read directory entry")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(len: u64) -> FileState {
+        FileState {
+            modified: None,
+            len,
+        }
+    }
+
+    fn snapshot(files: &[(&str, u64)]) -> Snapshot {
+        files
+            .iter()
+            .map(|(path, len)| (PathBuf::from(path), state(*len)))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = snapshot(&[("/ws/a.rs", 1), ("/ws/b.rs", 1), ("/ws/c.rs", 1)]);
+        let current = snapshot(&[("/ws/a.rs", 1), ("/ws/b.rs", 2), ("/ws/d.rs", 1)]);
+        assert_eq!(diff(&previous, &previous), vec![]);
+        assert_eq!(
+            diff(&previous, &current),
+            vec![
+                (PathBuf::from("/ws/b.rs"), FileChangeType::CHANGED),
+                (PathBuf::from("/ws/c.rs"), FileChangeType::DELETED),
+                (PathBuf::from("/ws/d.rs"), FileChangeType::CREATED),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".hidden")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        std::fs::create_dir_all(dir.path().join("out")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn foo() {}").unwrap();
+        std::fs::write(dir.path().join("src/gen.rs"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden/x"), "").unwrap();
+        std::fs::write(dir.path().join("target/CACHEDIR.TAG"), "").unwrap();
+        std::fs::write(dir.path().join("target/debug/x"), "").unwrap();
+        std::fs::write(dir.path().join("out/x"), "").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "/out\n").unwrap();
+        std::fs::write(dir.path().join(".ignore"), "gen.rs\n").unwrap();
+
+        let mut files = scan(dir.path()).unwrap().into_keys().collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec![dir.path().join("src/lib.rs")]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Cache of hover results.
+//!
+//! `symbol_info` requests hover information for every matching token, which is the hottest path of most sessions. The
+//! results only change if the workspace changes, so they are reused until the file watcher or the language server
+//! reports a change. The hover contents count towards the [shared budget](super::cache).
+use std::sync::{Arc, Mutex};
+
+use lsp_types::{Hover, HoverContents, MarkedString, Position, Uri};
+
+use super::{
+    cache::{CacheRegistry, CacheStats, Lru},
+    document_cache::DocumentState,
+    documents::Fingerprint,
+    symbol_cache::Revision,
+};
+
+/// Document, its content, and the position within it.
+type Key = (Uri, Fingerprint, Position);
+
+/// Hover results of a single language server.
+#[derive(Debug)]
+pub(crate) struct HoverCache {
+    /// Revision of the workspace that all entries belong to.
+    revision: Mutex<Option<Revision>>,
+
+    entries: Arc<Lru<Key, Hover>>,
+}
+
+impl HoverCache {
+    pub(crate) fn new(registry: &CacheRegistry) -> Self {
+        Self {
+            revision: Default::default(),
+            entries: registry.lru(),
+        }
+    }
+
+    /// Cached hover at the given position of the document, `None` if there is none.
+    pub(crate) fn get(&self, uri: &Uri, position: Position, state: DocumentState) -> Option<Hover> {
+        {
+            // forget all entries if the workspace changed
+            let mut revision = self.revision.lock().expect("not poisoned");
+            if *revision != Some(state.revision) {
+                *revision = Some(state.revision);
+                self.entries.clear();
+            }
+        }
+        self.entries
+            .get(&(uri.clone(), state.fingerprint, position), |_hover| true)
+    }
+
+    /// Remember hover, `state` is the one from before the request started.
+    pub(crate) fn insert(&self, uri: &Uri, position: Position, state: DocumentState, hover: Hover) {
+        let revision = self.revision.lock().expect("not poisoned");
+        if *revision != Some(state.revision) {
+            // workspace changed during the request
+            return;
+        }
+        let size = hover_size(&hover);
+        self.entries
+            .insert((uri.clone(), state.fingerprint, position), hover, size);
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.entries.stats()
+    }
+}
+
+/// Approximate memory footprint of the hover contents in bytes.
+fn hover_size(hover: &Hover) -> usize {
+    let marked_string_size = |marked_string: &MarkedString| match marked_string {
+        MarkedString::String(s) => s.len(),
+        MarkedString::LanguageString(s) => s.language.len() + s.value.len(),
+    };
+    match &hover.contents {
+        HoverContents::Scalar(marked_string) => marked_string_size(marked_string),
+        HoverContents::Array(marked_strings) => marked_strings.iter().map(marked_string_size).sum(),
+        HoverContents::Markup(markup) => markup.value.len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use crate::lsp::cache::CacheCLIConfig;
+
+    use super::*;
+
+    fn hover(s: &str) -> Hover {
+        Hover {
+            contents: HoverContents::Scalar(MarkedString::String(s.to_owned())),
+            range: None,
+        }
+    }
+
+    fn state(content: &str, files: u64) -> DocumentState {
+        DocumentState {
+            fingerprint: Fingerprint::of(content),
+            revision: Revision { files, work: 0 },
+        }
+    }
+
+    fn pos(character: u32) -> Position {
+        Position { line: 0, character }
+    }
+
+    #[test]
+    fn test_cache() {
+        let registry = CacheCLIConfig::parse_from([""]).registry();
+        let cache = HoverCache::new(&registry);
+        let uri = "file:///ws/src/lib.rs".parse::<Uri>().unwrap();
+        assert_eq!(cache.get(&uri, pos(1), state("a", 1)), None);
+
+        cache.insert(&uri, pos(1), state("a", 1), hover("foo"));
+        assert_eq!(cache.get(&uri, pos(1), state("a", 1)), Some(hover("foo")));
+        assert_eq!(cache.get(&uri, pos(2), state("a", 1)), None);
+        assert_eq!(cache.get(&uri, pos(1), state("b", 1)), None);
+
+        // changed workspace clears the cache, also for older revisions
+        assert_eq!(cache.get(&uri, pos(1), state("a", 2)), None);
+        assert_eq!(cache.get(&uri, pos(1), state("a", 1)), None);
+
+        // results of requests that started before a change are dropped
+        cache.insert(&uri, pos(1), state("a", 2), hover("foo"));
+        assert_eq!(cache.get(&uri, pos(1), state("a", 1)), None);
+
+        let stats = serde_json::to_value(cache.stats()).unwrap();
+        assert_eq!(stats["hits"], 1);
+        assert_eq!(stats["misses"], 6);
+        assert_eq!(stats["entries"], 0);
+    }
+}
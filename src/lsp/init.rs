@@ -1,39 +1,99 @@
-use std::{path::Path, process::Stdio, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
 
-use anyhow::{Context, Result, bail, ensure};
-use lsp_client::{LspClient, transport::io_transport};
+use anyhow::{Context, Result, anyhow, ensure};
+use lsp_client::LspClient;
 use lsp_types::{
-    ClientCapabilities, ClientInfo, GeneralClientCapabilities, HoverClientCapabilities,
-    InitializeParams, MarkupKind, PositionEncodingKind, SemanticTokensClientCapabilities,
+    CallHierarchyClientCapabilities, ClientCapabilities, ClientInfo,
+    DidChangeWatchedFilesClientCapabilities, DynamicRegistrationClientCapabilities,
+    GeneralClientCapabilities, HoverClientCapabilities, InitializeParams, MarkupKind, OneOf,
+    PositionEncodingKind, SemanticTokensClientCapabilities,
     SemanticTokensClientCapabilitiesRequests, SemanticTokensFullOptions,
-    SemanticTokensServerCapabilities, SymbolKind, SymbolKindCapability,
+    SemanticTokensServerCapabilities, ServerInfo, SymbolKind, SymbolKindCapability,
     TextDocumentClientCapabilities, TextDocumentSyncClientCapabilities, WindowClientCapabilities,
-    WorkspaceClientCapabilities, WorkspaceFolder, WorkspaceSymbolClientCapabilities,
+    WorkspaceClientCapabilities, WorkspaceSymbolClientCapabilities, WorkspaceSymbolOptions,
+    WorkspaceSymbolResolveSupportCapability,
 };
+use serde_json::{Value, json};
 use tokio::process::{Child, Command};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
     TaskManager,
     constants::{NAME, VERSION_STRING},
-    io_intercept::{BoxRead, BoxWrite, ReadFork, WriteFork},
-    lang::ProgrammingLanguageQuirks,
+    io_intercept::{BoxRead, BoxWrite, InterceptIoCLIConfig, Peer, TrafficTail},
+    lang::{LanguageServerCommand, ProgrammingLanguageQuirks},
+};
+
+use super::{
+    spawn_error::SpawnError,
+    tokens::TokenLegend,
+    transport::{ClientHandler, transport},
+    workspace::Workspace,
 };
 
-use super::tokens::TokenLegend;
+/// CLI config for the language server processes.
+#[derive(Debug, Clone, Default, clap::Parser)]
+pub(crate) struct LspProcessCLIConfig {
+    /// Environment variable (`KEY=VALUE`) for the language server process, e.g. `CARGO_TARGET_DIR` or proxy settings.
+    ///
+    /// Can be repeated. Takes precedence over the defaults of the programming language. Variables are
+    /// whitespace-separated when passed via environment variable.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LSP_ENV",
+        value_delimiter = ' ',
+        value_parser = parse_env
+    )]
+    lsp_env: Vec<(String, String)>,
+
+    /// Working directory of the language server process, defaults to the workspace root.
+    ///
+    /// Relative paths are resolved against the workspace root.
+    #[clap(long, env = "COMMON_SENSE_CODER_LSP_CWD")]
+    lsp_cwd: Option<PathBuf>,
+}
+
+fn parse_env(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected `KEY=VALUE`"))?;
+    ensure!(!key.is_empty(), "key must not be empty");
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+impl LspProcessCLIConfig {
+    /// Working directory of the language server process.
+    fn cwd(&self, root: &Path) -> PathBuf {
+        match &self.lsp_cwd {
+            Some(cwd) => root.join(cwd),
+            None => root.to_owned(),
+        }
+    }
+}
 
 pub(crate) async fn spawn_lsp(
+    name: &str,
     quirks: &Arc<dyn ProgrammingLanguageQuirks>,
-    intercept_io: Option<&Path>,
-    workspace: &Path,
+    process_cfg: &LspProcessCLIConfig,
+    intercept_io_cfg: &InterceptIoCLIConfig,
+    workspace: &Workspace,
     tasks: &mut TaskManager,
-) -> Result<(Arc<LspClient>, Child)> {
-    let stderr = if let Some(intercept_io) = intercept_io {
+) -> Result<(
+    Arc<LspClient>,
+    Arc<ClientHandler>,
+    Child,
+    Option<Arc<TrafficTail>>,
+)> {
+    let stderr = if let Some(intercept_io) = intercept_io_cfg.stderr_directory() {
         Stdio::from(
             tokio::fs::OpenOptions::new()
                 .create(true)
                 .append(true)
-                .open(intercept_io.join("lsp.stderr.txt"))
+                .open(intercept_io.join(format!("lsp.{name}.stderr.txt")))
                 .await
                 .context("open stderr log file for language server")?
                 .into_std()
@@ -43,39 +103,72 @@ pub(crate) async fn spawn_lsp(
         Stdio::null()
     };
 
-    let mut child = Command::new(quirks.language_server_binary())
-        .current_dir(workspace)
+    // otherwise reported as missing binary
+    let cwd = process_cfg.cwd(workspace.root());
+    ensure!(
+        cwd.is_dir(),
+        "language server working directory is not a directory: {}",
+        cwd.display()
+    );
+
+    let LanguageServerCommand { binary, args } = quirks.language_server();
+    let mut child = Command::new(binary)
+        .args(args)
+        .current_dir(cwd)
         .kill_on_drop(true)
         .envs(quirks.language_server_env())
+        .envs(process_cfg.lsp_env.iter().cloned())
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(stderr)
         .spawn()
-        .context("cannot spawn language server")?;
+        .map_err(|e| SpawnError::new(name, quirks.as_ref(), e))?;
 
     let stdin = Box::pin(child.stdin.take().expect("just initialized")) as BoxWrite;
     let stdout = Box::pin(child.stdout.take().expect("just initialized")) as BoxRead;
-    let (stdin, stdout) = if let Some(intercept_io) = intercept_io {
-        let stdin =
-            Box::pin(WriteFork::new(stdin, intercept_io, "lsp.stdin.txt", tasks).await?) as _;
-        let stdout =
-            Box::pin(ReadFork::new(stdout, intercept_io, "lsp.stdout.txt", tasks).await?) as _;
-        (stdin, stdout)
-    } else {
-        (stdin, stdout)
-    };
-    let (tx, rx) = io_transport(stdin, stdout);
+    let tail = intercept_io_cfg.tail();
+    let (stdout, stdin) = intercept_io_cfg
+        .fork(stdout, stdin, Peer::Lsp(name), tail.as_ref(), tasks)
+        .await?;
+    let handler = Arc::new(ClientHandler::new(workspace)?);
+    let (tx, rx) = transport(stdin, stdout, Arc::clone(&handler));
     let client = Arc::new(LspClient::new(tx, rx));
-    Ok((client, child))
+    Ok((client, handler, child, tail))
 }
 
+/// Features and info of an initialized language server.
+#[derive(Debug)]
+pub(crate) struct InitResult {
+    /// Legend of the semantic tokens, `None` if the language server does not provide them.
+    pub(crate) token_legend: Option<TokenLegend>,
+
+    /// Language server supports delta requests for semantic tokens.
+    pub(crate) semantic_tokens_delta: bool,
+
+    /// Language server can resolve workspace symbols that lack a range.
+    pub(crate) workspace_symbol_resolve: bool,
+
+    /// Language server accepts ranges for hover requests (see `experimental/hoverRange` of rust-analyzer).
+    pub(crate) hover_range: bool,
+
+    /// Name and version as reported by the language server.
+    pub(crate) server_info: Option<ServerInfo>,
+}
+
+/// Initialize language server.
 pub(crate) async fn init_lsp(
     client: &LspClient,
-    workspace: &Path,
+    handler: &ClientHandler,
+    workspace: &Workspace,
     quirks: &Arc<dyn ProgrammingLanguageQuirks>,
-) -> Result<TokenLegend> {
+) -> Result<InitResult> {
     debug!("initializing LSP");
 
+    let initialization_options = quirks
+        .initialization_options(workspace.root())
+        .context("get initialization options")?;
+    handler.set_settings(initialization_options.clone());
+
     let init_results = client
         .initialize(InitializeParams {
             capabilities: ClientCapabilities {
@@ -84,6 +177,9 @@ pub(crate) async fn init_lsp(
                     ..Default::default()
                 }),
                 text_document: Some(TextDocumentClientCapabilities {
+                    call_hierarchy: Some(CallHierarchyClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
                     hover: Some(HoverClientCapabilities {
                         content_format: Some(vec![MarkupKind::Markdown]),
                         dynamic_registration: Some(false),
@@ -131,24 +227,33 @@ pub(crate) async fn init_lsp(
                                 SymbolKind::VARIABLE,
                             ]),
                         }),
+                        resolve_support: Some(WorkspaceSymbolResolveSupportCapability {
+                            properties: vec!["location.range".to_owned()],
+                        }),
                         ..Default::default()
                     }),
                     workspace_folders: Some(true),
+                    configuration: Some(true),
+                    did_change_configuration: Some(DynamicRegistrationClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    did_change_watched_files: Some(DidChangeWatchedFilesClientCapabilities {
+                        dynamic_registration: Some(false),
+                        relative_pattern_support: Some(false),
+                    }),
                     ..Default::default()
                 }),
+                experimental: Some(json!({
+                    "hoverRange": true,
+                })),
                 ..Default::default()
             },
             client_info: Some(ClientInfo {
                 name: NAME.to_owned(),
                 version: Some(VERSION_STRING.to_owned()),
             }),
-            initialization_options: quirks.initialization_options(),
-            workspace_folders: Some(vec![WorkspaceFolder {
-                uri: format!("file://{}", workspace.display())
-                    .parse()
-                    .context("cannot parse workspace URI")?,
-                name: "root".to_owned(),
-            }]),
+            initialization_options,
+            workspace_folders: Some(workspace.lsp_folders()?),
             ..Default::default()
         })
         .await
@@ -164,31 +269,48 @@ pub(crate) async fn init_lsp(
         "position encoding is UTF-8"
     );
 
-    let token_legend = match server_caps
+    let semantic_tokens_options = server_caps
         .semantic_tokens_provider
-        .context("expect language server to support semantic tokens")?
-    {
-        SemanticTokensServerCapabilities::SemanticTokensOptions(semantic_tokens_options) => {
-            // check encoding
-            let full = semantic_tokens_options
-                .full
-                .context("language server supports semantic tokens for full document")?;
-            let uses_delta = match full {
-                lsp_types::SemanticTokensFullOptions::Bool(_) => false,
-                lsp_types::SemanticTokensFullOptions::Delta { delta } => delta.unwrap_or_default(),
-            };
-            ensure!(
-                uses_delta,
-                "language server uses delta mode to transfer semantic tokens"
-            );
-
-            // set up legend
-            TokenLegend::new(semantic_tokens_options.legend, quirks)
-        }
-        SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(_) => {
-            bail!("dynamic token registration not supported");
-        }
-    };
+        .map(|provider| match provider {
+            SemanticTokensServerCapabilities::SemanticTokensOptions(options) => options,
+            SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options) => {
+                options.semantic_tokens_options
+            }
+        })
+        .filter(|options| match options.full {
+            None | Some(SemanticTokensFullOptions::Bool(false)) => false,
+            Some(
+                SemanticTokensFullOptions::Bool(true) | SemanticTokensFullOptions::Delta { .. },
+            ) => true,
+        });
+    let semantic_tokens_delta = semantic_tokens_options.as_ref().is_some_and(|options| {
+        matches!(
+            options.full,
+            Some(SemanticTokensFullOptions::Delta { delta: Some(true) })
+        )
+    });
+    let token_legend =
+        semantic_tokens_options.map(|options| TokenLegend::new(options.legend, quirks));
+    if token_legend.is_none() {
+        warn!(
+            "language server does not provide semantic tokens for full documents, fall back to text search"
+        );
+    }
+
+    let workspace_symbol_resolve = matches!(
+        server_caps.workspace_symbol_provider,
+        Some(OneOf::Right(WorkspaceSymbolOptions {
+            resolve_provider: Some(true),
+            ..
+        }))
+    );
+
+    let hover_range = server_caps
+        .experimental
+        .as_ref()
+        .and_then(|experimental| experimental.get("hoverRange"))
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
 
     client.initialized().await.context("set init response")?;
 
@@ -201,5 +323,48 @@ pub(crate) async fn init_lsp(
         "LSP initialized"
     );
 
-    Ok(token_legend)
+    Ok(InitResult {
+        token_legend,
+        semantic_tokens_delta,
+        workspace_symbol_resolve,
+        hover_range,
+        server_info,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_process_cfg() {
+        let cfg = LspProcessCLIConfig::try_parse_from([
+            "",
+            "--lsp-env=CARGO_TARGET_DIR=/tmp/target",
+            "--lsp-env",
+            "EMPTY=",
+            "--lsp-cwd=crates/foo",
+        ])
+        .unwrap();
+        assert_eq!(
+            cfg.lsp_env,
+            vec![
+                ("CARGO_TARGET_DIR".to_owned(), "/tmp/target".to_owned()),
+                ("EMPTY".to_owned(), "".to_owned()),
+            ],
+        );
+        assert_eq!(cfg.cwd(Path::new("/ws")), PathBuf::from("/ws/crates/foo"));
+
+        let cfg = LspProcessCLIConfig::try_parse_from(["", "--lsp-cwd=/other"]).unwrap();
+        assert_eq!(cfg.cwd(Path::new("/ws")), PathBuf::from("/other"));
+        assert_eq!(
+            LspProcessCLIConfig::default().cwd(Path::new("/ws")),
+            PathBuf::from("/ws")
+        );
+
+        LspProcessCLIConfig::try_parse_from(["", "--lsp-env=FOO"]).unwrap_err();
+        LspProcessCLIConfig::try_parse_from(["", "--lsp-env==bar"]).unwrap_err();
+    }
 }
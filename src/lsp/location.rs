@@ -1,17 +1,21 @@
-use std::{
-    path::{Path, PathBuf},
-    str::FromStr,
-    sync::Arc,
-};
+use std::path::Path;
 
 use anyhow::{Context, Error, Result};
+use clap::ValueEnum;
 use lsp_types::{
     GotoDefinitionResponse, Location, LocationLink, Position, TextDocumentIdentifier,
     TextDocumentPositionParams, Uri,
 };
+use percent_encoding::percent_decode_str;
 use rmcp::schemars;
 use serde::Serialize;
 
+use super::{
+    dependency::Dependency,
+    uri::{file_uri_to_path, path_to_file_uri},
+    workspace::Workspace,
+};
+
 #[derive(Debug)]
 pub(crate) enum LocationVariants {
     Scalar(Location),
@@ -22,22 +26,20 @@ pub(crate) enum LocationVariants {
 impl LocationVariants {
     pub(crate) fn into_mcp_location(
         self,
-        workspace: Arc<Path>,
+        workspace: &Workspace,
         workspace_and_dependencies: bool,
     ) -> Result<Vec<McpLocation>> {
         match self {
             Self::Scalar(location) => {
                 Ok(
-                    McpLocation::try_new(location, workspace, workspace_and_dependencies)?
+                    McpLocation::try_new(location, workspace.clone(), workspace_and_dependencies)?
                         .into_iter()
                         .collect(),
                 )
             }
             Self::Array(locations) => locations
                 .into_iter()
-                .map(|loc| {
-                    McpLocation::try_new(loc, Arc::clone(&workspace), workspace_and_dependencies)
-                })
+                .map(|loc| McpLocation::try_new(loc, workspace.clone(), workspace_and_dependencies))
                 .filter_map(Result::transpose)
                 .collect::<Result<Vec<_>>>()
                 .context("format locations"),
@@ -46,7 +48,7 @@ impl LocationVariants {
                 .map(|loc| {
                     McpLocation::try_new_from_location_link(
                         loc,
-                        Arc::clone(&workspace),
+                        workspace.clone(),
                         workspace_and_dependencies,
                     )
                 })
@@ -72,6 +74,9 @@ impl From<GotoDefinitionResponse> for LocationVariants {
 #[serde(rename = "Location")]
 pub(crate) struct McpLocation {
     /// File path.
+    ///
+    /// Relative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of
+    /// dependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.
     pub(crate) file: String,
 
     /// 1-based line number.
@@ -82,38 +87,103 @@ pub(crate) struct McpLocation {
     #[schemars(range(min = 1))]
     pub(crate) character: u32,
 
+    /// Absolute file path, only set if both relative and absolute paths are requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) absolute_file: Option<String>,
+
+    /// Crate of a dependency file outside the workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) dependency: Option<Dependency>,
+
     #[serde(skip_serializing)]
     #[schemars(skip)]
-    pub(crate) workspace: Arc<Path>,
+    pub(crate) workspace: Workspace,
+}
+
+/// How file paths are rendered in tool results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum PathStyle {
+    /// Relative to the workspace, virtual paths for dependencies.
+    #[default]
+    Relative,
+
+    /// Absolute paths.
+    Absolute,
+
+    /// Relative paths, plus the absolute ones in a separate field.
+    Both,
+}
+
+impl PathStyle {
+    /// Render path, returns the file and the optional absolute file.
+    ///
+    /// `file` is the relative or virtual path.
+    fn render(self, file: String, absolute: &Path) -> (String, Option<String>) {
+        match self {
+            Self::Relative => (file, None),
+            Self::Absolute => (absolute.display().to_string(), None),
+            Self::Both => (file, Some(absolute.display().to_string())),
+        }
+    }
 }
 
 impl McpLocation {
+    /// Location within a file that was passed by the MCP client.
+    pub(crate) fn new(file: String, line: u32, character: u32, workspace: Workspace) -> Self {
+        let absolute = workspace.resolve(&file);
+        let (file, absolute_file) = workspace.path_style().render(file, &absolute);
+        Self {
+            file,
+            line,
+            character,
+            absolute_file,
+            dependency: None,
+            workspace,
+        }
+    }
+
     pub(crate) fn try_new(
         loc: Location,
-        workspace: Arc<Path>,
+        workspace: Workspace,
         workspace_and_dependencies: bool,
     ) -> Result<Option<Self>> {
         let Location { uri, range } = loc;
 
-        let path = uri.path();
-        let file = if path.is_absolute() {
-            let path = PathBuf::from_str(path.as_str()).context("parse URI as path")?;
-
-            // try to make it relative to the workspace root
-            match (path.strip_prefix(&workspace), workspace_and_dependencies) {
+        let mut dependency = None;
+        let (file, absolute_file) = if let Some(path) = file_uri_to_path(&uri) {
+            // try to make it relative to the workspace folders
+            if !workspace.allows(&path) {
+                return Ok(None);
+            }
+            let file = match (workspace.relativize(&path), workspace_and_dependencies) {
                 // path is within workspace
-                (Ok(path2), _) => path2,
+                (Some(path2), _) => path2,
                 // path outside workspace, but that's fine
-                (Err(_), true) => &path,
+                (None, true) => match workspace.dependencies().parse(&path) {
+                    Some(dep) => {
+                        let file = workspace.dependencies().virtualize(&dep);
+                        dependency = Some(dep);
+                        file
+                    }
+                    // extra directory of the jail, see `Workspace::allows`
+                    None if workspace.is_jailed() => path.display().to_string(),
+                    None => return Ok(None),
+                },
                 // path outside workspace, but we did not search for it
-                (Err(_), false) => {
+                (None, false) => {
                     return Ok(None);
                 }
-            }
-            .display()
-            .to_string()
+            };
+            workspace.path_style().render(file, &path)
         } else {
-            path.to_string()
+            // e.g. `jar:` URIs of Java dependencies, which cannot be checked against the jail
+            if workspace.is_jailed() {
+                return Ok(None);
+            }
+            let file = percent_decode_str(uri.path().as_str())
+                .decode_utf8_lossy()
+                .into_owned();
+            (file, None)
         };
 
         let start = range.start;
@@ -124,13 +194,15 @@ impl McpLocation {
             file,
             line,
             character,
+            absolute_file,
+            dependency,
             workspace,
         }))
     }
 
     pub(crate) fn try_new_from_location_link(
         loc: LocationLink,
-        workspace: Arc<Path>,
+        workspace: Workspace,
         workspace_and_dependencies: bool,
     ) -> Result<Option<Self>> {
         let loc = Location::new(loc.target_uri, loc.target_range);
@@ -153,6 +225,8 @@ impl TryFrom<&McpLocation> for TextDocumentPositionParams {
             file,
             line,
             character,
+            absolute_file: _,
+            dependency: _,
             workspace,
         } = loc;
 
@@ -166,22 +240,165 @@ impl TryFrom<&McpLocation> for TextDocumentPositionParams {
     }
 }
 
-pub(crate) fn path_to_uri(workspace: &Path, path: &str) -> Result<Uri> {
-    // prefix relative paths with workspace
-    let path = if path.starts_with("/") {
-        path
-    } else {
-        &format!("{}/{path}", workspace.display())
-    };
-
-    format!("file://{path}").parse().context("parse file URI")
+pub(crate) fn path_to_uri(workspace: &Workspace, path: &str) -> Result<Uri> {
+    // prefix relative paths with workspace folder
+    path_to_file_uri(&workspace.resolve(path))
 }
 
 pub(crate) fn path_to_text_document_identifier(
-    workspace: &Path,
+    workspace: &Workspace,
     path: &str,
 ) -> Result<TextDocumentIdentifier> {
     Ok(TextDocumentIdentifier {
         uri: path_to_uri(workspace, path)?,
     })
 }
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::lsp::dependency::{Origin, SourceRoot};
+
+    use super::*;
+
+    fn location(uri: &str) -> Location {
+        Location {
+            uri: uri.parse().unwrap(),
+            range: Default::default(),
+        }
+    }
+
+    fn workspace(path: &str) -> Workspace {
+        let workspace = Workspace::new([PathBuf::from(path)]).unwrap();
+        workspace.dependencies().set_sources(vec![SourceRoot::new(
+            Origin::Registry,
+            "/cargo/registry/src",
+        )]);
+        workspace
+    }
+
+    #[test]
+    fn test_try_new() {
+        let workspace = workspace("/my ws");
+        let file = |uri: &str, workspace_and_dependencies: bool| {
+            McpLocation::try_new(location(uri), workspace.clone(), workspace_and_dependencies)
+                .unwrap()
+                .map(|location| location.file)
+        };
+
+        assert_eq!(
+            file("file:///my%20ws/src/%C3%A4.rs", false).as_deref(),
+            Some("src/ä.rs")
+        );
+        assert_eq!(file("file:///dep%20dir/lib.rs", false), None);
+        assert_eq!(file("file:///dep%20dir/lib.rs", true), None);
+        assert_eq!(
+            file("jar:///lib%20dir/Foo.class", false).as_deref(),
+            Some("/lib dir/Foo.class")
+        );
+
+        let location = McpLocation::try_new(
+            location("file:///cargo/registry/src/index/serde-1.0.200/src/lib.rs"),
+            workspace.clone(),
+            true,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(location.file, "dep://serde@1.0.200/src/lib.rs");
+        assert_eq!(
+            location.dependency,
+            Some(Dependency {
+                name: "serde".to_owned(),
+                version: Some("1.0.200".to_owned()),
+                path: "src/lib.rs".to_owned(),
+                root: PathBuf::from("/cargo/registry/src/index/serde-1.0.200"),
+                origin: Origin::Registry,
+            })
+        );
+        assert_eq!(
+            path_to_uri(&workspace, &location.file).unwrap().as_str(),
+            "file:///cargo/registry/src/index/serde-1.0.200/src/lib.rs"
+        );
+
+        let uri = path_to_uri(&workspace, "src/ä b.rs").unwrap();
+        assert_eq!(uri.as_str(), "file:///my%20ws/src/%C3%A4%20b.rs");
+    }
+
+    #[test]
+    fn test_jail() {
+        // the jail resolves paths, so the files must exist
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        for file in [
+            "ws/src/lib.rs",
+            "cargo/registry/src/index/serde-1.0.200/src/lib.rs",
+            "extra/lib.rs",
+            "other/lib.rs",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let workspace = Workspace::new([dir.join("ws")])
+            .unwrap()
+            .with_jail(Some(vec![dir.join("extra")]));
+        workspace.dependencies().set_sources(vec![SourceRoot::new(
+            Origin::Registry,
+            dir.join("cargo/registry/src"),
+        )]);
+        let file = |path: &str| {
+            let uri = path_to_file_uri(&dir.join(path)).unwrap();
+            McpLocation::try_new(location(uri.as_str()), workspace.clone(), true)
+                .unwrap()
+                .map(|location| location.file)
+        };
+
+        assert_eq!(file("ws/src/lib.rs").as_deref(), Some("src/lib.rs"));
+        assert_eq!(
+            file("cargo/registry/src/index/serde-1.0.200/src/lib.rs").as_deref(),
+            Some("dep://serde@1.0.200/src/lib.rs")
+        );
+        assert_eq!(
+            file("extra/lib.rs"),
+            Some(dir.join("extra/lib.rs").display().to_string())
+        );
+        assert_eq!(file("other/lib.rs"), None);
+        assert_eq!(file("ws/src/missing.rs"), None);
+        assert_eq!(
+            McpLocation::try_new(location("file:///etc/passwd"), workspace.clone(), true).unwrap(),
+            None
+        );
+        assert_eq!(
+            McpLocation::try_new(location("jar:///lib/Foo.class"), workspace.clone(), true)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_path_style() {
+        let workspace = Workspace::new([PathBuf::from("/ws")]).unwrap();
+        let files = |style: PathStyle| {
+            let workspace = workspace.clone().with_path_style(style);
+            let location =
+                McpLocation::try_new(location("file:///ws/src/lib.rs"), workspace.clone(), false)
+                    .unwrap()
+                    .unwrap();
+            let input = McpLocation::new("src/lib.rs".to_owned(), 1, 1, workspace);
+            assert_eq!(location.file, input.file);
+            assert_eq!(location.absolute_file, input.absolute_file);
+            (location.file, location.absolute_file)
+        };
+
+        assert_eq!(files(PathStyle::Relative), ("src/lib.rs".to_owned(), None));
+        assert_eq!(
+            files(PathStyle::Absolute),
+            ("/ws/src/lib.rs".to_owned(), None)
+        );
+        assert_eq!(
+            files(PathStyle::Both),
+            ("src/lib.rs".to_owned(), Some("/ws/src/lib.rs".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Log and show messages sent by the language server.
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use lsp_client::LspClient;
+use lsp_types::{
+    LogMessageParams, MessageType, ShowMessageParams,
+    notification::{LogMessage, ShowMessage},
+};
+use tokio::sync::broadcast::{Receiver, Sender, channel};
+use tracing::debug;
+
+use crate::{TaskManager, tasks::RestartPolicy};
+
+/// Number of messages that are buffered for slow receivers.
+const CAPACITY: usize = 128;
+
+/// Messages are merely informational, so a broken subscription is re-established instead of stopping the server.
+const RESTART_POLICY: RestartPolicy = RestartPolicy::OnFailure {
+    backoff: Duration::from_secs(1),
+    max_restarts: 5,
+};
+
+/// Message sent by the language server via `window/logMessage` or `window/showMessage`.
+#[derive(Debug, Clone)]
+pub(crate) struct ServerMessage {
+    pub(crate) typ: MessageType,
+    pub(crate) message: String,
+}
+
+/// Distributes messages of a language server to any number of receivers.
+#[derive(Debug, Clone)]
+pub(crate) struct ServerMessages {
+    tx: Sender<ServerMessage>,
+}
+
+impl ServerMessages {
+    /// Start listening for messages.
+    pub(crate) fn start(tasks: &mut TaskManager, name: &str, client: Arc<LspClient>) -> Self {
+        let (tx, _rx) = channel(CAPACITY);
+
+        let tx_captured = tx.clone();
+        let name = name.to_owned();
+        tasks.spawn_supervised(
+            move |cancel| {
+                let tx = tx_captured.clone();
+                let client = Arc::clone(&client);
+                let name = name.clone();
+                async move {
+                let mut log_subscription = client
+                    .subscribe_to_method::<LogMessage>()
+                    .await
+                    .context("subscribe to 'logMessage'")?;
+                let mut show_subscription = client
+                    .subscribe_to_method::<ShowMessage>()
+                    .await
+                    .context("subscribe to 'showMessage'")?;
+
+                loop {
+                    let msg = tokio::select! {
+                        next = log_subscription.next() => match next {
+                            Some(res) => {
+                                let LogMessageParams { typ, message } =
+                                    res.context("receive log message")?;
+                                ServerMessage { typ, message }
+                            }
+                            None => break,
+                        },
+                        next = show_subscription.next() => match next {
+                            Some(res) => {
+                                let ShowMessageParams { typ, message } =
+                                    res.context("receive show message")?;
+                                ServerMessage { typ, message }
+                            }
+                            None => break,
+                        },
+                        _ = cancel.cancelled() => break,
+                    };
+                    debug!(lang = name.as_str(), typ = ?msg.typ, message = msg.message.as_str(), "language server message");
+
+                    // no receivers is fine
+                    tx.send(msg).ok();
+                }
+
+                log_subscription
+                    .unsubscribe()
+                    .await
+                    .context("unsubscribe log messages")?;
+                show_subscription
+                    .unsubscribe()
+                    .await
+                    .context("unsubscribe show messages")?;
+
+                Ok(())
+                }
+            },
+            "server messages",
+            RESTART_POLICY,
+        );
+
+        Self { tx }
+    }
+
+    /// Receive messages from now on.
+    pub(crate) fn subscribe(&self) -> Receiver<ServerMessage> {
+        self.tx.subscribe()
+    }
+}
@@ -0,0 +1,145 @@
+//! Latency and outcome of language server requests.
+//!
+//! Helps to diagnose why tool calls are slow, e.g. on big workspaces.
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::MissedTickBehavior;
+use tokio_util::task::AbortOnDropHandle;
+use tracing::info;
+
+use crate::cli::parse_secs;
+
+use super::server::LanguageServer;
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct MetricsCLIConfig {
+    /// Interval (in seconds) in which the request statistics of the language servers are logged.
+    ///
+    /// Not logged if not set. The statistics are also available via the `server_stats` tool.
+    #[clap(
+        long,
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_LSP_STATS_LOG_INTERVAL"
+    )]
+    lsp_stats_log_interval: Option<Duration>,
+}
+
+impl MetricsCLIConfig {
+    /// Start logging the request statistics, stops when the returned handle is dropped.
+    ///
+    /// Returns `None` if logging is disabled.
+    pub(crate) fn start(&self, servers: Arc<[LanguageServer]>) -> Option<AbortOnDropHandle<()>> {
+        let interval = self
+            .lsp_stats_log_interval
+            .filter(|interval| !interval.is_zero())?;
+
+        Some(AbortOnDropHandle::new(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                for server in servers.iter() {
+                    for (method, stats) in server.progress_guard().client().metrics().snapshot() {
+                        info!(
+                            lang = server.name(),
+                            method,
+                            count = stats.count,
+                            errors = stats.errors,
+                            mean_ms = stats.mean().as_secs_f64() * 1000.0,
+                            max_ms = stats.max.as_secs_f64() * 1000.0,
+                            "LSP request stats"
+                        );
+                    }
+                }
+            }
+        })))
+    }
+}
+
+/// Statistics of all requests of a single method.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MethodStats {
+    /// Number of requests, including failed ones.
+    pub(crate) count: u64,
+
+    /// Number of failed requests.
+    pub(crate) errors: u64,
+
+    /// Sum of the request durations.
+    pub(crate) total: Duration,
+
+    /// Longest request duration.
+    pub(crate) max: Duration,
+}
+
+impl MethodStats {
+    /// Mean request duration.
+    pub(crate) fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => self.total.div_f64(count as f64),
+        }
+    }
+}
+
+/// Request statistics of a single language server, keyed by method.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestMetrics {
+    methods: Arc<Mutex<BTreeMap<&'static str, MethodStats>>>,
+}
+
+impl RequestMetrics {
+    /// Record finished request.
+    pub(crate) fn record(&self, method: &'static str, duration: Duration, success: bool) {
+        let mut methods = self.methods.lock().expect("not poisoned");
+        let stats = methods.entry(method).or_default();
+        stats.count += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        stats.total += duration;
+        stats.max = stats.max.max(duration);
+    }
+
+    /// Current statistics, sorted by method.
+    pub(crate) fn snapshot(&self) -> BTreeMap<&'static str, MethodStats> {
+        self.methods.lock().expect("not poisoned").clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let metrics = RequestMetrics::default();
+        assert!(metrics.snapshot().is_empty());
+
+        metrics.record("b", Duration::from_millis(10), true);
+        metrics.record("b", Duration::from_millis(30), false);
+        metrics.record("a", Duration::from_millis(5), true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.keys().copied().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(
+            snapshot["b"],
+            MethodStats {
+                count: 2,
+                errors: 1,
+                total: Duration::from_millis(40),
+                max: Duration::from_millis(30),
+            }
+        );
+        assert_eq!(snapshot["b"].mean(), Duration::from_millis(20));
+        assert_eq!(MethodStats::default().mean(), Duration::ZERO);
+    }
+}
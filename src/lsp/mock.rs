@@ -0,0 +1,803 @@
+//! Fake language server for fast tests.
+//!
+//! Finds declarations like `fn` or `struct` in the Rust files of the workspace with a keyword scan instead of a real
+//! analysis. It speaks just enough of the protocol for the symbol tools: `initialize`, the progress that ends the
+//! initial work, workspace and document symbols, hovers, semantic tokens, definitions, and references. Other requests
+//! are answered with `null`.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use lsp_types::{SymbolKind, Uri};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tracing::{debug, info};
+
+use super::{
+    transport::{read_message, write_messages},
+    uri::{file_uri_to_path, path_to_file_uri},
+};
+
+/// CLI config for the fake language server.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct MockCLIConfig {
+    /// Act as a fake language server for tests and development, then exit.
+    ///
+    /// Use it as the language server of another instance, e.g. `--lsp-command=common-sense-coder
+    /// --lsp-arg=--mock-lsp`. Declarations are found with a keyword scan of the Rust files, which is instant but only
+    /// good enough to exercise the tools.
+    #[clap(long)]
+    mock_lsp: bool,
+
+    /// How the fake language server answers workspace symbol searches.
+    #[clap(long, default_value = "flat", requires = "mock_lsp")]
+    mock_lsp_workspace_symbols: MockWorkspaceSymbols,
+
+    /// Prepend an empty line to the file of the first `N` document symbol requests, like an edit during a tool call.
+    #[clap(long, default_value_t = 0, requires = "mock_lsp")]
+    mock_lsp_edits: usize,
+}
+
+/// Answer of the fake language server to workspace symbol searches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum MockWorkspaceSymbols {
+    /// Symbol information with locations.
+    #[default]
+    Flat,
+
+    /// Workspace symbols with files only, the ranges are resolved via `workspaceSymbol/resolve`.
+    Nested,
+
+    /// Like `nested`, but resolving fails.
+    Unresolvable,
+}
+
+impl MockCLIConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.mock_lsp
+    }
+
+    /// Serve fake language server via stdin/stdout.
+    pub(crate) async fn serve(&self) -> Result<()> {
+        info!("mock language server");
+        serve(
+            BufReader::new(tokio::io::stdin()),
+            tokio::io::stdout(),
+            self.mock_lsp_workspace_symbols,
+            self.mock_lsp_edits,
+        )
+        .await
+    }
+}
+
+/// Progress tokens that rust-analyzer reports for its initial work, the readiness of the Rust quirks waits for them.
+const INIT_PROGRESS: &[&str] = &[
+    "rustAnalyzer/Building CrateGraph",
+    "rustAnalyzer/Roots Scanned",
+    "rustAnalyzer/cachePriming",
+    "rust-analyzer/flycheck/0",
+];
+
+/// Keywords that introduce a declaration, with the symbol kind and semantic token type of the declared name.
+const KEYWORDS: &[(&str, SymbolKind, &str)] = &[
+    ("fn", SymbolKind::FUNCTION, "function"),
+    ("struct", SymbolKind::STRUCT, "struct"),
+    ("enum", SymbolKind::ENUM, "enum"),
+    ("trait", SymbolKind::INTERFACE, "interface"),
+    ("const", SymbolKind::CONSTANT, "const"),
+    ("static", SymbolKind::CONSTANT, "static"),
+    ("mod", SymbolKind::MODULE, "namespace"),
+    ("type", SymbolKind::TYPE_PARAMETER, "typeAlias"),
+    ("let", SymbolKind::VARIABLE, "variable"),
+];
+
+/// Semantic token modifier of declarations, the only one in the legend.
+const DECLARATION: u32 = 1;
+
+/// Declaration found by the keyword scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Declaration {
+    name: String,
+
+    /// Index into [`KEYWORDS`].
+    keyword: usize,
+
+    /// 0-based line of the name.
+    line: u32,
+
+    /// 0-based UTF-8 offset of the name.
+    character: u32,
+
+    /// Line of the declaration, shown as signature in hovers.
+    signature: String,
+
+    /// Doc comments (`///`) before the declaration.
+    docs: String,
+}
+
+impl Declaration {
+    fn kind(&self) -> SymbolKind {
+        KEYWORDS[self.keyword].1
+    }
+
+    fn location(&self, uri: &str) -> Value {
+        json!({
+            "uri": uri,
+            "range": range(self.line, self.character, self.name.len()),
+        })
+    }
+
+    fn symbol(&self, uri: &str) -> Value {
+        json!({
+            "name": self.name,
+            "kind": self.kind(),
+            "location": self.location(uri),
+        })
+    }
+}
+
+fn range(line: u32, character: u32, len: usize) -> Value {
+    json!({
+        "start": {"line": line, "character": character},
+        "end": {"line": line, "character": character + len as u32},
+    })
+}
+
+/// Identifiers of a line with their UTF-8 offset.
+fn identifiers(line: &str) -> Vec<(usize, &str)> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut idents = vec![];
+    let mut start = None;
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, is_ident(c)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                let ident = &line[s..i];
+                if !ident.starts_with(|c: char| c.is_ascii_digit()) {
+                    idents.push((s, ident));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    idents
+}
+
+/// Comments contain prose (or doc tests), not code.
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with("//")
+}
+
+/// Find declarations by their keywords.
+fn scan(content: &str) -> Vec<Declaration> {
+    let mut declarations = vec![];
+    let mut docs = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(doc) = trimmed.strip_prefix("///") {
+            docs.push(doc.strip_prefix(' ').unwrap_or(doc));
+            continue;
+        }
+        // attributes are between docs and the declaration
+        if is_comment(line) || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let idents = identifiers(line)
+            .into_iter()
+            .filter(|(_, ident)| *ident != "mut")
+            .collect::<Vec<_>>();
+        for pair in idents.windows(2) {
+            let [(_, keyword), (character, name)] = pair else {
+                continue;
+            };
+            let Some(keyword) = KEYWORDS.iter().position(|(kw, _, _)| kw == keyword) else {
+                continue;
+            };
+            if KEYWORDS.iter().any(|(kw, _, _)| kw == name) {
+                continue;
+            }
+            declarations.push(Declaration {
+                name: (*name).to_owned(),
+                keyword,
+                line: idx as u32,
+                character: *character as u32,
+                signature: trimmed
+                    .trim_end()
+                    .trim_end_matches('{')
+                    .trim_end()
+                    .to_owned(),
+                docs: std::mem::take(&mut docs).join("\n"),
+            });
+        }
+        docs.clear();
+    }
+    declarations
+}
+
+/// Identifier at a position.
+fn identifier_at(content: &str, line: u32, character: u32) -> Option<(usize, &str)> {
+    let text = content.lines().nth(line as usize)?;
+    identifiers(text)
+        .into_iter()
+        .find(|(start, ident)| (*start..start + ident.len()).contains(&(character as usize)))
+}
+
+/// Semantic tokens of a document, for identifiers that are declared somewhere in the workspace.
+fn semantic_tokens(content: &str, own: &[Declaration], names: &HashMap<&str, usize>) -> Vec<u32> {
+    let types = token_types();
+    let mut data = vec![];
+    let mut previous = (0, 0);
+    for (idx, line) in content.lines().enumerate() {
+        if is_comment(line) {
+            continue;
+        }
+        let idx = idx as u32;
+        for (start, ident) in identifiers(line) {
+            let start = start as u32;
+            let declaration = own
+                .iter()
+                .find(|decl| decl.line == idx && decl.character == start);
+            let keyword = match declaration {
+                Some(declaration) => declaration.keyword,
+                None => match names.get(ident) {
+                    Some(keyword) => *keyword,
+                    None => continue,
+                },
+            };
+            let token_type = types
+                .iter()
+                .position(|ty| *ty == KEYWORDS[keyword].2)
+                .expect("all types in legend");
+
+            let (previous_line, previous_start) = previous;
+            let delta_start = if idx == previous_line {
+                start - previous_start
+            } else {
+                start
+            };
+            data.extend([
+                idx - previous_line,
+                delta_start,
+                ident.len() as u32,
+                token_type as u32,
+                if declaration.is_some() {
+                    DECLARATION
+                } else {
+                    0
+                },
+            ]);
+            previous = (idx, start);
+        }
+    }
+    data
+}
+
+/// Token types of the legend, in order of the keywords.
+fn token_types() -> Vec<&'static str> {
+    let mut types = vec![];
+    for (_, _, ty) in KEYWORDS {
+        if !types.contains(ty) {
+            types.push(*ty);
+        }
+    }
+    types
+}
+
+/// State of the fake language server.
+#[derive(Debug, Default)]
+struct Mock {
+    workspace_symbols: MockWorkspaceSymbols,
+
+    /// Remaining edits, see [`MockCLIConfig::mock_lsp_edits`].
+    edits: usize,
+
+    folders: Vec<PathBuf>,
+
+    /// Content of open documents by URI.
+    documents: HashMap<String, String>,
+}
+
+impl Mock {
+    /// Rust files of the workspace with their content, open documents take precedence over the files on disk.
+    fn files(&self) -> Vec<(String, String)> {
+        let mut paths = vec![];
+        for folder in &self.folders {
+            collect_files(folder, &mut paths);
+        }
+        paths.sort();
+
+        let mut files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let uri = path_to_file_uri(&path).ok()?.as_str().to_owned();
+                let content = match self.documents.get(&uri) {
+                    Some(content) => content.clone(),
+                    None => std::fs::read_to_string(&path).ok()?,
+                };
+                Some((uri, content))
+            })
+            .collect::<Vec<_>>();
+        // open documents outside the folders
+        for (uri, content) in &self.documents {
+            if !files.iter().any(|(u, _)| u == uri) {
+                files.push((uri.clone(), content.clone()));
+            }
+        }
+        files
+    }
+
+    fn content(&self, uri: &str) -> Option<String> {
+        if let Some(content) = self.documents.get(uri) {
+            return Some(content.clone());
+        }
+        let path = file_uri_to_path(&uri.parse::<Uri>().ok()?)?;
+        std::fs::read_to_string(path).ok()
+    }
+
+    /// Declaration of the identifier at a position, declarations in the same file take precedence.
+    fn declaration_at(&self, params: &Value) -> Option<(String, Declaration, (usize, String))> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let position = &params["position"];
+        let content = self.content(uri)?;
+        let (start, ident) = identifier_at(
+            &content,
+            position["line"].as_u64()? as u32,
+            position["character"].as_u64()? as u32,
+        )?;
+        let ident = ident.to_owned();
+
+        let own = scan(&content)
+            .into_iter()
+            .find(|decl| decl.name == ident)
+            .map(|decl| (uri.to_owned(), decl));
+        let declaration = own.or_else(|| {
+            self.files().into_iter().find_map(|(uri, content)| {
+                scan(&content)
+                    .into_iter()
+                    .find(|decl| decl.name == ident)
+                    .map(|decl| (uri, decl))
+            })
+        })?;
+        Some((declaration.0, declaration.1, (start, ident)))
+    }
+
+    /// Edit the file of a request on disk, the answer is still based on the content before the edit.
+    fn edit(&mut self, params: &Value) -> Result<()> {
+        if self.edits == 0 {
+            return Ok(());
+        }
+        let Some(path) = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| file_uri_to_path(&uri.parse::<Uri>().ok()?))
+        else {
+            return Ok(());
+        };
+        self.edits -= 1;
+
+        let content = std::fs::read_to_string(&path).context("read edited file")?;
+        std::fs::write(&path, format!("\n{content}")).context("write edited file")
+    }
+
+    /// Error message of a request that fails.
+    fn error(&self, method: &str) -> Option<&'static str> {
+        (method == "workspaceSymbol/resolve"
+            && self.workspace_symbols == MockWorkspaceSymbols::Unresolvable)
+            .then_some("cannot resolve workspace symbol")
+    }
+
+    /// Result of a request.
+    fn answer(&self, method: &str, params: &Value) -> Value {
+        match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "positionEncoding": "utf-8",
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "declarationProvider": true,
+                    "referencesProvider": true,
+                    "documentSymbolProvider": true,
+                    "workspaceSymbolProvider": match self.workspace_symbols {
+                        MockWorkspaceSymbols::Flat => json!(true),
+                        MockWorkspaceSymbols::Nested | MockWorkspaceSymbols::Unresolvable => {
+                            json!({"resolveProvider": true})
+                        }
+                    },
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": token_types(),
+                            "tokenModifiers": ["declaration"],
+                        },
+                        "full": true,
+                    },
+                },
+                "serverInfo": {
+                    "name": "mock",
+                    // newer than any minimum version, so the version check passes
+                    "version": "9999.0.0 (9999-12-31)",
+                },
+            }),
+            "workspace/symbol" => {
+                let query = params["query"].as_str().unwrap_or_default().to_lowercase();
+                let symbols = self
+                    .files()
+                    .iter()
+                    .flat_map(|(uri, content)| {
+                        scan(content)
+                            .into_iter()
+                            .filter(|decl| decl.kind() != SymbolKind::VARIABLE)
+                            .filter(|decl| decl.name.to_lowercase().contains(&query))
+                            .map(|decl| match self.workspace_symbols {
+                                MockWorkspaceSymbols::Flat => decl.symbol(uri),
+                                MockWorkspaceSymbols::Nested
+                                | MockWorkspaceSymbols::Unresolvable => json!({
+                                    "name": decl.name,
+                                    "kind": decl.kind(),
+                                    "location": {"uri": uri},
+                                }),
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                Value::Array(symbols)
+            }
+            "workspaceSymbol/resolve" => {
+                let (Some(name), Some(uri)) =
+                    (params["name"].as_str(), params["location"]["uri"].as_str())
+                else {
+                    return Value::Null;
+                };
+                let Some(content) = self.content(uri) else {
+                    return Value::Null;
+                };
+                match scan(&content).into_iter().find(|decl| decl.name == name) {
+                    Some(decl) => decl.symbol(uri),
+                    None => Value::Null,
+                }
+            }
+            "textDocument/documentSymbol" => {
+                let Some(uri) = params["textDocument"]["uri"].as_str() else {
+                    return Value::Null;
+                };
+                let Some(content) = self.content(uri) else {
+                    return Value::Null;
+                };
+                scan(&content).iter().map(|decl| decl.symbol(uri)).collect()
+            }
+            "textDocument/hover" => {
+                let Some((_, declaration, (start, ident))) = self.declaration_at(params) else {
+                    return Value::Null;
+                };
+                let mut value = format!("```rust\n{}\n```", declaration.signature);
+                if !declaration.docs.is_empty() {
+                    value.push_str("\n\n---\n\n");
+                    value.push_str(&declaration.docs);
+                }
+                json!({
+                    "contents": {"kind": "markdown", "value": value},
+                    "range": range(
+                        params["position"]["line"].as_u64().unwrap_or_default() as u32,
+                        start as u32,
+                        ident.len(),
+                    ),
+                })
+            }
+            "textDocument/definition" | "textDocument/declaration" => {
+                match self.declaration_at(params) {
+                    Some((uri, declaration, _)) => json!([declaration.location(&uri)]),
+                    None => Value::Null,
+                }
+            }
+            "textDocument/references" => {
+                let Some((_, _, (_, ident))) = self.declaration_at(params) else {
+                    return Value::Null;
+                };
+                let include_declaration = params["context"]["includeDeclaration"]
+                    .as_bool()
+                    .unwrap_or_default();
+                let mut references = vec![];
+                for (uri, content) in self.files() {
+                    let declarations = scan(&content);
+                    for (idx, line) in content.lines().enumerate() {
+                        if is_comment(line) {
+                            continue;
+                        }
+                        for (start, name) in identifiers(line) {
+                            let is_declaration = declarations.iter().any(|decl| {
+                                decl.line == idx as u32 && decl.character == start as u32
+                            });
+                            if name == ident && (include_declaration || !is_declaration) {
+                                references.push(json!({
+                                    "uri": uri,
+                                    "range": range(idx as u32, start as u32, name.len()),
+                                }));
+                            }
+                        }
+                    }
+                }
+                Value::Array(references)
+            }
+            "textDocument/semanticTokens/full" => {
+                let Some(content) = params["textDocument"]["uri"]
+                    .as_str()
+                    .and_then(|uri| self.content(uri))
+                else {
+                    return Value::Null;
+                };
+                let workspace = self
+                    .files()
+                    .into_iter()
+                    .flat_map(|(_, content)| scan(&content))
+                    .collect::<Vec<_>>();
+                let names = workspace
+                    .iter()
+                    .filter(|decl| decl.kind() != SymbolKind::VARIABLE)
+                    .map(|decl| (decl.name.as_str(), decl.keyword))
+                    .collect::<HashMap<_, _>>();
+                let own = scan(&content);
+                json!({"data": semantic_tokens(&content, &own, &names)})
+            }
+            _ => Value::Null,
+        }
+    }
+
+    /// Apply a notification.
+    fn notify(&mut self, method: &str, params: &Value) {
+        match method {
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) =
+                    (document["uri"].as_str(), document["text"].as_str())
+                {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                }
+            }
+            "textDocument/didChange" => {
+                // only full updates are announced via `textDocumentSync`
+                if let (Some(uri), Some(text)) = (
+                    params["textDocument"]["uri"].as_str(),
+                    params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str()),
+                ) {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                }
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+            }
+            "workspace/didChangeWorkspaceFolders" => {
+                let event = &params["event"];
+                for folder in event["removed"].as_array().into_iter().flatten() {
+                    if let Some(path) = folder_path(folder) {
+                        self.folders.retain(|f| f != &path);
+                    }
+                }
+                self.folders.extend(
+                    event["added"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(folder_path),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+fn folder_path(folder: &Value) -> Option<PathBuf> {
+    file_uri_to_path(&folder["uri"].as_str()?.parse::<Uri>().ok()?)
+}
+
+/// Rust files within a directory, skipping hidden directories and build output.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// Serve fake language server to a client.
+async fn serve(
+    mut input: impl AsyncBufRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+    workspace_symbols: MockWorkspaceSymbols,
+    edits: usize,
+) -> Result<()> {
+    let mut mock = Mock {
+        workspace_symbols,
+        edits,
+        ..Default::default()
+    };
+
+    loop {
+        let msg = match read_message(&mut input).await {
+            Ok(msg) => msg,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context("read message"),
+        };
+        let msg = serde_json::from_slice::<Value>(&msg).context("parse message")?;
+        let Some(method) = msg.get("method").and_then(Value::as_str) else {
+            // response to one of our requests
+            continue;
+        };
+        debug!(method, "mock");
+        let params = msg.get("params").cloned().unwrap_or_default();
+
+        if method == "initialize" {
+            mock.folders = match params["workspaceFolders"].as_array() {
+                Some(folders) => folders.iter().filter_map(folder_path).collect(),
+                None => params["rootUri"]
+                    .as_str()
+                    .and_then(|uri| file_uri_to_path(&uri.parse::<Uri>().ok()?))
+                    .into_iter()
+                    .collect(),
+            };
+        }
+
+        if method == "textDocument/documentSymbol" {
+            mock.edit(&params)?;
+        }
+
+        let messages = match msg.get("id") {
+            Some(id) => vec![match mock.error(method) {
+                Some(message) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32603, "message": message},
+                }),
+                None => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": mock.answer(method, &params),
+                }),
+            }],
+            None => {
+                mock.notify(method, &params);
+                if method == "initialized" {
+                    INIT_PROGRESS
+                        .iter()
+                        .flat_map(|token| {
+                            [
+                                json!({"kind": "begin", "title": token}),
+                                json!({"kind": "end"}),
+                            ]
+                            .map(|value| {
+                                json!({
+                                    "jsonrpc": "2.0",
+                                    "method": "$/progress",
+                                    "params": {"token": token, "value": value},
+                                })
+                            })
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                }
+            }
+        };
+        write_messages(&mut output, &messages).await?;
+
+        if method == "exit" {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONTENT: &str = "\
+/// Adds.
+///
+/// Really.
+#[inline]
+pub(crate) const fn add(a: u32) -> u32 {
+    // fn not_a_declaration
+    let mut sum = a + ONE;
+    sum
+}
+
+pub struct Meters(f64);
+static ONE: u32 = 1;
+";
+
+    #[test]
+    fn test_scan() {
+        let declarations = scan(CONTENT);
+        assert_eq!(
+            declarations
+                .iter()
+                .map(|decl| (decl.name.as_str(), decl.kind(), decl.line, decl.character))
+                .collect::<Vec<_>>(),
+            [
+                ("add", SymbolKind::FUNCTION, 4, 20),
+                ("sum", SymbolKind::VARIABLE, 6, 12),
+                ("Meters", SymbolKind::STRUCT, 10, 11),
+                ("ONE", SymbolKind::CONSTANT, 11, 7),
+            ],
+        );
+        assert_eq!(declarations[0].docs, "Adds.\n\nReally.");
+        assert_eq!(
+            declarations[0].signature,
+            "pub(crate) const fn add(a: u32) -> u32"
+        );
+        assert_eq!(declarations[1].docs, "");
+    }
+
+    #[test]
+    fn test_identifier_at() {
+        assert_eq!(identifier_at(CONTENT, 6, 12), Some((12, "sum")));
+        assert_eq!(identifier_at(CONTENT, 6, 14), Some((12, "sum")));
+        assert_eq!(identifier_at(CONTENT, 6, 15), None);
+        assert_eq!(identifier_at(CONTENT, 100, 0), None);
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let own = scan(CONTENT);
+        let names = own
+            .iter()
+            .filter(|decl| decl.kind() != SymbolKind::VARIABLE)
+            .map(|decl| (decl.name.as_str(), decl.keyword))
+            .collect::<HashMap<_, _>>();
+        let types = token_types();
+        let ty = |name: &str| types.iter().position(|ty| *ty == name).unwrap() as u32;
+        assert_eq!(
+            semantic_tokens(CONTENT, &own, &names),
+            [
+                // `add` declaration
+                4,
+                20,
+                3,
+                ty("function"),
+                DECLARATION,
+                // `sum` declaration, later uses are no declared names
+                2,
+                12,
+                3,
+                ty("variable"),
+                DECLARATION,
+                // `ONE` use
+                0,
+                10,
+                3,
+                ty("static"),
+                0,
+                4,
+                11,
+                6,
+                ty("struct"),
+                DECLARATION,
+                1,
+                7,
+                3,
+                ty("static"),
+                DECLARATION,
+            ],
+        );
+    }
+}
@@ -1,5 +1,30 @@
+pub(crate) mod cache;
+pub(crate) mod dedup;
+pub(crate) mod dependency;
+pub(crate) mod dependency_sources;
+pub(crate) mod diagnostics;
+pub(crate) mod document_cache;
+pub(crate) mod documents;
+pub(crate) mod file_watcher;
+pub(crate) mod hover_cache;
 pub(crate) mod init;
 pub(crate) mod location;
+pub(crate) mod messages;
+pub(crate) mod metrics;
+pub(crate) mod mock;
+pub(crate) mod partial;
 pub(crate) mod progress_guard;
+pub(crate) mod replay;
 pub(crate) mod requests;
+pub(crate) mod retry;
+pub(crate) mod scheduler;
+pub(crate) mod semantic_tokens;
+pub(crate) mod server;
+pub(crate) mod shutdown;
+pub(crate) mod spawn_error;
+pub(crate) mod symbol_cache;
 pub(crate) mod tokens;
+pub(crate) mod transport;
+pub(crate) mod uri;
+pub(crate) mod version;
+pub(crate) mod workspace;
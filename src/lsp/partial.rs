@@ -0,0 +1,350 @@
+//! Partial results of long-running requests.
+//!
+//! Requests can pass a partial result token. The language server may then stream chunks of the result via
+//! `$/progress` before it sends the remaining response. The chunks are assembled into a single response.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use lsp_client::{LspClient, LspError};
+use lsp_types::{
+    DocumentSymbolResponse, Location, NumberOrString, ProgressToken, SemanticTokens,
+    SemanticTokensPartialResult, SemanticTokensResult, WorkspaceSymbolResponse,
+    notification::Notification,
+    request::{DocumentSymbolRequest, References, Request, SemanticTokensFullRequest},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::requests::WorkspaceSymbolRequestExt;
+
+/// `$/progress` notification with a value that is either work done progress or a partial result.
+#[derive(Debug)]
+pub(crate) enum RawProgress {}
+
+impl Notification for RawProgress {
+    type Params = RawProgressParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RawProgressParams {
+    pub(crate) token: ProgressToken,
+    pub(crate) value: Value,
+}
+
+/// Request that supports partial results.
+pub(crate) trait PartialResultRequest: Request {
+    /// Chunk of the result that is sent via `$/progress`.
+    type Chunk: DeserializeOwned + Send;
+
+    fn set_partial_result_token(params: &mut Self::Params, token: ProgressToken);
+
+    /// Combine the chunks (in order) with the final response.
+    fn assemble(chunks: Vec<Self::Chunk>, result: Self::Result) -> Self::Result;
+}
+
+impl PartialResultRequest for SemanticTokensFullRequest {
+    type Chunk = SemanticTokensPartialResult;
+
+    fn set_partial_result_token(params: &mut Self::Params, token: ProgressToken) {
+        params.partial_result_params.partial_result_token = Some(token);
+    }
+
+    fn assemble(chunks: Vec<Self::Chunk>, result: Self::Result) -> Self::Result {
+        if chunks.is_empty() {
+            return result;
+        }
+
+        let mut data = chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.data)
+            .collect::<Vec<_>>();
+        let result_id = match result {
+            None => None,
+            Some(SemanticTokensResult::Tokens(tokens)) => {
+                data.extend(tokens.data);
+                tokens.result_id
+            }
+            Some(SemanticTokensResult::Partial(partial)) => {
+                data.extend(partial.data);
+                None
+            }
+        };
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id,
+            data,
+        }))
+    }
+}
+
+impl PartialResultRequest for References {
+    type Chunk = Vec<Location>;
+
+    fn set_partial_result_token(params: &mut Self::Params, token: ProgressToken) {
+        params.partial_result_params.partial_result_token = Some(token);
+    }
+
+    fn assemble(chunks: Vec<Self::Chunk>, result: Self::Result) -> Self::Result {
+        if chunks.is_empty() {
+            return result;
+        }
+
+        Some(
+            chunks
+                .into_iter()
+                .flatten()
+                .chain(result.into_iter().flatten())
+                .collect(),
+        )
+    }
+}
+
+impl PartialResultRequest for WorkspaceSymbolRequestExt {
+    type Chunk = WorkspaceSymbolResponse;
+
+    fn set_partial_result_token(params: &mut Self::Params, token: ProgressToken) {
+        params.base.partial_result_params.partial_result_token = Some(token);
+    }
+
+    fn assemble(chunks: Vec<Self::Chunk>, result: Self::Result) -> Self::Result {
+        chunks
+            .into_iter()
+            .chain(result)
+            .reduce(|acc, chunk| match (acc, chunk) {
+                (WorkspaceSymbolResponse::Flat(mut acc), WorkspaceSymbolResponse::Flat(chunk)) => {
+                    acc.extend(chunk);
+                    WorkspaceSymbolResponse::Flat(acc)
+                }
+                (
+                    WorkspaceSymbolResponse::Nested(mut acc),
+                    WorkspaceSymbolResponse::Nested(chunk),
+                ) => {
+                    acc.extend(chunk);
+                    WorkspaceSymbolResponse::Nested(acc)
+                }
+                (acc, _) => {
+                    debug!("language server mixes flat and nested workspace symbols, ignore chunk");
+                    acc
+                }
+            })
+    }
+}
+
+impl PartialResultRequest for DocumentSymbolRequest {
+    type Chunk = DocumentSymbolResponse;
+
+    fn set_partial_result_token(params: &mut Self::Params, token: ProgressToken) {
+        params.partial_result_params.partial_result_token = Some(token);
+    }
+
+    fn assemble(chunks: Vec<Self::Chunk>, result: Self::Result) -> Self::Result {
+        chunks
+            .into_iter()
+            .chain(result)
+            .reduce(|acc, chunk| match (acc, chunk) {
+                (DocumentSymbolResponse::Flat(mut acc), DocumentSymbolResponse::Flat(chunk)) => {
+                    acc.extend(chunk);
+                    DocumentSymbolResponse::Flat(acc)
+                }
+                (
+                    DocumentSymbolResponse::Nested(mut acc),
+                    DocumentSymbolResponse::Nested(chunk),
+                ) => {
+                    acc.extend(chunk);
+                    DocumentSymbolResponse::Nested(acc)
+                }
+                (acc, _) => {
+                    debug!("language server mixes flat and nested document symbols, ignore chunk");
+                    acc
+                }
+            })
+    }
+}
+
+/// Routes partial results to the requests that asked for them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PartialResults {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: u64,
+    streams: HashMap<ProgressToken, mpsc::UnboundedSender<Value>>,
+}
+
+impl PartialResults {
+    /// Forward chunk to the request with the given token.
+    ///
+    /// Returns the chunk if the token does not belong to any of our requests, e.g. because it is work done progress.
+    pub(crate) fn forward(&self, token: &ProgressToken, chunk: Value) -> Option<Value> {
+        let state = self.state.lock().expect("not poisoned");
+        match state.streams.get(token) {
+            Some(tx) => {
+                // request may be gone already
+                tx.send(chunk).ok();
+                None
+            }
+            None => Some(chunk),
+        }
+    }
+
+    /// Send request and assemble its partial results.
+    pub(crate) async fn send_request<R>(
+        &self,
+        client: &LspClient,
+        mut params: R::Params,
+    ) -> Result<R::Result, LspError>
+    where
+        R: PartialResultRequest,
+    {
+        let mut stream = self.register();
+        R::set_partial_result_token(&mut params, stream.token.clone());
+
+        let mut chunks = vec![];
+        let mut fut = std::pin::pin!(client.send_request::<R>(params));
+        let result = loop {
+            tokio::select! {
+                biased;
+                Some(chunk) = stream.rx.recv() => {
+                    chunks.push(parse_chunk::<R>(chunk)?);
+                }
+                res = &mut fut => break res?,
+            }
+        };
+
+        // chunks that were received alongside the response
+        while let Ok(chunk) = stream.rx.try_recv() {
+            chunks.push(parse_chunk::<R>(chunk)?);
+        }
+        if !chunks.is_empty() {
+            debug!(
+                method = R::METHOD,
+                chunks = chunks.len(),
+                "assemble partial results"
+            );
+        }
+
+        Ok(R::assemble(chunks, result))
+    }
+
+    fn register(&self) -> PartialStream {
+        let mut state = self.state.lock().expect("not poisoned");
+        let token = NumberOrString::String(format!("common-sense-coder/partial/{}", state.next_id));
+        state.next_id += 1;
+        let (tx, rx) = mpsc::unbounded_channel();
+        state.streams.insert(token.clone(), tx);
+        PartialStream {
+            token,
+            rx,
+            results: self.clone(),
+        }
+    }
+}
+
+fn parse_chunk<R>(chunk: Value) -> Result<R::Chunk, LspError>
+where
+    R: PartialResultRequest,
+{
+    serde_json::from_value(chunk)
+        .map_err(|e| LspError::Jsonrpsee(jsonrpsee::core::client::Error::ParseError(e)))
+}
+
+/// Partial results for a single request, unregisters the token when dropped.
+#[derive(Debug)]
+struct PartialStream {
+    token: ProgressToken,
+    rx: mpsc::UnboundedReceiver<Value>,
+    results: PartialResults,
+}
+
+impl Drop for PartialStream {
+    fn drop(&mut self) {
+        self.results
+            .state
+            .lock()
+            .expect("not poisoned")
+            .streams
+            .remove(&self.token);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{Position, Range, SemanticToken, Uri};
+
+    use super::*;
+
+    fn token(delta_line: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start: 0,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    fn location(line: u32) -> Location {
+        Location {
+            uri: "file:///ws/lib.rs".parse::<Uri>().unwrap(),
+            range: Range {
+                start: Position { line, character: 0 },
+                end: Position { line, character: 0 },
+            },
+        }
+    }
+
+    #[test]
+    fn test_assemble_semantic_tokens() {
+        assert_eq!(SemanticTokensFullRequest::assemble(vec![], None), None);
+        assert_eq!(
+            SemanticTokensFullRequest::assemble(
+                vec![
+                    SemanticTokensPartialResult {
+                        data: vec![token(1)]
+                    },
+                    SemanticTokensPartialResult {
+                        data: vec![token(2)]
+                    },
+                ],
+                Some(SemanticTokensResult::Tokens(SemanticTokens {
+                    result_id: Some("r".to_owned()),
+                    data: vec![token(3)],
+                })),
+            ),
+            Some(SemanticTokensResult::Tokens(SemanticTokens {
+                result_id: Some("r".to_owned()),
+                data: vec![token(1), token(2), token(3)],
+            })),
+        );
+    }
+
+    #[test]
+    fn test_assemble_references() {
+        assert_eq!(References::assemble(vec![], None), None);
+        assert_eq!(
+            References::assemble(vec![vec![location(1)], vec![]], Some(vec![location(2)])),
+            Some(vec![location(1), location(2)]),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward() {
+        let results = PartialResults::default();
+        let other = NumberOrString::Number(1);
+        assert_eq!(results.forward(&other, Value::Null), Some(Value::Null));
+
+        let mut stream = results.register();
+        let token = stream.token.clone();
+        assert_eq!(results.forward(&token, Value::Bool(true)), None);
+        assert_eq!(stream.rx.recv().await, Some(Value::Bool(true)));
+
+        drop(stream);
+        assert_eq!(results.forward(&token, Value::Null), Some(Value::Null));
+    }
+}
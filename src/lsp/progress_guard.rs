@@ -1,24 +1,61 @@
-use std::{collections::HashSet, ops::Deref, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use anyhow::{Context, Result, ensure};
-use futures::Stream;
+use clap::Parser;
+use futures::{FutureExt, Stream, StreamExt};
+use itertools::Itertools;
 use lsp_client::LspClient;
 use lsp_types::{
-    NumberOrString, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
-    WorkDoneProgressEnd, WorkDoneProgressReport, notification::Progress,
+    NumberOrString, WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressEnd,
+    WorkDoneProgressReport, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    request::WorkspaceSymbolRequest,
+};
+use tokio::{
+    sync::watch::{Receiver, Sender, channel},
+    time::Instant,
 };
-use tokio::sync::watch::{Receiver, channel};
 use tokio_stream::wrappers::WatchStream;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+use crate::{ProgrammingLanguageQuirks, TaskManager, cli::parse_secs, lang::Readiness};
 
-use crate::{ProgrammingLanguageQuirks, TaskManager};
+use super::{
+    dedup::DedupClient,
+    partial::{PartialResults, RawProgress, RawProgressParams},
+    retry::RetryCLIConfig,
+};
+
+/// CLI config for the readiness of language servers.
+#[derive(Debug, Clone, Copy, Parser)]
+pub(crate) struct ReadinessCLIConfig {
+    /// Seconds to wait for the initial work (e.g. indexing) of a language server.
+    ///
+    /// Language servers that do not report the expected progress in time are used anyways, `server_status` reports
+    /// what is missing.
+    #[clap(
+        long,
+        default_value = "300",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_LSP_READY_TIMEOUT"
+    )]
+    lsp_ready_timeout: Duration,
+}
 
 /// Allows to wait for in-progress language server tasks.
 #[derive(Debug, Clone)]
 pub(crate) struct ProgressGuard {
     rx_rdy: Receiver<Ready>,
-    rx_evt: Receiver<String>,
-    client: Arc<LspClient>,
+    rx_evt: Receiver<Option<ProgressEvent>>,
+    work_revision: Arc<AtomicU64>,
+    client: DedupClient,
 }
 
 impl ProgressGuard {
@@ -27,85 +64,159 @@ impl ProgressGuard {
         tasks: &mut TaskManager,
         quirks: &Arc<dyn ProgrammingLanguageQuirks>,
         client: Arc<LspClient>,
+        cfg: ReadinessCLIConfig,
+        retry_cfg: RetryCLIConfig,
     ) -> Self {
         let (tx_rdy, rx_rdy) = channel(Ready {
             init: false,
             progress: true,
+            init_timed_out: None,
         });
-        let (tx_evt, rx_evt) = channel(String::new());
+        let (tx_evt, rx_evt) = channel(None);
+        let work_revision = Arc::new(AtomicU64::new(0));
 
-        // HACK: there doesn't seem to be a way to know what progress tokens
-        // to expect initially, so we just have a hard-coded list
-        let mut init_parts = quirks.init_progress_parts();
+        let readiness = quirks.readiness();
 
+        let partial_results = PartialResults::default();
+        let partial_results_captured = partial_results.clone();
         let client_captured = Arc::clone(&client);
+        let work_revision_captured = Arc::clone(&work_revision);
         tasks.spawn(async move |cancel| {
             let client = client_captured;
+            let partial_results = partial_results_captured;
+            let work_revision = work_revision_captured;
             let mut subscription = client
-                .subscribe_to_method::<Progress>()
+                .subscribe_to_method::<RawProgress>()
                 .await
                 .context("subscribe to 'progress'")?;
 
             let mut running = HashSet::new();
 
-            while let Some(res) = tokio::select! {
-                biased;
-                next = subscription.next() => next,
-                _ = cancel.cancelled() => None,
-            } {
-                let progress = res.context("receive progress")?;
-                let ProgressParamsValue::WorkDone(work_done_progress) = progress.value;
+            // The readiness strategy decides when the initial work is done (`init`). For the progress-based strategy
+            // that is re-evaluated with every event, the other strategies set it via a timer or a probe request.
+            let mut init_parts = None;
+            let mut quiescence = None;
+            let mut probe = futures::future::pending().boxed();
+            match readiness {
+                Readiness::Progress { parts } => {
+                    init_parts = Some(parts);
+                }
+                Readiness::Quiescence { duration } => {
+                    quiescence = Some(duration);
+                }
+                Readiness::Probe { query, interval } => {
+                    probe = probe_workspace_symbols(Arc::clone(&client), query, interval).boxed();
+                }
+            }
+            let mut init = init_parts.as_ref().is_some_and(|parts| parts.is_empty());
+            let quiet_timer = tokio::time::sleep(quiescence.unwrap_or_default());
+            let mut quiet_timer = std::pin::pin!(quiet_timer);
+            let init_timer = tokio::time::sleep(cfg.lsp_ready_timeout);
+            let mut init_timer = std::pin::pin!(init_timer);
+            let mut init_timed_out = None;
+
+            // Progress reports that did not match any init part, helps users to configure the right parts.
+            let mut unmatched = BTreeSet::new();
+
+            loop {
+                update_ready(&tx_rdy, Ready {
+                    init,
+                    progress: running.is_empty(),
+                    init_timed_out: init_timed_out.clone(),
+                });
+
+                let progress = tokio::select! {
+                    biased;
+                    next = subscription.next() => match next {
+                        Some(res) => res.context("receive progress")?,
+                        None => break,
+                    },
+                    _ = cancel.cancelled() => break,
+                    _ = &mut quiet_timer, if !init && quiescence.is_some() => {
+                        debug!("no progress for a while, init done");
+                        init = true;
+                        continue;
+                    }
+                    _ = &mut probe, if !init => {
+                        debug!("probe succeeded, init done");
+                        init = true;
+                        continue;
+                    }
+                    _ = &mut init_timer, if !init => {
+                        let missing = init_parts.iter().flatten().cloned().sorted().collect::<Vec<_>>();
+                        warn!(?missing, ?unmatched, "language server did not finish initialization in time, use it anyways");
+                        init = true;
+                        init_timed_out = Some(missing);
+                        continue;
+                    }
+                };
+                let RawProgressParams { token, value } = progress;
+                let Some(value) = partial_results.forward(&token, value) else {
+                    // not work done progress, but still shows that the request advances
+                    tx_evt.send(Some(ProgressEvent::new(token, Phase::Report, None, Some("partial result".to_owned()), None))).ok();
+                    continue;
+                };
+                let work_done_progress = match serde_json::from_value::<WorkDoneProgress>(value) {
+                    Ok(work_done_progress) => work_done_progress,
+                    Err(e) => {
+                        debug!(%e, ?token, "ignore unknown progress");
+                        continue;
+                    }
+                };
+
+                if let Some(duration) = quiescence {
+                    quiet_timer.as_mut().reset(Instant::now() + duration);
+                }
 
                 let evt = match work_done_progress {
                     WorkDoneProgress::Begin(WorkDoneProgressBegin{title, message, percentage, ..}) => {
                         ensure!(
-                            running.insert(progress.token.clone()),
+                            running.insert(token.clone()),
                             "Progress double start: {:?}",
-                            progress.token,
+                            token,
                         );
-                        if let NumberOrString::String(token) = &progress.token {
-                            init_parts.remove(token);
+                        if let Some(init_parts) = &mut init_parts {
+                            let mut matched = false;
+                            if let NumberOrString::String(name) = &token {
+                                matched |= init_parts.remove(name);
+                            }
+                            matched |= init_parts.remove(&title);
+                            if !matched && !init {
+                                unmatched.insert(format_token_and_title(&token, &title));
+                            }
+                            // stays ready if the init timeout was already hit
+                            init |= init_parts.is_empty();
+                            if let Some(missing) = &mut init_timed_out {
+                                missing.retain(|part| init_parts.contains(part));
+                            }
                         }
-                        debug!(phase="start", token=?progress.token, running=running.len(), to_init=init_parts.len(), "progress");
+                        debug!(phase="start", ?token, running=running.len(), init, "progress");
 
-                        format_event(&progress.token, "start", Some(title), message, percentage)
+                        ProgressEvent::new(token, Phase::Start, Some(title), message, percentage)
                     }
                     WorkDoneProgress::Report(WorkDoneProgressReport { message, percentage, .. }) => {
-                        format_event(&progress.token, "progress", None, message, percentage)
+                        ProgressEvent::new(token, Phase::Report, None, message, percentage)
                     }
                     WorkDoneProgress::End(WorkDoneProgressEnd { message }) => {
                         ensure!(
-                            running.remove(&progress.token),
+                            running.remove(&token),
                             "Progress end without start: {:?}",
-                            progress.token,
+                            token,
                         );
-                        debug!(phase="end", token=?progress.token, running=running.len(), to_init=init_parts.len(), "progress");
-                        format_event(&progress.token, "end", None, message, None)
+                        if running.is_empty() {
+                            work_revision.fetch_add(1, Ordering::Relaxed);
+                        }
+                        debug!(phase="end", ?token, running=running.len(), init, "progress");
+                        ProgressEvent::new(token, Phase::End, None, message, None)
                     }
                 };
-                tx_evt.send(evt).ok();
-
-                let new_rdy = Ready {
-                    init: init_parts.is_empty(),
+                // listeners of the event must not see the readiness from before it
+                update_ready(&tx_rdy, Ready {
+                    init,
                     progress: running.is_empty(),
-                };
-                tx_rdy.send_if_modified(|rdy| {
-                    if rdy != &new_rdy {
-                        let flag_changed = rdy.ready() != new_rdy.ready();
-
-                        *rdy = new_rdy;
-
-                        if flag_changed {
-                            info!(progrss=rdy.progress, init=rdy.init, ready=rdy.ready(), "ready changed");
-                        } else {
-                            debug!(progrss=rdy.progress, init=rdy.init, ready=rdy.ready(), "ready changed");
-                        }
-
-                        true
-                    } else {
-                        false
-                    }
+                    init_timed_out: init_timed_out.clone(),
                 });
+                tx_evt.send(Some(evt)).ok();
             }
 
             subscription.unsubscribe().await.context("unsubscribe progress")?;
@@ -116,13 +227,42 @@ impl ProgressGuard {
         Self {
             rx_rdy,
             rx_evt,
-            client,
+            work_revision,
+            client: DedupClient::new(client, partial_results, retry_cfg),
         }
     }
 
     /// A stream of progress events.
-    pub(crate) fn events(&self) -> impl Stream<Item = String> {
-        WatchStream::from_changes(self.rx_evt.clone())
+    ///
+    /// Only yields events that happen after this call.
+    pub(crate) fn events(&self) -> impl Stream<Item = ProgressEvent> + use<> {
+        let mut rx_evt = self.rx_evt.clone();
+        // the clone inherits the last seen version, which is not updated for the stored receiver
+        rx_evt.borrow_and_update();
+        WatchStream::from_changes(rx_evt).filter_map(futures::future::ready)
+    }
+
+    /// Increases whenever the language server becomes idle after some work, e.g. re-indexing after files changed.
+    pub(crate) fn work_revision(&self) -> u64 {
+        self.work_revision.load(Ordering::Relaxed)
+    }
+
+    /// Client, without waiting for outstanding tasks.
+    pub(crate) fn client(&self) -> &DedupClient {
+        &self.client
+    }
+
+    /// Checks if there are no outstanding tasks, without waiting.
+    pub(crate) fn is_ready(&self) -> bool {
+        self.rx_rdy.borrow().ready()
+    }
+
+    /// Missing parts of the initialization if the language server did not finish it in time.
+    ///
+    /// Parts that started late are removed. The parts are empty if the readiness strategy does not wait for progress
+    /// reports.
+    pub(crate) fn init_timed_out(&self) -> Option<Vec<String>> {
+        self.rx_rdy.borrow().init_timed_out.clone()
     }
 
     /// Wait for all outstanding tasks.
@@ -142,34 +282,155 @@ pub(crate) struct Guard<'a> {
 }
 
 impl Deref for Guard<'_> {
-    type Target = LspClient;
+    type Target = DedupClient;
 
     fn deref(&self) -> &Self::Target {
-        self.process_guard.client.as_ref()
+        &self.process_guard.client
+    }
+}
+
+/// Repeat `workspace/symbol` request until it returns at least one symbol.
+async fn probe_workspace_symbols(client: Arc<LspClient>, query: String, interval: Duration) {
+    loop {
+        let res = client
+            .send_request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+                query: query.clone(),
+                ..Default::default()
+            })
+            .await;
+        match res {
+            Ok(Some(WorkspaceSymbolResponse::Flat(symbols))) if !symbols.is_empty() => return,
+            Ok(Some(WorkspaceSymbolResponse::Nested(symbols))) if !symbols.is_empty() => return,
+            Ok(_) => {
+                debug!("probe returned no symbols");
+            }
+            Err(e) => {
+                debug!(%e, "probe failed");
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Describe progress report for users that want to configure init parts.
+fn format_token_and_title(token: &NumberOrString, title: &str) -> String {
+    match token {
+        NumberOrString::String(token) => format!("{token} ({title})"),
+        NumberOrString::Number(_) => title.to_owned(),
     }
 }
 
+fn update_ready(tx_rdy: &Sender<Ready>, new_rdy: Ready) {
+    tx_rdy.send_if_modified(|rdy| {
+        if rdy != &new_rdy {
+            let flag_changed = rdy.ready() != new_rdy.ready();
+
+            *rdy = new_rdy;
+
+            if flag_changed {
+                info!(
+                    progrss = rdy.progress,
+                    init = rdy.init,
+                    ready = rdy.ready(),
+                    "ready changed"
+                );
+            } else {
+                debug!(
+                    progrss = rdy.progress,
+                    init = rdy.init,
+                    ready = rdy.ready(),
+                    "ready changed"
+                );
+            }
+
+            true
+        } else {
+            false
+        }
+    });
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct Ready {
     init: bool,
     progress: bool,
+
+    /// Initialization parts that were still missing when [`ReadinessCLIConfig::lsp_ready_timeout`] was hit and that
+    /// did not start since.
+    init_timed_out: Option<Vec<String>>,
 }
 
 impl Ready {
     fn ready(&self) -> bool {
-        let Self { init, progress } = self;
+        let Self {
+            init,
+            progress,
+            init_timed_out: _,
+        } = self;
         *init && *progress
     }
 }
 
+/// Phase of a language server task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Start,
+    Report,
+    End,
+}
+
+impl Phase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Report => "progress",
+            Self::End => "end",
+        }
+    }
+}
+
+/// Progress event of a language server task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProgressEvent {
+    /// Identifies the task.
+    pub(crate) token: NumberOrString,
+
+    pub(crate) phase: Phase,
+
+    /// Percentage of the task that is done, if the language server reports it.
+    pub(crate) percentage: Option<u32>,
+
+    /// Human-readable description.
+    pub(crate) message: String,
+}
+
+impl ProgressEvent {
+    fn new(
+        token: NumberOrString,
+        phase: Phase,
+        title: Option<String>,
+        message: Option<String>,
+        percentage: Option<u32>,
+    ) -> Self {
+        let message = format_event(&token, phase, title, message, percentage);
+        Self {
+            token,
+            phase,
+            percentage,
+            message,
+        }
+    }
+}
+
 fn format_event(
     token: &NumberOrString,
-    phase: &'static str,
+    phase: Phase,
     title: Option<String>,
     message: Option<String>,
     percentage: Option<u32>,
 ) -> String {
-    let mut parts = vec![phase.to_owned()];
+    let mut parts = vec![phase.as_str().to_owned()];
     if let NumberOrString::String(token) = token {
         parts.push(token.clone());
     }
@@ -184,3 +445,77 @@ fn format_event(
     }
     parts.join(" ")
 }
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use lsp_client::transport::io_transport;
+    use serde_json::json;
+    use tokio::io::duplex;
+
+    use crate::{lang::rust::Rust, lsp::transport::write_messages};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_init_timeout() {
+        // language server that never reports any progress
+        let (client_out, _server_in) = duplex(1024);
+        let (mut server_out, client_in) = duplex(1024);
+        let (sender, receiver) = io_transport(client_out, client_in);
+        let client = Arc::new(LspClient::new(sender, receiver));
+
+        let mut tasks = TaskManager::new();
+        let quirks = Arc::new(Rust::default()) as Arc<dyn ProgrammingLanguageQuirks>;
+        let guard = ProgressGuard::start(
+            &mut tasks,
+            &quirks,
+            client,
+            ReadinessCLIConfig {
+                lsp_ready_timeout: Duration::from_millis(10),
+            },
+            RetryCLIConfig::parse_from([""]),
+        );
+        assert!(!guard.is_ready());
+        assert_eq!(guard.init_timed_out(), None);
+
+        guard.wait().await;
+        assert!(guard.is_ready());
+        assert_eq!(
+            guard.init_timed_out().unwrap(),
+            [
+                "rust-analyzer/flycheck/0",
+                "rustAnalyzer/Building CrateGraph",
+                "rustAnalyzer/Roots Scanned",
+                "rustAnalyzer/cachePriming",
+            ],
+        );
+
+        // late progress does not reset readiness
+        let mut events = std::pin::pin!(guard.events());
+        for value in [
+            json!({"kind": "begin", "title": "Roots Scanned"}),
+            json!({"kind": "end"}),
+        ] {
+            let msg = json!({
+                "jsonrpc": "2.0",
+                "method": "$/progress",
+                "params": {"token": "rustAnalyzer/Roots Scanned", "value": value},
+            });
+            write_messages(&mut server_out, &[msg]).await.unwrap();
+            events.next().await.unwrap();
+        }
+        guard.wait().await;
+        assert!(guard.is_ready());
+        assert_eq!(
+            guard.init_timed_out().unwrap(),
+            [
+                "rust-analyzer/flycheck/0",
+                "rustAnalyzer/Building CrateGraph",
+                "rustAnalyzer/cachePriming",
+            ],
+        );
+
+        tasks.shutdown().await.unwrap();
+    }
+}
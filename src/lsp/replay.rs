@@ -0,0 +1,392 @@
+//! Replay captured language server traffic.
+//!
+//! Acts as a language server that answers with the messages recorded via `--intercept-io-format=jsonl`. This
+//! reproduces bugs in decoding, progress handling, and tool formatting without running the real language server.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tracing::{debug, info, warn};
+
+use crate::io_intercept::Direction;
+
+use super::transport::{read_message, write_messages};
+
+/// JSON-RPC error code for requests that failed.
+const REQUEST_FAILED: i64 = -32803;
+
+/// CLI config for replaying language server traffic.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct ReplayCLIConfig {
+    /// Act as a language server that replays a capture of `--intercept-io-format=jsonl`, then exit.
+    ///
+    /// Use it as the language server of another instance, e.g. `--lsp-command=common-sense-coder
+    /// --lsp-arg=--lsp-replay=io.jsonl`. Requests are answered with the recorded response for the same method and
+    /// parameters, followed by the messages the language server sent afterwards. Workspace paths are rewritten.
+    #[clap(long)]
+    lsp_replay: Option<PathBuf>,
+}
+
+impl ReplayCLIConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.lsp_replay.is_some()
+    }
+
+    /// Replay capture via stdin/stdout.
+    pub(crate) async fn serve(&self) -> Result<()> {
+        let Some(path) = &self.lsp_replay else {
+            return Ok(());
+        };
+        info!(path=%path.display(), "replay language server");
+
+        let capture = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("read capture: {}", path.display()))?;
+        replay(
+            &capture,
+            BufReader::new(tokio::io::stdin()),
+            tokio::io::stdout(),
+        )
+        .await
+    }
+}
+
+/// Entry of the JSONL capture.
+#[derive(Debug, Deserialize)]
+struct CaptureEntry {
+    peer: String,
+    direction: Direction,
+    message: Option<Value>,
+}
+
+/// Message of the client and what the language server sent in return.
+#[derive(Debug)]
+struct Exchange {
+    method: String,
+
+    /// Serialized parameters.
+    params: String,
+
+    /// Request ID, `None` for notifications.
+    id: Option<Value>,
+
+    /// Messages of the language server in recorded order, including the response.
+    messages: Vec<Value>,
+
+    replayed: bool,
+}
+
+impl Exchange {
+    fn is_response(&self, msg: &Value) -> bool {
+        self.id.is_some() && msg.get("method").is_none() && msg.get("id") == self.id.as_ref()
+    }
+}
+
+/// Recorded session of a single language server.
+#[derive(Debug)]
+struct Recording {
+    /// Messages of the language server before the first client message.
+    preamble: Vec<Value>,
+
+    exchanges: Vec<Exchange>,
+}
+
+impl Recording {
+    /// Parse JSONL capture.
+    ///
+    /// The capture may contain several peers, the first language server is used.
+    fn parse(capture: &str) -> Result<Self> {
+        let mut peer = None;
+        let mut preamble = vec![];
+        let mut exchanges = Vec::<Exchange>::new();
+
+        for (idx, line) in capture.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str::<CaptureEntry>(line)
+                .with_context(|| format!("parse capture line {}", idx + 1))?;
+            if !entry.peer.starts_with("lsp.") {
+                continue;
+            }
+            let peer = peer.get_or_insert_with(|| entry.peer.clone());
+            if &entry.peer != peer {
+                continue;
+            }
+            // unparseable data cannot be replayed
+            let Some(msg) = entry.message else {
+                continue;
+            };
+
+            match entry.direction {
+                Direction::Sent => {
+                    // responses to requests of the language server are answered live
+                    let Some(method) = msg.get("method").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    exchanges.push(Exchange {
+                        method: method.to_owned(),
+                        params: params_key(&msg),
+                        id: msg.get("id").cloned(),
+                        messages: vec![],
+                        replayed: false,
+                    });
+                }
+                Direction::Received => {
+                    let exchange = if msg.get("method").is_none() {
+                        // responses belong to their request, even if other messages were sent in between
+                        let Some(exchange) = exchanges.iter_mut().find(|ex| ex.is_response(&msg))
+                        else {
+                            debug!(%msg, "response without request");
+                            continue;
+                        };
+                        Some(exchange)
+                    } else {
+                        exchanges.last_mut()
+                    };
+                    match exchange {
+                        Some(exchange) => exchange.messages.push(msg),
+                        None => preamble.push(msg),
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            preamble,
+            exchanges,
+        })
+    }
+
+    /// Messages to answer a client message with.
+    ///
+    /// Returns `None` for requests without recorded response.
+    fn answer(&mut self, msg: &Value) -> Option<Vec<Value>> {
+        let method = msg.get("method").and_then(Value::as_str)?;
+        let params = params_key(msg);
+        let id = msg.get("id");
+
+        let fresh = |ex: &&mut Exchange| !ex.replayed && ex.method == method;
+        let exchange = if let Some(pos) = self
+            .exchanges
+            .iter()
+            .position(|ex| !ex.replayed && ex.method == method && ex.params == params)
+        {
+            &mut self.exchanges[pos]
+        } else if let Some(pos) = self
+            .exchanges
+            .iter()
+            .rposition(|ex| ex.replayed && ex.method == method && ex.params == params)
+        {
+            // repeated message, only the response is sent again
+            let exchange = &self.exchanges[pos];
+            return Some(
+                exchange
+                    .messages
+                    .iter()
+                    .filter(|m| exchange.is_response(m))
+                    .map(|m| with_id(m, id))
+                    .collect(),
+            );
+        } else if let Some(exchange) = self.exchanges.iter_mut().find(fresh) {
+            warn!(
+                method,
+                "no recording with matching parameters, use next one"
+            );
+            exchange
+        } else {
+            return id.is_none().then(Vec::new);
+        };
+
+        exchange.replayed = true;
+        let exchange = &*exchange;
+        Some(
+            exchange
+                .messages
+                .iter()
+                .map(|m| {
+                    if exchange.is_response(m) {
+                        with_id(m, id)
+                    } else {
+                        m.clone()
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Replay capture to a client.
+///
+/// Workspace folders of the recording are replaced by the ones of the live `initialize` request.
+async fn replay(
+    capture: &str,
+    mut input: impl AsyncBufRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let mut recording = None;
+
+    loop {
+        let msg = match read_message(&mut input).await {
+            Ok(msg) => msg,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context("read message"),
+        };
+        let msg = serde_json::from_slice::<Value>(&msg).context("parse message")?;
+        let Some(method) = msg.get("method").and_then(Value::as_str) else {
+            // response to one of our requests
+            continue;
+        };
+        debug!(method, "replay");
+
+        let recording = match &mut recording {
+            Some(recording) => recording,
+            None => {
+                let capture = rewrite_folders(capture, &msg)?;
+                let recording = recording.insert(Recording::parse(&capture)?);
+                write_messages(&mut output, &recording.preamble).await?;
+                recording
+            }
+        };
+
+        match recording.answer(&msg) {
+            Some(messages) => write_messages(&mut output, &messages).await?,
+            None => {
+                let resp = if method == "shutdown" {
+                    json!({"jsonrpc": "2.0", "id": msg["id"], "result": null})
+                } else {
+                    warn!(method, "no recorded response");
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": msg["id"],
+                        "error": {
+                            "code": REQUEST_FAILED,
+                            "message": format!("no recorded response for {method}"),
+                        },
+                    })
+                };
+                write_messages(&mut output, &[resp]).await?;
+            }
+        }
+
+        if method == "exit" {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace workspace folders of the recording with the ones of the live `initialize` request.
+fn rewrite_folders(capture: &str, initialize: &Value) -> Result<String> {
+    let recorded = capture
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CaptureEntry>(line).ok())
+        .filter(|entry| entry.direction == Direction::Sent)
+        .filter_map(|entry| entry.message)
+        .find(|msg| msg["method"] == "initialize")
+        .context("capture does not contain an `initialize` request")?;
+
+    let mut capture = capture.to_owned();
+    for (from, to) in folder_uris(&recorded).iter().zip(folder_uris(initialize)) {
+        if from != &to {
+            debug!(from, to, "rewrite workspace folder");
+            capture = capture.replace(from, &to);
+        }
+    }
+    Ok(capture)
+}
+
+/// URIs of the workspace folders of an `initialize` request.
+fn folder_uris(initialize: &Value) -> Vec<String> {
+    let params = &initialize["params"];
+    match params["workspaceFolders"].as_array() {
+        Some(folders) => folders
+            .iter()
+            .filter_map(|folder| folder["uri"].as_str().map(ToOwned::to_owned))
+            .collect(),
+        None => params["rootUri"]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn params_key(msg: &Value) -> String {
+    msg.get("params").map(Value::to_string).unwrap_or_default()
+}
+
+fn with_id(msg: &Value, id: Option<&Value>) -> Value {
+    let mut msg = msg.clone();
+    msg["id"] = id.cloned().unwrap_or_default();
+    msg
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recording() {
+        let capture = [
+            json!({"peer": "mcp", "direction": "received", "message": {"jsonrpc": "2.0", "id": 0, "method": "initialize"}}),
+            json!({"peer": "lsp.rust", "direction": "sent", "message": {"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {"workspaceFolders": [{"uri": "file:///old", "name": "ws"}]}}}),
+            json!({"peer": "lsp.rust", "direction": "received", "message": {"jsonrpc": "2.0", "id": 0, "result": {"capabilities": {}}}}),
+            json!({"peer": "lsp.rust", "direction": "sent", "message": {"jsonrpc": "2.0", "method": "initialized", "params": {}}}),
+            json!({"peer": "lsp.rust", "direction": "received", "message": {"jsonrpc": "2.0", "method": "$/progress", "params": {"token": "x"}}}),
+            json!({"peer": "lsp.rust", "direction": "sent", "message": {"jsonrpc": "2.0", "id": 1, "method": "textDocument/hover", "params": {"uri": "file:///old/src/lib.rs"}}}),
+            json!({"peer": "lsp.rust", "direction": "sent", "message": {"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"uri": "file:///old/src/foo.rs"}}}),
+            json!({"peer": "lsp.rust", "direction": "received", "message": {"jsonrpc": "2.0", "id": 1, "result": "file:///old/src/lib.rs"}}),
+            json!({"peer": "lsp.rust", "direction": "received", "raw": "garbage"}),
+            json!({"peer": "lsp.toml", "direction": "sent", "message": {"jsonrpc": "2.0", "id": 3, "method": "textDocument/hover"}}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        let initialize = json!({"jsonrpc": "2.0", "id": 10, "method": "initialize", "params": {"workspaceFolders": [{"uri": "file:///new", "name": "ws"}]}});
+        let mut recording =
+            Recording::parse(&rewrite_folders(&capture, &initialize).unwrap()).unwrap();
+        assert_eq!(recording.preamble, Vec::<Value>::new());
+
+        assert_eq!(
+            recording.answer(&initialize),
+            Some(vec![
+                json!({"jsonrpc": "2.0", "id": 10, "result": {"capabilities": {}}})
+            ]),
+        );
+        assert_eq!(
+            recording.answer(&json!({"jsonrpc": "2.0", "method": "initialized", "params": {}})),
+            Some(vec![
+                json!({"jsonrpc": "2.0", "method": "$/progress", "params": {"token": "x"}})
+            ]),
+        );
+
+        let hover = |id: u64, file: &str| json!({"jsonrpc": "2.0", "id": id, "method": "textDocument/hover", "params": {"uri": format!("file:///new/src/{file}")}});
+        assert_eq!(
+            recording.answer(&hover(11, "lib.rs")),
+            Some(vec![
+                json!({"jsonrpc": "2.0", "id": 11, "result": "file:///new/src/lib.rs"})
+            ]),
+        );
+        assert_eq!(
+            recording.answer(&hover(12, "lib.rs")),
+            Some(vec![
+                json!({"jsonrpc": "2.0", "id": 12, "result": "file:///new/src/lib.rs"})
+            ]),
+        );
+        // recorded without response
+        assert_eq!(recording.answer(&hover(13, "foo.rs")), Some(vec![]));
+        assert_eq!(recording.answer(&hover(14, "bar.rs")), None);
+
+        assert_eq!(
+            recording.answer(&json!({"jsonrpc": "2.0", "method": "textDocument/didOpen"})),
+            Some(vec![]),
+        );
+    }
+}
@@ -1,5 +1,13 @@
-use lsp_types::request::{Request, WorkspaceSymbolRequest};
+use std::collections::HashMap;
+
+use lsp_types::{
+    GotoDefinitionResponse, LocationLink, Position, Range, TextDocumentIdentifier,
+    TextDocumentPositionParams,
+    notification::Notification,
+    request::{HoverRequest, Request, WorkspaceSymbolRequest},
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// Extended version of [`WorkspaceSymbolRequest`].
 ///
@@ -13,7 +21,7 @@ impl Request for WorkspaceSymbolRequestExt {
     const METHOD: &'static str = <WorkspaceSymbolRequest as Request>::METHOD;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct WorkspaceSymbolParamsExt {
     #[serde(flatten)]
@@ -23,7 +31,7 @@ pub(crate) struct WorkspaceSymbolParamsExt {
     pub(crate) filtering: WorkspaceSymbolScopeKindFiltering,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct WorkspaceSymbolScopeKindFiltering {
     /// Return only the symbols defined in the specified scope.
@@ -35,16 +43,202 @@ pub(crate) struct WorkspaceSymbolScopeKindFiltering {
     pub(crate) search_kind: Option<WorkspaceSymbolSearchKind>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) enum WorkspaceSymbolSearchScope {
     Workspace,
     WorkspaceAndDependencies,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) enum WorkspaceSymbolSearchKind {
     OnlyTypes,
     AllSymbols,
 }
+
+/// Hover request for a range, e.g. to get the type of an expression.
+///
+/// Only sent if the language server announces support, see
+/// <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#hover-range>.
+#[derive(Debug)]
+pub(crate) enum HoverRangeRequest {}
+
+impl Request for HoverRangeRequest {
+    type Params = HoverRangeParams;
+    type Result = <HoverRequest as Request>::Result;
+    const METHOD: &'static str = <HoverRequest as Request>::METHOD;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HoverRangeParams {
+    pub(crate) text_document: TextDocumentIdentifier,
+
+    /// The extension accepts a range where the base request expects a position.
+    pub(crate) position: Range,
+}
+
+/// Request with a method that is only known at runtime.
+///
+/// [`Request::METHOD`] is static, so the request is sent with a placeholder method and the transport replaces the
+/// method and parameters with the ones in [`RawRequestParams`].
+#[derive(Debug)]
+pub(crate) enum RawRequest {}
+
+impl Request for RawRequest {
+    type Params = RawRequestParams;
+    type Result = Value;
+    const METHOD: &'static str = "common-sense-coder/rawRequest";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RawRequestParams {
+    pub(crate) method: String,
+    pub(crate) params: Value,
+}
+
+/// Run the check command (usually `cargo check`) of rust-analyzer.
+///
+/// See <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#controlling-flycheck>.
+#[derive(Debug)]
+pub(crate) enum RunFlycheck {}
+
+impl Notification for RunFlycheck {
+    type Params = RunFlycheckParams;
+    const METHOD: &'static str = "rust-analyzer/runFlycheck";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunFlycheckParams {
+    /// Only check the crates that contain this document, checks the entire workspace if not set.
+    pub(crate) text_document: Option<TextDocumentIdentifier>,
+}
+
+/// Location of the `mod` declaration of the module that contains a position.
+///
+/// See <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#parent-module>.
+#[derive(Debug)]
+pub(crate) enum ParentModule {}
+
+impl Request for ParentModule {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<GotoDefinitionResponse>;
+    const METHOD: &'static str = "experimental/parentModule";
+}
+
+/// Things that rust-analyzer can run in a document, e.g. tests, binaries, or benchmarks.
+///
+/// See <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#runnables>.
+#[derive(Debug)]
+pub(crate) enum Runnables {}
+
+impl Request for Runnables {
+    type Params = RunnablesParams;
+    type Result = Vec<Runnable>;
+    const METHOD: &'static str = "experimental/runnables";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunnablesParams {
+    pub(crate) text_document: TextDocumentIdentifier,
+
+    /// Only return the runnables at this position, returns all runnables of the document if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) position: Option<Position>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Runnable {
+    /// e.g. `test tests::my_test`
+    pub(crate) label: String,
+
+    #[serde(default)]
+    pub(crate) location: Option<LocationLink>,
+
+    /// `cargo` or `shell`, only `cargo` has [`CargoRunnableArgs`].
+    pub(crate) kind: String,
+
+    pub(crate) args: Value,
+}
+
+/// Arguments of a runnable of kind `cargo`.
+///
+/// Fields that only some versions of rust-analyzer send are optional.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct CargoRunnableArgs {
+    pub(crate) environment: HashMap<String, String>,
+    pub(crate) cwd: Option<String>,
+    pub(crate) workspace_root: Option<String>,
+
+    /// Command to use instead of `cargo`, may contain arguments.
+    pub(crate) override_cargo: Option<String>,
+
+    pub(crate) cargo_args: Vec<String>,
+    pub(crate) cargo_extra_args: Vec<String>,
+
+    /// Arguments of the built executable, passed after `--`.
+    pub(crate) executable_args: Vec<String>,
+}
+
+/// Item tree of a document as indexed by rust-analyzer, in a Rust-like text format.
+///
+/// Macro calls are listed but not expanded.
+///
+/// See <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#view-item-tree>.
+#[derive(Debug)]
+pub(crate) enum ViewItemTree {}
+
+impl Request for ViewItemTree {
+    type Params = ViewTreeParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewItemTree";
+}
+
+/// Syntax tree of a document, serialized as JSON (see [`SyntaxNode`]).
+///
+/// See <https://rust-analyzer.github.io/book/contributing/lsp-extensions.html#view-syntax-tree>.
+#[derive(Debug)]
+pub(crate) enum ViewSyntaxTree {}
+
+impl Request for ViewSyntaxTree {
+    type Params = ViewTreeParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewSyntaxTree";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ViewTreeParams {
+    pub(crate) text_document: TextDocumentIdentifier,
+}
+
+/// Node or token of the JSON syntax tree returned by [`ViewSyntaxTree`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SyntaxNode {
+    #[serde(rename = "type")]
+    pub(crate) element: SyntaxElement,
+
+    /// e.g. `FN` or `IDENT`
+    pub(crate) kind: String,
+
+    /// `[byte offset, 0-based line, 0-based column]`
+    pub(crate) start: [u32; 3],
+
+    /// `[byte offset, 0-based line, 0-based column]`, exclusive
+    pub(crate) end: [u32; 3],
+
+    /// Empty for tokens.
+    #[serde(default)]
+    pub(crate) children: Vec<Self>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum SyntaxElement {
+    Node,
+    Token,
+}
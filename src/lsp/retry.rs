@@ -0,0 +1,120 @@
+//! Retry requests that failed due to transient language server errors.
+//!
+//! Language servers cancel requests whose result would be outdated, e.g. rust-analyzer while it re-indexes. Those
+//! requests usually succeed shortly after.
+use std::time::Duration;
+
+use lsp_client::LspError;
+use tracing::debug;
+
+use crate::cli::parse_secs;
+
+/// The server detected that the content of a document got modified outside normal conditions.
+const CONTENT_MODIFIED: i32 = -32801;
+
+/// The server cancelled the request.
+const SERVER_CANCELLED: i32 = -32802;
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct RetryCLIConfig {
+    /// How often a request is retried if the language server cancelled it, e.g. because the content was modified.
+    #[clap(long, default_value_t = 3, env = "COMMON_SENSE_CODER_LSP_RETRIES")]
+    lsp_retries: u32,
+
+    /// Delay (in seconds) before the first retry, doubled for every further retry.
+    #[clap(
+        long,
+        default_value = "0.1",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_LSP_RETRY_BACKOFF"
+    )]
+    lsp_retry_backoff: Duration,
+}
+
+impl RetryCLIConfig {
+    /// Perform request, retrying transient errors.
+    pub(crate) async fn run<T, F, Fut>(&self, method: &str, mut f: F) -> Result<T, LspError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LspError>>,
+    {
+        let mut backoff = self.lsp_retry_backoff;
+        let mut retries = 0;
+        loop {
+            match f().await {
+                Err(e) if retries < self.lsp_retries && is_transient(&e) => {
+                    retries += 1;
+                    debug!(method, retries, %e, "transient error, retry request");
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Request failed because the language server cancelled it, a retry may succeed.
+fn is_transient(e: &LspError) -> bool {
+    match e {
+        LspError::Jsonrpsee(jsonrpsee::core::client::Error::Call(e)) => {
+            matches!(e.code(), CONTENT_MODIFIED | SERVER_CANCELLED)
+        }
+        LspError::Jsonrpsee(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use jsonrpsee::{core::client::Error, types::ErrorObjectOwned};
+
+    use super::*;
+
+    fn error(code: i32) -> LspError {
+        LspError::Jsonrpsee(Error::Call(ErrorObjectOwned::owned(
+            code, "error", None::<()>,
+        )))
+    }
+
+    /// Run request that fails with the given error codes first.
+    async fn run(errors: &[i32]) -> (Result<(), LspError>, u32) {
+        let calls = AtomicU32::new(0);
+        let cfg = RetryCLIConfig {
+            lsp_retries: 3,
+            lsp_retry_backoff: Duration::ZERO,
+        };
+        let res = cfg
+            .run("test", async || {
+                let call = calls.fetch_add(1, Ordering::SeqCst) as usize;
+                match errors.get(call) {
+                    Some(code) => Err(error(*code)),
+                    None => Ok(()),
+                }
+            })
+            .await;
+        (res, calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let (res, calls) = run(&[]).await;
+        res.unwrap();
+        assert_eq!(calls, 1);
+
+        let (res, calls) = run(&[CONTENT_MODIFIED, SERVER_CANCELLED]).await;
+        res.unwrap();
+        assert_eq!(calls, 3);
+
+        // too many retries
+        let (res, calls) = run(&[CONTENT_MODIFIED; 4]).await;
+        res.unwrap_err();
+        assert_eq!(calls, 4);
+
+        // not transient
+        let (res, calls) = run(&[-32603]).await;
+        res.unwrap_err();
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,326 @@
+//! Limits how much work is sent to a language server at the same time.
+//!
+//! A burst of tool calls would otherwise hit the language server at once and make all of them slow. Tool calls queue
+//! up for a permit instead, higher priorities first and FIFO within the same priority.
+use std::{
+    collections::BinaryHeap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use clap::Parser;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// CLI config for the request scheduler.
+#[derive(Debug, Clone, Copy, Parser)]
+pub(crate) struct SchedulerCLIConfig {
+    /// Maximum number of tool calls that are processed by a language server at the same time.
+    ///
+    /// Further tool calls are queued, e.g. symbol lookups are preferred over completions.
+    #[clap(
+        long,
+        default_value = "4",
+        env = "COMMON_SENSE_CODER_LSP_MAX_CONCURRENCY"
+    )]
+    lsp_max_concurrency: NonZeroUsize,
+}
+
+impl Default for SchedulerCLIConfig {
+    fn default() -> Self {
+        Self {
+            lsp_max_concurrency: NonZeroUsize::new(4).expect("not zero"),
+        }
+    }
+}
+
+impl SchedulerCLIConfig {
+    /// Create scheduler for one language server.
+    pub(crate) fn scheduler(&self) -> Scheduler {
+        Scheduler::new(self.lsp_max_concurrency)
+    }
+
+    /// Apply the concurrency limit to an existing scheduler.
+    pub(crate) fn apply(&self, scheduler: &Scheduler) {
+        scheduler.set_max(self.lsp_max_concurrency);
+    }
+}
+
+/// Priority of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Priority {
+    /// Background work like completions, which are requested for every keystroke and are outdated quickly.
+    Low,
+
+    /// Expensive tool calls that issue many requests.
+    Normal,
+
+    /// Cheap tool calls.
+    High,
+}
+
+/// Queue and concurrency limit of a single language server.
+#[derive(Debug, Clone)]
+pub(crate) struct Scheduler {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    max: usize,
+    running: usize,
+
+    /// Sequence number of the next waiter, used for FIFO order within a priority.
+    seq: u64,
+
+    queue: BinaryHeap<Waiter>,
+}
+
+/// Tool call that waits for a permit.
+#[derive(Debug)]
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    tx: oneshot::Sender<Permit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // max-heap: higher priority first, then lower sequence number
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl Scheduler {
+    pub(crate) fn new(max: NonZeroUsize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                max: max.get(),
+                running: 0,
+                seq: 0,
+                queue: BinaryHeap::new(),
+            })),
+        }
+    }
+
+    /// Wait for a permit, the work may run as long as it is held.
+    ///
+    /// Cancel-safe: dropping the future before it finished does not leak a permit.
+    pub(crate) async fn acquire(&self, priority: Priority) -> Permit {
+        let rx = {
+            let mut state = self.state.lock().expect("not poisoned");
+            if state.running < state.max && state.queue.is_empty() {
+                state.running += 1;
+                return Permit {
+                    state: Some(Arc::clone(&self.state)),
+                };
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let seq = state.seq;
+            state.seq += 1;
+            state.queue.push(Waiter { priority, seq, tx });
+            debug!(?priority, queued = state.queue.len(), "queue tool call");
+            rx
+        };
+
+        rx.await
+            .expect("permits are never dropped without handing them over")
+    }
+
+    /// Change the concurrency limit, e.g. after the config was reloaded.
+    ///
+    /// Tool calls that hold a permit keep it, a lower limit applies once they finished.
+    pub(crate) fn set_max(&self, max: NonZeroUsize) {
+        let mut state = self.state.lock().expect("not poisoned");
+        state.max = max.get();
+        while state.running < state.max {
+            let Some(waiter) = state.queue.pop() else {
+                break;
+            };
+            let permit = Permit {
+                state: Some(Arc::clone(&self.state)),
+            };
+            match waiter.tx.send(permit) {
+                Ok(()) => {
+                    state.running += 1;
+                }
+                Err(mut permit) => {
+                    // waiter is gone, the permit must not hand over again while we hold the lock
+                    permit.state = None;
+                }
+            }
+        }
+    }
+
+    /// Number of tool calls that hold a permit.
+    pub(crate) fn running(&self) -> usize {
+        self.state.lock().expect("not poisoned").running
+    }
+
+    /// Number of tool calls that wait for a permit.
+    pub(crate) fn queued(&self) -> usize {
+        self.state.lock().expect("not poisoned").queue.len()
+    }
+}
+
+/// Allows a tool call to use the language server, hands over to the next waiter when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    state: Option<Arc<Mutex<State>>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Some(state_arc) = self.state.take() else {
+            return;
+        };
+
+        let mut state = state_arc.lock().expect("not poisoned");
+        // only hand over if the limit was not lowered in the meantime
+        while state.running <= state.max
+            && let Some(waiter) = state.queue.pop()
+        {
+            let permit = Self {
+                state: Some(Arc::clone(&state_arc)),
+            };
+            match waiter.tx.send(permit) {
+                Ok(()) => return,
+                Err(mut permit) => {
+                    // waiter is gone, the permit must not hand over again while we hold the lock
+                    permit.state = None;
+                }
+            }
+        }
+        state.running -= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+
+    use super::*;
+
+    fn scheduler(max: usize) -> Scheduler {
+        Scheduler::new(NonZeroUsize::new(max).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_limit() {
+        let scheduler = scheduler(2);
+        let p1 = scheduler.acquire(Priority::Normal).await;
+        let _p2 = scheduler.acquire(Priority::Normal).await;
+        assert_eq!(scheduler.running(), 2);
+
+        let mut fut = std::pin::pin!(scheduler.acquire(Priority::Normal));
+        assert!(fut.as_mut().now_or_never().is_none());
+        assert_eq!(scheduler.queued(), 1);
+
+        drop(p1);
+        assert_eq!(scheduler.queued(), 0);
+        let _p3 = fut.await;
+        assert_eq!(scheduler.running(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_max() {
+        let scheduler = scheduler(1);
+        let p1 = scheduler.acquire(Priority::Normal).await;
+
+        let mut fut2 = std::pin::pin!(scheduler.acquire(Priority::Normal));
+        let mut fut3 = std::pin::pin!(scheduler.acquire(Priority::Normal));
+        assert!(fut2.as_mut().now_or_never().is_none());
+        assert!(fut3.as_mut().now_or_never().is_none());
+        assert_eq!(scheduler.queued(), 2);
+
+        // raising the limit hands out permits right away
+        scheduler.set_max(NonZeroUsize::new(2).unwrap());
+        assert_eq!((scheduler.running(), scheduler.queued()), (2, 1));
+        let p2 = fut2.await;
+
+        // lowering the limit waits for running tool calls
+        scheduler.set_max(NonZeroUsize::new(1).unwrap());
+        drop(p1);
+        assert_eq!((scheduler.running(), scheduler.queued()), (1, 1));
+        drop(p2);
+        assert_eq!((scheduler.running(), scheduler.queued()), (1, 0));
+        let _p3 = fut3.await;
+    }
+
+    #[tokio::test]
+    async fn test_priority() {
+        let scheduler = scheduler(1);
+        let p = scheduler.acquire(Priority::Normal).await;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handles = vec![];
+        for (name, priority) in [
+            ("low", Priority::Low),
+            ("normal1", Priority::Normal),
+            ("high", Priority::High),
+            ("normal2", Priority::Normal),
+        ] {
+            let scheduler_captured = scheduler.clone();
+            let tx = tx.clone();
+            handles.push(tokio::spawn(async move {
+                let _p = scheduler_captured.acquire(priority).await;
+                tx.send(name).unwrap();
+            }));
+            // enqueue in order
+            while scheduler.queued() < handles.len() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+        drop(tx);
+
+        drop(p);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let mut order = vec![];
+        while let Some(name) = rx.recv().await {
+            order.push(name);
+        }
+        assert_eq!(order, ["high", "normal1", "normal2", "low"]);
+        assert_eq!(scheduler.running(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let scheduler = scheduler(1);
+        let p = scheduler.acquire(Priority::Normal).await;
+
+        let fut = scheduler.acquire(Priority::Normal);
+        let mut fut = Box::pin(fut);
+        assert!(fut.as_mut().now_or_never().is_none());
+        drop(fut);
+        assert_eq!(scheduler.queued(), 1);
+
+        // cancelled waiter is skipped
+        drop(p);
+        assert_eq!(scheduler.queued(), 0);
+        assert_eq!(scheduler.running(), 0);
+
+        let _p = scheduler.acquire(Priority::Low).await;
+        assert_eq!(scheduler.running(), 1);
+    }
+}
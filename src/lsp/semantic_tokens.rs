@@ -0,0 +1,262 @@
+//! Fetch semantic tokens, using delta requests for documents that were fetched before.
+//!
+//! Tool calls often query the same files repeatedly. For large files, transferring only the changed tokens saves
+//! bandwidth and language server CPU.
+use std::sync::Arc;
+
+use anyhow::{Result, ensure};
+use lsp_client::LspError;
+use lsp_types::{
+    SemanticToken, SemanticTokensDeltaParams, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensParams, SemanticTokensResult, TextDocumentIdentifier, Uri,
+    request::{SemanticTokensFullDeltaRequest, SemanticTokensFullRequest},
+};
+use tracing::debug;
+
+use super::{
+    cache::{CacheRegistry, CacheStats, Lru},
+    dedup::DedupClient,
+};
+
+/// Number of integers that encode a single token.
+const TOKEN_LEN: usize = 5;
+
+/// Semantic tokens of a single language server.
+#[derive(Debug)]
+pub(crate) struct SemanticTokens {
+    /// Language server supports delta requests.
+    delta: bool,
+
+    /// Tokens of the last response per document, keyed by URI.
+    cache: Arc<Lru<Uri, CachedTokens>>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedTokens {
+    result_id: String,
+    data: Arc<[SemanticToken]>,
+}
+
+impl SemanticTokens {
+    pub(crate) fn new(delta: bool, registry: &CacheRegistry) -> Self {
+        Self {
+            delta,
+            cache: registry.lru(),
+        }
+    }
+
+    /// Documents whose tokens are kept for delta requests.
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Get semantic tokens of the entire document.
+    ///
+    /// Returns `None` if the language server did not provide any tokens.
+    pub(crate) async fn fetch(
+        &self,
+        client: &DedupClient,
+        text_document: TextDocumentIdentifier,
+    ) -> Result<Option<Arc<[SemanticToken]>>, Arc<LspError>> {
+        let cached = self
+            .delta
+            .then(|| self.cache.get(&text_document.uri, |_cached| true))
+            .flatten();
+        if let Some(cached) = cached {
+            let resp = client
+                .send_request::<SemanticTokensFullDeltaRequest>(SemanticTokensDeltaParams {
+                    text_document: text_document.clone(),
+                    previous_result_id: cached.result_id,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await?;
+            let res = match resp {
+                None => None,
+                Some(SemanticTokensFullDeltaResult::Tokens(tokens)) => {
+                    Some((tokens.result_id, tokens.data.into()))
+                }
+                Some(SemanticTokensFullDeltaResult::TokensDelta(delta)) => {
+                    match apply_edits(&cached.data, delta.edits) {
+                        Ok(data) => Some((delta.result_id, data)),
+                        Err(e) => {
+                            debug!(%e, "cannot apply semantic token edits, fetch full document");
+                            None
+                        }
+                    }
+                }
+                Some(SemanticTokensFullDeltaResult::PartialTokensDelta { edits }) => {
+                    // no result ID to base further deltas on
+                    match apply_edits(&cached.data, edits) {
+                        Ok(data) => Some((None, data)),
+                        Err(e) => {
+                            debug!(%e, "cannot apply semantic token edits, fetch full document");
+                            None
+                        }
+                    }
+                }
+            };
+            if let Some((result_id, data)) = res {
+                self.store(text_document.uri, result_id, Arc::clone(&data));
+                return Ok(Some(data));
+            }
+        }
+
+        let resp = client
+            .send_request_partial::<SemanticTokensFullRequest>(SemanticTokensParams {
+                text_document: text_document.clone(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await?;
+        let (result_id, data) = match resp {
+            None => {
+                self.store(text_document.uri, None, Arc::from([]));
+                return Ok(None);
+            }
+            Some(SemanticTokensResult::Tokens(tokens)) => (tokens.result_id, tokens.data),
+            Some(SemanticTokensResult::Partial(partial)) => (None, partial.data),
+        };
+        let data = Arc::<[SemanticToken]>::from(data);
+        self.store(text_document.uri, result_id, Arc::clone(&data));
+        Ok(Some(data))
+    }
+
+    /// Remember tokens for the next delta request, or forget them if there is no result ID.
+    fn store(&self, uri: Uri, result_id: Option<String>, data: Arc<[SemanticToken]>) {
+        if !self.delta {
+            return;
+        }
+
+        let Some(result_id) = result_id else {
+            self.cache.remove(&uri);
+            return;
+        };
+        let size = result_id.len() + size_of_val(&*data);
+        self.cache
+            .insert(uri, CachedTokens { result_id, data }, size);
+    }
+}
+
+/// Apply edits to the tokens.
+///
+/// Edits address the flat integer encoding of the tokens and are all relative to the original tokens.
+fn apply_edits(
+    tokens: &[SemanticToken],
+    mut edits: Vec<SemanticTokensEdit>,
+) -> Result<Arc<[SemanticToken]>> {
+    let mut ints = tokens
+        .iter()
+        .flat_map(|token| {
+            [
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+                token.token_modifiers_bitset,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    // apply from the back, so that the offsets of the remaining edits stay valid
+    edits.sort_by_key(|edit| edit.start);
+    let mut limit = ints.len();
+    for edit in edits.into_iter().rev() {
+        let start = edit.start as usize;
+        let end = start + edit.delete_count as usize;
+        ensure!(
+            end <= limit,
+            "edit {start}..{end} out of bounds or overlapping"
+        );
+        ints.splice(
+            start..end,
+            edit.data.into_iter().flatten().flat_map(|token| {
+                [
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                ]
+            }),
+        );
+        limit = start;
+    }
+
+    ensure!(
+        ints.len().is_multiple_of(TOKEN_LEN),
+        "edited tokens are not a multiple of {TOKEN_LEN} integers"
+    );
+    Ok(ints
+        .chunks_exact(TOKEN_LEN)
+        .map(|chunk| SemanticToken {
+            delta_line: chunk[0],
+            delta_start: chunk[1],
+            length: chunk[2],
+            token_type: chunk[3],
+            token_modifiers_bitset: chunk[4],
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(delta_line: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start: 0,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    fn edit(start: u32, delete_count: u32, data: &[u32]) -> SemanticTokensEdit {
+        SemanticTokensEdit {
+            start,
+            delete_count,
+            data: Some(data.iter().copied().map(token).collect()),
+        }
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let tokens = [token(1), token(2), token(3)];
+        assert_eq!(&*apply_edits(&tokens, vec![]).unwrap(), &tokens);
+
+        // replace first, delete last, unsorted
+        assert_eq!(
+            &*apply_edits(&tokens, vec![edit(10, 5, &[]), edit(0, 5, &[4, 5])]).unwrap(),
+            &[token(4), token(5), token(2)],
+        );
+
+        // insert at the end
+        assert_eq!(
+            &*apply_edits(&tokens, vec![edit(15, 0, &[4])]).unwrap(),
+            &[token(1), token(2), token(3), token(4)],
+        );
+
+        // partial token
+        let err = apply_edits(
+            &tokens,
+            vec![SemanticTokensEdit {
+                start: 0,
+                delete_count: 1,
+                data: None,
+            }],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "edited tokens are not a multiple of 5 integers"
+        );
+
+        // out of bounds
+        apply_edits(&tokens, vec![edit(10, 10, &[])]).unwrap_err();
+
+        // overlapping
+        apply_edits(&tokens, vec![edit(0, 10, &[]), edit(5, 5, &[])]).unwrap_err();
+    }
+}
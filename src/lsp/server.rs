@@ -0,0 +1,264 @@
+//! Handle to an initialized language server.
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use lsp_types::{DidChangeConfigurationParams, ServerInfo, notification::DidChangeConfiguration};
+use serde_json::Value;
+
+use crate::{io_intercept::TrafficTail, lang::ProgrammingLanguageQuirks};
+
+use super::{
+    cache::CacheRegistry, diagnostics::Diagnostics, document_cache::DocumentCache,
+    documents::Documents, hover_cache::HoverCache, init::InitResult, messages::ServerMessages,
+    progress_guard::ProgressGuard, scheduler::Scheduler, semantic_tokens::SemanticTokens,
+    symbol_cache::SymbolCache, tokens::TokenLegend, transport::ClientHandler,
+    version::VersionStatus,
+};
+
+/// Initialized language server.
+#[derive(Debug)]
+pub(crate) struct LanguageServer {
+    /// Name of the programming language.
+    name: String,
+
+    quirks: Arc<dyn ProgrammingLanguageQuirks>,
+
+    /// Quirks that provide the settings, replaced if the config changes at runtime.
+    settings_quirks: Mutex<Arc<dyn ProgrammingLanguageQuirks>>,
+
+    /// File extensions (without leading dot) handled by this language server.
+    file_extensions: HashSet<String>,
+
+    handler: Arc<ClientHandler>,
+    progress_guard: ProgressGuard,
+    messages: ServerMessages,
+    diagnostics: Diagnostics,
+    scheduler: Scheduler,
+    documents: Documents,
+
+    /// Last exchanged messages, see `--debug-tail`.
+    traffic_tail: Option<Arc<TrafficTail>>,
+
+    /// Legend of the semantic tokens, `None` if the language server does not provide them.
+    token_legend: Option<TokenLegend>,
+
+    semantic_tokens: SemanticTokens,
+
+    symbol_cache: SymbolCache,
+
+    document_cache: DocumentCache,
+
+    hover_cache: HoverCache,
+
+    /// Memory budget shared by all caches of all language servers.
+    cache_registry: CacheRegistry,
+
+    /// Language server can resolve workspace symbols that lack a range.
+    workspace_symbol_resolve: bool,
+
+    /// Language server accepts ranges for hover requests.
+    hover_range: bool,
+
+    /// Name and version as reported by the language server.
+    server_info: Option<ServerInfo>,
+
+    version_status: VersionStatus,
+
+    /// Only handles auxiliary files that no primary language server handles.
+    secondary: bool,
+}
+
+impl LanguageServer {
+    #[expect(clippy::too_many_arguments, reason = "plain constructor")]
+    pub(crate) fn new(
+        name: String,
+        quirks: Arc<dyn ProgrammingLanguageQuirks>,
+        handler: Arc<ClientHandler>,
+        progress_guard: ProgressGuard,
+        messages: ServerMessages,
+        diagnostics: Diagnostics,
+        scheduler: Scheduler,
+        traffic_tail: Option<Arc<TrafficTail>>,
+        init_result: InitResult,
+        version_status: VersionStatus,
+        symbol_cache: SymbolCache,
+        cache_registry: CacheRegistry,
+        secondary: bool,
+    ) -> Self {
+        Self {
+            documents: Documents::new(name.clone(), progress_guard.client().clone()),
+            name,
+            file_extensions: quirks.file_extensions(),
+            settings_quirks: Mutex::new(Arc::clone(&quirks)),
+            quirks,
+            handler,
+            progress_guard,
+            messages,
+            diagnostics,
+            scheduler,
+            traffic_tail,
+            token_legend: init_result.token_legend,
+            semantic_tokens: SemanticTokens::new(
+                init_result.semantic_tokens_delta,
+                &cache_registry,
+            ),
+            symbol_cache,
+            document_cache: DocumentCache::new(&cache_registry),
+            hover_cache: HoverCache::new(&cache_registry),
+            cache_registry,
+            workspace_symbol_resolve: init_result.workspace_symbol_resolve,
+            hover_range: init_result.hover_range,
+            server_info: init_result.server_info,
+            version_status,
+            secondary,
+        }
+    }
+
+    /// Name of the programming language.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn quirks(&self) -> &Arc<dyn ProgrammingLanguageQuirks> {
+        &self.quirks
+    }
+
+    /// Handler for requests that the language server sends to us.
+    pub(crate) fn handler(&self) -> &ClientHandler {
+        &self.handler
+    }
+
+    /// Load the settings from the given quirks from now on, e.g. after the config file changed.
+    pub(crate) fn set_settings_quirks(&self, quirks: Arc<dyn ProgrammingLanguageQuirks>) {
+        *self.settings_quirks.lock().expect("not poisoned") = quirks;
+    }
+
+    /// Load the settings, they may have changed since the language server was started.
+    pub(crate) fn load_settings(&self, workspace_root: &Path) -> Result<Option<Value>> {
+        let quirks = Arc::clone(&self.settings_quirks.lock().expect("not poisoned"));
+        quirks
+            .initialization_options(workspace_root)
+            .context("load settings")
+    }
+
+    /// Push settings to the language server without restarting it.
+    pub(crate) async fn push_settings(&self, settings: Option<Value>) -> Result<()> {
+        self.handler.set_settings(settings.clone());
+        self.progress_guard
+            .client()
+            .send_notification::<DidChangeConfiguration>(DidChangeConfigurationParams {
+                settings: settings.unwrap_or_default(),
+            })
+            .await
+            .context("DidChangeConfiguration")
+    }
+
+    pub(crate) fn progress_guard(&self) -> &ProgressGuard {
+        &self.progress_guard
+    }
+
+    /// Log and show messages sent by the language server.
+    pub(crate) fn messages(&self) -> &ServerMessages {
+        &self.messages
+    }
+
+    /// Diagnostics published by the language server.
+    pub(crate) fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Last exchanged messages, `None` if `--debug-tail` is disabled.
+    pub(crate) fn traffic_tail(&self) -> Option<&TrafficTail> {
+        self.traffic_tail.as_deref()
+    }
+
+    /// Limits concurrent tool calls.
+    pub(crate) fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Documents that are open in the language server.
+    pub(crate) fn documents(&self) -> &Documents {
+        &self.documents
+    }
+
+    /// Legend of the semantic tokens, `None` if the language server does not provide them.
+    pub(crate) fn token_legend(&self) -> Option<&TokenLegend> {
+        self.token_legend.as_ref()
+    }
+
+    /// Fetches semantic tokens, only used if there is a [token legend](Self::token_legend).
+    pub(crate) fn semantic_tokens(&self) -> &SemanticTokens {
+        &self.semantic_tokens
+    }
+
+    /// Recent workspace symbol searches.
+    pub(crate) fn symbol_cache(&self) -> &SymbolCache {
+        &self.symbol_cache
+    }
+
+    /// Results of pinned documents, e.g. the ones that were warmed up.
+    pub(crate) fn document_cache(&self) -> &DocumentCache {
+        &self.document_cache
+    }
+
+    /// Recent hover results.
+    pub(crate) fn hover_cache(&self) -> &HoverCache {
+        &self.hover_cache
+    }
+
+    /// Memory budget shared by all caches of all language servers.
+    pub(crate) fn cache_registry(&self) -> &CacheRegistry {
+        &self.cache_registry
+    }
+
+    /// Language server can resolve workspace symbols that lack a range.
+    pub(crate) fn workspace_symbol_resolve(&self) -> bool {
+        self.workspace_symbol_resolve
+    }
+
+    /// Language server accepts ranges for hover requests.
+    pub(crate) fn hover_range(&self) -> bool {
+        self.hover_range
+    }
+
+    /// Name and version as reported by the language server.
+    pub(crate) fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    pub(crate) fn version_status(&self) -> &VersionStatus {
+        &self.version_status
+    }
+
+    /// Only handles auxiliary files that no primary language server handles.
+    ///
+    /// Secondary language servers are not used for workspace-wide symbol searches.
+    pub(crate) fn is_secondary(&self) -> bool {
+        self.secondary
+    }
+
+    /// File extensions (without leading dot) handled by this language server.
+    pub(crate) fn file_extensions(&self) -> &HashSet<String> {
+        &self.file_extensions
+    }
+
+    /// Checks if this language server is responsible for the given file, based on its extension.
+    ///
+    /// A language server without any configured file extensions handles all files.
+    pub(crate) fn handles_file(&self, file: &str) -> bool {
+        if self.file_extensions.is_empty() {
+            return true;
+        }
+
+        Path::new(file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| self.file_extensions.contains(ext))
+            .unwrap_or_default()
+    }
+}
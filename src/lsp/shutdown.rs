@@ -0,0 +1,132 @@
+//! Shut down language servers.
+//!
+//! A hung language server must not block the shutdown forever, so it is killed if it does not exit in time.
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, ensure};
+use lsp_client::LspClient;
+use tokio::process::Child;
+use tracing::{debug, warn};
+
+use crate::{TaskManager, cli::parse_secs};
+
+/// Language server process.
+pub(crate) type LspChild = (String, Arc<LspClient>, Child);
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct ShutdownCLIConfig {
+    /// Seconds that a language server has to shut down and exit before it is killed.
+    #[clap(
+        long,
+        default_value = "10",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_LSP_SHUTDOWN_TIMEOUT"
+    )]
+    lsp_shutdown_timeout: Duration,
+
+    /// Seconds that every background task (e.g. IO interception) has to stop during shutdown before it is killed.
+    #[clap(
+        long,
+        default_value = "5",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_TASK_SHUTDOWN_TIMEOUT"
+    )]
+    task_shutdown_timeout: Duration,
+}
+
+impl ShutdownCLIConfig {
+    /// Task manager that kills tasks which do not stop in time.
+    pub(crate) fn task_manager(&self) -> TaskManager {
+        TaskManager::new().with_shutdown_grace(self.task_shutdown_timeout)
+    }
+
+    /// Shut down language servers and wait for their processes to exit.
+    ///
+    /// Language servers that do not exit within the timeout are killed.
+    pub(crate) async fn shutdown_lsps(&self, children: Vec<LspChild>) -> Result<()> {
+        let mut res = Ok(());
+        for (name, client, mut child) in children {
+            debug!(lang = name.as_str(), "dismantle LSP");
+            let graceful =
+                tokio::time::timeout(self.lsp_shutdown_timeout, shutdown_lsp(&client, &mut child))
+                    .await;
+            match graceful {
+                Ok(graceful) => {
+                    res = res.and(graceful.with_context(|| format!("LSP for {name}")));
+                }
+                Err(_) => {
+                    res = res.and(
+                        child
+                            .kill()
+                            .await
+                            .context("kill language server")
+                            .with_context(|| format!("LSP for {name}")),
+                    );
+                    warn!(
+                        lang = name.as_str(),
+                        timeout_secs = self.lsp_shutdown_timeout.as_secs_f64(),
+                        status = "forced",
+                        "LSP did not exit in time, killed it",
+                    );
+                }
+            }
+            debug!(lang = name.as_str(), "LSP gone");
+        }
+        res
+    }
+}
+
+/// Ask language server to shut down and wait for its process to exit.
+async fn shutdown_lsp(client: &LspClient, child: &mut Child) -> Result<()> {
+    let res = async {
+        client
+            .shutdown()
+            .await
+            .context("shutdown language server")?;
+        client.exit().await.context("exit language server")?;
+        Result::<()>::Ok(())
+    }
+    .await;
+
+    let status = async {
+        let status = child.wait().await.context("terminate language server")?;
+
+        // `status.exit_ok` is unstable,
+        // see https://github.com/rust-lang/rust/issues/84908
+        ensure!(status.success(), "LSP exit was not clean: {status}");
+
+        Ok(())
+    }
+    .await;
+
+    res.and(status)
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use lsp_client::transport::io_transport;
+    use tokio::{io::duplex, process::Command};
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_hung_lsp() {
+        // server never answers
+        let (client_out, _server_in) = duplex(1024);
+        let (_server_out, client_in) = duplex(1024);
+        let (sender, receiver) = io_transport(client_out, client_in);
+        let client = Arc::new(LspClient::new(sender, receiver));
+        let child = Command::new("sleep")
+            .arg("60")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let cfg = ShutdownCLIConfig::parse_from(["", "--lsp-shutdown-timeout=0.1"]);
+        cfg.shutdown_lsps(vec![("test".to_owned(), client, child)])
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+//! Explain why a language server cannot be started.
+//!
+//! A bare "cannot spawn" error leaves users guessing, so this reports the binary, where it was searched and how to
+//! fix the problem.
+use std::{fmt, io, path::Path};
+
+use crate::lang::ProgrammingLanguageQuirks;
+
+/// Why the language server binary cannot be executed.
+#[derive(Debug)]
+pub(crate) enum SpawnProblem {
+    /// Binary does not exist.
+    NotFound,
+
+    /// Binary exists but we are not allowed to execute it.
+    PermissionDenied,
+
+    /// Binary exists but is not an executable for this platform, e.g. it was built for another architecture.
+    WrongFormat,
+
+    /// Any other I/O error.
+    Other(io::Error),
+}
+
+impl From<io::Error> for SpawnProblem {
+    fn from(e: io::Error) -> Self {
+        // `ENOEXEC` on Unix, `ERROR_BAD_EXE_FORMAT` on Windows
+        const WRONG_FORMAT: i32 = if cfg!(windows) { 193 } else { 8 };
+
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            _ if e.raw_os_error() == Some(WRONG_FORMAT) => Self::WrongFormat,
+            _ => Self::Other(e),
+        }
+    }
+}
+
+/// Language server binary cannot be executed.
+#[derive(Debug)]
+pub(crate) struct SpawnError {
+    lang: String,
+    binary: String,
+    problem: SpawnProblem,
+
+    /// `PATH` that is searched for the binary, `None` if the binary is given as a path.
+    search_path: Option<String>,
+
+    install_hint: Option<String>,
+
+    /// A pinned release can be downloaded via `--install-missing-lsp`.
+    installable: bool,
+}
+
+impl SpawnError {
+    pub(crate) fn new(
+        lang: &str,
+        quirks: &dyn ProgrammingLanguageQuirks,
+        problem: impl Into<SpawnProblem>,
+    ) -> Self {
+        let binary = quirks.language_server().binary;
+        let search_path = (Path::new(&binary).components().count() == 1).then(|| {
+            std::env::var_os("PATH")
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        Self {
+            lang: lang.to_owned(),
+            binary,
+            problem: problem.into(),
+            search_path,
+            install_hint: quirks.install_hint(),
+            installable: cfg!(feature = "install") && quirks.release().is_some(),
+        }
+    }
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            lang,
+            binary,
+            problem,
+            search_path,
+            install_hint,
+            installable,
+        } = self;
+
+        write!(f, "cannot start language server for {lang}: ")?;
+        match problem {
+            SpawnProblem::NotFound => write!(f, "binary `{binary}` not found")?,
+            SpawnProblem::PermissionDenied => write!(f, "binary `{binary}` is not executable")?,
+            SpawnProblem::WrongFormat => write!(
+                f,
+                "binary `{binary}` is not an executable for this platform ({}-{})",
+                std::env::consts::ARCH,
+                std::env::consts::OS,
+            )?,
+            SpawnProblem::Other(e) => write!(f, "cannot execute binary `{binary}`: {e}")?,
+        }
+        if let Some(search_path) = search_path {
+            write!(f, "\n- searched PATH: {search_path}")?;
+        }
+
+        match problem {
+            SpawnProblem::NotFound | SpawnProblem::Other(_) => {
+                if let Some(install_hint) = install_hint {
+                    write!(f, "\n- install it via {install_hint}")?;
+                }
+                if *installable {
+                    write!(
+                        f,
+                        "\n- use `--install-missing-lsp` to download a pinned release"
+                    )?;
+                }
+                write!(f, "\n- use `--lsp-command` to point to the binary")?;
+            }
+            SpawnProblem::PermissionDenied => {
+                write!(f, "\n- make it executable, e.g. `chmod +x {binary}`")?;
+            }
+            SpawnProblem::WrongFormat => {
+                write!(f, "\n- re-install it for this platform")?;
+                if let Some(install_hint) = install_hint {
+                    write!(f, " via {install_hint}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for SpawnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.problem {
+            SpawnProblem::Other(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use clap::Parser;
+
+    use crate::lang::{ProgrammingLanguage, overrides::LspOverridesCLIConfig, rust::Rust};
+
+    use super::*;
+
+    /// Error message for the Rust language server, optionally overriding the binary.
+    fn message(args: &[&str], problem: SpawnProblem) -> String {
+        let cfg =
+            LspOverridesCLIConfig::try_parse_from(std::iter::once("").chain(args.iter().copied()))
+                .unwrap();
+        let languages = cfg
+            .apply(vec![(
+                ProgrammingLanguage::Rust,
+                Arc::new(Rust::default()) as Arc<dyn ProgrammingLanguageQuirks>,
+            )])
+            .unwrap();
+        let mut e = SpawnError::new("rust", languages[0].1.as_ref(), problem);
+
+        // independent of the test environment
+        if e.search_path.is_some() {
+            e.search_path = Some("/usr/bin:/bin".to_owned());
+        }
+        e.installable = e.install_hint.is_some();
+
+        e.to_string()
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert_eq!(
+            message(&[], SpawnProblem::NotFound),
+            "\
+cannot start language server for rust: binary `rust-analyzer` not found
+- searched PATH: /usr/bin:/bin
+- install it via `rustup component add rust-analyzer`
+- use `--install-missing-lsp` to download a pinned release
+- use `--lsp-command` to point to the binary",
+        );
+        assert_eq!(
+            message(&["--lsp-command=/opt/ra"], SpawnProblem::NotFound),
+            "\
+cannot start language server for rust: binary `/opt/ra` not found
+- use `--lsp-command` to point to the binary",
+        );
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        assert_eq!(
+            message(
+                &["--lsp-command=/opt/ra"],
+                io::Error::from(io::ErrorKind::PermissionDenied).into()
+            ),
+            "\
+cannot start language server for rust: binary `/opt/ra` is not executable
+- make it executable, e.g. `chmod +x /opt/ra`",
+        );
+    }
+
+    #[test]
+    fn test_wrong_format() {
+        let e = io::Error::from_raw_os_error(if cfg!(windows) { 193 } else { 8 });
+        assert_eq!(
+            message(&[], e.into()),
+            format!(
+                "\
+cannot start language server for rust: binary `rust-analyzer` is not an executable for this platform ({}-{})
+- searched PATH: /usr/bin:/bin
+- re-install it for this platform via `rustup component add rust-analyzer`",
+                std::env::consts::ARCH,
+                std::env::consts::OS,
+            ),
+        );
+    }
+}
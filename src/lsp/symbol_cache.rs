@@ -0,0 +1,192 @@
+//! Cache of workspace symbol searches.
+//!
+//! Agents often repeat the same search within a few seconds, e.g. when they retry a tool call or look at the results
+//! one by one. Searching a big workspace is slow, so results are reused for a short time unless files change or the
+//! language server finishes some work, e.g. re-indexing.
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use lsp_types::SymbolInformation;
+
+use crate::cli::parse_secs;
+
+use super::cache::{CacheRegistry, CacheStats, Lru, symbols_size};
+
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub(crate) struct SymbolCacheCLIConfig {
+    /// How long (in seconds) the results of workspace symbol searches are reused.
+    ///
+    /// Changed files and finished language server work (e.g. indexing) invalidate the cache. Use `0` to disable the cache.
+    #[clap(
+        long,
+        default_value = "10",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_SYMBOL_CACHE_TTL"
+    )]
+    symbol_cache_ttl: Duration,
+}
+
+impl SymbolCacheCLIConfig {
+    pub(crate) fn cache(&self, registry: &CacheRegistry) -> SymbolCache {
+        SymbolCache {
+            ttl: self.symbol_cache_ttl,
+            entries: registry.lru(),
+        }
+    }
+}
+
+/// Query and whether dependencies are searched as well.
+type Key = (String, bool);
+
+/// State that search results depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Revision {
+    /// See [`SharedWorkspace::file_revision`](super::workspace::SharedWorkspace::file_revision).
+    pub(crate) files: u64,
+
+    /// See [`ProgressGuard::work_revision`](super::progress_guard::ProgressGuard::work_revision).
+    pub(crate) work: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    symbols: Vec<SymbolInformation>,
+    fetched: Instant,
+
+    /// Revision at the time of the search.
+    revision: Revision,
+}
+
+impl Entry {
+    fn is_valid(&self, ttl: Duration, revision: Revision) -> bool {
+        self.fetched.elapsed() < ttl && self.revision == revision
+    }
+}
+
+/// Results of recent workspace symbol searches of a single language server.
+#[derive(Debug)]
+pub(crate) struct SymbolCache {
+    ttl: Duration,
+    entries: Arc<Lru<Key, Entry>>,
+}
+
+impl SymbolCache {
+    /// Cached result of the search, `None` if there is none or it is outdated.
+    pub(crate) fn get(
+        &self,
+        query: &str,
+        workspace_and_dependencies: bool,
+        revision: Revision,
+    ) -> Option<Vec<SymbolInformation>> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+
+        self.entries
+            .get(&(query.to_owned(), workspace_and_dependencies), |entry| {
+                entry.is_valid(self.ttl, revision)
+            })
+            .map(|entry| entry.symbols)
+    }
+
+    /// Remember the result of a search, `revision` is the one from before the search started.
+    pub(crate) fn insert(
+        &self,
+        query: &str,
+        workspace_and_dependencies: bool,
+        revision: Revision,
+        symbols: Vec<SymbolInformation>,
+    ) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        self.entries
+            .retain(|_key, entry| entry.is_valid(self.ttl, revision));
+        let size = query.len() + symbols_size(&symbols);
+        self.entries.insert(
+            (query.to_owned(), workspace_and_dependencies),
+            Entry {
+                symbols,
+                fetched: Instant::now(),
+                revision,
+            },
+            size,
+        );
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.entries.stats()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use lsp_types::{Location, SymbolKind, Uri};
+
+    use crate::lsp::cache::CacheCLIConfig;
+
+    use super::*;
+
+    fn symbol(name: &str) -> SymbolInformation {
+        #[expect(deprecated)]
+        SymbolInformation {
+            name: name.to_owned(),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            location: Location {
+                uri: "file:///ws/src/lib.rs".parse::<Uri>().unwrap(),
+                range: Default::default(),
+            },
+            container_name: None,
+        }
+    }
+
+    const REV: Revision = Revision { files: 1, work: 1 };
+
+    fn new_cache(args: &[&str]) -> SymbolCache {
+        let registry = CacheCLIConfig::parse_from([""]).registry();
+        SymbolCacheCLIConfig::parse_from(args).cache(&registry)
+    }
+
+    fn hits_and_misses(cache: &SymbolCache) -> serde_json::Value {
+        let stats = serde_json::to_value(cache.stats()).unwrap();
+        serde_json::json!([stats["hits"], stats["misses"]])
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = new_cache(&[""]);
+        assert_eq!(cache.get("foo", false, REV), None);
+
+        cache.insert("foo", false, REV, vec![symbol("foo")]);
+        assert_eq!(cache.get("foo", false, REV), Some(vec![symbol("foo")]));
+        assert_eq!(cache.get("foo", true, REV), None);
+        assert_eq!(cache.get("bar", false, REV), None);
+
+        // changed files and finished work invalidate the cache
+        let files = Revision { files: 2, ..REV };
+        assert_eq!(cache.get("foo", false, files), None);
+        let work = Revision { work: 2, ..REV };
+        assert_eq!(cache.get("foo", false, work), None);
+
+        assert_eq!(hits_and_misses(&cache), serde_json::json!([1, 5]));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = new_cache(&["", "--symbol-cache-ttl=0.01"]);
+        cache.insert("foo", false, REV, vec![symbol("foo")]);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get("foo", false, REV), None);
+
+        let cache = new_cache(&["", "--symbol-cache-ttl=0"]);
+        cache.insert("foo", false, REV, vec![symbol("foo")]);
+        assert_eq!(cache.get("foo", false, REV), None);
+        assert_eq!(hits_and_misses(&cache), serde_json::json!([0, 0]));
+    }
+}
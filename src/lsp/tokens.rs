@@ -1,12 +1,13 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::{Context, Result};
 use itertools::Itertools;
 use lsp_types::{Position, SemanticToken, SemanticTokensLegend};
+use tracing::{debug, warn};
 
 use crate::ProgrammingLanguageQuirks;
 
-use super::location::McpLocation;
+use super::{location::McpLocation, workspace::Workspace};
 
 #[derive(Debug)]
 pub(crate) struct TokenLegend {
@@ -42,16 +43,69 @@ impl TokenLegend {
         }
     }
 
+    /// Legend for [`scan`](Self::scan), used if the language server does not provide semantic tokens.
+    pub(crate) fn text() -> Self {
+        Self {
+            token_types: vec![TokenType("text".to_owned())],
+            token_modifiers: vec![],
+        }
+    }
+
+    /// Find identifiers with the given name by plain text search.
+    pub(crate) fn scan<'a>(&'a self, file_content: &'a str, name: &str) -> Document<'a> {
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let token_type = &self.token_types[0];
+        if name.is_empty() {
+            return Document {
+                tokens: vec![],
+                stale: false,
+            };
+        }
+
+        let tokens = file_content
+            .lines()
+            .enumerate()
+            .flat_map(|(line, text)| {
+                text.match_indices(name)
+                    .filter(move |(start, data)| {
+                        let before = text[..*start].chars().next_back();
+                        let after = text[start + data.len()..].chars().next();
+                        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+                    })
+                    .map(move |(start, data)| Token {
+                        line: line as u32 + 1,
+                        character: start as u32 + 1,
+                        token_type,
+                        token_modifiers: TokenModifers {
+                            legend: &self.token_modifiers,
+                            bitset: 0,
+                        },
+                        data,
+                    })
+            })
+            .collect();
+
+        Document {
+            tokens,
+            stale: false,
+        }
+    }
+
+    /// Decode semantic tokens.
+    ///
+    /// Tokens that do not fit the file content are skipped and the document is marked as
+    /// [stale](Document::is_stale). This happens if the file changed after the tokens were computed.
     pub(crate) fn decode<'a>(
         &'a self,
         file_content: &'a str,
-        tokens: Vec<SemanticToken>,
+        tokens: &[SemanticToken],
     ) -> Result<Document<'a>> {
         let lines = file_content.lines().collect::<Vec<_>>();
 
         let mut line = 0u32;
         let mut start = 0u32;
         let mut doc_tokens = Vec::with_capacity(tokens.len());
+        let mut skipped = 0usize;
 
         for token in tokens {
             let SemanticToken {
@@ -60,7 +114,7 @@ impl TokenLegend {
                 length,
                 token_type,
                 token_modifiers_bitset,
-            } = token;
+            } = *token;
 
             line += delta_line;
             start = if delta_line > 0 {
@@ -74,12 +128,15 @@ impl TokenLegend {
                 .get(token_type as usize)
                 .with_context(|| format!("invalid token type: {token_type}"))?;
 
-            let range = (start as usize)..((start + length) as usize);
-            let data = lines
+            let range = (start as usize)..(start.saturating_add(length) as usize);
+            let Some(data) = lines
                 .get(line as usize)
-                .with_context(|| format!("token line of of bounds: {line}"))?
-                .get(range.clone())
-                .with_context(|| format!("range out of bounds: {range:?}"))?;
+                .and_then(|text| text.get(range.clone()))
+            else {
+                debug!(line, ?range, "token out of bounds, skip");
+                skipped += 1;
+                continue;
+            };
 
             doc_tokens.push(Token {
                 line: line + 1,
@@ -93,31 +150,81 @@ impl TokenLegend {
             })
         }
 
-        Ok(Document { tokens: doc_tokens })
+        if skipped > 0 {
+            warn!(
+                skipped,
+                "semantic tokens do not match file content, file changed?"
+            );
+        }
+
+        Ok(Document {
+            tokens: doc_tokens,
+            stale: skipped > 0,
+        })
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct Document<'legend> {
     tokens: Vec<Token<'legend>>,
+    stale: bool,
 }
 
 impl<'legend> Document<'legend> {
+    /// Some tokens did not match the file content and were skipped.
+    pub(crate) fn is_stale(&self) -> bool {
+        self.stale
+    }
+
     pub(crate) fn declared_variables(&self) -> impl Iterator<Item = &Token<'legend>> {
+        self.declarations()
+            .filter(|token| token.token_type().as_ref() == "variable")
+    }
+
+    /// Tokens that declare something, in document order.
+    pub(crate) fn declarations(&self) -> impl Iterator<Item = &Token<'legend>> {
+        self.tokens.iter().filter(|token| token.is_declaration())
+    }
+
+    /// Number of tokens per token type.
+    pub(crate) fn token_type_counts(&self) -> BTreeMap<&str, usize> {
         self.tokens
             .iter()
-            .filter(|token| token.token_type().as_ref() == "variable" && token.is_declaration())
+            .map(|token| token.token_type().as_ref())
+            .counts()
+            .into_iter()
+            .collect()
     }
 
+    /// Find the tokens with the given name.
+    ///
+    /// Tokens are ranked by the modifier scores of the language quirks first, so a declaration wins over usages or
+    /// tokens injected into doc comments. The position only breaks ties, because callers often pass the start of the
+    /// symbol range (e.g. the doc comment) instead of the exact token position.
+    ///
+    /// If a token type is given, tokens of other types are ignored.
     pub(crate) fn query(
         &self,
         name: &str,
+        token_type: Option<&str>,
+        line: Option<u32>,
+        character: Option<u32>,
+    ) -> Vec<&Token<'legend>> {
+        self.query_by(|data| data == name, token_type, line, character)
+    }
+
+    /// Same as [`query`](Self::query), but with a custom matcher for the token text.
+    pub(crate) fn query_by(
+        &self,
+        matches: impl Fn(&str) -> bool,
+        token_type: Option<&str>,
         line: Option<u32>,
         character: Option<u32>,
     ) -> Vec<&Token<'legend>> {
         self.tokens
             .iter()
-            .filter(|token| token.data == name)
+            .filter(|token| matches(token.data))
+            .filter(|token| token_type.is_none_or(|t| token.token_type().as_ref() == t))
             .min_set_by_key(|token| {
                 (
                     -token.token_modifiers().score(),
@@ -126,6 +233,36 @@ impl<'legend> Document<'legend> {
                 )
             })
     }
+
+    /// Find the token at the given position, or the nearest one if there is no token.
+    ///
+    /// Without a character, the best token of the line is used, see [`query`](Self::query).
+    pub(crate) fn at(
+        &self,
+        token_type: Option<&str>,
+        line: u32,
+        character: Option<u32>,
+    ) -> Option<&Token<'legend>> {
+        self.tokens
+            .iter()
+            .filter(|token| token_type.is_none_or(|t| token.token_type().as_ref() == t))
+            .min_by_key(|token| {
+                let end = token.character + token.data.len() as u32;
+                (
+                    line.abs_diff(token.line),
+                    character.map(|character| {
+                        if character < token.character {
+                            token.character - character
+                        } else {
+                            // `end` is exclusive
+                            (character + 1).saturating_sub(end)
+                        }
+                    }),
+                    -token.token_modifiers().score(),
+                    token.character,
+                )
+            })
+    }
 }
 
 #[derive(Debug)]
@@ -163,13 +300,8 @@ impl Token<'_> {
             .any(|modifier| modifier.name == "deprecated")
     }
 
-    pub(crate) fn mcp_location(&self, file: String, workspace: Arc<Path>) -> McpLocation {
-        McpLocation {
-            file,
-            line: self.line,
-            character: self.character,
-            workspace,
-        }
+    pub(crate) fn mcp_location(&self, file: String, workspace: Workspace) -> McpLocation {
+        McpLocation::new(file, self.line, self.character, workspace)
     }
 
     pub(crate) fn lsp_position(&self) -> Position {
@@ -279,6 +411,180 @@ impl Iterator for BitIter {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_scan() {
+        let legend = TokenLegend::text();
+        let doc = legend.scan("fn foo() {\n    foo_bar(foo);\n}\n// ä foo", "foo");
+        let positions = doc
+            .tokens
+            .iter()
+            .map(|token| (token.line, token.character, token.data))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [(1, 4, "foo"), (2, 13, "foo"), (4, 7, "foo")]);
+        assert_eq!(doc.tokens[0].token_type().as_ref(), "text");
+
+        assert_eq!(doc.query("foo", None, Some(2), None).len(), 1);
+        assert!(legend.scan("foo", "").tokens.is_empty());
+    }
+
+    #[test]
+    fn test_overview() {
+        let legend = TokenLegend {
+            token_types: vec![
+                TokenType("function".to_owned()),
+                TokenType("variable".to_owned()),
+            ],
+            token_modifiers: vec![TokenModifier {
+                name: "declaration".to_owned(),
+                score: 0,
+            }],
+        };
+        let token =
+            |delta_line, delta_start, length, token_type, token_modifiers_bitset| SemanticToken {
+                delta_line,
+                delta_start,
+                length,
+                token_type,
+                token_modifiers_bitset,
+            };
+        let doc = legend
+            .decode(
+                "fn foo() {\n    let x = bar();\n}",
+                &[
+                    token(0, 3, 3, 0, 1),
+                    token(1, 8, 1, 1, 1),
+                    token(0, 4, 3, 0, 0),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(
+            doc.token_type_counts(),
+            BTreeMap::from([("function", 2), ("variable", 1)])
+        );
+        let declarations = doc
+            .declarations()
+            .map(|token| token.data)
+            .collect::<Vec<_>>();
+        assert_eq!(declarations, ["foo", "x"]);
+        let variables = doc
+            .declared_variables()
+            .map(|token| token.data)
+            .collect::<Vec<_>>();
+        assert_eq!(variables, ["x"]);
+    }
+
+    #[test]
+    fn test_query_scores() {
+        let legend = TokenLegend {
+            token_types: vec![TokenType("function".to_owned())],
+            token_modifiers: vec![
+                TokenModifier {
+                    name: "declaration".to_owned(),
+                    score: 10,
+                },
+                TokenModifier {
+                    name: "injected".to_owned(),
+                    score: -100,
+                },
+            ],
+        };
+        let token = |delta_line, token_modifiers_bitset| SemanticToken {
+            delta_line,
+            delta_start: 0,
+            length: 3,
+            token_type: 0,
+            token_modifiers_bitset,
+        };
+        // injected declaration in a doc test, declaration, usage
+        let doc = legend
+            .decode(
+                "foo\nfoo\nfoo",
+                &[token(0, 0b11), token(1, 0b01), token(1, 0b00)],
+            )
+            .unwrap();
+
+        let lines = |tokens: Vec<&Token<'_>>| tokens.iter().map(|t| t.line).collect::<Vec<_>>();
+        assert_eq!(lines(doc.query("foo", None, None, None)), [2]);
+        // doc comment above the declaration
+        assert_eq!(lines(doc.query("foo", None, Some(1), None)), [2]);
+        assert_eq!(lines(doc.query("foo", None, Some(3), None)), [2]);
+        // token type
+        assert_eq!(lines(doc.query("foo", Some("function"), None, None)), [2]);
+        assert!(doc.query("foo", Some("struct"), None, None).is_empty());
+
+        assert!(doc.query("fo", None, None, None).is_empty());
+        let prefix = doc.query_by(|data| data.starts_with("fo"), None, None, None);
+        assert_eq!(lines(prefix), [2]);
+    }
+
+    #[test]
+    fn test_at() {
+        let legend = TokenLegend::text();
+        let doc = legend.scan("foo(foo);\n\nfoo", "foo");
+        let position = |token: Option<&Token<'_>>| token.map(|t| (t.line, t.character));
+
+        assert_eq!(position(doc.at(None, 1, Some(1))), Some((1, 1)));
+        assert_eq!(position(doc.at(None, 1, Some(3))), Some((1, 1)));
+        assert_eq!(position(doc.at(None, 1, Some(4))), Some((1, 1)));
+        assert_eq!(position(doc.at(None, 1, Some(6))), Some((1, 5)));
+        assert_eq!(position(doc.at(None, 1, Some(100))), Some((1, 5)));
+        assert_eq!(position(doc.at(None, 1, None)), Some((1, 1)));
+        assert_eq!(position(doc.at(None, 2, Some(1))), Some((1, 1)));
+        assert_eq!(position(doc.at(None, 3, Some(2))), Some((3, 1)));
+        assert_eq!(position(doc.at(Some("struct"), 1, None)), None);
+    }
+
+    #[test]
+    fn test_decode_stale() {
+        let legend = TokenLegend {
+            token_types: vec![TokenType("function".to_owned())],
+            token_modifiers: vec![],
+        };
+        let token = |delta_line, delta_start, length| SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        };
+
+        let doc = legend
+            .decode(
+                "fn foo() {}\nfn bar() {}",
+                &[token(0, 3, 3), token(1, 3, 3)],
+            )
+            .unwrap();
+        assert!(!doc.is_stale());
+        assert_eq!(doc.tokens.len(), 2);
+
+        // range out of line, line out of file
+        let doc = legend
+            .decode(
+                "fn foo() {}\nfn b",
+                &[token(0, 3, 3), token(1, 3, 3), token(1, 0, 2)],
+            )
+            .unwrap();
+        assert!(doc.is_stale());
+        let data = doc
+            .tokens
+            .iter()
+            .map(|token| token.data)
+            .collect::<Vec<_>>();
+        assert_eq!(data, ["foo"]);
+
+        // invalid token types are still an error
+        legend
+            .decode(
+                "fn foo() {}",
+                &[SemanticToken {
+                    token_type: 1,
+                    ..token(0, 3, 3)
+                }],
+            )
+            .unwrap_err();
+    }
+
     #[test]
     fn test_bit_iter() {
         let mut it = BitIter::new(0);
@@ -0,0 +1,377 @@
+//! Transport between [`LspClient`](lsp_client::LspClient) and language server.
+//!
+//! Language servers may send requests to the client, e.g. to pull the configuration. [`LspClient`](lsp_client::LspClient)
+//! cannot answer them, so they are intercepted and answered by this transport.
+use std::sync::{Arc, RwLock};
+
+use anyhow::Context;
+use jsonrpsee::core::client::{ReceivedMessage, TransportReceiverT, TransportSenderT};
+use lsp_types::{
+    ConfigurationParams, WorkspaceFolder,
+    request::{
+        RegisterCapability, Request, UnregisterCapability, WorkDoneProgressCreate,
+        WorkspaceConfiguration, WorkspaceFoldersRequest,
+    },
+};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::Mutex,
+};
+use tracing::{debug, warn};
+
+use crate::io_intercept::{BoxRead, BoxWrite};
+
+use super::{
+    requests::{RawRequest, RawRequestParams},
+    workspace::Workspace,
+};
+
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Create transport for the language server IO.
+pub(crate) fn transport(
+    stdin: BoxWrite,
+    stdout: BoxRead,
+    handler: Arc<ClientHandler>,
+) -> (Sender, Receiver) {
+    let writer = Arc::new(Mutex::new(stdin));
+    let sender = Sender {
+        writer: Arc::clone(&writer),
+    };
+    let receiver = Receiver {
+        reader: BufReader::new(stdout),
+        writer,
+        handler,
+    };
+    (sender, receiver)
+}
+
+/// Answers requests that the language server sends to us.
+#[derive(Debug)]
+pub(crate) struct ClientHandler {
+    workspace_folders: RwLock<Vec<WorkspaceFolder>>,
+    settings: RwLock<Option<Value>>,
+}
+
+impl ClientHandler {
+    pub(crate) fn new(workspace: &Workspace) -> anyhow::Result<Self> {
+        Ok(Self {
+            workspace_folders: RwLock::new(workspace.lsp_folders()?),
+            settings: RwLock::new(None),
+        })
+    }
+
+    /// Set folders that are reported via `workspace/workspaceFolders`.
+    pub(crate) fn set_workspace_folders(&self, workspace_folders: Vec<WorkspaceFolder>) {
+        *self.workspace_folders.write().expect("not poisoned") = workspace_folders;
+    }
+
+    /// Settings that are reported via `workspace/configuration`.
+    pub(crate) fn settings(&self) -> Option<Value> {
+        self.settings.read().expect("not poisoned").clone()
+    }
+
+    /// Set settings that are reported via `workspace/configuration`.
+    pub(crate) fn set_settings(&self, settings: Option<Value>) {
+        *self.settings.write().expect("not poisoned") = settings;
+    }
+
+    fn handle(&self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            WorkspaceConfiguration::METHOD => {
+                let params = serde_json::from_value::<ConfigurationParams>(params)
+                    .map_err(|e| (-32602, e.to_string()))?;
+                let settings = self.settings.read().expect("not poisoned");
+                Ok(Value::Array(
+                    params
+                        .items
+                        .iter()
+                        .map(|item| lookup_section(settings.as_ref(), item.section.as_deref()))
+                        .collect(),
+                ))
+            }
+            WorkspaceFoldersRequest::METHOD => {
+                Ok(json!(*self.workspace_folders.read().expect("not poisoned")))
+            }
+            WorkDoneProgressCreate::METHOD
+            | RegisterCapability::METHOD
+            | UnregisterCapability::METHOD => Ok(Value::Null),
+            _ => Err((METHOD_NOT_FOUND, format!("method not supported: {method}"))),
+        }
+    }
+}
+
+/// Look up configuration section.
+///
+/// The settings of a language server are a single object that might be namespaced, e.g. `{"csharp": {...}}`, or not,
+/// e.g. rust-analyzer uses plain settings but asks for the `rust-analyzer` section. So we descend into the section if
+/// it exists and fall back to the entire settings object otherwise.
+fn lookup_section(settings: Option<&Value>, section: Option<&str>) -> Value {
+    let Some(settings) = settings else {
+        return Value::Null;
+    };
+    let Some(section) = section else {
+        return settings.clone();
+    };
+
+    section
+        .split('.')
+        .try_fold(settings, |value, part| value.get(part))
+        .unwrap_or(settings)
+        .clone()
+}
+
+async fn write_message(writer: &Mutex<BoxWrite>, msg: &str) -> std::io::Result<()> {
+    let mut writer = writer.lock().await;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n{msg}", msg.len()).as_bytes())
+        .await?;
+    writer.flush().await
+}
+
+/// Read a single message.
+pub(crate) async fn read_message(
+    reader: &mut (impl AsyncBufRead + Unpin),
+) -> std::io::Result<Vec<u8>> {
+    // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#baseProtocol
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        match line.trim_end_matches("\r\n") {
+            "" => break,
+            header => {
+                if let Some(len) = header.strip_prefix("Content-Length: ") {
+                    content_length =
+                        Some(len.parse::<usize>().map_err(|e| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+                        })?);
+                }
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Content-Length header not found",
+        )
+    })?;
+    let mut buf = vec![0; content_length];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Write messages, see [`read_message`].
+pub(crate) async fn write_messages(
+    output: &mut (impl AsyncWrite + Unpin),
+    messages: &[Value],
+) -> anyhow::Result<()> {
+    for msg in messages {
+        let msg = msg.to_string();
+        output
+            .write_all(format!("Content-Length: {}\r\n\r\n{msg}", msg.len()).as_bytes())
+            .await
+            .context("write message")?;
+    }
+    output.flush().await.context("flush output")
+}
+
+/// Sending end of the transport.
+pub(crate) struct Sender {
+    writer: Arc<Mutex<BoxWrite>>,
+}
+
+impl std::fmt::Debug for Sender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl TransportSenderT for Sender {
+    type Error = std::io::Error;
+
+    async fn send(&mut self, msg: String) -> Result<(), Self::Error> {
+        let msg = unwrap_raw_request(msg)?;
+        write_message(&self.writer, &msg).await
+    }
+}
+
+/// Replace method and parameters of a [`RawRequest`] with the ones the caller asked for.
+///
+/// Other messages are passed through unchanged.
+fn unwrap_raw_request(msg: String) -> std::io::Result<String> {
+    // avoid parsing every message
+    if !msg.contains(RawRequest::METHOD) {
+        return Ok(msg);
+    }
+    let mut parsed = serde_json::from_str::<Value>(&msg)?;
+    if parsed["method"] != RawRequest::METHOD {
+        return Ok(msg);
+    }
+
+    let RawRequestParams { method, params } = serde_json::from_value(parsed["params"].take())?;
+    parsed["method"] = Value::String(method);
+    parsed["params"] = params;
+    Ok(parsed.to_string())
+}
+
+/// Receiving end of the transport.
+pub(crate) struct Receiver {
+    reader: BufReader<BoxRead>,
+    writer: Arc<Mutex<BoxWrite>>,
+    handler: Arc<ClientHandler>,
+}
+
+impl std::fmt::Debug for Receiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver")
+            .field("handler", &self.handler)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl TransportReceiverT for Receiver {
+    type Error = std::io::Error;
+
+    async fn receive(&mut self) -> Result<ReceivedMessage, Self::Error> {
+        loop {
+            let buf = read_message(&mut self.reader).await?;
+
+            // only requests have both a method and an ID
+            let Ok(Value::Object(mut msg)) = serde_json::from_slice::<Value>(&buf) else {
+                return Ok(ReceivedMessage::Bytes(buf));
+            };
+            let (Some(Value::String(method)), Some(id)) = (msg.remove("method"), msg.remove("id"))
+            else {
+                return Ok(ReceivedMessage::Bytes(buf));
+            };
+            let params = msg.remove("params").unwrap_or_default();
+
+            debug!(method = method.as_str(), "request from language server");
+            let resp = match self.handler.handle(&method, params) {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err((code, message)) => {
+                    warn!(
+                        method = method.as_str(),
+                        message, "cannot answer language server request"
+                    );
+                    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+                }
+            };
+            write_message(&self.writer, &resp.to_string()).await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use tokio::io::{AsyncBufRead, duplex};
+
+    use super::*;
+
+    async fn read(reader: &mut (impl AsyncBufRead + Unpin)) -> Value {
+        serde_json::from_slice(&read_message(reader).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_answer_requests() {
+        let (stdin, server_in) = duplex(1024);
+        let mut server_in = BufReader::new(server_in);
+        let (mut server_out, stdout) = duplex(1024);
+        let handler =
+            Arc::new(ClientHandler::new(&Workspace::new([PathBuf::from("/ws")]).unwrap()).unwrap());
+        handler.set_settings(Some(json!({"csharp": {"solution": "a.sln"}, "x": 1})));
+        let (mut sender, mut receiver) = transport(Box::pin(stdin), Box::pin(stdout), handler);
+
+        sender.send(r#"{"foo":1}"#.to_owned()).await.unwrap();
+        assert_eq!(read(&mut server_in).await, json!({"foo": 1}));
+
+        write_messages(
+            &mut server_out,
+            &[
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "workspace/configuration",
+                    "params": {"items": [{"section": "csharp"}, {"section": "rust-analyzer"}, {}]},
+                }),
+                json!({"jsonrpc": "2.0", "id": "a", "method": "foo/bar"}),
+                json!({"jsonrpc": "2.0", "method": "$/progress", "params": {}}),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let ReceivedMessage::Bytes(msg) = receiver.receive().await.unwrap() else {
+            panic!("should be bytes")
+        };
+        assert_eq!(
+            serde_json::from_slice::<Value>(&msg).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "$/progress", "params": {}}),
+        );
+
+        assert_eq!(
+            read(&mut server_in).await,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": [
+                    {"solution": "a.sln"},
+                    {"csharp": {"solution": "a.sln"}, "x": 1},
+                    {"csharp": {"solution": "a.sln"}, "x": 1},
+                ],
+            }),
+        );
+        assert_eq!(
+            read(&mut server_in).await,
+            json!({
+                "jsonrpc": "2.0",
+                "id": "a",
+                "error": {"code": METHOD_NOT_FOUND, "message": "method not supported: foo/bar"},
+            }),
+        );
+    }
+
+    #[test]
+    fn test_unwrap_raw_request() {
+        let msg = json!({"jsonrpc": "2.0", "id": 1, "method": "foo", "params": {"method": "x"}})
+            .to_string();
+        assert_eq!(unwrap_raw_request(msg.clone()).unwrap(), msg);
+
+        let msg = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": RawRequest::METHOD,
+            "params": {"method": "rust-analyzer/viewCrateGraph", "params": {"full": true}},
+        })
+        .to_string();
+        assert_eq!(
+            serde_json::from_str::<Value>(&unwrap_raw_request(msg).unwrap()).unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "rust-analyzer/viewCrateGraph",
+                "params": {"full": true},
+            }),
+        );
+    }
+
+    #[test]
+    fn test_lookup_section() {
+        let settings = json!({"a": {"b": 1}});
+        assert_eq!(lookup_section(None, Some("a")), Value::Null);
+        assert_eq!(lookup_section(Some(&settings), None), settings);
+        assert_eq!(lookup_section(Some(&settings), Some("a.b")), json!(1));
+        assert_eq!(lookup_section(Some(&settings), Some("a.c")), settings);
+    }
+}
@@ -0,0 +1,114 @@
+//! Conversion between file paths and `file://` URIs.
+//!
+//! Paths are percent-encoded and Windows paths use the `file:///C:/...` form, so language servers can parse them.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use lsp_types::Uri;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+
+/// Characters that are percent-encoded within the URI path.
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Create `file://` URI for an absolute path.
+pub(crate) fn path_to_file_uri(path: &Path) -> Result<Uri> {
+    let path = path.to_str().context("path is not UTF-8")?;
+    let path = if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_owned()
+    };
+
+    let mut uri = "file://".to_owned();
+    // drive letters, e.g. `C:/foo` => `/C:/foo`
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    uri.extend(utf8_percent_encode(&path, PATH));
+    uri.parse().context("parse file URI")
+}
+
+/// Get absolute path of a `file://` URI.
+///
+/// Returns `None` for other schemes and relative paths.
+pub(crate) fn file_uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    if !uri
+        .scheme()
+        .is_some_and(|scheme| scheme.as_str().eq_ignore_ascii_case("file"))
+    {
+        return None;
+    }
+
+    let path = percent_decode_str(uri.path().as_str()).decode_utf8().ok()?;
+    let path = if cfg!(windows) {
+        // `/C:/foo` => `C:/foo`
+        path.strip_prefix('/').unwrap_or(&path)
+    } else {
+        &path
+    };
+    let path = PathBuf::from(path);
+    path.is_absolute().then_some(path)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for path in ["/ws/src/lib.rs", "/my ws/a#b/c%d/ä.rs", "/ws/[x]"] {
+            let uri = path_to_file_uri(Path::new(path)).unwrap();
+            assert_eq!(file_uri_to_path(&uri).unwrap(), Path::new(path));
+        }
+
+        assert_eq!(
+            path_to_file_uri(Path::new("/my ws/ä.rs")).unwrap().as_str(),
+            "file:///my%20ws/%C3%A4.rs"
+        );
+    }
+
+    #[test]
+    fn test_file_uri_to_path() {
+        let parse = |s: &str| file_uri_to_path(&s.parse().unwrap());
+        assert_eq!(
+            parse("file:///ws/lib.rs"),
+            Some(PathBuf::from("/ws/lib.rs"))
+        );
+        assert_eq!(
+            parse("FILE:///ws/lib.rs"),
+            Some(PathBuf::from("/ws/lib.rs"))
+        );
+        assert_eq!(parse("https://example.com/lib.rs"), None);
+        assert_eq!(parse("file:relative"), None);
+    }
+}
+
+#[cfg(all(test, windows))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let uri = path_to_file_uri(Path::new(r"C:\my ws\src\lib.rs")).unwrap();
+        assert_eq!(uri.as_str(), "file:///C:/my%20ws/src/lib.rs");
+        assert_eq!(
+            file_uri_to_path(&uri).unwrap(),
+            Path::new(r"C:\my ws\src\lib.rs")
+        );
+    }
+}
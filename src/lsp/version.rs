@@ -0,0 +1,192 @@
+//! Language server version checks.
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use rmcp::schemars;
+
+/// Minimum language server version required by a programming language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MinimumVersion {
+    /// Release number, matched against the first `<major>.<minor>.<patch>` in the reported version.
+    Release([u64; 3]),
+
+    /// Build date (`YYYY-MM-DD`), matched against the first date in the reported version.
+    Date(&'static str),
+}
+
+impl MinimumVersion {
+    /// Parse [`Release`](Self::Release) from `<major>.<minor>.<patch>`.
+    pub(crate) fn parse_release(s: &str) -> Result<Self, String> {
+        parse_release(s)
+            .filter(|release| {
+                release
+                    .iter()
+                    .map(|part| part.to_string())
+                    .collect::<Vec<_>>()
+                    .join(".")
+                    == s
+            })
+            .map(Self::Release)
+            .ok_or_else(|| format!("invalid version `{s}`, expected `<major>.<minor>.<patch>`"))
+    }
+}
+
+impl Display for MinimumVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Release([major, minor, patch]) => write!(f, "{major}.{minor}.{patch}"),
+            Self::Date(date) => write!(f, "build date {date}"),
+        }
+    }
+}
+
+/// How to react to language servers that are too old.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum VersionCheckMode {
+    /// Refuse to start.
+    #[default]
+    Error,
+
+    /// Log a warning.
+    Warn,
+
+    /// Do not check the version.
+    Off,
+}
+
+/// Result of a version check.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum VersionStatus {
+    /// Version is supported.
+    Ok,
+
+    /// Version is older than the required minimum.
+    TooOld { minimum: String },
+
+    /// Version cannot be checked.
+    Unknown { reason: String },
+}
+
+impl VersionStatus {
+    /// Check reported version against minimum.
+    pub(crate) fn check(version: Option<&str>, minimum: Option<&MinimumVersion>) -> Self {
+        let Some(minimum) = minimum else {
+            return Self::Ok;
+        };
+        let Some(version) = version else {
+            return Self::Unknown {
+                reason: "language server did not report a version".to_owned(),
+            };
+        };
+
+        let ok = match minimum {
+            MinimumVersion::Release(minimum) => parse_release(version).map(|v| &v >= minimum),
+            MinimumVersion::Date(minimum) => parse_date(version).map(|v| v >= *minimum),
+        };
+        match ok {
+            Some(true) => Self::Ok,
+            Some(false) => Self::TooOld {
+                minimum: minimum.to_string(),
+            },
+            None => Self::Unknown {
+                reason: format!("cannot parse version `{version}` to compare it to {minimum}"),
+            },
+        }
+    }
+}
+
+/// Find first `<major>.<minor>.<patch>`.
+fn parse_release(version: &str) -> Option<[u64; 3]> {
+    version
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find_map(|word| {
+            let mut parts = word.split('.').map(|part| part.parse::<u64>().ok());
+            let release = [parts.next()??, parts.next()??, parts.next()??];
+            parts.next().is_none().then_some(release)
+        })
+}
+
+/// Find first `YYYY-MM-DD`.
+fn parse_date(version: &str) -> Option<&str> {
+    version
+        .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+        .find(|word| {
+            let parts = word.split('-').collect::<Vec<_>>();
+            matches!(parts.as_slice(), [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_release("1.96.0 (ac68faa 2026-05-25)"),
+            Some([1, 96, 0])
+        );
+        assert_eq!(
+            parse_release("0.3.2345-standalone (abc 2025-03-17)"),
+            Some([0, 3, 2345])
+        );
+        assert_eq!(parse_release("v2"), None);
+
+        assert_eq!(
+            parse_date("1.96.0 (ac68faa 2026-05-25)"),
+            Some("2026-05-25")
+        );
+        assert_eq!(parse_date("1.96.0"), None);
+
+        assert_eq!(
+            MinimumVersion::parse_release("1.2.3"),
+            Ok(MinimumVersion::Release([1, 2, 3])),
+        );
+        assert_eq!(
+            MinimumVersion::parse_release("1.2"),
+            Err("invalid version `1.2`, expected `<major>.<minor>.<patch>`".to_owned()),
+        );
+        MinimumVersion::parse_release("v1.2.3").unwrap_err();
+    }
+
+    #[test]
+    fn test_check() {
+        let date = MinimumVersion::Date("2023-01-01");
+        let release = MinimumVersion::Release([1, 2, 3]);
+
+        assert_eq!(VersionStatus::check(None, None), VersionStatus::Ok);
+        assert_eq!(
+            VersionStatus::check(Some("1.96.0 (ac68faa 2026-05-25)"), Some(&date)),
+            VersionStatus::Ok,
+        );
+        assert_eq!(
+            VersionStatus::check(Some("0.0.0 (abc 2022-06-01)"), Some(&date)),
+            VersionStatus::TooOld {
+                minimum: "build date 2023-01-01".to_owned()
+            },
+        );
+        assert_eq!(
+            VersionStatus::check(Some("1.10.0"), Some(&release)),
+            VersionStatus::Ok,
+        );
+        assert_eq!(
+            VersionStatus::check(Some("1.2.2"), Some(&release)),
+            VersionStatus::TooOld {
+                minimum: "1.2.3".to_owned()
+            },
+        );
+        assert_eq!(
+            VersionStatus::check(Some("nightly"), Some(&release)),
+            VersionStatus::Unknown {
+                reason: "cannot parse version `nightly` to compare it to 1.2.3".to_owned()
+            },
+        );
+        assert_eq!(
+            VersionStatus::check(None, Some(&release)),
+            VersionStatus::Unknown {
+                reason: "language server did not report a version".to_owned()
+            },
+        );
+    }
+}
@@ -0,0 +1,570 @@
+//! Workspace that consists of one or more folders.
+//!
+//! Monorepos may contain several independent projects, which are passed as separate workspace folders to the language
+//! server. With multiple folders, paths that are reported to the MCP client are prefixed with the folder name, e.g.
+//! `backend/src/lib.rs`. A single folder keeps the plain relative paths.
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::{Context, Result, ensure};
+use itertools::Itertools;
+use lsp_types::WorkspaceFolder;
+
+use super::{dependency::DependencyRoots, location::PathStyle, uri::path_to_file_uri};
+
+/// Workspace folders, the first one is the root.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Workspace {
+    folders: Arc<[Folder]>,
+
+    /// Roots of dependencies that were reported via virtual paths.
+    dependencies: DependencyRoots,
+
+    /// How paths are rendered in tool results.
+    path_style: PathStyle,
+
+    /// Extra directories whose files may be shown in strict mode, `None` if all files may be shown.
+    ///
+    /// See [`allows`](Self::allows).
+    jail: Option<Arc<[PathBuf]>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Folder {
+    /// Name that prefixes paths within the folder.
+    name: String,
+
+    /// Absolute path.
+    path: PathBuf,
+}
+
+impl Workspace {
+    /// Create workspace from absolute, canonicalized paths.
+    pub(crate) fn new(paths: impl IntoIterator<Item = PathBuf>) -> Result<Self> {
+        Self::from_folders(vec![], DependencyRoots::default(), paths)
+    }
+
+    /// Add folders to the existing ones.
+    fn from_folders(
+        mut folders: Vec<Folder>,
+        dependencies: DependencyRoots,
+        paths: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<Self> {
+        for path in paths {
+            ensure!(
+                path.is_absolute(),
+                "workspace folder is not absolute: {}",
+                path.display()
+            );
+            let name = path
+                .file_name()
+                .with_context(|| format!("workspace folder has no name: {}", path.display()))?
+                .to_string_lossy()
+                .into_owned();
+            if folders.iter().any(|folder| folder.path == path) {
+                continue;
+            }
+            ensure!(
+                !folders.iter().any(|folder| folder.name == name),
+                "workspace folders must have distinct names: {name}",
+            );
+            folders.push(Folder { name, path });
+        }
+        ensure!(!folders.is_empty(), "workspace needs at least one folder");
+
+        Ok(Self {
+            folders: folders.into(),
+            dependencies,
+            path_style: PathStyle::default(),
+            jail: None,
+        })
+    }
+
+    /// First folder, used to detect and start the language servers.
+    pub(crate) fn root(&self) -> &Path {
+        &self.folders[0].path
+    }
+
+    /// All folders.
+    pub(crate) fn folders(&self) -> impl Iterator<Item = &Path> {
+        self.folders.iter().map(|folder| folder.path.as_path())
+    }
+
+    /// Folders with their names.
+    pub(crate) fn named_folders(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.folders
+            .iter()
+            .map(|folder| (folder.name.as_str(), folder.path.as_path()))
+    }
+
+    /// New workspace with an additional folder.
+    pub(crate) fn with_folder(&self, path: PathBuf) -> Result<Self> {
+        Self::from_folders(self.folders.to_vec(), self.dependencies.clone(), [path])
+    }
+
+    /// New workspace without the folder of the given name or path.
+    pub(crate) fn without_folder(&self, folder: &str) -> Result<Self> {
+        let remaining = self
+            .folders
+            .iter()
+            .filter(|f| f.name != folder && f.path != Path::new(folder))
+            .map(|f| f.path.clone())
+            .collect::<Vec<_>>();
+        ensure!(
+            remaining.len() < self.folders.len(),
+            "unknown workspace folder: {folder}"
+        );
+        ensure!(
+            !remaining.is_empty(),
+            "cannot remove the last workspace folder"
+        );
+        Self::from_folders(vec![], self.dependencies.clone(), remaining)
+    }
+
+    /// Paths are prefixed with the folder name.
+    fn prefixed(&self) -> bool {
+        self.folders.len() > 1
+    }
+
+    /// Folder that contains the path, the innermost one for nested folders.
+    fn folder_of(&self, path: &Path) -> Option<&Folder> {
+        self.folders
+            .iter()
+            .filter(|folder| path.starts_with(&folder.path))
+            .max_by_key(|folder| folder.path.components().count())
+    }
+
+    /// Path is within one of the folders.
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        self.folder_of(path).is_some()
+    }
+
+    /// Turn absolute path into one that is relative to the workspace.
+    ///
+    /// Returns `None` if the path is outside of all folders.
+    pub(crate) fn relativize(&self, path: &Path) -> Option<String> {
+        let folder = self.folder_of(path)?;
+        let rel = path
+            .strip_prefix(&folder.path)
+            .expect("checked by folder_of")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .join("/");
+        Some(match (self.prefixed(), rel.is_empty()) {
+            (false, _) => rel,
+            (true, true) => folder.name.clone(),
+            (true, false) => format!("{}/{rel}", folder.name),
+        })
+    }
+
+    /// Same workspace, but render paths in the given style.
+    pub(crate) fn with_path_style(self, path_style: PathStyle) -> Self {
+        Self { path_style, ..self }
+    }
+
+    /// How paths are rendered in tool results.
+    pub(crate) fn path_style(&self) -> PathStyle {
+        self.path_style
+    }
+
+    /// Same workspace, but only show files within the workspace, dependencies, and the given extra directories.
+    ///
+    /// Relative directories are resolved against the root. `None` shows all files.
+    pub(crate) fn with_jail(self, jail: Option<Vec<PathBuf>>) -> Self {
+        let jail = jail.map(|dirs| {
+            dirs.iter()
+                .map(|dir| normalize(&self.root().join(dir)))
+                .collect()
+        });
+        Self { jail, ..self }
+    }
+
+    /// Checks if the file at the given absolute path may be shown to the MCP client.
+    ///
+    /// Without a jail, this is true for all files. Otherwise the file must be within the workspace, a known
+    /// dependency, or one of the extra directories of the jail. Symlinks are resolved, so they cannot point out of the
+    /// jail. Files that cannot be resolved, e.g. because they do not exist, are not allowed.
+    pub(crate) fn allows(&self, path: &Path) -> bool {
+        let Some(dirs) = &self.jail else {
+            return true;
+        };
+        let Ok(path) = std::fs::canonicalize(path) else {
+            return false;
+        };
+        let within = |dir: &Path| std::fs::canonicalize(dir).is_ok_and(|dir| path.starts_with(dir));
+        self.folders().any(within)
+            || self
+                .dependencies
+                .source_paths()
+                .iter()
+                .any(|dir| within(dir))
+            || dirs.iter().any(|dir| within(dir))
+    }
+
+    /// Only files that [`allows`](Self::allows) accepts are shown.
+    pub(crate) fn is_jailed(&self) -> bool {
+        self.jail.is_some()
+    }
+
+    /// Roots of dependencies, used for virtual paths like `dep://serde@1.0.200/src/lib.rs`.
+    pub(crate) fn dependencies(&self) -> &DependencyRoots {
+        &self.dependencies
+    }
+
+    /// Turn path that is relative to the workspace into an absolute one.
+    ///
+    /// Absolute paths are returned as is, virtual paths of dependencies are resolved if they are known. Relative paths
+    /// without a known folder prefix are resolved against the root.
+    pub(crate) fn resolve(&self, path: &str) -> PathBuf {
+        if let Some(path) = self.dependencies.resolve(path) {
+            return path;
+        }
+        if Path::new(path).is_absolute() {
+            return PathBuf::from(path);
+        }
+
+        if self.prefixed() {
+            let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+            if let Some(folder) = self.folders.iter().find(|folder| folder.name == name) {
+                return folder.path.join(rest);
+            }
+        }
+        self.root().join(path)
+    }
+
+    /// Names that prefix relative paths, empty for a single folder.
+    pub(crate) fn prefixes(&self) -> impl Iterator<Item = &str> {
+        self.folders
+            .iter()
+            .filter(|_| self.prefixed())
+            .map(|folder| folder.name.as_str())
+    }
+
+    /// Folders as reported to the language server.
+    pub(crate) fn lsp_folders(&self) -> Result<Vec<WorkspaceFolder>> {
+        self.lsp_folders_of(self.folders.iter())
+    }
+
+    /// Folders that were added and removed compared to the previous workspace, as reported to the language server.
+    pub(crate) fn lsp_folders_diff(
+        &self,
+        previous: &Self,
+    ) -> Result<(Vec<WorkspaceFolder>, Vec<WorkspaceFolder>)> {
+        let added = self.lsp_folders_of(
+            self.folders
+                .iter()
+                .filter(|folder| !previous.folders.iter().any(|f| f.path == folder.path)),
+        )?;
+        let removed = previous.lsp_folders_of(
+            previous
+                .folders
+                .iter()
+                .filter(|folder| !self.folders.iter().any(|f| f.path == folder.path)),
+        )?;
+        Ok((added, removed))
+    }
+
+    fn lsp_folders_of<'a>(
+        &self,
+        folders: impl Iterator<Item = &'a Folder>,
+    ) -> Result<Vec<WorkspaceFolder>> {
+        folders
+            .map(|folder| {
+                Ok(WorkspaceFolder {
+                    uri: path_to_file_uri(&folder.path).context("workspace URI")?,
+                    name: if self.prefixed() {
+                        folder.name.clone()
+                    } else {
+                        "root".to_owned()
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// Workspace that can change at runtime, shared by all MCP sessions.
+#[derive(Debug, Clone)]
+pub(crate) struct SharedWorkspace {
+    inner: Arc<RwLock<Workspace>>,
+
+    /// Increased whenever files within the workspace change.
+    file_revision: Arc<AtomicU64>,
+}
+
+impl SharedWorkspace {
+    /// Current workspace.
+    pub(crate) fn get(&self) -> Workspace {
+        self.inner.read().expect("not poisoned").clone()
+    }
+
+    /// Replace workspace, returns the previous and the new one.
+    ///
+    /// The workspace is locked during the update, so concurrent updates do not get lost. Files of added or removed
+    /// folders count as [changed](Self::files_changed).
+    pub(crate) fn update<F>(&self, f: F) -> Result<(Workspace, Workspace)>
+    where
+        F: FnOnce(&Workspace) -> Result<Workspace>,
+    {
+        let mut guard = self.inner.write().expect("not poisoned");
+        let new = f(&guard)?;
+        let previous = std::mem::replace(&mut *guard, new.clone());
+        self.files_changed();
+        Ok((previous, new))
+    }
+
+    /// Revision of the files, changes whenever files within the workspace change.
+    ///
+    /// Used to invalidate caches.
+    pub(crate) fn file_revision(&self) -> u64 {
+        self.file_revision.load(Ordering::SeqCst)
+    }
+
+    /// Files within the workspace changed.
+    pub(crate) fn files_changed(&self) {
+        self.file_revision.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl From<Workspace> for SharedWorkspace {
+    fn from(workspace: Workspace) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(workspace)),
+            file_revision: Default::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for Workspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, folder) in self.folders.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", folder.path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolve `.` and `..` without touching the file system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lsp::dependency::{Origin, SourceRoot};
+
+    use super::*;
+
+    fn workspace(paths: &[&str]) -> Workspace {
+        Workspace::new(paths.iter().map(PathBuf::from)).unwrap()
+    }
+
+    #[test]
+    fn test_single() {
+        let ws = workspace(&["/ws/main"]);
+        assert_eq!(ws.root(), Path::new("/ws/main"));
+        assert_eq!(
+            ws.relativize(Path::new("/ws/main/src/lib.rs")).as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(ws.relativize(Path::new("/ws/other/src/lib.rs")), None);
+        assert_eq!(ws.resolve("src/lib.rs"), Path::new("/ws/main/src/lib.rs"));
+        assert_eq!(ws.resolve("/abs/lib.rs"), Path::new("/abs/lib.rs"));
+        assert_eq!(ws.prefixes().count(), 0);
+        assert_eq!(ws.lsp_folders().unwrap()[0].name, "root");
+    }
+
+    #[test]
+    fn test_multiple() {
+        let ws = workspace(&["/ws/main", "/ws/dep", "/ws/main/nested"]);
+        assert_eq!(ws.root(), Path::new("/ws/main"));
+        assert_eq!(
+            ws.relativize(Path::new("/ws/main/src/lib.rs")).as_deref(),
+            Some("main/src/lib.rs")
+        );
+        assert_eq!(
+            ws.relativize(Path::new("/ws/dep/src/lib.rs")).as_deref(),
+            Some("dep/src/lib.rs")
+        );
+        assert_eq!(
+            ws.relativize(Path::new("/ws/main/nested/lib.rs"))
+                .as_deref(),
+            Some("nested/lib.rs")
+        );
+        assert_eq!(ws.relativize(Path::new("/ws/dep")).as_deref(), Some("dep"));
+        assert_eq!(ws.relativize(Path::new("/ws/depx/lib.rs")), None);
+        assert!(ws.contains(Path::new("/ws/dep/src/lib.rs")));
+        assert!(!ws.contains(Path::new("/ws/other")));
+
+        assert_eq!(
+            ws.resolve("dep/src/lib.rs"),
+            Path::new("/ws/dep/src/lib.rs")
+        );
+        assert_eq!(ws.resolve("nested"), Path::new("/ws/main/nested/"));
+        assert_eq!(ws.resolve("src/lib.rs"), Path::new("/ws/main/src/lib.rs"));
+        assert_eq!(ws.prefixes().collect::<Vec<_>>(), ["main", "dep", "nested"]);
+        assert_eq!(
+            ws.lsp_folders()
+                .unwrap()
+                .into_iter()
+                .map(|folder| folder.name)
+                .collect::<Vec<_>>(),
+            ["main", "dep", "nested"]
+        );
+        assert_eq!(ws.to_string(), "/ws/main, /ws/dep, /ws/main/nested");
+    }
+
+    #[test]
+    fn test_new() {
+        // duplicates are ignored
+        assert_eq!(
+            workspace(&["/ws/main", "/ws/main"]),
+            workspace(&["/ws/main"])
+        );
+        Workspace::new([PathBuf::from("/a/main"), PathBuf::from("/b/main")]).unwrap_err();
+        Workspace::new([PathBuf::from("relative")]).unwrap_err();
+        Workspace::new([PathBuf::from("/")]).unwrap_err();
+        Workspace::new([]).unwrap_err();
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let ws = workspace(&["/ws/main"]);
+        let ws2 = ws.with_folder(PathBuf::from("/ws/dep")).unwrap();
+        assert_eq!(ws2, workspace(&["/ws/main", "/ws/dep"]));
+        ws.with_folder(PathBuf::from("/other/main")).unwrap_err();
+
+        let (added, removed) = ws2.lsp_folders_diff(&ws).unwrap();
+        assert_eq!(
+            added.iter().map(|f| f.uri.as_str()).collect::<Vec<_>>(),
+            ["file:///ws/dep"]
+        );
+        assert!(removed.is_empty());
+
+        assert_eq!(ws2.without_folder("dep").unwrap(), ws);
+        assert_eq!(
+            ws2.without_folder("/ws/main").unwrap(),
+            workspace(&["/ws/dep"])
+        );
+        ws2.without_folder("nope").unwrap_err();
+        ws.without_folder("main").unwrap_err();
+
+        let shared = SharedWorkspace::from(ws.clone());
+        let (previous, current) = shared
+            .update(|ws| ws.with_folder(PathBuf::from("/ws/dep")))
+            .unwrap();
+        assert_eq!(previous, ws);
+        assert_eq!(current, ws2);
+        assert_eq!(shared.get(), ws2);
+        shared.update(|ws| ws.without_folder("nope")).unwrap_err();
+        assert_eq!(shared.get(), ws2);
+        assert_eq!(shared.file_revision(), 1);
+
+        let revision = shared.file_revision();
+        shared.clone().files_changed();
+        assert_ne!(shared.file_revision(), revision);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("/ws/main/./src/../../other/lib.rs")),
+            Path::new("/ws/other/lib.rs"),
+        );
+        assert_eq!(normalize(Path::new("/../lib.rs")), Path::new("/lib.rs"));
+    }
+
+    /// Create empty files below the directory.
+    fn touch(dir: &Path, files: &[&str]) {
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_jail() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = std::fs::canonicalize(dir.path()).unwrap();
+        touch(
+            &dir,
+            &[
+                "ws/main/src/lib.rs",
+                "ws/extra/lib.rs",
+                "ws/other/lib.rs",
+                "abs/lib.rs",
+                "absolute/lib.rs",
+                "cargo/registry/src/index/serde-1.0.200/src/lib.rs",
+                "home/.cargo/registry/src/index/serde-1.0.200/src/lib.rs",
+            ],
+        );
+
+        let ws = Workspace::new([dir.join("ws/main")]).unwrap();
+        ws.dependencies().set_sources(vec![SourceRoot::new(
+            Origin::Registry,
+            dir.join("cargo/registry/src"),
+        )]);
+        let dep = dir.join("cargo/registry/src/index/serde-1.0.200/src/lib.rs");
+        assert!(ws.allows(&dir.join("ws/other/lib.rs")));
+        assert!(!ws.is_jailed());
+
+        let ws = ws.with_jail(Some(vec![PathBuf::from("../extra"), dir.join("abs")]));
+        assert!(ws.is_jailed());
+        assert!(ws.allows(&dir.join("ws/main/src/lib.rs")));
+        assert!(ws.allows(&dep));
+        assert!(ws.allows(&dir.join("ws/extra/lib.rs")));
+        assert!(ws.allows(&dir.join("abs/lib.rs")));
+        assert!(!ws.allows(Path::new("/etc/passwd")));
+        assert!(!ws.allows(&dir.join("ws/other/lib.rs")));
+        assert!(!ws.allows(&dir.join("ws/main/../other/lib.rs")));
+        assert!(!ws.allows(&dir.join("absolute/lib.rs")));
+        assert!(!ws.allows(&dir.join("home/.cargo/registry/src/index/serde-1.0.200/src/lib.rs")));
+        // files that do not exist cannot be resolved
+        assert!(!ws.allows(&dir.join("ws/main/src/missing.rs")));
+
+        let ws = ws.with_jail(Some(vec![]));
+        assert!(ws.allows(&dir.join("ws/main/src/lib.rs")));
+        assert!(!ws.allows(&dir.join("ws/extra/lib.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_jail_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = std::fs::canonicalize(dir.path()).unwrap();
+        touch(&dir, &["ws/main/src/lib.rs", "secret/key.rs"]);
+        std::os::unix::fs::symlink(dir.join("secret"), dir.join("ws/main/escape")).unwrap();
+        std::os::unix::fs::symlink(dir.join("secret/key.rs"), dir.join("ws/main/src/key.rs"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.join("ws/main/src/lib.rs"), dir.join("secret/lib.rs"))
+            .unwrap();
+
+        let ws = Workspace::new([dir.join("ws/main")])
+            .unwrap()
+            .with_jail(Some(vec![]));
+        assert!(ws.allows(&dir.join("ws/main/src/lib.rs")));
+        assert!(!ws.allows(&dir.join("ws/main/escape/key.rs")));
+        assert!(!ws.allows(&dir.join("ws/main/src/key.rs")));
+        // links into the jail point to allowed files
+        assert!(ws.allows(&dir.join("secret/lib.rs")));
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    net::SocketAddr,
     path::{Path, PathBuf},
     process::{ExitCode, Termination},
     sync::Arc,
@@ -6,20 +7,58 @@ use std::{
 
 use anyhow::{Context, Result, ensure};
 use clap::Parser;
+use config_file::ConfigFile;
 use constants::{REVISION, VERSION, VERSION_STRING};
 use futures::FutureExt;
-use io_intercept::{BoxRead, BoxWrite, ReadFork, WriteFork};
-use lang::{ProgrammingLanguage, ProgrammingLanguageQuirks};
+use io_intercept::{BoxRead, BoxWrite, InterceptIoCLIConfig, Peer, TrafficTail};
+use lang::{
+    LanguageCLIConfig, ProgrammingLanguage, ProgrammingLanguageQuirks, install::InstallCLIConfig,
+    overrides::LspOverridesCLIConfig,
+};
 use logging::{LoggingCLIConfig, setup_logging};
 use lsp::{
-    init::{init_lsp, spawn_lsp},
-    progress_guard::ProgressGuard,
+    cache::{CacheCLIConfig, CacheRegistry},
+    dependency_sources::DependencySourcesCLIConfig,
+    diagnostics::Diagnostics,
+    file_watcher::FileWatcherCLIConfig,
+    init::{LspProcessCLIConfig, init_lsp, spawn_lsp},
+    messages::ServerMessages,
+    metrics::MetricsCLIConfig,
+    mock::MockCLIConfig,
+    progress_guard::{ProgressGuard, ReadinessCLIConfig},
+    replay::ReplayCLIConfig,
+    retry::RetryCLIConfig,
+    scheduler::{Scheduler, SchedulerCLIConfig},
+    server::LanguageServer,
+    shutdown::{LspChild, ShutdownCLIConfig},
+    spawn_error::SpawnError,
+    symbol_cache::SymbolCacheCLIConfig,
+    transport::ClientHandler,
+    version::{VersionCheckMode, VersionStatus},
+    workspace::{SharedWorkspace, Workspace},
 };
 use lsp_client::LspClient;
-use mcp::CodeExplorer;
+#[cfg(unix)]
+use mcp::daemon::{DaemonCLIConfig, Role};
+use mcp::{
+    CodeExplorer,
+    call::CallCLIConfig,
+    export::ExportSymbolsCLIConfig,
+    failed::FailedExplorer,
+    http::HttpCLIConfig,
+    roots::RootsExplorer,
+    sarif::ExportDiagnosticsCLIConfig,
+    tools::ToolsCLIConfig,
+    warmup::{Warmup, WarmupCLIConfig},
+};
+use reload::HotReload;
 use rmcp::{ServiceExt, transport::stdio};
+use signal::ShutdownSignal;
 use tasks::TaskManager;
-use tracing::{debug, info, warn};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
 // used in integration tests
 #[cfg(test)]
@@ -31,12 +70,17 @@ use predicates as _;
 #[cfg(test)]
 use tempfile as _;
 
+mod cli;
+mod config_file;
 mod constants;
+mod correlation;
 mod io_intercept;
 mod lang;
 mod logging;
 mod lsp;
 mod mcp;
+mod reload;
+mod signal;
 mod tasks;
 
 /// Provides a "common sense" interface for a language model via Model Context Provider (MCP).
@@ -46,24 +90,151 @@ mod tasks;
 #[command(version = VERSION_STRING)]
 struct Args {
     /// Workspace location, i.e. the root of the project.
-    #[clap(long, env = "COMMON_SENSE_CODER_WORKSPACE")]
-    workspace: PathBuf,
-
-    /// Intercept IO to/from language server and MCP client for debugging.
     ///
-    /// Dumps are stored in separate files in the provided directory.
-    #[clap(long, env = "COMMON_SENSE_CODER_INTERCEPT_IO")]
-    intercept_io: Option<PathBuf>,
+    /// Can be passed multiple times, e.g. for monorepos with several independent projects. Paths are then prefixed
+    /// with the folder name. The first folder is used to detect the programming language.
+    ///
+    /// If not set, the workspace is derived from the roots announced by the MCP client. This is only supported for
+    /// stdio clients.
+    #[clap(long, global = true, env = "COMMON_SENSE_CODER_WORKSPACE")]
+    workspace: Vec<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    intercept_io_cfg: InterceptIoCLIConfig,
+
+    #[clap(flatten)]
+    replay_cfg: ReplayCLIConfig,
+
+    #[clap(flatten)]
+    mock_cfg: MockCLIConfig,
 
     /// Programming language.
-    #[clap(long, default_value = "rust")]
-    programming_language: ProgrammingLanguage,
+    ///
+    /// Use `auto` to detect it from the content of the workspace root. Can be repeated or comma-separated to use
+    /// multiple languages at the same time, in which case one language server is spawned per language.
+    #[clap(long, default_value = "rust", value_delimiter = ',')]
+    programming_language: Vec<ProgrammingLanguage>,
+
+    /// Secondary language for auxiliary files, e.g. `toml` for `Cargo.toml` or `yaml` for CI configs.
+    ///
+    /// Can be repeated or comma-separated. Secondary language servers are spawned alongside the primary ones and only
+    /// handle files that no primary language server handles. They are skipped if their binary is missing and not used
+    /// for workspace-wide symbol searches.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        env = "COMMON_SENSE_CODER_SECONDARY_LANGUAGES"
+    )]
+    secondary_language: Vec<ProgrammingLanguage>,
+
+    /// Programming language config.
+    #[clap(flatten)]
+    language_cfg: LanguageCLIConfig,
+
+    /// Language server overrides.
+    #[clap(flatten)]
+    lsp_overrides_cfg: LspOverridesCLIConfig,
+
+    /// Language server process config.
+    #[clap(flatten)]
+    lsp_process_cfg: LspProcessCLIConfig,
+
+    /// Language server installation config.
+    #[clap(flatten)]
+    install_cfg: InstallCLIConfig,
+
+    /// How to react to language servers that are older than the minimum version that we support.
+    #[clap(
+        long,
+        default_value = "error",
+        env = "COMMON_SENSE_CODER_LSP_VERSION_CHECK"
+    )]
+    lsp_version_check: VersionCheckMode,
+
+    /// Language server request scheduling config.
+    #[clap(flatten)]
+    scheduler_cfg: SchedulerCLIConfig,
+
+    /// Language server readiness config.
+    #[clap(flatten)]
+    readiness_cfg: ReadinessCLIConfig,
+
+    /// Language server request retry config.
+    #[clap(flatten)]
+    retry_cfg: RetryCLIConfig,
+
+    /// File watcher config.
+    #[clap(flatten)]
+    file_watcher_cfg: FileWatcherCLIConfig,
+
+    /// Workspace symbol cache config.
+    #[clap(flatten)]
+    symbol_cache_cfg: SymbolCacheCLIConfig,
+
+    /// Dependency source roots config.
+    #[clap(flatten)]
+    dependency_sources_cfg: DependencySourcesCLIConfig,
+
+    /// Cache memory budget config.
+    #[clap(flatten)]
+    cache_cfg: CacheCLIConfig,
+
+    /// Warm-up config.
+    #[clap(flatten)]
+    warmup_cfg: WarmupCLIConfig,
+
+    /// Language server request metrics config.
+    #[clap(flatten)]
+    metrics_cfg: MetricsCLIConfig,
+
+    /// Language server shutdown config.
+    #[clap(flatten)]
+    shutdown_cfg: ShutdownCLIConfig,
+
+    /// HTTP config.
+    #[clap(flatten)]
+    http_cfg: HttpCLIConfig,
+
+    /// Daemon config.
+    #[cfg(unix)]
+    #[clap(flatten)]
+    daemon_cfg: DaemonCLIConfig,
+
+    /// Command line tool call config.
+    #[clap(flatten)]
+    call_cfg: CallCLIConfig,
+
+    /// Tool config.
+    #[clap(flatten)]
+    tools_cfg: ToolsCLIConfig,
 
     /// Logging config.
     #[clap(flatten)]
     logging_cfg: LoggingCLIConfig,
 }
 
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Print the effective configuration as JSON and exit.
+    ///
+    /// This shows the merged result of CLI arguments, environment variables, the config file, and defaults, including
+    /// where each value comes from.
+    PrintConfig,
+
+    #[clap(flatten)]
+    Export(ExportCommand),
+}
+
+/// Subcommands that run against the language servers without any client.
+#[derive(Debug, clap::Subcommand)]
+enum ExportCommand {
+    ExportSymbols(ExportSymbolsCLIConfig),
+    ExportDiagnostics(ExportDiagnosticsCLIConfig),
+}
+
 fn main() {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -91,61 +262,215 @@ async fn main_async() -> Result<()> {
             return Err(e).context("load dotenv");
         }
     };
-    let args = Args::parse();
-    setup_logging(args.logging_cfg).context("logging setup")?;
+    let (mut args, config_file) =
+        ConfigFile::parse::<Args>(|args| args.workspace.first().cloned())?;
+    let log_filter = setup_logging(args.logging_cfg).context("logging setup")?;
     info!(
         version = VERSION,
         revision = REVISION,
         dotenv_path = dotenv_path
             .as_ref()
             .map(|p| tracing::field::display(p.display())),
+        config_file = config_file
+            .path()
+            .filter(|p| p.is_file())
+            .map(|p| tracing::field::display(p.display())),
         "start common sense coder"
     );
 
-    let mut tasks = TaskManager::new();
-
-    let workspace = Arc::<Path>::from(
-        args.workspace
-            .canonicalize()
-            .context("canonicalize workspace path")?,
-    );
-    info!(path=%workspace.display(), "workspace");
+    if matches!(args.command, Some(Command::PrintConfig)) {
+        let config = config_file.effective::<Args>()?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config).context("serialize config")?
+        );
+        return Ok(());
+    }
 
-    if let Some(intercept_io) = &args.intercept_io {
-        info!(path=%intercept_io.display(), "interception IO");
+    let config_reporter = config_file.reporter::<Args>();
+    let hot_reload = HotReload::new(config_file, log_filter);
 
-        tokio::fs::create_dir_all(intercept_io)
-            .await
-            .context("create directories for IO interception")?;
+    if args.replay_cfg.enabled() {
+        return args.replay_cfg.serve().await.context("replay");
+    }
+    if args.mock_cfg.enabled() {
+        return args.mock_cfg.serve().await.context("mock language server");
     }
 
-    let quirks = args.programming_language.quirks();
-    let (client, mut child) = spawn_lsp(
-        &quirks,
-        args.intercept_io.as_deref(),
-        &args.workspace,
-        &mut tasks,
-    )
-    .await
-    .context("spawn LSP")?;
-    let progress_guard = ProgressGuard::start(&mut tasks, &quirks, Arc::clone(&client));
+    let mut tasks = args.shutdown_cfg.task_manager();
 
-    let (stdin, stdout) = stdio();
-    let stdin = Box::pin(stdin) as BoxRead;
-    let stdout = Box::pin(stdout) as BoxWrite;
-    let (stdin, stdout) = if let Some(intercept_io) = &args.intercept_io {
-        let stdin =
-            Box::pin(ReadFork::new(stdin, intercept_io, "mcp.stdin.txt", &mut tasks).await?) as _;
-        let stdout =
-            Box::pin(WriteFork::new(stdout, intercept_io, "mcp.stdout.txt", &mut tasks).await?)
-                as _;
-        (stdin, stdout)
+    let workspace = if args.workspace.is_empty() {
+        None
     } else {
-        (stdin, stdout)
+        let folders = args
+            .workspace
+            .iter()
+            .map(|workspace| {
+                workspace.canonicalize().with_context(|| {
+                    format!("canonicalize workspace path: {}", workspace.display())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Some(Workspace::new(folders).context("set up workspace")?)
+    };
+    match &workspace {
+        Some(workspace) => info!(path=%workspace, "workspace"),
+        None => info!("no workspace, use roots of MCP client"),
+    }
+
+    // daemon mode relies on Unix sockets
+    #[cfg(unix)]
+    let daemon = args.daemon_cfg.enabled();
+    #[cfg(not(unix))]
+    let daemon = false;
+
+    ensure!(
+        !(daemon && args.http_cfg.listen().is_some()),
+        "`--daemon` cannot be combined with `--listen`",
+    );
+    ensure!(
+        !(args.call_cfg.enabled() && (daemon || args.http_cfg.listen().is_some())),
+        "`--call` and `--batch` cannot be combined with `--daemon` or `--listen`",
+    );
+    ensure!(
+        !(args.command.is_some()
+            && (daemon || args.http_cfg.listen().is_some() || args.call_cfg.enabled())),
+        "subcommands cannot be combined with `--daemon`, `--listen`, `--call`, or `--batch`",
+    );
+    ensure!(
+        workspace.is_some()
+            || !(daemon
+                || args.http_cfg.listen().is_some()
+                || args.call_cfg.enabled()
+                || args.command.is_some()),
+        "`--workspace` is required for `--daemon`, `--listen`, `--call`, `--batch`, and subcommands",
+    );
+    args.http_cfg.check_auth()?;
+    #[cfg(unix)]
+    let role = match &workspace {
+        Some(workspace) => args.daemon_cfg.role(workspace)?,
+        None => Role::Standalone,
+    };
+    #[cfg(unix)]
+    let daemon_listener = match role {
+        Role::Standalone => None,
+        Role::Proxy(socket) => {
+            return args.daemon_cfg.proxy(&socket).await.context("daemon proxy");
+        }
+        Role::Daemon(socket) => {
+            let Some(listener) = mcp::daemon::bind(&socket)? else {
+                return Ok(());
+            };
+            Some(listener)
+        }
+    };
+
+    args.intercept_io_cfg.init().await?;
+
+    let lsp_setup = LspSetup {
+        programming_language: args.programming_language,
+        secondary_language: args.secondary_language,
+        language_cfg: args.language_cfg,
+        lsp_overrides_cfg: args.lsp_overrides_cfg,
+        lsp_process_cfg: args.lsp_process_cfg,
+        install_cfg: args.install_cfg,
+        intercept_io_cfg: args.intercept_io_cfg.clone(),
+        version_check: args.lsp_version_check,
+        scheduler_cfg: args.scheduler_cfg,
+        readiness_cfg: args.readiness_cfg,
+        retry_cfg: args.retry_cfg,
+        file_watcher_cfg: args.file_watcher_cfg,
+        symbol_cache_cfg: args.symbol_cache_cfg,
+        dependency_sources_cfg: args.dependency_sources_cfg,
+        cache_registry: args.cache_cfg.registry(),
+        warmup_cfg: args.warmup_cfg,
+        metrics_cfg: args.metrics_cfg,
+        shutdown_cfg: args.shutdown_cfg,
+    };
+
+    let tools_cfg = args
+        .tools_cfg
+        .with_active_requests(args.intercept_io_cfg.active_requests())
+        .with_config_reporter(config_reporter);
+
+    let Some(workspace) = workspace else {
+        let (stdin, stdout) = stdio_transport(&args.intercept_io_cfg, &mut tasks).await?;
+        let mut res = tokio::select! {
+            res = serve_roots(&lsp_setup, tools_cfg, hot_reload, stdin, stdout) => {
+                res.context("main")
+            }
+            e = tasks.run() => {
+                Err(e).context("tasks")
+            }
+        };
+
+        if let Err(e) = &res {
+            warn!(%e, "system failed");
+        }
+
+        info!("shutdown server");
+        res = res.and(tasks.shutdown().await.context("task shutdown"));
+        info!("shutdown complete");
+        lsp_setup.intercept_io_cfg.record_exit(&res);
+        return res;
+    };
+
+    #[cfg(unix)]
+    let daemon_transport = daemon_listener.map(|listener| McpTransport::Daemon {
+        cfg: args.daemon_cfg,
+        listener,
+    });
+    #[cfg(not(unix))]
+    let daemon_transport = None;
+
+    let transport = match (daemon_transport, args.http_cfg.listen()) {
+        (Some(transport), _) => transport,
+        (None, Some(addr)) => McpTransport::Http {
+            cfg: args.http_cfg,
+            addr,
+        },
+        (None, None) if args.call_cfg.enabled() => McpTransport::Call { cfg: args.call_cfg },
+        (None, None) if let Some(Command::Export(command)) = args.command => {
+            McpTransport::Command { command }
+        }
+        (None, None) => {
+            let (stdin, stdout) = stdio_transport(&args.intercept_io_cfg, &mut tasks).await?;
+            McpTransport::Stdio { stdin, stdout }
+        }
+    };
+
+    let (started, children) = match lsp_setup.spawn(&workspace, &mut tasks).await {
+        Ok((spawned, children)) => (Ok((spawned, transport)), children),
+        Err(e) => match (transport, e.downcast_ref::<SpawnError>()) {
+            (McpTransport::Stdio { stdin, stdout }, Some(spawn_error)) => {
+                // MCP clients often hide stderr, so also report the error to the client
+                eprintln!("Error: {e:?}");
+                (Err((spawn_error.to_string(), stdin, stdout)), vec![])
+            }
+            _ => return Err(e),
+        },
     };
 
+    let warmup = lsp_setup.warmup_cfg.spawn(&mut tasks);
+    let tools_cfg = tools_cfg.with_task_statuses(tasks.statuses());
     let mut res = tokio::select! {
-        res = main_inner(quirks, Arc::clone(&client), progress_guard, workspace, stdin, stdout) => {
+        res = async {
+            match started {
+                Ok((spawned, transport)) => {
+                    main_inner(
+                        spawned,
+                        workspace,
+                        &lsp_setup,
+                        tools_cfg,
+                        transport,
+                        hot_reload,
+                        warmup,
+                    )
+                    .await
+                }
+                Err((msg, stdin, stdout)) => serve_failed(msg, stdin, stdout).await,
+            }
+        } => {
             res.context("main")
         }
         e = tasks.run() => {
@@ -159,72 +484,524 @@ async fn main_async() -> Result<()> {
 
     info!("shutdown server");
 
-    debug!("dismantle LSP");
-    res = res.and(
-        async {
-            client
-                .shutdown()
+    res = res.and(lsp_setup.shutdown_cfg.shutdown_lsps(children).await);
+    res = res.and(tasks.shutdown().await.context("task shutdown"));
+
+    info!("shutdown complete");
+    lsp_setup.intercept_io_cfg.record_exit(&res);
+    res
+}
+
+/// MCP stdio transport, optionally intercepted.
+async fn stdio_transport(
+    intercept_io_cfg: &InterceptIoCLIConfig,
+    tasks: &mut TaskManager,
+) -> Result<(BoxRead, BoxWrite)> {
+    let (stdin, stdout) = stdio();
+    intercept_io_cfg
+        .fork(Box::pin(stdin), Box::pin(stdout), Peer::Mcp, None, tasks)
+        .await
+}
+
+/// Everything that is needed to start the language servers for a workspace.
+#[derive(Debug)]
+struct LspSetup {
+    programming_language: Vec<ProgrammingLanguage>,
+    secondary_language: Vec<ProgrammingLanguage>,
+    language_cfg: LanguageCLIConfig,
+    lsp_overrides_cfg: LspOverridesCLIConfig,
+    lsp_process_cfg: LspProcessCLIConfig,
+    install_cfg: InstallCLIConfig,
+    intercept_io_cfg: InterceptIoCLIConfig,
+    version_check: VersionCheckMode,
+    scheduler_cfg: SchedulerCLIConfig,
+    readiness_cfg: ReadinessCLIConfig,
+    retry_cfg: RetryCLIConfig,
+    file_watcher_cfg: FileWatcherCLIConfig,
+    symbol_cache_cfg: SymbolCacheCLIConfig,
+    dependency_sources_cfg: DependencySourcesCLIConfig,
+
+    /// Shared by the language servers of all workspaces.
+    cache_registry: CacheRegistry,
+
+    warmup_cfg: WarmupCLIConfig,
+    metrics_cfg: MetricsCLIConfig,
+    shutdown_cfg: ShutdownCLIConfig,
+}
+
+impl LspSetup {
+    /// Spawn language servers for the given workspace.
+    async fn spawn(
+        &self,
+        workspace: &Workspace,
+        tasks: &mut TaskManager,
+    ) -> Result<(Vec<SpawnedLsp>, Vec<LspChild>)> {
+        self.intercept_io_cfg.record_workspace(workspace);
+
+        let languages = resolve_languages(
+            &self.programming_language,
+            &self.secondary_language,
+            &self.language_cfg,
+            &self.lsp_overrides_cfg,
+            workspace.root(),
+        )?;
+
+        let mut spawned = Vec::with_capacity(languages.len());
+        let mut children = Vec::with_capacity(languages.len());
+        for (lang, quirks, secondary) in languages {
+            let name = lang.name();
+            let res = async {
+                let quirks = self
+                    .install_cfg
+                    .ensure_installed(&name, quirks)
+                    .await
+                    .with_context(|| format!("install LSP for {name}"))?;
+                let (client, handler, child, traffic_tail) = spawn_lsp(
+                    &name,
+                    &quirks,
+                    &self.lsp_process_cfg,
+                    &self.intercept_io_cfg,
+                    workspace,
+                    tasks,
+                )
                 .await
-                .context("shutdown language server")?;
-            client.exit().await.context("exit language server")?;
-            Ok(())
+                .with_context(|| format!("spawn LSP for {name}"))?;
+                Result::<_>::Ok((quirks, client, handler, child, traffic_tail))
+            }
+            .await;
+            let (quirks, client, handler, child, traffic_tail) = match res {
+                Ok(res) => res,
+                Err(e) if secondary => {
+                    let Some(spawn_error) = e.downcast_ref::<SpawnError>() else {
+                        return Err(e);
+                    };
+                    warn!(lang = name.as_str(), %spawn_error, "cannot start secondary language server, skip it");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let progress_guard = ProgressGuard::start(
+                tasks,
+                &quirks,
+                Arc::clone(&client),
+                self.readiness_cfg,
+                self.retry_cfg,
+            );
+            let messages = ServerMessages::start(tasks, &name, Arc::clone(&client));
+            let diagnostics = Diagnostics::start(tasks, &name, Arc::clone(&client));
+            let scheduler = self.scheduler_cfg.scheduler();
+
+            children.push((name.clone(), Arc::clone(&client), child));
+            spawned.push(SpawnedLsp {
+                name,
+                quirks,
+                client,
+                handler,
+                progress_guard,
+                messages,
+                diagnostics,
+                scheduler,
+                traffic_tail,
+                secondary,
+            });
         }
-        .await,
-    );
-    res = res.and(
-        async {
-            let status = child.wait().await.context("terminate language server")?;
 
-            // `status.exit_ok` is unstable,
-            // see https://github.com/rust-lang/rust/issues/84908
-            ensure!(status.success(), "LSP exit was not clean: {status}");
+        Ok((spawned, children))
+    }
+}
 
-            Ok(())
-        }
-        .await,
-    );
-    debug!("LSP gone");
+/// Language, its quirks, and whether it is a secondary language.
+type ResolvedLanguage = (
+    ProgrammingLanguage,
+    Arc<dyn ProgrammingLanguageQuirks>,
+    bool,
+);
 
-    res = res.and(tasks.shutdown().await.context("task shutdown"));
+/// Resolve primary and secondary languages, secondary ones are flagged.
+fn resolve_languages(
+    programming_language: &[ProgrammingLanguage],
+    secondary_language: &[ProgrammingLanguage],
+    language_cfg: &LanguageCLIConfig,
+    lsp_overrides_cfg: &LspOverridesCLIConfig,
+    workspace_root: &Path,
+) -> Result<Vec<ResolvedLanguage>> {
+    let languages =
+        ProgrammingLanguage::resolve(programming_language, workspace_root, language_cfg)
+            .context("set up programming languages")?;
+    let languages = lsp_overrides_cfg
+        .apply(languages)
+        .context("apply language server overrides")?;
+    let secondary = ProgrammingLanguage::resolve(secondary_language, workspace_root, language_cfg)
+        .context("set up secondary languages")?
+        .into_iter()
+        .filter(|(lang, _quirks)| !languages.iter().any(|(primary, _quirks)| primary == lang))
+        .collect::<Vec<_>>();
 
-    info!("shutdown complete");
-    res
+    Ok(languages
+        .into_iter()
+        .map(|(lang, quirks)| (lang, quirks, false))
+        .chain(
+            secondary
+                .into_iter()
+                .map(|(lang, quirks)| (lang, quirks, true)),
+        )
+        .collect())
 }
 
-async fn main_inner(
+/// Language server that was spawned but is not initialized yet.
+#[derive(Debug)]
+struct SpawnedLsp {
+    name: String,
     quirks: Arc<dyn ProgrammingLanguageQuirks>,
     client: Arc<LspClient>,
+    handler: Arc<ClientHandler>,
     progress_guard: ProgressGuard,
-    workspace: Arc<Path>,
-    stdin: BoxRead,
-    stdout: BoxWrite,
-) -> Result<()> {
-    let token_legend = init_lsp(&client, &workspace, &quirks)
-        .await
-        .context("init lsp")?;
+    messages: ServerMessages,
+    diagnostics: Diagnostics,
+    scheduler: Scheduler,
+    traffic_tail: Option<Arc<TrafficTail>>,
 
-    let service = CodeExplorer::new(progress_guard, token_legend, workspace)
-        .serve((stdin, stdout))
-        .await
-        .context("set up code explorer service")?;
-    let ct = service.cancellation_token();
-    let service_fut = service.waiting().fuse();
+    /// Only handles auxiliary files, see [`Args::secondary_language`].
+    secondary: bool,
+}
+
+/// How MCP clients connect to us.
+enum McpTransport {
+    /// Single client via stdio.
+    Stdio { stdin: BoxRead, stdout: BoxWrite },
+
+    /// Any number of clients via HTTP.
+    Http {
+        cfg: HttpCLIConfig,
+        addr: SocketAddr,
+    },
+
+    /// Any number of clients via the daemon socket.
+    #[cfg(unix)]
+    Daemon {
+        cfg: DaemonCLIConfig,
+        listener: UnixListener,
+    },
+
+    /// Tool calls from the command line or stdin.
+    Call { cfg: CallCLIConfig },
+
+    /// Subcommand, e.g. an export, without any client.
+    Command { command: ExportCommand },
+}
+
+/// Initialize spawned language servers.
+async fn init_lsps(
+    spawned: Vec<SpawnedLsp>,
+    workspace: &Workspace,
+    setup: &LspSetup,
+) -> Result<Arc<[LanguageServer]>> {
+    let version_check = setup.version_check;
+    let servers_fut = futures::future::try_join_all(spawned.into_iter().map(
+        async |SpawnedLsp {
+                   name,
+                   quirks,
+                   client,
+                   handler,
+                   progress_guard,
+                   messages,
+                   diagnostics,
+                   scheduler,
+                   traffic_tail,
+                   secondary,
+               }| {
+            let init_result = init_lsp(&client, &handler, workspace, &quirks)
+                .await
+                .with_context(|| format!("init lsp for {name}"))?;
+            setup
+                .intercept_io_cfg
+                .record_server(&name, init_result.server_info.as_ref());
+
+            let version_status = match version_check {
+                VersionCheckMode::Off => VersionStatus::Ok,
+                VersionCheckMode::Error | VersionCheckMode::Warn => VersionStatus::check(
+                    init_result
+                        .server_info
+                        .as_ref()
+                        .and_then(|info| info.version.as_deref()),
+                    quirks.minimum_version().as_ref(),
+                ),
+            };
+            match &version_status {
+                VersionStatus::Ok => {}
+                VersionStatus::TooOld { minimum } => {
+                    ensure!(
+                        version_check == VersionCheckMode::Warn,
+                        "language server for {name} is too old, need at least {minimum}; use `--lsp-version-check=warn` to start anyways",
+                    );
+                    warn!(lang = name.as_str(), minimum, "language server is too old");
+                }
+                VersionStatus::Unknown { reason } => {
+                    warn!(lang = name.as_str(), reason, "cannot check language server version");
+                }
+            }
+
+            Result::<_>::Ok(LanguageServer::new(
+                name,
+                quirks,
+                handler,
+                progress_guard,
+                messages,
+                diagnostics,
+                scheduler,
+                traffic_tail,
+                init_result,
+                version_status,
+                setup.symbol_cache_cfg.cache(&setup.cache_registry),
+                setup.cache_registry.clone(),
+                secondary,
+            ))
+        },
+    ));
+    // language servers take a while to start, detect the dependency sources in the meantime
+    let (servers, sources) = tokio::join!(
+        servers_fut,
+        setup.dependency_sources_cfg.detect(workspace.root()),
+    );
+    workspace.dependencies().set_sources(sources);
+
+    Ok(Arc::<[LanguageServer]>::from(servers?))
+}
+
+async fn main_inner(
+    spawned: Vec<SpawnedLsp>,
+    workspace: Workspace,
+    setup: &LspSetup,
+    tools_cfg: ToolsCLIConfig,
+    transport: McpTransport,
+    hot_reload: HotReload,
+    warmup: Warmup,
+) -> Result<()> {
+    let servers = init_lsps(spawned, &workspace, setup).await?;
+    let workspace = SharedWorkspace::from(workspace);
+    let _file_watcher = setup
+        .file_watcher_cfg
+        .start(Arc::clone(&servers), workspace.clone());
+    let _metrics_log = setup.metrics_cfg.start(Arc::clone(&servers));
+    let _hot_reload = hot_reload.start(Arc::clone(&servers), workspace.clone(), tools_cfg.clone());
+    warmup.start(Arc::clone(&servers), workspace.clone(), tools_cfg.clone());
+    let ct = CancellationToken::new();
+    let service_fut = match transport {
+        McpTransport::Stdio { stdin, stdout } => {
+            let service = CodeExplorer::new(servers, workspace, tools_cfg)
+                .serve_with_ct((stdin, stdout), ct.clone())
+                .await
+                .context("set up code explorer service")?;
+            async move {
+                service.waiting().await?;
+                Ok(())
+            }
+            .boxed()
+        }
+        McpTransport::Http { cfg, addr } => {
+            let ct = ct.clone();
+            async move { cfg.serve(addr, servers, workspace, tools_cfg, ct).await }.boxed()
+        }
+        #[cfg(unix)]
+        McpTransport::Daemon { cfg, listener } => {
+            let ct = ct.clone();
+            async move { cfg.serve(listener, servers, workspace, tools_cfg, ct).await }.boxed()
+        }
+        McpTransport::Call { cfg } => {
+            let ct = ct.clone();
+            async move { cfg.run(servers, workspace, tools_cfg, ct).await }.boxed()
+        }
+        McpTransport::Command { command } => {
+            let ct = ct.clone();
+            async move {
+                match command {
+                    ExportCommand::ExportSymbols(cfg) => {
+                        cfg.run(servers, workspace, tools_cfg, ct).await
+                    }
+                    ExportCommand::ExportDiagnostics(cfg) => {
+                        cfg.run(servers, workspace, tools_cfg, ct).await
+                    }
+                }
+            }
+            .boxed()
+        }
+    };
     let mut service_fut = std::pin::pin!(service_fut);
 
-    let mut signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-        .context("create signal handler")?;
+    let mut signal = ShutdownSignal::new()?;
 
     tokio::select! {
         _ = signal.recv() => {
             info!("received shutdown signal");
             ct.cancel();
+            service_fut.await.context("wait for service")?;
         }
         res = &mut service_fut => {
             res.context("wait for service")?;
         }
     }
 
-    service_fut.await.context("wait for service")?;
+    Ok(())
+}
+
+/// Serve MCP client via stdio, failing all tool calls because the language servers cannot be started.
+async fn serve_failed(msg: String, stdin: BoxRead, stdout: BoxWrite) -> Result<()> {
+    let ct = CancellationToken::new();
+    let service = FailedExplorer::new(msg)
+        .serve_with_ct((stdin, stdout), ct.clone())
+        .await
+        .context("set up failed code explorer service")?;
+    let mut service_fut = std::pin::pin!(service.waiting());
+
+    let mut signal = ShutdownSignal::new()?;
+
+    tokio::select! {
+        _ = signal.recv() => {
+            info!("received shutdown signal");
+            ct.cancel();
+            service_fut.await.context("wait for service")?;
+        }
+        res = &mut service_fut => {
+            res.context("wait for service")?;
+        }
+    }
 
     Ok(())
 }
+
+/// What to do after the language servers of a workspace were shut down.
+#[derive(Debug, Clone, Copy)]
+enum RootsNext {
+    /// Start language servers for the new workspace.
+    Restart,
+
+    /// Shutdown was requested, wait for the MCP service.
+    Shutdown,
+
+    /// MCP service finished.
+    Done,
+}
+
+/// Serve MCP client via stdio, using the workspace that the client announces via roots.
+///
+/// The language servers are restarted when the client changes its roots.
+async fn serve_roots(
+    setup: &LspSetup,
+    tools_cfg: ToolsCLIConfig,
+    hot_reload: HotReload,
+    stdin: BoxRead,
+    stdout: BoxWrite,
+) -> Result<()> {
+    let ct = CancellationToken::new();
+    let explorer = RootsExplorer::new(CodeExplorer::configured_tool_router(
+        &tools_cfg,
+        setup.intercept_io_cfg.debug_tail_enabled(),
+    ));
+    let state = explorer.state().clone();
+    let service = explorer
+        .serve_with_ct((stdin, stdout), ct.clone())
+        .await
+        .context("set up code explorer service")?;
+    let mut service_fut = std::pin::pin!(service.waiting());
+    let mut rx_workspace = state.workspace();
+
+    let mut signal = ShutdownSignal::new()?;
+
+    loop {
+        let workspace = tokio::select! {
+            _ = signal.recv() => {
+                info!("received shutdown signal");
+                ct.cancel();
+                service_fut.await.context("wait for service")?;
+                return Ok(());
+            }
+            res = &mut service_fut => {
+                res.context("wait for service")?;
+                return Ok(());
+            }
+            res = async { rx_workspace.wait_for(Option::is_some).await.map(|ws| ws.clone()) } => {
+                res.context("watch workspace")?.expect("waited for it")
+            }
+        };
+        info!(path=%workspace, "workspace");
+
+        let mut tasks = setup.shutdown_cfg.task_manager();
+        let mut children = vec![];
+        let mut file_watcher = None;
+        let mut metrics_log = None;
+        let mut config_watcher = None;
+        let next = async {
+            match setup.spawn(&workspace, &mut tasks).await {
+                Ok((spawned, spawned_children)) => {
+                    children = spawned_children;
+                    let servers = tokio::select! {
+                        res = init_lsps(spawned, &workspace, setup) => res?,
+                        e = tasks.run() => return Err(e).context("tasks"),
+                    };
+                    let shared_workspace = SharedWorkspace::from(workspace.clone());
+                    file_watcher = setup
+                        .file_watcher_cfg
+                        .start(Arc::clone(&servers), shared_workspace.clone());
+                    metrics_log = setup.metrics_cfg.start(Arc::clone(&servers));
+                    let tools_cfg = tools_cfg.clone().with_task_statuses(tasks.statuses());
+                    config_watcher = Some(hot_reload.clone().start(
+                        Arc::clone(&servers),
+                        shared_workspace.clone(),
+                        tools_cfg.clone(),
+                    ));
+                    setup.warmup_cfg.spawn(&mut tasks).start(
+                        Arc::clone(&servers),
+                        shared_workspace.clone(),
+                        tools_cfg.clone(),
+                    );
+                    state.set_explorer(CodeExplorer::new(servers, shared_workspace, tools_cfg));
+                }
+                Err(e) => {
+                    let Some(spawn_error) = e.downcast_ref::<SpawnError>() else {
+                        return Err(e);
+                    };
+                    // wait for the client to announce another workspace
+                    error!("{spawn_error}");
+                    state.set_failed(spawn_error.to_string());
+                }
+            }
+
+            tokio::select! {
+                _ = signal.recv() => {
+                    info!("received shutdown signal");
+                    ct.cancel();
+                    Ok(RootsNext::Shutdown)
+                }
+                res = &mut service_fut => {
+                    res.context("wait for service")?;
+                    Ok(RootsNext::Done)
+                }
+                res = rx_workspace.changed() => {
+                    res.context("watch workspace")?;
+                    info!("workspace changed, restart language servers");
+                    Ok(RootsNext::Restart)
+                }
+                e = tasks.run() => Err(e).context("tasks"),
+            }
+        }
+        .await;
+
+        state.reset();
+        drop(file_watcher.take());
+        drop(metrics_log.take());
+        drop(config_watcher.take());
+        let cleanup = setup
+            .shutdown_cfg
+            .shutdown_lsps(children)
+            .await
+            .and(tasks.shutdown().await.context("task shutdown"));
+        match next.and_then(|next| cleanup.map(|()| next))? {
+            RootsNext::Restart => {}
+            RootsNext::Shutdown => {
+                service_fut.await.context("wait for service")?;
+                return Ok(());
+            }
+            RootsNext::Done => return Ok(()),
+        }
+    }
+}
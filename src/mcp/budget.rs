@@ -0,0 +1,299 @@
+//! Global budget for the size of tool results.
+//!
+//! Some results, e.g. the references of a commonly used symbol, can get huge and fill up the context of small models.
+//! Results that exceed the budget are shrunk by cutting the largest arrays first and the longest strings afterwards.
+//! What was cut is listed in the result, so clients know that they did not get everything.
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use rmcp::model::{CallToolResult, Content};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+/// Field of the result that lists what was cut.
+const TRUNCATED_FIELD: &str = "response_truncated";
+
+/// Strings up to this number of characters are not cut.
+const MIN_STRING_CHARS: usize = 100;
+
+/// Shrink the structured content of the result to at most `max_chars` characters, see module docs.
+pub(super) fn enforce(result: &mut CallToolResult, max_chars: usize) {
+    let Some(value) = result.structured_content.as_mut() else {
+        return;
+    };
+    if size(value) <= max_chars {
+        return;
+    }
+
+    let cuts = shrink(value, max_chars);
+    if cuts.is_empty() {
+        return;
+    }
+    debug!(cuts = cuts.len(), max_chars, "truncated tool result");
+    if let Value::Object(map) = value {
+        map.insert(TRUNCATED_FIELD.to_owned(), marker(&cuts));
+    }
+    result.content = vec![Content::text(value.to_string())];
+}
+
+/// What was cut at a given JSON pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cut {
+    Items(usize),
+    Chars(usize),
+}
+
+/// Cut arrays and strings until the value including the marker fits into the budget.
+fn shrink(value: &mut Value, max_chars: usize) -> BTreeMap<String, Cut> {
+    let mut cuts = BTreeMap::new();
+    loop {
+        let size = size(value) + marker_size(&cuts);
+        if size <= max_chars {
+            return cuts;
+        }
+        let excess = size - max_chars;
+
+        let mut candidates = vec![];
+        collect(value, String::new(), &mut candidates);
+        candidates.sort_by_key(|candidate| Reverse(candidate.size));
+
+        // cut arrays first, but do not favor one over the others
+        let mut arrays = candidates
+            .iter()
+            .filter(|candidate| candidate.kind == Kind::Array);
+        if let Some(largest) = arrays.next() {
+            let second = arrays
+                .next()
+                .map(|candidate| candidate.size)
+                .unwrap_or_default();
+            let amount = excess.min((largest.size - second).max(largest.size.div_ceil(4)));
+            let Some(Value::Array(items)) = value.pointer_mut(&largest.pointer) else {
+                unreachable!("candidate is an array");
+            };
+            let omitted = cut_array(items, amount);
+            match cuts.entry(largest.pointer.clone()).or_insert(Cut::Items(0)) {
+                Cut::Items(n) => *n += omitted,
+                Cut::Chars(_) => unreachable!("arrays are not strings"),
+            }
+            continue;
+        }
+
+        // strings are cut once, so their marker stays intact
+        if let Some(largest) = candidates.iter().find(|candidate| {
+            candidate.kind == Kind::String && !cuts.contains_key(&candidate.pointer)
+        }) {
+            let Some(Value::String(s)) = value.pointer_mut(&largest.pointer) else {
+                unreachable!("candidate is a string");
+            };
+            // the cut is listed in the marker, which grows accordingly
+            let mut with_cut = cuts.clone();
+            with_cut.insert(largest.pointer.clone(), Cut::Chars(excess));
+            let omitted = cut_string(s, excess + marker_size(&with_cut) - marker_size(&cuts));
+            cuts.insert(largest.pointer.clone(), Cut::Chars(omitted));
+            continue;
+        }
+
+        warn!(size, max_chars, "tool result exceeds the size budget");
+        return cuts;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Array,
+    String,
+}
+
+/// Array or string that can be cut.
+#[derive(Debug)]
+struct Candidate {
+    pointer: String,
+    size: usize,
+    kind: Kind,
+}
+
+/// Collect candidates for cutting, returns the serialized size of the value.
+fn collect(value: &Value, pointer: String, candidates: &mut Vec<Candidate>) -> usize {
+    let size = match value {
+        Value::Array(items) => {
+            let size = items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| collect(item, format!("{pointer}/{idx}"), candidates))
+                .sum::<usize>()
+                + separators(items.len());
+            if items.len() > 1 {
+                candidates.push(Candidate {
+                    pointer,
+                    size,
+                    kind: Kind::Array,
+                });
+            }
+            return size;
+        }
+        Value::Object(map) => {
+            return map
+                .iter()
+                .map(|(key, value)| {
+                    let key_pointer = key.replace('~', "~0").replace('/', "~1");
+                    size_of_str(key)
+                        + 1
+                        + collect(value, format!("{pointer}/{key_pointer}"), candidates)
+                })
+                .sum::<usize>()
+                + separators(map.len());
+        }
+        Value::String(s) => size_of_str(s),
+        Value::Null | Value::Bool(_) | Value::Number(_) => value.to_string().chars().count(),
+    };
+    if matches!(value, Value::String(s) if s.chars().count() > MIN_STRING_CHARS) {
+        candidates.push(Candidate {
+            pointer,
+            size,
+            kind: Kind::String,
+        });
+    }
+    size
+}
+
+/// Serialized size of the value in characters.
+fn size(value: &Value) -> usize {
+    collect(value, String::new(), &mut vec![])
+}
+
+/// Serialized size of a JSON string in characters, including quotes and escapes.
+fn size_of_str(s: &str) -> usize {
+    Value::String(s.to_owned()).to_string().chars().count()
+}
+
+/// Brackets and commas of an array or object with `n` elements.
+fn separators(n: usize) -> usize {
+    2 + n.saturating_sub(1)
+}
+
+/// Remove items from the end until at least `amount` characters are saved, keeping at least one item.
+fn cut_array(items: &mut Vec<Value>, amount: usize) -> usize {
+    let mut saved = 0;
+    let mut omitted = 0;
+    while saved < amount && items.len() > 1 {
+        let item = items.pop().expect("checked length");
+        saved += size(&item) + 1;
+        omitted += 1;
+    }
+    omitted
+}
+
+/// Shorten the string by about `amount` characters and append a marker, returns the number of omitted characters.
+fn cut_string(s: &mut String, amount: usize) -> usize {
+    let chars = s.chars().count();
+    // reserve room for the marker
+    let keep = chars.saturating_sub(amount + 32);
+    let omitted = chars - keep;
+    let end = s
+        .char_indices()
+        .nth(keep)
+        .map(|(idx, _)| idx)
+        .unwrap_or(s.len());
+    s.truncate(end);
+    s.push_str(&format!("… [{omitted} characters truncated]"));
+    omitted
+}
+
+/// Lists what was cut, see [`TRUNCATED_FIELD`].
+fn marker(cuts: &BTreeMap<String, Cut>) -> Value {
+    cuts.iter()
+        .map(|(pointer, cut)| match cut {
+            Cut::Items(n) => json!({"path": pointer, "omitted_items": n}),
+            Cut::Chars(n) => json!({"path": pointer, "omitted_chars": n}),
+        })
+        .collect()
+}
+
+/// Size that the marker adds to the top-level object.
+fn marker_size(cuts: &BTreeMap<String, Cut>) -> usize {
+    if cuts.is_empty() {
+        return 0;
+    }
+    size_of_str(TRUNCATED_FIELD) + 1 + size(&marker(cuts)) + 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enforced(value: Value, max_chars: usize) -> Value {
+        let mut result = CallToolResult::structured(value);
+        enforce(&mut result, max_chars);
+
+        let value = result.structured_content.unwrap();
+        assert!(size(&value) <= max_chars, "{value}");
+        assert_eq!(result.content[0].as_text().unwrap().text, value.to_string());
+        value
+    }
+
+    #[test]
+    fn test_size() {
+        for value in [
+            json!({}),
+            json!([]),
+            json!({"a": [1, 2, {"b": null}], "c/d": "e\"f", "g": true}),
+            json!(["ü", 1.5, -3]),
+        ] {
+            assert_eq!(size(&value), value.to_string().chars().count(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_within_budget() {
+        let value = json!({"symbols": [1, 2, 3]});
+        assert_eq!(enforced(value.clone(), 100), value);
+    }
+
+    #[test]
+    fn test_cut_arrays() {
+        let value = json!({
+            "a": (0..100).collect::<Vec<_>>(),
+            "b": (0..50).collect::<Vec<_>>(),
+            "c": [1],
+        });
+        let value = enforced(value, 250);
+
+        let len = |key: &str| value[key].as_array().unwrap().len();
+        assert!(len("a") < 100 && len("b") < 50, "{value}");
+        assert_eq!(len("c"), 1);
+        assert_eq!(
+            value[TRUNCATED_FIELD],
+            json!([
+                {"path": "/a", "omitted_items": 100 - len("a")},
+                {"path": "/b", "omitted_items": 50 - len("b")},
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_cut_strings() {
+        let value = json!({
+            "info": [{"hover": "x".repeat(1000), "name": "y".repeat(200)}],
+        });
+        let value = enforced(value, 500);
+
+        let hover = value["info"][0]["hover"].as_str().unwrap();
+        assert!(hover.ends_with("characters truncated]"), "{hover}");
+        assert_eq!(value["info"][0]["name"], "y".repeat(200));
+        let omitted = value[TRUNCATED_FIELD][0]["omitted_chars"].as_u64().unwrap();
+        assert_eq!(
+            value[TRUNCATED_FIELD],
+            json!([{"path": "/info/0/hover", "omitted_chars": omitted}]),
+        );
+        assert_eq!(
+            hover.chars().take_while(|c| *c == 'x').count() as u64 + omitted,
+            1000
+        );
+    }
+
+    #[test]
+    fn test_escaped_pointer() {
+        let value = json!({"a/b": (0..100).collect::<Vec<_>>()});
+        let value = enforced(value, 150);
+        assert_eq!(value[TRUNCATED_FIELD][0]["path"], "/a~1b");
+    }
+}
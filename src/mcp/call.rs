@@ -0,0 +1,223 @@
+//! Tool calls from the command line or stdin, without an MCP client.
+//!
+//! The tools are served via an in-process MCP session, so arguments are validated and routed exactly like for a real
+//! MCP client.
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use rmcp::{
+    RoleClient, RoleServer, ServiceExt,
+    model::{CallToolRequestParams, JsonObject},
+    service::RunningService,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+use crate::lsp::{server::LanguageServer, workspace::SharedWorkspace};
+
+use super::{CodeExplorer, tools::ToolsCLIConfig};
+
+/// Buffer size of the in-process MCP session.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// CLI config for calling tools from the command line or stdin.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct CallCLIConfig {
+    /// Call the given tool (e.g. `find_symbol`), print its result as JSON to stdout and exit.
+    ///
+    /// Waits for the language servers to be ready. Exits with an error if the tool call fails.
+    #[clap(long)]
+    call: Option<String>,
+
+    /// Arguments (as JSON object) for the tool passed via `--call`.
+    #[clap(long, requires = "call", value_parser = parse_json_object)]
+    args: Option<JsonObject>,
+
+    /// Read tool calls from stdin and write their results to stdout, both as JSON lines.
+    ///
+    /// Every input line is an object like `{"tool": "find_symbol", "args": {"query": "foo"}}`. Every output line is
+    /// either `{"result": ...}` or `{"error": "..."}`, in the order of the input. Failed tool calls do not stop the
+    /// batch.
+    #[clap(long, conflicts_with = "call")]
+    batch: bool,
+}
+
+fn parse_json_object(s: &str) -> Result<JsonObject, String> {
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
+
+impl CallCLIConfig {
+    /// Command line tool call or batch mode is requested.
+    pub(crate) fn enabled(&self) -> bool {
+        self.call.is_some() || self.batch
+    }
+
+    /// Perform tool call(s) and print result(s).
+    pub(crate) async fn run(
+        &self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<()> {
+        let session = LocalSession::connect(servers, workspace, tools_cfg, ct.clone()).await?;
+        let res = match &self.call {
+            Some(tool) => call_once(&session, tool, self.args.clone()).await,
+            None => batch(&session, ct).await,
+        };
+        session.close().await?;
+        res
+    }
+}
+
+/// Perform a single tool call and print its result.
+async fn call_once(session: &LocalSession, tool: &str, args: Option<JsonObject>) -> Result<()> {
+    let value = session.call_tool(tool, args).await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&value).context("serialize result")?
+    );
+    Ok(())
+}
+
+/// Tool call within the `--batch` input.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchCall {
+    tool: String,
+    args: Option<JsonObject>,
+}
+
+/// Result within the `--batch` output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchResult {
+    Result(Value),
+    Error(String),
+}
+
+/// Perform tool calls read from stdin until it is closed.
+async fn batch(session: &LocalSession, ct: CancellationToken) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    loop {
+        let line = tokio::select! {
+            _ = ct.cancelled() => break,
+            line = lines.next_line() => line.context("read stdin")?,
+        };
+        let Some(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let res = match serde_json::from_str::<BatchCall>(&line) {
+            Ok(BatchCall { tool, args }) => session.call_tool(&tool, args).await,
+            Err(e) => Err(e).context("parse tool call"),
+        };
+        let res = match res {
+            Ok(value) => BatchResult::Result(value),
+            Err(e) => BatchResult::Error(format!("{e:#}")),
+        };
+
+        let mut out = serde_json::to_string(&res).context("serialize result")?;
+        out.push('\n');
+        stdout
+            .write_all(out.as_bytes())
+            .await
+            .context("write stdout")?;
+        stdout.flush().await.context("flush stdout")?;
+    }
+
+    Ok(())
+}
+
+/// In-process MCP session.
+#[derive(Debug)]
+struct LocalSession {
+    server: RunningService<RoleServer, CodeExplorer>,
+    client: RunningService<RoleClient, ()>,
+}
+
+impl LocalSession {
+    /// Set up session.
+    async fn connect(
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<Self> {
+        let (server_io, client_io) = tokio::io::duplex(BUFFER_SIZE);
+        let (server, client) = tokio::join!(
+            CodeExplorer::new(servers, workspace, tools_cfg).serve_with_ct(server_io, ct),
+            ().serve(client_io),
+        );
+        Ok(Self {
+            server: server.context("set up code explorer service")?,
+            client: client.context("set up MCP client")?,
+        })
+    }
+
+    /// Call tool and return its structured result.
+    async fn call_tool(&self, tool: &str, args: Option<JsonObject>) -> Result<Value> {
+        let mut params = CallToolRequestParams::new(tool.to_owned());
+        if let Some(args) = args {
+            params = params.with_arguments(args);
+        }
+        let resp = self
+            .client
+            .call_tool(params)
+            .await
+            .with_context(|| format!("call tool `{tool}`"))?;
+
+        let value = match resp.structured_content {
+            Some(value) => value,
+            None => serde_json::to_value(&resp.content).context("serialize content")?,
+        };
+        if resp.is_error.unwrap_or_default() {
+            bail!("tool `{tool}` failed: {value}");
+        }
+
+        Ok(value)
+    }
+
+    /// Shut down session.
+    async fn close(self) -> Result<()> {
+        let Self { server, client } = self;
+        client.cancel().await.context("shut down MCP client")?;
+        server.waiting().await.context("wait for service")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let cfg = CallCLIConfig::try_parse_from(["", "--call=find_symbol"]).unwrap();
+        assert!(cfg.enabled());
+        assert_eq!(cfg.args, None);
+
+        let cfg =
+            CallCLIConfig::try_parse_from(["", "--call=find_symbol", r#"--args={"query": "foo"}"#])
+                .unwrap();
+        assert_eq!(Value::Object(cfg.args.unwrap()), json!({"query": "foo"}));
+
+        assert!(!CallCLIConfig::default().enabled());
+        CallCLIConfig::try_parse_from(["", "--call=find_symbol", "--args=[]"]).unwrap_err();
+        CallCLIConfig::try_parse_from(["", r#"--args={}"#]).unwrap_err();
+
+        let cfg = CallCLIConfig::try_parse_from(["", "--batch"]).unwrap();
+        assert!(cfg.enabled());
+        CallCLIConfig::try_parse_from(["", "--batch", "--call=find_symbol"]).unwrap_err();
+    }
+}
@@ -0,0 +1,578 @@
+//! Call graph around a function, rendered as [DOT](https://graphviz.org/doc/info/lang.html) or
+//! [Mermaid](https://mermaid.js.org/syntax/flowchart.html).
+//!
+//! The graph is built from call hierarchy requests, starting at the given function and following incoming and/or
+//! outgoing calls up to a given depth. Functions are identified by the position of their name, so calls via different
+//! paths end up at the same node.
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write,
+};
+
+use anyhow::Context;
+use lsp_types::{
+    CallHierarchyIncomingCallsParams, CallHierarchyItem, CallHierarchyOutgoingCallsParams,
+    CallHierarchyPrepareParams, Location, TextDocumentPositionParams,
+    request::{CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare},
+};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    dedup::DedupClient,
+    location::{McpLocation, path_to_text_document_identifier},
+    scheduler::Priority,
+};
+
+use super::{
+    CodeExplorer,
+    error::{OptionExt, ResultExt},
+    hints,
+    timeout::{Deadline, TimedOut},
+};
+
+/// Number of calls that `call_graph` follows by default.
+const DEFAULT_DEPTH: u32 = 2;
+
+/// Upper limit for the depth of `call_graph`.
+const MAX_DEPTH: u32 = 5;
+
+/// Upper limit for the number of nodes, so hot functions (e.g. logging helpers) do not blow up the graph.
+pub(super) const MAX_NODES: usize = 50;
+
+/// Which calls are followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum CallDirection {
+    /// functions that call the given one
+    Incoming,
+
+    /// functions that the given one calls
+    Outgoing,
+
+    /// both directions
+    #[default]
+    Both,
+}
+
+impl CallDirection {
+    pub(super) fn incoming(self) -> bool {
+        matches!(self, Self::Incoming | Self::Both)
+    }
+
+    pub(super) fn outgoing(self) -> bool {
+        matches!(self, Self::Outgoing | Self::Both)
+    }
+}
+
+/// Text format of the rendered graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum GraphFormat {
+    /// GraphViz DOT language
+    #[default]
+    Dot,
+
+    /// Mermaid flowchart, which many chat clients render inline
+    Mermaid,
+}
+
+/// Function within the call graph.
+#[derive(Debug)]
+struct Node {
+    name: String,
+
+    /// e.g. `src/lib.rs:7`
+    location: String,
+}
+
+/// Call graph with bounded size.
+#[derive(Debug, Default)]
+pub(super) struct CallGraph {
+    nodes: Vec<Node>,
+
+    /// Node index per identity, see [`add`](Self::add).
+    index: HashMap<String, usize>,
+
+    /// Caller and callee.
+    edges: BTreeSet<(usize, usize)>,
+
+    /// Nodes were dropped because of [`MAX_NODES`].
+    truncated: bool,
+}
+
+impl CallGraph {
+    /// Add node if it is new.
+    ///
+    /// Returns the node index, `None` if the graph is full.
+    pub(super) fn add(&mut self, id: String, name: &str, location: String) -> Option<usize> {
+        if let Some(idx) = self.index.get(&id) {
+            return Some(*idx);
+        }
+        if self.nodes.len() >= MAX_NODES {
+            self.truncated = true;
+            return None;
+        }
+
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            location,
+        });
+        self.index.insert(id, idx);
+        Some(idx)
+    }
+
+    pub(super) fn add_edge(&mut self, caller: usize, callee: usize) {
+        self.edges.insert((caller, callee));
+    }
+
+    pub(super) fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(super) fn edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub(super) fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Render in the given format, the first node is the root and highlighted.
+    pub(super) fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n  rankdir=LR;\n  node [shape=box];\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let label = escape_dot(&format!("{}\n{}", node.name, node.location));
+            let style = if idx == 0 { ", style=bold" } else { "" };
+            writeln!(dot, "  n{idx} [label=\"{label}\"{style}];").expect("write to string");
+        }
+        for (caller, callee) in &self.edges {
+            writeln!(dot, "  n{caller} -> n{callee};").expect("write to string");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let label = format!(
+                "{}<br/>{}",
+                escape_mermaid(&node.name),
+                escape_mermaid(&node.location)
+            );
+            writeln!(mermaid, "  n{idx}[\"{label}\"]").expect("write to string");
+        }
+        for (caller, callee) in &self.edges {
+            writeln!(mermaid, "  n{caller} --> n{callee}").expect("write to string");
+        }
+        if !self.nodes.is_empty() {
+            mermaid.push_str("  style n0 stroke-width:3px\n");
+        }
+        mermaid
+    }
+}
+
+/// Escape text for a quoted DOT string.
+fn escape_dot(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text for a quoted Mermaid label, which may contain HTML.
+fn escape_mermaid(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '#' => escaped.push_str("#35;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[tool_router(router = call_graph_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Call Graph",
+        description = "Get the call graph around a function or method as DOT (GraphViz) or Mermaid text: the functions that call it and/or the functions it calls, up to a given depth. The function is selected by name or by its position within the file.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn call_graph(
+        &self,
+        Parameters(CallGraphRequest {
+            file,
+            name,
+            line,
+            character,
+            direction,
+            depth,
+            format,
+            workspace_and_dependencies,
+            timeout_secs,
+        }): Parameters<CallGraphRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<CallGraphResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(CallGraphResult::timed_out()));
+        };
+        let Some(token_legend) = server.token_legend() else {
+            return Err(McpError::invalid_params(
+                "language server does not provide semantic tokens",
+                None,
+            ));
+        };
+        let direction = direction.unwrap_or_default();
+        let depth = depth.unwrap_or(DEFAULT_DEPTH).min(MAX_DEPTH);
+        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        if let Some(line) = line {
+            let lines = file_content.lines().count();
+            if line as usize > lines {
+                return Err(hints::position_drifted(&file, line, lines));
+            }
+        }
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+        let Some(data) = deadline
+            .run(self.semantic_tokens(server, &client, &file, &file_content))
+            .await
+        else {
+            return Ok(Json(CallGraphResult::timed_out()));
+        };
+        let data =
+            data?.expected("language server did not provide any semantic tokens".to_owned())?;
+        let doc = token_legend
+            .decode(&file_content, &data)
+            .context("decode semantic tokens")
+            .internal()?;
+        let (tokens, symbol) = match (&name, line) {
+            (Some(name), line) => (
+                doc.query(name, None, line, character),
+                format!("`{name}` in {file}"),
+            ),
+            (None, Some(line)) => (
+                doc.at(None, line, character).into_iter().collect(),
+                format!("{file}:{line}"),
+            ),
+            (None, None) => {
+                return Err(McpError::invalid_params(
+                    "either `name` or `line` is required",
+                    None,
+                ));
+            }
+        };
+
+        // the first token that the language server can resolve to a function, e.g. skip shadowing variables
+        let mut root = None;
+        for token in tokens {
+            let Some(items) = deadline
+                .run(
+                    client.send_request::<CallHierarchyPrepare>(CallHierarchyPrepareParams {
+                        text_document_position_params: TextDocumentPositionParams {
+                            text_document: text_document.clone(),
+                            position: token.lsp_position(),
+                        },
+                        work_done_progress_params: Default::default(),
+                    }),
+                )
+                .await
+            else {
+                return Ok(Json(CallGraphResult::timed_out()));
+            };
+            root = items
+                .context("CallHierarchyPrepare")
+                .internal()?
+                .and_then(|items| items.into_iter().next());
+            if root.is_some() {
+                break;
+            }
+        }
+        let Some(root) = root else {
+            if let Some(e) = hints::not_indexed(server) {
+                return Err(e);
+            }
+            return Err(hints::not_callable(&symbol));
+        };
+
+        let mut graph = CallGraph::default();
+        let root_idx = self
+            .call_graph_node(&mut graph, &root, true)?
+            .expected(format!("{symbol} is outside of the workspace"))?;
+        for (enabled, incoming) in [(direction.incoming(), true), (direction.outgoing(), false)] {
+            if !enabled {
+                continue;
+            }
+
+            let mut visited = HashSet::from([root_idx]);
+            let mut frontier = vec![(root_idx, root.clone())];
+            for _ in 0..depth {
+                let mut next = vec![];
+                for (idx, item) in frontier {
+                    for other in self.calls(&client, item, incoming, &deadline).await? {
+                        let Some(other_idx) =
+                            self.call_graph_node(&mut graph, &other, workspace_and_dependencies)?
+                        else {
+                            continue;
+                        };
+                        if incoming {
+                            graph.add_edge(other_idx, idx);
+                        } else {
+                            graph.add_edge(idx, other_idx);
+                        }
+                        if visited.insert(other_idx) {
+                            next.push((other_idx, other));
+                        }
+                    }
+                }
+                frontier = next;
+            }
+        }
+
+        Ok(Json(CallGraphResult {
+            graph: graph.render(format.unwrap_or_default()),
+            nodes: graph.nodes(),
+            edges: graph.edges(),
+            truncated: graph.is_truncated(),
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+impl CodeExplorer {
+    /// Add a call hierarchy item to the graph, `None` if it is outside the workspace or the graph is full.
+    fn call_graph_node(
+        &self,
+        graph: &mut CallGraph,
+        item: &CallHierarchyItem,
+        workspace_and_dependencies: bool,
+    ) -> Result<Option<usize>, McpError> {
+        let location = Location::new(item.uri.clone(), item.selection_range);
+        let Some(location) =
+            McpLocation::try_new(location, self.workspace(), workspace_and_dependencies)
+                .context("convert call hierarchy location")
+                .internal()?
+        else {
+            return Ok(None);
+        };
+        let start = item.selection_range.start;
+        Ok(graph.add(
+            format!("{}:{}:{}", item.uri.as_str(), start.line, start.character),
+            &item.name,
+            format!("{}:{}", location.file, location.line),
+        ))
+    }
+
+    /// Callers or callees of a call hierarchy item, empty if the deadline passed.
+    async fn calls(
+        &self,
+        client: &DedupClient,
+        item: CallHierarchyItem,
+        incoming: bool,
+        deadline: &Deadline,
+    ) -> Result<Vec<CallHierarchyItem>, McpError> {
+        let items = if incoming {
+            deadline
+                .run(client.send_request::<CallHierarchyIncomingCalls>(
+                    CallHierarchyIncomingCallsParams {
+                        item,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ))
+                .await
+                .transpose()
+                .context("CallHierarchyIncomingCalls")
+                .internal()?
+                .flatten()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|call| call.from)
+                .collect()
+        } else {
+            deadline
+                .run(client.send_request::<CallHierarchyOutgoingCalls>(
+                    CallHierarchyOutgoingCallsParams {
+                        item,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ))
+                .await
+                .transpose()
+                .context("CallHierarchyOutgoingCalls")
+                .internal()?
+                .flatten()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|call| call.to)
+                .collect()
+        };
+        Ok(items)
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CallGraphRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// function name, if not set the function at the given line and character is used
+    name: Option<String>,
+
+    /// 1-based line number within the file
+    #[schemars(range(min = 1))]
+    line: Option<u32>,
+
+    /// 1-based character index within the line
+    #[schemars(range(min = 1))]
+    character: Option<u32>,
+
+    /// which calls to follow, defaults to both
+    direction: Option<CallDirection>,
+
+    /// number of calls to follow from the function, defaults to 2
+    #[schemars(range(min = 1, max = 5))]
+    depth: Option<u32>,
+
+    /// text format of the graph, defaults to DOT
+    format: Option<GraphFormat>,
+
+    /// include functions of dependencies
+    workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct CallGraphResult {
+    /// call graph in the requested format, edges point from caller to callee
+    graph: String,
+
+    /// number of functions
+    nodes: usize,
+
+    /// number of calls
+    edges: usize,
+
+    /// functions were dropped because the graph got too large, use a smaller depth
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for CallGraphResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut graph = CallGraph::default();
+        let root = graph
+            .add("a".to_owned(), "main", "src/main.rs:1".to_owned())
+            .unwrap();
+        let callee = graph
+            .add("b".to_owned(), "parse<\"x\">", "src/lib.rs:7".to_owned())
+            .unwrap();
+        graph.add_edge(root, callee);
+        graph.add_edge(callee, callee);
+        graph.add_edge(root, callee);
+        assert_eq!(
+            graph.add("b".to_owned(), "parse", String::new()),
+            Some(callee)
+        );
+
+        assert_eq!(
+            graph.render(GraphFormat::Dot),
+            r#"digraph calls {
+  rankdir=LR;
+  node [shape=box];
+  n0 [label="main\nsrc/main.rs:1", style=bold];
+  n1 [label="parse<\"x\">\nsrc/lib.rs:7"];
+  n0 -> n1;
+  n1 -> n1;
+}
+"#
+        );
+        assert_eq!(
+            graph.render(GraphFormat::Mermaid),
+            r#"flowchart LR
+  n0["main<br/>src/main.rs:1"]
+  n1["parse#lt;#quot;x#quot;#gt;<br/>src/lib.rs:7"]
+  n0 --> n1
+  n1 --> n1
+  style n0 stroke-width:3px
+"#
+        );
+        assert_eq!((graph.nodes(), graph.edges()), (2, 2));
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let mut graph = CallGraph::default();
+        for i in 0..MAX_NODES {
+            graph.add(i.to_string(), "f", String::new()).unwrap();
+        }
+        assert!(!graph.is_truncated());
+        assert_eq!(graph.add("0".to_owned(), "f", String::new()), Some(0));
+        assert_eq!(graph.add("new".to_owned(), "f", String::new()), None);
+        assert!(graph.is_truncated());
+    }
+}
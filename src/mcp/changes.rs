@@ -0,0 +1,469 @@
+//! Symbols that changed between two git revisions.
+//!
+//! `git diff` reports changed line ranges, which are mapped to the innermost document symbols that enclose them, in
+//! the old and in the new version of a file. A symbol that exists in both versions is modified, otherwise it was added
+//! or removed. Symbols are identified by name, kind, and container, so a renamed symbol is reported as removed and
+//! added.
+use std::{collections::BTreeSet, ops::Range, path::Path, process::Stdio};
+
+use anyhow::{Context, Result, ensure};
+use lsp_types::{SymbolInformation, SymbolKind, Uri};
+use rmcp::schemars;
+use tokio::process::Command;
+
+use crate::lsp::uri::path_to_file_uri;
+
+/// Change of a symbol.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Change of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum FileChange {
+    Added,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+/// Changes of a single file, as reported by `git diff`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct FileDiff {
+    /// Path in the old revision, relative to the folder, `None` for added files.
+    pub(super) old_path: Option<String>,
+
+    /// Path in the new revision, relative to the folder, `None` for removed files.
+    pub(super) new_path: Option<String>,
+
+    /// Changed lines (1-based) in the old revision.
+    pub(super) old_lines: Vec<Range<u32>>,
+
+    /// Changed lines (1-based) in the new revision.
+    pub(super) new_lines: Vec<Range<u32>>,
+}
+
+impl FileDiff {
+    pub(super) fn change(&self) -> FileChange {
+        match (&self.old_path, &self.new_path) {
+            (None, _) => FileChange::Added,
+            (_, None) => FileChange::Removed,
+            (Some(old), Some(new)) if old != new => FileChange::Renamed,
+            _ => FileChange::Modified,
+        }
+    }
+}
+
+/// Symbol that was added, modified, or removed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct ChangedSymbol {
+    /// line in the new revision, or in the old one for removed symbols
+    pub(super) line: u32,
+
+    pub(super) name: String,
+    pub(super) kind: String,
+
+    /// enclosing symbol, e.g. the type of a method
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) container: Option<String>,
+
+    pub(super) change: Change,
+}
+
+/// Run git in the given directory and return its stdout.
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("run git")?;
+    ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.first().copied().unwrap_or_default(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    String::from_utf8(output.stdout).context("git output is not UTF-8")
+}
+
+/// Commit hash of a revision, e.g. `HEAD~1` or a branch name.
+pub(super) async fn resolve(dir: &Path, rev: &str) -> Result<String> {
+    ensure!(!rev.starts_with('-'), "invalid revision: {rev}");
+    let commit = git(
+        dir,
+        &["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+    )
+    .await
+    .with_context(|| format!("unknown revision: {rev}"))?;
+    Ok(commit.trim().to_owned())
+}
+
+/// Changed files between two commits, or between a commit and the working tree if `new` is `None`.
+///
+/// Only files within the directory are reported, paths are relative to it.
+pub(super) async fn diff(dir: &Path, old: &str, new: Option<&str>) -> Result<Vec<FileDiff>> {
+    let mut args = vec![
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--unified=0",
+        "--find-renames",
+        "--relative",
+        old,
+    ];
+    args.extend(new);
+    args.push("--");
+    Ok(parse_diff(&git(dir, &args).await?))
+}
+
+/// Content of a file in the given commit, the path is relative to the directory.
+pub(super) async fn show(dir: &Path, commit: &str, path: &str) -> Result<String> {
+    git(dir, &["show", &format!("{commit}:./{path}")]).await
+}
+
+/// URI under which the content of a file in the given commit is opened in the language server.
+///
+/// It is outside the workspace, so the language server does not mix it up with the file in the working tree.
+pub(super) fn revision_uri(dir: &Path, commit: &str, path: &str) -> Result<Uri> {
+    let folder = dir
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    path_to_file_uri(
+        &std::env::temp_dir()
+            .join(env!("CARGO_PKG_NAME"))
+            .join(commit)
+            .join(folder.as_ref())
+            .join(path),
+    )
+}
+
+/// Parse output of `git diff --unified=0`.
+fn parse_diff(s: &str) -> Vec<FileDiff> {
+    let mut files = vec![];
+    let mut current: Option<FileDiff> = None;
+    // removed lines may look like headers, e.g. `--- a` for a removed `-- a`
+    let mut in_hunks = false;
+    for line in s.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.extend(current.take());
+            // fallback for diffs without `---`/`+++` lines, e.g. binary files, ambiguous if the path contains ` b/`
+            let (old, new) = rest
+                .strip_prefix("a/")
+                .and_then(|rest| rest.split_once(" b/"))
+                .unwrap_or_default();
+            current = Some(FileDiff {
+                old_path: Some(old.to_owned()),
+                new_path: Some(new.to_owned()),
+                ..Default::default()
+            });
+            in_hunks = false;
+            continue;
+        }
+        let Some(file) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(hunk) = line.strip_prefix("@@ -") {
+            // `@@ -<start>[,<count>] +<start>[,<count>] @@`
+            in_hunks = true;
+            let Some((old, rest)) = hunk.split_once(" +") else {
+                continue;
+            };
+            let new = rest.split(' ').next().unwrap_or_default();
+            file.old_lines.extend(parse_hunk_range(old));
+            file.new_lines.extend(parse_hunk_range(new));
+        } else if in_hunks {
+            continue;
+        } else if line.starts_with("new file mode") {
+            file.old_path = None;
+        } else if line.starts_with("deleted file mode") {
+            file.new_path = None;
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.old_path = Some(path.to_owned());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.new_path = Some(path.to_owned());
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            file.old_path = path.strip_prefix("a/").map(ToOwned::to_owned);
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            file.new_path = path.strip_prefix("b/").map(ToOwned::to_owned);
+        }
+    }
+    files.extend(current);
+    files
+}
+
+/// Parse `<start>[,<count>]` of a hunk header, `None` if no lines changed.
+fn parse_hunk_range(s: &str) -> Option<Range<u32>> {
+    let (start, count) = match s.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (s.parse().ok()?, 1),
+    };
+    (count > 0).then_some(start..start + count)
+}
+
+/// Symbols that changed between the old and the new version of a file, sorted by line.
+pub(super) fn changed_symbols(
+    old: &[SymbolInformation],
+    old_lines: &[Range<u32>],
+    new: &[SymbolInformation],
+    new_lines: &[Range<u32>],
+) -> Vec<ChangedSymbol> {
+    let old_touched = touched(old, old_lines);
+    let new_touched = touched(new, new_lines);
+
+    let mut changed = BTreeSet::new();
+    for symbol in &new_touched {
+        let change = if old.iter().any(|s| same(s, symbol)) {
+            Change::Modified
+        } else {
+            Change::Added
+        };
+        changed.insert(changed_symbol(symbol, change));
+    }
+    for symbol in &old_touched {
+        match new.iter().find(|s| same(s, symbol)) {
+            // already reported if the new version was touched as well
+            Some(new_symbol) if !new_touched.iter().any(|s| same(s, symbol)) => {
+                changed.insert(changed_symbol(new_symbol, Change::Modified));
+            }
+            Some(_) => {}
+            None => {
+                changed.insert(changed_symbol(symbol, Change::Removed));
+            }
+        }
+    }
+    changed.into_iter().collect()
+}
+
+/// Innermost symbols that contain changed lines.
+///
+/// A symbol is skipped if another touched symbol is within it, e.g. an `impl` block of which a method changed.
+/// Variables are ignored, their enclosing symbol is reported instead.
+fn touched<'a>(
+    symbols: &'a [SymbolInformation],
+    lines: &[Range<u32>],
+) -> Vec<&'a SymbolInformation> {
+    let candidates = symbols
+        .iter()
+        .filter(|symbol| symbol.kind != SymbolKind::VARIABLE)
+        .filter(|symbol| {
+            let range = line_range(symbol);
+            lines.iter().any(|changed| overlaps(changed, &range))
+        })
+        .collect::<Vec<_>>();
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|symbol| {
+            let range = line_range(symbol);
+            !candidates.iter().any(|other| {
+                let other = line_range(other);
+                other != range && contains(&range, &other)
+            })
+        })
+        .collect()
+}
+
+/// Lines (1-based) that a symbol spans.
+fn line_range(symbol: &SymbolInformation) -> Range<u32> {
+    let range = symbol.location.range;
+    range.start.line + 1..range.end.line + 2
+}
+
+fn overlaps(a: &Range<u32>, b: &Range<u32>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+fn contains(outer: &Range<u32>, inner: &Range<u32>) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Symbols of different versions are the same if name, kind, and container match.
+fn same(a: &SymbolInformation, b: &SymbolInformation) -> bool {
+    a.name == b.name && a.kind == b.kind && a.container_name == b.container_name
+}
+
+fn changed_symbol(symbol: &SymbolInformation, change: Change) -> ChangedSymbol {
+    ChangedSymbol {
+        line: symbol.location.range.start.line + 1,
+        name: symbol.name.clone(),
+        kind: format!("{:?}", symbol.kind),
+        container: symbol.container_name.clone(),
+        change,
+    }
+}
+
+#[cfg(test)]
+#[expect(
+    clippy::single_range_in_vec_init,
+    reason = "lists of changed lines that happen to have one entry"
+)]
+mod test {
+    use std::ops::RangeInclusive;
+
+    use lsp_types::{Location, Position};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3,2 @@ fn foo() {
+-    1
++    2
++    + 3
+@@ -10,2 +11,0 @@ fn bar() {
+-    let x = 1;
+--- x
+diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1,3 @@
++fn new() {
++}
++
+diff --git a/old name.rs b/new name.rs
+similarity index 100%
+rename from old name.rs
+rename to new name.rs
+diff --git a/src/gone.rs b/src/gone.rs
+deleted file mode 100644
+index 4444444..0000000
+--- a/src/gone.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn gone() {}
+";
+        assert_eq!(
+            parse_diff(diff),
+            vec![
+                FileDiff {
+                    old_path: Some("src/lib.rs".to_owned()),
+                    new_path: Some("src/lib.rs".to_owned()),
+                    old_lines: vec![3..4, 10..12],
+                    new_lines: vec![3..5],
+                },
+                FileDiff {
+                    old_path: None,
+                    new_path: Some("src/new.rs".to_owned()),
+                    old_lines: vec![],
+                    new_lines: vec![1..4],
+                },
+                FileDiff {
+                    old_path: Some("old name.rs".to_owned()),
+                    new_path: Some("new name.rs".to_owned()),
+                    old_lines: vec![],
+                    new_lines: vec![],
+                },
+                FileDiff {
+                    old_path: Some("src/gone.rs".to_owned()),
+                    new_path: None,
+                    old_lines: vec![1..2],
+                    new_lines: vec![],
+                },
+            ]
+        );
+        assert_eq!(parse_diff(""), vec![]);
+
+        let changes = parse_diff(diff)
+            .iter()
+            .map(FileDiff::change)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                FileChange::Modified,
+                FileChange::Added,
+                FileChange::Renamed,
+                FileChange::Removed
+            ]
+        );
+    }
+
+    /// Symbol spanning the given lines (1-based).
+    fn symbol(
+        name: &str,
+        kind: SymbolKind,
+        lines: RangeInclusive<u32>,
+        container: Option<&str>,
+    ) -> SymbolInformation {
+        #[expect(deprecated, reason = "lsp-types still requires this field")]
+        SymbolInformation {
+            name: name.to_owned(),
+            kind,
+            tags: None,
+            deprecated: None,
+            location: Location {
+                uri: "file:///lib.rs".parse().unwrap(),
+                range: lsp_types::Range {
+                    start: Position::new(lines.start() - 1, 0),
+                    end: Position::new(lines.end() - 1, 1),
+                },
+            },
+            container_name: container.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_changed_symbols() {
+        let old = vec![
+            symbol("Foo", SymbolKind::STRUCT, 1..=3, None),
+            symbol("impl Foo", SymbolKind::OBJECT, 5..=15, None),
+            symbol("a", SymbolKind::METHOD, 6..=8, Some("impl Foo")),
+            symbol("b", SymbolKind::METHOD, 10..=14, Some("impl Foo")),
+            symbol("x", SymbolKind::VARIABLE, 11..=11, None),
+            symbol("old", SymbolKind::FUNCTION, 17..=19, None),
+        ];
+        // `a` unchanged, `b` lost a line, `c` was added, `old` was removed
+        let new = vec![
+            symbol("Foo", SymbolKind::STRUCT, 1..=3, None),
+            symbol("impl Foo", SymbolKind::OBJECT, 5..=18, None),
+            symbol("a", SymbolKind::METHOD, 6..=8, Some("impl Foo")),
+            symbol("b", SymbolKind::METHOD, 10..=13, Some("impl Foo")),
+            symbol("c", SymbolKind::METHOD, 15..=17, Some("impl Foo")),
+        ];
+        let changed = changed_symbols(&old, &[11..12, 16..20], &new, &[14..18]);
+        let changed = changed
+            .iter()
+            .map(|symbol| (symbol.line, symbol.name.as_str(), symbol.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            vec![
+                (10, "b", Change::Modified),
+                (15, "c", Change::Added),
+                (17, "old", Change::Removed),
+            ]
+        );
+
+        // change of the `impl` header that is not within a method
+        let changed = changed_symbols(&old, &[5..6], &new, &[5..6]);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].name, "impl Foo");
+        assert_eq!(changed[0].change, Change::Modified);
+
+        // added file, the `impl` block is represented by its methods
+        let changed = changed_symbols(&[], &[], &new, &[1..19]);
+        assert_eq!(changed.len(), 4);
+        assert!(changed.iter().all(|symbol| symbol.change == Change::Added));
+    }
+}
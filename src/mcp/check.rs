@@ -0,0 +1,334 @@
+//! Diagnostics of an on-demand check, e.g. `cargo check` via rust-analyzer.
+use std::time::Duration;
+
+use anyhow::Context;
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeWatchedFilesParams, FileChangeType, FileEvent,
+    Location, NumberOrString, notification::DidChangeWatchedFiles,
+};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+use tokio_stream::StreamExt;
+use tracing::debug;
+
+use crate::lsp::{
+    location::{McpLocation, path_to_text_document_identifier},
+    requests::{RunFlycheck, RunFlycheckParams},
+    scheduler::Priority,
+};
+
+use super::{CodeExplorer, error::ResultExt, hints, timeout::TimedOut};
+
+/// How long to wait for the check to start before triggering it again.
+///
+/// The language server silently drops the trigger if files change while it figures out what to check.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the check is triggered before giving up, e.g. because the language server does not support it.
+const MAX_TRIGGERS: usize = 10;
+
+/// How long no diagnostics must be published after the check finished, because the language server publishes them
+/// after reporting the end of the check.
+pub(super) const SETTLE: Duration = Duration::from_millis(500);
+
+/// Severity of a diagnostic, most severe first.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl From<Option<DiagnosticSeverity>> for Severity {
+    fn from(severity: Option<DiagnosticSeverity>) -> Self {
+        match severity {
+            Some(DiagnosticSeverity::ERROR) => Self::Error,
+            Some(DiagnosticSeverity::INFORMATION) => Self::Information,
+            Some(DiagnosticSeverity::HINT) => Self::Hint,
+            // the language server did not say, so it should not go unnoticed
+            _ => Self::Warning,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct CheckDiagnostic {
+    pub(super) location: McpLocation,
+    pub(super) severity: Severity,
+
+    /// e.g. `E0308` or `dead_code`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) code: Option<String>,
+
+    pub(super) message: String,
+}
+
+impl CheckDiagnostic {
+    pub(super) fn new(location: McpLocation, diagnostic: Diagnostic) -> Self {
+        Self {
+            location,
+            severity: diagnostic.severity.into(),
+            code: diagnostic.code.map(|code| match code {
+                NumberOrString::String(code) => code,
+                NumberOrString::Number(code) => code.to_string(),
+            }),
+            message: diagnostic.message,
+        }
+    }
+}
+
+/// Sort diagnostics, most severe first and then by location.
+///
+/// The language server publishes diagnostics in no particular order, e.g. the same warning for multiple targets.
+pub(super) fn sort(diagnostics: &mut [CheckDiagnostic]) {
+    fn key(diagnostic: &CheckDiagnostic) -> (Severity, &str, u32, u32, &str) {
+        let location = &diagnostic.location;
+        (
+            diagnostic.severity,
+            &location.file,
+            location.line,
+            location.character,
+            &diagnostic.message,
+        )
+    }
+
+    diagnostics.sort_by(|a, b| key(a).cmp(&key(b)));
+}
+
+#[tool_router(router = check_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Run Check",
+        description = "Check whether the code still compiles, e.g. after editing files: runs the check command of the language server (e.g. `cargo check` for rust-analyzer) for the entire workspace or only the crate of the given file, waits for it to finish, and returns the resulting errors and warnings.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn run_check(
+        &self,
+        Parameters(RunCheckRequest { file, timeout_secs }): Parameters<RunCheckRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<RunCheckResult>, McpError> {
+        let server = match &file {
+            Some(file) => self.server_for_file(file)?,
+            None => self
+                .servers
+                .iter()
+                .find(|server| server.quirks().flycheck_progress_prefix().is_some())
+                .ok_or_else(|| {
+                    McpError::invalid_params("no language server supports on-demand checks", None)
+                })?,
+        };
+        let Some(prefix) = server.quirks().flycheck_progress_prefix() else {
+            return Err(McpError::invalid_params(
+                format!("{} does not support on-demand checks", server.name()),
+                None,
+            ));
+        };
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(RunCheckResult::timed_out()));
+        };
+
+        let text_document = match &file {
+            Some(file) => {
+                let Some(file_content) = self.read_file(file).await? else {
+                    return Err(hints::file_not_found(&self.workspace(), file).await);
+                };
+                let text_document = path_to_text_document_identifier(&self.workspace(), file)
+                    .context("convert path to text document identifier")
+                    .internal()?;
+                // the file watcher only polls for changes, so a fresh edit may not be known yet
+                server
+                    .documents()
+                    .sync(&text_document.uri, &file_content)
+                    .await
+                    .context("sync document")
+                    .internal()?;
+                client
+                    .send_notification::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+                        changes: vec![FileEvent::new(
+                            text_document.uri.clone(),
+                            FileChangeType::CHANGED,
+                        )],
+                    })
+                    .await
+                    .context("DidChangeWatchedFiles")
+                    .internal()?;
+                Some(text_document)
+            }
+            None => None,
+        };
+
+        let done = deadline
+            .run(async {
+                for trigger in 1.. {
+                    // subscribe before triggering the check, so its start is not missed
+                    let mut events = server.progress_guard().events();
+                    client
+                        .send_notification::<RunFlycheck>(RunFlycheckParams {
+                            text_document: text_document.clone(),
+                        })
+                        .await
+                        .context("RunFlycheck")
+                        .internal()?;
+                    let started = tokio::time::timeout(START_TIMEOUT, async {
+                        while let Some(evt) = events.next().await {
+                            if matches!(&evt.token, NumberOrString::String(token) if token.starts_with(&prefix))
+                            {
+                                return true;
+                            }
+                        }
+                        false
+                    })
+                    .await;
+                    match started {
+                        Ok(true) => break,
+                        // shutting down
+                        Ok(false) => return Ok(()),
+                        Err(_) if trigger < MAX_TRIGGERS => {
+                            debug!("check did not start, trigger it again")
+                        }
+                        Err(_) => {
+                            return Err(McpError::internal_error(
+                                format!(
+                                    "check did not start after {MAX_TRIGGERS} attempts of {}s each",
+                                    START_TIMEOUT.as_secs()
+                                ),
+                                None,
+                            ));
+                        }
+                    }
+                }
+                // the language server was idle before the trigger, so the check is done once it is idle again
+                server.progress_guard().wait().await;
+                server.diagnostics().settle(SETTLE).await;
+                Ok::<_, McpError>(())
+            })
+            .await;
+        if let Some(res) = done {
+            res?;
+        }
+
+        let mut diagnostics = vec![];
+        for (uri, items) in server.diagnostics().snapshot() {
+            for diagnostic in items {
+                let location = Location::new(uri.clone(), diagnostic.range);
+                let Some(location) = McpLocation::try_new(location, self.workspace(), false)
+                    .context("convert diagnostic location")
+                    .internal()?
+                else {
+                    continue;
+                };
+                diagnostics.push(CheckDiagnostic::new(location, diagnostic));
+            }
+        }
+        sort(&mut diagnostics);
+        let count = |severity| {
+            diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.severity == severity)
+                .count()
+        };
+
+        Ok(Json(RunCheckResult {
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+            diagnostics,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct RunCheckRequest {
+    /// only check the crate (or similar unit) that contains this file, checks the entire workspace if not set
+    file: Option<String>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct RunCheckResult {
+    /// number of errors
+    errors: usize,
+
+    /// number of warnings
+    warnings: usize,
+
+    /// diagnostics within the workspace, most severe first
+    diagnostics: Vec<CheckDiagnostic>,
+
+    /// the timeout was hit, the check may still be running and results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for RunCheckResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lsp::workspace::Workspace;
+
+    use super::*;
+
+    #[test]
+    fn test_sort() {
+        let workspace = Workspace::new([std::env::temp_dir()]).unwrap();
+        let diagnostic = |file: &str, line: u32, severity: Option<DiagnosticSeverity>| {
+            CheckDiagnostic::new(
+                McpLocation::new(file.to_owned(), line, 1, workspace.clone()),
+                Diagnostic {
+                    severity,
+                    message: format!("{file}:{line}"),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let mut diagnostics = vec![
+            diagnostic("b.rs", 1, Some(DiagnosticSeverity::HINT)),
+            diagnostic("b.rs", 3, Some(DiagnosticSeverity::ERROR)),
+            diagnostic("a.rs", 2, None),
+            diagnostic("a.rs", 10, Some(DiagnosticSeverity::ERROR)),
+            diagnostic("a.rs", 5, Some(DiagnosticSeverity::WARNING)),
+        ];
+        sort(&mut diagnostics);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.severity, d.message.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (Severity::Error, "a.rs:10"),
+                (Severity::Error, "b.rs:3"),
+                (Severity::Warning, "a.rs:2"),
+                (Severity::Warning, "a.rs:5"),
+                (Severity::Hint, "b.rs:1"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Argument completion for interactive MCP clients.
+//!
+//! MCP has no reference type for tools, so clients send the tool name as prompt reference. The `path` of the file
+//! resource template is completed as well.
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use lsp_types::{WorkspaceSymbolParams, WorkspaceSymbolResponse, request::WorkspaceSymbolRequest};
+use rmcp::model::{
+    ArgumentInfo, CompleteRequestParams, CompleteResult, CompletionInfo, ErrorData as McpError,
+    Reference,
+};
+use tracing::debug;
+
+use crate::lsp::{
+    scheduler::Priority,
+    server::LanguageServer,
+    workspace::{SharedWorkspace, Workspace},
+};
+
+use super::error::ResultExt;
+
+/// How long workspace symbols are reused for completions.
+const SYMBOL_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// What an argument is completed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Path relative to the workspace.
+    File,
+
+    /// Absolute path within the workspace.
+    AbsoluteFile,
+
+    /// Symbol name.
+    Symbol,
+}
+
+impl Kind {
+    fn for_request(reference: &Reference, argument: &str) -> Option<Self> {
+        match (reference, argument) {
+            (Reference::Prompt(tool), argument) => match (tool.name.as_str(), argument) {
+                ("find_symbol" | "symbol_info", "file") => Some(Self::File),
+                ("find_symbol", "query") | ("symbol_info", "name") => Some(Self::Symbol),
+                _ => None,
+            },
+            (Reference::Resource(resource), "path") if resource.uri.starts_with("file://") => {
+                Some(Self::AbsoluteFile)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Workspace symbol names of a single query.
+#[derive(Debug)]
+struct CachedSymbols {
+    query: String,
+    names: Vec<String>,
+    fetched: Instant,
+
+    /// [File revision](SharedWorkspace::file_revision) at the time of the query.
+    file_revision: u64,
+}
+
+/// Completes tool arguments.
+#[derive(Debug, Default)]
+pub(super) struct Completer {
+    symbols: Mutex<Option<CachedSymbols>>,
+}
+
+impl Completer {
+    pub(super) async fn complete(
+        &self,
+        servers: &[LanguageServer],
+        shared_workspace: &SharedWorkspace,
+        request: CompleteRequestParams,
+    ) -> Result<CompleteResult, McpError> {
+        let workspace = &shared_workspace.get();
+        let CompleteRequestParams {
+            r#ref,
+            argument: ArgumentInfo { name, value },
+            ..
+        } = request;
+        let Some(kind) = Kind::for_request(&r#ref, &name) else {
+            return Ok(CompleteResult::default());
+        };
+
+        let values = match kind {
+            Kind::File => complete_workspace_path(workspace, &value).await,
+            Kind::AbsoluteFile => {
+                let mut values = vec![];
+                for folder in workspace.folders() {
+                    values.extend(complete_absolute_path(folder, &value).await);
+                }
+                values
+            }
+            Kind::Symbol => {
+                self.complete_symbol(servers, shared_workspace.file_revision(), &value)
+                    .await?
+            }
+        };
+
+        let total = values.len();
+        let has_more = total > CompletionInfo::MAX_VALUES;
+        let values = values
+            .into_iter()
+            .take(CompletionInfo::MAX_VALUES)
+            .collect();
+        let completion = CompletionInfo::with_pagination(values, Some(total as u32), has_more)
+            .map_err(|e| McpError::internal_error(e, None))?;
+        Ok(CompleteResult::new(completion))
+    }
+
+    /// Complete symbol names.
+    ///
+    /// The workspace symbols of the last query are cached, since completions are requested for every keystroke and
+    /// usually only extend the previous value. Changed files invalidate the cache.
+    async fn complete_symbol(
+        &self,
+        servers: &[LanguageServer],
+        file_revision: u64,
+        value: &str,
+    ) -> Result<Vec<String>, McpError> {
+        if value.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let cached = self
+            .symbols
+            .lock()
+            .expect("not poisoned")
+            .as_ref()
+            .filter(|cached| {
+                value.starts_with(&cached.query)
+                    && cached.fetched.elapsed() < SYMBOL_CACHE_TTL
+                    && cached.file_revision == file_revision
+            })
+            .map(|cached| cached.names.clone());
+        let names = match cached {
+            Some(names) => names,
+            None => {
+                let names = fetch_symbol_names(servers, value).await?;
+                // results of language servers that are still indexing are incomplete
+                if servers
+                    .iter()
+                    .all(|server| server.progress_guard().is_ready())
+                {
+                    *self.symbols.lock().expect("not poisoned") = Some(CachedSymbols {
+                        query: value.to_owned(),
+                        names: names.clone(),
+                        fetched: Instant::now(),
+                        file_revision,
+                    });
+                }
+                names
+            }
+        };
+
+        let value = value.to_lowercase();
+        Ok(names
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&value))
+            .collect())
+    }
+}
+
+/// Fetch sorted and deduplicated workspace symbol names.
+///
+/// This does NOT wait for the language servers to be ready, since completions need to be fast.
+async fn fetch_symbol_names(
+    servers: &[LanguageServer],
+    query: &str,
+) -> Result<Vec<String>, McpError> {
+    let mut names = vec![];
+    for server in servers.iter().filter(|server| !server.is_secondary()) {
+        let _permit = server.scheduler().acquire(Priority::Low).await;
+        let resp = server
+            .progress_guard()
+            .client()
+            .send_request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+                query: query.to_owned(),
+                ..Default::default()
+            })
+            .await
+            .context("WorkspaceSymbolRequest")
+            .internal()?;
+        match resp {
+            None => {}
+            Some(WorkspaceSymbolResponse::Flat(symbols)) => {
+                names.extend(symbols.into_iter().map(|s| s.name));
+            }
+            Some(WorkspaceSymbolResponse::Nested(symbols)) => {
+                names.extend(symbols.into_iter().map(|s| s.name));
+            }
+        }
+    }
+    debug!(query, n = names.len(), "fetched symbols for completion");
+
+    Ok(names.into_iter().sorted().dedup().collect())
+}
+
+/// Complete path relative to the workspace.
+///
+/// With multiple workspace folders, the path starts with the folder name.
+async fn complete_workspace_path(workspace: &Workspace, value: &str) -> Vec<String> {
+    let mut prefixes = workspace.prefixes().peekable();
+    if prefixes.peek().is_none() {
+        return complete_path(workspace.root(), value).await;
+    }
+
+    match value.split_once('/') {
+        Some((name, rest)) if prefixes.any(|prefix| prefix == name) => {
+            complete_path(&workspace.resolve(name), rest)
+                .await
+                .into_iter()
+                .map(|path| format!("{name}/{path}"))
+                .collect()
+        }
+        Some(_) => vec![],
+        None => prefixes
+            .filter(|prefix| prefix.starts_with(value))
+            .map(|prefix| format!("{prefix}/"))
+            .collect(),
+    }
+}
+
+/// Complete path relative to a directory.
+///
+/// Directories end with `/`. Hidden entries are only listed if the value asks for them.
+async fn complete_path(workspace: &Path, value: &str) -> Vec<String> {
+    let (dir, prefix) = match value.rsplit_once('/') {
+        Some((dir, prefix)) => (Some(dir), prefix),
+        None => (None, value),
+    };
+    if dir.is_some_and(|dir| dir.starts_with('/') || dir.split('/').any(|part| part == "..")) {
+        return vec![];
+    }
+
+    let Ok(mut entries) = tokio::fs::read_dir(workspace.join(dir.unwrap_or_default())).await else {
+        return vec![];
+    };
+
+    let mut values = vec![];
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+            continue;
+        }
+        let is_dir = entry
+            .file_type()
+            .await
+            .map(|t| t.is_dir())
+            .unwrap_or_default();
+
+        let mut value = match dir {
+            Some(dir) => format!("{dir}/{name}"),
+            None => name,
+        };
+        if is_dir {
+            value.push('/');
+        }
+        values.push(value);
+    }
+
+    values.sort_unstable();
+    values
+}
+
+/// Complete absolute path within the workspace.
+async fn complete_absolute_path(workspace: &Path, value: &str) -> Vec<String> {
+    let workspace = workspace.display().to_string();
+    match value.strip_prefix(&format!("{workspace}/")) {
+        Some(rest) => complete_path(Path::new(&workspace), rest)
+            .await
+            .into_iter()
+            .map(|path| format!("{workspace}/{path}"))
+            .collect(),
+        None if workspace.starts_with(value) => vec![format!("{workspace}/")],
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        let tool = Reference::for_prompt;
+
+        assert_eq!(
+            Kind::for_request(&tool("find_symbol"), "query"),
+            Some(Kind::Symbol)
+        );
+        assert_eq!(
+            Kind::for_request(&tool("symbol_info"), "name"),
+            Some(Kind::Symbol)
+        );
+        assert_eq!(
+            Kind::for_request(&tool("symbol_info"), "file"),
+            Some(Kind::File)
+        );
+        assert_eq!(Kind::for_request(&tool("symbol_info"), "line"), None);
+        assert_eq!(Kind::for_request(&tool("server_status"), "file"), None);
+        assert_eq!(
+            Kind::for_request(&Reference::for_resource("file://{path}"), "path"),
+            Some(Kind::AbsoluteFile)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+
+        assert_eq!(
+            complete_path(dir.path(), "").await,
+            vec!["Cargo.toml", "src/"]
+        );
+        assert_eq!(complete_path(dir.path(), ".").await, vec![".git/"]);
+        assert_eq!(
+            complete_path(dir.path(), "src/").await,
+            vec!["src/lib.rs", "src/main.rs", "src/nested/"]
+        );
+        assert_eq!(complete_path(dir.path(), "src/l").await, vec!["src/lib.rs"]);
+        assert_eq!(
+            complete_path(dir.path(), "foo/").await,
+            Vec::<String>::new()
+        );
+        assert_eq!(complete_path(dir.path(), "../").await, Vec::<String>::new());
+        assert_eq!(
+            complete_path(dir.path(), "/etc/").await,
+            Vec::<String>::new()
+        );
+
+        let ws = dir.path().display().to_string();
+        assert_eq!(
+            complete_absolute_path(dir.path(), &format!("{ws}/src/m")).await,
+            vec![format!("{ws}/src/main.rs")]
+        );
+        assert_eq!(
+            complete_absolute_path(dir.path(), "/").await,
+            vec![format!("{ws}/")]
+        );
+        assert_eq!(
+            complete_absolute_path(dir.path(), "/nope").await,
+            Vec::<String>::new()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_workspace_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("backend/src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("frontend")).unwrap();
+        std::fs::write(dir.path().join("backend/src/lib.rs"), "").unwrap();
+
+        let single = Workspace::new([dir.path().join("backend")]).unwrap();
+        assert_eq!(complete_workspace_path(&single, "s").await, vec!["src/"]);
+
+        let multi =
+            Workspace::new([dir.path().join("backend"), dir.path().join("frontend")]).unwrap();
+        assert_eq!(
+            complete_workspace_path(&multi, "").await,
+            vec!["backend/", "frontend/"]
+        );
+        assert_eq!(complete_workspace_path(&multi, "b").await, vec!["backend/"]);
+        assert_eq!(
+            complete_workspace_path(&multi, "backend/src/").await,
+            vec!["backend/src/lib.rs"]
+        );
+        assert_eq!(
+            complete_workspace_path(&multi, "other/").await,
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Daemon that keeps the language servers running across MCP client reconnects.
+//!
+//! The process started by the MCP client only forwards stdio to a background daemon via a Unix socket. The daemon is
+//! started on demand and serves every connection as a separate MCP session.
+use std::{
+    ffi::OsString,
+    os::unix::{ffi::OsStrExt, fs::DirBuilderExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use rmcp::{ServiceExt, transport::stdio};
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::{UnixListener, UnixStream},
+    task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    cli::parse_secs,
+    lsp::{
+        server::LanguageServer,
+        workspace::{SharedWorkspace, Workspace},
+    },
+};
+
+use super::{CodeExplorer, tools::ToolsCLIConfig};
+
+/// How long the MCP client waits for a freshly spawned daemon.
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// CLI config for the daemon mode.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct DaemonCLIConfig {
+    /// Keep the language servers running in a background daemon after the MCP client disconnects.
+    ///
+    /// The next start with the same arguments re-attaches to the daemon instead of starting the language servers
+    /// again. IO interception only covers the language servers in this mode.
+    #[clap(long, env = "COMMON_SENSE_CODER_DAEMON")]
+    daemon: bool,
+
+    /// Seconds that the daemon keeps running without any connected MCP client.
+    #[clap(
+        long,
+        default_value = "600",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_DAEMON_GRACE_PERIOD"
+    )]
+    daemon_grace_period: Duration,
+
+    /// Directory for the daemon sockets and logs.
+    ///
+    /// Defaults to `$XDG_RUNTIME_DIR/common-sense-coder` or a directory within the system-wide temporary directory.
+    #[clap(long, env = "COMMON_SENSE_CODER_DAEMON_DIR")]
+    daemon_dir: Option<PathBuf>,
+
+    /// Serve on the given socket, used internally to start the daemon.
+    #[clap(long, hide = true, requires = "daemon")]
+    daemon_socket: Option<PathBuf>,
+}
+
+/// Role of this process.
+#[derive(Debug)]
+pub(crate) enum Role {
+    /// No daemon, serve MCP directly.
+    Standalone,
+
+    /// Forward stdio to the daemon at the given socket.
+    Proxy(PathBuf),
+
+    /// Serve MCP on the given socket.
+    Daemon(PathBuf),
+}
+
+impl DaemonCLIConfig {
+    /// Daemon mode is enabled.
+    pub(crate) fn enabled(&self) -> bool {
+        self.daemon
+    }
+
+    /// Determine role of this process.
+    pub(crate) fn role(&self, workspace: &Workspace) -> Result<Role> {
+        if let Some(socket) = &self.daemon_socket {
+            return Ok(Role::Daemon(socket.clone()));
+        }
+        if !self.daemon {
+            return Ok(Role::Standalone);
+        }
+
+        let dir = match &self.daemon_dir {
+            Some(dir) => dir.clone(),
+            None => match std::env::var_os("XDG_RUNTIME_DIR") {
+                Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("common-sense-coder"),
+                _ => std::env::temp_dir().join("common-sense-coder"),
+            },
+        };
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("create daemon directory: {}", dir.display()))?;
+
+        Ok(Role::Proxy(
+            dir.join(format!("{}.sock", daemon_key(workspace))),
+        ))
+    }
+
+    /// Forward stdio to the daemon, starting it if required.
+    pub(crate) async fn proxy(&self, socket: &Path) -> Result<()> {
+        let stream = match UnixStream::connect(socket).await {
+            Ok(stream) => {
+                info!(socket=%socket.display(), "attach to running daemon");
+                stream
+            }
+            Err(e) => {
+                debug!(%e, socket=%socket.display(), "cannot connect to daemon");
+                spawn_daemon(socket)?;
+                connect_spawned(socket).await?
+            }
+        };
+
+        let (mut socket_read, mut socket_write) = stream.into_split();
+        let (mut stdin, mut stdout) = stdio();
+        tokio::select! {
+            res = tokio::io::copy(&mut stdin, &mut socket_write) => {
+                res.context("forward stdin to daemon")?;
+            }
+            res = tokio::io::copy(&mut socket_read, &mut stdout) => {
+                res.context("forward daemon to stdout")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serve MCP sessions until cancelled or idle for longer than the grace period.
+    pub(crate) async fn serve(
+        &self,
+        listener: UnixListener,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<()> {
+        let grace_period = self.daemon_grace_period;
+        info!(?grace_period, "daemon ready");
+
+        let mut sessions = JoinSet::new();
+        loop {
+            tokio::select! {
+                _ = ct.cancelled() => {
+                    break;
+                }
+                _ = tokio::time::sleep(grace_period), if sessions.is_empty() => {
+                    info!("no MCP client within grace period, stop daemon");
+                    break;
+                }
+                Some(_) = sessions.join_next(), if !sessions.is_empty() => {}
+                res = listener.accept() => {
+                    let stream = match res {
+                        Ok((stream, _addr)) => stream,
+                        Err(e) => {
+                            warn!(%e, "cannot accept connection");
+                            continue;
+                        }
+                    };
+                    info!("new MCP session");
+
+                    let explorer = CodeExplorer::new(Arc::clone(&servers), workspace.clone(), tools_cfg.clone());
+                    let ct = ct.child_token();
+                    sessions.spawn(async move {
+                        let res = async {
+                            explorer
+                                .serve_with_ct(stream, ct)
+                                .await
+                                .context("set up code explorer service")?
+                                .waiting()
+                                .await
+                                .context("wait for service")?;
+                            Result::<()>::Ok(())
+                        }
+                        .await;
+                        match res {
+                            Ok(()) => info!("MCP session closed"),
+                            Err(e) => warn!(%e, "MCP session failed"),
+                        }
+                    });
+                }
+            }
+        }
+
+        // stop accepting new clients before the language servers shut down
+        drop(listener);
+        if let Some(socket) = &self.daemon_socket {
+            std::fs::remove_file(socket)
+                .with_context(|| format!("remove socket: {}", socket.display()))?;
+        }
+
+        while sessions.join_next().await.is_some() {}
+
+        Ok(())
+    }
+}
+
+/// Bind daemon socket.
+///
+/// Returns [`None`] if another daemon is already serving on it.
+pub(crate) fn bind(socket: &Path) -> Result<Option<UnixListener>> {
+    match UnixListener::bind(socket) {
+        Ok(listener) => Ok(Some(listener)),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+                info!(socket=%socket.display(), "daemon already running");
+                return Ok(None);
+            }
+
+            // stale socket of a daemon that crashed
+            std::fs::remove_file(socket)
+                .with_context(|| format!("remove stale socket: {}", socket.display()))?;
+            UnixListener::bind(socket)
+                .map(Some)
+                .with_context(|| format!("bind to {}", socket.display()))
+        }
+        Err(e) => Err(e).with_context(|| format!("bind to {}", socket.display())),
+    }
+}
+
+/// Key that identifies daemons with the same configuration.
+fn daemon_key(workspace: &Workspace) -> String {
+    let mut hasher = Sha256::new();
+    for folder in workspace.folders() {
+        hasher.update(folder.as_os_str().as_bytes());
+        hasher.update([0]);
+    }
+    for arg in std::env::args_os().skip(1) {
+        hasher.update([0]);
+        hasher.update(arg.as_bytes());
+    }
+    let mut env = std::env::vars_os()
+        .filter(|(k, _v)| k.as_bytes().starts_with(b"COMMON_SENSE_CODER_"))
+        .collect::<Vec<_>>();
+    env.sort();
+    for (k, v) in env {
+        hasher.update([0]);
+        hasher.update(k.as_bytes());
+        hasher.update([b'=']);
+        hasher.update(v.as_bytes());
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Start daemon in the background.
+fn spawn_daemon(socket: &Path) -> Result<()> {
+    let log_path = socket.with_extension("log");
+    info!(socket=%socket.display(), log=%log_path.display(), "start daemon");
+
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("open daemon log: {}", log_path.display()))?;
+    let mut socket_arg = OsString::from("--daemon-socket=");
+    socket_arg.push(socket);
+
+    // the daemon outlives us, so we never wait for it
+    std::process::Command::new(std::env::current_exe().context("determine executable")?)
+        .args(std::env::args_os().skip(1))
+        .arg(socket_arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        // detach from terminal signals like Ctrl-C
+        .process_group(0)
+        .spawn()
+        .context("spawn daemon")?;
+
+    Ok(())
+}
+
+/// Connect to freshly spawned daemon.
+async fn connect_spawned(socket: &Path) -> Result<UnixStream> {
+    let start = tokio::time::Instant::now();
+    loop {
+        match UnixStream::connect(socket).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if start.elapsed() > SPAWN_TIMEOUT => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "connect to daemon at {}, see {} for logs",
+                        socket.display(),
+                        socket.with_extension("log").display()
+                    )
+                });
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn workspace(path: &Path) -> Workspace {
+        Workspace::new([path.to_owned()]).unwrap()
+    }
+
+    #[test]
+    fn test_role() {
+        let dir = TempDir::new().unwrap();
+
+        let cfg = DaemonCLIConfig::try_parse_from(["", "--daemon-grace-period=1"]).unwrap();
+        assert!(matches!(
+            cfg.role(&workspace(dir.path())).unwrap(),
+            Role::Standalone
+        ));
+
+        let daemon_dir = dir.path().join("daemon");
+        let cfg = DaemonCLIConfig::try_parse_from([
+            "".to_owned(),
+            "--daemon".to_owned(),
+            format!("--daemon-dir={}", daemon_dir.display()),
+        ])
+        .unwrap();
+        let Role::Proxy(socket) = cfg.role(&workspace(dir.path())).unwrap() else {
+            panic!("should be proxy")
+        };
+        assert_eq!(socket.parent().unwrap(), daemon_dir);
+        assert!(daemon_dir.is_dir());
+
+        // same config => same daemon
+        let Role::Proxy(socket2) = cfg.role(&workspace(dir.path())).unwrap() else {
+            panic!("should be proxy")
+        };
+        assert_eq!(socket, socket2);
+        let Role::Proxy(socket3) = cfg.role(&workspace(&daemon_dir)).unwrap() else {
+            panic!("should be proxy")
+        };
+        assert_ne!(socket, socket3);
+        let Role::Proxy(socket4) = cfg
+            .role(&Workspace::new([dir.path().to_owned(), daemon_dir.clone()]).unwrap())
+            .unwrap()
+        else {
+            panic!("should be proxy")
+        };
+        assert_ne!(socket, socket4);
+
+        let cfg =
+            DaemonCLIConfig::try_parse_from(["", "--daemon", "--daemon-socket=/x.sock"]).unwrap();
+        assert!(
+            matches!(cfg.role(&workspace(dir.path())).unwrap(), Role::Daemon(p) if p == Path::new("/x.sock"))
+        );
+
+        DaemonCLIConfig::try_parse_from(["", "--daemon-socket=/x.sock"]).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_bind() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("d.sock");
+
+        let listener = bind(&socket).unwrap().unwrap();
+        assert!(bind(&socket).unwrap().is_none());
+
+        // stale socket is replaced
+        drop(listener);
+        assert!(socket.exists());
+        assert!(bind(&socket).unwrap().is_some());
+    }
+}
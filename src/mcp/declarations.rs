@@ -0,0 +1,444 @@
+//! Declarations within a file, based on the flat document symbols and the source text.
+//!
+//! The document symbols only tell where a declaration is, e.g. the fields of a struct. Details like the type of a
+//! field are parsed from the source text of the declaration.
+use lsp_types::{Hover, Position, Range, SymbolInformation, SymbolKind};
+
+use super::type_at;
+
+/// Symbols directly within the parent, e.g. the fields of a struct or the items of a trait.
+///
+/// Symbols nested deeper (e.g. within a default method body) and variables are skipped.
+pub(super) fn children<'a>(
+    parent: &SymbolInformation,
+    symbols: &'a [SymbolInformation],
+) -> Vec<&'a SymbolInformation> {
+    let outer = parent.location.range;
+    let candidates = symbols
+        .iter()
+        .filter(|symbol| symbol.kind != SymbolKind::VARIABLE)
+        .filter(|symbol| {
+            let range = symbol.location.range;
+            range != outer && contains(&outer, &range)
+        })
+        .collect::<Vec<_>>();
+    candidates
+        .iter()
+        .copied()
+        .filter(|symbol| {
+            let range = symbol.location.range;
+            !candidates.iter().any(|other| {
+                let other = other.location.range;
+                other != range && contains(&other, &range)
+            })
+        })
+        .collect()
+}
+
+fn contains(outer: &Range, inner: &Range) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Source text of a range, the language servers use UTF-8 positions.
+pub(super) fn range_text(content: &str, range: Range) -> Option<&str> {
+    let offset = |position: Position| {
+        let mut offset = 0;
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            if i == position.line as usize {
+                return (position.character as usize <= line.len())
+                    .then_some(offset + position.character as usize);
+            }
+            offset += line.len();
+        }
+        (position.line as usize == content.split_inclusive('\n').count() && position.character == 0)
+            .then_some(offset)
+    };
+    content.get(offset(range.start)?..offset(range.end)?)
+}
+
+/// The line is a comment or an attribute, which precede the actual declaration.
+fn is_preamble(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("//") || line.starts_with("/*") || line.starts_with('#')
+}
+
+/// Position of the name of a symbol within its range.
+///
+/// The range usually starts with doc comments and attributes, which are skipped.
+pub(super) fn name_position(content: &str, range: Range, name: &str) -> Option<Position> {
+    content
+        .lines()
+        .enumerate()
+        .skip(range.start.line as usize)
+        .take((range.end.line - range.start.line) as usize + 1)
+        .filter(|(_, line)| !is_preamble(line))
+        .find_map(|(i, line)| {
+            let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+            line.match_indices(name)
+                .find(|(start, _)| {
+                    let end = start + name.len();
+                    !line[..*start].ends_with(is_ident) && !line[end..].starts_with(is_ident)
+                })
+                .map(|(start, _)| Position::new(i as u32, start as u32))
+        })
+}
+
+/// Field of a struct or variant of an enum, parsed from its source text.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Member {
+    /// e.g. `pub` or `pub(crate)`
+    pub(super) visibility: Option<String>,
+
+    /// type of a field, or the payload of a variant like `(u32, u32)` or `{ radius: f64 }`
+    pub(super) ty: Option<String>,
+}
+
+impl Member {
+    pub(super) fn parse(kind: SymbolKind, name: &str, text: &str) -> Self {
+        let declaration = text
+            .lines()
+            .skip_while(|line| is_preamble(line) || line.trim().is_empty())
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let declaration = declaration.trim_end_matches(',').trim();
+
+        let (visibility, declaration) = match declaration.strip_prefix("pub") {
+            Some(rest) if rest.starts_with('(') => match rest.split_once(')') {
+                Some((scope, rest)) => (Some(format!("pub{scope})")), rest.trim_start()),
+                None => (None, declaration),
+            },
+            Some(rest) if rest.starts_with(' ') => (Some("pub".to_owned()), rest.trim_start()),
+            _ => (None, declaration),
+        };
+
+        let ty = if kind == SymbolKind::ENUM_MEMBER {
+            declaration.strip_prefix(name).map(str::trim)
+        } else {
+            match declaration.strip_prefix(name) {
+                Some(rest) if rest.trim_start().starts_with(':') => {
+                    Some(rest.trim_start()[1..].trim())
+                }
+                // tuple fields are named by their index, the declaration is just the type
+                _ => name
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then_some(declaration),
+            }
+        };
+
+        Self {
+            visibility,
+            ty: ty.filter(|ty| !ty.is_empty()).map(ToOwned::to_owned),
+        }
+    }
+}
+
+/// Fields of a tuple struct, which are not reported as symbols, parsed from the declaration of the struct.
+///
+/// The fields are named by their index.
+pub(super) fn tuple_fields(name: &str, text: &str) -> Vec<(String, Member)> {
+    let declaration = text
+        .lines()
+        .filter(|line| !is_preamble(line))
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let Some(rest) = declaration
+        .split_once("struct ")
+        .and_then(|(_, rest)| rest.trim_start().strip_prefix(name))
+    else {
+        return vec![];
+    };
+    let rest = rest.trim_start();
+    // skip generics
+    let rest = match rest.strip_prefix('<') {
+        Some(generics) => match split_top_level(generics, '>').into_iter().nth(1) {
+            Some(rest) => rest.trim_start(),
+            None => return vec![],
+        },
+        None => rest,
+    };
+    let Some(rest) = rest.strip_prefix('(') else {
+        return vec![];
+    };
+    let Some(fields) = split_top_level(rest, ')').into_iter().next() else {
+        return vec![];
+    };
+
+    split_top_level(fields, ',')
+        .into_iter()
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .enumerate()
+        .map(|(i, field)| {
+            let name = i.to_string();
+            let member = Member::parse(SymbolKind::FIELD, &name, field);
+            (name, member)
+        })
+        .collect()
+}
+
+/// Split at the separator, ignoring separators within brackets.
+///
+/// Stops at an unbalanced closing bracket.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        // arrows of function pointers are no brackets
+        let arrow = c == '>' && previous == Some('-');
+        previous = Some(c);
+        match c {
+            _ if arrow => {}
+            _ if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+                if matches!(c, ')' | '>' | ']' | '}') {
+                    parts.push(&text[start..]);
+                    return parts;
+                }
+            }
+            '(' | '<' | '[' | '{' => depth += 1,
+            ')' | '>' | ']' | '}' => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => {
+                    parts.push(&text[start..i]);
+                    return parts;
+                }
+            },
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Documentation in a hover response, i.e. the text after the code blocks.
+///
+/// Sections that describe the memory layout are skipped.
+pub(super) fn docs(hover: &Hover) -> Option<String> {
+    let markdown = type_at::markdown(hover);
+    let text = markdown
+        .rsplit_once("```")
+        .map_or(markdown.as_str(), |(_, text)| text);
+    let docs = text
+        .split("\n---\n")
+        .map(str::trim)
+        .filter(|section| !section.is_empty() && !section.starts_with("size = "))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (!docs.is_empty()).then_some(docs)
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{HoverContents, Location, MarkupContent, MarkupKind, Uri};
+
+    use super::*;
+
+    const CONTENT: &str = "\
+/// A shape.
+#[derive(Debug)]
+pub enum Shape {
+    /// A circle.
+    Circle { radius: f64 },
+    Pair(u32, u32),
+    #[default]
+    Empty,
+    Code = 3,
+}
+
+pub struct Meters(pub(crate) f64, u8);
+";
+
+    fn symbol(name: &str, kind: SymbolKind, lines: (u32, u32)) -> SymbolInformation {
+        #[expect(deprecated, reason = "lsp-types still requires this field")]
+        SymbolInformation {
+            name: name.to_owned(),
+            kind,
+            tags: None,
+            deprecated: None,
+            location: Location::new(
+                "file:///lib.rs".parse::<Uri>().unwrap(),
+                Range::new(Position::new(lines.0, 0), Position::new(lines.1, 1)),
+            ),
+            container_name: None,
+        }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+    }
+
+    #[test]
+    fn test_children() {
+        let shape = symbol("Shape", SymbolKind::ENUM, (0, 9));
+        let symbols = [
+            shape.clone(),
+            symbol("Circle", SymbolKind::ENUM_MEMBER, (3, 4)),
+            symbol("radius", SymbolKind::FIELD, (4, 4)),
+            symbol("Pair", SymbolKind::ENUM_MEMBER, (5, 5)),
+            symbol("x", SymbolKind::VARIABLE, (5, 5)),
+            symbol("Meters", SymbolKind::STRUCT, (11, 11)),
+        ];
+        assert_eq!(
+            children(&shape, &symbols)
+                .into_iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>(),
+            ["Circle", "Pair"]
+        );
+    }
+
+    #[test]
+    fn test_range_text() {
+        assert_eq!(range_text(CONTENT, range((2, 9), (2, 14))), Some("Shape"));
+        assert_eq!(range_text(CONTENT, range((8, 13), (9, 1))), Some("\n}"));
+        assert_eq!(range_text(CONTENT, range((12, 0), (12, 0))), Some(""));
+        assert_eq!(range_text(CONTENT, range((2, 9), (2, 100))), None);
+        assert_eq!(range_text(CONTENT, range((20, 0), (20, 1))), None);
+        // not a char boundary
+        assert_eq!(range_text("ä", range((0, 1), (0, 2))), None);
+    }
+
+    #[test]
+    fn test_name_position() {
+        let enum_range = range((0, 0), (9, 1));
+        assert_eq!(
+            name_position(CONTENT, enum_range, "Shape"),
+            Some(Position::new(2, 9))
+        );
+        assert_eq!(
+            name_position(CONTENT, enum_range, "Pair"),
+            Some(Position::new(5, 4))
+        );
+        assert_eq!(name_position(CONTENT, enum_range, "A"), None);
+        assert_eq!(name_position(CONTENT, enum_range, "default"), None);
+        assert_eq!(name_position(CONTENT, enum_range, "Pai"), None);
+        assert_eq!(name_position(CONTENT, enum_range, "Meters"), None);
+    }
+
+    #[test]
+    fn test_member_parse() {
+        for (kind, name, text, visibility, ty) in [
+            (
+                SymbolKind::ENUM_MEMBER,
+                "Circle",
+                "    /// A circle.\n    Circle { radius: f64 },",
+                None,
+                Some("{ radius: f64 }"),
+            ),
+            (
+                SymbolKind::ENUM_MEMBER,
+                "Pair",
+                "Pair(u32, u32)",
+                None,
+                Some("(u32, u32)"),
+            ),
+            (
+                SymbolKind::ENUM_MEMBER,
+                "Empty",
+                "#[default]\n    Empty",
+                None,
+                None,
+            ),
+            (
+                SymbolKind::ENUM_MEMBER,
+                "Code",
+                "Code = 3",
+                None,
+                Some("= 3"),
+            ),
+            (
+                SymbolKind::FIELD,
+                "radius",
+                "/// Radius.\npub radius: f64",
+                Some("pub"),
+                Some("f64"),
+            ),
+            (
+                SymbolKind::FIELD,
+                "map",
+                "pub(crate) map: HashMap<String, Vec<u8>>",
+                Some("pub(crate)"),
+                Some("HashMap<String, Vec<u8>>"),
+            ),
+            (
+                SymbolKind::FIELD,
+                "0",
+                "pub(crate) f64",
+                Some("pub(crate)"),
+                Some("f64"),
+            ),
+            (SymbolKind::FIELD, "1", "u8", None, Some("u8")),
+            (SymbolKind::FIELD, "public", "public", None, None),
+        ] {
+            assert_eq!(
+                Member::parse(kind, name, text),
+                Member {
+                    visibility: visibility.map(ToOwned::to_owned),
+                    ty: ty.map(ToOwned::to_owned),
+                },
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tuple_fields() {
+        let fields = |name: &str, text: &str| {
+            tuple_fields(name, text)
+                .into_iter()
+                .map(|(name, member)| (name, member.visibility, member.ty))
+                .collect::<Vec<_>>()
+        };
+        let field = |name: &str, visibility: Option<&str>, ty: &str| {
+            (
+                name.to_owned(),
+                visibility.map(ToOwned::to_owned),
+                Some(ty.to_owned()),
+            )
+        };
+        assert_eq!(
+            fields("Meters", CONTENT.lines().nth(11).unwrap()),
+            [
+                field("0", Some("pub(crate)"), "f64"),
+                field("1", None, "u8")
+            ]
+        );
+        assert_eq!(
+            fields(
+                "Wrapper",
+                "/// Docs (with parens).\n#[derive(Debug)]\npub struct Wrapper<T: Iterator<Item = u8>>(\n    pub (T, T),\n    fn(u8) -> u8,\n)\nwhere\n    T: Clone;"
+            ),
+            [
+                field("0", Some("pub"), "(T, T)"),
+                field("1", None, "fn(u8) -> u8")
+            ]
+        );
+        assert_eq!(fields("Shape", CONTENT), []);
+        assert_eq!(fields("Unit", "struct Unit;"), []);
+    }
+
+    #[test]
+    fn test_docs() {
+        let hover = |value: &str| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: value.to_owned(),
+            }),
+            range: None,
+        };
+        assert_eq!(
+            docs(&hover(
+                "```rust\nlib::Shape\n```\n\n```rust\nCircle { radius: f64 }\n```\n\n---\n\nsize = 8, align = 0x8\n\n---\n\nA circle.\n\nRound."
+            )),
+            Some("A circle.\n\nRound.".to_owned())
+        );
+        assert_eq!(docs(&hover("```rust\nPair(u32, u32)\n```")), None);
+        assert_eq!(docs(&hover("just text")), Some("just text".to_owned()));
+    }
+}
@@ -1,9 +1,28 @@
 //! Error handling for [`rmcp`].
-use rmcp::model::ErrorData as McpError;
+use std::sync::Arc;
+
+use lsp_client::LspError;
+use lsp_types::error_codes::{
+    CONTENT_MODIFIED, REQUEST_CANCELLED, SERVER_CANCELLED, SERVER_NOT_INITIALIZED,
+};
+use rmcp::model::{ErrorCode, ErrorData as McpError};
+use serde_json::json;
+
+/// The language server discarded the request because the code changed, see [`CONTENT_MODIFIED`].
+const LSP_CONTENT_MODIFIED: ErrorCode = ErrorCode(-32010);
+
+/// The language server cancelled the request, see [`REQUEST_CANCELLED`] and [`SERVER_CANCELLED`].
+const LSP_REQUEST_CANCELLED: ErrorCode = ErrorCode(-32011);
+
+/// The language server is not initialized yet, see [`SERVER_NOT_INITIALIZED`].
+const LSP_SERVER_NOT_INITIALIZED: ErrorCode = ErrorCode(-32012);
 
 /// Convert errors into [`McpError`].
 pub(crate) trait ErrorExt {
     /// Treat errors as [internal error](McpError::internal_error).
+    ///
+    /// Errors caused by the language server get a distinct error code where possible and a `data` payload with the
+    /// LSP error code and whether a retry may succeed.
     fn internal(self) -> McpError;
 }
 
@@ -12,8 +31,39 @@ where
     E: AsRef<dyn std::error::Error>,
 {
     fn internal(self) -> McpError {
-        McpError::internal_error(format_error_chain(self.as_ref()), None)
+        let e = self.as_ref();
+        let message = format_error_chain(e);
+        let Some(lsp_code) = lsp_error_code(e) else {
+            return McpError::internal_error(message, None);
+        };
+
+        let (code, retryable) = match i64::from(lsp_code) {
+            CONTENT_MODIFIED => (LSP_CONTENT_MODIFIED, true),
+            REQUEST_CANCELLED | SERVER_CANCELLED => (LSP_REQUEST_CANCELLED, true),
+            SERVER_NOT_INITIALIZED => (LSP_SERVER_NOT_INITIALIZED, true),
+            _ => (ErrorCode::INTERNAL_ERROR, false),
+        };
+        McpError::new(
+            code,
+            message,
+            Some(json!({"lsp_code": lsp_code, "retryable": retryable})),
+        )
+    }
+}
+
+/// Code of the first language server error response in the error chain.
+fn lsp_error_code(e: &(dyn std::error::Error + 'static)) -> Option<i32> {
+    let mut maybe_e = Some(e);
+    while let Some(e) = maybe_e {
+        let lsp_error = e
+            .downcast_ref::<LspError>()
+            .or_else(|| e.downcast_ref::<Arc<LspError>>().map(AsRef::as_ref));
+        if let Some(LspError::Jsonrpsee(jsonrpsee::core::client::Error::Call(e))) = lsp_error {
+            return Some(e.code());
+        }
+        maybe_e = e.source();
     }
+    None
 }
 
 fn format_error_chain(e: &dyn std::error::Error) -> String {
@@ -79,8 +129,63 @@ impl<T> OptionExt for Option<T> {
 
 #[cfg(test)]
 mod test {
+    use anyhow::Context;
+    use jsonrpsee::{core::client::Error, types::ErrorObjectOwned};
+
     use super::*;
 
+    #[test]
+    fn test_lsp_error() {
+        let lsp_error = |code: i64| {
+            LspError::Jsonrpsee(Error::Call(ErrorObjectOwned::owned(
+                code as i32,
+                "error",
+                None::<()>,
+            )))
+        };
+        let to_mcp = |e: anyhow::Error| {
+            let e = e.internal();
+            (e.code, e.data)
+        };
+
+        assert_eq!(
+            to_mcp(anyhow::Error::from(lsp_error(CONTENT_MODIFIED)).context("request")),
+            (
+                LSP_CONTENT_MODIFIED,
+                Some(json!({"lsp_code": -32801, "retryable": true}))
+            ),
+        );
+        assert_eq!(
+            to_mcp(
+                anyhow::Error::from(Arc::new(lsp_error(SERVER_CANCELLED)))
+                    .context("inner")
+                    .context("outer")
+            ),
+            (
+                LSP_REQUEST_CANCELLED,
+                Some(json!({"lsp_code": -32802, "retryable": true}))
+            ),
+        );
+        assert_eq!(
+            to_mcp(anyhow::Error::from(lsp_error(SERVER_NOT_INITIALIZED))),
+            (
+                LSP_SERVER_NOT_INITIALIZED,
+                Some(json!({"lsp_code": -32002, "retryable": true}))
+            ),
+        );
+        assert_eq!(
+            to_mcp(anyhow::Error::from(lsp_error(-32601))),
+            (
+                ErrorCode::INTERNAL_ERROR,
+                Some(json!({"lsp_code": -32601, "retryable": false}))
+            ),
+        );
+        assert_eq!(
+            to_mcp(Err::<(), _>(std::fmt::Error).context("other").unwrap_err()),
+            (ErrorCode::INTERNAL_ERROR, None),
+        );
+    }
+
     #[test]
     fn test_format_error_chain() {
         assert_eq!(format_error_chain(&TextError::new("foo")), "foo");
@@ -0,0 +1,346 @@
+//! Export the document symbols of all workspace files, without an MCP client.
+//!
+//! The index is built from the same language servers and workspace file listing that the tools use, e.g. to build
+//! offline embeddings or code search corpora.
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use lsp_types::{
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Position, Range,
+    SymbolInformation, TextDocumentIdentifier, request::DocumentSymbolRequest,
+};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::lsp::{
+    file_watcher::list_files,
+    location::path_to_uri,
+    scheduler::Priority,
+    server::LanguageServer,
+    workspace::{SharedWorkspace, Workspace},
+};
+
+use super::{CodeExplorer, McpError, error::ResultExt, tools::ToolsCLIConfig};
+
+/// Output format of the symbol index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// A single JSON document.
+    #[default]
+    Json,
+
+    /// One JSON object per file and line.
+    Jsonl,
+}
+
+/// Export the document symbols of all workspace files as JSON and exit.
+///
+/// Waits for the language servers to be ready. Files that no language server handles are skipped.
+#[derive(Debug, Clone, Default, clap::Parser)]
+pub(crate) struct ExportSymbolsCLIConfig {
+    /// File to write the index to, defaults to stdout.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Output format.
+    #[clap(long, default_value = "json")]
+    format: ExportFormat,
+}
+
+/// Symbols of a single file.
+#[derive(Debug, serde::Serialize)]
+struct FileSymbols {
+    file: String,
+
+    /// language server that provided the symbols
+    language: String,
+
+    symbols: Vec<ExportedSymbol>,
+}
+
+/// Symbol with its nested symbols, e.g. the methods of an `impl` block.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct ExportedSymbol {
+    name: String,
+    kind: String,
+
+    /// 1-based first line
+    line: u32,
+
+    /// 1-based last line
+    end_line: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<Self>,
+}
+
+impl ExportedSymbol {
+    fn new(name: String, kind: String, range: Range, detail: Option<String>) -> Self {
+        Self {
+            name,
+            kind,
+            line: range.start.line + 1,
+            end_line: range.end.line + 1,
+            detail,
+            children: vec![],
+        }
+    }
+}
+
+impl From<DocumentSymbol> for ExportedSymbol {
+    fn from(symbol: DocumentSymbol) -> Self {
+        Self {
+            children: symbol
+                .children
+                .unwrap_or_default()
+                .into_iter()
+                .map(Self::from)
+                .collect(),
+            ..Self::new(
+                symbol.name,
+                format!("{:?}", symbol.kind),
+                symbol.range,
+                symbol.detail,
+            )
+        }
+    }
+}
+
+impl ExportSymbolsCLIConfig {
+    /// Export symbols of all workspace files.
+    pub(crate) async fn run(
+        &self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<()> {
+        let explorer = CodeExplorer::new(servers, workspace, tools_cfg);
+        let workspace = explorer.workspace();
+        let files = tokio::task::spawn_blocking(move || workspace_files(&workspace))
+            .await
+            .context("join listing")??;
+
+        let start = Instant::now();
+        let mut exported = vec![];
+        for file in &files {
+            if ct.is_cancelled() {
+                break;
+            }
+            match explorer.export_file(file).await {
+                Ok(Some(symbols)) => exported.push(symbols),
+                Ok(None) => {}
+                Err(e) => warn!(file, %e, "cannot export symbols"),
+            }
+        }
+        info!(
+            files = exported.len(),
+            elapsed_ms = start.elapsed().as_millis(),
+            "exported symbols",
+        );
+
+        match &self.output {
+            Some(path) => {
+                let file = tokio::fs::File::create(path)
+                    .await
+                    .with_context(|| format!("create output file: {}", path.display()))?;
+                self.write(&exported, file).await
+            }
+            None => self.write(&exported, tokio::io::stdout()).await,
+        }
+    }
+
+    async fn write<W>(&self, exported: &[FileSymbols], out: W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut out = BufWriter::new(out);
+        match self.format {
+            ExportFormat::Json => {
+                let mut s = serde_json::to_string_pretty(&serde_json::json!({ "files": exported }))
+                    .context("serialize index")?;
+                s.push('\n');
+                out.write_all(s.as_bytes()).await.context("write index")?;
+            }
+            ExportFormat::Jsonl => {
+                for file in exported {
+                    let mut s = serde_json::to_string(file).context("serialize index")?;
+                    s.push('\n');
+                    out.write_all(s.as_bytes()).await.context("write index")?;
+                }
+            }
+        }
+        out.flush().await.context("flush index")?;
+        Ok(())
+    }
+}
+
+impl CodeExplorer {
+    /// Symbols of a single file, `None` if no language server handles it.
+    async fn export_file(&self, file: &str) -> Result<Option<FileSymbols>, McpError> {
+        let Ok(server) = self.server_for_file(file) else {
+            return Ok(None);
+        };
+        let Some(content) = self.read_file(file).await? else {
+            // removed in the meantime
+            return Ok(None);
+        };
+
+        let client = server.progress_guard().wait().await;
+        let _permit = server.scheduler().acquire(Priority::Low).await;
+        let uri = path_to_uri(&self.workspace(), file)
+            .context("convert path to URI")
+            .internal()?;
+        server
+            .documents()
+            .open(&uri, &content)
+            .await
+            .context("open document")
+            .internal()?;
+        let resp = client
+            .send_request_partial::<DocumentSymbolRequest>(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier { uri },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .context("DocumentSymbolRequest")
+            .internal()?;
+
+        let symbols = match resp {
+            None => vec![],
+            Some(DocumentSymbolResponse::Flat(symbols)) => nest(symbols),
+            Some(DocumentSymbolResponse::Nested(symbols)) => {
+                symbols.into_iter().map(ExportedSymbol::from).collect()
+            }
+        };
+        Ok(Some(FileSymbols {
+            file: file.to_owned(),
+            language: server.name().to_owned(),
+            symbols,
+        }))
+    }
+}
+
+/// Files of all workspace folders, sorted.
+///
+/// This blocks, see [`list_files`].
+pub(super) fn workspace_files(workspace: &Workspace) -> Result<Vec<String>> {
+    let mut files = vec![];
+    for folder in workspace.folders() {
+        files.extend(
+            list_files(folder)?
+                .iter()
+                .filter(|path| Path::is_file(path))
+                .filter_map(|path| workspace.relativize(path)),
+        );
+    }
+    files.sort_unstable();
+    files.dedup();
+    Ok(files)
+}
+
+/// Nest flat symbols by their ranges, i.e. a symbol becomes a child of the innermost symbol that contains it.
+fn nest(mut symbols: Vec<SymbolInformation>) -> Vec<ExportedSymbol> {
+    fn key(pos: Position) -> (u32, u32) {
+        (pos.line, pos.character)
+    }
+
+    // outer symbols first
+    symbols.sort_by_key(|symbol| {
+        let range = symbol.location.range;
+        (key(range.start), std::cmp::Reverse(key(range.end)))
+    });
+
+    let mut roots = vec![];
+    // open symbols with their end, innermost last
+    let mut stack: Vec<((u32, u32), ExportedSymbol)> = vec![];
+    let close = |stack: &mut Vec<((u32, u32), ExportedSymbol)>, roots: &mut Vec<ExportedSymbol>| {
+        let (_end, symbol) = stack.pop().expect("not empty");
+        match stack.last_mut() {
+            Some((_end, parent)) => parent.children.push(symbol),
+            None => roots.push(symbol),
+        }
+    };
+    for symbol in symbols {
+        let range = symbol.location.range;
+        while stack
+            .last()
+            .is_some_and(|(end, _symbol)| *end < key(range.end))
+        {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((
+            key(range.end),
+            ExportedSymbol::new(symbol.name, format!("{:?}", symbol.kind), range, None),
+        ));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{Location, SymbolKind, Uri};
+
+    use super::*;
+
+    #[expect(deprecated, reason = "lsp-types still requires this field")]
+    fn symbol(name: &str, kind: SymbolKind, start: u32, end: u32) -> SymbolInformation {
+        SymbolInformation {
+            name: name.to_owned(),
+            kind,
+            tags: None,
+            deprecated: None,
+            location: Location {
+                uri: "file:///lib.rs".parse::<Uri>().unwrap(),
+                range: Range {
+                    start: Position::new(start, 0),
+                    end: Position::new(end, 1),
+                },
+            },
+            container_name: None,
+        }
+    }
+
+    fn names(symbols: &[ExportedSymbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|symbol| {
+                if symbol.children.is_empty() {
+                    symbol.name.clone()
+                } else {
+                    format!("{}({})", symbol.name, names(&symbol.children).join(", "))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nest() {
+        let symbols = nest(vec![
+            symbol("new", SymbolKind::METHOD, 6, 8),
+            symbol("Foo", SymbolKind::STRUCT, 0, 3),
+            symbol("impl Foo", SymbolKind::OBJECT, 5, 13),
+            symbol("x", SymbolKind::FIELD, 1, 1),
+            symbol("free", SymbolKind::FUNCTION, 15, 17),
+            symbol("get", SymbolKind::METHOD, 10, 12),
+        ]);
+        assert_eq!(names(&symbols), ["Foo(x)", "impl Foo(new, get)", "free"]);
+
+        let new = &symbols[1].children[0];
+        assert_eq!((new.line, new.end_line), (7, 9));
+        assert_eq!(new.kind, "Method");
+    }
+}
@@ -0,0 +1,55 @@
+//! MCP service for when the language servers cannot be started.
+//!
+//! MCP clients often hide the stderr output of the server, so the error is reported by every tool call instead of
+//! just exiting.
+use rmcp::{
+    RoleServer, ServerHandler,
+    model::{
+        CallToolRequestParams, CallToolResult, ErrorData as McpError, ListToolsResult,
+        PaginatedRequestParams, ServerInfo,
+    },
+    service::RequestContext,
+};
+use tracing::info;
+
+use super::{CodeExplorer, restrict_tool_formats, server_info};
+
+/// Lists the tools of the [`CodeExplorer`] but fails all tool calls with the same error.
+#[derive(Debug, Clone)]
+pub(crate) struct FailedExplorer {
+    msg: String,
+}
+
+impl FailedExplorer {
+    pub(crate) fn new(msg: String) -> Self {
+        Self { msg }
+    }
+}
+
+impl ServerHandler for FailedExplorer {
+    fn get_info(&self) -> ServerInfo {
+        server_info(&CodeExplorer::all_tools_router())
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        info!(
+            name = request.name.as_ref(),
+            "call tool, but language servers failed"
+        );
+        Err(McpError::internal_error(self.msg.clone(), None))
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(restrict_tool_formats(
+            CodeExplorer::all_tools_router().list_all(),
+        )))
+    }
+}
@@ -0,0 +1,157 @@
+//! Overview of a file: the number of tokens per type and the declared identifiers.
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    location::{McpLocation, path_to_uri},
+    scheduler::Priority,
+};
+
+use super::{
+    CodeExplorer,
+    error::{OptionExt, ResultExt},
+    hints,
+    timeout::TimedOut,
+};
+
+#[tool_router(router = file_overview_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "File Overview",
+        description = "Get a cheap structural overview of a file: the number of tokens per type (e.g. functions, structs, variables) and the identifiers declared in it. Use the `symbol_info` tool afterwards to learn more about interesting symbols.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn file_overview(
+        &self,
+        Parameters(FileOverviewRequest { file, timeout_secs }): Parameters<FileOverviewRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<FileOverviewResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(FileOverviewResult::timed_out()));
+        };
+        let Some(token_legend) = server.token_legend() else {
+            return Err(McpError::invalid_params(
+                "language server does not provide semantic tokens",
+                None,
+            ));
+        };
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        server
+            .documents()
+            .open(
+                &path_to_uri(&self.workspace(), &file)
+                    .context("convert path to URI")
+                    .internal()?,
+                &file_content,
+            )
+            .await
+            .context("open document")
+            .internal()?;
+        let Some(data) = deadline
+            .run(self.semantic_tokens(server, &client, &file, &file_content))
+            .await
+        else {
+            return Ok(Json(FileOverviewResult::timed_out()));
+        };
+        let data =
+            data?.expected("language server did not provide any semantic tokens".to_owned())?;
+        let doc = token_legend
+            .decode(&file_content, &data)
+            .context("decode semantic tokens")
+            .internal()?;
+
+        let token_counts = doc
+            .token_type_counts()
+            .into_iter()
+            .map(|(token_type, count)| (token_type.to_owned(), count))
+            .collect();
+
+        let mut declarations = BTreeMap::<_, Vec<_>>::new();
+        for token in doc.declarations() {
+            declarations
+                .entry((token.data().to_owned(), token.token_type().to_string()))
+                .or_default()
+                .push(token.mcp_location(file.clone(), self.workspace()));
+        }
+        let declarations = declarations
+            .into_iter()
+            .map(|((name, token_type), locations)| DeclarationInfo {
+                name,
+                token_type,
+                locations,
+            })
+            .collect();
+
+        Ok(Json(FileOverviewResult {
+            token_counts,
+            declarations,
+            timed_out: false,
+            stale: doc.is_stale(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct FileOverviewRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct FileOverviewResult {
+    /// number of tokens per token type
+    token_counts: BTreeMap<String, usize>,
+
+    /// declared identifiers, sorted by name
+    declarations: Vec<DeclarationInfo>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// the file changed while it was analyzed, results may be incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stale: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for FileOverviewResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct DeclarationInfo {
+    name: String,
+    token_type: String,
+
+    /// all declarations of this name and type, e.g. shadowed variables
+    locations: Vec<McpLocation>,
+}
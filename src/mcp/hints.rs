@@ -0,0 +1,190 @@
+//! Actionable errors for common mistakes in tool calls.
+//!
+//! LLM clients tend to retry calls that fail with a generic error over and over. These errors explain what is wrong
+//! and how to fix the call instead.
+use std::{fmt::Display, path::Path};
+
+use itertools::Itertools;
+use rmcp::model::ErrorData as McpError;
+
+use crate::lsp::{
+    dependency::is_virtual,
+    server::LanguageServer,
+    workspace::{Workspace, normalize},
+};
+
+/// Error for a file of a tool call that does not exist.
+pub(super) async fn file_not_found(workspace: &Workspace, file: &str) -> McpError {
+    if is_virtual(file) {
+        return hinted(
+            format!("file not found: {file}"),
+            "virtual paths of dependencies are only known after a tool returned them, use `find_symbol` with `workspace_and_dependencies` to look them up",
+        );
+    }
+
+    if !workspace.contains(&normalize(&workspace.resolve(file))) {
+        // clients often put a slash in front of relative paths
+        let relative = file.trim_start_matches('/');
+        if Path::new(file).is_absolute()
+            && !relative.is_empty()
+            && tokio::fs::try_exists(workspace.resolve(relative))
+                .await
+                .unwrap_or_default()
+        {
+            return hinted(
+                format!("absolute path given where a relative one is expected: {file}"),
+                format!("paths are relative to the workspace root, use `{relative}`"),
+            );
+        }
+
+        let root = workspace.root().display();
+        let hint = if workspace.is_jailed() {
+            format!("use a path relative to the workspace root `{root}`")
+        } else {
+            format!(
+                "use a path relative to the workspace root `{root}` or add the folder with the `add_workspace_folder` tool"
+            )
+        };
+        return hinted(format!("file is outside of the workspace: {file}"), hint);
+    }
+
+    let prefixes = workspace.prefixes().collect::<Vec<_>>();
+    let hint = if prefixes.is_empty() {
+        "paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined".to_owned()
+    } else {
+        format!(
+            "paths start with the name of the workspace folder, one of: {}",
+            prefixes.iter().join(", ")
+        )
+    };
+    hinted(format!("file not found: {file}"), hint)
+}
+
+/// Error for a position that is beyond the end of the file.
+///
+/// This usually happens if the file was edited after the client learned about the position.
+pub(super) fn position_drifted(file: &str, line: u32, lines: usize) -> McpError {
+    hinted(
+        format!("line {line} is beyond the end of {file}, which has {lines} lines"),
+        "the file probably changed since the position was looked up, pass `name` or look up the position again with `file_overview` or `find_symbol`",
+    )
+}
+
+/// Error for a file that changed during every attempt to fetch its symbols.
+pub(super) fn file_keeps_changing(file: &str, attempts: usize) -> McpError {
+    hinted(
+        format!("{file} changed during each of {attempts} attempts to fetch its symbols"),
+        "retry the call once the file is no longer written to, e.g. after a formatter or code generator finished",
+    )
+}
+
+/// Error for a call graph that does not start at a function.
+pub(super) fn not_callable(symbol: &str) -> McpError {
+    hinted(
+        format!("no function or method found: {symbol}"),
+        "call graphs start at a function or method, use `file_overview` to look up the functions declared in the file",
+    )
+}
+
+/// Error for a trait detail request that does not point at a trait.
+pub(super) fn not_a_trait(symbol: &str) -> McpError {
+    hinted(
+        format!("no trait found: {symbol}"),
+        "the trait must be declared in the given file, use `find_symbol` to look up where it is declared",
+    )
+}
+
+/// Error for a type members request that does not point at a struct or enum.
+pub(super) fn not_a_struct_or_enum(symbol: &str) -> McpError {
+    hinted(
+        format!("no struct or enum found: {symbol}"),
+        "the type must be declared in the given file, use `find_symbol` to look up where it is declared",
+    )
+}
+
+/// Error for a position without a typed expression.
+pub(super) fn no_type(position: &str) -> McpError {
+    hinted(
+        format!("no type found at {position}"),
+        "point at a variable or expression, or pass `end_line` and `end_character` to select an entire expression",
+    )
+}
+
+/// Error for a test that the language server does not know.
+pub(super) fn test_not_found(symbol: &str, tests: &[&str]) -> McpError {
+    let hint = if tests.is_empty() {
+        "the language server knows no tests in the file, tests in other files need the path of that file".to_owned()
+    } else {
+        format!("tests in the file are: {}", tests.iter().join(", "))
+    };
+    hinted(format!("no test found: {symbol}"), hint)
+}
+
+/// Error for a tool call without results while the language server may still be indexing.
+///
+/// Returns `None` if the language server finished its initial work.
+pub(super) fn not_indexed(server: &LanguageServer) -> Option<McpError> {
+    let progress_guard = server.progress_guard();
+    let missing = progress_guard
+        .init_timed_out()
+        .filter(|missing| !missing.is_empty());
+    if progress_guard.is_ready() && missing.is_none() {
+        return None;
+    }
+
+    let mut message = format!("workspace is not indexed yet by {}", server.name());
+    if let Some(missing) = missing {
+        message.push_str(&format!(", missing: {}", missing.join(", ")));
+    }
+    Some(hinted(
+        message,
+        "retry the call in a few seconds, `server_status` shows the indexing progress",
+    ))
+}
+
+/// Invalid parameters, with a hint on how to fix them.
+///
+/// The hint is part of the message, because many clients do not show the error data.
+fn hinted(message: impl Display, hint: impl Display) -> McpError {
+    McpError::invalid_params(format!("{message}\n- {hint}"), None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src").join("lib.rs"), "").unwrap();
+        let workspace = Workspace::new([dir.path().to_owned()]).unwrap();
+        let root = dir.path().display();
+
+        let message = async |file: &str| file_not_found(&workspace, file).await.message;
+        assert_eq!(
+            message("/src/lib.rs").await,
+            "absolute path given where a relative one is expected: /src/lib.rs\n- paths are relative to the workspace root, use `src/lib.rs`",
+        );
+        assert_eq!(
+            message("../other/lib.rs").await,
+            format!(
+                "file is outside of the workspace: ../other/lib.rs\n- use a path relative to the workspace root `{root}` or add the folder with the `add_workspace_folder` tool"
+            ),
+        );
+        assert_eq!(
+            message("/does/not/exist.rs").await,
+            format!(
+                "file is outside of the workspace: /does/not/exist.rs\n- use a path relative to the workspace root `{root}` or add the folder with the `add_workspace_folder` tool"
+            ),
+        );
+        assert_eq!(
+            message("src/main.rs").await,
+            "file not found: src/main.rs\n- paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined",
+        );
+        assert_eq!(
+            message("dep://serde@1.0.0/src/lib.rs").await,
+            "file not found: dep://serde@1.0.0/src/lib.rs\n- virtual paths of dependencies are only known after a tool returned them, use `find_symbol` with `workspace_and_dependencies` to look them up",
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! MCP via streamable HTTP, allowing multiple clients to share the language servers.
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::{Context, Result, ensure};
+use bytes::Bytes;
+use clap::Parser;
+use futures::{FutureExt, future::BoxFuture};
+use http::{HeaderMap, Request, Response, StatusCode, header};
+use http_body_util::{BodyExt, Empty, combinators::BoxBody};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+};
+use sha2::{Digest, Sha256};
+use tokio::{net::TcpListener, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+use tower_service::Service;
+use tracing::{debug, info, warn};
+
+use crate::lsp::{server::LanguageServer, workspace::SharedWorkspace};
+
+use super::{CodeExplorer, tools::ToolsCLIConfig};
+
+/// CLI config for serving MCP via HTTP.
+#[derive(Debug, Default, Parser)]
+pub(crate) struct HttpCLIConfig {
+    /// Serve MCP via streamable HTTP on the given address (e.g. `127.0.0.1:8080`) instead of stdio.
+    ///
+    /// Multiple clients can connect at the same time. They get their own MCP sessions but share the language servers,
+    /// so the workspace is only indexed once. IO interception only covers the language servers in this mode.
+    #[clap(long, env = "COMMON_SENSE_CODER_LISTEN")]
+    listen: Option<SocketAddr>,
+
+    /// Accepted `Host` header of HTTP requests, protects against DNS rebinding attacks.
+    ///
+    /// Can be repeated or comma-separated. Defaults to loopback hosts, i.e. `localhost`, `127.0.0.1` and `::1`.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LISTEN_ALLOWED_HOSTS",
+        value_delimiter = ','
+    )]
+    listen_allowed_host: Vec<String>,
+
+    /// Require HTTP clients to send this token via `Authorization: Bearer <token>`.
+    ///
+    /// Without a token, every client that can reach the address may read the code base. Hence a token is required
+    /// for non-loopback addresses, unless `--listen-without-auth` is passed.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_LISTEN_AUTH_TOKEN",
+        hide_env_values = true
+    )]
+    listen_auth_token: Option<String>,
+
+    /// Serve non-loopback addresses without `--listen-auth-token`, e.g. behind an authenticating reverse proxy.
+    #[clap(long, env = "COMMON_SENSE_CODER_LISTEN_WITHOUT_AUTH")]
+    listen_without_auth: bool,
+}
+
+impl HttpCLIConfig {
+    /// Address to listen on, if HTTP is used.
+    pub(crate) fn listen(&self) -> Option<SocketAddr> {
+        self.listen
+    }
+
+    /// Refuse to expose the code base without auth token, see [`listen_auth_token`](Self::listen_auth_token).
+    pub(crate) fn check_auth(&self) -> Result<()> {
+        if let Some(addr) = self.listen {
+            ensure!(
+                addr.ip().is_loopback()
+                    || self.listen_auth_token.is_some()
+                    || self.listen_without_auth,
+                "`--listen={addr}` requires `--listen-auth-token`, otherwise every client that can reach the address \
+                 may read the code base; pass `--listen-without-auth` to serve it anyways",
+            );
+        }
+        Ok(())
+    }
+
+    /// Serve MCP until cancelled.
+    pub(crate) async fn serve(
+        &self,
+        addr: SocketAddr,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<()> {
+        let mut config =
+            StreamableHttpServerConfig::default().with_cancellation_token(ct.child_token());
+        if !self.listen_allowed_host.is_empty() {
+            config = config.with_allowed_hosts(self.listen_allowed_host.iter().cloned());
+        }
+        let service = RequireToken::new(
+            StreamableHttpService::new(
+                move || {
+                    info!("new MCP session");
+                    Ok(CodeExplorer::new(
+                        Arc::clone(&servers),
+                        workspace.clone(),
+                        tools_cfg.clone(),
+                    ))
+                },
+                Arc::new(LocalSessionManager::default()),
+                config,
+            ),
+            self.listen_auth_token.as_deref(),
+        );
+        if self.listen_auth_token.is_none() {
+            warn!("no auth token configured, every client that can reach the address is accepted");
+        }
+
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("bind to {addr}"))?;
+        info!(
+            addr = %listener.local_addr().context("get local address")?,
+            "listen for MCP clients"
+        );
+
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                _ = ct.cancelled() => {
+                    break;
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                res = listener.accept() => {
+                    let (stream, peer) = match res {
+                        Ok(res) => res,
+                        Err(e) => {
+                            warn!(%e, "cannot accept connection");
+                            continue;
+                        }
+                    };
+                    debug!(%peer, "accept connection");
+
+                    let service = TowerToHyperService::new(service.clone());
+                    let ct = ct.clone();
+                    connections.spawn(async move {
+                        let builder = auto::Builder::new(TokioExecutor::new());
+                        let conn = builder.serve_connection(TokioIo::new(stream), service);
+                        let mut conn = std::pin::pin!(conn);
+                        let res = tokio::select! {
+                            res = conn.as_mut() => res,
+                            _ = ct.cancelled() => {
+                                conn.as_mut().graceful_shutdown();
+                                conn.await
+                            }
+                        };
+                        match res {
+                            Ok(()) => debug!(%peer, "connection closed"),
+                            Err(e) => warn!(%peer, %e, "connection failed"),
+                        }
+                    });
+                }
+            }
+        }
+
+        while connections.join_next().await.is_some() {}
+
+        Ok(())
+    }
+}
+
+/// Rejects HTTP requests that do not carry the expected bearer token.
+#[derive(Clone)]
+struct RequireToken<S> {
+    inner: S,
+
+    /// SHA-256 of the expected token, `None` if no token is required.
+    ///
+    /// Comparing hashes does not leak the token via timing.
+    token_hash: Option<Arc<[u8]>>,
+}
+
+impl<S> std::fmt::Debug for RequireToken<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequireToken")
+            .field("required", &self.token_hash.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> RequireToken<S> {
+    fn new(inner: S, token: Option<&str>) -> Self {
+        Self {
+            inner,
+            token_hash: token.map(|token| Sha256::digest(token).to_vec().into()),
+        }
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.token_hash else {
+            return true;
+        };
+        let Some(token) = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .and_then(|(scheme, token)| scheme.eq_ignore_ascii_case("bearer").then_some(token))
+        else {
+            return false;
+        };
+        Sha256::digest(token.trim()).as_slice() == expected.as_ref()
+    }
+}
+
+impl<S, B> Service<Request<B>> for RequireToken<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody<Bytes, Infallible>>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if !self.authorized(req.headers()) {
+            debug!(uri = %req.uri(), "reject request without valid auth token");
+            let resp = Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, "Bearer")
+                .body(Empty::new().boxed())
+                .expect("valid response");
+            return futures::future::ready(Ok(resp)).boxed();
+        }
+        self.inner.call(req).boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        let headers = |value: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(value) = value {
+                headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            }
+            headers
+        };
+
+        let open = RequireToken::new((), None);
+        assert!(open.authorized(&headers(None)));
+        assert!(open.authorized(&headers(Some("Bearer foo"))));
+
+        let secured = RequireToken::new((), Some("secret"));
+        assert!(secured.authorized(&headers(Some("Bearer secret"))));
+        assert!(secured.authorized(&headers(Some("bearer secret"))));
+        assert!(!secured.authorized(&headers(None)));
+        assert!(!secured.authorized(&headers(Some("Bearer wrong"))));
+        assert!(!secured.authorized(&headers(Some("Basic secret"))));
+        assert!(!secured.authorized(&headers(Some("secret"))));
+    }
+}
@@ -0,0 +1,165 @@
+//! Forward language server messages to the MCP client via logging notifications.
+use std::sync::OnceLock;
+
+use futures::StreamExt;
+use lsp_types::MessageType;
+use rmcp::{
+    Peer, RoleServer,
+    model::{LoggingLevel, LoggingMessageNotificationParam},
+};
+use serde_json::Value;
+use tokio::sync::watch;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::debug;
+
+use crate::lsp::{messages::ServerMessage, server::LanguageServer};
+
+/// Level used until the client sets one via `logging/setLevel`.
+const DEFAULT_LEVEL: LoggingLevel = LoggingLevel::Warning;
+
+/// Forwards messages of the language servers to a single MCP session.
+#[derive(Debug)]
+pub(super) struct LogForwarder {
+    /// Minimum level of forwarded messages.
+    level: watch::Sender<LoggingLevel>,
+
+    /// Forwarding task, started when the client is initialized and stopped when the session ends.
+    task: OnceLock<DropGuard>,
+}
+
+impl Default for LogForwarder {
+    fn default() -> Self {
+        Self {
+            level: watch::Sender::new(DEFAULT_LEVEL),
+            task: OnceLock::new(),
+        }
+    }
+}
+
+impl LogForwarder {
+    /// Start forwarding messages.
+    pub(super) fn start(&self, servers: &[LanguageServer], peer: &Peer<RoleServer>) {
+        self.task.get_or_init(|| {
+            let ct = CancellationToken::new();
+            let mut stream = futures::stream::select_all(servers.iter().map(|server| {
+                let name = server.name().to_owned();
+                BroadcastStream::new(server.messages().subscribe())
+                    .map(move |res| (name.clone(), res))
+            }));
+            let level = self.level.subscribe();
+            let peer = peer.clone();
+            let ct_captured = ct.clone();
+
+            tokio::spawn(async move {
+                let ct = ct_captured;
+                loop {
+                    let (name, res) = tokio::select! {
+                        _ = ct.cancelled() => return,
+                        next = stream.next() => match next {
+                            Some(next) => next,
+                            None => return,
+                        },
+                    };
+                    let msg = match res {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            debug!(%e, "skip language server messages");
+                            continue;
+                        }
+                    };
+                    let Some(param) = notification(name, msg, *level.borrow()) else {
+                        continue;
+                    };
+                    #[expect(
+                        deprecated,
+                        reason = "MCP clients still use logging, there is no replacement yet"
+                    )]
+                    let res = peer.notify_logging_message(param).await;
+                    if let Err(e) = res {
+                        debug!(%e, "cannot notify client, stop forwarding messages");
+                        return;
+                    }
+                }
+            });
+
+            ct.drop_guard()
+        });
+    }
+
+    /// Set minimum level of forwarded messages.
+    pub(super) fn set_level(&self, level: LoggingLevel) {
+        debug!(?level, "set logging level");
+        self.level.send_replace(level);
+    }
+}
+
+/// Create notification for language server message, if it passes the level.
+fn notification(
+    name: String,
+    msg: ServerMessage,
+    min_level: LoggingLevel,
+) -> Option<LoggingMessageNotificationParam> {
+    let ServerMessage { typ, message } = msg;
+    let level = match typ {
+        MessageType::ERROR => LoggingLevel::Error,
+        MessageType::WARNING => LoggingLevel::Warning,
+        MessageType::INFO => LoggingLevel::Info,
+        _ => LoggingLevel::Debug,
+    };
+
+    // levels are declared in order of severity
+    ((level as u8) >= (min_level as u8)).then(|| {
+        LoggingMessageNotificationParam::new(level, Value::String(message)).with_logger(name)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_notification() {
+        let msg = |typ| ServerMessage {
+            typ,
+            message: "foo".to_owned(),
+        };
+
+        let param = notification(
+            "rust".to_owned(),
+            msg(MessageType::WARNING),
+            LoggingLevel::Info,
+        )
+        .unwrap();
+        assert_eq!(param.level, LoggingLevel::Warning);
+        assert_eq!(param.logger.as_deref(), Some("rust"));
+        assert_eq!(param.data, Value::String("foo".to_owned()));
+
+        assert!(
+            notification(
+                "rust".to_owned(),
+                msg(MessageType::ERROR),
+                LoggingLevel::Error
+            )
+            .is_some()
+        );
+        assert!(
+            notification(
+                "rust".to_owned(),
+                msg(MessageType::INFO),
+                LoggingLevel::Warning
+            )
+            .is_none()
+        );
+        assert_eq!(
+            notification(
+                "rust".to_owned(),
+                msg(MessageType::LOG),
+                LoggingLevel::Debug
+            )
+            .unwrap()
+            .level,
+            LoggingLevel::Debug,
+        );
+    }
+}
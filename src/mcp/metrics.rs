@@ -0,0 +1,152 @@
+//! Invocation counts and latencies of the MCP tools.
+//!
+//! Helps operators to find out which tools are slow or failing in their agent workflows.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rmcp::schemars;
+
+/// Number of most recent calls per tool that the percentiles are computed from.
+const MAX_SAMPLES: usize = 1024;
+
+/// Statistics of a single tool.
+#[derive(Debug, Default)]
+struct ToolRecord {
+    count: u64,
+    errors: u64,
+    samples: VecDeque<Duration>,
+}
+
+/// Call statistics of all tools since startup, shared by all clones.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ToolMetrics {
+    tools: Arc<Mutex<BTreeMap<String, ToolRecord>>>,
+}
+
+impl ToolMetrics {
+    /// Record finished tool call.
+    pub(super) fn record(&self, tool: &str, duration: Duration, success: bool) {
+        let mut tools = self.tools.lock().expect("not poisoned");
+        let record = tools.entry(tool.to_owned()).or_default();
+        record.count += 1;
+        if !success {
+            record.errors += 1;
+        }
+        if record.samples.len() == MAX_SAMPLES {
+            record.samples.pop_front();
+        }
+        record.samples.push_back(duration);
+    }
+
+    /// Current statistics, sorted by tool.
+    pub(super) fn snapshot(&self) -> Vec<ToolStats> {
+        self.tools
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|(tool, record)| {
+                let mut samples = record.samples.iter().copied().collect::<Vec<_>>();
+                samples.sort_unstable();
+                ToolStats {
+                    tool: tool.clone(),
+                    count: record.count,
+                    errors: record.errors,
+                    p50_ms: percentile(&samples, 50).as_secs_f64() * 1000.0,
+                    p95_ms: percentile(&samples, 95).as_secs_f64() * 1000.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted samples, zero if there are none.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    match sorted.len() {
+        0 => Duration::ZERO,
+        n => sorted[(n * p).div_ceil(100).clamp(1, n) - 1],
+    }
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct ToolStats {
+    /// name of the tool, e.g. `find_symbol`
+    pub(super) tool: String,
+
+    /// number of calls, including failed ones
+    pub(super) count: u64,
+
+    /// number of calls that returned an error
+    pub(super) errors: u64,
+
+    /// median call duration in milliseconds, over the last 1024 calls
+    pub(super) p50_ms: f64,
+
+    /// 95th percentile of the call duration in milliseconds, over the last 1024 calls
+    pub(super) p95_ms: f64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let metrics = ToolMetrics::default();
+        assert!(metrics.snapshot().is_empty());
+
+        for ms in 1..=20 {
+            metrics.record("b", Duration::from_millis(ms), ms != 7);
+        }
+        metrics.record("a", Duration::from_millis(5), true);
+
+        assert_eq!(
+            metrics.snapshot(),
+            [
+                ToolStats {
+                    tool: "a".to_owned(),
+                    count: 1,
+                    errors: 0,
+                    p50_ms: 5.0,
+                    p95_ms: 5.0,
+                },
+                ToolStats {
+                    tool: "b".to_owned(),
+                    count: 20,
+                    errors: 1,
+                    p50_ms: 10.0,
+                    p95_ms: 19.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_samples() {
+        let metrics = ToolMetrics::default();
+        for _ in 0..MAX_SAMPLES {
+            metrics.record("a", Duration::from_millis(100), true);
+        }
+        metrics.record("a", Duration::from_millis(1), true);
+
+        let stats = &metrics.snapshot()[0];
+        assert_eq!(stats.count, MAX_SAMPLES as u64 + 1);
+        assert_eq!(stats.p50_ms, 100.0);
+        assert_eq!(
+            metrics.tools.lock().unwrap()["a"].samples.len(),
+            MAX_SAMPLES
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+        let sorted = [1, 2, 3, 4].map(Duration::from_secs);
+        assert_eq!(percentile(&sorted, 0), Duration::from_secs(1));
+        assert_eq!(percentile(&sorted, 50), Duration::from_secs(2));
+        assert_eq!(percentile(&sorted, 95), Duration::from_secs(4));
+        assert_eq!(percentile(&sorted, 100), Duration::from_secs(4));
+    }
+}
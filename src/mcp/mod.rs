@@ -1,119 +1,805 @@
-use std::{io::ErrorKind, ops::Deref, path::Path, sync::Arc};
+use std::{collections::BTreeMap, io::ErrorKind, ops::Deref, path::Path, sync::Arc, time::Instant};
 
 use anyhow::Context;
+use changes::{ChangedSymbol, FileChange};
+use completion::Completer;
 use error::{OptionExt, ResultExt};
-use lsp_client::LspClient;
+use itertools::Itertools;
+use logging::LogForwarder;
 use lsp_types::{
-    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, HoverContents, HoverParams,
-    LanguageString, Location, MarkedString, Range, ReferenceContext, ReferenceParams,
-    SemanticTokensParams, SymbolInformation, SymbolKind, SymbolTag, TextDocumentIdentifier,
-    TextDocumentPositionParams, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    DidChangeWorkspaceFoldersParams, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, Hover, HoverContents, HoverParams, LanguageString, Location,
+    MarkedString, OneOf, Position, Range, ReferenceContext, ReferenceParams, SemanticToken,
+    SymbolInformation, SymbolKind, SymbolTag, TextDocumentIdentifier, TextDocumentPositionParams,
+    Uri, WorkspaceFoldersChangeEvent, WorkspaceLocation, WorkspaceSymbol, WorkspaceSymbolParams,
+    WorkspaceSymbolResponse,
+    notification::DidChangeWorkspaceFolders,
     request::{
         DocumentSymbolRequest, GotoDeclaration, GotoDeclarationParams, GotoDefinition,
         GotoImplementation, GotoImplementationParams, GotoTypeDefinition, GotoTypeDefinitionParams,
-        HoverRequest, References, SemanticTokensFullRequest,
+        HoverRequest, References, WorkspaceSymbolResolve,
     },
 };
+use metrics::ToolStats;
+use progress::ProgressReporter;
+use resources::Subscriptions;
 use rmcp::{
-    Json, RoleServer, ServerHandler,
+    Json, Peer, RoleServer, ServerHandler,
     handler::server::{
         tool::{ToolCallContext, ToolRouter},
         wrapper::Parameters,
     },
     model::{
-        CallToolRequestParams, CallToolResult, ErrorData as McpError, Implementation,
-        ListToolsResult, PaginatedRequestParams, ProgressNotificationParam, ServerCapabilities,
-        ServerInfo,
+        CallToolRequestParams, CallToolResult, CompleteRequestParams, CompleteResult,
+        ErrorData as McpError, Implementation, ListResourceTemplatesResult, ListToolsResult,
+        PaginatedRequestParams, ReadResourceRequestParams, ReadResourceResult, ServerCapabilities,
+        ServerInfo, SetLevelRequestParams, SubscribeRequestParams, Tool, UnsubscribeRequestParams,
     },
     schemars::{
         self, Schema,
         transform::{RestrictFormats, Transform},
     },
-    service::RequestContext,
+    service::{NotificationContext, RequestContext},
     tool, tool_router,
 };
 use search::SearchMode;
+use secrets::SecretsCLIConfig;
+use statistics::{Counts, PackageCounts, Statistics};
+use timeout::{Deadline, TimedOut};
 use tokio_stream::StreamExt;
-use tracing::{debug, info};
+use tokio_util::task::AbortOnDropHandle;
+use tools::ToolsCLIConfig;
+use tracing::{Instrument, debug, info, info_span};
 
 use crate::{
-    ProgressGuard,
+    config_file::EffectiveConfig,
     constants::{NAME, VERSION_STRING},
+    lang::DependencySymbol,
     lsp::{
+        cache::{CacheBudgetStats, CacheStats},
+        dedup::DedupClient,
+        document_cache::DocumentState,
+        documents::Fingerprint,
         location::{LocationVariants, McpLocation, path_to_text_document_identifier, path_to_uri},
         progress_guard::Guard,
         requests::{
-            WorkspaceSymbolParamsExt, WorkspaceSymbolRequestExt, WorkspaceSymbolScopeKindFiltering,
-            WorkspaceSymbolSearchKind, WorkspaceSymbolSearchScope,
+            RawRequest, RawRequestParams, WorkspaceSymbolParamsExt, WorkspaceSymbolRequestExt,
+            WorkspaceSymbolScopeKindFiltering, WorkspaceSymbolSearchKind,
+            WorkspaceSymbolSearchScope,
         },
+        scheduler::{Permit, Priority},
+        server::LanguageServer,
+        symbol_cache::Revision,
         tokens::{Token, TokenLegend},
+        uri::path_to_file_uri,
+        version::VersionStatus,
+        workspace::{SharedWorkspace, Workspace},
     },
+    tasks::TaskStatus,
 };
 
+mod budget;
+pub(crate) mod call;
+mod call_graph;
+mod changes;
+mod check;
+mod completion;
+#[cfg(unix)]
+pub(crate) mod daemon;
+mod declarations;
 mod error;
+pub(crate) mod export;
+pub(crate) mod failed;
+mod file_overview;
+mod hints;
+pub(crate) mod http;
+mod logging;
+mod metrics;
+mod parent_module;
+mod policy;
+mod progress;
+mod proximity;
+mod resources;
+pub(crate) mod roots;
+mod run_test;
+pub(crate) mod sarif;
 mod search;
+mod secrets;
+mod statistics;
+mod syntax_tree;
+mod timeout;
+pub(crate) mod tools;
+mod traits;
+mod type_at;
+mod type_members;
+pub(crate) mod warmup;
+
+/// How often a file is fetched if it keeps changing during a tool call.
+const MAX_FETCH_ATTEMPTS: usize = 3;
+
+/// Number of messages that `debug_tail` returns by default.
+const DEFAULT_DEBUG_TAIL_LIMIT: usize = 20;
+
+/// Number of `workspaceSymbol/resolve` requests that are in flight at the same time.
+const MAX_CONCURRENT_RESOLVES: usize = 16;
+
+/// Ready language server client that the current tool call may use.
+#[derive(Debug)]
+struct ScheduledClient<'a> {
+    guard: Guard<'a>,
+    _permit: Permit,
+
+    /// Sends partial results, `None` if the MCP client did not ask for progress notifications.
+    reporter: Option<ProgressReporter>,
+
+    /// Redacts secrets in partial results.
+    secrets_cfg: SecretsCLIConfig,
+}
+
+impl ScheduledClient<'_> {
+    /// Send a section of the result for the given token before the tool call is done.
+    async fn partial_result(
+        &self,
+        token: &TokenInfo,
+        section: &str,
+        value: &(impl serde::Serialize + Sync),
+    ) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+        let mut message = serde_json::json!({
+            "token": token,
+            "section": section,
+            "value": value,
+        });
+        self.secrets_cfg.redact_value(&mut message);
+        reporter.partial_result(message.to_string()).await;
+    }
+}
+
+impl Deref for ScheduledClient<'_> {
+    type Target = DedupClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
 
 #[derive(Debug)]
 pub(crate) struct CodeExplorer {
-    progress_guard: ProgressGuard,
-    token_legend: TokenLegend,
-    workspace: Arc<Path>,
+    servers: Arc<[LanguageServer]>,
+    workspace: SharedWorkspace,
+    tools_cfg: ToolsCLIConfig,
     tool_router: ToolRouter<Self>,
+    subscriptions: Subscriptions,
+    log_forwarder: LogForwarder,
+    completer: Completer,
 }
 
 impl CodeExplorer {
     pub(crate) fn new(
-        progress_guard: ProgressGuard,
-        token_legend: TokenLegend,
-        workspace: Arc<Path>,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
     ) -> Self {
+        assert!(!servers.is_empty(), "need at least one language server");
+
+        let tool_router = Self::configured_tool_router(
+            &tools_cfg,
+            servers.iter().any(|server| server.traffic_tail().is_some()),
+        );
+
         Self {
-            progress_guard,
-            token_legend,
+            servers,
             workspace,
-            tool_router: Self::tool_router(),
+            tools_cfg,
+            tool_router,
+            subscriptions: Subscriptions::default(),
+            log_forwarder: LogForwarder::default(),
+            completer: Completer::default(),
         }
     }
 
-    async fn wait_for_client(&self, ctx: RequestContext<RoleServer>) -> Guard<'_> {
-        let fut_progress = async {
-            if let Some(progress_token) = ctx.meta.get_progress_token() {
-                let mut stream_evt = self.progress_guard.events();
-                let mut progress = 0u32;
-
-                while let Some(evt) = stream_evt.next().await {
-                    ctx.peer
-                        .notify_progress(ProgressNotificationParam {
-                            progress_token: progress_token.clone(),
-                            progress: progress as f64,
-                            total: None,
-                            message: Some(evt),
-                        })
-                        .await
-                        .ok();
-                    progress += 1;
+    /// Router for the tools that are enabled by the config, `debug_tail` tells if the language server traffic is kept.
+    pub(crate) fn configured_tool_router(
+        tools_cfg: &ToolsCLIConfig,
+        debug_tail: bool,
+    ) -> ToolRouter<Self> {
+        let mut tool_router = Self::all_tools_router();
+        if !tools_cfg.enable_lsp_request_tool || tools_cfg.workspace_jail().is_some() {
+            tool_router.remove_route("lsp_request");
+        }
+        if !tools_cfg.enable_run_check_tool || tools_cfg.workspace_jail().is_some() {
+            // build scripts and procedural macros run arbitrary code, which no jail can contain
+            tool_router.remove_route("run_check");
+        }
+        if !tools_cfg.enable_run_test_tool || tools_cfg.workspace_jail().is_some() {
+            // tests run arbitrary code, which no jail can contain
+            tool_router.remove_route("run_test");
+        }
+        if !tools_cfg.enable_syntax_tree_tools {
+            tool_router.remove_route("view_item_tree");
+            tool_router.remove_route("view_syntax_tree");
+        }
+        if tools_cfg.workspace_jail().is_some() {
+            // the model must not widen the jail
+            tool_router.remove_route("add_workspace_folder");
+        }
+        if !debug_tail {
+            tool_router.remove_route("debug_tail");
+        }
+        if tools_cfg.config_reporter.is_none() {
+            tool_router.remove_route("get_config");
+        }
+        if tools_cfg.read_only {
+            for tool in tool_router.list_all() {
+                if policy::modifies_workspace(&tool) {
+                    tool_router.disable_route(tool.name);
                 }
             }
+        }
+        tool_router
+    }
 
-            futures::future::pending::<()>().await
-        };
+    /// Router for all tools, including the ones that are defined in submodules.
+    pub(crate) fn all_tools_router() -> ToolRouter<Self> {
+        Self::tool_router()
+            + Self::file_overview_router()
+            + Self::call_graph_router()
+            + Self::traits_router()
+            + Self::type_members_router()
+            + Self::type_at_router()
+            + Self::parent_module_router()
+            + Self::syntax_tree_router()
+            + Self::check_router()
+            + Self::run_test_router()
+    }
+
+    /// Start sending notifications to the client of this session.
+    pub(crate) fn attach(&self, peer: &Peer<RoleServer>) {
+        self.log_forwarder.start(&self.servers, peer);
+    }
+
+    /// Current workspace.
+    fn workspace(&self) -> Workspace {
+        self.workspace
+            .get()
+            .with_path_style(self.tools_cfg.path_style())
+            .with_jail(self.tools_cfg.workspace_jail())
+    }
+
+    /// Workspace root for tool results, if requested.
+    fn workspace_root(&self) -> Option<String> {
+        self.tools_cfg
+            .include_workspace_root()
+            .then(|| self.workspace().root().display().to_string())
+    }
+
+    /// Pick language server for the given file.
+    fn server_for_file(&self, file: &str) -> Result<&LanguageServer, McpError> {
+        self.servers
+            .iter()
+            .find(|server| server.handles_file(file))
+            .ok_or_else(|| {
+                let extension = Path::new(file)
+                    .extension()
+                    .map(|ext| format!("`.{}` files", ext.display()))
+                    .unwrap_or_else(|| "files without extension".to_owned());
+                let supported = self
+                    .servers
+                    .iter()
+                    .map(|server| {
+                        format!(
+                            "{} ({})",
+                            server.name(),
+                            server
+                                .file_extensions()
+                                .iter()
+                                .sorted()
+                                .map(|ext| format!(".{ext}"))
+                                .join(", ")
+                        )
+                    })
+                    .join(", ");
+                McpError::invalid_params(
+                    format!(
+                        "no language server handles {extension} like {file}, supported are: {supported}"
+                    ),
+                    None,
+                )
+            })
+    }
+
+    async fn wait_for_client<'a>(
+        &self,
+        server: &'a LanguageServer,
+        priority: Priority,
+        ctx: RequestContext<RoleServer>,
+    ) -> ScheduledClient<'a> {
+        self.wait_for_clients(&[server], priority, ctx)
+            .await
+            .pop()
+            .expect("one server requested")
+    }
 
-        let fut_wait = async { self.progress_guard.wait().await };
+    async fn wait_for_clients<'a>(
+        &self,
+        servers: &[&'a LanguageServer],
+        priority: Priority,
+        ctx: RequestContext<RoleServer>,
+    ) -> Vec<ScheduledClient<'a>> {
+        // forward progress until the language servers are ready, partial results are sent by the clients
+        let reporter = ProgressReporter::new(&ctx);
+        let progress = reporter.clone().map(|reporter| {
+            let mut stream_evt =
+                futures::stream::select_all(servers.iter().enumerate().map(|(idx, server)| {
+                    let name = server.name().to_owned();
+                    let prefix = servers.len() > 1;
+                    Box::pin(server.progress_guard().events().map(move |mut evt| {
+                        if prefix {
+                            evt.message = format!("{name}: {}", evt.message);
+                        }
+                        (idx, evt)
+                    }))
+                }));
+
+            AbortOnDropHandle::new(tokio::spawn(async move {
+                loop {
+                    let due = async {
+                        match reporter.due().await {
+                            Some(due) => tokio::time::sleep_until(due).await,
+                            None => std::future::pending().await,
+                        }
+                    };
+                    tokio::select! {
+                        evt = stream_evt.next() => {
+                            let Some((idx, evt)) = evt else {
+                                break;
+                            };
+                            reporter.event(idx, evt).await;
+                        }
+                        _ = due => reporter.flush().await,
+                    }
+                }
+            }))
+        });
 
-        tokio::select! {
-            _ = fut_progress => unreachable!(),
-            guard = fut_wait => guard,
+        // wait for readiness first, so waiting for indexing does not block other tool calls
+        let guards =
+            futures::future::join_all(servers.iter().map(|server| server.progress_guard().wait()))
+                .await;
+        drop(progress);
+        if let Some(reporter) = &reporter {
+            // the last state before readiness, which the throttling may have held back
+            reporter.flush().await;
         }
+
+        futures::future::join_all(servers.iter().zip(guards).map(async |(server, guard)| {
+            let permit = server.scheduler().acquire(priority).await;
+            ScheduledClient {
+                guard,
+                _permit: permit,
+                reporter: reporter.clone(),
+                secrets_cfg: self.tools_cfg.secrets_cfg().clone(),
+            }
+        }))
+        .await
     }
 
     async fn read_file(&self, file: &str) -> Result<Option<String>, McpError> {
-        match tokio::fs::read_to_string(self.workspace.join(file)).await {
+        let workspace = self.workspace();
+        let path = workspace.resolve(file);
+        if !workspace.allows(&path) {
+            // same as a missing file, so the jail does not reveal which files exist
+            return Ok(None);
+        }
+        match tokio::fs::read_to_string(path).await {
             Ok(s) => Ok(Some(s)),
             Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
             Err(e) => Err(e).context("read file").internal(),
         }
     }
 
+    /// Symbols defined within a file, including variable declarations.
+    ///
+    /// Re-fetched if the file changes in the meantime, so positions do not refer to stale content. Fails if the file
+    /// keeps changing.
+    async fn document_symbols(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        file: &str,
+    ) -> Result<Vec<SymbolInformation>, McpError> {
+        for attempt in 1..=MAX_FETCH_ATTEMPTS {
+            let (symbol_informations, fingerprint) =
+                self.document_symbols_once(server, client, file).await?;
+            if !self.file_changed(file, fingerprint).await? {
+                return Ok(symbol_informations);
+            }
+            debug!(
+                file,
+                attempt, "file changed while fetching symbols, re-fetch"
+            );
+        }
+        Err(hints::file_keeps_changing(file, MAX_FETCH_ATTEMPTS))
+    }
+
+    async fn document_symbols_once(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        file: &str,
+    ) -> Result<(Vec<SymbolInformation>, Fingerprint), McpError> {
+        // LSP may error for non-existing files, so try to read it first
+        let Some(file_content) = self.read_file(file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), file).await);
+        };
+
+        let uri = path_to_uri(&self.workspace(), file)
+            .context("convert path to URI")
+            .internal()?;
+        let fingerprint = Fingerprint::of(&file_content);
+        let state = self.document_state(server, &file_content);
+        if let Some(symbol_informations) = server.document_cache().symbols(&uri, state) {
+            debug!(file, "document symbols from cache");
+            return Ok((symbol_informations, fingerprint));
+        }
+        server
+            .documents()
+            .open(&uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        let resp = client
+            .send_request_partial::<DocumentSymbolRequest>(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .context("DocumentSymbolRequest")
+            .internal()?;
+
+        let mut symbol_informations = match resp {
+            None => {
+                // no symbols
+                vec![]
+            }
+            Some(DocumentSymbolResponse::Flat(symbol_informations)) => symbol_informations,
+            Some(DocumentSymbolResponse::Nested(_)) => {
+                return Err(McpError::internal_error(
+                    "nested symbols are not yet implemented",
+                    None,
+                ));
+            }
+        };
+
+        // variable declarations are not part of the symbol index, hence we need to fetch them manually
+        let Some(token_legend) = server.token_legend() else {
+            server
+                .document_cache()
+                .store_symbols(&uri, state, &symbol_informations);
+            return Ok((symbol_informations, fingerprint));
+        };
+        let data = self
+            .semantic_tokens(server, client, file, &file_content)
+            .await?;
+        if let Some(data) = data {
+            let doc = token_legend
+                .decode(&file_content, &data)
+                .context("decode semantic tokens")
+                .internal()?;
+
+            for token in doc.declared_variables() {
+                let location = Location {
+                    uri: path_to_uri(&self.workspace(), file)
+                        .context("convert path to URI")
+                        .internal()?,
+                    range: Range {
+                        // in the then we just care about the position, so set both values to it
+                        start: token.lsp_position(),
+                        end: token.lsp_position(),
+                    },
+                };
+
+                #[expect(deprecated, reason = "lsp-types still requires this field")]
+                let symbol_information = SymbolInformation {
+                    name: token.data().to_owned(),
+                    kind: SymbolKind::VARIABLE,
+                    tags: token.is_deprecated().then_some(vec![SymbolTag::DEPRECATED]),
+                    deprecated: None,
+                    location,
+                    container_name: None,
+                };
+                symbol_informations.push(symbol_information);
+            }
+        }
+
+        server
+            .document_cache()
+            .store_symbols(&uri, state, &symbol_informations);
+        Ok((symbol_informations, fingerprint))
+    }
+
+    /// Symbols that changed in a single file, see [`changes`].
+    async fn file_changes(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        folder: &Path,
+        base: &str,
+        head: Option<&str>,
+        diff: &changes::FileDiff,
+    ) -> Result<Vec<ChangedSymbol>, McpError> {
+        if diff.old_lines.is_empty() && diff.new_lines.is_empty() {
+            // e.g. pure renames
+            return Ok(vec![]);
+        }
+
+        let old = match &diff.old_path {
+            Some(path) => {
+                let content = changes::show(folder, base, path).await.internal()?;
+                let uri = changes::revision_uri(folder, base, path).internal()?;
+                self.content_symbols(server, client, &uri, &content).await?
+            }
+            None => vec![],
+        };
+        let new = match (&diff.new_path, head) {
+            (Some(path), Some(head)) => {
+                let content = changes::show(folder, head, path).await.internal()?;
+                let uri = changes::revision_uri(folder, head, path).internal()?;
+                self.content_symbols(server, client, &uri, &content).await?
+            }
+            (Some(path), None) => {
+                let path = folder.join(path);
+                if !self.workspace().allows(&path) {
+                    return Ok(vec![]);
+                }
+                let content = tokio::fs::read_to_string(&path)
+                    .await
+                    .context("read file")
+                    .internal()?;
+                let uri = path_to_file_uri(&path)
+                    .context("convert path to URI")
+                    .internal()?;
+                self.content_symbols(server, client, &uri, &content).await?
+            }
+            (None, _) => vec![],
+        };
+
+        Ok(changes::changed_symbols(
+            &old,
+            &diff.old_lines,
+            &new,
+            &diff.new_lines,
+        ))
+    }
+
+    /// Document symbols of the given content, without variable declarations.
+    async fn content_symbols(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        uri: &Uri,
+        content: &str,
+    ) -> Result<Vec<SymbolInformation>, McpError> {
+        server
+            .documents()
+            .open(uri, content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        let resp = client
+            .send_request_partial::<DocumentSymbolRequest>(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .context("DocumentSymbolRequest")
+            .internal()?;
+        match resp {
+            None => Ok(vec![]),
+            Some(DocumentSymbolResponse::Flat(symbol_informations)) => Ok(symbol_informations),
+            Some(DocumentSymbolResponse::Nested(_)) => Err(McpError::internal_error(
+                "nested symbols are not yet implemented",
+                None,
+            )),
+        }
+    }
+
+    /// Semantic tokens of the entire file, `None` if the language server did not provide any.
+    async fn semantic_tokens(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        file: &str,
+        file_content: &str,
+    ) -> Result<Option<Arc<[SemanticToken]>>, McpError> {
+        let text_document = path_to_text_document_identifier(&self.workspace(), file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        let uri = text_document.uri.clone();
+        let state = self.document_state(server, file_content);
+        let cache = server.document_cache();
+        if let Some(data) = cache.semantic_tokens(&uri, state) {
+            debug!(file, "semantic tokens from cache");
+            return Ok(Some(data));
+        }
+
+        let data = server
+            .semantic_tokens()
+            .fetch(client, text_document)
+            .await
+            .context("SemanticTokensFullRequest")
+            .internal()?;
+        if let Some(data) = &data {
+            cache.store_semantic_tokens(&uri, state, data);
+        }
+        Ok(data)
+    }
+
+    /// Hover at the position, `None` if the deadline passed or the language server has no information.
+    async fn hover(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        text_document_position_params: &TextDocumentPositionParams,
+        state: DocumentState,
+        deadline: &Deadline,
+    ) -> Result<Option<Hover>, McpError> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = text_document_position_params;
+        let cache = server.hover_cache();
+        if let Some(hover) = cache.get(&text_document.uri, *position, state) {
+            return Ok(Some(hover));
+        }
+
+        let hover = deadline
+            .run(client.send_request::<HoverRequest>(HoverParams {
+                text_document_position_params: text_document_position_params.clone(),
+                work_done_progress_params: Default::default(),
+            }))
+            .await
+            .transpose()
+            .context("HoverRequest")
+            .internal()?
+            .flatten();
+        if let Some(hover) = &hover {
+            cache.insert(&text_document.uri, *position, state, hover.clone());
+        }
+        Ok(hover)
+    }
+
+    /// State of the file and the workspace that the results of per-document requests depend on.
+    fn document_state(&self, server: &LanguageServer, file_content: &str) -> DocumentState {
+        DocumentState {
+            fingerprint: Fingerprint::of(file_content),
+            revision: self.revision(server),
+        }
+    }
+
+    /// State of the workspace that the results of the language server depend on.
+    fn revision(&self, server: &LanguageServer) -> Revision {
+        Revision {
+            files: self.workspace.file_revision(),
+            work: server.progress_guard().work_revision(),
+        }
+    }
+
+    /// Checks if the file content differs from the given fingerprint, e.g. because it was edited or deleted.
+    async fn file_changed(&self, file: &str, fingerprint: Fingerprint) -> Result<bool, McpError> {
+        Ok(self
+            .read_file(file)
+            .await?
+            .is_none_or(|content| Fingerprint::of(&content) != fingerprint))
+    }
+
+    /// Search symbols in the entire workspace.
+    async fn workspace_symbols(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        query: &str,
+        workspace_and_dependencies: bool,
+    ) -> Result<Vec<SymbolInformation>, McpError> {
+        let cache = server.symbol_cache();
+        let revision = self.revision(server);
+        if let Some(symbols) = cache.get(query, workspace_and_dependencies, revision) {
+            debug!(query, "workspace symbols from cache");
+            return Ok(symbols);
+        }
+
+        let symbols = self
+            .workspace_symbols_uncached(server, client, query, workspace_and_dependencies)
+            .await?;
+        // results are incomplete while the language server is indexing
+        if server.progress_guard().is_ready() {
+            cache.insert(query, workspace_and_dependencies, revision, symbols.clone());
+        }
+        Ok(symbols)
+    }
+
+    async fn workspace_symbols_uncached(
+        &self,
+        server: &LanguageServer,
+        client: &DedupClient,
+        query: &str,
+        workspace_and_dependencies: bool,
+    ) -> Result<Vec<SymbolInformation>, McpError> {
+        let resp = client
+            .send_request_partial::<WorkspaceSymbolRequestExt>(WorkspaceSymbolParamsExt {
+                base: WorkspaceSymbolParams {
+                    query: query.to_owned(),
+                    ..Default::default()
+                },
+                filtering: WorkspaceSymbolScopeKindFiltering {
+                    search_scope: Some(if workspace_and_dependencies {
+                        WorkspaceSymbolSearchScope::WorkspaceAndDependencies
+                    } else {
+                        WorkspaceSymbolSearchScope::Workspace
+                    }),
+                    search_kind: Some(if workspace_and_dependencies {
+                        // `WorkspaceSymbolSearchScope::WorkspaceAndDependencies` + `WorkspaceSymbolSearchKind::AllSymbols`
+                        // SHOULD work with `AllSymbols` but seems to produce empty results. Maybe it's a bug
+                        // in rust-analyzer or just not implemented. There are a some issues related to symbol
+                        // filtering:
+                        //
+                        // - https://github.com/rust-lang/rust-analyzer/issues/13938
+                        // - https://github.com/rust-lang/rust-analyzer/issues/16491
+                        WorkspaceSymbolSearchKind::OnlyTypes
+                    } else {
+                        WorkspaceSymbolSearchKind::AllSymbols
+                    }),
+                },
+            })
+            .await
+            .context("WorkspaceSymbolRequest")
+            .internal()?;
+
+        match resp {
+            None => {
+                // no symbols
+                Ok(vec![])
+            }
+            Some(WorkspaceSymbolResponse::Flat(symbol_informations)) => Ok(symbol_informations),
+            Some(WorkspaceSymbolResponse::Nested(symbols)) => {
+                let symbols = symbols
+                    .into_iter()
+                    // only matches are shown, so there is no need to resolve the others
+                    .filter(|symbol| SearchMode::Fuzzy.check(query, &symbol.name))
+                    .map(async |symbol| {
+                        // servers may defer the range to `workspaceSymbol/resolve`
+                        if !matches!(symbol.location, OneOf::Right(_))
+                            || !server.workspace_symbol_resolve()
+                        {
+                            return workspace_symbol_to_information(symbol);
+                        }
+                        match client
+                            .send_request::<WorkspaceSymbolResolve>(symbol.clone())
+                            .await
+                        {
+                            Ok(symbol) => workspace_symbol_to_information(symbol),
+                            Err(e) => {
+                                // the start of the file is still a useful location
+                                debug!(%e, symbol = symbol.name.as_str(), "cannot resolve workspace symbol");
+                                workspace_symbol_to_information(symbol)
+                            }
+                        }
+                    });
+                let symbols = futures::StreamExt::buffer_unordered(
+                    futures::stream::iter(symbols),
+                    MAX_CONCURRENT_RESOLVES,
+                );
+                Ok(futures::StreamExt::collect(symbols).await)
+            }
+        }
+    }
+
     fn filter_symbol_informations(
         &self,
         symbol_informations: &[SymbolInformation],
@@ -147,7 +833,7 @@ impl CodeExplorer {
 
                 let location = match McpLocation::try_new(
                     location.clone(),
-                    Arc::clone(&self.workspace),
+                    self.workspace(),
                     workspace_and_dependencies,
                 )
                 .context("create MCP location")
@@ -170,36 +856,168 @@ impl CodeExplorer {
             .collect::<Result<Vec<_>, _>>()?;
 
         results.sort_unstable();
+        // language servers of different languages may report the same symbol, e.g. if one of them scans all files
+        results.dedup();
 
         Ok(results)
     }
 
+    /// Symbol info based on the current file content.
+    ///
+    /// Returns `None` if the deadline passed before the semantic tokens were fetched. The flag is set if the semantic
+    /// tokens did not match the file content.
+    #[expect(clippy::too_many_arguments, reason = "arguments of the tool call")]
+    async fn symbol_info_once(
+        &self,
+        server: &LanguageServer,
+        client: &ScheduledClient<'_>,
+        file: &str,
+        name: Option<&str>,
+        fuzzy: bool,
+        token_type: Option<&str>,
+        line: Option<u32>,
+        character: Option<u32>,
+        workspace_and_dependencies: bool,
+        deadline: &Deadline,
+    ) -> Result<Option<(Vec<SymbolInfo>, bool, Fingerprint)>, McpError> {
+        let file_content = match self.read_file(file).await? {
+            Some(s) => s,
+            None => {
+                return Err(hints::file_not_found(&self.workspace(), file).await);
+            }
+        };
+        if let Some(line) = line {
+            let lines = file_content.lines().count();
+            if line as usize > lines {
+                return Err(hints::position_drifted(file, line, lines));
+            }
+        }
+        server
+            .documents()
+            .open(
+                &path_to_uri(&self.workspace(), file)
+                    .context("convert path to URI")
+                    .internal()?,
+                &file_content,
+            )
+            .await
+            .context("open document")
+            .internal()?;
+        let text_legend;
+        let mut token_type = token_type;
+        let doc = match server.token_legend() {
+            Some(token_legend) => {
+                let Some(data) = deadline
+                    .run(self.semantic_tokens(server, client, file, &file_content))
+                    .await
+                else {
+                    return Ok(None);
+                };
+                let data = data?
+                    .expected("language server did not provide any semantic tokens".to_owned())?;
+                token_legend
+                    .decode(&file_content, &data)
+                    .context("decode semantic tokens")
+                    .internal()?
+            }
+            None => {
+                let Some(name) = name else {
+                    return Err(McpError::invalid_params(
+                        "language server does not provide semantic tokens, `name` is required",
+                        None,
+                    ));
+                };
+
+                // hover and goto requests still work, only the token type and modifiers are unknown
+                token_type = None;
+                text_legend = TokenLegend::text();
+                text_legend.scan(&file_content, name)
+            }
+        };
+        let tokens = match (name, line) {
+            (Some(name), line) => {
+                let mut tokens = doc.query(name, token_type, line, character);
+                if tokens.is_empty() && fuzzy {
+                    debug!(name, "no exact token match, search fuzzy");
+                    tokens = doc.query_by(
+                        |data| SearchMode::Fuzzy.check(name, data),
+                        token_type,
+                        line,
+                        character,
+                    );
+                }
+                tokens
+            }
+            (None, Some(line)) => doc.at(token_type, line, character).into_iter().collect(),
+            (None, None) => {
+                return Err(McpError::invalid_params(
+                    "either `name` or `line` is required",
+                    None,
+                ));
+            }
+        };
+        let state = self.document_state(server, &file_content);
+        let mut results = vec![];
+        for token in tokens {
+            let Some(res) = self
+                .symbol_info_for_token(
+                    token,
+                    file,
+                    server,
+                    client,
+                    state,
+                    workspace_and_dependencies,
+                    deadline,
+                )
+                .await?
+            else {
+                continue;
+            };
+            results.push(res);
+        }
+
+        Ok(Some((
+            results,
+            doc.is_stale(),
+            Fingerprint::of(&file_content),
+        )))
+    }
+
+    #[expect(clippy::too_many_arguments, reason = "context of the token")]
     async fn symbol_info_for_token(
         &self,
         token: &Token<'_>,
         path: &str,
-        client: &LspClient,
+        server: &LanguageServer,
+        client: &ScheduledClient<'_>,
+        state: DocumentState,
         workspace_and_dependencies: bool,
+        deadline: &Deadline,
     ) -> Result<Option<SymbolInfo>, McpError> {
-        let location = token.mcp_location(path.to_owned(), Arc::clone(&self.workspace));
-
-        let modifiers = token
-            .token_modifiers()
-            .iter()
-            .map(|m| m.to_string())
-            .collect::<Vec<_>>();
+        let token_info = TokenInfo {
+            name: token.data().to_owned(),
+            location: token.mcp_location(path.to_owned(), self.workspace()),
+            token_type: token.token_type().to_string(),
+            modifiers: token
+                .token_modifiers()
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+        };
 
-        let text_document_position_params = TextDocumentPositionParams::try_from(&location)
-            .context("create text document position params")
-            .internal()?;
-        let Some(resp) = client
-            .send_request::<HoverRequest>(HoverParams {
-                text_document_position_params: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-            })
-            .await
-            .context("HoverRequest")
-            .internal()?
+        let text_document_position_params =
+            TextDocumentPositionParams::try_from(&token_info.location)
+                .context("create text document position params")
+                .internal()?;
+        let Some(resp) = self
+            .hover(
+                server,
+                client,
+                &text_document_position_params,
+                state,
+                deadline,
+            )
+            .await?
         else {
             return Ok(None);
         };
@@ -218,111 +1036,189 @@ impl CodeExplorer {
                 })
             }
         };
+        client.partial_result(&token_info, "hover", &hover).await;
+
+        // the requests are independent, stream each section as soon as it arrives instead of waiting for the slowest
+        let declarations = async {
+            let declarations = match deadline
+                .run(
+                    client.send_request::<GotoDeclaration>(GotoDeclarationParams {
+                        text_document_position_params: text_document_position_params.clone(),
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    }),
+                )
+                .await
+                .transpose()
+                .context("GotoDeclaration")
+                .internal()?
+                .flatten()
+            {
+                Some(resp) => LocationVariants::from(resp)
+                    .into_mcp_location(&self.workspace(), workspace_and_dependencies)
+                    .context("convert declaration locations")
+                    .internal()?,
+                None => vec![],
+            };
+            client
+                .partial_result(&token_info, "declarations", &declarations)
+                .await;
+            Ok::<_, McpError>(declarations)
+        };
 
-        let declarations = match client
-            .send_request::<GotoDeclaration>(GotoDeclarationParams {
-                text_document_position_params: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("GotoDeclaration")
-            .internal()?
-        {
-            Some(resp) => LocationVariants::from(resp)
-                .into_mcp_location(Arc::clone(&self.workspace), workspace_and_dependencies)
-                .context("convert declaration locations")
-                .internal()?,
-            None => vec![],
+        let definitions = async {
+            let definitions = match deadline
+                .run(client.send_request::<GotoDefinition>(GotoDefinitionParams {
+                    text_document_position_params: text_document_position_params.clone(),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }))
+                .await
+                .transpose()
+                .context("GotoDefinition")
+                .internal()?
+                .flatten()
+            {
+                Some(resp) => LocationVariants::from(resp)
+                    .into_mcp_location(&self.workspace(), workspace_and_dependencies)
+                    .context("convert definition locations")
+                    .internal()?,
+                None => vec![],
+            };
+            client
+                .partial_result(&token_info, "definitions", &definitions)
+                .await;
+            Ok::<_, McpError>(definitions)
         };
 
-        let definitions = match client
-            .send_request::<GotoDefinition>(GotoDefinitionParams {
-                text_document_position_params: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("GotoDefinition")
-            .internal()?
-        {
-            Some(resp) => LocationVariants::from(resp)
-                .into_mcp_location(Arc::clone(&self.workspace), workspace_and_dependencies)
-                .context("convert definition locations")
-                .internal()?,
-            None => vec![],
+        let implementations = async {
+            let implementations = match deadline
+                .run(
+                    client.send_request::<GotoImplementation>(GotoImplementationParams {
+                        text_document_position_params: text_document_position_params.clone(),
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    }),
+                )
+                .await
+                .transpose()
+                .context("GotoImplementation")
+                .internal()?
+                .flatten()
+            {
+                Some(resp) => LocationVariants::from(resp)
+                    .into_mcp_location(&self.workspace(), workspace_and_dependencies)
+                    .context("convert implementation locations")
+                    .internal()?,
+                None => vec![],
+            };
+            client
+                .partial_result(&token_info, "implementations", &implementations)
+                .await;
+            Ok::<_, McpError>(implementations)
         };
 
-        let implementations = match client
-            .send_request::<GotoImplementation>(GotoImplementationParams {
-                text_document_position_params: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("GotoImplementation")
-            .internal()?
-        {
-            Some(resp) => LocationVariants::from(resp)
-                .into_mcp_location(Arc::clone(&self.workspace), workspace_and_dependencies)
-                .context("convert implementation locations")
-                .internal()?,
-            None => vec![],
+        let type_definitions = async {
+            let type_definitions = match deadline
+                .run(
+                    client.send_request::<GotoTypeDefinition>(GotoTypeDefinitionParams {
+                        text_document_position_params: text_document_position_params.clone(),
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    }),
+                )
+                .await
+                .transpose()
+                .context("GotoTypeDefinition")
+                .internal()?
+                .flatten()
+            {
+                Some(resp) => LocationVariants::from(resp)
+                    .into_mcp_location(&self.workspace(), workspace_and_dependencies)
+                    .context("convert type definition locations")
+                    .internal()?,
+                None => vec![],
+            };
+            client
+                .partial_result(&token_info, "type_definitions", &type_definitions)
+                .await;
+            Ok::<_, McpError>(type_definitions)
         };
 
-        let type_definitions = match client
-            .send_request::<GotoTypeDefinition>(GotoTypeDefinitionParams {
-                text_document_position_params: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("GotoTypeDefinition")
-            .internal()?
-        {
-            Some(resp) => LocationVariants::from(resp)
-                .into_mcp_location(Arc::clone(&self.workspace), workspace_and_dependencies)
-                .context("convert type definition locations")
-                .internal()?,
-            None => vec![],
+        let references = async {
+            let references = match deadline
+                .run(client.send_request_partial::<References>(ReferenceParams {
+                    text_document_position: text_document_position_params.clone(),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                    context: ReferenceContext {
+                        include_declaration: false,
+                    },
+                }))
+                .await
+                .transpose()
+                .context("References")
+                .internal()?
+                .flatten()
+            {
+                Some(locations) => locations
+                    .into_iter()
+                    .map(|loc| {
+                        McpLocation::try_new(loc, self.workspace(), workspace_and_dependencies)
+                    })
+                    .filter_map(Result::transpose)
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("format references")
+                    .internal()?,
+                None => vec![],
+            };
+            let references = proximity::sort(&token_info.location.file, references);
+            client
+                .partial_result(&token_info, "references", &references)
+                .await;
+            Ok::<_, McpError>(references)
         };
 
-        let references = match client
-            .send_request::<References>(ReferenceParams {
-                text_document_position: text_document_position_params.clone(),
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-                context: ReferenceContext {
-                    include_declaration: false,
-                },
-            })
-            .await
-            .context("References")
-            .internal()?
-        {
-            Some(locations) => locations
-                .into_iter()
-                .map(|loc| {
-                    McpLocation::try_new(
-                        loc,
-                        Arc::clone(&self.workspace),
-                        workspace_and_dependencies,
-                    )
+        let (declarations, definitions, implementations, type_definitions, references) = futures::try_join!(
+            declarations,
+            definitions,
+            implementations,
+            type_definitions,
+            references
+        )?;
+
+        let docs_url = definitions
+            .iter()
+            .find_map(|location| location.dependency.as_ref())
+            .and_then(|dependency| {
+                server.quirks().docs_url(&DependencySymbol {
+                    dependency,
+                    name: token.data(),
+                    token_type: &token_info.token_type,
+                    hover: hover
+                        .iter()
+                        .filter(|h| h.language.is_some())
+                        .map(|h| h.value.as_str())
+                        .collect(),
                 })
-                .filter_map(Result::transpose)
-                .collect::<Result<Vec<_>, _>>()
-                .context("format references")
-                .internal()?,
-            None => vec![],
+            });
+
+        // conditions of the definition, e.g. for local variables the token itself
+        let location = definitions.first().unwrap_or(&token_info.location);
+        let cfg = if let Ok(Some(content)) = self.read_file(&location.file).await {
+            server.quirks().cfg_conditions(
+                &content,
+                Position::new(location.line - 1, location.character - 1),
+            )
+        } else {
+            vec![]
         };
 
         Ok(Some(SymbolInfo {
-            token: TokenInfo {
-                location,
-                token_type: token.token_type().to_string(),
-                modifiers,
-            },
+            token: token_info,
             hover,
+            docs_url,
+            cfg,
             declarations,
             definitions,
             implementations,
@@ -335,7 +1231,14 @@ impl CodeExplorer {
 #[tool_router]
 impl CodeExplorer {
     #[tool(
-        description = "Find symbol (e.g. a struct, enum, method, ...) in code base. Use the `symbol_info` tool afterwards to learn more about the found symbols."
+        title = "Find Symbol",
+        description = "Find symbol (e.g. a struct, enum, method, ...) in code base. Use the `symbol_info` tool afterwards to learn more about the found symbols.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
     )]
     async fn find_symbol(
         &self,
@@ -344,147 +1247,63 @@ impl CodeExplorer {
             file,
             fuzzy,
             workspace_and_dependencies: workspace_and_dependencies_orig,
+            timeout_secs,
         }): Parameters<FindSymbolRequest>,
         ctx: RequestContext<RoleServer>,
     ) -> Result<Json<FindSymbolResult>, McpError> {
-        let client = self.wait_for_client(ctx).await;
-
         let query = empty_string_to_none(query);
         let file = empty_string_to_none(file);
         let fuzzy = fuzzy.unwrap_or_default();
         let workspace_and_dependencies = workspace_and_dependencies_orig.unwrap_or_default();
-
-        let symbol_informations = match file {
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+
+        let servers = match file.as_deref() {
+            Some(file) => vec![self.server_for_file(file)?],
+            None => self
+                .servers
+                .iter()
+                .filter(|server| !server.is_secondary())
+                .collect::<Vec<_>>(),
+        };
+        let symbol_informations = match file.as_deref() {
             Some(file) => {
-                // LSP may error for non-existing files, so try to read it first
-                let Some(file_content) = self.read_file(&file).await? else {
-                    return Err(McpError::invalid_params(
-                        format!("file not found: {file}"),
-                        None,
-                    ));
-                };
-
-                let resp = client
-                    .send_request::<DocumentSymbolRequest>(DocumentSymbolParams {
-                        text_document: TextDocumentIdentifier {
-                            uri: path_to_uri(&self.workspace, &file)
-                                .context("convert path to URI")
-                                .internal()?,
-                        },
-                        work_done_progress_params: Default::default(),
-                        partial_result_params: Default::default(),
-                    })
+                let server = servers[0];
+                match deadline
+                    .run(self.wait_for_client(server, Priority::High, ctx))
                     .await
-                    .context("DocumentSymbolRequest")
-                    .internal()?;
-
-                let mut symbol_informations = match resp {
-                    None => {
-                        // no symbols
-                        vec![]
-                    }
-                    Some(DocumentSymbolResponse::Flat(symbol_informations)) => symbol_informations,
-                    Some(DocumentSymbolResponse::Nested(_)) => {
-                        return Err(McpError::internal_error(
-                            "nested symbols are not yet implemented",
-                            None,
-                        ));
-                    }
-                };
-
-                // variable declarations are not part of the symbol index, hence we need to fetch them manually
-                let resp = client
-                    .send_request::<SemanticTokensFullRequest>(SemanticTokensParams {
-                        text_document: path_to_text_document_identifier(&self.workspace, &file)
-                            .context("convert path to text document identifier")
-                            .internal()?,
-                        work_done_progress_params: Default::default(),
-                        partial_result_params: Default::default(),
-                    })
-                    .await
-                    .context("SemanticTokensFullRequest")
-                    .internal()?;
-
-                if let Some(lsp_types::SemanticTokensResult::Tokens(semantic_tokens)) = resp {
-                    let doc = self
-                        .token_legend
-                        .decode(&file_content, semantic_tokens.data)
-                        .context("decode semantic tokens")
-                        .internal()?;
-
-                    for token in doc.declared_variables() {
-                        let location = Location {
-                            uri: path_to_uri(&self.workspace, &file)
-                                .context("convert path to URI")
-                                .internal()?,
-                            range: Range {
-                                // in the then we just care about the position, so set both values to it
-                                start: token.lsp_position(),
-                                end: token.lsp_position(),
-                            },
-                        };
-
-                        #[expect(deprecated, reason = "lsp-types still requires this field")]
-                        let symbol_information = SymbolInformation {
-                            name: token.data().to_owned(),
-                            kind: SymbolKind::VARIABLE,
-                            tags: token.is_deprecated().then_some(vec![SymbolTag::DEPRECATED]),
-                            deprecated: None,
-                            location,
-                            container_name: None,
-                        };
-                        symbol_informations.push(symbol_information);
-                    }
+                {
+                    Some(client) => deadline
+                        .run(self.document_symbols(server, &client, file))
+                        .await
+                        .transpose()?
+                        .unwrap_or_default(),
+                    None => vec![],
                 }
-
-                symbol_informations
             }
             None => {
                 let query = query.as_ref().required("query".to_string())?;
-                let resp = client
-                    .send_request::<WorkspaceSymbolRequestExt>(WorkspaceSymbolParamsExt {
-                        base: WorkspaceSymbolParams {
-                            query: query.clone(),
-                            ..Default::default()
-                        },
-                        filtering: WorkspaceSymbolScopeKindFiltering {
-                            search_scope: Some(if workspace_and_dependencies {
-                                WorkspaceSymbolSearchScope::WorkspaceAndDependencies
-                            } else {
-                                WorkspaceSymbolSearchScope::Workspace
-                            }),
-                            search_kind: Some(if workspace_and_dependencies {
-                                // `WorkspaceSymbolSearchScope::WorkspaceAndDependencies` + `WorkspaceSymbolSearchKind::AllSymbols`
-                                // SHOULD work with `AllSymbols` but seems to produce empty results. Maybe it's a bug
-                                // in rust-analyzer or just not implemented. There are a some issues related to symbol
-                                // filtering:
-                                //
-                                // - https://github.com/rust-lang/rust-analyzer/issues/13938
-                                // - https://github.com/rust-lang/rust-analyzer/issues/16491
-                                WorkspaceSymbolSearchKind::OnlyTypes
-                            } else {
-                                WorkspaceSymbolSearchKind::AllSymbols
-                            }),
-                        },
-                    })
+                let clients = deadline
+                    .run(self.wait_for_clients(&servers, Priority::High, ctx))
                     .await
-                    .context("WorkspaceSymbolRequest")
-                    .internal()?;
-
-                let Some(resp) = resp else {
-                    // no symbols
-                    return Ok(Json(FindSymbolResult { symbols: vec![] }));
-                };
+                    .unwrap_or_default();
 
-                match resp {
-                    WorkspaceSymbolResponse::Flat(symbol_informations) => symbol_informations,
-                    WorkspaceSymbolResponse::Nested(_) => {
-                        return Err(McpError::internal_error(
-                            "nested symbols are not yet implemented",
-                            None,
-                        ));
+                // merge results of all language servers, they are sorted later on
+                let mut symbol_informations = vec![];
+                for (server, client) in servers.iter().zip(clients) {
+                    if let Some(symbols) = deadline
+                        .run(self.workspace_symbols(
+                            server,
+                            &client,
+                            query,
+                            workspace_and_dependencies,
+                        ))
+                        .await
+                        .transpose()?
+                    {
+                        symbol_informations.extend(symbols);
                     }
                 }
+                symbol_informations
             }
         };
 
@@ -508,74 +1327,637 @@ impl CodeExplorer {
                 true,
             )?;
         }
-        Ok(Json(FindSymbolResult { symbols: results }))
+        if results.is_empty()
+            && !deadline.timed_out()
+            && let Some(e) = servers.into_iter().find_map(hints::not_indexed)
+        {
+            return Err(e);
+        }
+        Ok(Json(FindSymbolResult {
+            symbols: results,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+
+    #[tool(
+        title = "Symbol Info",
+        description = "Get detailed information about a given symbol (struct, enum, method, trait, ...) like documentation, declaration, references, usage across the code base, etc. The symbol is selected by name or by its position within the file.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn symbol_info(
+        &self,
+        Parameters(SymbolInfoRequest {
+            file,
+            name,
+            fuzzy,
+            token_type,
+            line,
+            character,
+            workspace_and_dependencies,
+            timeout_secs,
+        }): Parameters<SymbolInfoRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<SymbolInfoResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(SymbolInfoResult::timed_out()));
+        };
+
+        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+
+        // re-fetch if the file changes in the meantime, so positions do not refer to stale content
+        for attempt in 1..=MAX_FETCH_ATTEMPTS {
+            let Some((info, stale, fingerprint)) = self
+                .symbol_info_once(
+                    server,
+                    &client,
+                    &file,
+                    name.as_deref(),
+                    fuzzy.unwrap_or_default(),
+                    token_type.as_deref(),
+                    line,
+                    character,
+                    workspace_and_dependencies,
+                    &deadline,
+                )
+                .await?
+            else {
+                return Ok(Json(SymbolInfoResult::timed_out()));
+            };
+            if !self.file_changed(&file, fingerprint).await? {
+                if info.is_empty()
+                    && !deadline.timed_out()
+                    && let Some(e) = hints::not_indexed(server)
+                {
+                    return Err(e);
+                }
+                return Ok(Json(SymbolInfoResult {
+                    info,
+                    timed_out: deadline.timed_out(),
+                    stale,
+                    workspace_root: self.workspace_root(),
+                }));
+            }
+            debug!(
+                file,
+                attempt, "file changed while fetching symbol info, re-fetch"
+            );
+        }
+        Err(hints::file_keeps_changing(&file, MAX_FETCH_ATTEMPTS))
+    }
+
+    #[tool(
+        title = "Changed Symbols",
+        description = "List the symbols (e.g. functions, structs, methods) that were added, modified, or removed between two git revisions, or between a revision and the working tree. Use this to review changes, then use the `symbol_info` tool to learn more about interesting symbols.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn changed_symbols(
+        &self,
+        Parameters(ChangedSymbolsRequest {
+            base,
+            head,
+            timeout_secs,
+        }): Parameters<ChangedSymbolsRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<ChangedSymbolsResult>, McpError> {
+        let workspace = self.workspace();
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let git_err = |e: anyhow::Error| McpError::invalid_params(format!("{e:#}"), None);
+
+        let mut diffs = vec![];
+        for folder in workspace.folders() {
+            let base = changes::resolve(folder, base.as_deref().unwrap_or("HEAD"))
+                .await
+                .map_err(git_err)?;
+            let head = match &head {
+                Some(head) => Some(changes::resolve(folder, head).await.map_err(git_err)?),
+                None => None,
+            };
+            for diff in changes::diff(folder, &base, head.as_deref())
+                .await
+                .map_err(git_err)?
+            {
+                diffs.push((folder.to_owned(), base.clone(), head.clone(), diff));
+            }
+        }
+
+        // pick one language server per file, like `server_for_file`
+        let server_for_diff = |diff: &changes::FileDiff| {
+            let path = diff.new_path.as_ref().or(diff.old_path.as_ref())?;
+            self.servers.iter().find(|server| server.handles_file(path))
+        };
+        let mut servers = Vec::<&LanguageServer>::new();
+        for server in diffs
+            .iter()
+            .filter_map(|(_, _, _, diff)| server_for_diff(diff))
+        {
+            if !servers.iter().any(|s| std::ptr::eq(*s, server)) {
+                servers.push(server);
+            }
+        }
+        let clients = deadline
+            .run(self.wait_for_clients(&servers, Priority::Normal, ctx))
+            .await
+            .unwrap_or_default();
+        let clients = servers.into_iter().zip(clients).collect::<Vec<_>>();
+
+        let mut files = vec![];
+        for (folder, base, head, diff) in &diffs {
+            let render = |path: &str| {
+                let path = folder.join(path);
+                workspace
+                    .relativize(&path)
+                    .unwrap_or_else(|| path.display().to_string())
+            };
+            let change = diff.change();
+            let file = render(
+                diff.new_path
+                    .as_deref()
+                    .or(diff.old_path.as_deref())
+                    .unwrap_or_default(),
+            );
+            let old_file = (change == FileChange::Renamed)
+                .then(|| diff.old_path.as_deref().map(render))
+                .flatten();
+
+            let client = server_for_diff(diff)
+                .and_then(|server| clients.iter().find(|(s, _client)| std::ptr::eq(*s, server)));
+            let symbols = match client {
+                Some((server, client)) => deadline
+                    .run(self.file_changes(server, client, folder, base, head.as_deref(), diff))
+                    .await
+                    .transpose()?
+                    .unwrap_or_default(),
+                None => vec![],
+            };
+
+            files.push(ChangedFile {
+                file,
+                old_file,
+                change,
+                symbols,
+            });
+        }
+        files.sort_by(|a, b| a.file.cmp(&b.file));
+
+        Ok(Json(ChangedSymbolsResult {
+            files,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+
+    #[tool(
+        title = "Project Statistics",
+        description = "Get the size of the project: number of files, lines, and symbols per kind (e.g. functions, structs), per language and per package (e.g. crate). Use this to get a sense of the project size and where most of the code lives before exploring it.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn project_statistics(
+        &self,
+        Parameters(ProjectStatisticsRequest { timeout_secs }): Parameters<ProjectStatisticsRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<ProjectStatisticsResult>, McpError> {
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let workspace = self.workspace();
+        let files = tokio::task::spawn_blocking(move || export::workspace_files(&workspace))
+            .await
+            .context("join listing")
+            .internal()?
+            .internal()?;
+
+        // pick one language server per file, like `server_for_file`
+        let servers = self
+            .servers
+            .iter()
+            .filter(|server| files.iter().any(|file| server.handles_file(file)))
+            .collect::<Vec<_>>();
+        let clients = deadline
+            .run(self.wait_for_clients(&servers, Priority::Low, ctx))
+            .await
+            .unwrap_or_default();
+        let packages = servers
+            .iter()
+            .map(|server| {
+                files
+                    .iter()
+                    .map(Path::new)
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| server.quirks().is_package_manifest(name))
+                    })
+                    .filter_map(|path| path.parent()?.to_str().map(ToOwned::to_owned))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut statistics = Statistics::default();
+        for file in &files {
+            let Some(idx) = servers.iter().position(|server| server.handles_file(file)) else {
+                continue;
+            };
+            let Some(client) = clients.get(idx) else {
+                continue;
+            };
+            let Some(content) = self.read_file(file).await? else {
+                // removed in the meantime
+                continue;
+            };
+            let Some(symbols) = deadline
+                .run(self.document_symbols(servers[idx], client, file))
+                .await
+            else {
+                break;
+            };
+            // local variables are only added for the symbol search and would drown the other kinds
+            let kinds = symbols?
+                .into_iter()
+                .map(|symbol| symbol.kind)
+                .filter(|kind| *kind != SymbolKind::VARIABLE)
+                .collect::<Vec<_>>();
+            statistics.add(
+                servers[idx].name(),
+                statistics::package_of(file, &packages[idx]),
+                content.lines().count(),
+                &kinds,
+            );
+        }
+        let (languages, packages) = statistics.finish();
+
+        Ok(Json(ProjectStatisticsResult {
+            languages,
+            packages,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+
+    #[tool(
+        title = "Server Status",
+        description = "Get status of the language servers, e.g. their version and if they are ready or still indexing the code base.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn server_status(&self) -> Result<Json<ServerStatusResult>, McpError> {
+        let servers = self
+            .servers
+            .iter()
+            .map(|server| {
+                let init_timed_out = server.progress_guard().init_timed_out();
+                ServerStatus {
+                    language: server.name().to_owned(),
+                    server_name: server.server_info().map(|info| info.name.clone()),
+                    server_version: server.server_info().and_then(|info| info.version.clone()),
+                    version_check: server.version_status().clone(),
+                    ready: server.progress_guard().is_ready(),
+                    init_timed_out: init_timed_out.is_some(),
+                    missing_init_parts: init_timed_out.unwrap_or_default(),
+                    running: server.scheduler().running(),
+                    queued: server.scheduler().queued(),
+                }
+            })
+            .collect();
+
+        Ok(Json(ServerStatusResult {
+            servers,
+            tasks: self.tools_cfg.task_statuses.snapshot(),
+        }))
+    }
+
+    #[tool(
+        title = "Server Stats",
+        description = "Get call counts, error counts, and latency percentiles of the tools, latency and error statistics of the requests sent to the language servers, and cache hit rates since startup, e.g. to find out which tools are slow or failing.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    async fn server_stats(&self) -> Result<Json<ServerStatsResult>, McpError> {
+        let servers = self
+            .servers
+            .iter()
+            .map(|server| ServerStats {
+                language: server.name().to_owned(),
+                requests: server
+                    .progress_guard()
+                    .client()
+                    .metrics()
+                    .snapshot()
+                    .into_iter()
+                    .map(|(method, stats)| RequestStats {
+                        method: method.to_owned(),
+                        count: stats.count,
+                        errors: stats.errors,
+                        mean_ms: stats.mean().as_secs_f64() * 1000.0,
+                        max_ms: stats.max.as_secs_f64() * 1000.0,
+                    })
+                    .collect(),
+                workspace_symbol_cache: server.symbol_cache().stats(),
+                hover_cache: server.hover_cache().stats(),
+                document_cache: server.document_cache().stats(),
+                semantic_tokens_cache: server.semantic_tokens().stats(),
+            })
+            .collect();
+
+        // all language servers share the same budget
+        let cache_budget = self
+            .servers
+            .first()
+            .map(|server| server.cache_registry().stats());
+
+        Ok(Json(ServerStatsResult {
+            tools: self.tools_cfg.tool_metrics.snapshot(),
+            servers,
+            cache_budget,
+            task_restarts: self.tools_cfg.task_statuses.total_restarts(),
+        }))
+    }
+
+    #[tool(
+        title = "Reload Configuration",
+        description = "Reload the language server configuration (e.g. after changing the file passed via `--lsp-init-options`) and push it to the language servers without restarting them.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn reload_config(&self) -> Result<Json<ReloadConfigResult>, McpError> {
+        let mut reloaded = vec![];
+        for server in self.servers.iter() {
+            let settings = server
+                .load_settings(self.workspace().root())
+                .with_context(|| format!("load configuration for {}", server.name()))
+                .internal()?;
+            server.push_settings(settings).await.internal()?;
+            reloaded.push(server.name().to_owned());
+        }
+
+        Ok(Json(ReloadConfigResult { reloaded }))
+    }
+
+    #[tool(
+        title = "Get Configuration",
+        description = "Get the effective configuration of this server, i.e. CLI arguments, environment variables, config file, and defaults merged, and where each value comes from. Use this to find out why a setting does not take effect.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn get_config(&self) -> Result<Json<EffectiveConfig>, McpError> {
+        let config = self
+            .tools_cfg
+            .config_reporter
+            .as_ref()
+            .expected("configuration is not available".to_owned())?
+            .effective()
+            .internal()?;
+        Ok(Json(config))
+    }
+
+    #[tool(
+        title = "LSP Request",
+        description = "Send a raw request to a language server and get its raw response. Only use this for server-specific extensions that no other tool covers.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    async fn lsp_request(
+        &self,
+        Parameters(LspRequestRequest {
+            method,
+            params,
+            language,
+        }): Parameters<LspRequestRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<LspRequestResult>, McpError> {
+        let server = match language {
+            Some(language) => self
+                .servers
+                .iter()
+                .find(|server| server.name() == language)
+                .ok_or_else(|| {
+                    McpError::invalid_params(
+                        format!(
+                            "no language server for {language}, running are: {}",
+                            self.servers.iter().map(|server| server.name()).join(", ")
+                        ),
+                        None,
+                    )
+                })?,
+            None => match &*self.servers {
+                [server] => server,
+                servers => {
+                    return Err(McpError::invalid_params(
+                        format!(
+                            "`language` is required if multiple language servers are running: {}",
+                            servers.iter().map(|server| server.name()).join(", ")
+                        ),
+                        None,
+                    ));
+                }
+            },
+        };
+
+        let client = self.wait_for_client(server, Priority::High, ctx).await;
+        let response = client
+            .send_request::<RawRequest>(RawRequestParams {
+                method: method.clone(),
+                params: params.unwrap_or_default(),
+            })
+            .await
+            .with_context(|| format!("LSP request {method}"))
+            .internal()?;
+
+        Ok(Json(LspRequestResult { response }))
     }
 
     #[tool(
-        description = "Get detailed information about a given symbol (struct, enum, method, trait, ...) like documentation, declaration, references, usage across the code base, etc."
+        title = "Debug Tail",
+        description = "Get the last messages exchanged with the language servers, e.g. to debug why a tool call returns unexpected results.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
     )]
-    async fn symbol_info(
+    async fn debug_tail(
         &self,
-        Parameters(SymbolInfoRequest {
-            file,
-            name,
-            line,
-            character,
-            workspace_and_dependencies,
-        }): Parameters<SymbolInfoRequest>,
-        ctx: RequestContext<RoleServer>,
-    ) -> Result<Json<SymbolInfoResult>, McpError> {
-        let client = self.wait_for_client(ctx).await;
+        Parameters(DebugTailRequest { language, limit }): Parameters<DebugTailRequest>,
+    ) -> Result<Json<DebugTailResult>, McpError> {
+        if let Some(language) = &language
+            && !self.servers.iter().any(|server| server.name() == language)
+        {
+            return Err(McpError::invalid_params(
+                format!(
+                    "no language server for {language}, running are: {}",
+                    self.servers.iter().map(|server| server.name()).join(", ")
+                ),
+                None,
+            ));
+        }
 
-        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+        let limit = limit.unwrap_or(DEFAULT_DEBUG_TAIL_LIMIT);
+        let mut messages = self
+            .servers
+            .iter()
+            .filter(|server| {
+                language
+                    .as_ref()
+                    .is_none_or(|language| server.name() == language)
+            })
+            .filter_map(|server| server.traffic_tail())
+            .flat_map(|tail| tail.last(limit))
+            .collect::<Vec<_>>();
+        // stable, keeps the order of messages with the same timestamp
+        messages.sort_by_key(|entry| entry["timestamp_us"].as_u64());
+        let messages = messages.split_off(messages.len().saturating_sub(limit));
 
-        let file_content = match self.read_file(&file).await? {
-            Some(s) => s,
-            None => {
+        Ok(Json(DebugTailResult { messages }))
+    }
+
+    #[tool(
+        title = "Add Workspace Folder",
+        description = "Add a folder to the workspace, e.g. another repository. Once the workspace has multiple folders, file paths start with the folder name.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn add_workspace_folder(
+        &self,
+        Parameters(AddWorkspaceFolderRequest { path }): Parameters<AddWorkspaceFolderRequest>,
+    ) -> Result<Json<WorkspaceFoldersResult>, McpError> {
+        if !Path::new(&path).is_absolute() {
+            return Err(McpError::invalid_params(
+                format!("path must be absolute: {path}"),
+                None,
+            ));
+        }
+        let path = match tokio::fs::canonicalize(&path).await {
+            Ok(path) if path.is_dir() => path,
+            Ok(_) => {
                 return Err(McpError::invalid_params(
-                    format!("file not found: {file}"),
+                    format!("not a directory: {path}"),
                     None,
                 ));
             }
-        };
-        let resp = client
-            .send_request::<SemanticTokensFullRequest>(SemanticTokensParams {
-                text_document: path_to_text_document_identifier(&self.workspace, &file)
-                    .context("convert path to text document identifier")
-                    .internal()?,
-                work_done_progress_params: Default::default(),
-                partial_result_params: Default::default(),
-            })
-            .await
-            .context("SemanticTokensFullRequest")
-            .internal()?
-            .expected("language server did not provide any semantic tokens".to_owned())?;
-        let doc = match resp {
-            lsp_types::SemanticTokensResult::Tokens(semantic_tokens) => self
-                .token_legend
-                .decode(&file_content, semantic_tokens.data)
-                .context("decode semantic tokens")
-                .internal()?,
-            lsp_types::SemanticTokensResult::Partial(_) => {
-                return Err(McpError::internal_error(
-                    "partial semantic token results are not supported",
+            Err(e) => {
+                return Err(McpError::invalid_params(
+                    format!("cannot access {path}: {e}"),
                     None,
                 ));
             }
         };
-        let tokens = doc.query(&name, line, character);
-        let mut results = vec![];
-        for token in tokens {
-            let Some(res) = self
-                .symbol_info_for_token(token, &file, &client, workspace_and_dependencies)
-                .await?
-            else {
-                continue;
-            };
-            results.push(res);
+
+        self.change_workspace(|workspace| workspace.with_folder(path))
+            .await
+    }
+
+    #[tool(
+        title = "Remove Workspace Folder",
+        description = "Remove a folder from the workspace. The last folder cannot be removed.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    async fn remove_workspace_folder(
+        &self,
+        Parameters(RemoveWorkspaceFolderRequest { folder }): Parameters<
+            RemoveWorkspaceFolderRequest,
+        >,
+    ) -> Result<Json<WorkspaceFoldersResult>, McpError> {
+        self.change_workspace(|workspace| workspace.without_folder(&folder))
+            .await
+    }
+}
+
+impl CodeExplorer {
+    /// Change workspace folders and tell the language servers about it.
+    async fn change_workspace<F>(&self, f: F) -> Result<Json<WorkspaceFoldersResult>, McpError>
+    where
+        F: FnOnce(&Workspace) -> anyhow::Result<Workspace>,
+    {
+        let (previous, current) = self
+            .workspace
+            .update(f)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let (added, removed) = current
+            .lsp_folders_diff(&previous)
+            .context("workspace folders")
+            .internal()?;
+        if !(added.is_empty() && removed.is_empty()) {
+            info!(path=%current, "workspace folders changed");
+            let folders = current
+                .lsp_folders()
+                .context("workspace folders")
+                .internal()?;
+            for server in self.servers.iter() {
+                server.handler().set_workspace_folders(folders.clone());
+                server
+                    .progress_guard()
+                    .client()
+                    .send_notification::<DidChangeWorkspaceFolders>(
+                        DidChangeWorkspaceFoldersParams {
+                            event: WorkspaceFoldersChangeEvent {
+                                added: added.clone(),
+                                removed: removed.clone(),
+                            },
+                        },
+                    )
+                    .await
+                    .context("DidChangeWorkspaceFolders")
+                    .internal()?;
+            }
         }
 
-        Ok(Json(SymbolInfoResult { info: results }))
+        Ok(Json(WorkspaceFoldersResult {
+            folders: current
+                .named_folders()
+                .map(|(name, path)| WorkspaceFolderInfo {
+                    name: name.to_owned(),
+                    path: path.display().to_string(),
+                })
+                .collect(),
+        }))
     }
 }
 
@@ -594,11 +1976,23 @@ struct FindSymbolRequest {
 
     /// search workspace and dependencies
     workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
 }
 
 #[derive(Debug, serde::Serialize, schemars::JsonSchema)]
 struct FindSymbolResult {
     symbols: Vec<SymbolResult>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
@@ -624,24 +2018,54 @@ impl Ord for SymbolResult {
     }
 }
 
-#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
 struct SymbolInfoResult {
     info: Vec<SymbolInfo>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// the file changed while it was analyzed, results may be incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stale: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for SymbolInfoResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
 }
 
 #[derive(Debug, serde::Serialize, schemars::JsonSchema)]
 struct SymbolInfo {
     token: TokenInfo,
     hover: Vec<HoverInfo>,
+
+    /// documentation URL (best-effort), only for symbols that are defined in a dependency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    docs_url: Option<String>,
+
+    /// `cfg` conditions that guard the definition, incl. those of enclosing items, e.g. `test` or `feature = "serde"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cfg: Vec<String>,
+
     declarations: Vec<McpLocation>,
     definitions: Vec<McpLocation>,
     implementations: Vec<McpLocation>,
     type_definitions: Vec<McpLocation>,
-    references: Vec<McpLocation>,
+
+    /// nearest first: same file, same module (directory), elsewhere in the workspace, dependencies
+    references: Vec<proximity::Reference>,
 }
 
 #[derive(Debug, serde::Serialize, schemars::JsonSchema)]
 struct TokenInfo {
+    name: String,
     location: McpLocation,
     token_type: String,
     modifiers: Vec<String>,
@@ -652,8 +2076,14 @@ struct SymbolInfoRequest {
     /// path to the file, can be absolute or relative
     file: String,
 
-    /// symbol name
-    name: String,
+    /// symbol name, if not set the symbol at the given line and character is used
+    name: Option<String>,
+
+    /// search fuzzy if no symbol matches the name exactly
+    fuzzy: Option<bool>,
+
+    /// only consider tokens of this type, e.g. `function` or `struct`
+    token_type: Option<String>,
 
     /// 1-based line number within the file
     #[schemars(range(min = 1))]
@@ -665,6 +2095,235 @@ struct SymbolInfoRequest {
 
     /// search workspace and dependencies
     workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ServerStatusResult {
+    servers: Vec<ServerStatus>,
+
+    /// background tasks, e.g. progress tracking of the language servers
+    tasks: Vec<TaskStatus>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ServerStatus {
+    /// programming language
+    language: String,
+
+    /// name reported by the language server
+    server_name: Option<String>,
+
+    /// version reported by the language server
+    server_version: Option<String>,
+
+    /// result of the minimum version check
+    version_check: VersionStatus,
+
+    /// language server finished indexing and has no work in progress
+    ready: bool,
+
+    /// language server did not finish its initial work in time and is used anyways, results may be incomplete
+    init_timed_out: bool,
+
+    /// expected progress reports of the initial work that never started
+    missing_init_parts: Vec<String>,
+
+    /// number of tool calls that currently use the language server
+    running: usize,
+
+    /// number of tool calls that wait for the language server
+    queued: usize,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ChangedSymbolsRequest {
+    /// git revision to compare against, e.g. `HEAD~1` or a branch name, defaults to `HEAD`
+    base: Option<String>,
+
+    /// git revision with the changes, defaults to the working tree
+    head: Option<String>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ChangedSymbolsResult {
+    /// changed files, sorted by path
+    files: Vec<ChangedFile>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ChangedFile {
+    file: String,
+
+    /// previous path of renamed files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    old_file: Option<String>,
+
+    change: FileChange,
+
+    /// changed symbols, sorted by line
+    symbols: Vec<ChangedSymbol>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ProjectStatisticsRequest {
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ProjectStatisticsResult {
+    /// counts per language server
+    languages: BTreeMap<String, Counts>,
+
+    /// counts per package (e.g. crate), largest first
+    packages: Vec<PackageCounts>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ServerStatsResult {
+    /// statistics per tool since startup, sorted by tool, excluding the running call
+    tools: Vec<ToolStats>,
+
+    servers: Vec<ServerStats>,
+
+    /// memory usage of all caches combined
+    cache_budget: Option<CacheBudgetStats>,
+
+    /// restarts of failed background tasks, e.g. the listeners for language server messages, see `server_status`
+    task_restarts: usize,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ServerStats {
+    /// programming language
+    language: String,
+
+    /// statistics per LSP method, sorted by method
+    requests: Vec<RequestStats>,
+
+    /// cached workspace symbol searches
+    workspace_symbol_cache: CacheStats,
+
+    /// cached hover results
+    hover_cache: CacheStats,
+
+    /// cached symbols and semantic tokens of pinned documents, see `--warmup-globs`
+    document_cache: CacheStats,
+
+    /// semantic tokens that delta requests are based on
+    semantic_tokens_cache: CacheStats,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct RequestStats {
+    /// LSP method, e.g. `textDocument/documentSymbol`
+    method: String,
+
+    /// number of requests, including failed ones
+    count: u64,
+
+    /// number of failed requests
+    errors: u64,
+
+    /// mean request duration in milliseconds
+    mean_ms: f64,
+
+    /// longest request duration in milliseconds
+    max_ms: f64,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ReloadConfigResult {
+    /// programming languages whose language servers received the new configuration
+    reloaded: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct LspRequestRequest {
+    /// LSP method, e.g. `rust-analyzer/expandMacro`
+    #[schemars(length(min = 1))]
+    method: String,
+
+    /// parameters of the request as JSON, positions use UTF-8 offsets
+    params: Option<serde_json::Value>,
+
+    /// programming language of the language server, only required if multiple language servers are running
+    language: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct LspRequestResult {
+    /// raw response of the language server
+    response: serde_json::Value,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DebugTailRequest {
+    /// programming language of the language server, all language servers if omitted
+    language: Option<String>,
+
+    /// maximum number of messages, defaults to 20
+    limit: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct DebugTailResult {
+    /// messages with timestamp, peer, and direction, oldest first
+    messages: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AddWorkspaceFolderRequest {
+    /// absolute path of the folder
+    #[schemars(length(min = 1))]
+    path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct RemoveWorkspaceFolderRequest {
+    /// name or absolute path of the folder
+    #[schemars(length(min = 1))]
+    folder: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct WorkspaceFoldersResult {
+    /// workspace folders after the change, the first one is the root
+    folders: Vec<WorkspaceFolderInfo>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct WorkspaceFolderInfo {
+    /// name that prefixes file paths if the workspace has multiple folders
+    name: String,
+
+    /// absolute path
+    path: String,
 }
 
 #[derive(Debug, serde::Serialize, schemars::JsonSchema)]
@@ -709,23 +2368,146 @@ fn parse_markdown_code_blocks(value: &str) -> Option<Vec<HoverInfo>> {
     (!blocks.is_empty()).then_some(blocks)
 }
 
+/// Convert workspace symbol into the flat representation.
+///
+/// Symbols without a range point to the start of the file.
+fn workspace_symbol_to_information(symbol: WorkspaceSymbol) -> SymbolInformation {
+    let WorkspaceSymbol {
+        name,
+        kind,
+        tags,
+        container_name,
+        location,
+        data: _,
+    } = symbol;
+    let location = match location {
+        OneOf::Left(location) => location,
+        OneOf::Right(WorkspaceLocation { uri }) => Location {
+            uri,
+            range: Range::default(),
+        },
+    };
+
+    #[expect(deprecated, reason = "lsp-types still requires this field")]
+    SymbolInformation {
+        name,
+        kind,
+        tags,
+        deprecated: None,
+        location,
+        container_name,
+    }
+}
+
 fn empty_string_to_none(s: Option<String>) -> Option<String> {
     s.and_then(|s| (!s.is_empty()).then_some(s))
 }
 
+/// Capabilities and instructions of the server.
+///
+/// The instructions summarize the tools of the router, so that they only mention tools that can be called.
+fn server_info(tool_router: &ToolRouter<CodeExplorer>) -> ServerInfo {
+    #[expect(
+        deprecated,
+        reason = "MCP clients still use logging, there is no replacement yet"
+    )]
+    let capabilities = ServerCapabilities::builder()
+        .enable_tools()
+        .enable_logging()
+        .enable_completions()
+        .enable_resources()
+        .enable_resources_subscribe()
+        .build();
+
+    let mut instructions =
+        "This server helps you to understand a code base.\n\nIt comes with these tools:\n"
+            .to_owned();
+    for tool in tool_router
+        .list_all()
+        .into_iter()
+        .filter(|tool| !tool_router.is_disabled(&tool.name))
+    {
+        // the first sentence of the description is a summary
+        let summary = first_sentence(tool.description.as_deref().unwrap_or_default());
+        instructions.push_str(&format!("- `{}`: {summary}.\n", tool.name));
+    }
+    instructions.push_str("\nFirst use the `find_symbol` tool to get the file path of the respective symbol. Then use the `symbol_info` tool to get the detailed information about them.");
+
+    ServerInfo::new(capabilities)
+        .with_server_info(Implementation::new(NAME, VERSION_STRING))
+        .with_instructions(instructions)
+}
+
+/// First sentence of a text without the period, abbreviations like "e.g." do not end it.
+fn first_sentence(text: &str) -> &str {
+    let mut start = 0;
+    while let Some(idx) = text[start..].find(". ") {
+        let end = start + idx;
+        if !["e.g", "i.e"]
+            .iter()
+            .any(|abbr| text[..end].ends_with(abbr))
+        {
+            return &text[..end];
+        }
+        start = end + 2;
+    }
+    text.trim_end_matches('.')
+}
+
+/// Tool definitions as announced to MCP clients.
+fn restrict_tool_formats(tools: Vec<Tool>) -> Vec<Tool> {
+    // Workaround because some MCP users complain about non-standard formats.
+    //
+    // See <https://github.com/GREsau/schemars/pull/405>, but that's not used by [`rmcp`].
+    tools
+        .into_iter()
+        .map(|mut tool| {
+            let mut input_schema: Schema = tool.input_schema.deref().clone().into();
+            RestrictFormats::default().transform(&mut input_schema);
+            tool.input_schema = Arc::new(
+                input_schema
+                    .as_object()
+                    .expect("schema should be an object")
+                    .clone(),
+            );
+
+            let mut output_schema: Schema = tool
+                .output_schema
+                .as_ref()
+                .expect("output schema set")
+                .deref()
+                .clone()
+                .into();
+            RestrictFormats::default().transform(&mut output_schema);
+            tool.output_schema = Some(Arc::new(
+                output_schema
+                    .as_object()
+                    .expect("schema should be an object")
+                    .clone(),
+            ));
+
+            tool
+        })
+        .collect()
+}
+
+/// Add request ID to the error data, so the error can be correlated with logs and interception dumps.
+fn with_request_id(mut e: McpError, request_id: u64) -> McpError {
+    match &mut e.data {
+        None => {
+            e.data = Some(serde_json::json!({"request_id": request_id}));
+        }
+        Some(serde_json::Value::Object(data)) => {
+            data.insert("request_id".to_owned(), request_id.into());
+        }
+        Some(_) => {}
+    }
+    e
+}
+
 impl ServerHandler for CodeExplorer {
     fn get_info(&self) -> ServerInfo {
-        ServerInfo::new(ServerCapabilities::builder().enable_tools().build())
-            .with_server_info(Implementation::new(NAME, VERSION_STRING))
-            .with_instructions("\
-                This server helps you to understand a code base.\
-                \
-                It comes with two tools:\
-                - `find_symbols`: Searches symbols (structs, enums, methods, traits, ...) defined/used by the code base.\
-                - `symbol_info`: Provides detailed information about a symbol like documentation and usage pattern.\
-                \
-                First use the `find_symbols` tool to get the file path of the respective symbol. Then use the `symbol_info` tool to get the detailed information about them.\
-            ".trim().to_owned())
+        server_info(&self.tool_router)
     }
 
     async fn call_tool(
@@ -733,9 +2515,37 @@ impl ServerHandler for CodeExplorer {
         request: CallToolRequestParams,
         context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
-        info!(name = request.name.as_ref(), "call tool");
-        let tcc = ToolCallContext::new(self, request, context);
-        self.tool_router.call(tcc).await
+        let request_guard = self.tools_cfg.active_requests.start();
+        let request_id = request_guard.id();
+        let span = info_span!("tool_call", request_id, tool = request.name.as_ref());
+        async move {
+            info!("call tool");
+            if self.tool_router.is_disabled(&request.name) {
+                return Err(with_request_id(
+                    policy::read_only(&request.name),
+                    request_id,
+                ));
+            }
+            let tool = request.name.clone();
+            let start = Instant::now();
+            let tcc = ToolCallContext::new(self, request, context);
+            let result = self.tool_router.call(tcc).await;
+            self.tools_cfg.tool_metrics.record(
+                &tool,
+                start.elapsed(),
+                result
+                    .as_ref()
+                    .is_ok_and(|result| result.is_error != Some(true)),
+            );
+            let mut result = result.map_err(|e| with_request_id(e, request_id))?;
+            self.tools_cfg.secrets_cfg().redact_result(&mut result);
+            if let Some(max_chars) = self.tools_cfg.max_response_chars() {
+                budget::enforce(&mut result, max_chars);
+            }
+            Ok(result)
+        }
+        .instrument(span)
+        .await
     }
 
     async fn list_tools(
@@ -743,42 +2553,76 @@ impl ServerHandler for CodeExplorer {
         _request: Option<PaginatedRequestParams>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, McpError> {
-        let items = self.tool_router.list_all();
-
-        // Workaround because some MCP users complain about non-standard formats.
-        //
-        // See <https://github.com/GREsau/schemars/pull/405>, but that's not used by [`rmcp`].
-        let items = items
-            .into_iter()
-            .map(|mut tool| {
-                let mut input_schema: Schema = tool.input_schema.deref().clone().into();
-                RestrictFormats::default().transform(&mut input_schema);
-                tool.input_schema = Arc::new(
-                    input_schema
-                        .as_object()
-                        .expect("schema should be an object")
-                        .clone(),
-                );
+        Ok(ListToolsResult::with_all_items(restrict_tool_formats(
+            self.tool_router.list_all(),
+        )))
+    }
 
-                let mut output_schema: Schema = tool
-                    .output_schema
-                    .as_ref()
-                    .expect("output schema set")
-                    .deref()
-                    .clone()
-                    .into();
-                RestrictFormats::default().transform(&mut output_schema);
-                tool.output_schema = Some(Arc::new(
-                    output_schema
-                        .as_object()
-                        .expect("schema should be an object")
-                        .clone(),
-                ));
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            resources::resource_templates(),
+        ))
+    }
 
-                tool
-            })
-            .collect();
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        resources::read(
+            &self.workspace(),
+            &request.uri,
+            self.tools_cfg.secrets_cfg(),
+        )
+        .await
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let path = resources::uri_to_path(&self.workspace(), &request.uri)?;
+        self.subscriptions
+            .subscribe(request.uri, path, &context.peer)
+            .await;
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.subscriptions.unsubscribe(&request.uri);
+        Ok(())
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        info!("client initialized");
+        self.attach(&context.peer);
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.log_forwarder.set_level(request.level);
+        Ok(())
+    }
 
-        Ok(ListToolsResult::with_all_items(items))
+    async fn complete(
+        &self,
+        request: CompleteRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        self.completer
+            .complete(&self.servers, &self.workspace, request)
+            .await
     }
 }
@@ -0,0 +1,221 @@
+//! Module path of a position, following the `mod` declarations up to the crate root.
+use anyhow::Context;
+use lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    location::{LocationVariants, McpLocation, path_to_text_document_identifier},
+    requests::ParentModule,
+    scheduler::Priority,
+};
+
+use super::{CodeExplorer, declarations, error::ResultExt, hints, timeout::TimedOut, traits};
+
+/// Upper limit for the nesting of modules that `parent_module` follows, guards against cycles.
+const MAX_DEPTH: usize = 64;
+
+#[tool_router(router = parent_module_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Parent Module",
+        description = "Find where a file or position hangs in the module tree: the `mod` declarations from the module that contains the position up to the crate root, plus the module path. Useful after landing in a deeply nested file.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn parent_module(
+        &self,
+        Parameters(ParentModuleRequest {
+            file,
+            line,
+            character,
+            workspace_and_dependencies,
+            timeout_secs,
+        }): Parameters<ParentModuleRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<ParentModuleResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if !server.quirks().supports_parent_module() {
+            return Err(McpError::invalid_params(
+                format!("{} does not support finding parent modules", server.name()),
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(ParentModuleResult::timed_out()));
+        };
+        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        let line = line.unwrap_or(1);
+        let lines = file_content.lines().count();
+        if line as usize > lines.max(1) {
+            return Err(hints::position_drifted(&file, line, lines));
+        }
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        // walk up the module tree, each declaration is within the next parent module
+        let mut params = TextDocumentPositionParams {
+            text_document,
+            // 1-based => 0-based
+            position: Position::new(line - 1, character.unwrap_or(1) - 1),
+        };
+        let mut visited = vec![];
+        let mut parents = vec![];
+        let mut reached_root = false;
+        let mut timed_out = false;
+        while parents.len() < MAX_DEPTH {
+            let Some(resp) = deadline
+                .run(client.send_request::<ParentModule>(params.clone()))
+                .await
+            else {
+                timed_out = true;
+                break;
+            };
+            let Some(location) = resp
+                .context("ParentModule")
+                .internal()?
+                .map(|resp| traits::locations(LocationVariants::from(resp)))
+                .and_then(|locations| locations.into_iter().next())
+            else {
+                reached_root = true;
+                break;
+            };
+            // crate roots point to their manifest
+            if !location.uri.path().as_str().ends_with(".rs") {
+                reached_root = true;
+                break;
+            }
+            if visited.contains(&location) {
+                break;
+            }
+            visited.push(location.clone());
+
+            let Some(mcp_location) = McpLocation::try_new(
+                location.clone(),
+                self.workspace(),
+                workspace_and_dependencies,
+            )
+            .context("convert module location")
+            .internal()?
+            else {
+                break;
+            };
+            let content = self.read_file(&mcp_location.file).await?;
+            let text = content.as_deref().and_then(|content| {
+                let declaration = content.lines().nth(location.range.start.line as usize)?;
+                let name = declarations::range_text(content, location.range)?;
+                Some((name.to_owned(), declaration.trim().to_owned()))
+            });
+            let (name, declaration) = text.unzip();
+            parents.push(ModuleDeclaration {
+                name,
+                declaration,
+                location: mcp_location,
+            });
+
+            params = TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: location.uri },
+                position: location.range.start,
+            };
+        }
+
+        let module_path = (reached_root && parents.iter().all(|parent| parent.name.is_some()))
+            .then(|| {
+                std::iter::once("crate")
+                    .chain(
+                        parents
+                            .iter()
+                            .rev()
+                            .filter_map(|parent| parent.name.as_deref()),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("::")
+            });
+
+        Ok(Json(ParentModuleResult {
+            module_path,
+            parents,
+            timed_out: timed_out || deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ParentModuleRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// 1-based line number within the file, defaults to the first line
+    #[schemars(range(min = 1))]
+    line: Option<u32>,
+
+    /// 1-based character index within the line, defaults to the first character
+    #[schemars(range(min = 1))]
+    character: Option<u32>,
+
+    /// follow the module tree into dependencies
+    workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ModuleDeclaration {
+    /// name of the module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+
+    /// source line of the declaration, e.g. `pub(crate) mod requests;`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    declaration: Option<String>,
+
+    location: McpLocation,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct ParentModuleResult {
+    /// path of the module that contains the position, e.g. `crate::lsp::requests`, if the crate root was reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    module_path: Option<String>,
+
+    /// `mod` declarations from the module that contains the position up to the crate root, empty for the crate root
+    parents: Vec<ModuleDeclaration>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for ParentModuleResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
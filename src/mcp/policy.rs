@@ -0,0 +1,58 @@
+//! Restrictions of what MCP clients are allowed to do.
+use rmcp::model::{ErrorCode, ErrorData as McpError, Tool};
+use serde_json::json;
+
+/// The tool call was refused because the server is read-only, see [`read_only`].
+const READ_ONLY: ErrorCode = ErrorCode(-32020);
+
+/// The tool may modify files of the workspace, judging by its annotations.
+///
+/// Follows the defaults of the MCP spec, i.e. tools without annotations are assumed to be destructive.
+pub(super) fn modifies_workspace(tool: &Tool) -> bool {
+    tool.annotations.as_ref().is_none_or(|annotations| {
+        !annotations.read_only_hint.unwrap_or_default() && annotations.is_destructive()
+    })
+}
+
+/// Error for a call of a tool that is disabled by `--read-only`.
+pub(super) fn read_only(tool: &str) -> McpError {
+    McpError::new(
+        READ_ONLY,
+        format!(
+            "tool `{tool}` may modify the workspace, which is not allowed\n- the server runs in read-only mode, do not retry the call"
+        ),
+        Some(json!({"policy": "read_only"})),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use rmcp::model::ToolAnnotations;
+
+    use super::*;
+
+    #[test]
+    fn test_modifies_workspace() {
+        let tool = |annotations: Option<ToolAnnotations>| {
+            let tool = Tool::new("tool", "", Arc::default());
+            match annotations {
+                Some(annotations) => tool.annotate(annotations),
+                None => tool,
+            }
+        };
+
+        assert!(modifies_workspace(&tool(None)));
+        assert!(modifies_workspace(&tool(Some(ToolAnnotations::new()))));
+        assert!(modifies_workspace(&tool(Some(
+            ToolAnnotations::new().read_only(false).destructive(true)
+        ))));
+        assert!(!modifies_workspace(&tool(Some(
+            ToolAnnotations::new().read_only(true)
+        ))));
+        assert!(!modifies_workspace(&tool(Some(
+            ToolAnnotations::new().read_only(false).destructive(false)
+        ))));
+    }
+}
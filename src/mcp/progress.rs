@@ -0,0 +1,369 @@
+//! Progress notifications for requests that wait for the language servers.
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use lsp_types::NumberOrString;
+use rmcp::{
+    Peer, RoleServer,
+    model::{ProgressNotificationParam, ProgressToken},
+    service::RequestContext,
+};
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::lsp::progress_guard::{Phase, ProgressEvent};
+
+/// Minimum time between two notifications for language server progress, i.e. at most 2 per second.
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Turns progress events of language server tasks into MCP progress values.
+///
+/// MCP requires the progress to increase with every notification. Every task that reports percentages adds 100 to
+/// the total and its percentage to the progress, finished tasks count as 100%. As long as no task reported a
+/// percentage, the progress is a plain counter without total.
+#[derive(Debug, Default)]
+pub(super) struct ProgressTracker {
+    /// Last percentage of running tasks, keyed by server index and token.
+    tasks: HashMap<(usize, NumberOrString), u32>,
+    done: u32,
+    total: u32,
+    sent: Option<f64>,
+}
+
+impl ProgressTracker {
+    /// Progress and total for the given event of the given server.
+    ///
+    /// Returns `None` if the event does not advance the progress.
+    pub(super) fn track(
+        &mut self,
+        server: usize,
+        evt: &ProgressEvent,
+    ) -> Option<(f64, Option<f64>)> {
+        let key = (server, evt.token.clone());
+        match (evt.phase, evt.percentage) {
+            (Phase::End, _) => {
+                if let Some(last) = self.tasks.remove(&key) {
+                    self.done += 100 - last;
+                }
+            }
+            (Phase::Start | Phase::Report, Some(percentage)) => {
+                let percentage = percentage.min(100);
+                match self.tasks.get_mut(&key) {
+                    Some(last) => {
+                        if percentage > *last {
+                            self.done += percentage - *last;
+                            *last = percentage;
+                        }
+                    }
+                    None => {
+                        self.total += 100;
+                        self.done += percentage;
+                        self.tasks.insert(key, percentage);
+                    }
+                }
+            }
+            (Phase::Start | Phase::Report, None) => {}
+        }
+
+        let (progress, total) = if self.total == 0 {
+            (self.sent.map(|sent| sent + 1.0).unwrap_or_default(), None)
+        } else {
+            (f64::from(self.done), Some(f64::from(self.total)))
+        };
+        if self.sent.is_some_and(|sent| progress <= sent) {
+            return None;
+        }
+        self.sent = Some(progress);
+        Some((progress, total))
+    }
+
+    /// Progress and total for a step that is not a language server task, e.g. a partial result.
+    ///
+    /// Always advances the progress.
+    pub(super) fn step(&mut self) -> (f64, Option<f64>) {
+        let (progress, total) = if self.total == 0 {
+            (self.sent.map(|sent| sent + 1.0).unwrap_or_default(), None)
+        } else {
+            self.done += 1;
+            self.total += 1;
+            (f64::from(self.done), Some(f64::from(self.total)))
+        };
+        let (progress, total) = match self.sent {
+            // counter was ahead before the first task reported a percentage
+            Some(sent) if progress <= sent => (sent + 1.0, None),
+            _ => (progress, total),
+        };
+        self.sent = Some(progress);
+        (progress, total)
+    }
+}
+
+/// Progress notification that was not sent yet.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Notification {
+    progress: f64,
+    total: Option<f64>,
+    message: String,
+}
+
+/// Rate-limits notifications for language server progress.
+///
+/// Notifications within [`MIN_INTERVAL`] of the previous one are held back and only the latest one is sent once the
+/// interval passed, so the progress never goes backwards and the last state is not lost.
+#[derive(Debug, Default)]
+pub(super) struct Throttle {
+    last_sent: Option<Instant>,
+    pending: Option<Notification>,
+}
+
+impl Throttle {
+    /// Notification to send now, `None` if it is held back.
+    pub(super) fn offer(
+        &mut self,
+        now: Instant,
+        notification: Notification,
+    ) -> Option<Notification> {
+        if self
+            .last_sent
+            .is_some_and(|last_sent| now.duration_since(last_sent) < MIN_INTERVAL)
+        {
+            self.pending = Some(notification);
+            return None;
+        }
+        self.sent(now);
+        Some(notification)
+    }
+
+    /// Held back notification, to send it now.
+    pub(super) fn flush(&mut self, now: Instant) -> Option<Notification> {
+        let notification = self.pending.take()?;
+        self.sent(now);
+        Some(notification)
+    }
+
+    /// When the held back notification is due, `None` if there is none.
+    pub(super) fn due(&self) -> Option<Instant> {
+        self.pending.as_ref()?;
+        Some(
+            self.last_sent
+                .map(|last_sent| last_sent + MIN_INTERVAL)
+                .unwrap_or_else(Instant::now),
+        )
+    }
+
+    /// Another notification was sent, e.g. a partial result, which supersedes the held back one.
+    pub(super) fn sent(&mut self, now: Instant) {
+        self.last_sent = Some(now);
+        self.pending = None;
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    tracker: ProgressTracker,
+    throttle: Throttle,
+}
+
+/// Sends progress notifications of a single tool call.
+///
+/// Language server progress and partial results share the progress token, hence they also share the tracker.
+#[derive(Debug, Clone)]
+pub(super) struct ProgressReporter {
+    peer: Peer<RoleServer>,
+    progress_token: ProgressToken,
+
+    /// Held while sending, so notifications never go backwards.
+    state: Arc<Mutex<State>>,
+}
+
+impl ProgressReporter {
+    /// `None` if the MCP client did not ask for progress notifications.
+    pub(super) fn new(ctx: &RequestContext<RoleServer>) -> Option<Self> {
+        Some(Self {
+            peer: ctx.peer.clone(),
+            progress_token: ctx.meta.get_progress_token()?,
+            state: Default::default(),
+        })
+    }
+
+    /// Forward progress event of the language server with the given index, rate-limited.
+    pub(super) async fn event(&self, server: usize, evt: ProgressEvent) {
+        let mut state = self.state.lock().await;
+        let Some((progress, total)) = state.tracker.track(server, &evt) else {
+            return;
+        };
+        let notification = Notification {
+            progress,
+            total,
+            message: evt.message,
+        };
+        if let Some(notification) = state.throttle.offer(Instant::now(), notification) {
+            self.notify(notification).await;
+        }
+    }
+
+    /// When the held back language server progress is due, see [`flush`](Self::flush).
+    pub(super) async fn due(&self) -> Option<Instant> {
+        self.state.lock().await.throttle.due()
+    }
+
+    /// Send held back language server progress, e.g. once the language servers are ready.
+    pub(super) async fn flush(&self) {
+        let mut state = self.state.lock().await;
+        if let Some(notification) = state.throttle.flush(Instant::now()) {
+            self.notify(notification).await;
+        }
+    }
+
+    /// Send part of the result before the tool call is done.
+    ///
+    /// Partial results are never held back.
+    pub(super) async fn partial_result(&self, message: String) {
+        let mut state = self.state.lock().await;
+        let (progress, total) = state.tracker.step();
+        state.throttle.sent(Instant::now());
+        self.notify(Notification {
+            progress,
+            total,
+            message,
+        })
+        .await;
+    }
+
+    async fn notify(
+        &self,
+        Notification {
+            progress,
+            total,
+            message,
+        }: Notification,
+    ) {
+        self.peer
+            .notify_progress(ProgressNotificationParam {
+                progress_token: self.progress_token.clone(),
+                progress,
+                total,
+                message: Some(message),
+            })
+            .await
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn evt(token: i32, phase: Phase, percentage: Option<u32>) -> ProgressEvent {
+        ProgressEvent {
+            token: NumberOrString::Number(token),
+            phase,
+            percentage,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_counter() {
+        let mut tracker = ProgressTracker::default();
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::Start, None)),
+            Some((0.0, None))
+        );
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::Report, None)),
+            Some((1.0, None))
+        );
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::End, None)),
+            Some((2.0, None))
+        );
+    }
+
+    #[test]
+    fn test_percentage() {
+        let mut tracker = ProgressTracker::default();
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::Start, Some(0))),
+            Some((0.0, Some(100.0)))
+        );
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::Report, Some(40))),
+            Some((40.0, Some(100.0)))
+        );
+        // going backwards is ignored
+        assert_eq!(tracker.track(0, &evt(1, Phase::Report, Some(30))), None);
+        // same token of another server is another task
+        assert_eq!(
+            tracker.track(1, &evt(1, Phase::Start, Some(10))),
+            Some((50.0, Some(200.0)))
+        );
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::End, None)),
+            Some((110.0, Some(200.0)))
+        );
+        // tasks without percentage do not advance the progress anymore
+        assert_eq!(tracker.track(0, &evt(2, Phase::Start, None)), None);
+        assert_eq!(
+            tracker.track(1, &evt(1, Phase::Report, Some(150))),
+            Some((200.0, Some(200.0)))
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let mut tracker = ProgressTracker::default();
+        assert_eq!(tracker.step(), (0.0, None));
+        assert_eq!(tracker.step(), (1.0, None));
+
+        // counter is still ahead of the percentages
+        assert_eq!(tracker.track(0, &evt(1, Phase::Start, Some(0))), None);
+        assert_eq!(tracker.step(), (2.0, None));
+
+        assert_eq!(
+            tracker.track(0, &evt(1, Phase::Report, Some(50))),
+            Some((51.0, Some(101.0)))
+        );
+        assert_eq!(tracker.step(), (52.0, Some(102.0)));
+    }
+
+    fn notification(progress: f64) -> Notification {
+        Notification {
+            progress,
+            total: Some(100.0),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_throttle() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut throttle = Throttle::default();
+        assert_eq!(throttle.due(), None);
+
+        assert_eq!(
+            throttle.offer(at(0), notification(1.0)),
+            Some(notification(1.0))
+        );
+        assert_eq!(throttle.offer(at(100), notification(2.0)), None);
+        assert_eq!(throttle.offer(at(200), notification(3.0)), None);
+        assert_eq!(throttle.due(), Some(at(500)));
+
+        // only the latest one is sent
+        assert_eq!(
+            throttle.offer(at(600), notification(4.0)),
+            Some(notification(4.0))
+        );
+        assert_eq!(throttle.due(), None);
+        assert_eq!(throttle.flush(at(700)), None);
+
+        assert_eq!(throttle.offer(at(700), notification(5.0)), None);
+        assert_eq!(throttle.flush(at(800)), Some(notification(5.0)));
+        assert_eq!(throttle.offer(at(900), notification(6.0)), None);
+
+        // e.g. a partial result
+        throttle.sent(at(1000));
+        assert_eq!(throttle.due(), None);
+        assert_eq!(throttle.offer(at(1200), notification(7.0)), None);
+        assert_eq!(throttle.due(), Some(at(1500)));
+    }
+}
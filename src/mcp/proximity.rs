@@ -0,0 +1,95 @@
+//! Order references by how close they are to the symbol, so the nearest usages come first.
+use std::path::Path;
+
+use rmcp::schemars;
+
+use crate::lsp::location::McpLocation;
+
+/// Bucket of a reference, ordered from nearest to farthest.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Proximity {
+    /// same file as the symbol
+    SameFile,
+
+    /// another file in the same directory, e.g. a sibling or parent module
+    SameModule,
+
+    /// elsewhere in the workspace
+    Workspace,
+
+    /// within a dependency
+    Dependency,
+}
+
+impl Proximity {
+    /// Proximity of `file` to the `origin` file, both rendered in the same path style.
+    fn of(origin: &str, file: &str, dependency: bool) -> Self {
+        if dependency || file.contains("://") {
+            Self::Dependency
+        } else if file == origin {
+            Self::SameFile
+        } else if Path::new(file).parent() == Path::new(origin).parent() {
+            Self::SameModule
+        } else {
+            Self::Workspace
+        }
+    }
+}
+
+/// Location of a reference with its proximity bucket.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct Reference {
+    #[serde(flatten)]
+    pub(super) location: McpLocation,
+
+    pub(super) proximity: Proximity,
+}
+
+/// Bucket references relative to the `origin` file and sort them, nearest first.
+///
+/// Within a bucket, references are sorted by file and position.
+pub(super) fn sort(origin: &str, locations: Vec<McpLocation>) -> Vec<Reference> {
+    let mut references = locations
+        .into_iter()
+        .map(|location| Reference {
+            proximity: Proximity::of(origin, &location.file, location.dependency.is_some()),
+            location,
+        })
+        .collect::<Vec<_>>();
+    references.sort_by(|a, b| (a.proximity, &a.location).cmp(&(b.proximity, &b.location)));
+    references
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_proximity() {
+        let origin = "src/mcp/mod.rs";
+        for (file, dependency, expected) in [
+            ("src/mcp/mod.rs", false, Proximity::SameFile),
+            ("src/mcp/hints.rs", false, Proximity::SameModule),
+            ("src/main.rs", false, Proximity::Workspace),
+            ("tests/mcp/mod.rs", false, Proximity::Workspace),
+            (
+                "dep://serde@1.0.200/src/lib.rs",
+                false,
+                Proximity::Dependency,
+            ),
+            ("/opt/jar/Foo.java", true, Proximity::Dependency),
+        ] {
+            assert_eq!(Proximity::of(origin, file, dependency), expected, "{file}");
+        }
+
+        assert_eq!(
+            Proximity::of("lib.rs", "main.rs", false),
+            Proximity::SameModule
+        );
+        assert!(Proximity::SameFile < Proximity::SameModule);
+        assert!(Proximity::Workspace < Proximity::Dependency);
+    }
+}
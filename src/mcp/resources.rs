@@ -0,0 +1,255 @@
+//! Workspace files as MCP resources.
+//!
+//! Clients can subscribe to files and get notified when they change. Changes are detected by polling the file
+//! metadata, which is cheap for the handful of files a client usually subscribes to.
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, SystemTime},
+};
+
+use lsp_types::Uri;
+use rmcp::{
+    Peer, RoleServer,
+    model::{
+        ErrorData as McpError, RawResourceTemplate, ReadResourceResult, ResourceContents,
+        ResourceTemplate, ResourceUpdatedNotificationParam,
+    },
+};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{debug, info};
+
+use super::secrets::SecretsCLIConfig;
+use crate::lsp::{uri::file_uri_to_path, workspace::Workspace};
+
+/// Scheme prefix of resource URIs.
+const URI_PREFIX: &str = "file://";
+
+/// How often subscribed files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// MIME type of file resources.
+const MIME_TYPE: &str = "text/plain";
+
+/// Templates of the resources that are offered.
+pub(super) fn resource_templates() -> Vec<ResourceTemplate> {
+    vec![ResourceTemplate::new(
+        RawResourceTemplate {
+            uri_template: format!("{URI_PREFIX}{{path}}"),
+            name: "file".to_owned(),
+            title: Some("Workspace file".to_owned()),
+            description: Some("File within the workspace, `path` is absolute.".to_owned()),
+            mime_type: Some(MIME_TYPE.to_owned()),
+            icons: None,
+        },
+        None,
+    )]
+}
+
+/// Get path for resource URI.
+///
+/// Only files within the workspace are allowed.
+pub(super) fn uri_to_path(workspace: &Workspace, uri: &str) -> Result<PathBuf, McpError> {
+    let raw = uri
+        .strip_prefix(URI_PREFIX)
+        .ok_or_else(|| McpError::invalid_params(format!("not a file URI: {uri}"), None))?;
+    // clients may also send paths that are not percent-encoded
+    let path = uri
+        .parse::<Uri>()
+        .ok()
+        .and_then(|uri| file_uri_to_path(&uri))
+        .unwrap_or_else(|| PathBuf::from(raw));
+    let path = path.as_path();
+
+    let escapes = path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::CurDir));
+    if !path.is_absolute() || escapes || !workspace.contains(path) {
+        return Err(McpError::invalid_params(
+            format!("resource is not within the workspace: {uri}"),
+            None,
+        ));
+    }
+
+    Ok(path.to_owned())
+}
+
+/// Read resource.
+///
+/// Secrets in the file are redacted, see [`SecretsCLIConfig`].
+pub(super) async fn read(
+    workspace: &Workspace,
+    uri: &str,
+    secrets_cfg: &SecretsCLIConfig,
+) -> Result<ReadResourceResult, McpError> {
+    let path = uri_to_path(workspace, uri)?;
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(McpError::resource_not_found(
+                format!("file not found: {}", path.display()),
+                None,
+            ));
+        }
+        Err(e) => {
+            return Err(McpError::internal_error(
+                format!("cannot read {}: {e}", path.display()),
+                None,
+            ));
+        }
+    };
+
+    Ok(ReadResourceResult::new(vec![
+        ResourceContents::TextResourceContents {
+            uri: uri.to_owned(),
+            mime_type: Some(MIME_TYPE.to_owned()),
+            text: secrets_cfg.redact(&text).into_owned(),
+            meta: None,
+        },
+    ]))
+}
+
+/// Resource subscriptions of a single MCP session.
+#[derive(Debug, Default)]
+pub(super) struct Subscriptions {
+    /// Subscribed files, keyed by URI.
+    files: Arc<Mutex<HashMap<String, Subscription>>>,
+
+    /// Watcher task, started with the first subscription and stopped when the session ends.
+    watcher: OnceLock<DropGuard>,
+}
+
+impl Subscriptions {
+    /// Subscribe to changes of the given file.
+    ///
+    /// The file does not need to exist yet, creating it counts as change.
+    pub(super) async fn subscribe(&self, uri: String, path: PathBuf, peer: &Peer<RoleServer>) {
+        let state = FileState::get(&path).await;
+        info!(uri, "subscribe resource");
+        self.files
+            .lock()
+            .expect("not poisoned")
+            .insert(uri, Subscription { path, state });
+
+        self.watcher.get_or_init(|| {
+            let ct = CancellationToken::new();
+            tokio::spawn(watch(Arc::clone(&self.files), peer.clone(), ct.clone()));
+            ct.drop_guard()
+        });
+    }
+
+    /// Unsubscribe from the given file.
+    pub(super) fn unsubscribe(&self, uri: &str) {
+        info!(uri, "unsubscribe resource");
+        self.files.lock().expect("not poisoned").remove(uri);
+    }
+}
+
+/// Subscribed file.
+#[derive(Debug)]
+struct Subscription {
+    path: PathBuf,
+
+    /// Last known state, `None` if the file does not exist.
+    state: Option<FileState>,
+}
+
+/// File metadata used to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileState {
+    async fn get(path: &Path) -> Option<Self> {
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Poll subscribed files and notify client about changes.
+async fn watch(
+    files: Arc<Mutex<HashMap<String, Subscription>>>,
+    peer: Peer<RoleServer>,
+    ct: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ct.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        let paths = files
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|(uri, sub)| (uri.clone(), sub.path.clone()))
+            .collect::<Vec<_>>();
+
+        for (uri, path) in paths {
+            let state = FileState::get(&path).await;
+            let changed = match files.lock().expect("not poisoned").get_mut(&uri) {
+                Some(sub) if sub.state != state => {
+                    sub.state = state;
+                    true
+                }
+                // unchanged or unsubscribed in the meantime
+                _ => false,
+            };
+            if !changed {
+                continue;
+            }
+
+            debug!(uri, "resource updated");
+            if let Err(e) = peer
+                .notify_resource_updated(ResourceUpdatedNotificationParam::new(uri))
+                .await
+            {
+                debug!(%e, "cannot notify client, stop watching resources");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uri_to_path() {
+        let workspace = &Workspace::new([PathBuf::from("/ws")]).unwrap();
+
+        assert_eq!(
+            uri_to_path(workspace, "file:///ws/src/lib.rs").unwrap(),
+            Path::new("/ws/src/lib.rs"),
+        );
+
+        assert_eq!(
+            uri_to_path(workspace, "file:///ws/my%20src/%C3%A4.rs").unwrap(),
+            Path::new("/ws/my src/ä.rs"),
+        );
+        assert_eq!(
+            uri_to_path(workspace, "file:///ws/my src/ä.rs").unwrap(),
+            Path::new("/ws/my src/ä.rs"),
+        );
+
+        uri_to_path(workspace, "/ws/src/lib.rs").unwrap_err();
+        uri_to_path(workspace, "file:///ws/%2E%2E/etc/passwd").unwrap_err();
+        uri_to_path(workspace, "http:///ws/src/lib.rs").unwrap_err();
+        uri_to_path(workspace, "file://src/lib.rs").unwrap_err();
+        uri_to_path(workspace, "file:///other/src/lib.rs").unwrap_err();
+        uri_to_path(workspace, "file:///ws/../etc/passwd").unwrap_err();
+        uri_to_path(workspace, "file:///wsx/lib.rs").unwrap_err();
+    }
+}
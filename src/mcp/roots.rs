@@ -0,0 +1,323 @@
+//! Derive the workspace from the roots announced by the MCP client.
+//!
+//! This is used when no workspace is configured. The language servers can only be started once the client told us
+//! its roots, so [`RootsExplorer`] serves the session and forwards requests to the [`CodeExplorer`] of the current
+//! workspace as soon as it exists.
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use lsp_types::Uri;
+use rmcp::{
+    Peer, RoleServer, ServerHandler,
+    handler::server::router::tool::ToolRouter,
+    model::{
+        CallToolRequestParams, CallToolResult, CompleteRequestParams, CompleteResult,
+        ErrorData as McpError, ListResourceTemplatesResult, ListToolsResult,
+        PaginatedRequestParams, ReadResourceRequestParams, ReadResourceResult, Root, ServerInfo,
+        SetLevelRequestParams, SubscribeRequestParams, UnsubscribeRequestParams,
+    },
+    service::{NotificationContext, RequestContext},
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::lsp::{uri::file_uri_to_path, workspace::Workspace};
+
+use super::{CodeExplorer, server_info};
+
+/// Explorer of the current workspace.
+#[derive(Debug, Clone, Default)]
+enum Explorer {
+    /// Workspace is not known yet or its language servers are starting.
+    #[default]
+    Pending,
+
+    /// Ready to serve requests.
+    Ready(Arc<CodeExplorer>),
+
+    /// Workspace cannot be derived from the client or its language servers cannot be started.
+    Failed(String),
+}
+
+/// State shared between the MCP session and the code that manages the language servers.
+#[derive(Debug, Clone)]
+pub(crate) struct RootsState {
+    workspace: watch::Sender<Option<Workspace>>,
+    explorer: watch::Sender<Explorer>,
+    peer: Arc<OnceLock<Peer<RoleServer>>>,
+}
+
+impl Default for RootsState {
+    fn default() -> Self {
+        Self {
+            workspace: watch::Sender::new(None),
+            explorer: watch::Sender::new(Explorer::default()),
+            peer: Default::default(),
+        }
+    }
+}
+
+impl RootsState {
+    /// Workspace announced by the client, `None` until it is known.
+    pub(crate) fn workspace(&self) -> watch::Receiver<Option<Workspace>> {
+        self.workspace.subscribe()
+    }
+
+    /// Serve requests with the given explorer.
+    pub(crate) fn set_explorer(&self, explorer: CodeExplorer) {
+        if let Some(peer) = self.peer.get() {
+            explorer.attach(peer);
+        }
+        self.explorer
+            .send_replace(Explorer::Ready(Arc::new(explorer)));
+    }
+
+    /// Hold back requests until [`set_explorer`](Self::set_explorer) is called again.
+    pub(crate) fn reset(&self) {
+        self.explorer.send_replace(Explorer::Pending);
+    }
+
+    /// Fail requests until the workspace changes, e.g. because its language servers cannot be started.
+    pub(crate) fn set_failed(&self, msg: String) {
+        self.explorer.send_replace(Explorer::Failed(msg));
+    }
+
+    /// Fail requests unless a workspace is already known.
+    fn fail(&self, msg: String) {
+        warn!(msg, "cannot derive workspace from roots");
+        self.explorer.send_if_modified(|explorer| match explorer {
+            Explorer::Pending if self.workspace.borrow().is_none() => {
+                *explorer = Explorer::Failed(msg);
+                true
+            }
+            _ => false,
+        });
+    }
+
+    /// Fetch roots from client and update workspace.
+    async fn update(&self, peer: &Peer<RoleServer>) {
+        let supported = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        if !supported {
+            self.fail(
+                "MCP client does not support roots, use `--workspace` to set the workspace"
+                    .to_owned(),
+            );
+            return;
+        }
+
+        #[expect(
+            deprecated,
+            reason = "MCP clients still use roots, there is no replacement yet"
+        )]
+        let roots = peer.list_roots().await;
+        let roots = match roots {
+            Ok(res) => res.roots,
+            Err(e) => {
+                self.fail(format!("cannot list roots of MCP client: {e}"));
+                return;
+            }
+        };
+        let Some(workspace) = pick_workspace(&roots) else {
+            self.fail(
+                "MCP client did not announce any local directory as root, use `--workspace` to set the workspace"
+                    .to_owned(),
+            );
+            return;
+        };
+        let workspace = match workspace
+            .canonicalize()
+            .map_err(anyhow::Error::from)
+            .and_then(|path| Workspace::new([path]))
+        {
+            Ok(workspace) => workspace,
+            Err(e) => {
+                self.fail(format!("cannot canonicalize {}: {e}", workspace.display()));
+                return;
+            }
+        };
+
+        let changed = self.workspace.send_if_modified(|current| {
+            if current.as_ref() == Some(&workspace) {
+                return false;
+            }
+            info!(path=%workspace, "workspace from MCP client roots");
+            *current = Some(workspace);
+            true
+        });
+        if changed {
+            // do not hand out the explorer of the previous workspace while it shuts down
+            self.reset();
+        }
+    }
+}
+
+/// Pick workspace from roots, which is the first local directory.
+fn pick_workspace(roots: &[Root]) -> Option<PathBuf> {
+    let mut candidates = roots.iter().filter_map(|root| {
+        let uri = root.uri.parse::<Uri>().ok()?;
+        file_uri_to_path(&uri)
+    });
+    let picked = candidates.next()?;
+    let ignored = candidates.count();
+    if ignored > 0 {
+        info!(
+            ignored,
+            "MCP client announced multiple roots, only use the first one"
+        );
+    }
+    Some(picked)
+}
+
+/// MCP service that serves the workspace announced by the client.
+#[derive(Debug)]
+pub(crate) struct RootsExplorer {
+    state: RootsState,
+
+    /// Tools of the explorers, known before the workspace is.
+    tool_router: ToolRouter<CodeExplorer>,
+}
+
+impl RootsExplorer {
+    pub(crate) fn new(tool_router: ToolRouter<CodeExplorer>) -> Self {
+        Self {
+            state: RootsState::default(),
+            tool_router,
+        }
+    }
+
+    pub(crate) fn state(&self) -> &RootsState {
+        &self.state
+    }
+
+    /// Wait for explorer of the current workspace.
+    async fn explorer(&self) -> Result<Arc<CodeExplorer>, McpError> {
+        let mut rx = self.state.explorer.subscribe();
+        let explorer = rx
+            .wait_for(|explorer| !matches!(explorer, Explorer::Pending))
+            .await
+            .map_err(|_| McpError::internal_error("shutting down", None))?
+            .clone();
+        match explorer {
+            Explorer::Pending => unreachable!("waited for it"),
+            Explorer::Ready(explorer) => Ok(explorer),
+            Explorer::Failed(msg) => Err(McpError::internal_error(msg, None)),
+        }
+    }
+
+    /// Update workspace in the background.
+    ///
+    /// Listing the roots is a request to the client, which must not block the notification handler.
+    fn update_in_background(&self, peer: Peer<RoleServer>) {
+        let state = self.state.clone();
+        tokio::spawn(async move { state.update(&peer).await });
+    }
+}
+
+impl ServerHandler for RootsExplorer {
+    fn get_info(&self) -> ServerInfo {
+        server_info(&self.tool_router)
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        self.explorer().await?.call_tool(request, context).await
+    }
+
+    async fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        self.explorer().await?.list_tools(request, context).await
+    }
+
+    async fn list_resource_templates(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        self.explorer()
+            .await?
+            .list_resource_templates(request, context)
+            .await
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.explorer().await?.read_resource(request, context).await
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.explorer().await?.subscribe(request, context).await
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.explorer().await?.unsubscribe(request, context).await
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        info!("client initialized");
+        // set before the roots are requested, so it is known to every explorer
+        self.state.peer.set(context.peer.clone()).ok();
+        self.update_in_background(context.peer);
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        info!("roots changed");
+        self.update_in_background(context.peer);
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.explorer().await?.set_level(request, context).await
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        self.explorer().await?.complete(request, context).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pick_workspace() {
+        assert_eq!(pick_workspace(&[]), None);
+        assert_eq!(
+            pick_workspace(&[
+                Root::new("https://example.com/foo"),
+                Root::new("file:///foo/bar"),
+                Root::new("file:///baz"),
+            ]),
+            Some(PathBuf::from("/foo/bar")),
+        );
+        assert_eq!(pick_workspace(&[Root::new("file://relative")]), None);
+        assert_eq!(pick_workspace(&[Root::new("not a URI")]), None);
+    }
+}
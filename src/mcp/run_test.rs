@@ -0,0 +1,566 @@
+//! Run a single test via the runnables of rust-analyzer.
+//!
+//! The language server tells us how to run a test (usually `cargo test` with a filter), we only execute that command.
+use std::{collections::HashMap, fmt, process::Stdio, time::Duration};
+
+use anyhow::{Context, Result};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process::Command,
+};
+use tracing::info;
+
+use crate::lsp::{
+    location::path_to_text_document_identifier,
+    requests::{CargoRunnableArgs, Runnable, Runnables, RunnablesParams},
+    scheduler::Priority,
+};
+
+use super::{CodeExplorer, error::ResultExt, hints, timeout::TimedOut};
+
+/// Maximum number of bytes that are kept of stdout and stderr each.
+///
+/// The end of the output is kept, because that is where test harnesses summarize failures.
+const MAX_OUTPUT_BYTES: usize = 10_000;
+
+/// Labels of the runnables that are tests, e.g. `test tests::my_test`.
+const TEST_LABEL_PREFIXES: &[&str] = &["test ", "test-mod ", "doctest "];
+
+/// Path of the test (or test module) of a runnable, `None` if the runnable is no test.
+pub(super) fn test_path(runnable: &Runnable) -> Option<&str> {
+    TEST_LABEL_PREFIXES
+        .iter()
+        .find_map(|prefix| runnable.label.strip_prefix(prefix))
+}
+
+/// Arguments of a runnable that runs tests via cargo.
+fn cargo_test_args(runnable: &Runnable) -> Option<CargoRunnableArgs> {
+    if runnable.kind != "cargo" || test_path(runnable).is_none() {
+        return None;
+    }
+    let args = serde_json::from_value::<CargoRunnableArgs>(runnable.args.clone()).ok()?;
+    (args.cargo_args.first().map(String::as_str) == Some("test")).then_some(args)
+}
+
+/// Select the test to run.
+///
+/// With a line (0-based), only tests that contain the line are considered. With a name, the test whose path is the
+/// name or ends with `::<name>` is selected, exact matches first. Otherwise the innermost test is selected.
+pub(super) fn select<'a>(
+    runnables: &'a [Runnable],
+    name: Option<&str>,
+    line: Option<u32>,
+) -> Option<(&'a Runnable, CargoRunnableArgs)> {
+    let tests = runnables
+        .iter()
+        .filter(|runnable| match (line, &runnable.location) {
+            (None, _) => true,
+            (Some(line), Some(location)) => {
+                (location.target_range.start.line..=location.target_range.end.line).contains(&line)
+            }
+            (Some(_), None) => false,
+        })
+        .filter_map(|runnable| Some((runnable, cargo_test_args(runnable)?)));
+
+    match name {
+        Some(name) => {
+            let suffix = format!("::{name}");
+            let (exact, suffixed): (Vec<_>, Vec<_>) = tests
+                .filter(|(runnable, _)| {
+                    test_path(runnable).is_some_and(|path| path == name || path.ends_with(&suffix))
+                })
+                .partition(|(runnable, _)| test_path(runnable) == Some(name));
+            exact.into_iter().chain(suffixed).next()
+        }
+        None => tests.max_by_key(|(runnable, _)| {
+            runnable
+                .location
+                .as_ref()
+                .map(|location| location.target_range.start)
+        }),
+    }
+}
+
+/// Paths of all tests, for error messages.
+pub(super) fn test_paths(runnables: &[Runnable]) -> Vec<&str> {
+    runnables
+        .iter()
+        .filter(|runnable| cargo_test_args(runnable).is_some())
+        .filter_map(test_path)
+        .collect()
+}
+
+/// Command that runs a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TestCommand {
+    program: String,
+    args: Vec<String>,
+    cwd: String,
+    env: HashMap<String, String>,
+}
+
+impl TestCommand {
+    pub(super) fn new(args: CargoRunnableArgs) -> Result<Self> {
+        let cwd = args
+            .cwd
+            .or(args.workspace_root)
+            .context("runnable has no working directory")?;
+        let (program, mut cmd_args) = match &args.override_cargo {
+            Some(cargo) => {
+                let mut parts = cargo.split_whitespace().map(ToOwned::to_owned);
+                let program = parts.next().context("empty cargo override")?;
+                (program, parts.collect())
+            }
+            None => ("cargo".to_owned(), vec![]),
+        };
+        cmd_args.extend(args.cargo_args);
+        cmd_args.extend(args.cargo_extra_args);
+        if !args.executable_args.is_empty() {
+            cmd_args.push("--".to_owned());
+            cmd_args.extend(args.executable_args);
+        }
+
+        Ok(Self {
+            program,
+            args: cmd_args,
+            cwd,
+            env: args.environment,
+        })
+    }
+}
+
+impl fmt::Display for TestCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                write!(f, " '{arg}'")?;
+            } else {
+                write!(f, " {arg}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a test command.
+#[derive(Debug)]
+pub(super) struct TestOutput {
+    /// `None` if the command was killed, e.g. by a signal or because of the timeout
+    pub(super) exit_code: Option<i32>,
+    pub(super) success: bool,
+    pub(super) stdout: String,
+    pub(super) stderr: String,
+    pub(super) timed_out: bool,
+}
+
+/// Run the command, kill it if it takes longer than the timeout.
+pub(super) async fn run(cmd: &TestCommand, timeout: Duration) -> Result<TestOutput> {
+    let mut command = Command::new(&cmd.program);
+    command
+        .args(&cmd.args)
+        .current_dir(&cmd.cwd)
+        .envs(&cmd.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // cargo does not pass signals on to the test binary, so we kill the entire group
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("spawn {}", cmd.program))?;
+    let mut stdout_pipe = child.stdout.take().context("stdout is piped")?;
+    let mut stderr_pipe = child.stderr.take().context("stderr is piped")?;
+
+    let mut stdout = Tail::new(MAX_OUTPUT_BYTES);
+    let mut stderr = Tail::new(MAX_OUTPUT_BYTES);
+    let status = tokio::time::timeout(timeout, async {
+        let (status, (), ()) = tokio::join!(
+            child.wait(),
+            read(&mut stdout_pipe, &mut stdout),
+            read(&mut stderr_pipe, &mut stderr),
+        );
+        status
+    })
+    .await;
+
+    let status = match status {
+        Ok(status) => Some(status.context("wait for test command")?),
+        Err(_) => {
+            kill(&mut child).await;
+            None
+        }
+    };
+
+    Ok(TestOutput {
+        exit_code: status.and_then(|status| status.code()),
+        success: status.is_some_and(|status| status.success()),
+        stdout: stdout.finish(),
+        stderr: stderr.finish(),
+        timed_out: status.is_none(),
+    })
+}
+
+/// Kill the process and everything it spawned.
+async fn kill(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        Command::new("kill")
+            .args(["-KILL", "--", &format!("-{pid}")])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .ok();
+    }
+    child.kill().await.ok();
+}
+
+/// Read pipe until it is closed.
+async fn read(pipe: &mut (impl AsyncRead + Unpin), tail: &mut Tail) {
+    let mut buf = [0; 8192];
+    while let Ok(n @ 1..) = pipe.read(&mut buf).await {
+        tail.push(&buf[..n]);
+    }
+}
+
+/// End of an output, at most `cap` bytes.
+#[derive(Debug)]
+struct Tail {
+    cap: usize,
+    bytes: Vec<u8>,
+    omitted: usize,
+}
+
+impl Tail {
+    fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            bytes: vec![],
+            omitted: 0,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.bytes.extend_from_slice(data);
+        // shrink in batches, so that the buffer is not shifted for every chunk
+        if self.bytes.len() > 2 * self.cap {
+            self.shrink();
+        }
+    }
+
+    fn shrink(&mut self) {
+        let excess = self.bytes.len().saturating_sub(self.cap);
+        self.bytes.drain(..excess);
+        self.omitted += excess;
+    }
+
+    fn finish(mut self) -> String {
+        self.shrink();
+        let text = String::from_utf8_lossy(&self.bytes);
+        if self.omitted > 0 {
+            format!("[{} bytes omitted]\n{text}", self.omitted)
+        } else {
+            text.into_owned()
+        }
+    }
+}
+
+#[tool_router(router = run_test_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Run Test",
+        description = "Run a single test and get its output, e.g. to check whether a fix works: selects the test in the given file by name and/or line, runs it the way the language server suggests (e.g. `cargo test` for rust-analyzer), and returns the exit code and the end of stdout and stderr.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = true
+        )
+    )]
+    async fn run_test(
+        &self,
+        Parameters(RunTestRequest {
+            file,
+            name,
+            line,
+            timeout_secs,
+        }): Parameters<RunTestRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<RunTestResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if !server.quirks().supports_runnables() {
+            return Err(McpError::invalid_params(
+                format!("{} does not support running tests", server.name()),
+                None,
+            ));
+        }
+        if name.is_none() && line.is_none() {
+            return Err(McpError::invalid_params(
+                "either `name` or `line` is required",
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(RunTestResult::timed_out()));
+        };
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        if let Some(line) = line {
+            let lines = file_content.lines().count();
+            if line as usize > lines {
+                return Err(hints::position_drifted(&file, line, lines));
+            }
+        }
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+        let Some(runnables) = deadline
+            .run(client.send_request::<Runnables>(RunnablesParams {
+                text_document,
+                position: None,
+            }))
+            .await
+        else {
+            return Ok(Json(RunTestResult::timed_out()));
+        };
+        let runnables = runnables.context("Runnables").internal()?;
+
+        let Some((runnable, args)) = select(&runnables, name.as_deref(), line.map(|line| line - 1))
+        else {
+            let symbol = match (&name, line) {
+                (Some(name), _) => format!("`{name}` in {file}"),
+                (None, line) => format!("{file}:{}", line.unwrap_or_default()),
+            };
+            return Err(hints::test_not_found(&symbol, &test_paths(&runnables)));
+        };
+        let command = TestCommand::new(args)
+            .context("build test command")
+            .internal()?;
+        info!(%command, "run test");
+        let output = run(&command, self.tools_cfg.run_test_timeout)
+            .await
+            .context("run test")
+            .internal()?;
+
+        Ok(Json(RunTestResult {
+            test: runnable.label.clone(),
+            command: command.to_string(),
+            exit_code: output.exit_code,
+            success: output.success,
+            stdout: output.stdout,
+            stderr: output.stderr,
+            timed_out: output.timed_out,
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct RunTestRequest {
+    /// path to the file that contains the test, can be absolute or relative
+    file: String,
+
+    /// name of the test or test module, optionally with its module path like `tests::my_test`
+    name: Option<String>,
+
+    /// 1-based line number within the test, selects the innermost test at this line
+    #[schemars(range(min = 1))]
+    line: Option<u32>,
+
+    /// seconds to wait for the language server, overrides the server default; the test itself is limited by the
+    /// server config
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct RunTestResult {
+    /// label of the test, e.g. `test tests::my_test`
+    test: String,
+
+    /// command line that ran the test
+    command: String,
+
+    /// exit code of the command, not set if it was killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+
+    /// the test passed
+    success: bool,
+
+    /// end of the standard output
+    stdout: String,
+
+    /// end of the standard error
+    stderr: String,
+
+    /// the timeout was hit, the test was killed or did not run at all
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+}
+
+impl TimedOut for RunTestResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{LocationLink, Position, Range, Uri};
+    use serde_json::json;
+
+    use super::*;
+
+    fn runnable(label: &str, lines: Option<(u32, u32)>, cargo_args: &[&str]) -> Runnable {
+        Runnable {
+            label: label.to_owned(),
+            location: lines.map(|(start, end)| LocationLink {
+                origin_selection_range: None,
+                target_uri: "file:///lib.rs".parse::<Uri>().unwrap(),
+                target_range: Range::new(Position::new(start, 0), Position::new(end, 1)),
+                target_selection_range: Range::new(
+                    Position::new(start, 3),
+                    Position::new(start, 4),
+                ),
+            }),
+            kind: "cargo".to_owned(),
+            args: json!({
+                "cwd": "/ws",
+                "cargoArgs": cargo_args,
+                "executableArgs": [label.split_once(' ').unwrap().1, "--exact"],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let runnables = [
+            runnable("cargo test -p ws --all-targets", None, &["test"]),
+            runnable("test-mod tests", Some((10, 25)), &["test"]),
+            runnable("test tests::a", Some((12, 14)), &["test"]),
+            runnable("test tests::nested::a", Some((20, 22)), &["test"]),
+            runnable("test-mod other", Some((28, 35)), &["test"]),
+            runnable("test a", Some((30, 32)), &["test"]),
+            runnable("bench tests::b", Some((40, 42)), &["bench"]),
+            runnable("run main", Some((1, 3)), &["run"]),
+        ];
+        let label = |name, line| {
+            select(&runnables, name, line).map(|(runnable, _)| runnable.label.as_str())
+        };
+
+        assert_eq!(label(Some("a"), None), Some("test a"));
+        assert_eq!(label(Some("a"), Some(13)), Some("test tests::a"));
+        assert_eq!(
+            label(Some("nested::a"), None),
+            Some("test tests::nested::a")
+        );
+        assert_eq!(label(Some("tests"), None), Some("test-mod tests"));
+        assert_eq!(label(Some("b"), None), None);
+        assert_eq!(label(Some("main"), None), None);
+        assert_eq!(label(None, Some(21)), Some("test tests::nested::a"));
+        assert_eq!(label(None, Some(24)), Some("test-mod tests"));
+        assert_eq!(label(None, Some(30)), Some("test a"));
+        assert_eq!(label(None, Some(41)), None);
+
+        assert_eq!(
+            test_paths(&runnables),
+            ["tests", "tests::a", "tests::nested::a", "other", "a"]
+        );
+    }
+
+    #[test]
+    fn test_command() {
+        let cmd = TestCommand::new(CargoRunnableArgs {
+            cwd: None,
+            workspace_root: Some("/ws".to_owned()),
+            override_cargo: Some("cross +nightly".to_owned()),
+            cargo_args: vec!["test".to_owned(), "--lib".to_owned()],
+            cargo_extra_args: vec!["--features=a b".to_owned()],
+            executable_args: vec!["tests::a".to_owned(), "--exact".to_owned()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(cmd.cwd, "/ws");
+        assert_eq!(
+            cmd.to_string(),
+            "cross +nightly test --lib '--features=a b' -- tests::a --exact"
+        );
+
+        let cmd = TestCommand::new(CargoRunnableArgs {
+            cwd: Some("/ws/member".to_owned()),
+            cargo_args: vec!["test".to_owned()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(cmd.cwd, "/ws/member");
+        assert_eq!(cmd.to_string(), "cargo test");
+
+        TestCommand::new(CargoRunnableArgs::default()).unwrap_err();
+    }
+
+    #[test]
+    fn test_tail() {
+        let mut tail = Tail::new(4);
+        tail.push(b"ab");
+        assert_eq!(Tail::new(4).finish(), "");
+
+        tail.push(b"cdefghij");
+        tail.push(b"k");
+        assert_eq!(tail.finish(), "[7 bytes omitted]\nhijk");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run() {
+        let cmd = |script: &str| TestCommand {
+            program: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            cwd: std::env::temp_dir().display().to_string(),
+            env: HashMap::from([("GREETING".to_owned(), "hello".to_owned())]),
+        };
+
+        let output = run(
+            &cmd("echo $GREETING; echo oops >&2; exit 3"),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success);
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert!(!output.timed_out);
+
+        // the grandchild keeps the pipes open, so it must be killed as well
+        let output = run(
+            &cmd("echo started; sleep 60 & wait"),
+            Duration::from_millis(500),
+        )
+        .await
+        .unwrap();
+        assert_eq!(output.exit_code, None);
+        assert!(!output.success);
+        assert_eq!(output.stdout, "started\n");
+        assert!(output.timed_out);
+    }
+}
@@ -0,0 +1,424 @@
+//! Export the diagnostics of all workspace files as a [SARIF 2.1.0] report, without an MCP client.
+//!
+//! All workspace files are opened in their language server, so it computes their diagnostics, e.g. rust-analyzer
+//! only reports its native diagnostics for open files. Once no more diagnostics are published, they are converted into
+//! one SARIF run per language server, which code scanning UIs in CI can show.
+//!
+//! [SARIF 2.1.0]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    cli::parse_secs,
+    lsp::{
+        location::path_to_uri,
+        server::LanguageServer,
+        uri::{file_uri_to_path, path_to_file_uri},
+        workspace::{SharedWorkspace, Workspace},
+    },
+};
+
+use super::{
+    CodeExplorer, McpError, error::ResultExt, export::workspace_files, tools::ToolsCLIConfig,
+};
+
+/// Upper limit for waiting until the language servers stop publishing diagnostics.
+const MAX_SETTLE: Duration = Duration::from_secs(300);
+
+/// Name of the base URI that file locations are relative to.
+const SRCROOT: &str = "SRCROOT";
+
+/// Export the diagnostics (errors, warnings) of all workspace files as SARIF 2.1.0 report and exit.
+///
+/// Waits for the language servers to be ready and to finish their checks (e.g. `cargo check`).
+#[derive(Debug, Clone, clap::Parser)]
+pub(crate) struct ExportDiagnosticsCLIConfig {
+    /// File to write the report to, defaults to stdout.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Seconds without new diagnostics after which the language servers are considered done.
+    #[clap(long, default_value = "2", value_parser = parse_secs)]
+    settle_secs: Duration,
+}
+
+impl ExportDiagnosticsCLIConfig {
+    /// Collect diagnostics of all workspace files and write the report.
+    pub(crate) async fn run(
+        &self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+        ct: CancellationToken,
+    ) -> Result<()> {
+        let explorer = CodeExplorer::new(servers, workspace, tools_cfg);
+        let workspace = explorer.workspace();
+        let files = tokio::task::spawn_blocking(move || workspace_files(&workspace))
+            .await
+            .context("join listing")??;
+
+        for server in explorer.servers.iter() {
+            server.progress_guard().wait().await;
+        }
+        for file in &files {
+            if ct.is_cancelled() {
+                return Ok(());
+            }
+            if let Err(e) = explorer.open_file(file).await {
+                warn!(file, %e, "cannot open file");
+            }
+        }
+        let quiet = self.settle_secs;
+        let settled = tokio::time::timeout(
+            MAX_SETTLE,
+            futures::future::join_all(
+                explorer
+                    .servers
+                    .iter()
+                    .map(|server| server.diagnostics().settle(quiet)),
+            ),
+        )
+        .await;
+        if settled.is_err() {
+            warn!("language servers did not stop publishing diagnostics, report what we have");
+        }
+
+        let report = report(&explorer.servers, &explorer.workspace()).await;
+        info!(
+            results = report
+                .runs
+                .iter()
+                .map(|run| run.results.len())
+                .sum::<usize>(),
+            "exported diagnostics",
+        );
+        let mut s = serde_json::to_string_pretty(&report).context("serialize report")?;
+        s.push('\n');
+        match &self.output {
+            Some(path) => tokio::fs::write(path, s)
+                .await
+                .with_context(|| format!("write report: {}", path.display())),
+            None => {
+                let mut stdout = tokio::io::stdout();
+                stdout
+                    .write_all(s.as_bytes())
+                    .await
+                    .context("write report")?;
+                stdout.flush().await.context("flush report")
+            }
+        }
+    }
+}
+
+impl CodeExplorer {
+    /// Open file in its language server, so it computes the diagnostics.
+    async fn open_file(&self, file: &str) -> Result<(), McpError> {
+        let Ok(server) = self.server_for_file(file) else {
+            return Ok(());
+        };
+        let Some(content) = self.read_file(file).await? else {
+            return Ok(());
+        };
+        let uri = path_to_uri(&self.workspace(), file)
+            .context("convert path to URI")
+            .internal()?;
+        server
+            .documents()
+            .open(&uri, &content)
+            .await
+            .context("open document")
+            .internal()
+    }
+}
+
+/// SARIF report, limited to what we fill in.
+#[derive(Debug, serde::Serialize)]
+struct Report {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    original_uri_base_ids: BTreeMap<&'static str, ArtifactLocation>,
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Driver {
+    name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    level: &'static str,
+    message: Message,
+    locations: Vec<ResultLocation>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+/// 1-based lines and columns, the end column is exclusive.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: u32,
+    start_column: u32,
+    end_line: u32,
+    end_column: u32,
+}
+
+/// Report of the current diagnostics of all language servers, files outside the workspace are skipped.
+async fn report(servers: &[LanguageServer], workspace: &Workspace) -> Report {
+    let root = path_to_file_uri(workspace.root())
+        .map(|uri| format!("{}/", uri.as_str().trim_end_matches('/')))
+        .ok();
+    let mut contents = HashMap::new();
+
+    let mut runs = vec![];
+    for server in servers {
+        let mut rules = BTreeMap::new();
+        let mut results = vec![];
+        for (uri, diagnostics) in server.diagnostics().snapshot() {
+            let Some(path) = file_uri_to_path(&uri) else {
+                continue;
+            };
+            let Some(file) = workspace
+                .relativize(&path)
+                .filter(|_| workspace.allows(&path))
+            else {
+                continue;
+            };
+            if !contents.contains_key(&file) {
+                // positions are UTF-8 offsets, but SARIF counts characters
+                let content = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+                contents.insert(file.clone(), content);
+            }
+            let content = &contents[&file];
+
+            for diagnostic in diagnostics {
+                let rule_id = rule_id(&diagnostic);
+                rules.entry(rule_id.clone()).or_insert_with(|| Rule {
+                    id: rule_id.clone(),
+                    help_uri: diagnostic
+                        .code_description
+                        .as_ref()
+                        .map(|description| description.href.as_str().to_owned()),
+                });
+                results.push(SarifResult {
+                    rule_id,
+                    level: level(diagnostic.severity),
+                    message: Message {
+                        text: diagnostic.message,
+                    },
+                    locations: vec![ResultLocation {
+                        physical_location: PhysicalLocation {
+                            artifact_location: ArtifactLocation {
+                                uri: file.clone(),
+                                uri_base_id: root.is_some().then_some(SRCROOT),
+                            },
+                            region: region(content, diagnostic.range.start, diagnostic.range.end),
+                        },
+                    }],
+                });
+            }
+        }
+        // language servers publish in any order
+        results.sort_by(|a, b| {
+            let location = |r: &SarifResult| {
+                let location = &r.locations[0].physical_location;
+                (
+                    location.artifact_location.uri.clone(),
+                    location.region.start_line,
+                    location.region.start_column,
+                )
+            };
+            location(a)
+                .cmp(&location(b))
+                .then_with(|| a.rule_id.cmp(&b.rule_id))
+                .then_with(|| a.message.text.cmp(&b.message.text))
+        });
+
+        runs.push(Run {
+            tool: Tool {
+                driver: Driver {
+                    name: server
+                        .server_info()
+                        .map(|info| info.name.clone())
+                        .unwrap_or_else(|| server.name().to_owned()),
+                    version: server.server_info().and_then(|info| info.version.clone()),
+                    rules: rules.into_values().collect(),
+                },
+            },
+            original_uri_base_ids: root
+                .iter()
+                .map(|root| {
+                    (
+                        SRCROOT,
+                        ArtifactLocation {
+                            uri: root.clone(),
+                            uri_base_id: None,
+                        },
+                    )
+                })
+                .collect(),
+            column_kind: "unicodeCodePoints",
+            results,
+        });
+    }
+
+    Report {
+        schema: "https://json.schemastore.org/sarif-2.1.0.json",
+        version: "2.1.0",
+        runs,
+    }
+}
+
+/// Rule of a diagnostic, e.g. `E0308` or `unused_variables`, falls back to its source.
+fn rule_id(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code.clone(),
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        None => diagnostic
+            .source
+            .clone()
+            .unwrap_or_else(|| "diagnostic".to_owned()),
+    }
+}
+
+fn level(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::INFORMATION | DiagnosticSeverity::HINT) => "note",
+        // the language server did not say, so it should not go unnoticed
+        _ => "warning",
+    }
+}
+
+fn region(content: &str, start: Position, end: Position) -> Region {
+    Region {
+        start_line: start.line + 1,
+        start_column: column(content, start) + 1,
+        end_line: end.line + 1,
+        end_column: column(content, end) + 1,
+    }
+}
+
+/// Convert UTF-8 offset within the line into a character offset.
+fn column(content: &str, pos: Position) -> u32 {
+    content
+        .split('\n')
+        .nth(pos.line as usize)
+        .and_then(|line| line.get(..pos.character as usize))
+        .map(|prefix| prefix.chars().count() as u32)
+        .unwrap_or(pos.character)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_region() {
+        let content = "fn main() {\n    let ä = \"ö\";\n}\n";
+        // `"ö"`, `ä` and `ö` take two bytes each
+        assert_eq!(
+            region(content, Position::new(1, 13), Position::new(1, 17)),
+            Region {
+                start_line: 2,
+                start_column: 13,
+                end_line: 2,
+                end_column: 16,
+            }
+        );
+        // past the end of the content
+        assert_eq!(
+            region(content, Position::new(5, 2), Position::new(5, 3)),
+            Region {
+                start_line: 6,
+                start_column: 3,
+                end_line: 6,
+                end_column: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_rule_id() {
+        let diagnostic = Diagnostic {
+            code: Some(NumberOrString::String("E0308".to_owned())),
+            source: Some("rustc".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(rule_id(&diagnostic), "E0308");
+        assert_eq!(
+            rule_id(&Diagnostic {
+                code: None,
+                ..diagnostic
+            }),
+            "rustc"
+        );
+        assert_eq!(rule_id(&Diagnostic::default()), "diagnostic");
+    }
+}
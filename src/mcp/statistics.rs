@@ -0,0 +1,152 @@
+//! Size of the project, per language and per package.
+//!
+//! Packages are the directories that contain a package manifest (see
+//! [`is_package_manifest`](crate::lang::ProgrammingLanguageQuirks::is_package_manifest)). Files belong to the innermost
+//! package of their language.
+use std::{collections::BTreeMap, path::Path};
+
+use lsp_types::SymbolKind;
+use rmcp::schemars;
+
+/// Counts of a set of files.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct Counts {
+    files: usize,
+    lines: usize,
+
+    /// number of symbols per kind, e.g. `Function` or `Struct`
+    symbols: BTreeMap<String, usize>,
+}
+
+impl Counts {
+    fn add(&mut self, lines: usize, symbols: &[SymbolKind]) {
+        self.files += 1;
+        self.lines += lines;
+        for kind in symbols {
+            *self.symbols.entry(format!("{kind:?}")).or_default() += 1;
+        }
+    }
+}
+
+/// Counts of a single package.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub(super) struct PackageCounts {
+    /// directory of the package, `.` for the workspace root
+    path: String,
+
+    language: String,
+
+    #[serde(flatten)]
+    counts: Counts,
+}
+
+/// Collects counts file by file.
+#[derive(Debug, Default)]
+pub(super) struct Statistics {
+    languages: BTreeMap<String, Counts>,
+    packages: BTreeMap<(String, String), Counts>,
+}
+
+impl Statistics {
+    pub(super) fn add(
+        &mut self,
+        language: &str,
+        package: Option<&str>,
+        lines: usize,
+        symbols: &[SymbolKind],
+    ) {
+        self.languages
+            .entry(language.to_owned())
+            .or_default()
+            .add(lines, symbols);
+        if let Some(package) = package {
+            self.packages
+                .entry((package.to_owned(), language.to_owned()))
+                .or_default()
+                .add(lines, symbols);
+        }
+    }
+
+    /// Counts per language and per package, largest packages first.
+    pub(super) fn finish(self) -> (BTreeMap<String, Counts>, Vec<PackageCounts>) {
+        let mut packages = self
+            .packages
+            .into_iter()
+            .map(|((path, language), counts)| PackageCounts {
+                path: if path.is_empty() {
+                    ".".to_owned()
+                } else {
+                    path
+                },
+                language,
+                counts,
+            })
+            .collect::<Vec<_>>();
+        packages.sort_by(|a, b| {
+            b.counts
+                .lines
+                .cmp(&a.counts.lines)
+                .then(a.path.cmp(&b.path))
+        });
+        (self.languages, packages)
+    }
+}
+
+/// Innermost package directory that contains the file.
+pub(super) fn package_of<'a>(file: &str, packages: &'a [String]) -> Option<&'a str> {
+    packages
+        .iter()
+        .filter(|dir| Path::new(file).starts_with(dir))
+        .max_by_key(|dir| dir.len())
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_package_of() {
+        let packages = ["".to_owned(), "crates/a".to_owned(), "crates/ab".to_owned()];
+        assert_eq!(package_of("src/lib.rs", &packages), Some(""));
+        assert_eq!(
+            package_of("crates/a/src/lib.rs", &packages),
+            Some("crates/a")
+        );
+        assert_eq!(package_of("crates/ab/lib.rs", &packages), Some("crates/ab"));
+        assert_eq!(package_of("crates/abc/lib.rs", &packages), Some(""));
+        assert_eq!(package_of("src/lib.rs", &packages[1..]), None);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut statistics = Statistics::default();
+        statistics.add(
+            "rust",
+            Some(""),
+            10,
+            &[SymbolKind::FUNCTION, SymbolKind::STRUCT],
+        );
+        statistics.add("rust", Some("member"), 20, &[SymbolKind::FUNCTION]);
+        statistics.add("rust", Some(""), 5, &[SymbolKind::FUNCTION]);
+        statistics.add("toml", None, 3, &[]);
+
+        let (languages, packages) = statistics.finish();
+        assert_eq!(
+            languages["rust"],
+            Counts {
+                files: 3,
+                lines: 35,
+                symbols: BTreeMap::from([("Function".to_owned(), 3), ("Struct".to_owned(), 1)]),
+            }
+        );
+        assert_eq!(languages["toml"].files, 1);
+        assert_eq!(
+            packages
+                .iter()
+                .map(|p| (p.path.as_str(), p.counts.lines))
+                .collect::<Vec<_>>(),
+            [("member", 20), (".", 15)]
+        );
+    }
+}
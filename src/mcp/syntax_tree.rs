@@ -0,0 +1,343 @@
+//! Item tree and syntax tree of rust-analyzer, the latter rendered in its classic indented text format.
+use std::fmt::Write;
+
+use anyhow::Context;
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    location::path_to_text_document_identifier,
+    requests::{SyntaxElement, SyntaxNode, ViewItemTree, ViewSyntaxTree, ViewTreeParams},
+    scheduler::Priority,
+};
+
+use super::{CodeExplorer, error::ResultExt, hints, timeout::TimedOut};
+
+/// Render the tree, one element per line, e.g. `FN@0..47` or `IDENT@14..27 "main"`.
+///
+/// Only elements that overlap the 0-based, inclusive `lines` are rendered, so the ancestors of a line are kept. Token
+/// texts are taken from `content`.
+pub(super) fn render(root: &SyntaxNode, content: &str, lines: Option<(u32, u32)>) -> String {
+    let mut out = String::new();
+    render_node(root, content, lines, 0, &mut out);
+    out
+}
+
+fn render_node(
+    node: &SyntaxNode,
+    content: &str,
+    lines: Option<(u32, u32)>,
+    depth: usize,
+    out: &mut String,
+) {
+    if let Some((first, last)) = lines {
+        let (start, end) = (node.start[1], node.end[1]);
+        // an element that ends at the start of a line does not cover it
+        let end = if node.end[2] == 0 && end > start {
+            end - 1
+        } else {
+            end
+        };
+        if end < first || start > last {
+            return;
+        }
+    }
+
+    let range = node.start[0]..node.end[0];
+    write!(
+        out,
+        "{:indent$}{}@{:?}",
+        "",
+        node.kind,
+        range,
+        indent = depth * 2
+    )
+    .expect("write to string");
+    if node.element == SyntaxElement::Token
+        && let Some(text) = content.get(range.start as usize..range.end as usize)
+    {
+        write!(out, " {text:?}").expect("write to string");
+    }
+    out.push('\n');
+
+    for child in &node.children {
+        render_node(child, content, lines, depth + 1, out);
+    }
+}
+
+#[tool_router(router = syntax_tree_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "View Item Tree",
+        description = "Show the item tree of a Rust file as indexed by rust-analyzer: items with their visibility, and macro calls with what they expand to (e.g. items). For debugging why symbol queries behave unexpectedly, e.g. for macro-generated items.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn view_item_tree(
+        &self,
+        Parameters(ViewItemTreeRequest { file, timeout_secs }): Parameters<ViewItemTreeRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<ViewItemTreeResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if !server.quirks().supports_syntax_trees() {
+            return Err(McpError::invalid_params(
+                format!("{} does not support item trees", server.name()),
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(ViewItemTreeResult::timed_out()));
+        };
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        let Some(resp) = deadline
+            .run(client.send_request::<ViewItemTree>(ViewTreeParams { text_document }))
+            .await
+        else {
+            return Ok(Json(ViewItemTreeResult::timed_out()));
+        };
+        let item_tree = resp.context("ViewItemTree").internal()?;
+
+        Ok(Json(ViewItemTreeResult {
+            item_tree,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+
+    #[tool(
+        title = "View Syntax Tree",
+        description = "Show the syntax tree of a Rust file as parsed by rust-analyzer, one node or token per line with its byte range, optionally limited to a range of lines. For debugging how code is parsed, e.g. macro invocations or code that symbol queries do not find.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn view_syntax_tree(
+        &self,
+        Parameters(ViewSyntaxTreeRequest {
+            file,
+            start_line,
+            end_line,
+            timeout_secs,
+        }): Parameters<ViewSyntaxTreeRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<ViewSyntaxTreeResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if !server.quirks().supports_syntax_trees() {
+            return Err(McpError::invalid_params(
+                format!("{} does not support syntax trees", server.name()),
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(ViewSyntaxTreeResult::timed_out()));
+        };
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        let lines = file_content.lines().count();
+        let lines = match (start_line, end_line) {
+            (None, None) => None,
+            (start_line, end_line) => {
+                let start_line = start_line.unwrap_or(1);
+                let end_line = end_line.unwrap_or(lines.max(1) as u32);
+                if start_line > end_line {
+                    return Err(McpError::invalid_params(
+                        format!("start_line {start_line} is after end_line {end_line}"),
+                        None,
+                    ));
+                }
+                if start_line as usize > lines.max(1) {
+                    return Err(hints::position_drifted(&file, start_line, lines));
+                }
+                // 1-based => 0-based
+                Some((start_line - 1, end_line - 1))
+            }
+        };
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        let Some(resp) = deadline
+            .run(client.send_request::<ViewSyntaxTree>(ViewTreeParams { text_document }))
+            .await
+        else {
+            return Ok(Json(ViewSyntaxTreeResult::timed_out()));
+        };
+        let json = resp.context("ViewSyntaxTree").internal()?;
+        let root = serde_json::from_str::<SyntaxNode>(&json)
+            .context("parse syntax tree")
+            .internal()?;
+
+        Ok(Json(ViewSyntaxTreeResult {
+            syntax_tree: render(&root, &file_content, lines),
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ViewItemTreeRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct ViewItemTreeResult {
+    /// item tree in a Rust-like format, each item preceded by a comment with its AST ID
+    item_tree: String,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for ViewItemTreeResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ViewSyntaxTreeRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// only show nodes that overlap this or later lines, 1-based, defaults to the first line
+    #[schemars(range(min = 1))]
+    start_line: Option<u32>,
+
+    /// only show nodes that overlap this or earlier lines, 1-based and inclusive, defaults to the last line
+    #[schemars(range(min = 1))]
+    end_line: Option<u32>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct ViewSyntaxTreeResult {
+    /// one node or token per line, indented by depth, e.g. `FN@0..47` or `IDENT@14..27 "main"` (byte ranges)
+    syntax_tree: String,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for ViewSyntaxTreeResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONTENT: &str = "fn a() {}\nfn b() {\n    1\n}\n";
+
+    fn tree() -> SyntaxNode {
+        let json = r#"{"type":"Node","kind":"SOURCE_FILE","start":[0,0,0],"end":[27,4,0],"children":[
+            {"type":"Node","kind":"FN","start":[0,0,0],"end":[9,0,9],"children":[
+                {"type":"Token","kind":"FN_KW","start":[0,0,0],"end":[2,0,2]}
+            ]},
+            {"type":"Token","kind":"WHITESPACE","start":[9,0,9],"end":[10,1,0]},
+            {"type":"Node","kind":"FN","start":[10,1,0],"end":[26,3,1],"children":[
+                {"type":"Token","kind":"FN_KW","start":[10,1,0],"end":[12,1,2]},
+                {"type":"Node","kind":"LITERAL","start":[23,2,4],"end":[24,2,5],"children":[
+                    {"type":"Token","kind":"INT_NUMBER","start":[23,2,4],"end":[24,2,5]}
+                ]}
+            ]},
+            {"type":"Token","kind":"WHITESPACE","start":[26,3,1],"end":[27,4,0]}
+        ]}"#;
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(&tree(), CONTENT, None),
+            r#"SOURCE_FILE@0..27
+  FN@0..9
+    FN_KW@0..2 "fn"
+  WHITESPACE@9..10 "\n"
+  FN@10..26
+    FN_KW@10..12 "fn"
+    LITERAL@23..24
+      INT_NUMBER@23..24 "1"
+  WHITESPACE@26..27 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_lines() {
+        assert_eq!(
+            render(&tree(), CONTENT, Some((2, 2))),
+            r#"SOURCE_FILE@0..27
+  FN@10..26
+    LITERAL@23..24
+      INT_NUMBER@23..24 "1"
+"#
+        );
+        assert_eq!(
+            render(&tree(), CONTENT, Some((0, 0))),
+            r#"SOURCE_FILE@0..27
+  FN@0..9
+    FN_KW@0..2 "fn"
+  WHITESPACE@9..10 "\n"
+"#
+        );
+    }
+}
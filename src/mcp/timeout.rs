@@ -0,0 +1,147 @@
+//! Deadlines for tool calls.
+//!
+//! A slow language server should not block a tool call forever. Once the deadline is hit, the tool returns what it
+//! gathered so far and marks the result as timed out.
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use clap::Parser;
+use rmcp::model::ErrorData as McpError;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::cli::parse_secs;
+
+/// CLI config for tool call timeouts.
+#[derive(Debug, Default, Clone, Copy, Parser)]
+pub(crate) struct TimeoutCLIConfig {
+    /// Seconds after which a tool call returns partial results.
+    ///
+    /// This includes waiting for the language servers to finish indexing. Tool calls can override it via the
+    /// `timeout_secs` argument. Without a timeout, tool calls wait for the language servers indefinitely.
+    #[clap(long, value_parser = parse_secs, env = "COMMON_SENSE_CODER_TOOL_TIMEOUT")]
+    tool_timeout: Option<Duration>,
+}
+
+impl TimeoutCLIConfig {
+    /// Start deadline for a tool call, optionally overridden by the call itself.
+    ///
+    /// Fails for negative, non-finite, or too large timeouts of the call.
+    pub(super) fn deadline(&self, timeout_secs: Option<f64>) -> Result<Deadline, McpError> {
+        let timeout = match timeout_secs {
+            Some(secs) => Some(Duration::try_from_secs_f64(secs).map_err(|e| {
+                McpError::invalid_params(
+                    format!(
+                        "invalid `timeout_secs` {secs}: {e}, use a non-negative number of seconds"
+                    ),
+                    None,
+                )
+            })?),
+            None => self.tool_timeout,
+        };
+        Ok(Deadline {
+            at: timeout.and_then(|timeout| Instant::now().checked_add(timeout)),
+            hit: AtomicBool::new(false),
+        })
+    }
+}
+
+/// Deadline of a single tool call.
+#[derive(Debug)]
+pub(super) struct Deadline {
+    at: Option<Instant>,
+    hit: AtomicBool,
+}
+
+impl Deadline {
+    /// Run future until the deadline.
+    ///
+    /// Returns `None` if the deadline was hit before the future finished.
+    pub(super) async fn run<F>(&self, fut: F) -> Option<F::Output>
+    where
+        F: Future,
+    {
+        let Some(at) = self.at else {
+            return Some(fut.await);
+        };
+        match tokio::time::timeout_at(at, fut).await {
+            Ok(res) => Some(res),
+            Err(_) => {
+                debug!("tool call deadline hit");
+                self.hit.store(true, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Any [`run`](Self::run) hit the deadline, i.e. the results are partial.
+    pub(super) fn timed_out(&self) -> bool {
+        self.hit.load(Ordering::Relaxed)
+    }
+}
+
+/// Tool result that marks partial results via a `timed_out` flag.
+pub(super) trait TimedOut: Default {
+    /// The `timed_out` flag.
+    fn timed_out_mut(&mut self) -> &mut bool;
+
+    /// Empty result for a tool call that hit the deadline before it gathered anything.
+    fn timed_out() -> Self {
+        let mut res = Self::default();
+        *res.timed_out_mut() = true;
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let cfg = TimeoutCLIConfig::default();
+        let deadline = cfg.deadline(None).unwrap();
+        assert_eq!(deadline.run(async { 1 }).await, Some(1));
+        assert!(!deadline.timed_out());
+
+        let deadline = cfg.deadline(Some(0.01)).unwrap();
+        assert_eq!(deadline.run(async { 1 }).await, Some(1));
+        assert!(!deadline.timed_out());
+        assert_eq!(deadline.run(futures::future::pending::<()>()).await, None);
+        assert!(deadline.timed_out());
+        // once hit, everything else times out right away
+        assert_eq!(
+            deadline
+                .run(tokio::time::sleep(Duration::from_secs(1)))
+                .await,
+            None
+        );
+
+        let cfg = TimeoutCLIConfig {
+            tool_timeout: Some(Duration::ZERO),
+        };
+        let deadline = cfg.deadline(None).unwrap();
+        assert_eq!(
+            deadline
+                .run(tokio::time::sleep(Duration::from_secs(1)))
+                .await,
+            None
+        );
+        assert!(deadline.timed_out());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_timeout() {
+        let cfg = TimeoutCLIConfig::default();
+        for secs in [-1.0, f64::NAN, f64::INFINITY, 1e30] {
+            let err = cfg.deadline(Some(secs)).unwrap_err();
+            assert!(err.message.starts_with("invalid `timeout_secs`"), "{err}");
+        }
+
+        // huge but valid timeouts wait forever
+        let deadline = cfg.deadline(Some(1e15)).unwrap();
+        assert_eq!(deadline.run(async { 1 }).await, Some(1));
+    }
+}
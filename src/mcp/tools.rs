@@ -0,0 +1,249 @@
+//! Which tools are offered to MCP clients and how they behave.
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use clap::{ArgAction, Parser};
+
+use super::{metrics::ToolMetrics, secrets::SecretsCLIConfig, timeout::TimeoutCLIConfig};
+use crate::{
+    cli::parse_secs, config_file::ConfigReporter, correlation::ActiveRequests,
+    lsp::location::PathStyle, tasks::TaskStatuses,
+};
+
+/// CLI config for the tools.
+#[derive(Debug, Clone, Parser)]
+pub(crate) struct ToolsCLIConfig {
+    /// Tool call timeout config.
+    #[clap(flatten)]
+    timeout_cfg: TimeoutCLIConfig,
+
+    /// Secret redaction config.
+    #[clap(flatten)]
+    secrets_cfg: SecretsCLIConfig,
+
+    /// Offer the `lsp_request` tool that sends arbitrary requests to the language servers.
+    ///
+    /// This is an escape hatch for server-specific extensions that have no dedicated tool. Requests are NOT
+    /// validated, so they may modify the state of the language server.
+    ///
+    /// The tool is only offered with `--read-only=false`.
+    #[clap(long, env = "COMMON_SENSE_CODER_ENABLE_LSP_REQUEST_TOOL")]
+    pub(super) enable_lsp_request_tool: bool,
+
+    /// Offer the `run_check` tool that runs the check command of the language server, e.g. `cargo check`.
+    ///
+    /// Checks execute build scripts and procedural macros of the workspace. The tool is not offered with
+    /// `--workspace-jail`.
+    #[clap(long, env = "COMMON_SENSE_CODER_ENABLE_RUN_CHECK_TOOL")]
+    pub(super) enable_run_check_tool: bool,
+
+    /// Offer the `run_test` tool that runs a single test via the runnables of the language server, e.g. `cargo test`.
+    ///
+    /// Tests execute arbitrary code of the workspace. The tool is only offered with `--read-only=false` and not with
+    /// `--workspace-jail`.
+    #[clap(long, env = "COMMON_SENSE_CODER_ENABLE_RUN_TEST_TOOL")]
+    pub(super) enable_run_test_tool: bool,
+
+    /// Seconds after which a test run by `run_test` is killed.
+    #[clap(
+        long,
+        default_value = "300",
+        value_parser = parse_secs,
+        env = "COMMON_SENSE_CODER_RUN_TEST_TIMEOUT"
+    )]
+    pub(super) run_test_timeout: Duration,
+
+    /// Offer the `view_item_tree` and `view_syntax_tree` tools that show the internal trees of rust-analyzer.
+    ///
+    /// These are meant for debugging unexpected results of other tools, e.g. for macro-generated code.
+    #[clap(long, env = "COMMON_SENSE_CODER_ENABLE_SYNTAX_TREE_TOOLS")]
+    pub(super) enable_syntax_tree_tools: bool,
+
+    /// Refuse tools that may modify files of the workspace, e.g. edits.
+    ///
+    /// Such tools are hidden and calls to them fail. Use `--read-only=false` to offer them.
+    #[clap(
+        long,
+        default_value_t = true,
+        action = ArgAction::Set,
+        env = "COMMON_SENSE_CODER_READ_ONLY"
+    )]
+    pub(super) read_only: bool,
+
+    /// Never show files outside the workspace and the sources of known dependencies (e.g. crates of the cargo
+    /// registry), not even with `workspace_and_dependencies`.
+    ///
+    /// Locations of other files are dropped from tool results and tools refuse to read them. The `lsp_request` tool
+    /// is not offered, because its raw responses cannot be checked, and neither are `add_workspace_folder`,
+    /// `run_check`, and `run_test`.
+    #[clap(long, env = "COMMON_SENSE_CODER_WORKSPACE_JAIL")]
+    workspace_jail: bool,
+
+    /// Extra directory whose files may be shown with `--workspace-jail`, relative to the workspace root or absolute.
+    ///
+    /// Can be repeated or comma-separated.
+    #[clap(
+        long,
+        env = "COMMON_SENSE_CODER_WORKSPACE_JAIL_ALLOW",
+        value_delimiter = ',',
+        requires = "workspace_jail"
+    )]
+    workspace_jail_allow: Vec<PathBuf>,
+
+    /// How file paths are rendered in tool results.
+    #[clap(
+        long,
+        default_value = "relative",
+        env = "COMMON_SENSE_CODER_PATH_STYLE"
+    )]
+    path_style: PathStyle,
+
+    /// Include the absolute path of the workspace root in tool results that contain file paths.
+    #[clap(long, env = "COMMON_SENSE_CODER_INCLUDE_WORKSPACE_ROOT")]
+    include_workspace_root: bool,
+
+    /// Maximum number of characters of a tool result.
+    ///
+    /// Larger results are shrunk by cutting the largest lists and texts, and the cuts are listed in the
+    /// `response_truncated` field of the result. Use 0 to disable the limit.
+    #[clap(
+        long,
+        default_value_t = 50_000,
+        env = "COMMON_SENSE_CODER_MAX_RESPONSE_CHARS"
+    )]
+    max_response_chars: usize,
+
+    /// Background tasks reported by `server_status`, see [`with_task_statuses`](Self::with_task_statuses).
+    #[clap(skip)]
+    pub(super) task_statuses: TaskStatuses,
+
+    /// Allocates request IDs for tool calls, see [`with_active_requests`](Self::with_active_requests).
+    #[clap(skip)]
+    pub(super) active_requests: ActiveRequests,
+
+    /// Call statistics of the tools reported by `server_stats`, shared by all clones.
+    #[clap(skip)]
+    pub(super) tool_metrics: ToolMetrics,
+
+    /// Reports the effective configuration via `get_config`, see [`with_config_reporter`](Self::with_config_reporter).
+    #[clap(skip)]
+    pub(super) config_reporter: Option<ConfigReporter>,
+
+    /// Tool defaults that were changed at runtime, shared by all clones, see [`reload`](Self::reload).
+    #[clap(skip)]
+    reloaded: Arc<RwLock<Option<ToolDefaults>>>,
+}
+
+/// Settings of [`ToolsCLIConfig`] that can change at runtime.
+#[derive(Debug, Clone, Copy)]
+struct ToolDefaults {
+    timeout_cfg: TimeoutCLIConfig,
+    path_style: PathStyle,
+    include_workspace_root: bool,
+    max_response_chars: usize,
+}
+
+impl ToolsCLIConfig {
+    /// Report the given background tasks.
+    pub(crate) fn with_task_statuses(self, task_statuses: TaskStatuses) -> Self {
+        Self {
+            task_statuses,
+            ..self
+        }
+    }
+
+    /// Register tool calls in the given registry, so they can be correlated with the language server traffic.
+    pub(crate) fn with_active_requests(self, active_requests: ActiveRequests) -> Self {
+        Self {
+            active_requests,
+            ..self
+        }
+    }
+
+    /// Report the effective configuration via the given reporter.
+    pub(crate) fn with_config_reporter(self, config_reporter: ConfigReporter) -> Self {
+        Self {
+            config_reporter: Some(config_reporter),
+            ..self
+        }
+    }
+
+    /// Use the tool defaults of the given config from now on, e.g. after the config file changed.
+    ///
+    /// Which tools are offered does not change at runtime.
+    pub(crate) fn reload(&self, other: &Self) {
+        *self.reloaded.write().expect("not poisoned") = Some(ToolDefaults {
+            timeout_cfg: other.timeout_cfg,
+            path_style: other.path_style,
+            include_workspace_root: other.include_workspace_root,
+            max_response_chars: other.max_response_chars,
+        });
+    }
+
+    /// Current tool defaults.
+    fn defaults(&self) -> ToolDefaults {
+        self.reloaded
+            .read()
+            .expect("not poisoned")
+            .unwrap_or(ToolDefaults {
+                timeout_cfg: self.timeout_cfg,
+                path_style: self.path_style,
+                include_workspace_root: self.include_workspace_root,
+                max_response_chars: self.max_response_chars,
+            })
+    }
+
+    /// Extra directories that may be shown, `None` if there is no jail.
+    ///
+    /// See [`Workspace::with_jail`](crate::lsp::workspace::Workspace::with_jail).
+    pub(super) fn workspace_jail(&self) -> Option<Vec<PathBuf>> {
+        self.workspace_jail
+            .then(|| self.workspace_jail_allow.clone())
+    }
+
+    pub(super) fn timeout_cfg(&self) -> TimeoutCLIConfig {
+        self.defaults().timeout_cfg
+    }
+
+    pub(super) fn path_style(&self) -> PathStyle {
+        self.defaults().path_style
+    }
+
+    pub(super) fn include_workspace_root(&self) -> bool {
+        self.defaults().include_workspace_root
+    }
+
+    /// Size budget of tool results, `None` if there is no limit.
+    pub(super) fn max_response_chars(&self) -> Option<usize> {
+        Some(self.defaults().max_response_chars).filter(|max| *max > 0)
+    }
+
+    /// Redaction of secrets in tool results and resources.
+    pub(super) fn secrets_cfg(&self) -> &SecretsCLIConfig {
+        &self.secrets_cfg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reload() {
+        let cfg = ToolsCLIConfig::parse_from([""]);
+        let clone = cfg.clone();
+        assert_eq!(clone.path_style(), PathStyle::Relative);
+        assert!(!clone.include_workspace_root());
+
+        cfg.reload(&ToolsCLIConfig::parse_from([
+            "",
+            "--path-style=absolute",
+            "--include-workspace-root",
+        ]));
+        assert_eq!(clone.path_style(), PathStyle::Absolute);
+        assert!(clone.include_workspace_root());
+    }
+}
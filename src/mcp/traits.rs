@@ -0,0 +1,347 @@
+//! Structure of a trait (or interface): its associated items and which of them have a default.
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use lsp_types::{
+    Location, SymbolKind, TextDocumentIdentifier, TextDocumentPositionParams,
+    request::{GotoImplementation, GotoImplementationParams},
+};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    location::{LocationVariants, McpLocation},
+    scheduler::Priority,
+    uri::file_uri_to_path,
+};
+
+use super::{
+    CodeExplorer, declarations,
+    error::{OptionExt, ResultExt},
+    hints,
+    timeout::TimedOut,
+};
+
+/// The item (given by its source text) has a default, e.g. a method with a body or a constant with a value.
+pub(super) fn is_provided(kind: SymbolKind, text: &str) -> bool {
+    let text = text.trim_end();
+    if matches!(kind, SymbolKind::FUNCTION | SymbolKind::METHOD) {
+        text.ends_with('}')
+    } else {
+        text.contains('=')
+    }
+}
+
+/// Plain locations, links point to the selected part of their target.
+pub(super) fn locations(variants: LocationVariants) -> Vec<Location> {
+    match variants {
+        LocationVariants::Scalar(location) => vec![location],
+        LocationVariants::Array(locations) => locations,
+        LocationVariants::Link(links) => links
+            .into_iter()
+            .map(|link| Location::new(link.target_uri, link.target_selection_range))
+            .collect(),
+    }
+}
+
+#[tool_router(router = traits_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Trait Detail",
+        description = "Get the structure of a trait (or interface): its associated items (methods, constants, types), which of them are required and which have a default, and the types that implement it.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn trait_detail(
+        &self,
+        Parameters(TraitDetailRequest {
+            file,
+            name,
+            line,
+            workspace_and_dependencies,
+            timeout_secs,
+        }): Parameters<TraitDetailRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<TraitDetailResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if name.is_none() && line.is_none() {
+            return Err(McpError::invalid_params(
+                "either `name` or `line` is required",
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(TraitDetailResult::timed_out()));
+        };
+        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        if let Some(line) = line {
+            let lines = file_content.lines().count();
+            if line as usize > lines {
+                return Err(hints::position_drifted(&file, line, lines));
+            }
+        }
+        let Some(symbols) = deadline
+            .run(self.document_symbols(server, &client, &file))
+            .await
+        else {
+            return Ok(Json(TraitDetailResult::timed_out()));
+        };
+        let symbols = symbols?;
+
+        // innermost trait, in case traits are declared within functions
+        let Some(trait_symbol) = symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::INTERFACE)
+            .filter(|symbol| name.as_ref().is_none_or(|name| &symbol.name == name))
+            .filter(|symbol| {
+                let range = symbol.location.range;
+                line.is_none_or(|line| (range.start.line..=range.end.line).contains(&(line - 1)))
+            })
+            .max_by_key(|symbol| symbol.location.range.start)
+        else {
+            let symbol = match (&name, line) {
+                (Some(name), _) => format!("`{name}` in {file}"),
+                (None, line) => format!("{file}:{}", line.unwrap_or_default()),
+            };
+            return Err(hints::not_a_trait(&symbol));
+        };
+        let trait_range = trait_symbol.location.range;
+
+        let items = declarations::children(trait_symbol, &symbols)
+            .into_iter()
+            .map(|symbol| {
+                let text = declarations::range_text(&file_content, symbol.location.range);
+                Ok(TraitItem {
+                    name: symbol.name.clone(),
+                    kind: format!("{:?}", symbol.kind),
+                    provided: text.is_some_and(|text| is_provided(symbol.kind, text)),
+                    location: McpLocation::try_new(symbol.location.clone(), self.workspace(), true)
+                        .context("convert item location")
+                        .internal()?
+                        .expected("item is within the file".to_owned())?,
+                })
+            })
+            .collect::<Result<Vec<_>, McpError>>()?;
+
+        let mut implementors = vec![];
+        if let Some(position) =
+            declarations::name_position(&file_content, trait_range, &trait_symbol.name)
+        {
+            let text_document_position_params = TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: trait_symbol.location.uri.clone(),
+                },
+                position,
+            };
+            let resp = deadline
+                .run(
+                    client.send_request::<GotoImplementation>(GotoImplementationParams {
+                        text_document_position_params,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    }),
+                )
+                .await
+                .transpose()
+                .context("GotoImplementation")
+                .internal()?
+                .flatten();
+            let locations = resp
+                .map(|resp| locations(LocationVariants::from(resp)))
+                .unwrap_or_default();
+
+            let mut contents = HashMap::<PathBuf, Option<String>>::new();
+            for location in locations {
+                let Some(mcp_location) = McpLocation::try_new(
+                    location.clone(),
+                    self.workspace(),
+                    workspace_and_dependencies,
+                )
+                .context("convert implementation location")
+                .internal()?
+                else {
+                    continue;
+                };
+                let type_name = match file_uri_to_path(&location.uri) {
+                    Some(path) => {
+                        let content = match contents.entry(path) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => {
+                                let content = tokio::fs::read_to_string(entry.key()).await.ok();
+                                entry.insert(content)
+                            }
+                        };
+                        content
+                            .as_deref()
+                            .and_then(|content| declarations::range_text(content, location.range))
+                            .filter(|text| !text.is_empty() && !text.contains('\n'))
+                            .map(ToOwned::to_owned)
+                    }
+                    None => None,
+                };
+                implementors.push(Implementor {
+                    type_name,
+                    location: mcp_location,
+                });
+            }
+            implementors.sort_by(|a, b| a.location.cmp(&b.location));
+        }
+
+        let provided = items.iter().filter(|item| item.provided).count();
+        Ok(Json(TraitDetailResult {
+            name: trait_symbol.name.clone(),
+            location: Some(
+                McpLocation::try_new(trait_symbol.location.clone(), self.workspace(), true)
+                    .context("convert trait location")
+                    .internal()?
+                    .expected("trait is within the file".to_owned())?,
+            ),
+            required: items.len() - provided,
+            provided,
+            items,
+            implementor_count: implementors.len(),
+            implementors,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct TraitDetailRequest {
+    /// path to the file that declares the trait, can be absolute or relative
+    file: String,
+
+    /// trait name, if not set the trait at the given line is used
+    name: Option<String>,
+
+    /// 1-based line number within the trait
+    #[schemars(range(min = 1))]
+    line: Option<u32>,
+
+    /// include implementations in dependencies
+    workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct TraitItem {
+    name: String,
+
+    /// e.g. `Function` or `Constant`
+    kind: String,
+
+    /// the item has a default (e.g. a method body), so implementations do not need to define it
+    provided: bool,
+
+    location: McpLocation,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct Implementor {
+    /// implementing type as written in the implementation, e.g. `Vec<T>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    type_name: Option<String>,
+
+    location: McpLocation,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct TraitDetailResult {
+    name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<McpLocation>,
+
+    /// number of items that implementations must define
+    required: usize,
+
+    /// number of items with a default
+    provided: usize,
+
+    /// associated items in declaration order
+    items: Vec<TraitItem>,
+
+    /// number of implementations
+    implementor_count: usize,
+
+    implementors: Vec<Implementor>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for TraitDetailResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONTENT: &str = "\
+/// A trait.
+#[expect(dead_code)]
+pub trait Shape: Sized {
+    const SIDES: usize;
+    const NAME: &str = \"shape\";
+    type Unit;
+
+    fn area(&self) -> f64;
+
+    fn describe(&self) -> String {
+        let area = self.area();
+        format!(\"{area}\")
+    }
+}
+";
+
+    #[test]
+    fn test_is_provided() {
+        let line = |i: usize| CONTENT.lines().nth(i).unwrap();
+        assert!(!is_provided(SymbolKind::CONSTANT, line(3)));
+        assert!(is_provided(SymbolKind::CONSTANT, line(4)));
+        assert!(!is_provided(SymbolKind::TYPE_PARAMETER, line(5)));
+        assert!(!is_provided(SymbolKind::FUNCTION, line(7)));
+        assert!(is_provided(
+            SymbolKind::FUNCTION,
+            &CONTENT
+                .lines()
+                .skip(9)
+                .take(4)
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+        assert!(!is_provided(
+            SymbolKind::METHOD,
+            "fn f(&self) where Self: Iterator<Item = u8>;"
+        ));
+    }
+}
@@ -0,0 +1,433 @@
+//! Type of an expression, extracted from hover responses.
+use anyhow::Context;
+use lsp_types::{
+    Hover, HoverContents, MarkedString, Position, Range, TextDocumentPositionParams,
+    request::{GotoTypeDefinition, GotoTypeDefinitionParams},
+};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{
+    location::{LocationVariants, McpLocation, path_to_text_document_identifier},
+    requests::{HoverRangeParams, HoverRangeRequest},
+    scheduler::Priority,
+};
+
+use super::{CodeExplorer, error::ResultExt, hints, timeout::TimedOut};
+
+/// Type of an expression or variable.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct TypeInfo {
+    pub(super) ty: String,
+
+    /// type that the expression is implicitly converted to, e.g. `&str` for a `&String` argument
+    pub(super) coerced_to: Option<String>,
+}
+
+/// Type from a hover response for a range, which only describes the type of the expression.
+///
+/// rust-analyzer renders coercions as a `text` block with `Type:` and `Coerced to:` lines, other types as a single
+/// code block.
+pub(super) fn from_range_hover(hover: &Hover) -> Option<TypeInfo> {
+    let markdown = markdown(hover);
+    let (language, body) = *code_blocks(&markdown).first()?;
+    let body = body.trim();
+    if language == "text" {
+        let mut ty = None;
+        let mut coerced_to = None;
+        for line in body.lines() {
+            if let Some(rest) = line.strip_prefix("Type:") {
+                ty = Some(rest.trim().to_owned());
+            } else if let Some(rest) = line.strip_prefix("Coerced to:") {
+                coerced_to = Some(rest.trim().to_owned());
+            }
+        }
+        if let Some(ty) = ty {
+            return Some(TypeInfo { ty, coerced_to });
+        }
+    }
+    (!body.is_empty()).then(|| TypeInfo {
+        ty: body.to_owned(),
+        coerced_to: None,
+    })
+}
+
+/// Type from a plain hover response, which describes the declaration, e.g. `let x: u32` or `pub field: u64`.
+pub(super) fn from_hover(hover: &Hover) -> Option<TypeInfo> {
+    let markdown = markdown(hover);
+    // the first block is often the module path of the symbol, the declaration follows
+    code_blocks(&markdown)
+        .into_iter()
+        .find_map(|(_, body)| body.lines().last().and_then(declared_type))
+        .map(|ty| TypeInfo {
+            ty: ty.to_owned(),
+            coerced_to: None,
+        })
+}
+
+/// Type of a declaration like `let mut x: u32` or `pub(crate) const N: usize = 1`.
+fn declared_type(declaration: &str) -> Option<&str> {
+    let mut declaration = declaration.trim();
+    loop {
+        let stripped = ["pub ", "let ", "mut ", "ref ", "const ", "static "]
+            .iter()
+            .find_map(|prefix| declaration.strip_prefix(prefix))
+            .or_else(|| {
+                let rest = declaration.strip_prefix("pub(")?;
+                Some(rest.split_once(") ")?.1)
+            });
+        match stripped {
+            Some(stripped) => declaration = stripped.trim_start(),
+            None => break,
+        }
+    }
+
+    let (name, ty) = declaration.split_once(": ")?;
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let ty = ty.split_once(" = ").map_or(ty, |(ty, _)| ty);
+    let ty = ty.trim().trim_end_matches([',', ';']);
+    (!ty.is_empty()).then_some(ty)
+}
+
+/// Range of the identifier at the position, the language servers use UTF-8 positions.
+pub(super) fn identifier_range(content: &str, position: Position) -> Option<Range> {
+    let line = content.lines().nth(position.line as usize)?;
+    let at = position.character as usize;
+    if !line.is_char_boundary(at.min(line.len())) {
+        return None;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let start = line[..at.min(line.len())]
+        .rfind(|c| !is_ident(c))
+        .map_or(0, |i| i + 1);
+    let end = line[start..]
+        .find(|c| !is_ident(c))
+        .map_or(line.len(), |i| start + i);
+    (start < end && at < end).then(|| {
+        Range::new(
+            Position::new(position.line, start as u32),
+            Position::new(position.line, end as u32),
+        )
+    })
+}
+
+/// Hover contents as markdown.
+pub(super) fn markdown(hover: &Hover) -> String {
+    let marked = |s: &MarkedString| match s {
+        MarkedString::String(s) => s.clone(),
+        MarkedString::LanguageString(s) => format!("```{}\n{}\n```", s.language, s.value),
+    };
+    match &hover.contents {
+        HoverContents::Scalar(s) => marked(s),
+        HoverContents::Array(array) => array.iter().map(marked).collect::<Vec<_>>().join("\n"),
+        HoverContents::Markup(markup) => markup.value.clone(),
+    }
+}
+
+/// Language and body of the fenced code blocks.
+fn code_blocks(markdown: &str) -> Vec<(&str, &str)> {
+    let mut blocks = vec![];
+    let mut rest = markdown;
+    while let Some((_, block)) = rest.split_once("```")
+        && let Some((language, block)) = block.split_once('\n')
+        && let Some((body, remaining)) = block.split_once("```")
+    {
+        blocks.push((language.trim(), body));
+        rest = remaining;
+    }
+    blocks
+}
+
+#[tool_router(router = type_at_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Type At",
+        description = "Get the inferred type of a variable or expression at a position (or within a range) of a file, plus where that type is defined. Cheaper than `symbol_info` if only the type matters.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn type_at(
+        &self,
+        Parameters(TypeAtRequest {
+            file,
+            line,
+            character,
+            end_line,
+            end_character,
+            workspace_and_dependencies,
+            timeout_secs,
+        }): Parameters<TypeAtRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<TypeAtResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(TypeAtResult::timed_out()));
+        };
+        let workspace_and_dependencies = workspace_and_dependencies.unwrap_or_default();
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        let lines = file_content.lines().count();
+        for line in [Some(line), end_line].into_iter().flatten() {
+            if line as usize > lines {
+                return Err(hints::position_drifted(&file, line, lines));
+            }
+        }
+        let text_document = path_to_text_document_identifier(&self.workspace(), &file)
+            .context("convert path to text document identifier")
+            .internal()?;
+        server
+            .documents()
+            .open(&text_document.uri, &file_content)
+            .await
+            .context("open document")
+            .internal()?;
+
+        // 1-based => 0-based
+        let start = Position::new(line - 1, character - 1);
+        let range = match (end_line, end_character) {
+            (Some(end_line), Some(end_character)) => Some(Range::new(
+                start,
+                Position::new(end_line - 1, end_character - 1),
+            )),
+            (None, None) => identifier_range(&file_content, start),
+            _ => {
+                return Err(McpError::invalid_params(
+                    "`end_line` and `end_character` must be passed together",
+                    None,
+                ));
+            }
+        };
+
+        let mut info = None;
+        if server.hover_range()
+            && let Some(range) = range
+        {
+            let Some(hover) = deadline
+                .run(client.send_request::<HoverRangeRequest>(HoverRangeParams {
+                    text_document: text_document.clone(),
+                    position: range,
+                }))
+                .await
+            else {
+                return Ok(Json(TypeAtResult::timed_out()));
+            };
+            info = hover
+                .context("HoverRangeRequest")
+                .internal()?
+                .as_ref()
+                .and_then(from_range_hover);
+        }
+        let text_document_position_params = TextDocumentPositionParams {
+            text_document,
+            position: start,
+        };
+        if info.is_none() {
+            let state = self.document_state(server, &file_content);
+            let Some(hover) = self
+                .hover(
+                    server,
+                    &client,
+                    &text_document_position_params,
+                    state,
+                    &deadline,
+                )
+                .await?
+            else {
+                return Ok(Json(TypeAtResult::timed_out()));
+            };
+            info = from_hover(&hover);
+        }
+        let Some(info) = info else {
+            return Err(hints::no_type(&format!("{file}:{line}:{character}")));
+        };
+
+        let type_definitions = match deadline
+            .run(
+                client.send_request::<GotoTypeDefinition>(GotoTypeDefinitionParams {
+                    text_document_position_params,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                }),
+            )
+            .await
+            .transpose()
+            .context("GotoTypeDefinition")
+            .internal()?
+            .flatten()
+        {
+            Some(resp) => LocationVariants::from(resp)
+                .into_mcp_location(&self.workspace(), workspace_and_dependencies)
+                .context("convert type definition locations")
+                .internal()?,
+            None => vec![],
+        };
+
+        Ok(Json(TypeAtResult {
+            r#type: info.ty,
+            coerced_to: info.coerced_to,
+            type_definitions,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct TypeAtRequest {
+    /// path to the file, can be absolute or relative
+    file: String,
+
+    /// 1-based line number within the file
+    #[schemars(range(min = 1))]
+    line: u32,
+
+    /// 1-based character index within the line
+    #[schemars(range(min = 1))]
+    character: u32,
+
+    /// 1-based line number of the end of an expression, if not set the variable or expression at the position is used
+    #[schemars(range(min = 1))]
+    end_line: Option<u32>,
+
+    /// 1-based character index after the end of an expression
+    #[schemars(range(min = 1))]
+    end_character: Option<u32>,
+
+    /// include type definitions in dependencies
+    workspace_and_dependencies: Option<bool>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct TypeAtResult {
+    /// inferred type, e.g. `Vec<u32>`
+    r#type: String,
+
+    /// type that the expression is implicitly converted to, e.g. `&str` for a `&String` argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    coerced_to: Option<String>,
+
+    /// where the type is defined
+    type_definitions: Vec<McpLocation>,
+
+    /// the timeout was hit, results are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for TypeAtResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{MarkupContent, MarkupKind};
+
+    use super::*;
+
+    fn hover(value: &str) -> Hover {
+        Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: value.to_owned(),
+            }),
+            range: None,
+        }
+    }
+
+    fn ty(ty: &str, coerced_to: Option<&str>) -> Option<TypeInfo> {
+        Some(TypeInfo {
+            ty: ty.to_owned(),
+            coerced_to: coerced_to.map(ToOwned::to_owned),
+        })
+    }
+
+    #[test]
+    fn test_from_range_hover() {
+        assert_eq!(
+            from_range_hover(&hover("```rust\nVec<u32>\n```")),
+            ty("Vec<u32>", None)
+        );
+        assert_eq!(
+            from_range_hover(&hover(
+                "```text\nType:       &String\nCoerced to:     &str\n```\n"
+            )),
+            ty("&String", Some("&str"))
+        );
+        assert_eq!(from_range_hover(&hover("no code")), None);
+        assert_eq!(from_range_hover(&hover("```rust\n\n```")), None);
+    }
+
+    #[test]
+    fn test_from_hover() {
+        assert_eq!(
+            from_hover(&hover("```rust\nlet mut accu: u64\n```")),
+            ty("u64", None)
+        );
+        assert_eq!(
+            from_hover(&hover(
+                "```rust\nmain_lib::MyMainStruct\n```\n\n```rust\npub field: u64\n```\n\n---\n\nA field."
+            )),
+            ty("u64", None)
+        );
+        assert_eq!(
+            from_hover(&hover("```rust\npub(crate) const N: usize = 1\n```")),
+            ty("usize", None)
+        );
+        assert_eq!(
+            from_hover(&hover(
+                "```rust\npub fn my_lib_fn(left: u64, right: u64) -> u64\n```"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_identifier_range() {
+        let content = "let accu = left + right;\n";
+        let range = |line, start, end| {
+            Some(Range::new(
+                Position::new(line, start),
+                Position::new(line, end),
+            ))
+        };
+        assert_eq!(
+            identifier_range(content, Position::new(0, 4)),
+            range(0, 4, 8)
+        );
+        assert_eq!(
+            identifier_range(content, Position::new(0, 6)),
+            range(0, 4, 8)
+        );
+        assert_eq!(
+            identifier_range(content, Position::new(0, 18)),
+            range(0, 18, 23)
+        );
+        assert_eq!(identifier_range(content, Position::new(0, 9)), None);
+        assert_eq!(identifier_range(content, Position::new(0, 100)), None);
+        assert_eq!(identifier_range(content, Position::new(1, 0)), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! Fields of a struct or the variants of an enum.
+use anyhow::Context;
+use lsp_types::{SymbolKind, TextDocumentIdentifier, TextDocumentPositionParams};
+use rmcp::{
+    Json, RoleServer, handler::server::wrapper::Parameters, model::ErrorData as McpError, schemars,
+    service::RequestContext, tool, tool_router,
+};
+
+use crate::lsp::{location::McpLocation, scheduler::Priority};
+
+use super::{
+    CodeExplorer, declarations,
+    error::{OptionExt, ResultExt},
+    hints,
+    timeout::TimedOut,
+};
+
+#[tool_router(router = type_members_router, vis = "pub(super)")]
+impl CodeExplorer {
+    #[tool(
+        title = "Type Members",
+        description = "List the fields of a struct or the variants of an enum with their types, visibility, and documentation as structured data. More complete and easier to use than the hover text of the type.",
+        annotations(
+            read_only_hint = true,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    async fn type_members(
+        &self,
+        Parameters(TypeMembersRequest {
+            file,
+            name,
+            line,
+            timeout_secs,
+        }): Parameters<TypeMembersRequest>,
+        ctx: RequestContext<RoleServer>,
+    ) -> Result<Json<TypeMembersResult>, McpError> {
+        let server = self.server_for_file(&file)?;
+        if name.is_none() && line.is_none() {
+            return Err(McpError::invalid_params(
+                "either `name` or `line` is required",
+                None,
+            ));
+        }
+        let deadline = self.tools_cfg.timeout_cfg().deadline(timeout_secs)?;
+        let Some(client) = deadline
+            .run(self.wait_for_client(server, Priority::Normal, ctx))
+            .await
+        else {
+            return Ok(Json(TypeMembersResult::timed_out()));
+        };
+
+        let Some(file_content) = self.read_file(&file).await? else {
+            return Err(hints::file_not_found(&self.workspace(), &file).await);
+        };
+        if let Some(line) = line {
+            let lines = file_content.lines().count();
+            if line as usize > lines {
+                return Err(hints::position_drifted(&file, line, lines));
+            }
+        }
+        let Some(symbols) = deadline
+            .run(self.document_symbols(server, &client, &file))
+            .await
+        else {
+            return Ok(Json(TypeMembersResult::timed_out()));
+        };
+        let symbols = symbols?;
+
+        // innermost type, in case types are declared within functions
+        let Some(type_symbol) = symbols
+            .iter()
+            .filter(|symbol| matches!(symbol.kind, SymbolKind::STRUCT | SymbolKind::ENUM))
+            .filter(|symbol| name.as_ref().is_none_or(|name| &symbol.name == name))
+            .filter(|symbol| {
+                let range = symbol.location.range;
+                line.is_none_or(|line| (range.start.line..=range.end.line).contains(&(line - 1)))
+            })
+            .max_by_key(|symbol| symbol.location.range.start)
+        else {
+            let symbol = match (&name, line) {
+                (Some(name), _) => format!("`{name}` in {file}"),
+                (None, line) => format!("{file}:{}", line.unwrap_or_default()),
+            };
+            return Err(hints::not_a_struct_or_enum(&symbol));
+        };
+
+        let state = self.document_state(server, &file_content);
+        let mut members = vec![];
+        for symbol in declarations::children(type_symbol, &symbols) {
+            if !matches!(symbol.kind, SymbolKind::FIELD | SymbolKind::ENUM_MEMBER) {
+                continue;
+            }
+            let range = symbol.location.range;
+            let member = declarations::range_text(&file_content, range)
+                .map(|text| declarations::Member::parse(symbol.kind, &symbol.name, text));
+
+            // docs are rendered by the language server, e.g. with resolved links
+            let mut docs = None;
+            if !deadline.timed_out()
+                && let Some(position) =
+                    declarations::name_position(&file_content, range, &symbol.name)
+            {
+                let params = TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: symbol.location.uri.clone(),
+                    },
+                    position,
+                };
+                docs = self
+                    .hover(server, &client, &params, state, &deadline)
+                    .await?
+                    .as_ref()
+                    .and_then(declarations::docs);
+            }
+
+            members.push(TypeMember {
+                name: symbol.name.clone(),
+                kind: format!("{:?}", symbol.kind),
+                visibility: member.as_ref().and_then(|member| member.visibility.clone()),
+                r#type: member.and_then(|member| member.ty),
+                docs,
+                location: McpLocation::try_new(symbol.location.clone(), self.workspace(), true)
+                    .context("convert member location")
+                    .internal()?
+                    .expected("member is within the file".to_owned())?,
+            });
+        }
+
+        let location = McpLocation::try_new(type_symbol.location.clone(), self.workspace(), true)
+            .context("convert type location")
+            .internal()?
+            .expected("type is within the file".to_owned())?;
+
+        // tuple fields are no symbols
+        if members.is_empty()
+            && type_symbol.kind == SymbolKind::STRUCT
+            && let Some(text) = declarations::range_text(&file_content, type_symbol.location.range)
+        {
+            for (name, member) in declarations::tuple_fields(&type_symbol.name, text) {
+                members.push(TypeMember {
+                    name,
+                    kind: format!("{:?}", SymbolKind::FIELD),
+                    visibility: member.visibility,
+                    r#type: member.ty,
+                    docs: None,
+                    location: McpLocation::try_new(
+                        type_symbol.location.clone(),
+                        self.workspace(),
+                        true,
+                    )
+                    .context("convert type location")
+                    .internal()?
+                    .expected("type is within the file".to_owned())?,
+                });
+            }
+        }
+
+        Ok(Json(TypeMembersResult {
+            name: type_symbol.name.clone(),
+            kind: format!("{:?}", type_symbol.kind),
+            location: Some(location),
+            members,
+            timed_out: deadline.timed_out(),
+            workspace_root: self.workspace_root(),
+        }))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct TypeMembersRequest {
+    /// path to the file that declares the type, can be absolute or relative
+    file: String,
+
+    /// name of the struct or enum, if not set the type at the given line is used
+    name: Option<String>,
+
+    /// 1-based line number within the type
+    #[schemars(range(min = 1))]
+    line: Option<u32>,
+
+    /// seconds after which partial results are returned, overrides the server default
+    #[schemars(range(min = 0.0))]
+    timeout_secs: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct TypeMember {
+    /// name of the field or variant, the index for tuple fields
+    name: String,
+
+    /// `Field` or `EnumMember`
+    kind: String,
+
+    /// e.g. `pub` or `pub(crate)`, not set for private fields and variants
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visibility: Option<String>,
+
+    /// type of a field, or the payload of a variant like `(u32, u32)` or `{ radius: f64 }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    r#type: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    docs: Option<String>,
+
+    location: McpLocation,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+struct TypeMembersResult {
+    name: String,
+
+    /// `Struct` or `Enum`
+    kind: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<McpLocation>,
+
+    /// fields or variants in declaration order
+    members: Vec<TypeMember>,
+
+    /// the timeout was hit, results (e.g. docs) are incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    timed_out: bool,
+
+    /// absolute path of the workspace root, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<String>,
+}
+
+impl TimedOut for TypeMembersResult {
+    fn timed_out_mut(&mut self) -> &mut bool {
+        &mut self.timed_out
+    }
+}
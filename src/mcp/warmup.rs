@@ -0,0 +1,255 @@
+//! Fetch results of core files in the background, so the first tool calls for them do not wait for the language server.
+//!
+//! Once the language servers are ready, the document symbols and semantic tokens of the files that match
+//! `--warmup-globs` are fetched and pinned in the [document cache](crate::lsp::document_cache). Tool calls keep them
+//! up to date afterwards.
+use std::{sync::Arc, time::Instant};
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+
+use crate::{
+    TaskManager,
+    lsp::{
+        file_watcher::list_files, location::path_to_uri, scheduler::Priority,
+        server::LanguageServer, workspace::SharedWorkspace, workspace::Workspace,
+    },
+};
+
+use super::{CodeExplorer, MAX_FETCH_ATTEMPTS, McpError, error::ResultExt, tools::ToolsCLIConfig};
+
+/// Upper limit for the number of warmed up files, so broad globs do not keep the language servers busy for long.
+const MAX_FILES: usize = 100;
+
+#[derive(Debug, Clone, clap::Parser)]
+pub(crate) struct WarmupCLIConfig {
+    /// Glob (relative to the workspace) of files whose symbols and semantic tokens are fetched once the language
+    /// servers are ready.
+    ///
+    /// Supports `*`, `?`, `**`, character classes like `[ab]`, and alternatives like `{a,b}`, e.g. `src/core/**/*.rs`.
+    /// Can be repeated or comma-separated.
+    #[clap(long, env = "COMMON_SENSE_CODER_WARMUP_GLOBS", value_parser = parse_globs)]
+    warmup_globs: Vec<GlobSet>,
+}
+
+/// Parse comma-separated globs, commas within alternatives (`{a,b}`) do not separate globs.
+fn parse_globs(s: &str) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    let mut add = |glob: &str| {
+        let glob = glob.strip_prefix("./").unwrap_or(glob);
+        if !glob.is_empty() {
+            builder.add(
+                GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        Ok::<_, String>(())
+    };
+
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                add(&s[start..i])?;
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    add(&s[start..])?;
+
+    builder.build().map_err(|e| e.to_string())
+}
+
+impl WarmupCLIConfig {
+    /// Spawn warm-up task, which waits for [`Warmup::start`].
+    pub(crate) fn spawn(&self, tasks: &mut TaskManager) -> Warmup {
+        if self.warmup_globs.is_empty() {
+            return Warmup { tx: None };
+        }
+
+        let globs = self.warmup_globs.clone();
+        let (tx, rx) = oneshot::channel::<CodeExplorer>();
+        tasks.spawn(
+            async move |cancel| {
+                let warm_up = async {
+                    // language servers may fail to initialize
+                    if let Ok(explorer) = rx.await {
+                        explorer.warm_up(globs).await;
+                    }
+                };
+                tokio::select! {
+                    _ = warm_up => {}
+                    _ = cancel.cancelled() => return Ok(()),
+                }
+
+                // tasks must not end before shutdown
+                cancel.cancelled().await;
+                Ok(())
+            },
+            "warm-up",
+        );
+        Warmup { tx: Some(tx) }
+    }
+}
+
+/// Starts the warm-up once the language servers are initialized.
+#[derive(Debug)]
+pub(crate) struct Warmup {
+    /// `None` if there is nothing to warm up.
+    tx: Option<oneshot::Sender<CodeExplorer>>,
+}
+
+impl Warmup {
+    pub(crate) fn start(
+        self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+    ) {
+        if let Some(tx) = self.tx {
+            tx.send(CodeExplorer::new(servers, workspace, tools_cfg))
+                .ok();
+        }
+    }
+}
+
+impl CodeExplorer {
+    async fn warm_up(&self, globs: Vec<GlobSet>) {
+        let workspace = self.workspace();
+        let res = tokio::task::spawn_blocking(move || matching_files(&workspace, &globs))
+            .await
+            .context("join listing");
+        let mut files = match res.and_then(|res| res) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!(%e, "cannot list files to warm up");
+                return;
+            }
+        };
+        if files.len() > MAX_FILES {
+            warn!(
+                matched = files.len(),
+                max = MAX_FILES,
+                "too many files match the warm-up globs, only warm up the first ones",
+            );
+            files.truncate(MAX_FILES);
+        }
+
+        let start = Instant::now();
+        for file in &files {
+            if let Err(e) = self.warm_up_file(file).await {
+                warn!(file, %e, "cannot warm up file");
+            }
+        }
+        info!(
+            files = files.len(),
+            elapsed_ms = start.elapsed().as_millis(),
+            "warm-up done",
+        );
+    }
+
+    async fn warm_up_file(&self, file: &str) -> Result<(), McpError> {
+        // globs may match files that no language server handles, e.g. docs
+        let Ok(server) = self.server_for_file(file) else {
+            return Ok(());
+        };
+        debug!(file, lang = server.name(), "warm up file");
+
+        let uri = path_to_uri(&self.workspace(), file)
+            .context("convert path to URI")
+            .internal()?;
+        server.document_cache().pin(uri);
+
+        for attempt in 1..=MAX_FETCH_ATTEMPTS {
+            let client = server.progress_guard().wait().await;
+            let _permit = server.scheduler().acquire(Priority::Low).await;
+            let revision = self.revision(server);
+            self.document_symbols(server, &client, file).await?;
+
+            // the language server may have started more work (e.g. indexing) in the meantime
+            if self.revision(server) == revision {
+                break;
+            }
+            debug!(file, attempt, "workspace changed during warm-up, re-fetch");
+        }
+        Ok(())
+    }
+}
+
+/// Files of the workspace that match any of the globs, sorted.
+///
+/// This blocks, see [`list_files`].
+fn matching_files(workspace: &Workspace, globs: &[GlobSet]) -> Result<Vec<String>> {
+    let mut files = vec![];
+    for folder in workspace.folders() {
+        files.extend(
+            list_files(folder)?
+                .iter()
+                .filter_map(|path| workspace.relativize(path))
+                .filter(|file| globs.iter().any(|glob| glob.is_match(file))),
+        );
+    }
+    files.sort_unstable();
+    files.dedup();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_globs() {
+        for (glob, path, expected) in [
+            ("src/lib.rs", "src/lib.rs", true),
+            ("./src/lib.rs", "src/lib.rs", true),
+            ("src/lib.rs", "src/main.rs", false),
+            ("src/*.rs", "src/lib.rs", true),
+            ("src/*.rs", "src/core/lib.rs", false),
+            ("src/l?b.rs", "src/lib.rs", true),
+            ("src/l?b.rs", "src/lb.rs", false),
+            ("src/**/*.rs", "src/lib.rs", true),
+            ("src/**/*.rs", "src/core/a/b.rs", true),
+            ("src/**", "src/core/a/b.rs", true),
+            ("**/mod.rs", "src/core/mod.rs", true),
+            ("**/mod.rs", "src/core/mod.rs.bak", false),
+            ("*", "src/lib.rs", false),
+            ("*.toml", "Cargo.toml", true),
+            ("src/*/*.rs", "src/ü/lib.rs", true),
+            ("src/[lm]*.rs", "src/main.rs", true),
+            ("src/[lm]*.rs", "src/core.rs", false),
+            ("src/{lib,main}.rs", "src/main.rs", true),
+            ("src/{lib,main}.rs", "src/core.rs", false),
+            ("*.md,src/*.rs", "src/lib.rs", true),
+            ("*.md,src/*.rs", "README.md", true),
+            ("*.md,src/*.rs", "Cargo.toml", false),
+            (
+                "**/**/**/*a*a*a*a*a*a*b",
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                false,
+            ),
+        ] {
+            assert_eq!(
+                parse_globs(glob).unwrap().is_match(path),
+                expected,
+                "{glob} vs {path}"
+            );
+        }
+
+        assert_eq!(parse_globs("a,,b,").unwrap().len(), 2);
+        assert!(parse_globs("src/[lib.rs").is_err());
+        assert!(parse_globs("src/{lib.rs").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Apply changes of the config files at runtime.
+//!
+//! Restarting the language servers throws away their index, which is what users try to avoid. So the config file of
+//! the workspace and the file passed via `--lsp-init-options=@<path>` are polled for changes. Settings that are safe to
+//! change at runtime are applied directly, language server settings are pushed via `workspace/didChangeConfiguration`.
+//! All other changes only take effect after a restart.
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use tokio::time::MissedTickBehavior;
+use tokio_util::task::AbortOnDropHandle;
+use toml::Table;
+use tracing::{debug, info, warn};
+
+use crate::{
+    Args,
+    config_file::{ConfigFile, changed_keys},
+    logging::LogFilterHandle,
+    lsp::{server::LanguageServer, workspace::SharedWorkspace},
+    mcp::tools::ToolsCLIConfig,
+    resolve_languages,
+};
+
+/// How often the config files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keys of the config file that are applied at runtime.
+const RELOADABLE: &[&str] = &[
+    "include-workspace-root",
+    "log-filter",
+    "lsp-max-concurrency",
+    "max-response-chars",
+    "path-style",
+    "rust-features",
+    "rust-proc-macro",
+    "rust-target",
+    "tool-timeout",
+    "verbose",
+];
+
+/// Watches the config files, see module docs.
+///
+/// Without `--workspace`, the config file cannot be located at startup, so only the init options file is watched.
+#[derive(Debug, Clone)]
+pub(crate) struct HotReload {
+    config_file: ConfigFile,
+    log_filter: Arc<LogFilterHandle>,
+}
+
+impl HotReload {
+    pub(crate) fn new(config_file: ConfigFile, log_filter: LogFilterHandle) -> Self {
+        Self {
+            config_file,
+            log_filter: Arc::new(log_filter),
+        }
+    }
+
+    /// Start watching the config files, stops when the returned handle is dropped.
+    pub(crate) fn start(
+        self,
+        servers: Arc<[LanguageServer]>,
+        workspace: SharedWorkspace,
+        tools_cfg: ToolsCLIConfig,
+    ) -> AbortOnDropHandle<()> {
+        AbortOnDropHandle::new(tokio::spawn(watch(Reloader {
+            hot_reload: self,
+            servers,
+            workspace,
+            tools_cfg,
+        })))
+    }
+}
+
+#[derive(Debug)]
+struct Reloader {
+    hot_reload: HotReload,
+    servers: Arc<[LanguageServer]>,
+    workspace: SharedWorkspace,
+    tools_cfg: ToolsCLIConfig,
+}
+
+async fn watch(reloader: Reloader) {
+    let config_file = &reloader.hot_reload.config_file;
+    let (mut table, mut paths) = match config_file
+        .table()
+        .and_then(|table| Ok((table, watched_paths(config_file, &config_file.reparse()?))))
+    {
+        Ok(res) => res,
+        Err(e) => {
+            warn!(%e, "cannot read config, hot-reload disabled");
+            return;
+        }
+    };
+    let mut last_stamps = stamps(&paths).await;
+    debug!(paths=?paths, "watch config files");
+
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+
+        let current = stamps(&paths).await;
+        if current == last_stamps {
+            continue;
+        }
+        last_stamps = current;
+
+        match reloader.reload(&table).await {
+            Ok((new_table, args)) => {
+                table = new_table;
+                let new_paths = watched_paths(config_file, &args);
+                if new_paths != paths {
+                    paths = new_paths;
+                    last_stamps = stamps(&paths).await;
+                }
+            }
+            Err(e) => {
+                warn!(%e, "cannot reload config, keep the previous one");
+            }
+        }
+    }
+}
+
+impl Reloader {
+    /// Apply the current config, `table` is the content of the config file that was applied last.
+    async fn reload(&self, table: &Table) -> Result<(Table, Args)> {
+        let config_file = &self.hot_reload.config_file;
+        let args = config_file.reparse::<Args>()?;
+        let new_table = config_file.table()?;
+
+        let restart = restart_keys(table, &new_table);
+        if !restart.is_empty() {
+            warn!(
+                keys = %restart.iter().join(","),
+                "config changes only take effect after a restart",
+            );
+        }
+
+        self.hot_reload.log_filter.reload(&args.logging_cfg)?;
+        self.tools_cfg.reload(&args.tools_cfg);
+
+        let workspace_root = self.workspace.get().root().to_owned();
+        let languages = resolve_languages(
+            &args.programming_language,
+            &args.secondary_language,
+            &args.language_cfg,
+            &args.lsp_overrides_cfg,
+            &workspace_root,
+        )?;
+        for server in self.servers.iter() {
+            args.scheduler_cfg.apply(server.scheduler());
+
+            if let Some((_lang, quirks, _secondary)) = languages
+                .iter()
+                .find(|(lang, _quirks, _secondary)| lang.name() == server.name())
+            {
+                server.set_settings_quirks(Arc::clone(quirks));
+            }
+            let settings = server
+                .load_settings(&workspace_root)
+                .with_context(|| format!("load configuration for {}", server.name()))?;
+            if settings != server.handler().settings() {
+                info!(
+                    lang = server.name(),
+                    "push changed language server settings"
+                );
+                server
+                    .push_settings(settings)
+                    .await
+                    .with_context(|| format!("push configuration to {}", server.name()))?;
+            }
+        }
+
+        info!("config reloaded");
+        Ok((new_table, args))
+    }
+}
+
+/// Changed keys of the config file that are not applied at runtime.
+fn restart_keys<'a>(old: &'a Table, new: &'a Table) -> Vec<&'a str> {
+    changed_keys(old, new)
+        .into_iter()
+        .filter(|key| !RELOADABLE.contains(&key.replace('_', "-").as_str()))
+        .collect()
+}
+
+/// Files whose changes trigger a reload.
+fn watched_paths(config_file: &ConfigFile, args: &Args) -> Vec<PathBuf> {
+    config_file
+        .path()
+        .into_iter()
+        .chain(args.lsp_overrides_cfg.init_options_file())
+        .map(Path::to_owned)
+        .collect()
+}
+
+/// Modification time and size of the files, `None` for files that do not exist.
+async fn stamps(paths: &[PathBuf]) -> Vec<Option<(Option<SystemTime>, u64)>> {
+    let mut stamps = Vec::with_capacity(paths.len());
+    for path in paths {
+        let stamp = tokio::fs::metadata(path)
+            .await
+            .ok()
+            .map(|metadata| (metadata.modified().ok(), metadata.len()));
+        stamps.push(stamp);
+    }
+    stamps
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_restart_keys() {
+        let old = toml::from_str::<Table>(
+            r#"
+            max-response-chars = 1000
+            path_style = "relative"
+            lsp-command = "rust-analyzer"
+            cache-size = 10
+            "#,
+        )
+        .unwrap();
+        let new = toml::from_str::<Table>(
+            r#"
+            max-response-chars = 2000
+            path_style = "absolute"
+            lsp-command = "ra-multiplex"
+            log-filter = "debug"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(restart_keys(&old, &old), Vec::<&str>::new());
+        // underscores are accepted like in the config file itself
+        assert_eq!(restart_keys(&old, &new), ["cache-size", "lsp-command"]);
+    }
+
+    #[test]
+    fn test_reloadable_keys_exist() {
+        let command = Args::command();
+        for key in RELOADABLE {
+            assert!(
+                command
+                    .get_arguments()
+                    .any(|arg| arg.get_long() == Some(*key)),
+                "{key}"
+            );
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Signals that ask the server to shut down gracefully.
+//!
+//! This is `SIGTERM` on Unix and ctrl-c / ctrl-break on Windows.
+use anyhow::{Context, Result};
+
+/// Receives shutdown signals.
+#[derive(Debug)]
+pub(crate) struct ShutdownSignal {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+
+    #[cfg(windows)]
+    ctrl_break: tokio::signal::windows::CtrlBreak,
+}
+
+impl ShutdownSignal {
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            terminate: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .context("create signal handler")?,
+            #[cfg(windows)]
+            ctrl_c: tokio::signal::windows::ctrl_c().context("create ctrl-c handler")?,
+            #[cfg(windows)]
+            ctrl_break: tokio::signal::windows::ctrl_break()
+                .context("create ctrl-break handler")?,
+        })
+    }
+
+    /// Wait for the next shutdown signal.
+    pub(crate) async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.terminate.recv().await;
+
+        #[cfg(windows)]
+        tokio::select! {
+            _ = self.ctrl_c.recv() => {}
+            _ = self.ctrl_break.recv() => {}
+        }
+    }
+}
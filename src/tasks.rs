@@ -1,20 +1,204 @@
-use std::panic::AssertUnwindSafe;
+use std::{
+    collections::HashMap,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use anyhow::{Context, Error, Result};
+use anyhow::{Context, Error, Result, anyhow};
 use futures::{FutureExt, future::BoxFuture};
-use tokio::task::JoinSet;
+use rmcp::schemars;
+use tokio::task::{Id, JoinSet};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
-#[derive(Debug, Default)]
+/// Factory for the futures of a (restartable) task.
+type TaskFn = Box<dyn FnMut(CancellationToken) -> BoxFuture<'static, Result<()>> + Send>;
+
+/// What happens when a task ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RestartPolicy {
+    /// Report the result to [`TaskManager::run`] and [`TaskManager::shutdown`].
+    Never,
+
+    /// Restart after errors and panics.
+    OnFailure {
+        /// Delay before the first restart, doubled for every further restart.
+        backoff: Duration,
+
+        /// Report the error once the task was restarted that many times.
+        max_restarts: usize,
+    },
+
+    /// Restart whenever the task ends, also if it succeeded.
+    #[cfg_attr(not(test), expect(dead_code, reason = "no task needs it yet"))]
+    Always {
+        /// Delay before the first restart, doubled for every further restart.
+        backoff: Duration,
+
+        /// Report the result once the task was restarted that many times.
+        max_restarts: usize,
+    },
+}
+
+impl RestartPolicy {
+    /// Delay before the next restart, `None` if the task must not be restarted.
+    fn restart_delay(&self, res: &Result<()>, restarts: usize) -> Option<Duration> {
+        let (backoff, max_restarts) = match *self {
+            Self::Never => return None,
+            Self::OnFailure { .. } if res.is_ok() => return None,
+            Self::OnFailure {
+                backoff,
+                max_restarts,
+            }
+            | Self::Always {
+                backoff,
+                max_restarts,
+            } => (backoff, max_restarts),
+        };
+        (restarts < max_restarts).then(|| {
+            backoff.saturating_mul(2u32.saturating_pow(restarts.try_into().unwrap_or(u32::MAX)))
+        })
+    }
+}
+
+/// Lifecycle state of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TaskState {
+    Running,
+
+    /// Failed or ended and waits for the restart.
+    Restarting,
+
+    Finished,
+    Failed,
+
+    /// Did not stop within the shutdown grace period and was aborted.
+    Killed,
+}
+
+/// Status of a task at a point in time.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub(crate) struct TaskStatus {
+    name: String,
+    state: TaskState,
+
+    /// number of restarts so far
+    restarts: usize,
+
+    /// error of the last failed run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+
+    /// seconds since the last (re)start, until the end if the task is not running
+    uptime_secs: f64,
+}
+
+/// Bookkeeping of a task.
+#[derive(Debug)]
+struct TaskRecord {
+    name: String,
+    state: TaskState,
+    restarts: usize,
+    last_error: Option<String>,
+    started: Instant,
+    ended: Option<Instant>,
+}
+
+/// Statuses of all tasks of a [`TaskManager`], shared by all clones.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TaskStatuses {
+    records: Arc<Mutex<Vec<TaskRecord>>>,
+}
+
+impl TaskStatuses {
+    /// Current status of all tasks, in the order they were spawned.
+    pub(crate) fn snapshot(&self) -> Vec<TaskStatus> {
+        let now = Instant::now();
+        self.records
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|record| TaskStatus {
+                name: record.name.clone(),
+                state: record.state,
+                restarts: record.restarts,
+                last_error: record.last_error.clone(),
+                uptime_secs: record
+                    .ended
+                    .unwrap_or(now)
+                    .duration_since(record.started)
+                    .as_secs_f64(),
+            })
+            .collect()
+    }
+
+    /// Restarts of all tasks since startup.
+    pub(crate) fn total_restarts(&self) -> usize {
+        self.records
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|record| record.restarts)
+            .sum()
+    }
+
+    fn register(&self, name: &str) -> usize {
+        let mut records = self.records.lock().expect("not poisoned");
+        records.push(TaskRecord {
+            name: name.to_owned(),
+            state: TaskState::Running,
+            restarts: 0,
+            last_error: None,
+            started: Instant::now(),
+            ended: None,
+        });
+        records.len() - 1
+    }
+
+    fn update(&self, idx: usize, f: impl FnOnce(&mut TaskRecord)) {
+        f(&mut self.records.lock().expect("not poisoned")[idx]);
+    }
+}
+
+/// Default for [`TaskManager::with_shutdown_grace`].
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
 pub(crate) struct TaskManager {
     tasks: JoinSet<(Result<()>, String)>,
     cancel: CancellationToken,
+    statuses: TaskStatuses,
+
+    /// Name and status index of every task, used to report tasks that were aborted.
+    ids: HashMap<Id, (String, usize)>,
+
+    shutdown_grace: Duration,
 }
 
 impl TaskManager {
     pub(crate) fn new() -> Self {
-        Self::default()
+        Self {
+            tasks: JoinSet::new(),
+            cancel: CancellationToken::new(),
+            statuses: TaskStatuses::default(),
+            ids: HashMap::new(),
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
+        }
+    }
+
+    /// Time that every task has to stop after [`shutdown`](Self::shutdown) was called, before it is killed.
+    pub(crate) fn with_shutdown_grace(self, shutdown_grace: Duration) -> Self {
+        Self {
+            shutdown_grace,
+            ..self
+        }
+    }
+
+    /// Handle to the statuses of all tasks.
+    pub(crate) fn statuses(&self) -> TaskStatuses {
+        self.statuses.clone()
     }
 
     pub(crate) fn spawn<F, Fut, S>(&mut self, f: F, name: S)
@@ -24,45 +208,94 @@ impl TaskManager {
         S: Into<String>,
     {
         let name: String = name.into();
-        let future = f(self.cancel.clone());
-        self.spawn_inner(Box::pin(future), name);
+        let mut future = Some(f(self.cancel.clone()).boxed());
+        self.spawn_inner(
+            Box::new(move |_cancel| future.take().expect("never restarted")),
+            name,
+            RestartPolicy::Never,
+        );
+    }
+
+    /// Spawn task that is restarted according to the given policy.
+    ///
+    /// The task is not restarted after [`shutdown`](Self::shutdown) was called.
+    pub(crate) fn spawn_supervised<F, Fut, S>(&mut self, mut f: F, name: S, policy: RestartPolicy)
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+        S: Into<String>,
+    {
+        let name: String = name.into();
+        self.spawn_inner(Box::new(move |cancel| f(cancel).boxed()), name, policy);
     }
 
     /// Non-generic version of `spawn`.
     #[inline(never)]
-    fn spawn_inner(&mut self, future: BoxFuture<'static, Result<()>>, name: String) {
-        self.tasks.spawn(async move {
-            debug!(phase = "spawn", name = name.as_str(), "task");
-
-            let res = AssertUnwindSafe(future).catch_unwind().await;
+    fn spawn_inner(&mut self, mut f: TaskFn, name: String, policy: RestartPolicy) {
+        let cancel = self.cancel.clone();
+        let statuses = self.statuses.clone();
+        let idx = statuses.register(&name);
+        self.ids.insert(
+            self.tasks
+                .spawn({
+                    let name = name.clone();
+                    async move {
+                        let mut restarts = 0;
+                        loop {
+                            let res = run_task(f(cancel.clone()), &name).await;
+                            let delay = if cancel.is_cancelled() {
+                                None
+                            } else {
+                                policy.restart_delay(&res, restarts)
+                            };
+                            statuses.update(idx, |record| {
+                                record.ended = Some(Instant::now());
+                                if let Err(e) = &res {
+                                    record.last_error = Some(format!("{e:#}"));
+                                }
+                                record.state = match (delay, &res) {
+                                    (Some(_), _) => TaskState::Restarting,
+                                    (None, Ok(())) => TaskState::Finished,
+                                    (None, Err(_)) => TaskState::Failed,
+                                };
+                            });
+                            let Some(delay) = delay else {
+                                return (res, name);
+                            };
 
-            let res = match res {
-                Ok(Ok(())) => {
-                    debug!(phase = "complete", name = name.as_str(), "task");
-                    Ok(())
-                }
-                Ok(Err(e)) => {
-                    warn!(phase = "error", name = name.as_str(), error = %e, "task");
-                    Err(e)
-                }
-                Err(e) => {
-                    let msg = e
-                        .downcast_ref::<String>()
-                        .map(|s| s.to_owned())
-                        .or(e.downcast_ref::<&str>().map(|s| (*s).to_owned()));
-                    warn!(
-                        phase = "error",
-                        name = name.as_str(),
-                        msg = msg.as_deref(),
-                        "task"
-                    );
-                    Err(Error::msg(msg.unwrap_or_else(|| "<unknown>".to_owned())).context("panic"))
-                }
-            };
-
-            let res = res.with_context(|| format!("task {name}"));
-            (res, name)
-        });
+                            restarts += 1;
+                            warn!(
+                                phase = "restart",
+                                name = name.as_str(),
+                                restarts,
+                                delay_ms = delay.as_millis(),
+                                "task"
+                            );
+                            tokio::select! {
+                                _ = tokio::time::sleep(delay) => {}
+                                _ = cancel.cancelled() => {
+                                    statuses.update(idx, |record| {
+                                        record.state = if res.is_ok() {
+                                            TaskState::Finished
+                                        } else {
+                                            TaskState::Failed
+                                        };
+                                    });
+                                    return (res, name);
+                                }
+                            }
+                            statuses.update(idx, |record| {
+                                record.state = TaskState::Running;
+                                record.restarts = restarts;
+                                record.started = Instant::now();
+                                record.ended = None;
+                            });
+                        }
+                    }
+                })
+                .id(),
+            (name, idx),
+        );
     }
 
     pub(crate) async fn run(&mut self) -> Error {
@@ -78,14 +311,47 @@ impl TaskManager {
         }
     }
 
+    /// Cancel all tasks and wait for them to stop.
+    ///
+    /// Tasks that do not stop within the shutdown grace period are killed and reported as errors.
     pub(crate) async fn shutdown(mut self) -> Result<()> {
         self.cancel.cancel();
+        let deadline = tokio::time::Instant::now() + self.shutdown_grace;
+        let mut aborted = false;
 
         let mut res = Ok(());
-        while let Some(task_res) = self.tasks.join_next().await {
+        loop {
+            let task_res = tokio::select! {
+                next = self.tasks.join_next_with_id() => match next {
+                    Some(task_res) => task_res,
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline), if !aborted => {
+                    self.tasks.abort_all();
+                    aborted = true;
+                    continue;
+                }
+            };
             let task_res = match task_res {
-                Ok((Ok(()), _name)) => Ok(()),
-                Ok((Err(e), _name)) => Err(e),
+                Ok((_id, (Ok(()), _name))) => Ok(()),
+                Ok((_id, (Err(e), _name))) => Err(e),
+                Err(e) if e.is_cancelled() => {
+                    let (name, idx) = self.ids.remove(&e.id()).expect("spawned by us");
+                    warn!(
+                        phase = "killed",
+                        name = name.as_str(),
+                        grace_secs = self.shutdown_grace.as_secs_f64(),
+                        "task"
+                    );
+                    self.statuses.update(idx, |record| {
+                        record.state = TaskState::Killed;
+                        record.ended = Some(Instant::now());
+                    });
+                    Err(anyhow!(
+                        "task {name} killed, did not stop within {}s",
+                        self.shutdown_grace.as_secs_f64()
+                    ))
+                }
                 Err(e) => Err(Error::new(e).context("join")),
             };
 
@@ -96,8 +362,49 @@ impl TaskManager {
     }
 }
 
+/// Run task once and convert panics to errors.
+async fn run_task(future: BoxFuture<'static, Result<()>>, name: &str) -> Result<()> {
+    debug!(phase = "spawn", name, "task");
+
+    let started = Instant::now();
+    let res = AssertUnwindSafe(future).catch_unwind().await;
+    let uptime_ms = started.elapsed().as_millis();
+
+    let res = match res {
+        Ok(Ok(())) => {
+            debug!(phase = "complete", name, uptime_ms, "task");
+            Ok(())
+        }
+        Ok(Err(e)) => {
+            warn!(phase = "error", name, uptime_ms, error = %e, "task");
+            Err(e)
+        }
+        Err(e) => {
+            let msg = e
+                .downcast_ref::<String>()
+                .map(|s| s.to_owned())
+                .or(e.downcast_ref::<&str>().map(|s| (*s).to_owned()));
+            warn!(
+                phase = "error",
+                name,
+                uptime_ms,
+                msg = msg.as_deref(),
+                "task"
+            );
+            Err(Error::msg(msg.unwrap_or_else(|| "<unknown>".to_owned())).context("panic"))
+        }
+    };
+
+    res.with_context(|| format!("task {name}"))
+}
+
 #[cfg(test)]
 mod test {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
     use super::*;
 
     #[tokio::test]
@@ -133,4 +440,197 @@ mod test {
         let err = tasks.shutdown().await.unwrap_err();
         assert_eq!(format!("{err:#}"), "task test: panic: foo");
     }
+
+    #[tokio::test]
+    async fn test_restart_on_failure() {
+        let mut tasks = TaskManager::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_captured = Arc::clone(&calls);
+        tasks.spawn_supervised(
+            move |_token| {
+                let calls = Arc::clone(&calls_captured);
+                async move {
+                    match calls.fetch_add(1, Ordering::SeqCst) {
+                        0 => Err(Error::msg("foo")),
+                        1 => panic!("bar"),
+                        _ => Ok(()),
+                    }
+                }
+            },
+            "test",
+            RestartPolicy::OnFailure {
+                backoff: Duration::ZERO,
+                max_restarts: 5,
+            },
+        );
+        let err = tasks.run().await;
+        assert_eq!(format!("{err:#}"), "task 'test' returned early");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_restart_limit() {
+        let mut tasks = TaskManager::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_captured = Arc::clone(&calls);
+        tasks.spawn_supervised(
+            move |_token| {
+                let calls = Arc::clone(&calls_captured);
+                async move {
+                    let call = calls.fetch_add(1, Ordering::SeqCst);
+                    Err(Error::msg(format!("call {call}")))
+                }
+            },
+            "test",
+            RestartPolicy::OnFailure {
+                backoff: Duration::ZERO,
+                max_restarts: 2,
+            },
+        );
+        let err = tasks.run().await;
+        assert_eq!(format!("{err:#}"), "task test: call 2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_restart_always() {
+        let mut tasks = TaskManager::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_captured = Arc::clone(&calls);
+        tasks.spawn_supervised(
+            move |_token| {
+                let calls = Arc::clone(&calls_captured);
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            },
+            "test",
+            RestartPolicy::Always {
+                backoff: Duration::ZERO,
+                max_restarts: 3,
+            },
+        );
+        let err = tasks.run().await;
+        assert_eq!(format!("{err:#}"), "task 'test' returned early");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_no_restart_after_shutdown() {
+        let mut tasks = TaskManager::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_captured = Arc::clone(&calls);
+        tasks.spawn_supervised(
+            move |token: CancellationToken| {
+                let calls = Arc::clone(&calls_captured);
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    token.cancelled().await;
+                    Ok(())
+                }
+            },
+            "test",
+            RestartPolicy::Always {
+                backoff: Duration::ZERO,
+                max_restarts: 3,
+            },
+        );
+        tasks.shutdown().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_restart_delay() {
+        let policy = RestartPolicy::OnFailure {
+            backoff: Duration::from_secs(1),
+            max_restarts: 3,
+        };
+        let err = Err(Error::msg("foo"));
+        assert_eq!(policy.restart_delay(&Ok(()), 0), None);
+        assert_eq!(policy.restart_delay(&err, 0), Some(Duration::from_secs(1)));
+        assert_eq!(policy.restart_delay(&err, 2), Some(Duration::from_secs(4)));
+        assert_eq!(policy.restart_delay(&err, 3), None);
+        assert_eq!(RestartPolicy::Never.restart_delay(&err, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_statuses() {
+        let mut tasks = TaskManager::new();
+        let statuses = tasks.statuses();
+        tasks.spawn(
+            async |token: CancellationToken| {
+                token.cancelled().await;
+                Ok(())
+            },
+            "idle",
+        );
+        tasks.spawn_supervised(
+            |_token| async { Err(Error::msg("foo")) },
+            "broken",
+            RestartPolicy::OnFailure {
+                backoff: Duration::ZERO,
+                max_restarts: 1,
+            },
+        );
+        tasks.run().await;
+
+        let strip = |statuses: Vec<TaskStatus>| {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    assert!(status.uptime_secs >= 0.0);
+                    (
+                        status.name,
+                        status.state,
+                        status.restarts,
+                        status.last_error,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            strip(statuses.snapshot()),
+            [
+                ("idle".to_owned(), TaskState::Running, 0, None),
+                (
+                    "broken".to_owned(),
+                    TaskState::Failed,
+                    1,
+                    Some("task broken: foo".to_owned())
+                ),
+            ],
+        );
+
+        tasks.shutdown().await.unwrap();
+        assert_eq!(statuses.snapshot()[0].state, TaskState::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_kill() {
+        let mut tasks = TaskManager::new().with_shutdown_grace(Duration::from_millis(10));
+        let statuses = tasks.statuses();
+        tasks.spawn(
+            async |token: CancellationToken| {
+                token.cancelled().await;
+                Ok(())
+            },
+            "idle",
+        );
+        tasks.spawn(async |_token| futures::future::pending().await, "stuck");
+
+        let err = tasks.shutdown().await.unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "task stuck killed, did not stop within 0.01s"
+        );
+        assert_eq!(
+            statuses
+                .snapshot()
+                .into_iter()
+                .map(|status| status.state)
+                .collect::<Vec<_>>(),
+            [TaskState::Finished, TaskState::Killed],
+        );
+    }
 }
@@ -1,18 +1,37 @@
 // crates used by main binary or other helps
 use anyhow as _;
+use async_trait as _;
+use bytes as _;
 use clap as _;
 use dotenvy as _;
+#[cfg(feature = "install")]
+use flate2 as _;
 use futures as _;
+use globset as _;
+use http as _;
+use http_body_util as _;
+use hyper_util as _;
+use ignore as _;
 use insta as _;
 use itertools as _;
+use jsonrpsee as _;
 use lsp_client as _;
 use lsp_types as _;
+use percent_encoding as _;
+use regex as _;
+#[cfg(feature = "install")]
+use reqwest as _;
 use rmcp as _;
 use serde as _;
 use serde_json as _;
+use sha2 as _;
+#[cfg(feature = "install")]
+use tar as _;
 use tokio as _;
 use tokio_stream as _;
 use tokio_util as _;
+use toml as _;
+use tower_service as _;
 use tracing as _;
 use tracing_log as _;
 use tracing_subscriber as _;
@@ -61,3 +80,83 @@ fn test_dotenv_invalid() {
         .failure()
         .stderr(predicates::str::contains("Error parsing line"));
 }
+
+#[test]
+fn test_config_file_invalid() {
+    let workspace = TempDir::new().unwrap();
+    std::fs::write(
+        workspace.path().join(".common-sense-coder.toml"),
+        "lsp-command = \"evil\"",
+    )
+    .unwrap();
+    Command::cargo_bin(pkg_name!())
+        .unwrap()
+        .arg("--workspace")
+        .arg(workspace.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`lsp-command` can only be passed via CLI or environment",
+        ));
+}
+
+#[test]
+fn test_config_file_init_options() {
+    for key in ["lsp-init-options", "custom-lsp-init-options"] {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(
+            workspace.path().join(".common-sense-coder.toml"),
+            format!(r#"{key} = {{ check = {{ overrideCommand = ["sh", "-c", "evil"] }} }}"#),
+        )
+        .unwrap();
+        Command::cargo_bin(pkg_name!())
+            .unwrap()
+            .arg("--workspace")
+            .arg(workspace.path())
+            .arg("print-config")
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains(format!(
+                "`{key}` can only be passed via CLI or environment"
+            )));
+    }
+}
+
+#[test]
+fn test_listen_without_auth() {
+    let workspace = TempDir::new().unwrap();
+    Command::cargo_bin(pkg_name!())
+        .unwrap()
+        .arg("--workspace")
+        .arg(workspace.path())
+        .arg("--listen=0.0.0.0:0")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--listen=0.0.0.0:0` requires `--listen-auth-token`",
+        ));
+}
+
+#[test]
+fn test_print_config() {
+    let workspace = TempDir::new().unwrap();
+    std::fs::write(
+        workspace.path().join(".common-sense-coder.toml"),
+        "lsp-ready-timeout = 120",
+    )
+    .unwrap();
+    Command::cargo_bin(pkg_name!())
+        .unwrap()
+        .arg("--workspace")
+        .arg(workspace.path())
+        .arg("print-config")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            r#""name": "lsp-ready-timeout",
+      "values": [
+        "120"
+      ],
+      "source": "config_file""#,
+        ));
+}
@@ -0,0 +1,144 @@
+use serde_json::Value;
+
+use crate::setup::TestSetup;
+
+#[tokio::test]
+async fn test_call() {
+    let output =
+        TestSetup::run(&["--call=find_symbol", r#"--args={"query": "MyMainStruct"}"#]).await;
+    assert!(output.status.success(), "{output:?}");
+
+    let result = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    insta::assert_json_snapshot!(result, @r#"
+    {
+      "symbols": [
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#);
+}
+
+#[tokio::test]
+async fn test_call_error() {
+    let output = TestSetup::run(&["--call=symbol_info", r#"--args={"file": "foo.rs"}"#]).await;
+    assert!(!output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+
+    let output = TestSetup::run(&["--call=find_symbol", "--listen=127.0.0.1:0"]).await;
+    assert!(!output.status.success(), "{output:?}");
+}
+
+#[tokio::test]
+async fn test_batch() {
+    let output = TestSetup::run_with_stdin(
+        &["--batch"],
+        r#"{"tool": "find_symbol", "args": {"query": "MyMainStruct"}}
+
+{"tool": "symbol_info", "args": {"file": "foo.rs", "name": "foo"}}
+not json
+{"tool": "server_status"}
+"#,
+    )
+    .await;
+    assert!(output.status.success(), "{output:?}");
+
+    let mut results = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 4, "{results:?}");
+
+    // version depends on the installed language server
+    results[3]["result"]["servers"][0]["server_version"] = Value::from("<version>");
+    for task in results[3]["result"]["tasks"].as_array_mut().unwrap() {
+        task["uptime_secs"] = Value::from("<uptime>");
+    }
+
+    insta::assert_json_snapshot!(results, @r#"
+    [
+      {
+        "result": {
+          "symbols": [
+            {
+              "name": "MyMainStruct",
+              "kind": "Struct",
+              "deprecated": false,
+              "location": {
+                "file": "src/lib.rs",
+                "line": 39,
+                "character": 19
+              }
+            }
+          ]
+        }
+      },
+      {
+        "error": "call tool `symbol_info`: Mcp error: -32602: file not found: foo.rs\n- paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined({\"request_id\":2})"
+      },
+      {
+        "error": "parse tool call: expected ident at line 1 column 2"
+      },
+      {
+        "result": {
+          "servers": [
+            {
+              "language": "rust",
+              "server_name": "rust-analyzer",
+              "server_version": "<version>",
+              "version_check": {
+                "status": "ok"
+              },
+              "ready": true,
+              "init_timed_out": false,
+              "missing_init_parts": [],
+              "running": 0,
+              "queued": 0
+            }
+          ],
+          "tasks": [
+            {
+              "name": "lsp.rust.stdout.txt",
+              "state": "running",
+              "restarts": 0,
+              "uptime_secs": "<uptime>"
+            },
+            {
+              "name": "lsp.rust.stdin.txt",
+              "state": "running",
+              "restarts": 0,
+              "uptime_secs": "<uptime>"
+            },
+            {
+              "name": "progress guard",
+              "state": "running",
+              "restarts": 0,
+              "uptime_secs": "<uptime>"
+            },
+            {
+              "name": "server messages",
+              "state": "running",
+              "restarts": 0,
+              "uptime_secs": "<uptime>"
+            },
+            {
+              "name": "diagnostics",
+              "state": "running",
+              "restarts": 0,
+              "uptime_secs": "<uptime>"
+            }
+          ]
+        }
+      }
+    ]
+    "#);
+}
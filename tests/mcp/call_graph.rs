@@ -0,0 +1,97 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_call_graph() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .call_graph(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("my_lib_fn")),
+        ]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "graph": "digraph calls {\n  rankdir=LR;\n  node [shape=box];\n  n0 [label=\"my_lib_fn\\nsrc/lib.rs:14\", style=bold];\n  n1 [label=\"my_lib_fn\\nworkspace_member/src/lib.rs:1\"];\n  n2 [label=\"my_sub_lib_fn\\nsrc/sub.rs:1\"];\n  n3 [label=\"my_private_lib_fn\\nsrc/lib.rs:23\"];\n  n4 [label=\"foo\\nsrc/lib.rs:28\"];\n  n0 -> n1;\n  n0 -> n2;\n  n0 -> n3;\n  n0 -> n4;\n}\n",
+      "nodes": 5,
+      "edges": 4
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_call_graph_incoming() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .call_graph(map([
+            ("file", json!("src/lib.rs")),
+            ("line", json!(23)),
+            ("character", json!(4)),
+            ("direction", json!("incoming")),
+        ]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "graph": "digraph calls {\n  rankdir=LR;\n  node [shape=box];\n  n0 [label=\"my_private_lib_fn\\nsrc/lib.rs:23\", style=bold];\n  n1 [label=\"my_lib_fn\\nsrc/lib.rs:14\"];\n  n1 -> n0;\n}\n",
+      "nodes": 2,
+      "edges": 1
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_call_graph_mermaid() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .call_graph(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("my_private_lib_fn")),
+            ("direction", json!("incoming")),
+            ("format", json!("mermaid")),
+        ]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "graph": "flowchart LR\n  n0[\"my_private_lib_fn<br/>src/lib.rs:23\"]\n  n1[\"my_lib_fn<br/>src/lib.rs:14\"]\n  n1 --> n0\n  style n0 stroke-width:3px\n",
+      "nodes": 2,
+      "edges": 1
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_call_graph_not_callable() {
+    let setup = TestSetup::new().await;
+
+    let err = setup
+        .call_graph(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("MyMainStruct")),
+        ]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(err, @r#"
+    {
+      "code": -32602,
+      "message": "no function or method found: `MyMainStruct` in src/lib.rs\n- call graphs start at a function or method, use `file_overview` to look up the functions declared in the file",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
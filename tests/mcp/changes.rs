@@ -0,0 +1,161 @@
+use std::{path::Path, process::Command};
+
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+const LIB_V1: &str = r#"pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn norm(&self) -> i64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn flip(&self) -> Self {
+        Self {
+            x: self.y,
+            y: self.x,
+        }
+    }
+}
+
+pub fn old_helper() -> u64 {
+    1
+}
+"#;
+
+const LIB_V2: &str = r#"pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn norm(&self) -> i64 {
+        self.x.abs().max(self.y.abs())
+    }
+
+    pub fn flip(&self) -> Self {
+        Self {
+            x: self.y,
+            y: self.x,
+        }
+    }
+}
+
+pub fn new_helper() -> u64 {
+    2
+}
+"#;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// Add a field to `Point`.
+fn with_z(lib: &str) -> String {
+    lib.replace("pub y: i64,", "pub y: i64,\n    pub z: i64,")
+}
+
+#[tokio::test]
+async fn test_changed_symbols() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"points\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("README.md"), "# Points\n").unwrap();
+    std::fs::write(root.join("src/lib.rs"), LIB_V1).unwrap();
+    git(&root, &["init", "--quiet"]);
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "--quiet", "-m", "v1"]);
+    git(&root, &["mv", "README.md", "POINTS.md"]);
+    std::fs::write(root.join("src/lib.rs"), with_z(LIB_V1)).unwrap();
+    git(&root, &["commit", "--quiet", "--all", "-m", "v2"]);
+    std::fs::write(root.join("src/lib.rs"), with_z(LIB_V2)).unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    // working tree vs `HEAD`
+    let results = setup.changed_symbols(map([])).await.unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "files": [
+        {
+          "file": "src/lib.rs",
+          "change": "modified",
+          "symbols": [
+            {
+              "line": 8,
+              "name": "norm",
+              "kind": "Method",
+              "container": "impl Point",
+              "change": "modified"
+            },
+            {
+              "line": 20,
+              "name": "new_helper",
+              "kind": "Function",
+              "change": "added"
+            },
+            {
+              "line": 20,
+              "name": "old_helper",
+              "kind": "Function",
+              "change": "removed"
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+
+    let results = setup
+        .changed_symbols(map([("base", json!("HEAD~1")), ("head", json!("HEAD"))]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "files": [
+        {
+          "file": "POINTS.md",
+          "old_file": "README.md",
+          "change": "renamed",
+          "symbols": []
+        },
+        {
+          "file": "src/lib.rs",
+          "change": "modified",
+          "symbols": [
+            {
+              "line": 4,
+              "name": "z",
+              "kind": "Field",
+              "container": "Point",
+              "change": "added"
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+
+    let err = setup
+        .changed_symbols(map([("base", json!("does-not-exist"))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(err["message"], @r#""unknown revision: does-not-exist: git rev-parse failed: fatal: Needed a single revision""#);
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,138 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_run_check_disabled() {
+    for args in [&[][..], &["--enable-run-check-tool", "--workspace-jail"]] {
+        let setup = TestSetup::new_with_args(args).await;
+
+        assert!(
+            !setup
+                .list_all_tools()
+                .await
+                .iter()
+                .any(|tool| tool.name == "run_check")
+        );
+
+        setup.shutdown().await;
+    }
+}
+
+#[tokio::test]
+async fn test_run_check() {
+    let setup = TestSetup::new_with_args(&["--enable-run-check-tool"]).await;
+
+    let results = setup.run_check(map([])).await.unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "errors": 0,
+      "warnings": 3,
+      "diagnostics": [
+        {
+          "location": {
+            "file": "src/lib.rs",
+            "line": 32,
+            "character": 4
+          },
+          "severity": "warning",
+          "code": "dead_code",
+          "message": "function `main` is never used\n`#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default"
+        },
+        {
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          },
+          "severity": "warning",
+          "code": "dead_code",
+          "message": "struct `MyMainStruct` is never constructed"
+        },
+        {
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          },
+          "severity": "warning",
+          "code": "dead_code",
+          "message": "struct `MyMainStruct` is never constructed\n`#[warn(dead_code)]` (part of `#[warn(unused)]`) on by default"
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_run_check_after_edit() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"edited\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), "pub fn f() -> u32 {\n    1\n}\n").unwrap();
+
+    let setup = TestSetup::new_with_workspaces_and_args(
+        &[root.to_str().unwrap()],
+        &["--enable-run-check-tool"],
+    )
+    .await;
+
+    let results = setup
+        .run_check(map([("file", json!("src/lib.rs"))]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "errors": 0,
+      "warnings": 0,
+      "diagnostics": []
+    }
+    "#);
+
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub fn f() -> u32 {\n    \"1\"\n}\n",
+    )
+    .unwrap();
+    let results = setup
+        .run_check(map([("file", json!("src/lib.rs"))]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "errors": 1,
+      "warnings": 0,
+      "diagnostics": [
+        {
+          "location": {
+            "file": "src/lib.rs",
+            "line": 2,
+            "character": 5
+          },
+          "severity": "error",
+          "code": "E0308",
+          "message": "mismatched types\nexpected `u32`, found `&str`"
+        },
+        {
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 15
+          },
+          "severity": "hint",
+          "code": "E0308",
+          "message": "expected `u32` because of return type"
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
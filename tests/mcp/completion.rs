@@ -0,0 +1,108 @@
+use rmcp::model::Reference;
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_complete_file() {
+    let setup = TestSetup::new().await;
+
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_prompt("symbol_info"), "file", "sr").await,
+        @r#"
+    {
+      "completion": {
+        "values": [
+          "src/"
+        ],
+        "total": 1,
+        "hasMore": false
+      }
+    }
+    "#
+    );
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_prompt("find_symbol"), "file", "src/").await,
+        @r#"
+    {
+      "completion": {
+        "values": [
+          "src/lib.rs",
+          "src/sub.rs"
+        ],
+        "total": 2,
+        "hasMore": false
+      }
+    }
+    "#
+    );
+
+    let value = setup.workspace_path("src/l");
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_resource("file://{path}"), "path", &value).await,
+        @r#"
+    {
+      "completion": {
+        "values": [
+          "/fixtures/main_lib/src/lib.rs"
+        ],
+        "total": 1,
+        "hasMore": false
+      }
+    }
+    "#
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_complete_symbol() {
+    let setup = TestSetup::new().await;
+
+    // wait for language server to be ready
+    setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_prompt("symbol_info"), "name", "MyMain").await,
+        @r#"
+    {
+      "completion": {
+        "values": [
+          "MyMainStruct"
+        ],
+        "total": 1,
+        "hasMore": false
+      }
+    }
+    "#
+    );
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_prompt("find_symbol"), "query", "mymainst").await,
+        @r#"
+    {
+      "completion": {
+        "values": [
+          "MyMainStruct"
+        ],
+        "total": 1,
+        "hasMore": false
+      }
+    }
+    "#
+    );
+    insta::assert_json_snapshot!(
+        setup.complete(Reference::for_prompt("server_status"), "query", "MyMain").await,
+        @r#"
+    {
+      "completion": {
+        "values": []
+      }
+    }
+    "#
+    );
+
+    setup.shutdown().await;
+}
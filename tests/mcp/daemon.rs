@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_reconnect() {
+    let daemon_dir = TempDir::new().unwrap();
+    let mut setup = TestSetup::new_with_args(&[
+        "--daemon",
+        &format!("--daemon-dir={}", daemon_dir.path().display()),
+        "--daemon-grace-period=5",
+    ])
+    .await;
+
+    let args = map([("query", json!("MyMainStruct"))]);
+    let symbols = setup.find_symbol_ok(args.clone()).await;
+    assert!(
+        !symbols["symbols"].as_array().unwrap().is_empty(),
+        "{symbols}"
+    );
+
+    setup.reconnect().await;
+    assert_eq!(setup.find_symbol_ok(args).await, symbols);
+
+    // both sessions were served by the same daemon
+    let files = list_files(&daemon_dir);
+    assert_eq!(files.len(), 2, "{files:?}");
+    let log_path = files
+        .iter()
+        .find(|f| f.ends_with(".log"))
+        .expect("daemon log");
+    let log = std::fs::read_to_string(daemon_dir.path().join(log_path)).unwrap();
+    assert_eq!(log.matches("new MCP session").count(), 2, "{log}");
+
+    setup.shutdown().await;
+
+    // daemon stops after grace period
+    for _ in 0..600 {
+        if list_files(&daemon_dir)
+            .iter()
+            .all(|f| !f.ends_with(".sock"))
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("daemon did not stop");
+}
+
+fn list_files(dir: &TempDir) -> Vec<String> {
+    std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect()
+}
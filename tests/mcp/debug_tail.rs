@@ -0,0 +1,75 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_debug_tail_disabled() {
+    let setup = TestSetup::new().await;
+
+    assert!(
+        !setup
+            .list_all_tools()
+            .await
+            .iter()
+            .any(|tool| tool.name == "debug_tail")
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_debug_tail() {
+    let setup = TestSetup::new_with_args(&["--debug-tail=100"]).await;
+
+    setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+
+    let resp = setup.debug_tail(map([("limit", json!(2))])).await.unwrap();
+    let messages = resp["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0]["timestamp_us"].as_u64() <= messages[1]["timestamp_us"].as_u64());
+    for message in messages {
+        assert_eq!(message["peer"], json!("lsp.rust"));
+        assert!(message["message"]["jsonrpc"] == json!("2.0"), "{message}");
+    }
+
+    let resp = setup.debug_tail(serde_json::Map::new()).await.unwrap();
+    assert!(resp["messages"].as_array().unwrap().len() <= 20);
+
+    // request and response of the symbol search
+    let resp = setup
+        .debug_tail(map([("language", json!("rust")), ("limit", json!(100))]))
+        .await
+        .unwrap();
+    let messages = resp["messages"].as_array().unwrap();
+    let request = messages
+        .iter()
+        .find(|message| {
+            message["direction"] == json!("sent")
+                && message["message"]["method"] == json!("workspace/symbol")
+        })
+        .unwrap();
+    assert!(messages.iter().any(|message| {
+        message["direction"] == json!("received")
+            && message["message"]["id"] == request["message"]["id"]
+    }));
+
+    // the symbol search was the first tool call
+    assert_eq!(request["request_ids"], json!([1]));
+
+    insta::assert_json_snapshot!(
+        setup.debug_tail(map([("language", json!("go"))])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no language server for go, running are: rust",
+      "data": {
+        "request_id": 5
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
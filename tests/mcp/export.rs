@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+use crate::setup::TestSetup;
+
+#[tokio::test]
+async fn test_export_symbols() {
+    let output = TestSetup::run(&["export-symbols"]).await;
+    assert!(output.status.success(), "{output:?}");
+
+    let index = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    let files = index["files"].as_array().unwrap();
+    let names = files
+        .iter()
+        .map(|file| file["file"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    insta::assert_json_snapshot!(names, @r#"
+    [
+      "build.rs",
+      "src/lib.rs",
+      "src/sub.rs",
+      "unused_workspace_member/build.rs",
+      "unused_workspace_member/src/lib.rs",
+      "workspace_member/build.rs",
+      "workspace_member/src/lib.rs"
+    ]
+    "#);
+
+    let lib = files
+        .iter()
+        .find(|file| file["file"] == "src/lib.rs")
+        .unwrap();
+    insta::assert_json_snapshot!(lib, @r#"
+    {
+      "file": "src/lib.rs",
+      "language": "rust",
+      "symbols": [
+        {
+          "name": "sub",
+          "kind": "Module",
+          "line": 5,
+          "end_line": 5
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "line": 7,
+          "end_line": 20
+        },
+        {
+          "name": "my_private_lib_fn",
+          "kind": "Function",
+          "line": 22,
+          "end_line": 25
+        },
+        {
+          "name": "foo",
+          "kind": "Function",
+          "line": 27,
+          "end_line": 30
+        },
+        {
+          "name": "main",
+          "kind": "Function",
+          "line": 32,
+          "end_line": 34
+        },
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "line": 36,
+          "end_line": 41,
+          "children": [
+            {
+              "name": "field",
+              "kind": "Field",
+              "line": 40,
+              "end_line": 40
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+}
+
+#[tokio::test]
+async fn test_export_symbols_jsonl() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.jsonl");
+    let output = TestSetup::run(&[
+        "export-symbols",
+        "--format=jsonl",
+        &format!("--output={}", path.display()),
+    ])
+    .await;
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+
+    let index = std::fs::read_to_string(&path).unwrap();
+    let files = index
+        .lines()
+        .map(|line| {
+            let file = serde_json::from_str::<Value>(line).unwrap();
+            file["file"].as_str().unwrap().to_owned()
+        })
+        .collect::<Vec<_>>();
+    insta::assert_json_snapshot!(files, @r#"
+    [
+      "build.rs",
+      "src/lib.rs",
+      "src/sub.rs",
+      "unused_workspace_member/build.rs",
+      "unused_workspace_member/src/lib.rs",
+      "workspace_member/build.rs",
+      "workspace_member/src/lib.rs"
+    ]
+    "#);
+}
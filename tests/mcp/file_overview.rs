@@ -0,0 +1,206 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_file_overview() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .file_overview(map([("file", json!("src/lib.rs"))]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "token_counts": {
+        "builtinType": 6,
+        "comment": 12,
+        "function": 15,
+        "generic": 2,
+        "keyword": 21,
+        "macro": 2,
+        "namespace": 6,
+        "number": 4,
+        "operator": 18,
+        "parameter": 6,
+        "property": 1,
+        "punctuation": 5,
+        "string": 1,
+        "struct": 1,
+        "variable": 8
+      },
+      "declarations": [
+        {
+          "name": "MyMainStruct",
+          "token_type": "struct",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            }
+          ]
+        },
+        {
+          "name": "accu",
+          "token_type": "variable",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 15,
+              "character": 9
+            },
+            {
+              "file": "src/lib.rs",
+              "line": 16,
+              "character": 9
+            },
+            {
+              "file": "src/lib.rs",
+              "line": 17,
+              "character": 9
+            },
+            {
+              "file": "src/lib.rs",
+              "line": 18,
+              "character": 9
+            }
+          ]
+        },
+        {
+          "name": "dep_lib_fn",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 2,
+              "character": 34
+            }
+          ]
+        },
+        {
+          "name": "field",
+          "token_type": "property",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 40,
+              "character": 9
+            }
+          ]
+        },
+        {
+          "name": "foo",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 28,
+              "character": 4
+            }
+          ]
+        },
+        {
+          "name": "left",
+          "token_type": "parameter",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 14,
+              "character": 18
+            }
+          ]
+        },
+        {
+          "name": "main",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 32,
+              "character": 4
+            }
+          ]
+        },
+        {
+          "name": "my_lib_fn",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 14,
+              "character": 8
+            }
+          ]
+        },
+        {
+          "name": "my_private_lib_fn",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 23,
+              "character": 4
+            }
+          ]
+        },
+        {
+          "name": "right",
+          "token_type": "parameter",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 14,
+              "character": 29
+            }
+          ]
+        },
+        {
+          "name": "sub",
+          "token_type": "namespace",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 5,
+              "character": 5
+            }
+          ]
+        },
+        {
+          "name": "workspace_member_lib_fn",
+          "token_type": "function",
+          "locations": [
+            {
+              "file": "src/lib.rs",
+              "line": 3,
+              "character": 36
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_file_not_found() {
+    let setup = TestSetup::new().await.with_normalize_paths(false);
+
+    let results = setup
+        .file_overview(map([("file", json!("does_not_exist.rs"))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "file not found: does_not_exist.rs\n- paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
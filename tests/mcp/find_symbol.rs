@@ -1,4 +1,4 @@
-use crate::setup::{TestSetup, map};
+use crate::setup::{TestSetup, map, mock_args};
 use serde_json::json;
 
 #[tokio::test]
@@ -165,9 +165,14 @@ async fn test_global_query() {
           "kind": "Function",
           "deprecated": false,
           "location": {
-            "file": "/fixtures/dependency_lib/src/lib.rs",
+            "file": "dep://dependency_lib@0.1.0/src/lib.rs",
             "line": 1,
-            "character": 8
+            "character": 8,
+            "dependency": {
+              "crate": "dependency_lib",
+              "version": "0.1.0",
+              "path": "src/lib.rs"
+            }
           }
         },
         {
@@ -221,6 +226,71 @@ async fn test_global_query() {
     setup.shutdown().await;
 }
 
+#[tokio::test]
+async fn test_workspace_jail() {
+    let files = async |setup: &TestSetup| {
+        let results = setup
+            .find_symbol_ok(map([
+                ("query", json!("my_lib_fn")),
+                ("workspace_and_dependencies", json!(true)),
+            ]))
+            .await;
+        results["symbols"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol["location"]["file"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    // the path dependency `dependency_lib` is not a dependency source root, so it is outside the jail
+    let source_root = "--dependency-source-root=registry=/cargo/registry/src";
+    let setup = TestSetup::new_with_args(&["--workspace-jail", source_root]).await;
+    insta::assert_json_snapshot!(files(&setup).await, @r#"
+    [
+      "src/lib.rs",
+      "unused_workspace_member/src/lib.rs",
+      "workspace_member/src/lib.rs"
+    ]
+    "#);
+
+    // files outside the jail cannot be read either
+    let err = setup
+        .symbol_info(map([
+            ("file", json!("../dependency_lib/src/lib.rs")),
+            ("name", json!("my_lib_fn")),
+        ]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(err["message"], @r#""file is outside of the workspace: ../dependency_lib/src/lib.rs\n- use a path relative to the workspace root `/fixtures/main_lib`""#);
+    assert!(
+        !setup
+            .list_all_tools()
+            .await
+            .iter()
+            .any(|tool| tool.name == "add_workspace_folder")
+    );
+    setup.shutdown().await;
+
+    let setup = TestSetup::new_with_args(&[
+        "--workspace-jail",
+        "--workspace-jail-allow=../dependency_lib",
+        source_root,
+    ])
+    .await;
+    // give the language server time to index the dependency, see `test_global_query`
+    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    insta::assert_json_snapshot!(files(&setup).await, @r#"
+    [
+      "/fixtures/dependency_lib/src/lib.rs",
+      "src/lib.rs",
+      "unused_workspace_member/src/lib.rs",
+      "workspace_member/src/lib.rs"
+    ]
+    "#);
+    setup.shutdown().await;
+}
+
 #[tokio::test]
 async fn test_fallback_to_global_query() {
     let setup = TestSetup::new().await;
@@ -237,9 +307,14 @@ async fn test_fallback_to_global_query() {
           "kind": "Function",
           "deprecated": false,
           "location": {
-            "file": "/fixtures/dependency_lib/src/lib.rs",
+            "file": "dep://dependency_lib@0.1.0/src/lib.rs",
             "line": 5,
-            "character": 8
+            "character": 8,
+            "dependency": {
+              "crate": "dependency_lib",
+              "version": "0.1.0",
+              "path": "src/lib.rs"
+            }
           }
         }
       ]
@@ -351,9 +426,14 @@ async fn test_global_fuzzy_query() {
           "kind": "Function",
           "deprecated": false,
           "location": {
-            "file": "/fixtures/dependency_lib/src/lib.rs",
+            "file": "dep://dependency_lib@0.1.0/src/lib.rs",
             "line": 1,
-            "character": 8
+            "character": 8,
+            "dependency": {
+              "crate": "dependency_lib",
+              "version": "0.1.0",
+              "path": "src/lib.rs"
+            }
           }
         },
         {
@@ -361,9 +441,14 @@ async fn test_global_fuzzy_query() {
           "kind": "Function",
           "deprecated": false,
           "location": {
-            "file": "/fixtures/dependency_lib/src/lib.rs",
+            "file": "dep://dependency_lib@0.1.0/src/lib.rs",
             "line": 5,
-            "character": 8
+            "character": 8,
+            "dependency": {
+              "crate": "dependency_lib",
+              "version": "0.1.0",
+              "path": "src/lib.rs"
+            }
           }
         },
         {
@@ -579,7 +664,10 @@ async fn test_file() {
         @r#"
     {
       "code": -32602,
-      "message": "file not found: does_not_exist.rs"
+      "message": "file not found: does_not_exist.rs\n- paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined",
+      "data": {
+        "request_id": 3
+      }
     }
     "#,
     );
@@ -682,3 +770,135 @@ async fn test_file_fuzzy_query() {
 
     setup.shutdown().await;
 }
+
+#[tokio::test]
+async fn test_concurrency_limit() {
+    let setup = TestSetup::new_with_args(&["--lsp-max-concurrency=1"]).await;
+
+    let args = || map([("query", json!("MyMainStruct"))]);
+    let (a, b, c) = tokio::join!(
+        setup.find_symbol_ok(args()),
+        setup.find_symbol_ok(args()),
+        setup.find_symbol_ok(args()),
+    );
+    assert_eq!(a, b);
+    assert_eq!(a, c);
+    assert_eq!(a["symbols"].as_array().unwrap().len(), 1);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_path_style() {
+    let setup = TestSetup::new_with_args(&["--path-style=both", "--include-workspace-root"]).await;
+
+    let results = setup
+        .find_symbol_ok(map([("query", json!("my_private_lib_fn"))]))
+        .await;
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_private_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 23,
+            "character": 4,
+            "absolute_file": "/fixtures/main_lib/src/lib.rs"
+          }
+        }
+      ],
+      "workspace_root": "/fixtures/main_lib"
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_response_budget() {
+    let setup = TestSetup::new_with_args(&["--max-response-chars=300"]).await;
+
+    let results = setup
+        .find_symbol_ok(map([("query", json!("my_lib_fn"))]))
+        .await;
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 14,
+            "character": 8
+          }
+        }
+      ],
+      "response_truncated": [
+        {
+          "path": "/symbols",
+          "omitted_items": 2
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_file_changes_during_fetch() {
+    for (edits, expected) in [
+        // the second fetch sees the edit
+        (
+            "1",
+            Ok(json!({
+                "symbols": [{
+                    "name": "edited_fn",
+                    "kind": "Function",
+                    "deprecated": false,
+                    "location": {"file": "src/lib.rs", "line": 2, "character": 8},
+                }],
+            })),
+        ),
+        (
+            "3",
+            Err(json!({
+                "code": -32602,
+                "message": "src/lib.rs changed during each of 3 attempts to fetch its symbols\n- retry the call once the file is no longer written to, e.g. after a formatter or code generator finished",
+                "data": {"request_id": 1},
+            })),
+        ),
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"edited\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn edited_fn() {}\n").unwrap();
+
+        let [command, arg] = mock_args();
+        let edits = format!("--lsp-arg=--mock-lsp-edits={edits}");
+        let setup = TestSetup::new_with_workspaces_and_args(
+            &[root.to_str().unwrap()],
+            &[&command, &arg, &edits],
+        )
+        .await;
+
+        assert_eq!(
+            setup
+                .find_symbol(map([("file", json!("src/lib.rs"))]))
+                .await,
+            expected,
+        );
+
+        setup.shutdown().await;
+    }
+}
@@ -0,0 +1,45 @@
+use crate::setup::TestSetup;
+use serde_json::Value;
+
+#[tokio::test]
+async fn test_get_config() {
+    let setup = TestSetup::new_with_args(&["--tool-timeout=30"]).await;
+
+    let config = setup.get_config().await.unwrap();
+    let setting = |name: &str| {
+        config["settings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|setting| setting["name"] == name)
+            .cloned()
+            .unwrap_or(Value::Null)
+    };
+
+    insta::assert_json_snapshot!(setting("tool-timeout"), @r#"
+    {
+      "name": "tool-timeout",
+      "values": [
+        "30"
+      ],
+      "source": "cli"
+    }
+    "#);
+    insta::assert_json_snapshot!(setting("path-style"), @r#"
+    {
+      "name": "path-style",
+      "values": [
+        "relative"
+      ],
+      "source": "default"
+    }
+    "#);
+    insta::assert_json_snapshot!(setting("lsp-init-options"), @r#"
+    {
+      "name": "lsp-init-options",
+      "values": []
+    }
+    "#);
+
+    setup.shutdown().await;
+}
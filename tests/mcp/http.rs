@@ -0,0 +1,56 @@
+use rmcp::model::CallToolRequestParams;
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_multiple_clients() {
+    let setup = TestSetup::new_http().await.with_normalize_paths(false);
+    let other = setup.connect().await;
+
+    let args = map([("query", json!("MyMainStruct"))]);
+    let (a, b) = tokio::join!(
+        setup.find_symbol_ok(args.clone()),
+        other.call_tool(CallToolRequestParams::new("find_symbol").with_arguments(args)),
+    );
+    let b = b.expect("no error").structured_content.expect("structured");
+    assert!(!a["symbols"].as_array().unwrap().is_empty(), "{a}");
+    assert_eq!(a, b);
+
+    // a client leaving does not affect the others
+    other.cancel().await.expect("shut down other client");
+    insta::assert_json_snapshot!(
+        setup.reload_config().await.unwrap(),
+        @r#"
+    {
+      "reloaded": [
+        "rust"
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_auth_token() {
+    let setup = TestSetup::new_http_with_auth_token(Some("secret"))
+        .await
+        .with_normalize_paths(false);
+    assert!(
+        !setup
+            .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+            .await["symbols"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    setup.try_connect(None).await.unwrap_err();
+    setup.try_connect(Some("wrong")).await.unwrap_err();
+    let other = setup.try_connect(Some("secret")).await.unwrap();
+    other.cancel().await.expect("shut down other client");
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,144 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map, mock_args};
+
+#[tokio::test]
+async fn test_multiple_languages() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"mixed\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub fn mixed_fn() {}\n\nmacro_rules! never_expanded {\n    () => {\n        fn mixed_unexpanded_fn() {}\n    };\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("settings.toml"),
+        "[settings]\nmod = \"mixed_settings\"\n",
+    )
+    .unwrap();
+
+    // `auto` is resolved to Rust next to the custom language
+    let [command, _arg] = mock_args();
+    let setup = TestSetup::new_with_workspaces_and_args(
+        &[root.to_str().unwrap()],
+        &[
+            "--programming-language=auto,custom",
+            &command.replace("--lsp-command", "--custom-lsp-command"),
+            "--custom-lsp-arg=--mock-lsp",
+            "--custom-lsp-file-extension=toml",
+        ],
+    )
+    .await;
+
+    // files are routed by extension
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([("file", json!("src/lib.rs"))])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "mixed_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 1
+          }
+        },
+        {
+          "name": "never_expanded",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 3,
+            "character": 1
+          }
+        }
+      ]
+    }
+    "#,
+    );
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([("file", json!("settings.toml"))])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "mixed_settings",
+          "kind": "Module",
+          "deprecated": false,
+          "location": {
+            "file": "settings.toml",
+            "line": 2,
+            "character": 8
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // results of all language servers are merged: the fake one scans open documents (i.e. the TOML file from above)
+    // and finds the function within the macro, rust-analyzer finds the package, both find `mixed_fn`
+    insta::assert_json_snapshot!(
+        setup
+            .find_symbol_ok(map([("query", json!("mixed")), ("fuzzy", json!(true))]))
+            .await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "mixed_settings",
+          "kind": "Module",
+          "deprecated": false,
+          "location": {
+            "file": "settings.toml",
+            "line": 2,
+            "character": 8
+          }
+        },
+        {
+          "name": "mixed",
+          "kind": "Package",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 1
+          }
+        },
+        {
+          "name": "mixed_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        },
+        {
+          "name": "mixed_unexpanded_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 5,
+            "character": 12
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,124 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_lsp_request_disabled() {
+    let setup = TestSetup::new().await;
+
+    assert!(
+        !setup
+            .list_all_tools()
+            .await
+            .iter()
+            .any(|tool| tool.name == "lsp_request")
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_lsp_request_read_only() {
+    let setup = TestSetup::new_with_args(&["--enable-lsp-request-tool"]).await;
+
+    assert!(
+        !setup
+            .list_all_tools()
+            .await
+            .iter()
+            .any(|tool| tool.name == "lsp_request")
+    );
+    insta::assert_json_snapshot!(
+        setup.lsp_request(map([
+            ("method", json!("workspace/symbol")),
+            ("params", json!({"query": "MyMainStruct"})),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32020,
+      "message": "tool `lsp_request` may modify the workspace, which is not allowed\n- the server runs in read-only mode, do not retry the call",
+      "data": {
+        "policy": "read_only",
+        "request_id": 1
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_lsp_request() {
+    let setup = TestSetup::new_with_args(&["--enable-lsp-request-tool", "--read-only=false"]).await;
+
+    // wait for the language server to be ready
+    setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+
+    insta::assert_json_snapshot!(
+        setup.lsp_request(map([
+            ("method", json!("workspace/symbol")),
+            ("params", json!({"query": "MyMainStruct"})),
+        ])).await.unwrap(),
+        @r#"
+    {
+      "response": [
+        {
+          "name": "MyMainStruct",
+          "kind": 23,
+          "location": {
+            "uri": "file:///fixtures/main_lib/src/lib.rs",
+            "range": {
+              "start": {
+                "line": 38,
+                "character": 18
+              },
+              "end": {
+                "line": 38,
+                "character": 30
+              }
+            }
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup.lsp_request(map([
+            ("method", json!("foo/bar")),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32603,
+      "message": "LSP request foo/bar: jsonrpsee error: ErrorObject { code: MethodNotFound, message: \"unknown request\", data: None }",
+      "data": {
+        "lsp_code": -32601,
+        "retryable": false,
+        "request_id": 3
+      }
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup.lsp_request(map([
+            ("method", json!("workspace/symbol")),
+            ("language", json!("csharp")),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no language server for csharp, running are: rust",
+      "data": {
+        "request_id": 4
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
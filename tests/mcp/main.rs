@@ -1,20 +1,72 @@
 // crates used by main binary
+use ::http as _;
 use anyhow as _;
+use async_trait as _;
+use bytes as _;
 use clap as _;
 use dotenvy as _;
+#[cfg(feature = "install")]
+use flate2 as _;
 use futures as _;
+use globset as _;
+use http_body_util as _;
+use hyper_util as _;
+use ignore as _;
 use itertools as _;
+use jsonrpsee as _;
 use lsp_client as _;
 use lsp_types as _;
+use percent_encoding as _;
 use predicates as _;
+use regex as _;
+#[cfg(feature = "install")]
+use reqwest as _;
 use serde as _;
+use sha2 as _;
+#[cfg(feature = "install")]
+use tar as _;
 use tokio_stream as _;
 use tokio_util as _;
+use toml as _;
+use tower_service as _;
 use tracing as _;
 use tracing_log as _;
 use tracing_subscriber as _;
 
+mod call;
+mod call_graph;
+mod changes;
+mod check;
+mod completion;
+#[cfg(unix)]
+mod daemon;
+mod debug_tail;
+mod export;
+mod file_overview;
 mod find_symbol;
+mod get_config;
+mod http;
+mod languages;
+mod lsp_request;
 mod metadata;
+mod mock;
+mod parent_module;
+mod project_statistics;
+mod reload_config;
+mod replay;
+mod resources;
+mod roots;
+mod run_test;
+mod sarif;
+mod secrets;
+mod server_stats;
+mod server_status;
 mod setup;
+mod startup;
 mod symbol_info;
+mod syntax_tree;
+mod timeout;
+mod trait_detail;
+mod type_at;
+mod type_members;
+mod workspace;
@@ -8,77 +8,2313 @@ async fn test_list_tools() {
         setup.list_all_tools().await,
         @r##"
     [
+      {
+        "name": "add_workspace_folder",
+        "title": "Add Workspace Folder",
+        "description": "Add a folder to the workspace, e.g. another repository. Once the workspace has multiple folders, file paths start with the folder name.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "path"
+          ],
+          "type": "object",
+          "properties": {
+            "path": {
+              "description": "absolute path of the folder",
+              "type": "string",
+              "minLength": 1
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "WorkspaceFolderInfo": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "description": "name that prefixes file paths if the workspace has multiple folders",
+                  "type": "string"
+                },
+                "path": {
+                  "description": "absolute path",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "name",
+                "path"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "folders": {
+              "description": "workspace folders after the change, the first one is the root",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/WorkspaceFolderInfo"
+              }
+            }
+          },
+          "required": [
+            "folders"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": false,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "call_graph",
+        "title": "Call Graph",
+        "description": "Get the call graph around a function or method as DOT (GraphViz) or Mermaid text: the functions that call it and/or the functions it calls, up to a given depth. The function is selected by name or by its position within the file.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "CallDirection": {
+              "description": "Which calls are followed.",
+              "oneOf": [
+                {
+                  "description": "functions that call the given one",
+                  "type": "string",
+                  "const": "incoming"
+                },
+                {
+                  "description": "functions that the given one calls",
+                  "type": "string",
+                  "const": "outgoing"
+                },
+                {
+                  "description": "both directions",
+                  "type": "string",
+                  "const": "both"
+                }
+              ]
+            },
+            "GraphFormat": {
+              "description": "Text format of the rendered graph.",
+              "oneOf": [
+                {
+                  "description": "GraphViz DOT language",
+                  "type": "string",
+                  "const": "dot"
+                },
+                {
+                  "description": "Mermaid flowchart, which many chat clients render inline",
+                  "type": "string",
+                  "const": "mermaid"
+                }
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file, can be absolute or relative",
+              "type": "string"
+            },
+            "name": {
+              "description": "function name, if not set the function at the given line and character is used",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "line": {
+              "description": "1-based line number within the file",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "character": {
+              "description": "1-based character index within the line",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "direction": {
+              "description": "which calls to follow, defaults to both",
+              "anyOf": [
+                {
+                  "$ref": "#/$defs/CallDirection"
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "depth": {
+              "description": "number of calls to follow from the function, defaults to 2",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "maximum": 5,
+              "minimum": 1
+            },
+            "format": {
+              "description": "text format of the graph, defaults to DOT",
+              "anyOf": [
+                {
+                  "$ref": "#/$defs/GraphFormat"
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "workspace_and_dependencies": {
+              "description": "include functions of dependencies",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          },
+          "required": [
+            "file"
+          ]
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "graph",
+            "nodes",
+            "edges"
+          ],
+          "type": "object",
+          "properties": {
+            "graph": {
+              "description": "call graph in the requested format, edges point from caller to callee",
+              "type": "string"
+            },
+            "nodes": {
+              "description": "number of functions",
+              "type": "integer",
+              "minimum": 0
+            },
+            "edges": {
+              "description": "number of calls",
+              "type": "integer",
+              "minimum": 0
+            },
+            "truncated": {
+              "description": "functions were dropped because the graph got too large, use a smaller depth",
+              "type": "boolean"
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          }
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "changed_symbols",
+        "title": "Changed Symbols",
+        "description": "List the symbols (e.g. functions, structs, methods) that were added, modified, or removed between two git revisions, or between a revision and the working tree. Use this to review changes, then use the `symbol_info` tool to learn more about interesting symbols.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "properties": {
+            "base": {
+              "description": "git revision to compare against, e.g. `HEAD~1` or a branch name, defaults to `HEAD`",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "head": {
+              "description": "git revision with the changes, defaults to the working tree",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          },
+          "type": "object"
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "ChangedFile": {
+              "type": "object",
+              "properties": {
+                "file": {
+                  "type": "string"
+                },
+                "old_file": {
+                  "description": "previous path of renamed files",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "change": {
+                  "$ref": "#/$defs/FileChange"
+                },
+                "symbols": {
+                  "description": "changed symbols, sorted by line",
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/ChangedSymbol"
+                  }
+                }
+              },
+              "required": [
+                "file",
+                "change",
+                "symbols"
+              ]
+            },
+            "FileChange": {
+              "description": "Change of a file.",
+              "type": "string",
+              "enum": [
+                "added",
+                "modified",
+                "removed",
+                "renamed"
+              ]
+            },
+            "ChangedSymbol": {
+              "description": "Symbol that was added, modified, or removed.",
+              "type": "object",
+              "properties": {
+                "line": {
+                  "description": "line in the new revision, or in the old one for removed symbols",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "name": {
+                  "type": "string"
+                },
+                "kind": {
+                  "type": "string"
+                },
+                "container": {
+                  "description": "enclosing symbol, e.g. the type of a method",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "change": {
+                  "$ref": "#/$defs/Change"
+                }
+              },
+              "required": [
+                "line",
+                "name",
+                "kind",
+                "change"
+              ]
+            },
+            "Change": {
+              "description": "Change of a symbol.",
+              "type": "string",
+              "enum": [
+                "added",
+                "modified",
+                "removed"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "files": {
+              "description": "changed files, sorted by path",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/ChangedFile"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "files"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "file_overview",
+        "title": "File Overview",
+        "description": "Get a cheap structural overview of a file: the number of tokens per type (e.g. functions, structs, variables) and the identifiers declared in it. Use the `symbol_info` tool afterwards to learn more about interesting symbols.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "file"
+          ],
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file, can be absolute or relative",
+              "type": "string"
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "DeclarationInfo": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "type": "string"
+                },
+                "token_type": {
+                  "type": "string"
+                },
+                "locations": {
+                  "description": "all declarations of this name and type, e.g. shadowed variables",
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Location"
+                  }
+                }
+              },
+              "required": [
+                "name",
+                "token_type",
+                "locations"
+              ]
+            },
+            "Location": {
+              "description": "Describes a location of a symbol.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character"
+              ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "token_counts": {
+              "description": "number of tokens per token type",
+              "type": "object",
+              "additionalProperties": {
+                "type": "integer",
+                "minimum": 0
+              }
+            },
+            "declarations": {
+              "description": "declared identifiers, sorted by name",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/DeclarationInfo"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "stale": {
+              "description": "the file changed while it was analyzed, results may be incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "token_counts",
+            "declarations"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
       {
         "name": "find_symbol",
+        "title": "Find Symbol",
         "description": "Find symbol (e.g. a struct, enum, method, ...) in code base. Use the `symbol_info` tool afterwards to learn more about the found symbols.",
         "inputSchema": {
           "$schema": "https://json-schema.org/draft/2020-12/schema",
           "properties": {
-            "query": {
-              "description": "the symbol that you are looking for, required if `path` is not provided",
+            "query": {
+              "description": "the symbol that you are looking for, required if `path` is not provided",
+              "type": [
+                "string",
+                "null"
+              ],
+              "minLength": 1
+            },
+            "file": {
+              "description": "path to the file, otherwise search the entire workspace",
+              "type": [
+                "string",
+                "null"
+              ],
+              "minLength": 1
+            },
+            "fuzzy": {
+              "description": "search fuzzy",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "workspace_and_dependencies": {
+              "description": "search workspace and dependencies",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          },
+          "type": "object"
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "SymbolResult": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "type": "string"
+                },
+                "kind": {
+                  "type": "string"
+                },
+                "deprecated": {
+                  "type": "boolean"
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                }
+              },
+              "required": [
+                "name",
+                "kind",
+                "deprecated",
+                "location"
+              ]
+            },
+            "Location": {
+              "description": "Describes a location of a symbol.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character"
+              ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "symbols": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/SymbolResult"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "symbols"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "get_config",
+        "title": "Get Configuration",
+        "description": "Get the effective configuration of this server, i.e. CLI arguments, environment variables, config file, and defaults merged, and where each value comes from. Use this to find out why a setting does not take effect.",
+        "inputSchema": {
+          "type": "object",
+          "properties": {}
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "Setting": {
+              "description": "Effective value of a setting.",
+              "type": "object",
+              "properties": {
+                "name": {
+                  "description": "long name of the CLI argument, also the key within the config file",
+                  "type": "string"
+                },
+                "values": {
+                  "description": "values, secrets are redacted",
+                  "type": "array",
+                  "items": {
+                    "type": "string"
+                  }
+                },
+                "source": {
+                  "description": "where the value comes from, missing if the setting is not set",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Source"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "name",
+                "values"
+              ]
+            },
+            "Source": {
+              "description": "Where the value of a setting comes from.",
+              "type": "string",
+              "enum": [
+                "cli",
+                "env",
+                "config_file",
+                "default"
+              ]
+            }
+          },
+          "required": [
+            "settings"
+          ],
+          "type": "object",
+          "properties": {
+            "config_file": {
+              "description": "config file that was merged, missing if there is none",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "settings": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/Setting"
+              }
+            }
+          }
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "parent_module",
+        "title": "Parent Module",
+        "description": "Find where a file or position hangs in the module tree: the `mod` declarations from the module that contains the position up to the crate root, plus the module path. Useful after landing in a deeply nested file.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "file"
+          ],
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file, can be absolute or relative",
+              "type": "string"
+            },
+            "line": {
+              "description": "1-based line number within the file, defaults to the first line",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "character": {
+              "description": "1-based character index within the line, defaults to the first character",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "workspace_and_dependencies": {
+              "description": "follow the module tree into dependencies",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "ModuleDeclaration": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "description": "name of the module",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "declaration": {
+                  "description": "source line of the declaration, e.g. `pub(crate) mod requests;`",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                }
+              },
+              "required": [
+                "location"
+              ]
+            },
+            "Location": {
+              "description": "Describes a location of a symbol.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character"
+              ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "module_path": {
+              "description": "path of the module that contains the position, e.g. `crate::lsp::requests`, if the crate root was reached",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "parents": {
+              "description": "`mod` declarations from the module that contains the position up to the crate root, empty for the crate root",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/ModuleDeclaration"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "parents"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "project_statistics",
+        "title": "Project Statistics",
+        "description": "Get the size of the project: number of files, lines, and symbols per kind (e.g. functions, structs), per language and per package (e.g. crate). Use this to get a sense of the project size and where most of the code lives before exploring it.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "properties": {
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          },
+          "type": "object"
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "Counts": {
+              "description": "Counts of a set of files.",
+              "type": "object",
+              "properties": {
+                "files": {
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "lines": {
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "symbols": {
+                  "description": "number of symbols per kind, e.g. `Function` or `Struct`",
+                  "type": "object",
+                  "additionalProperties": {
+                    "type": "integer",
+                    "minimum": 0
+                  }
+                }
+              },
+              "required": [
+                "files",
+                "lines",
+                "symbols"
+              ]
+            },
+            "PackageCounts": {
+              "description": "Counts of a single package.",
+              "type": "object",
+              "properties": {
+                "path": {
+                  "description": "directory of the package, `.` for the workspace root",
+                  "type": "string"
+                },
+                "language": {
+                  "type": "string"
+                },
+                "files": {
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "lines": {
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "symbols": {
+                  "description": "number of symbols per kind, e.g. `Function` or `Struct`",
+                  "type": "object",
+                  "additionalProperties": {
+                    "type": "integer",
+                    "minimum": 0
+                  }
+                }
+              },
+              "required": [
+                "path",
+                "language",
+                "files",
+                "lines",
+                "symbols"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "languages": {
+              "description": "counts per language server",
+              "type": "object",
+              "additionalProperties": {
+                "$ref": "#/$defs/Counts"
+              }
+            },
+            "packages": {
+              "description": "counts per package (e.g. crate), largest first",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/PackageCounts"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "languages",
+            "packages"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "reload_config",
+        "title": "Reload Configuration",
+        "description": "Reload the language server configuration (e.g. after changing the file passed via `--lsp-init-options`) and push it to the language servers without restarting them.",
+        "inputSchema": {
+          "type": "object",
+          "properties": {}
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "reloaded"
+          ],
+          "type": "object",
+          "properties": {
+            "reloaded": {
+              "description": "programming languages whose language servers received the new configuration",
+              "type": "array",
+              "items": {
+                "type": "string"
+              }
+            }
+          }
+        },
+        "annotations": {
+          "readOnlyHint": false,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "remove_workspace_folder",
+        "title": "Remove Workspace Folder",
+        "description": "Remove a folder from the workspace. The last folder cannot be removed.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "folder"
+          ],
+          "type": "object",
+          "properties": {
+            "folder": {
+              "description": "name or absolute path of the folder",
+              "type": "string",
+              "minLength": 1
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "WorkspaceFolderInfo": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "description": "name that prefixes file paths if the workspace has multiple folders",
+                  "type": "string"
+                },
+                "path": {
+                  "description": "absolute path",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "name",
+                "path"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "folders": {
+              "description": "workspace folders after the change, the first one is the root",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/WorkspaceFolderInfo"
+              }
+            }
+          },
+          "required": [
+            "folders"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": false,
+          "destructiveHint": false,
+          "idempotentHint": false,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "server_stats",
+        "title": "Server Stats",
+        "description": "Get call counts, error counts, and latency percentiles of the tools, latency and error statistics of the requests sent to the language servers, and cache hit rates since startup, e.g. to find out which tools are slow or failing.",
+        "inputSchema": {
+          "type": "object",
+          "properties": {}
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "ToolStats": {
+              "type": "object",
+              "properties": {
+                "tool": {
+                  "description": "name of the tool, e.g. `find_symbol`",
+                  "type": "string"
+                },
+                "count": {
+                  "description": "number of calls, including failed ones",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "errors": {
+                  "description": "number of calls that returned an error",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "p50_ms": {
+                  "description": "median call duration in milliseconds, over the last 1024 calls",
+                  "type": "number"
+                },
+                "p95_ms": {
+                  "description": "95th percentile of the call duration in milliseconds, over the last 1024 calls",
+                  "type": "number"
+                }
+              },
+              "required": [
+                "tool",
+                "count",
+                "errors",
+                "p50_ms",
+                "p95_ms"
+              ]
+            },
+            "ServerStats": {
+              "type": "object",
+              "properties": {
+                "language": {
+                  "description": "programming language",
+                  "type": "string"
+                },
+                "requests": {
+                  "description": "statistics per LSP method, sorted by method",
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/RequestStats"
+                  }
+                },
+                "workspace_symbol_cache": {
+                  "description": "cached workspace symbol searches",
+                  "$ref": "#/$defs/CacheStats"
+                },
+                "hover_cache": {
+                  "description": "cached hover results",
+                  "$ref": "#/$defs/CacheStats"
+                },
+                "document_cache": {
+                  "description": "cached symbols and semantic tokens of pinned documents, see `--warmup-globs`",
+                  "$ref": "#/$defs/CacheStats"
+                },
+                "semantic_tokens_cache": {
+                  "description": "semantic tokens that delta requests are based on",
+                  "$ref": "#/$defs/CacheStats"
+                }
+              },
+              "required": [
+                "language",
+                "requests",
+                "workspace_symbol_cache",
+                "hover_cache",
+                "document_cache",
+                "semantic_tokens_cache"
+              ]
+            },
+            "RequestStats": {
+              "type": "object",
+              "properties": {
+                "method": {
+                  "description": "LSP method, e.g. `textDocument/documentSymbol`",
+                  "type": "string"
+                },
+                "count": {
+                  "description": "number of requests, including failed ones",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "errors": {
+                  "description": "number of failed requests",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "mean_ms": {
+                  "description": "mean request duration in milliseconds",
+                  "type": "number"
+                },
+                "max_ms": {
+                  "description": "longest request duration in milliseconds",
+                  "type": "number"
+                }
+              },
+              "required": [
+                "method",
+                "count",
+                "errors",
+                "mean_ms",
+                "max_ms"
+              ]
+            },
+            "CacheStats": {
+              "description": "Effectiveness of a cache.",
+              "type": "object",
+              "properties": {
+                "entries": {
+                  "description": "number of cached entries",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "bytes": {
+                  "description": "approximate memory footprint of the cached entries in bytes",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "hits": {
+                  "description": "lookups that were answered from the cache",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "misses": {
+                  "description": "lookups that needed a request to the language server",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "hit_rate": {
+                  "description": "share of lookups that were answered from the cache, `null` if there were none",
+                  "type": [
+                    "number",
+                    "null"
+                  ]
+                },
+                "evictions": {
+                  "description": "entries that were evicted to stay within `--cache-max-mib`",
+                  "type": "integer",
+                  "minimum": 0
+                }
+              },
+              "required": [
+                "entries",
+                "bytes",
+                "hits",
+                "misses",
+                "evictions"
+              ]
+            },
+            "CacheBudgetStats": {
+              "description": "Memory usage of all caches combined.",
+              "type": "object",
+              "properties": {
+                "max_bytes": {
+                  "description": "budget in bytes, see `--cache-max-mib`",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "bytes": {
+                  "description": "approximate memory footprint of all cached entries in bytes",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "evictions": {
+                  "description": "entries that were evicted to stay within the budget",
+                  "type": "integer",
+                  "minimum": 0
+                }
+              },
+              "required": [
+                "max_bytes",
+                "bytes",
+                "evictions"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "tools": {
+              "description": "statistics per tool since startup, sorted by tool, excluding the running call",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/ToolStats"
+              }
+            },
+            "servers": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/ServerStats"
+              }
+            },
+            "cache_budget": {
+              "description": "memory usage of all caches combined",
+              "anyOf": [
+                {
+                  "$ref": "#/$defs/CacheBudgetStats"
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "task_restarts": {
+              "description": "restarts of failed background tasks, e.g. the listeners for language server messages, see `server_status`",
+              "type": "integer",
+              "minimum": 0
+            }
+          },
+          "required": [
+            "tools",
+            "servers",
+            "task_restarts"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": false,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "server_status",
+        "title": "Server Status",
+        "description": "Get status of the language servers, e.g. their version and if they are ready or still indexing the code base.",
+        "inputSchema": {
+          "type": "object",
+          "properties": {}
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "ServerStatus": {
+              "type": "object",
+              "properties": {
+                "language": {
+                  "description": "programming language",
+                  "type": "string"
+                },
+                "server_name": {
+                  "description": "name reported by the language server",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "server_version": {
+                  "description": "version reported by the language server",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "version_check": {
+                  "description": "result of the minimum version check",
+                  "$ref": "#/$defs/VersionStatus"
+                },
+                "ready": {
+                  "description": "language server finished indexing and has no work in progress",
+                  "type": "boolean"
+                },
+                "init_timed_out": {
+                  "description": "language server did not finish its initial work in time and is used anyways, results may be incomplete",
+                  "type": "boolean"
+                },
+                "missing_init_parts": {
+                  "description": "expected progress reports of the initial work that never started",
+                  "type": "array",
+                  "items": {
+                    "type": "string"
+                  }
+                },
+                "running": {
+                  "description": "number of tool calls that currently use the language server",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "queued": {
+                  "description": "number of tool calls that wait for the language server",
+                  "type": "integer",
+                  "minimum": 0
+                }
+              },
+              "required": [
+                "language",
+                "version_check",
+                "ready",
+                "init_timed_out",
+                "missing_init_parts",
+                "running",
+                "queued"
+              ]
+            },
+            "VersionStatus": {
+              "description": "Result of a version check.",
+              "oneOf": [
+                {
+                  "description": "Version is supported.",
+                  "type": "object",
+                  "properties": {
+                    "status": {
+                      "type": "string",
+                      "const": "ok"
+                    }
+                  },
+                  "required": [
+                    "status"
+                  ]
+                },
+                {
+                  "description": "Version is older than the required minimum.",
+                  "type": "object",
+                  "properties": {
+                    "minimum": {
+                      "type": "string"
+                    },
+                    "status": {
+                      "type": "string",
+                      "const": "too_old"
+                    }
+                  },
+                  "required": [
+                    "status",
+                    "minimum"
+                  ]
+                },
+                {
+                  "description": "Version cannot be checked.",
+                  "type": "object",
+                  "properties": {
+                    "reason": {
+                      "type": "string"
+                    },
+                    "status": {
+                      "type": "string",
+                      "const": "unknown"
+                    }
+                  },
+                  "required": [
+                    "status",
+                    "reason"
+                  ]
+                }
+              ]
+            },
+            "TaskStatus": {
+              "description": "Status of a task at a point in time.",
+              "type": "object",
+              "properties": {
+                "name": {
+                  "type": "string"
+                },
+                "state": {
+                  "$ref": "#/$defs/TaskState"
+                },
+                "restarts": {
+                  "description": "number of restarts so far",
+                  "type": "integer",
+                  "minimum": 0
+                },
+                "last_error": {
+                  "description": "error of the last failed run",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "uptime_secs": {
+                  "description": "seconds since the last (re)start, until the end if the task is not running",
+                  "type": "number"
+                }
+              },
+              "required": [
+                "name",
+                "state",
+                "restarts",
+                "uptime_secs"
+              ]
+            },
+            "TaskState": {
+              "description": "Lifecycle state of a task.",
+              "oneOf": [
+                {
+                  "type": "string",
+                  "enum": [
+                    "running",
+                    "finished",
+                    "failed"
+                  ]
+                },
+                {
+                  "description": "Failed or ended and waits for the restart.",
+                  "type": "string",
+                  "const": "restarting"
+                },
+                {
+                  "description": "Did not stop within the shutdown grace period and was aborted.",
+                  "type": "string",
+                  "const": "killed"
+                }
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "servers": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/ServerStatus"
+              }
+            },
+            "tasks": {
+              "description": "background tasks, e.g. progress tracking of the language servers",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/TaskStatus"
+              }
+            }
+          },
+          "required": [
+            "servers",
+            "tasks"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "symbol_info",
+        "title": "Symbol Info",
+        "description": "Get detailed information about a given symbol (struct, enum, method, trait, ...) like documentation, declaration, references, usage across the code base, etc. The symbol is selected by name or by its position within the file.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "file"
+          ],
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file, can be absolute or relative",
+              "type": "string"
+            },
+            "name": {
+              "description": "symbol name, if not set the symbol at the given line and character is used",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "fuzzy": {
+              "description": "search fuzzy if no symbol matches the name exactly",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "token_type": {
+              "description": "only consider tokens of this type, e.g. `function` or `struct`",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "line": {
+              "description": "1-based line number within the file",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "character": {
+              "description": "1-based character index within the line",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "workspace_and_dependencies": {
+              "description": "search workspace and dependencies",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "SymbolInfo": {
+              "type": "object",
+              "properties": {
+                "token": {
+                  "$ref": "#/$defs/TokenInfo"
+                },
+                "hover": {
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/HoverInfo"
+                  }
+                },
+                "docs_url": {
+                  "description": "documentation URL (best-effort), only for symbols that are defined in a dependency",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "cfg": {
+                  "description": "`cfg` conditions that guard the definition, incl. those of enclosing items, e.g. `test` or `feature = \"serde\"`",
+                  "type": "array",
+                  "items": {
+                    "type": "string"
+                  }
+                },
+                "declarations": {
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Location"
+                  }
+                },
+                "definitions": {
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Location"
+                  }
+                },
+                "implementations": {
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Location"
+                  }
+                },
+                "type_definitions": {
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Location"
+                  }
+                },
+                "references": {
+                  "description": "nearest first: same file, same module (directory), elsewhere in the workspace, dependencies",
+                  "type": "array",
+                  "items": {
+                    "$ref": "#/$defs/Reference"
+                  }
+                }
+              },
+              "required": [
+                "token",
+                "hover",
+                "declarations",
+                "definitions",
+                "implementations",
+                "type_definitions",
+                "references"
+              ]
+            },
+            "TokenInfo": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "type": "string"
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                },
+                "token_type": {
+                  "type": "string"
+                },
+                "modifiers": {
+                  "type": "array",
+                  "items": {
+                    "type": "string"
+                  }
+                }
+              },
+              "required": [
+                "name",
+                "location",
+                "token_type",
+                "modifiers"
+              ]
+            },
+            "Location": {
+              "description": "Describes a location of a symbol.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character"
+              ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
+            },
+            "HoverInfo": {
+              "type": "object",
+              "properties": {
+                "language": {
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "value": {
+                  "type": "string"
+                }
+              },
+              "required": [
+                "value"
+              ]
+            },
+            "Reference": {
+              "description": "Location of a reference with its proximity bucket.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                },
+                "proximity": {
+                  "$ref": "#/$defs/Proximity"
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character",
+                "proximity"
+              ]
+            },
+            "Proximity": {
+              "description": "Bucket of a reference, ordered from nearest to farthest.",
+              "oneOf": [
+                {
+                  "description": "same file as the symbol",
+                  "type": "string",
+                  "const": "same_file"
+                },
+                {
+                  "description": "another file in the same directory, e.g. a sibling or parent module",
+                  "type": "string",
+                  "const": "same_module"
+                },
+                {
+                  "description": "elsewhere in the workspace",
+                  "type": "string",
+                  "const": "workspace"
+                },
+                {
+                  "description": "within a dependency",
+                  "type": "string",
+                  "const": "dependency"
+                }
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "info": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/SymbolInfo"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "stale": {
+              "description": "the file changed while it was analyzed, results may be incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "info"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "trait_detail",
+        "title": "Trait Detail",
+        "description": "Get the structure of a trait (or interface): its associated items (methods, constants, types), which of them are required and which have a default, and the types that implement it.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "file"
+          ],
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file that declares the trait, can be absolute or relative",
+              "type": "string"
+            },
+            "name": {
+              "description": "trait name, if not set the trait at the given line is used",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "line": {
+              "description": "1-based line number within the trait",
+              "type": [
+                "integer",
+                "null"
+              ],
+              "minimum": 1
+            },
+            "workspace_and_dependencies": {
+              "description": "include implementations in dependencies",
+              "type": [
+                "boolean",
+                "null"
+              ]
+            },
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
+              "type": [
+                "number",
+                "null"
+              ],
+              "minimum": 0.0
+            }
+          }
+        },
+        "outputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "$defs": {
+            "Location": {
+              "description": "Describes a location of a symbol.",
+              "type": "object",
+              "properties": {
+                "file": {
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
+                  "type": "string"
+                },
+                "line": {
+                  "description": "1-based line number.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "character": {
+                  "description": "1-based character.",
+                  "type": "integer",
+                  "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
+                }
+              },
+              "required": [
+                "file",
+                "line",
+                "character"
+              ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
+            },
+            "TraitItem": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "type": "string"
+                },
+                "kind": {
+                  "description": "e.g. `Function` or `Constant`",
+                  "type": "string"
+                },
+                "provided": {
+                  "description": "the item has a default (e.g. a method body), so implementations do not need to define it",
+                  "type": "boolean"
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                }
+              },
+              "required": [
+                "name",
+                "kind",
+                "provided",
+                "location"
+              ]
+            },
+            "Implementor": {
+              "type": "object",
+              "properties": {
+                "type_name": {
+                  "description": "implementing type as written in the implementation, e.g. `Vec<T>`",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                }
+              },
+              "required": [
+                "location"
+              ]
+            }
+          },
+          "type": "object",
+          "properties": {
+            "name": {
+              "type": "string"
+            },
+            "location": {
+              "anyOf": [
+                {
+                  "$ref": "#/$defs/Location"
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "required": {
+              "description": "number of items that implementations must define",
+              "type": "integer",
+              "minimum": 0
+            },
+            "provided": {
+              "description": "number of items with a default",
+              "type": "integer",
+              "minimum": 0
+            },
+            "items": {
+              "description": "associated items in declaration order",
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/TraitItem"
+              }
+            },
+            "implementor_count": {
+              "description": "number of implementations",
+              "type": "integer",
+              "minimum": 0
+            },
+            "implementors": {
+              "type": "array",
+              "items": {
+                "$ref": "#/$defs/Implementor"
+              }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
+            }
+          },
+          "required": [
+            "name",
+            "required",
+            "provided",
+            "items",
+            "implementor_count",
+            "implementors"
+          ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
+        }
+      },
+      {
+        "name": "type_at",
+        "title": "Type At",
+        "description": "Get the inferred type of a variable or expression at a position (or within a range) of a file, plus where that type is defined. Cheaper than `symbol_info` if only the type matters.",
+        "inputSchema": {
+          "$schema": "https://json-schema.org/draft/2020-12/schema",
+          "required": [
+            "file",
+            "line",
+            "character"
+          ],
+          "type": "object",
+          "properties": {
+            "file": {
+              "description": "path to the file, can be absolute or relative",
+              "type": "string"
+            },
+            "line": {
+              "description": "1-based line number within the file",
+              "type": "integer",
+              "minimum": 1
+            },
+            "character": {
+              "description": "1-based character index within the line",
+              "type": "integer",
+              "minimum": 1
+            },
+            "end_line": {
+              "description": "1-based line number of the end of an expression, if not set the variable or expression at the position is used",
               "type": [
-                "string",
+                "integer",
                 "null"
               ],
-              "minLength": 1
+              "minimum": 1
             },
-            "file": {
-              "description": "path to the file, otherwise search the entire workspace",
+            "end_character": {
+              "description": "1-based character index after the end of an expression",
               "type": [
-                "string",
+                "integer",
                 "null"
               ],
-              "minLength": 1
+              "minimum": 1
             },
-            "fuzzy": {
-              "description": "search fuzzy",
+            "workspace_and_dependencies": {
+              "description": "include type definitions in dependencies",
               "type": [
                 "boolean",
                 "null"
               ]
             },
-            "workspace_and_dependencies": {
-              "description": "search workspace and dependencies",
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
               "type": [
-                "boolean",
+                "number",
                 "null"
-              ]
+              ],
+              "minimum": 0.0
             }
-          },
-          "type": "object"
+          }
         },
         "outputSchema": {
           "$schema": "https://json-schema.org/draft/2020-12/schema",
           "$defs": {
-            "SymbolResult": {
-              "type": "object",
-              "properties": {
-                "name": {
-                  "type": "string"
-                },
-                "kind": {
-                  "type": "string"
-                },
-                "deprecated": {
-                  "type": "boolean"
-                },
-                "location": {
-                  "$ref": "#/$defs/Location"
-                }
-              },
-              "required": [
-                "name",
-                "kind",
-                "deprecated",
-                "location"
-              ]
-            },
             "Location": {
               "description": "Describes a location of a symbol.",
               "type": "object",
               "properties": {
                 "file": {
-                  "description": "File path.",
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
                   "type": "string"
                 },
                 "line": {
@@ -90,6 +2326,24 @@ async fn test_list_tools() {
                   "description": "1-based character.",
                   "type": "integer",
                   "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
                 }
               },
               "required": [
@@ -97,150 +2351,126 @@ async fn test_list_tools() {
                 "line",
                 "character"
               ]
+            },
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
+              "type": "object",
+              "properties": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "path": {
+                  "description": "File path, relative to the crate root.",
+                  "type": "string"
+                }
+              },
+              "required": [
+                "crate",
+                "path"
+              ]
             }
           },
           "type": "object",
           "properties": {
-            "symbols": {
+            "type": {
+              "description": "inferred type, e.g. `Vec<u32>`",
+              "type": "string"
+            },
+            "coerced_to": {
+              "description": "type that the expression is implicitly converted to, e.g. `&str` for a `&String` argument",
+              "type": [
+                "string",
+                "null"
+              ]
+            },
+            "type_definitions": {
+              "description": "where the type is defined",
               "type": "array",
               "items": {
-                "$ref": "#/$defs/SymbolResult"
+                "$ref": "#/$defs/Location"
               }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
             }
           },
           "required": [
-            "symbols"
+            "type",
+            "type_definitions"
           ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
         }
       },
       {
-        "name": "symbol_info",
-        "description": "Get detailed information about a given symbol (struct, enum, method, trait, ...) like documentation, declaration, references, usage across the code base, etc.",
+        "name": "type_members",
+        "title": "Type Members",
+        "description": "List the fields of a struct or the variants of an enum with their types, visibility, and documentation as structured data. More complete and easier to use than the hover text of the type.",
         "inputSchema": {
           "$schema": "https://json-schema.org/draft/2020-12/schema",
           "required": [
-            "file",
-            "name"
+            "file"
           ],
           "type": "object",
           "properties": {
             "file": {
-              "description": "path to the file, can be absolute or relative",
+              "description": "path to the file that declares the type, can be absolute or relative",
               "type": "string"
             },
             "name": {
-              "description": "symbol name",
-              "type": "string"
-            },
-            "line": {
-              "description": "1-based line number within the file",
+              "description": "name of the struct or enum, if not set the type at the given line is used",
               "type": [
-                "integer",
+                "string",
                 "null"
-              ],
-              "minimum": 1
+              ]
             },
-            "character": {
-              "description": "1-based character index within the line",
+            "line": {
+              "description": "1-based line number within the type",
               "type": [
                 "integer",
                 "null"
               ],
               "minimum": 1
             },
-            "workspace_and_dependencies": {
-              "description": "search workspace and dependencies",
+            "timeout_secs": {
+              "description": "seconds after which partial results are returned, overrides the server default",
               "type": [
-                "boolean",
+                "number",
                 "null"
-              ]
+              ],
+              "minimum": 0.0
             }
           }
         },
         "outputSchema": {
           "$schema": "https://json-schema.org/draft/2020-12/schema",
           "$defs": {
-            "SymbolInfo": {
-              "type": "object",
-              "properties": {
-                "token": {
-                  "$ref": "#/$defs/TokenInfo"
-                },
-                "hover": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/HoverInfo"
-                  }
-                },
-                "declarations": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/Location"
-                  }
-                },
-                "definitions": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/Location"
-                  }
-                },
-                "implementations": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/Location"
-                  }
-                },
-                "type_definitions": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/Location"
-                  }
-                },
-                "references": {
-                  "type": "array",
-                  "items": {
-                    "$ref": "#/$defs/Location"
-                  }
-                }
-              },
-              "required": [
-                "token",
-                "hover",
-                "declarations",
-                "definitions",
-                "implementations",
-                "type_definitions",
-                "references"
-              ]
-            },
-            "TokenInfo": {
-              "type": "object",
-              "properties": {
-                "location": {
-                  "$ref": "#/$defs/Location"
-                },
-                "token_type": {
-                  "type": "string"
-                },
-                "modifiers": {
-                  "type": "array",
-                  "items": {
-                    "type": "string"
-                  }
-                }
-              },
-              "required": [
-                "location",
-                "token_type",
-                "modifiers"
-              ]
-            },
             "Location": {
               "description": "Describes a location of a symbol.",
               "type": "object",
               "properties": {
                 "file": {
-                  "description": "File path.",
+                  "description": "File path.\n\nRelative to the workspace. If the workspace has multiple folders, the path starts with the folder name. Files of\ndependencies use virtual paths like `dep://serde@1.0.200/src/lib.rs` or `std://core/src/option.rs`.",
                   "type": "string"
                 },
                 "line": {
@@ -252,6 +2482,24 @@ async fn test_list_tools() {
                   "description": "1-based character.",
                   "type": "integer",
                   "minimum": 1
+                },
+                "absolute_file": {
+                  "description": "Absolute file path, only set if both relative and absolute paths are requested.",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "dependency": {
+                  "description": "Crate of a dependency file outside the workspace.",
+                  "anyOf": [
+                    {
+                      "$ref": "#/$defs/Dependency"
+                    },
+                    {
+                      "type": "null"
+                    }
+                  ]
                 }
               },
               "required": [
@@ -260,36 +2508,122 @@ async fn test_list_tools() {
                 "character"
               ]
             },
-            "HoverInfo": {
+            "Dependency": {
+              "description": "Package a dependency file belongs to.",
               "type": "object",
               "properties": {
-                "language": {
+                "crate": {
+                  "description": "Crate name.",
+                  "type": "string"
+                },
+                "version": {
+                  "description": "Crate version, unknown for the standard library.",
                   "type": [
                     "string",
                     "null"
                   ]
                 },
-                "value": {
+                "path": {
+                  "description": "File path, relative to the crate root.",
                   "type": "string"
                 }
               },
               "required": [
-                "value"
+                "crate",
+                "path"
+              ]
+            },
+            "TypeMember": {
+              "type": "object",
+              "properties": {
+                "name": {
+                  "description": "name of the field or variant, the index for tuple fields",
+                  "type": "string"
+                },
+                "kind": {
+                  "description": "`Field` or `EnumMember`",
+                  "type": "string"
+                },
+                "visibility": {
+                  "description": "e.g. `pub` or `pub(crate)`, not set for private fields and variants",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "type": {
+                  "description": "type of a field, or the payload of a variant like `(u32, u32)` or `{ radius: f64 }`",
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "docs": {
+                  "type": [
+                    "string",
+                    "null"
+                  ]
+                },
+                "location": {
+                  "$ref": "#/$defs/Location"
+                }
+              },
+              "required": [
+                "name",
+                "kind",
+                "location"
               ]
             }
           },
           "type": "object",
           "properties": {
-            "info": {
+            "name": {
+              "type": "string"
+            },
+            "kind": {
+              "description": "`Struct` or `Enum`",
+              "type": "string"
+            },
+            "location": {
+              "anyOf": [
+                {
+                  "$ref": "#/$defs/Location"
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "members": {
+              "description": "fields or variants in declaration order",
               "type": "array",
               "items": {
-                "$ref": "#/$defs/SymbolInfo"
+                "$ref": "#/$defs/TypeMember"
               }
+            },
+            "timed_out": {
+              "description": "the timeout was hit, results (e.g. docs) are incomplete",
+              "type": "boolean"
+            },
+            "workspace_root": {
+              "description": "absolute path of the workspace root, if requested",
+              "type": [
+                "string",
+                "null"
+              ]
             }
           },
           "required": [
-            "info"
+            "name",
+            "kind",
+            "members"
           ]
+        },
+        "annotations": {
+          "readOnlyHint": true,
+          "destructiveHint": false,
+          "idempotentHint": true,
+          "openWorldHint": false
         }
       }
     ]
@@ -298,3 +2632,45 @@ async fn test_list_tools() {
 
     setup.shutdown().await;
 }
+
+#[tokio::test]
+async fn test_instructions() {
+    let setup = TestSetup::new().await;
+    insta::assert_snapshot!(setup.instructions(), @r"
+    This server helps you to understand a code base.
+
+    It comes with these tools:
+    - `add_workspace_folder`: Add a folder to the workspace, e.g. another repository.
+    - `call_graph`: Get the call graph around a function or method as DOT (GraphViz) or Mermaid text: the functions that call it and/or the functions it calls, up to a given depth.
+    - `changed_symbols`: List the symbols (e.g. functions, structs, methods) that were added, modified, or removed between two git revisions, or between a revision and the working tree.
+    - `file_overview`: Get a cheap structural overview of a file: the number of tokens per type (e.g. functions, structs, variables) and the identifiers declared in it.
+    - `find_symbol`: Find symbol (e.g. a struct, enum, method, ...) in code base.
+    - `get_config`: Get the effective configuration of this server, i.e. CLI arguments, environment variables, config file, and defaults merged, and where each value comes from.
+    - `parent_module`: Find where a file or position hangs in the module tree: the `mod` declarations from the module that contains the position up to the crate root, plus the module path.
+    - `project_statistics`: Get the size of the project: number of files, lines, and symbols per kind (e.g. functions, structs), per language and per package (e.g. crate).
+    - `reload_config`: Reload the language server configuration (e.g. after changing the file passed via `--lsp-init-options`) and push it to the language servers without restarting them.
+    - `remove_workspace_folder`: Remove a folder from the workspace.
+    - `server_stats`: Get call counts, error counts, and latency percentiles of the tools, latency and error statistics of the requests sent to the language servers, and cache hit rates since startup, e.g. to find out which tools are slow or failing.
+    - `server_status`: Get status of the language servers, e.g. their version and if they are ready or still indexing the code base.
+    - `symbol_info`: Get detailed information about a given symbol (struct, enum, method, trait, ...) like documentation, declaration, references, usage across the code base, etc.
+    - `trait_detail`: Get the structure of a trait (or interface): its associated items (methods, constants, types), which of them are required and which have a default, and the types that implement it.
+    - `type_at`: Get the inferred type of a variable or expression at a position (or within a range) of a file, plus where that type is defined.
+    - `type_members`: List the fields of a struct or the variants of an enum with their types, visibility, and documentation as structured data.
+
+    First use the `find_symbol` tool to get the file path of the respective symbol. Then use the `symbol_info` tool to get the detailed information about them.
+    ");
+    setup.shutdown().await;
+
+    // only tools that can be called are mentioned
+    let setup = TestSetup::new_with_args(&["--workspace-jail"]).await;
+    let instructions = setup.instructions();
+    assert!(
+        !instructions.contains("`add_workspace_folder`"),
+        "{instructions}"
+    );
+    assert!(
+        instructions.contains("`remove_workspace_folder`"),
+        "{instructions}"
+    );
+    setup.shutdown().await;
+}
@@ -0,0 +1,223 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map, mock_args};
+
+#[tokio::test]
+async fn test_mock() {
+    let [command, arg] = mock_args();
+    let setup = TestSetup::new_with_args(&[&command, &arg]).await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .find_symbol_ok(map([("query", json!("my_lib_fn"))]))
+            .await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 14,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "unused_workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .symbol_info_ok(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("my_private_lib_fn")),
+            ]))
+            .await,
+        @r#"
+    {
+      "info": [
+        {
+          "token": {
+            "name": "my_private_lib_fn",
+            "location": {
+              "file": "src/lib.rs",
+              "line": 23,
+              "character": 4
+            },
+            "token_type": "function",
+            "modifiers": [
+              "declaration"
+            ]
+          },
+          "hover": [
+            {
+              "value": "```rust\nfn my_private_lib_fn() -> u64\n```\n\n---\n\nA private function that returns a constant value."
+            }
+          ],
+          "declarations": [
+            {
+              "file": "src/lib.rs",
+              "line": 23,
+              "character": 4
+            }
+          ],
+          "definitions": [
+            {
+              "file": "src/lib.rs",
+              "line": 23,
+              "character": 4
+            }
+          ],
+          "implementations": [],
+          "type_definitions": [],
+          "references": [
+            {
+              "file": "src/lib.rs",
+              "line": 18,
+              "character": 41,
+              "proximity": "same_file"
+            }
+          ]
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_mock_nested_workspace_symbols() {
+    let [command, arg] = mock_args();
+    let setup = TestSetup::new_with_args(&[
+        &command,
+        &arg,
+        "--lsp-arg=--mock-lsp-workspace-symbols=nested",
+    ])
+    .await;
+
+    // ranges are resolved
+    insta::assert_json_snapshot!(
+        setup
+            .find_symbol_ok(map([("query", json!("my_lib_fn"))]))
+            .await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 14,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "unused_workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_mock_unresolvable_workspace_symbols() {
+    let [command, arg] = mock_args();
+    let setup = TestSetup::new_with_args(&[
+        &command,
+        &arg,
+        "--lsp-arg=--mock-lsp-workspace-symbols=unresolvable",
+    ])
+    .await;
+
+    // symbols are kept, at the start of their file
+    insta::assert_json_snapshot!(
+        setup
+            .find_symbol_ok(map([("query", json!("my_lib_fn"))]))
+            .await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 1
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "unused_workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 1
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 1
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
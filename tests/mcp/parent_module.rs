@@ -0,0 +1,118 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_parent_module() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("src/outer")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"tree\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), "pub mod outer;\n").unwrap();
+    std::fs::write(root.join("src/outer.rs"), "pub(crate) mod inner;\n").unwrap();
+    std::fs::write(
+        root.join("src/outer/inner.rs"),
+        "pub fn deep() {}\n\nmod tests {\n    fn helper() {}\n}\n",
+    )
+    .unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .parent_module(map([("file", json!("src/outer/inner.rs"))]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "module_path": "crate::outer::inner",
+      "parents": [
+        {
+          "name": "inner",
+          "declaration": "pub(crate) mod inner;",
+          "location": {
+            "file": "src/outer.rs",
+            "line": 1,
+            "character": 16
+          }
+        },
+        {
+          "name": "outer",
+          "declaration": "pub mod outer;",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 9
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // inline module
+    insta::assert_json_snapshot!(
+        setup
+            .parent_module(map([
+                ("file", json!("src/outer/inner.rs")),
+                ("line", json!(4)),
+                ("character", json!(8)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "module_path": "crate::outer::inner::tests",
+      "parents": [
+        {
+          "name": "tests",
+          "declaration": "mod tests {",
+          "location": {
+            "file": "src/outer/inner.rs",
+            "line": 3,
+            "character": 5
+          }
+        },
+        {
+          "name": "inner",
+          "declaration": "pub(crate) mod inner;",
+          "location": {
+            "file": "src/outer.rs",
+            "line": 1,
+            "character": 16
+          }
+        },
+        {
+          "name": "outer",
+          "declaration": "pub mod outer;",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 1,
+            "character": 9
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // crate root
+    insta::assert_json_snapshot!(
+        setup
+            .parent_module(map([("file", json!("src/lib.rs"))]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "module_path": "crate",
+      "parents": []
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
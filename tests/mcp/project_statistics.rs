@@ -0,0 +1,58 @@
+use crate::setup::TestSetup;
+
+#[tokio::test]
+async fn test_project_statistics() {
+    let setup = TestSetup::new().await;
+
+    let results = setup.project_statistics().await.unwrap();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "languages": {
+        "rust": {
+          "files": 7,
+          "lines": 53,
+          "symbols": {
+            "Field": 1,
+            "Function": 10,
+            "Module": 1,
+            "Struct": 1
+          }
+        }
+      },
+      "packages": [
+        {
+          "path": ".",
+          "language": "rust",
+          "files": 3,
+          "lines": 45,
+          "symbols": {
+            "Field": 1,
+            "Function": 6,
+            "Module": 1,
+            "Struct": 1
+          }
+        },
+        {
+          "path": "unused_workspace_member",
+          "language": "rust",
+          "files": 2,
+          "lines": 4,
+          "symbols": {
+            "Function": 2
+          }
+        },
+        {
+          "path": "workspace_member",
+          "language": "rust",
+          "files": 2,
+          "lines": 4,
+          "symbols": {
+            "Function": 2
+          }
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use crate::setup::{TestSetup, map, mock_args};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_reload_config() {
+    let setup = TestSetup::new().await;
+
+    insta::assert_json_snapshot!(setup.reload_config().await.unwrap(), @r#"
+    {
+      "reloaded": [
+        "rust"
+      ]
+    }
+    "#);
+
+    // language server is still usable
+    let symbols = setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+    assert!(
+        !symbols["symbols"].as_array().unwrap().is_empty(),
+        "{symbols}"
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_hot_reload_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"reloaded\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub fn reloaded_a() {}\npub fn reloaded_b() {}\npub fn reloaded_c() {}\n",
+    )
+    .unwrap();
+
+    let [command, arg] = mock_args();
+    let setup =
+        TestSetup::new_with_workspaces_and_args(&[root.to_str().unwrap()], &[&command, &arg])
+            .await
+            .with_normalize_paths(false);
+    let query = || map([("query", json!("reloaded")), ("fuzzy", json!(true))]);
+
+    let results = setup.find_symbol_ok(query()).await;
+    assert_eq!(results["symbols"].as_array().unwrap().len(), 3, "{results}");
+    assert!(results.get("response_truncated").is_none(), "{results}");
+
+    std::fs::write(
+        root.join(".common-sense-coder.toml"),
+        "max-response-chars = 300\npath-style = \"absolute\"\n",
+    )
+    .unwrap();
+
+    // the file is polled, so wait for the change to be applied
+    let mut results = setup.find_symbol_ok(query()).await;
+    for _ in 0..100 {
+        if results.get("response_truncated").is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        results = setup.find_symbol_ok(query()).await;
+    }
+    assert!(results.get("response_truncated").is_some(), "{results}");
+    assert_eq!(
+        results["symbols"][0]["location"]["file"],
+        json!(root.join("src/lib.rs").display().to_string()),
+    );
+
+    setup.shutdown().await;
+}
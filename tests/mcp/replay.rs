@@ -0,0 +1,138 @@
+use serde_json::{Value, json};
+
+use crate::setup::{TestSetup, map, replay_args};
+
+#[tokio::test]
+async fn test_replay_call() {
+    let [command, arg] = replay_args("symbol_info.jsonl");
+    let output = TestSetup::run(&[
+        &command,
+        &arg,
+        "--call=symbol_info",
+        r#"--args={"file": "src/lib.rs", "name": "MyMainStruct"}"#,
+    ])
+    .await;
+    assert!(output.status.success(), "{output:?}");
+
+    let result = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    insta::assert_json_snapshot!(result, @r#"
+    {
+      "info": [
+        {
+          "token": {
+            "name": "MyMainStruct",
+            "location": {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            },
+            "token_type": "struct",
+            "modifiers": [
+              "declaration"
+            ]
+          },
+          "hover": [
+            {
+              "value": "```rust\nmain_lib\n```\n\n```rust\npub(crate) struct MyMainStruct {\n    pub field: u64,\n}\n```\n\n---\n\nA struct that \"shadows\" the `main` function.\n\nSee <https://github.com/rust-lang/rust-analyzer/issues/19486#issuecomment-2817393342>."
+            }
+          ],
+          "declarations": [
+            {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            }
+          ],
+          "definitions": [
+            {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            }
+          ],
+          "implementations": [],
+          "type_definitions": [],
+          "references": []
+        }
+      ]
+    }
+    "#);
+}
+
+#[tokio::test]
+async fn test_replay_mcp() {
+    let [command, arg] = replay_args("symbol_info.jsonl");
+    let setup = TestSetup::new_with_args(&[&command, &arg]).await;
+
+    let resp = setup
+        .symbol_info_ok(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("MyMainStruct")),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(resp, @r#"
+    {
+      "info": [
+        {
+          "token": {
+            "name": "MyMainStruct",
+            "location": {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            },
+            "token_type": "struct",
+            "modifiers": [
+              "declaration"
+            ]
+          },
+          "hover": [
+            {
+              "value": "```rust\nmain_lib\n```\n\n```rust\npub(crate) struct MyMainStruct {\n    pub field: u64,\n}\n```\n\n---\n\nA struct that \"shadows\" the `main` function.\n\nSee <https://github.com/rust-lang/rust-analyzer/issues/19486#issuecomment-2817393342>."
+            }
+          ],
+          "declarations": [
+            {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            }
+          ],
+          "definitions": [
+            {
+              "file": "src/lib.rs",
+              "line": 39,
+              "character": 19
+            }
+          ],
+          "implementations": [],
+          "type_definitions": [],
+          "references": []
+        }
+      ]
+    }
+    "#);
+
+    // not recorded
+    let resp = setup
+        .symbol_info(map([
+            ("file", json!("src/sub.rs")),
+            ("name", json!("my_sub_lib_fn")),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(resp, @r#"
+    {
+      "Err": {
+        "code": -32603,
+        "message": "SemanticTokensFullRequest: jsonrpsee error: ErrorObject { code: ServerError(-32803), message: \"no recorded response for textDocument/semanticTokens/full\", data: None }",
+        "data": {
+          "lsp_code": -32803,
+          "retryable": false,
+          "request_id": 2
+        }
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use crate::setup::TestSetup;
+
+#[tokio::test]
+async fn test_read_resource() {
+    let setup = TestSetup::new().await;
+
+    let uri = format!("file://{}", setup.workspace_path("Cargo.toml"));
+    insta::assert_json_snapshot!(setup.read_resource(&uri).await.unwrap(), @r#"
+    {
+      "contents": [
+        {
+          "uri": "file:///fixtures/main_lib/Cargo.toml",
+          "mimeType": "text/plain",
+          "text": "[package]\nname = \"main_lib\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\ndependency_lib = { path = \"../dependency_lib\" }\nworkspace_member = { path = \"./workspace_member\" }\n\n[workspace]\nmembers = [\n    \"unused_workspace_member\",\n    \"workspace_member\",\n]\n"
+        }
+      ]
+    }
+    "#);
+
+    let uri = format!("file://{}", setup.workspace_path("does_not_exist.rs"));
+    insta::assert_json_snapshot!(setup.read_resource(&uri).await.unwrap_err(), @r#"
+    {
+      "code": -32002,
+      "message": "file not found: /fixtures/main_lib/does_not_exist.rs"
+    }
+    "#);
+
+    insta::assert_json_snapshot!(setup.read_resource("file:///etc/passwd").await.unwrap_err(), @r#"
+    {
+      "code": -32602,
+      "message": "resource is not within the workspace: file:///etc/passwd"
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_subscribe() {
+    let setup = TestSetup::new().await;
+
+    // file that is not part of the Rust project, so the language server does not care
+    let path = setup.workspace_path("subscribe_test.txt");
+    let uri = format!("file://{path}");
+    let _cleanup = RemoveOnDrop(path.clone());
+
+    setup.subscribe(&uri).await;
+
+    std::fs::write(&path, "foo").unwrap();
+    assert_eq!(setup.wait_for_resource_updates().await, vec![uri.clone()]);
+
+    std::fs::write(&path, "foobar").unwrap();
+    assert_eq!(setup.wait_for_resource_updates().await, vec![uri.clone()]);
+
+    setup.unsubscribe(&uri).await;
+    std::fs::remove_file(&path).unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(setup.take_resource_updates(), Vec::<String>::new());
+
+    setup.shutdown().await;
+}
+
+/// Removes file when dropped, even if the test fails.
+#[derive(Debug)]
+struct RemoveOnDrop(String);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
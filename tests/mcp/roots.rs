@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use crate::setup::{TestSetup, map};
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn test_roots() {
+    let setup = TestSetup::new_with_roots(&["main_lib"]).await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+        ])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_roots_changed() {
+    let setup = TestSetup::new_with_roots(&["main_lib"]).await;
+    assert_ne!(lib_fn_files(&setup).await.unwrap(), Vec::<String>::new());
+
+    setup.set_roots(&["dependency_lib"]).await;
+
+    // language servers are restarted in the background, requests that race with the restart may fail
+    let mut files = vec![];
+    for _ in 0..100 {
+        files = lib_fn_files(&setup).await.unwrap_or_default();
+        if files == ["src/lib.rs"] {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    insta::assert_json_snapshot!(files, @r#"
+    [
+      "src/lib.rs"
+    ]
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_no_roots() {
+    let setup = TestSetup::new_without_workspace().await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol(map([
+            ("query", json!("MyMainStruct")),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32603,
+      "message": "MCP client does not support roots, use `--workspace` to set the workspace"
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+/// Files that contain `my_lib_fn`.
+async fn lib_fn_files(setup: &TestSetup) -> Result<Vec<String>, Value> {
+    let res = setup
+        .find_symbol(map([("query", json!("my_lib_fn"))]))
+        .await?;
+    Ok(res["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|symbol| symbol["location"]["file"].as_str().map(ToOwned::to_owned))
+        .collect())
+}
@@ -0,0 +1,141 @@
+use serde_json::{Value, json};
+
+use crate::setup::{TestSetup, map};
+
+const LIB: &str = r#"pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes() {
+        assert_eq!(add(1, 2), 3);
+    }
+
+    #[test]
+    fn fails() {
+        assert_eq!(add(1, 2), 4);
+    }
+}
+"#;
+
+/// Result without the output, which contains timings and paths.
+fn summary(result: &Value) -> Value {
+    let mut result = result.clone();
+    let result = result.as_object_mut().unwrap();
+    result.remove("stdout");
+    result.remove("stderr");
+    Value::Object(result.clone())
+}
+
+#[tokio::test]
+async fn test_run_test_disabled() {
+    for args in [
+        &["--read-only=false"][..],
+        &["--enable-run-test-tool"],
+        &[
+            "--enable-run-test-tool",
+            "--read-only=false",
+            "--workspace-jail",
+        ],
+    ] {
+        let setup = TestSetup::new_with_args(args).await;
+
+        assert!(
+            !setup
+                .list_all_tools()
+                .await
+                .iter()
+                .any(|tool| tool.name == "run_test")
+        );
+
+        setup.shutdown().await;
+    }
+}
+
+#[tokio::test]
+async fn test_run_test() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"tested\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), LIB).unwrap();
+
+    let setup = TestSetup::new_with_workspaces_and_args(
+        &[root.to_str().unwrap()],
+        &["--enable-run-test-tool", "--read-only=false"],
+    )
+    .await;
+
+    let result = setup
+        .run_test(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("passes")),
+        ]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(summary(&result), @r#"
+    {
+      "test": "test tests::passes",
+      "command": "cargo test --package tested --lib -- tests::passes --exact --nocapture --include-ignored",
+      "exit_code": 0,
+      "success": true
+    }
+    "#);
+    assert!(
+        result["stdout"]
+            .as_str()
+            .unwrap()
+            .contains("test tests::passes ... ok"),
+        "{result:#}"
+    );
+
+    // line within the failing test
+    let result = setup
+        .run_test(map([("file", json!("src/lib.rs")), ("line", json!(16))]))
+        .await
+        .unwrap();
+    insta::assert_json_snapshot!(summary(&result), @r#"
+    {
+      "test": "test tests::fails",
+      "command": "cargo test --package tested --lib -- tests::fails --exact --nocapture --include-ignored",
+      "exit_code": 101,
+      "success": false
+    }
+    "#);
+    assert!(
+        result["stdout"]
+            .as_str()
+            .unwrap()
+            .contains("test tests::fails ... FAILED"),
+        "{result:#}"
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .run_test(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("missing")),
+            ]))
+            .await
+            .unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no test found: `missing` in src/lib.rs\n- tests in the file are: tests, tests::passes, tests::fails",
+      "data": {
+        "request_id": 3
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
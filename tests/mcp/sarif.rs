@@ -0,0 +1,137 @@
+use serde_json::Value;
+
+use crate::setup::TestSetup;
+
+#[tokio::test]
+async fn test_export_diagnostics() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"broken\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "mod missing;\n\npub fn f() -> u32 {\n    \"ä\"\n}\n",
+    )
+    .unwrap();
+
+    let output =
+        TestSetup::run_with_workspaces(&[root.to_str().unwrap()], &["export-diagnostics"]).await;
+    assert!(output.status.success(), "{output:?}");
+
+    let mut report = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    let run = &mut report["runs"][0];
+    assert_eq!(
+        run["originalUriBaseIds"]["SRCROOT"]["uri"],
+        format!("file://{}/", root.display())
+    );
+    run["originalUriBaseIds"]["SRCROOT"]["uri"] = Value::from("<root>");
+    // version depends on the installed language server
+    run["tool"]["driver"]["version"] = Value::from("<version>");
+    insta::assert_json_snapshot!(report, @r#"
+    {
+      "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+      "version": "2.1.0",
+      "runs": [
+        {
+          "tool": {
+            "driver": {
+              "name": "rust-analyzer",
+              "version": "<version>",
+              "rules": [
+                {
+                  "id": "E0308",
+                  "helpUri": "https://doc.rust-lang.org/error-index.html#E0308"
+                },
+                {
+                  "id": "E0583",
+                  "helpUri": "https://doc.rust-lang.org/error-index.html#E0583"
+                }
+              ]
+            }
+          },
+          "originalUriBaseIds": {
+            "SRCROOT": {
+              "uri": "<root>"
+            }
+          },
+          "columnKind": "unicodeCodePoints",
+          "results": [
+            {
+              "ruleId": "E0583",
+              "level": "error",
+              "message": {
+                "text": "file not found for module `missing`\nto create the module `missing`, create file \"src/missing.rs\" or \"src/missing/mod.rs\"\nif there is a `mod missing` elsewhere in the crate already, import it with `use crate::...` instead"
+              },
+              "locations": [
+                {
+                  "physicalLocation": {
+                    "artifactLocation": {
+                      "uri": "src/lib.rs",
+                      "uriBaseId": "SRCROOT"
+                    },
+                    "region": {
+                      "startLine": 1,
+                      "startColumn": 1,
+                      "endLine": 1,
+                      "endColumn": 13
+                    }
+                  }
+                }
+              ]
+            },
+            {
+              "ruleId": "E0308",
+              "level": "note",
+              "message": {
+                "text": "expected `u32` because of return type"
+              },
+              "locations": [
+                {
+                  "physicalLocation": {
+                    "artifactLocation": {
+                      "uri": "src/lib.rs",
+                      "uriBaseId": "SRCROOT"
+                    },
+                    "region": {
+                      "startLine": 3,
+                      "startColumn": 15,
+                      "endLine": 3,
+                      "endColumn": 18
+                    }
+                  }
+                }
+              ]
+            },
+            {
+              "ruleId": "E0308",
+              "level": "error",
+              "message": {
+                "text": "mismatched types\nexpected `u32`, found `&str`"
+              },
+              "locations": [
+                {
+                  "physicalLocation": {
+                    "artifactLocation": {
+                      "uri": "src/lib.rs",
+                      "uriBaseId": "SRCROOT"
+                    },
+                    "region": {
+                      "startLine": 4,
+                      "startColumn": 5,
+                      "endLine": 4,
+                      "endColumn": 8
+                    }
+                  }
+                }
+              ]
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+}
@@ -0,0 +1,32 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_redact_secrets() {
+    let setup = TestSetup::new_with_args(&[
+        r#"--redact-secrets-pattern=path = "(?P<secret>[^"]+)""#,
+        "--redact-secrets-pattern=returns a constant",
+    ])
+    .await;
+
+    let uri = format!("file://{}", setup.workspace_path("Cargo.toml"));
+    let resource = setup.read_resource(&uri).await.unwrap();
+    insta::assert_json_snapshot!(resource["contents"][0]["text"], @r#""[package]\nname = \"main_lib\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\ndependency_lib = { path = \"<redacted>\" }\nworkspace_member = { path = \"<redacted>\" }\n\n[workspace]\nmembers = [\n    \"unused_workspace_member\",\n    \"workspace_member\",\n]\n""#);
+
+    let results = setup
+        .symbol_info_ok(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("my_private_lib_fn")),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(results["info"][0]["hover"], @r#"
+    [
+      {
+        "value": "```rust\nmain_lib\n```\n\n```rust\nfn my_private_lib_fn() -> u64\n```\n\n---\n\nA private function that <redacted> value."
+      }
+    ]
+    "#);
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,328 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_server_stats() {
+    let setup = TestSetup::new().await;
+
+    setup
+        .find_symbol_ok(map([("file", json!("src/lib.rs"))]))
+        .await;
+
+    let mut stats = setup.server_stats().await.unwrap();
+
+    // durations depend on the machine
+    for request in stats["servers"][0]["requests"].as_array_mut().unwrap() {
+        for field in ["mean_ms", "max_ms"] {
+            let duration = &mut request[field];
+            assert!(matches!(duration, Value::Number(_)), "{duration}");
+            *duration = json!("<ms>");
+        }
+    }
+
+    for tool in stats["tools"].as_array_mut().unwrap() {
+        for field in ["p50_ms", "p95_ms"] {
+            let duration = &mut tool[field];
+            assert!(matches!(duration, Value::Number(_)), "{duration}");
+            *duration = json!("<ms>");
+        }
+    }
+
+    // sizes depend on the paths of the fixtures
+    let redact_bytes = |cache: &mut Value| {
+        if let Some(bytes) = cache.get_mut("bytes") {
+            assert!(matches!(bytes, Value::Number(_)), "{bytes}");
+            *bytes = json!("<bytes>");
+        }
+    };
+    for cache in stats["servers"][0].as_object_mut().unwrap().values_mut() {
+        redact_bytes(cache);
+    }
+    redact_bytes(&mut stats["cache_budget"]);
+
+    insta::assert_json_snapshot!(stats, @r#"
+    {
+      "tools": [
+        {
+          "tool": "find_symbol",
+          "count": 1,
+          "errors": 0,
+          "p50_ms": "<ms>",
+          "p95_ms": "<ms>"
+        }
+      ],
+      "servers": [
+        {
+          "language": "rust",
+          "requests": [
+            {
+              "method": "textDocument/documentSymbol",
+              "count": 1,
+              "errors": 0,
+              "mean_ms": "<ms>",
+              "max_ms": "<ms>"
+            },
+            {
+              "method": "textDocument/semanticTokens/full",
+              "count": 1,
+              "errors": 0,
+              "mean_ms": "<ms>",
+              "max_ms": "<ms>"
+            }
+          ],
+          "workspace_symbol_cache": {
+            "entries": 0,
+            "bytes": "<bytes>",
+            "hits": 0,
+            "misses": 0,
+            "hit_rate": null,
+            "evictions": 0
+          },
+          "hover_cache": {
+            "entries": 0,
+            "bytes": "<bytes>",
+            "hits": 0,
+            "misses": 0,
+            "hit_rate": null,
+            "evictions": 0
+          },
+          "document_cache": {
+            "entries": 0,
+            "bytes": "<bytes>",
+            "hits": 0,
+            "misses": 0,
+            "hit_rate": null,
+            "evictions": 0
+          },
+          "semantic_tokens_cache": {
+            "entries": 1,
+            "bytes": "<bytes>",
+            "hits": 0,
+            "misses": 1,
+            "hit_rate": 0.0,
+            "evictions": 0
+          }
+        }
+      ],
+      "cache_budget": {
+        "max_bytes": 67108864,
+        "bytes": "<bytes>",
+        "evictions": 0
+      },
+      "task_restarts": 0
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_tool_stats() {
+    let setup = TestSetup::new().await;
+
+    for _ in 0..2 {
+        setup
+            .find_symbol_ok(map([("file", json!("src/lib.rs"))]))
+            .await;
+    }
+    setup
+        .symbol_info(map([
+            ("file", json!("src/missing.rs")),
+            ("name", json!("missing")),
+        ]))
+        .await
+        .unwrap_err();
+    setup.server_stats().await.unwrap();
+
+    let stats = setup.server_stats().await.unwrap();
+    let tools = stats["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| {
+            let p50 = tool["p50_ms"].as_f64().unwrap();
+            let p95 = tool["p95_ms"].as_f64().unwrap();
+            assert!(p50 <= p95, "{tool}");
+            (
+                tool["tool"].as_str().unwrap().to_owned(),
+                tool["count"].as_u64().unwrap(),
+                tool["errors"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    insta::assert_json_snapshot!(tools, @r#"
+    [
+      [
+        "find_symbol",
+        2,
+        0
+      ],
+      [
+        "server_stats",
+        1,
+        0
+      ],
+      [
+        "symbol_info",
+        1,
+        1
+      ]
+    ]
+    "#);
+    assert_eq!(stats["task_restarts"], json!(0));
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_semantic_tokens_delta() {
+    let setup = TestSetup::new().await;
+
+    for _ in 0..2 {
+        setup
+            .find_symbol_ok(map([("file", json!("src/lib.rs"))]))
+            .await;
+    }
+
+    let methods = request_counts(&setup).await;
+
+    // the second call only fetches the changes
+    insta::assert_json_snapshot!(methods, @r#"
+    [
+      [
+        "textDocument/documentSymbol",
+        2
+      ],
+      [
+        "textDocument/semanticTokens/full",
+        1
+      ],
+      [
+        "textDocument/semanticTokens/full/delta",
+        1
+      ]
+    ]
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_workspace_symbol_cache() {
+    let setup = TestSetup::new_with_args(&["--symbol-cache-ttl=600"]).await;
+
+    // the language server may re-index in between, which invalidates the cache
+    for _ in 0..3 {
+        setup
+            .find_symbol_ok(map([("query", json!("my_lib_fn"))]))
+            .await;
+    }
+
+    let stats = setup.server_stats().await.unwrap();
+    let server = &stats["servers"][0];
+    let cache = &server["workspace_symbol_cache"];
+    let hits = cache["hits"].as_u64().unwrap();
+    let misses = cache["misses"].as_u64().unwrap();
+    assert!(hits >= 1, "{cache}");
+    assert_eq!(hits + misses, 3, "{cache}");
+    let count = request_counts(&setup)
+        .await
+        .into_iter()
+        .find(|(method, _count)| method == "workspace/symbol")
+        .unwrap()
+        .1;
+    assert_eq!(count, misses);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_hover_cache() {
+    let setup = TestSetup::new().await;
+
+    // the language server may re-index in between, which invalidates the cache
+    for _ in 0..3 {
+        setup
+            .symbol_info_ok(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("my_lib_fn")),
+            ]))
+            .await;
+    }
+
+    let stats = setup.server_stats().await.unwrap();
+    let cache = &stats["servers"][0]["hover_cache"];
+    let hits = cache["hits"].as_u64().unwrap();
+    let misses = cache["misses"].as_u64().unwrap();
+    assert!(hits >= 1, "{cache}");
+    let count = request_counts(&setup)
+        .await
+        .into_iter()
+        .find(|(method, _count)| method == "textDocument/hover")
+        .unwrap()
+        .1;
+    assert_eq!(count, misses);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_warmup() {
+    let setup = TestSetup::new_with_args(&["--warmup-globs=src/*.rs,*.md"]).await;
+
+    // warm-up runs in the background
+    let semantic_tokens = async || {
+        request_counts(&setup)
+            .await
+            .into_iter()
+            .filter(|(method, _count)| method.starts_with("textDocument/semanticTokens/"))
+            .map(|(_method, count)| count)
+            .sum::<u64>()
+    };
+    for _ in 0..100 {
+        if semantic_tokens().await >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(semantic_tokens().await >= 2);
+
+    // without warm-up, every call fetches the semantic tokens, at least via a delta request
+    let mut cached = false;
+    for _ in 0..10 {
+        let before = semantic_tokens().await;
+        setup
+            .symbol_info_ok(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("my_lib_fn")),
+            ]))
+            .await;
+        if semantic_tokens().await == before {
+            cached = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(cached);
+
+    setup.shutdown().await;
+}
+
+/// Number of requests per method of the first language server.
+async fn request_counts(setup: &TestSetup) -> Vec<(String, u64)> {
+    let stats = setup.server_stats().await.unwrap();
+    stats["servers"][0]["requests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|request| {
+            (
+                request["method"].as_str().unwrap().to_owned(),
+                request["count"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
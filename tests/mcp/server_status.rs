@@ -0,0 +1,93 @@
+use serde_json::{Value, json};
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_server_status() {
+    let setup = TestSetup::new().await;
+
+    // wait for the language server to be ready
+    setup
+        .find_symbol_ok(map([("file", json!("src/lib.rs"))]))
+        .await;
+
+    let mut status = setup.server_status().await.unwrap();
+
+    // version depends on the installed language server
+    let version = &mut status["servers"][0]["server_version"];
+    assert!(matches!(version, Value::String(_)), "{version}");
+    *version = json!("<version>");
+
+    // uptime depends on the timing
+    for task in status["tasks"].as_array_mut().unwrap() {
+        let uptime = &mut task["uptime_secs"];
+        assert!(uptime.is_f64(), "{uptime}");
+        *uptime = json!("<uptime>");
+    }
+
+    insta::assert_json_snapshot!(status, @r#"
+    {
+      "servers": [
+        {
+          "language": "rust",
+          "server_name": "rust-analyzer",
+          "server_version": "<version>",
+          "version_check": {
+            "status": "ok"
+          },
+          "ready": true,
+          "init_timed_out": false,
+          "missing_init_parts": [],
+          "running": 0,
+          "queued": 0
+        }
+      ],
+      "tasks": [
+        {
+          "name": "mcp.stdin.txt",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "mcp.stdout.txt",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "lsp.rust.stdout.txt",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "lsp.rust.stdin.txt",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "progress guard",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "server messages",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        },
+        {
+          "name": "diagnostics",
+          "state": "running",
+          "restarts": 0,
+          "uptime_secs": "<uptime>"
+        }
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
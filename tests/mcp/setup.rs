@@ -1,15 +1,37 @@
-use std::{ops::Deref, path::Path, process::Stdio};
+use std::{
+    net::TcpListener,
+    ops::Deref,
+    path::Path,
+    process::{Output, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use assert_cmd::{cargo::cargo_bin, pkg_name};
 use rmcp::{
-    RoleClient,
-    model::{CallToolRequestParams, JsonObject, Tool},
-    service::{RunningService, ServiceError, ServiceExt},
-    transport::TokioChildProcess,
+    ClientHandler, RoleClient,
+    model::{
+        ArgumentInfo, CallToolRequestParams, ClientCapabilities, ClientInfo, CompleteRequestParams,
+        ErrorData as McpError, JsonObject, ListRootsResult, Meta, NumberOrString,
+        ProgressNotificationParam, ProgressToken, ReadResourceRequestParams, Reference,
+        ResourceUpdatedNotificationParam, Root, SubscribeRequestParams, Tool,
+        UnsubscribeRequestParams,
+    },
+    service::{
+        ClientInitializeError, NotificationContext, RequestContext, RunningService, ServiceError,
+        ServiceExt,
+    },
+    transport::{
+        StreamableHttpClientTransport, TokioChildProcess,
+        streamable_http_client::StreamableHttpClientTransportConfig,
+    },
 };
 use serde_json::Value;
 use tempfile::TempDir;
-use tokio::process::Command;
+use tokio::{
+    io::AsyncWriteExt,
+    process::{Child, Command},
+};
 
 /// Temporary directory that holds IO interception data (like logs).
 ///
@@ -53,6 +75,69 @@ impl Drop for InterceptIoDir {
     }
 }
 
+/// MCP client that records notifications sent by the server.
+#[derive(Debug, Default)]
+pub(crate) struct TestClient {
+    resource_updates: Arc<Mutex<Vec<String>>>,
+
+    /// Messages of progress notifications.
+    progress_messages: Arc<Mutex<Vec<String>>>,
+
+    /// Roots announced to the server, `None` if the client does not support roots.
+    roots: Option<Arc<Mutex<Vec<Root>>>>,
+}
+
+impl ClientHandler for TestClient {
+    fn get_info(&self) -> ClientInfo {
+        let mut info = ClientInfo::default();
+        if self.roots.is_some() {
+            #[expect(deprecated, reason = "test roots support")]
+            let capabilities = ClientCapabilities::builder()
+                .enable_roots()
+                .enable_roots_list_changed()
+                .build();
+            info.capabilities = capabilities;
+        }
+        info
+    }
+
+    async fn list_roots(
+        &self,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, McpError> {
+        let roots = self
+            .roots
+            .as_ref()
+            .map(|roots| roots.lock().expect("not poisoned").clone())
+            .unwrap_or_default();
+        Ok(ListRootsResult::new(roots))
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.resource_updates
+            .lock()
+            .expect("not poisoned")
+            .push(params.uri);
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        if let Some(message) = params.message {
+            self.progress_messages
+                .lock()
+                .expect("not poisoned")
+                .push(message);
+        }
+    }
+}
+
 /// Test fixture that contains a running MCP server.
 #[derive(Debug)]
 pub(crate) struct TestSetup {
@@ -60,18 +145,228 @@ pub(crate) struct TestSetup {
 
     intercept_io_dir: InterceptIoDir,
 
-    #[expect(dead_code)]
     cwd: TempDir,
 
-    service: Option<RunningService<RoleClient, ()>>,
+    /// Additional server arguments.
+    args: Vec<String>,
+
+    /// Fixtures that are passed via `--workspace`.
+    workspaces: Vec<String>,
+
+    /// Roots announced by the client, `None` if the client does not support roots.
+    roots: Option<Arc<Mutex<Vec<Root>>>>,
+
+    service: Option<RunningService<RoleClient, TestClient>>,
+
+    /// Server that was started in HTTP mode.
+    http: Option<HttpServer>,
 
     normalize_paths: bool,
 }
 
+/// MCP server that listens for HTTP clients.
+#[derive(Debug)]
+struct HttpServer {
+    child: Child,
+    url: String,
+
+    /// Token that clients need to send.
+    auth_token: Option<String>,
+}
+
 impl TestSetup {
     pub(crate) async fn new() -> Self {
-        let server_path = cargo_bin(pkg_name!()).canonicalize().expect("canonicalize");
+        Self::new_with_args(&[]).await
+    }
+
+    /// Start server with additional arguments.
+    pub(crate) async fn new_with_args(args: &[&str]) -> Self {
+        let mut this = Self::prepare(args);
+        this.service = Some(this.spawn_stdio().await);
+        this
+    }
+
+    /// Start server with the given fixtures as workspace folders.
+    pub(crate) async fn new_with_workspaces(fixtures: &[&str]) -> Self {
+        Self::new_with_workspaces_and_args(fixtures, &[]).await
+    }
+
+    /// Start server with the given fixtures as workspace folders and additional arguments.
+    pub(crate) async fn new_with_workspaces_and_args(fixtures: &[&str], args: &[&str]) -> Self {
+        let mut this = Self::prepare(args);
+        this.workspaces = fixtures
+            .iter()
+            .map(|fixture| (*fixture).to_owned())
+            .collect();
+        this.service = Some(this.spawn_stdio().await);
+        this
+    }
+
+    /// Start server without `--workspace`, the client announces the given fixtures as roots.
+    pub(crate) async fn new_with_roots(fixtures: &[&str]) -> Self {
+        let mut this = Self::prepare(&[]);
+        this.workspaces.clear();
+        this.roots = Some(Arc::new(Mutex::new(this.fixture_roots(fixtures))));
+        this.service = Some(this.spawn_stdio().await);
+        this
+    }
+
+    /// Start server without `--workspace` and connect a client that does not support roots.
+    pub(crate) async fn new_without_workspace() -> Self {
+        let mut this = Self::prepare(&[]);
+        this.workspaces.clear();
+        this.service = Some(this.spawn_stdio().await);
+        this
+    }
+
+    /// Change the roots announced by the client.
+    pub(crate) async fn set_roots(&self, fixtures: &[&str]) {
+        *self
+            .roots
+            .as_ref()
+            .expect("client supports roots")
+            .lock()
+            .expect("not poisoned") = self.fixture_roots(fixtures);
+
+        self.service
+            .as_ref()
+            .expect("not shut down")
+            .notify_roots_list_changed()
+            .await
+            .expect("notify roots changed");
+    }
+
+    fn fixture_roots(&self, fixtures: &[&str]) -> Vec<Root> {
+        fixtures
+            .iter()
+            .map(|fixture| Root::new(format!("file://{}/{fixture}", self.fixtures_path)))
+            .collect()
+    }
+
+    fn client(&self) -> TestClient {
+        TestClient {
+            resource_updates: Default::default(),
+            progress_messages: Default::default(),
+            roots: self.roots.clone(),
+        }
+    }
+
+    /// Start server that listens for HTTP clients and connect to it.
+    ///
+    /// Use [`connect`](Self::connect) to add more clients.
+    pub(crate) async fn new_http() -> Self {
+        Self::new_http_with_auth_token(None).await
+    }
+
+    /// Same as [`new_http`](Self::new_http) but clients need to send the given token.
+    pub(crate) async fn new_http_with_auth_token(auth_token: Option<&str>) -> Self {
+        let mut this = Self::prepare(&[]);
+        let (mut cmd, server_stderr) = this.command().await;
+        if let Some(auth_token) = auth_token {
+            cmd.arg("--listen-auth-token").arg(auth_token);
+        }
+
+        // pick free port
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .expect("bind")
+            .local_addr()
+            .expect("local addr");
+        let child = cmd
+            .arg("--listen")
+            .arg(addr.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(server_stderr)
+            .kill_on_drop(true)
+            .spawn()
+            .expect("spawn language server");
+        this.http = Some(HttpServer {
+            child,
+            url: format!("http://{addr}/mcp"),
+            auth_token: auth_token.map(ToOwned::to_owned),
+        });
+
+        this.service = Some(this.connect().await);
+        this
+    }
+
+    /// Connect another client to a server that was started via [`new_http`](Self::new_http).
+    pub(crate) async fn connect(&self) -> RunningService<RoleClient, TestClient> {
+        let http = self.http.as_ref().expect("HTTP server");
+
+        // server only listens after the language server is initialized
+        for _ in 0..600 {
+            if let Ok(service) = self.try_connect(http.auth_token.as_deref()).await {
+                return service;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("cannot connect to {}", http.url);
+    }
 
+    /// Try to connect another client with the given auth token, without retrying.
+    pub(crate) async fn try_connect(
+        &self,
+        auth_token: Option<&str>,
+    ) -> Result<RunningService<RoleClient, TestClient>, ClientInitializeError> {
+        let http = self.http.as_ref().expect("HTTP server");
+        let mut config = StreamableHttpClientTransportConfig::with_uri(http.url.as_str());
+        if let Some(auth_token) = auth_token {
+            config = config.auth_header(auth_token);
+        }
+        let transport = StreamableHttpClientTransport::from_config(config);
+        self.client().serve(transport).await
+    }
+
+    /// Run server with additional arguments to completion, without connecting an MCP client.
+    pub(crate) async fn run(args: &[&str]) -> Output {
+        Self::run_with_stdin(args, "").await
+    }
+
+    /// Same as [`run`](Self::run) but passes the given data via stdin.
+    pub(crate) async fn run_with_stdin(args: &[&str], stdin: &str) -> Output {
+        Self::prepare(args).output(stdin).await
+    }
+
+    /// Same as [`run`](Self::run) but with the given fixtures as workspace folders.
+    pub(crate) async fn run_with_workspaces(fixtures: &[&str], args: &[&str]) -> Output {
+        let mut this = Self::prepare(args);
+        this.workspaces = fixtures
+            .iter()
+            .map(|fixture| (*fixture).to_owned())
+            .collect();
+        this.output("").await
+    }
+
+    async fn output(&self, stdin: &str) -> Output {
+        // stderr is captured as part of the output
+        let (mut cmd, _server_stderr) = self.command().await;
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn server");
+
+        let mut child_stdin = child.stdin.take().expect("stdin piped");
+        child_stdin
+            .write_all(stdin.as_bytes())
+            .await
+            .expect("write stdin");
+        drop(child_stdin);
+
+        child.wait_with_output().await.expect("run server")
+    }
+
+    /// Disconnect and start server again with the same arguments.
+    pub(crate) async fn reconnect(&mut self) {
+        let service = self.service.take().expect("not shut down yet");
+        service.cancel().await.expect("shut down service");
+
+        self.service = Some(self.spawn_stdio().await);
+    }
+
+    fn prepare(args: &[&str]) -> Self {
         let fixtures_path = Path::new(file!())
             .parent()
             .expect("parent 1")
@@ -80,10 +375,37 @@ impl TestSetup {
             .join("fixtures")
             .canonicalize()
             .expect("canonicalize");
-        let main_lib_path = fixtures_path.join("main_lib").display().to_string();
 
-        let intercept_io_dir = InterceptIoDir::new();
-        let server_stderr_path = intercept_io_dir.join("server.stderr.txt");
+        Self {
+            fixtures_path: fixtures_path.display().to_string(),
+            intercept_io_dir: InterceptIoDir::new(),
+            // add a cwd to avoid dependency on it
+            cwd: TempDir::new().expect("create CWD temp dir"),
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            workspaces: vec!["main_lib".to_owned()],
+            roots: None,
+            service: None,
+            http: None,
+            normalize_paths: true,
+        }
+    }
+
+    async fn spawn_stdio(&self) -> RunningService<RoleClient, TestClient> {
+        let (cmd, server_stderr) = self.command().await;
+
+        let child = TokioChildProcess::builder(cmd)
+            .stderr(server_stderr)
+            .spawn()
+            .expect("spawn language server")
+            .0;
+        self.client().serve(child).await.expect("service start")
+    }
+
+    /// Prepare server command.
+    async fn command(&self) -> (Command, Stdio) {
+        let server_path = cargo_bin(pkg_name!()).canonicalize().expect("canonicalize");
+
+        let server_stderr_path = self.intercept_io_dir.join("server.stderr.txt");
         println!("server stderr: {}", server_stderr_path.display());
         let server_stderr = Stdio::from(
             tokio::fs::OpenOptions::new()
@@ -96,32 +418,19 @@ impl TestSetup {
                 .await,
         );
 
-        // add a cwd to avoid dependency on it
-        let cwd = TempDir::new().expect("create CWD temp dir");
-
         let mut cmd = Command::new(server_path);
-        cmd.current_dir(cwd.path())
+        cmd.current_dir(self.cwd.path())
             .env("RUST_BACKTRACE", "1")
             .arg("--intercept-io")
-            .arg(intercept_io_dir.display().to_string())
-            .arg("--workspace")
-            .arg(main_lib_path)
-            .arg("-vv");
-
-        let child = TokioChildProcess::builder(cmd)
-            .stderr(server_stderr)
-            .spawn()
-            .expect("spawn language server")
-            .0;
-        let service = ().serve(child).await.expect("service start");
-
-        Self {
-            fixtures_path: fixtures_path.display().to_string(),
-            intercept_io_dir,
-            cwd,
-            service: Some(service),
-            normalize_paths: true,
+            .arg(self.intercept_io_dir.display().to_string())
+            .arg("-vv")
+            .args(&self.args);
+        for workspace in &self.workspaces {
+            cmd.arg("--workspace")
+                .arg(Path::new(&self.fixtures_path).join(workspace));
         }
+
+        (cmd, server_stderr)
     }
 
     pub(crate) fn with_normalize_paths(mut self, normalize_paths: bool) -> Self {
@@ -129,6 +438,16 @@ impl TestSetup {
         self
     }
 
+    /// Instructions that the server announced during initialization.
+    pub(crate) fn instructions(&self) -> String {
+        self.service
+            .as_ref()
+            .expect("not shut down")
+            .peer_info()
+            .and_then(|info| info.instructions.clone())
+            .expect("instructions")
+    }
+
     pub(crate) async fn list_all_tools(&self) -> Vec<Tool> {
         self.service
             .as_ref()
@@ -148,7 +467,11 @@ impl TestSetup {
         {
             Ok(resp) => resp,
             Err(ServiceError::McpError(error)) => {
-                return Err(serde_json::to_value(error).expect("serialize MCP error"));
+                let mut error = serde_json::to_value(error).expect("serialize MCP error");
+                if self.normalize_paths {
+                    error = normalize_paths(error, &self.fixtures_path);
+                }
+                return Err(error);
             }
             Err(error) => panic!("call tool: {error}"),
         };
@@ -157,6 +480,16 @@ impl TestSetup {
             .structured_content
             .expect("tool result should always have structured content");
 
+        // clients that do not understand structured content read the text block instead
+        let [content] = resp.content.as_slice() else {
+            panic!("tool result should have exactly one content block");
+        };
+        let text = &content.as_text().expect("content should be text").text;
+        assert_eq!(
+            serde_json::from_str::<Value>(text).expect("text content should be JSON"),
+            data,
+        );
+
         if self.normalize_paths {
             data = normalize_paths(data, &self.fixtures_path);
         }
@@ -182,14 +515,263 @@ impl TestSetup {
             .await
     }
 
+    /// Call `symbol_info` with a progress token, so the server streams partial results.
+    pub(crate) async fn symbol_info_with_progress(&self, args: JsonObject) -> Result<Value, Value> {
+        let mut params = CallToolRequestParams::new("symbol_info").with_arguments(args);
+        params.meta = Some(Meta::with_progress_token(ProgressToken(
+            NumberOrString::Number(1),
+        )));
+        self.call_tool(params).await
+    }
+
     pub(crate) async fn symbol_info_ok(&self, args: JsonObject) -> Value {
         self.symbol_info(args).await.expect("no error")
     }
+
+    pub(crate) async fn file_overview(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("file_overview").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn call_graph(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("call_graph").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn changed_symbols(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("changed_symbols").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn project_statistics(&self) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("project_statistics"))
+            .await
+    }
+
+    pub(crate) async fn run_check(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("run_check").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn run_test(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("run_test").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn trait_detail(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("trait_detail").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn type_at(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("type_at").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn type_members(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("type_members").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn parent_module(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("parent_module").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn view_item_tree(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("view_item_tree").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn view_syntax_tree(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("view_syntax_tree").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn server_status(&self) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("server_status"))
+            .await
+    }
+
+    pub(crate) async fn server_stats(&self) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("server_stats"))
+            .await
+    }
+
+    pub(crate) async fn lsp_request(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("lsp_request").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn debug_tail(&self, args: JsonObject) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("debug_tail").with_arguments(args))
+            .await
+    }
+
+    pub(crate) async fn reload_config(&self) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("reload_config"))
+            .await
+    }
+
+    pub(crate) async fn get_config(&self) -> Result<Value, Value> {
+        self.call_tool(CallToolRequestParams::new("get_config"))
+            .await
+    }
+
+    pub(crate) async fn add_workspace_folder(&self, path: &str) -> Result<Value, Value> {
+        self.call_tool(
+            CallToolRequestParams::new("add_workspace_folder")
+                .with_arguments(map([("path", Value::from(path))])),
+        )
+        .await
+    }
+
+    pub(crate) async fn remove_workspace_folder(&self, folder: &str) -> Result<Value, Value> {
+        self.call_tool(
+            CallToolRequestParams::new("remove_workspace_folder")
+                .with_arguments(map([("folder", Value::from(folder))])),
+        )
+        .await
+    }
+
+    pub(crate) async fn complete(
+        &self,
+        reference: Reference,
+        argument: &str,
+        value: &str,
+    ) -> Value {
+        let resp = self
+            .service
+            .as_ref()
+            .expect("not shut down")
+            .complete(CompleteRequestParams::new(
+                reference,
+                ArgumentInfo {
+                    name: argument.to_owned(),
+                    value: value.to_owned(),
+                },
+            ))
+            .await
+            .expect("complete");
+        normalize_paths(
+            serde_json::to_value(resp).expect("serialize completion"),
+            &self.fixtures_path,
+        )
+    }
+
+    /// Absolute path of a fixture.
+    pub(crate) fn fixture_path(&self, fixture: &str) -> String {
+        format!("{}/{fixture}", self.fixtures_path)
+    }
+
+    /// Absolute path of a file within the test workspace.
+    pub(crate) fn workspace_path(&self, file: &str) -> String {
+        format!("{}/main_lib/{file}", self.fixtures_path)
+    }
+
+    pub(crate) async fn read_resource(&self, uri: &str) -> Result<Value, Value> {
+        match self
+            .service
+            .as_ref()
+            .expect("not shut down")
+            .read_resource(ReadResourceRequestParams::new(uri))
+            .await
+        {
+            Ok(resp) => Ok(normalize_paths(
+                serde_json::to_value(resp).expect("serialize resource"),
+                &self.fixtures_path,
+            )),
+            Err(ServiceError::McpError(error)) => Err(normalize_paths(
+                serde_json::to_value(error).expect("serialize MCP error"),
+                &self.fixtures_path,
+            )),
+            Err(error) => panic!("read resource: {error}"),
+        }
+    }
+
+    pub(crate) async fn subscribe(&self, uri: &str) {
+        self.service
+            .as_ref()
+            .expect("not shut down")
+            .subscribe(SubscribeRequestParams::new(uri))
+            .await
+            .expect("subscribe");
+    }
+
+    pub(crate) async fn unsubscribe(&self, uri: &str) {
+        self.service
+            .as_ref()
+            .expect("not shut down")
+            .unsubscribe(UnsubscribeRequestParams::new(uri))
+            .await
+            .expect("unsubscribe");
+    }
+
+    /// Take resource update notifications received so far.
+    pub(crate) fn take_resource_updates(&self) -> Vec<String> {
+        std::mem::take(
+            &mut self
+                .service
+                .as_ref()
+                .expect("not shut down")
+                .service()
+                .resource_updates
+                .lock()
+                .expect("not poisoned"),
+        )
+    }
+
+    /// Take messages of all progress notifications received so far.
+    pub(crate) fn take_progress_messages(&self) -> Vec<String> {
+        std::mem::take(
+            &mut self
+                .service
+                .as_ref()
+                .expect("not shut down")
+                .service()
+                .progress_messages
+                .lock()
+                .expect("not poisoned"),
+        )
+    }
+
+    /// Wait until at least one resource update notification was received and take them.
+    pub(crate) async fn wait_for_resource_updates(&self) -> Vec<String> {
+        for _ in 0..100 {
+            let updates = self.take_resource_updates();
+            if !updates.is_empty() {
+                return updates;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("no resource update received");
+    }
+
     pub(crate) async fn shutdown(mut self) {
         // take service service BEFORE potentially panicking
         let service = self.service.take().expect("not shut down yet");
 
         service.cancel().await.expect("shut down service");
+
+        if let Some(mut http) = self.http.take() {
+            #[cfg(unix)]
+            {
+                let pid = http.child.id().expect("server running");
+                let status = Command::new("kill")
+                    .arg(pid.to_string())
+                    .status()
+                    .await
+                    .expect("send SIGTERM");
+                assert!(status.success());
+
+                let status = http.child.wait().await.expect("wait for server");
+                assert!(status.success(), "server exit was not clean: {status}");
+            }
+
+            // there is no way to send ctrl-c to a single process
+            #[cfg(not(unix))]
+            http.child.kill().await.expect("kill server");
+        }
     }
 }
 
@@ -221,6 +803,36 @@ fn normalize_paths(value: Value, fixtures_path: &str) -> Value {
     }
 }
 
+/// Arguments that replace the language server with a replay of a capture in `fixtures/replay`.
+///
+/// Captures can be recorded via `--intercept-io-format=jsonl`.
+pub(crate) fn replay_args(capture: &str) -> [String; 2] {
+    let server_path = cargo_bin(pkg_name!()).canonicalize().expect("canonicalize");
+    let capture_path = Path::new(file!())
+        .parent()
+        .expect("parent 1")
+        .parent()
+        .expect("parent 2")
+        .join("fixtures")
+        .join("replay")
+        .join(capture)
+        .canonicalize()
+        .expect("canonicalize");
+    [
+        format!("--lsp-command={}", server_path.display()),
+        format!("--lsp-arg=--lsp-replay={}", capture_path.display()),
+    ]
+}
+
+/// Arguments that replace the language server with the fake one of `--mock-lsp`, which starts instantly.
+pub(crate) fn mock_args() -> [String; 2] {
+    let server_path = cargo_bin(pkg_name!()).canonicalize().expect("canonicalize");
+    [
+        format!("--lsp-command={}", server_path.display()),
+        "--lsp-arg=--mock-lsp".to_owned(),
+    ]
+}
+
 pub(crate) fn map<const N: usize>(m: [(&'static str, Value); N]) -> JsonObject {
     m.into_iter().map(|(k, v)| (k.to_owned(), v)).collect()
 }
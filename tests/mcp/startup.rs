@@ -0,0 +1,57 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_missing_language_server() {
+    let setup = TestSetup::new_with_args(&["--lsp-command", "/does/not/exist"]).await;
+
+    // tools are still listed so that the client can learn about the problem
+    assert!(!setup.list_all_tools().await.is_empty());
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol(map([
+            ("query", json!("MyMainStruct")),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32603,
+      "message": "cannot start language server for rust: binary `/does/not/exist` not found\n- use `--lsp-command` to point to the binary"
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_missing_secondary_language_server() {
+    let setup = TestSetup::new_with_args(&[
+        "--secondary-language=custom",
+        "--custom-lsp-command=/does/not/exist",
+        "--custom-lsp-file-extension=toml",
+    ])
+    .await;
+
+    // primary language server is used anyways
+    setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol(map([
+            ("file", json!("Cargo.toml")),
+        ])).await.unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no language server handles `.toml` files like Cargo.toml, supported are: rust (.rs)",
+      "data": {
+        "request_id": 2
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
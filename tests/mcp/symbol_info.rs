@@ -1,6 +1,8 @@
-use serde_json::json;
+use std::{collections::BTreeMap, time::Duration};
 
-use crate::setup::{TestSetup, map};
+use serde_json::{Value, json};
+
+use crate::setup::{TestSetup, map, mock_args};
 
 #[tokio::test]
 async fn test_info_for_all_in_file() {
@@ -51,6 +53,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "sub",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 5,
@@ -91,7 +94,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 1,
-                  "character": 12
+                  "character": 12,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -109,6 +113,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "my_lib_fn",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 14,
@@ -157,6 +162,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "accu",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 15,
@@ -193,7 +199,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 16,
-                  "character": 16
+                  "character": 16,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -211,6 +218,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "accu",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 16,
@@ -247,7 +255,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 17,
-                  "character": 16
+                  "character": 16,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -265,6 +274,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "accu",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 17,
@@ -301,7 +311,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 18,
-                  "character": 16
+                  "character": 16,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -319,6 +330,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "accu",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 18,
@@ -355,7 +367,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 19,
-                  "character": 5
+                  "character": 5,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -373,6 +386,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "my_private_lib_fn",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 23,
@@ -408,7 +422,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 18,
-                  "character": 41
+                  "character": 41,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -426,6 +441,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "foo",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 28,
@@ -461,7 +477,8 @@ async fn test_info_for_all_in_file() {
                 {
                   "file": "src/lib.rs",
                   "line": 18,
-                  "character": 63
+                  "character": 63,
+                  "proximity": "same_file"
                 }
               ]
             }
@@ -479,6 +496,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "main",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 32,
@@ -531,6 +549,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "MyMainStruct",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 39,
@@ -578,6 +597,7 @@ async fn test_info_for_all_in_file() {
           "info": [
             {
               "token": {
+                "name": "field",
                 "location": {
                   "file": "src/lib.rs",
                   "line": 40,
@@ -640,6 +660,7 @@ async fn test_multi_match() {
       "info": [
         {
           "token": {
+            "name": "accu",
             "location": {
               "file": "src/lib.rs",
               "line": 15,
@@ -676,12 +697,14 @@ async fn test_multi_match() {
             {
               "file": "src/lib.rs",
               "line": 16,
-              "character": 16
+              "character": 16,
+              "proximity": "same_file"
             }
           ]
         },
         {
           "token": {
+            "name": "accu",
             "location": {
               "file": "src/lib.rs",
               "line": 16,
@@ -718,12 +741,14 @@ async fn test_multi_match() {
             {
               "file": "src/lib.rs",
               "line": 17,
-              "character": 16
+              "character": 16,
+              "proximity": "same_file"
             }
           ]
         },
         {
           "token": {
+            "name": "accu",
             "location": {
               "file": "src/lib.rs",
               "line": 17,
@@ -760,12 +785,14 @@ async fn test_multi_match() {
             {
               "file": "src/lib.rs",
               "line": 18,
-              "character": 16
+              "character": 16,
+              "proximity": "same_file"
             }
           ]
         },
         {
           "token": {
+            "name": "accu",
             "location": {
               "file": "src/lib.rs",
               "line": 18,
@@ -802,7 +829,8 @@ async fn test_multi_match() {
             {
               "file": "src/lib.rs",
               "line": 19,
-              "character": 5
+              "character": 5,
+              "proximity": "same_file"
             }
           ]
         }
@@ -813,6 +841,186 @@ async fn test_multi_match() {
     setup.shutdown().await;
 }
 
+#[tokio::test]
+async fn test_token_type() {
+    let setup = TestSetup::new().await;
+
+    let file = "src/lib.rs";
+
+    let results = setup
+        .symbol_info_ok(map([
+            ("file", json!(file)),
+            ("name", json!("sub")),
+            ("token_type", json!("namespace")),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(results["info"][0]["token"], @r#"
+    {
+      "name": "sub",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 5,
+        "character": 5
+      },
+      "token_type": "namespace",
+      "modifiers": [
+        "declaration"
+      ]
+    }
+    "#);
+
+    let results = setup
+        .symbol_info_ok(map([
+            ("file", json!(file)),
+            ("name", json!("sub")),
+            ("token_type", json!("function")),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "info": []
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_position_only() {
+    let setup = TestSetup::new().await;
+
+    let file = "src/lib.rs";
+
+    let results = setup
+        .symbol_info_ok(map([
+            ("file", json!(file)),
+            ("line", json!(15)),
+            ("character", json!(11)),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(results["info"][0]["token"], @r#"
+    {
+      "name": "accu",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 15,
+        "character": 9
+      },
+      "token_type": "variable",
+      "modifiers": [
+        "declaration"
+      ]
+    }
+    "#);
+
+    let results = setup
+        .symbol_info(map([("file", json!(file))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "either `name` or `line` is required",
+      "data": {
+        "request_id": 2
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_partial_results() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .symbol_info_with_progress(map([
+            ("file", json!("src/lib.rs")),
+            ("line", json!(15)),
+            ("character", json!(11)),
+        ]))
+        .await
+        .expect("no error");
+    let info = &results["info"][0];
+
+    // notifications may arrive after the tool result
+    let mut sections = BTreeMap::new();
+    for _ in 0..100 {
+        for message in setup.take_progress_messages() {
+            // language server progress is plain text
+            let Ok(partial) = serde_json::from_str::<Value>(&message) else {
+                continue;
+            };
+            assert_eq!(partial["token"], info["token"]);
+            let section = partial["section"].as_str().expect("str").to_owned();
+            sections.insert(section, partial["value"].clone());
+        }
+        if sections.len() == 6 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // partial results match the final result
+    assert_eq!(
+        sections.keys().collect::<Vec<_>>(),
+        [
+            "declarations",
+            "definitions",
+            "hover",
+            "implementations",
+            "references",
+            "type_definitions",
+        ],
+    );
+    for (section, value) in sections {
+        assert_eq!(value, info[&section], "{section}");
+    }
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_fuzzy() {
+    let setup = TestSetup::new().await;
+
+    let file = "src/lib.rs";
+
+    let results = setup
+        .symbol_info_ok(map([("file", json!(file)), ("name", json!("private_lib"))]))
+        .await;
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "info": []
+    }
+    "#);
+
+    let results = setup
+        .symbol_info_ok(map([
+            ("file", json!(file)),
+            ("name", json!("private_lib")),
+            ("fuzzy", json!(true)),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(results["info"][0]["token"], @r#"
+    {
+      "name": "my_private_lib_fn",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 23,
+        "character": 4
+      },
+      "token_type": "function",
+      "modifiers": [
+        "declaration"
+      ]
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
 #[tokio::test]
 async fn test_foreign_symbol() {
     let setup = TestSetup::new().await.with_normalize_paths(false);
@@ -835,7 +1043,7 @@ async fn test_foreign_symbol() {
                 .expect("should be string")
                 .to_owned()
         })
-        .filter(|file| file.starts_with("/"))
+        .filter(|file| file.starts_with("dep://"))
         .collect::<Vec<_>>();
     assert_eq!(files.len(), 1);
     let file = &files[0];
@@ -851,8 +1059,9 @@ async fn test_foreign_symbol() {
       "info": [
         {
           "token": {
+            "name": "my_lib_fn",
             "location": {
-              "file": "/fixtures/dependency_lib/src/lib.rs",
+              "file": "dep://dependency_lib@0.1.0/src/lib.rs",
               "line": 1,
               "character": 8
             },
@@ -880,7 +1089,8 @@ async fn test_foreign_symbol() {
             {
               "file": "src/lib.rs",
               "line": 2,
-              "character": 21
+              "character": 21,
+              "proximity": "workspace"
             }
           ]
         }
@@ -905,7 +1115,229 @@ async fn test_file_not_found() {
     insta::assert_json_snapshot!(results, @r#"
     {
       "code": -32602,
-      "message": "file not found: does_not_exist.rs"
+      "message": "file not found: does_not_exist.rs\n- paths are relative to the workspace root, use `find_symbol` to look up where a symbol is defined",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_absolute_path_instead_of_relative() {
+    let setup = TestSetup::new().await.with_normalize_paths(false);
+
+    let results = setup
+        .symbol_info(map([
+            ("file", json!("/src/lib.rs")),
+            ("name", json!("my_lib_fn")),
+        ]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "absolute path given where a relative one is expected: /src/lib.rs\n- paths are relative to the workspace root, use `src/lib.rs`",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_file_outside_workspace() {
+    let setup = TestSetup::new().await;
+
+    let results = setup
+        .symbol_info(map([
+            ("file", json!("../does_not_exist/src/lib.rs")),
+            ("name", json!("foo")),
+        ]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "file is outside of the workspace: ../does_not_exist/src/lib.rs\n- use a path relative to the workspace root `/fixtures/main_lib` or add the folder with the `add_workspace_folder` tool",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_position_beyond_end_of_file() {
+    let setup = TestSetup::new().await.with_normalize_paths(false);
+
+    let results = setup
+        .symbol_info(map([("file", json!("src/lib.rs")), ("line", json!(10000))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "line 10000 is beyond the end of src/lib.rs, which has 41 lines\n- the file probably changed since the position was looked up, pass `name` or look up the position again with `file_overview` or `find_symbol`",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unsupported_file_type() {
+    let setup = TestSetup::new().await.with_normalize_paths(false);
+
+    let results = setup
+        .symbol_info(map([("file", json!("Cargo.toml")), ("name", json!("foo"))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "no language server handles `.toml` files like Cargo.toml, supported are: rust (.rs)",
+      "data": {
+        "request_id": 1
+      }
+    }
+    "#);
+
+    let results = setup
+        .find_symbol(map([("file", json!("Makefile"))]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(results, @r#"
+    {
+      "code": -32602,
+      "message": "no language server handles files without extension like Makefile, supported are: rust (.rs)",
+      "data": {
+        "request_id": 2
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_references_proximity() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("src/nested")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"near\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "mod nested;\nmod sibling;\n\npub fn helper() -> u32 {\n    1\n}\n\npub fn local() -> u32 {\n    helper()\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/nested/mod.rs"),
+        "pub fn far() -> u32 {\n    crate::helper()\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/sibling.rs"),
+        "pub fn near() -> u32 {\n    crate::helper() + crate::helper()\n}\n",
+    )
+    .unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    let result = setup
+        .symbol_info_ok(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("helper")),
+            ("line", json!(4)),
+        ]))
+        .await;
+    insta::assert_json_snapshot!(result["info"][0]["references"], @r#"
+    [
+      {
+        "file": "src/lib.rs",
+        "line": 9,
+        "character": 5,
+        "proximity": "same_file"
+      },
+      {
+        "file": "src/sibling.rs",
+        "line": 2,
+        "character": 12,
+        "proximity": "same_module"
+      },
+      {
+        "file": "src/sibling.rs",
+        "line": 2,
+        "character": 30,
+        "proximity": "same_module"
+      },
+      {
+        "file": "src/nested/mod.rs",
+        "line": 2,
+        "character": 12,
+        "proximity": "workspace"
+      }
+    ]
+    "#);
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cfg() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        r#"#[cfg(feature = "serde")]
+pub fn gated() {}
+
+pub fn always() {}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(unix, windows))]
+    fn helper() {}
+}
+"#,
+    )
+    .unwrap();
+
+    let [command, arg] = mock_args();
+    let setup =
+        TestSetup::new_with_workspaces_and_args(&[root.to_str().unwrap()], &[&command, &arg]).await;
+
+    let mut cfgs = BTreeMap::new();
+    for name in ["gated", "always", "helper"] {
+        let result = setup
+            .symbol_info_ok(map([("file", json!("src/lib.rs")), ("name", json!(name))]))
+            .await;
+        cfgs.insert(name, result["info"][0]["cfg"].clone());
+    }
+    insta::assert_json_snapshot!(cfgs, @r#"
+    {
+      "always": null,
+      "gated": [
+        "feature = \"serde\""
+      ],
+      "helper": [
+        "test",
+        "any(unix, windows)"
+      ]
     }
     "#);
 
@@ -0,0 +1,85 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_syntax_tree_disabled() {
+    let setup = TestSetup::new().await;
+
+    let tools = setup.list_all_tools().await;
+    assert!(!tools.iter().any(|tool| tool.name == "view_item_tree"));
+    assert!(!tools.iter().any(|tool| tool.name == "view_syntax_tree"));
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_syntax_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"trees\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "macro_rules! make {\n    ($name:ident) => {\n        pub fn $name() {}\n    };\n}\n\nmake!(generated);\n\npub fn plain() -> u8 {\n    1\n}\n",
+    )
+    .unwrap();
+
+    let setup = TestSetup::new_with_workspaces_and_args(
+        &[root.to_str().unwrap()],
+        &["--enable-syntax-tree-tools"],
+    )
+    .await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .view_item_tree(map([("file", json!("src/lib.rs"))]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "item_tree": "// AstId: MacroRules[5FDE, 0]\nmacro_rules! make { ... }\n\n// AstId: MacroCall[5FDE, 0], SyntaxContextId: ROOT2024, ExpandTo: Items\nmake!(...);\n\n// AstId: Fn[8ECA, 0]\npub fn plain;\n"
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .view_syntax_tree(map([
+                ("file", json!("src/lib.rs")),
+                ("start_line", json!(10)),
+                ("end_line", json!(10)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "syntax_tree": "SOURCE_FILE@0..129\n  FN@98..128\n    BLOCK_EXPR@119..128\n      STMT_LIST@119..128\n        WHITESPACE@120..125 \"\\n    \"\n        LITERAL@125..126\n          INT_NUMBER@125..126 \"1\"\n        WHITESPACE@126..127 \"\\n\"\n"
+    }
+    "#,
+    );
+
+    let err = setup
+        .view_syntax_tree(map([
+            ("file", json!("src/lib.rs")),
+            ("start_line", json!(3)),
+            ("end_line", json!(2)),
+        ]))
+        .await
+        .unwrap_err();
+    insta::assert_json_snapshot!(err, @r#"
+    {
+      "code": -32602,
+      "message": "start_line 3 is after end_line 2",
+      "data": {
+        "request_id": 3
+      }
+    }
+    "#);
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,103 @@
+use crate::setup::{TestSetup, map};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_timeout_per_call() {
+    let setup = TestSetup::new().await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+            ("timeout_secs", json!(0)),
+        ])).await,
+        @r#"
+    {
+      "symbols": [],
+      "timed_out": true
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup.symbol_info_ok(map([
+            ("file", json!("src/lib.rs")),
+            ("name", json!("MyMainStruct")),
+            ("timeout_secs", json!(0)),
+        ])).await,
+        @r#"
+    {
+      "info": [],
+      "timed_out": true
+    }
+    "#,
+    );
+
+    // generous timeout does not change the result
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+            ("timeout_secs", json!(600)),
+        ])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_timeout_cli() {
+    let setup = TestSetup::new_with_args(&["--tool-timeout=0"]).await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+        ])).await,
+        @r#"
+    {
+      "symbols": [],
+      "timed_out": true
+    }
+    "#,
+    );
+
+    // per-call timeout takes precedence
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+            ("timeout_secs", json!(600)),
+        ])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "deprecated": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 39,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
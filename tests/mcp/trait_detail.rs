@@ -0,0 +1,163 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+const LIB: &str = r#"/// Something with an area.
+pub trait Shape {
+    const SIDES: usize;
+    const NAME: &str = "shape";
+
+    fn area(&self) -> f64;
+
+    fn describe(&self) -> String {
+        let area = self.area();
+        format!("{} with area {area}", Self::NAME)
+    }
+}
+
+pub struct Square(pub f64);
+
+impl Shape for Square {
+    const SIDES: usize = 4;
+
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+}
+
+impl<T> Shape for Vec<T> {
+    const SIDES: usize = 0;
+
+    fn area(&self) -> f64 {
+        self.len() as f64
+    }
+}
+"#;
+
+#[tokio::test]
+async fn test_trait_detail() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"shapes\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), LIB).unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .trait_detail(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("Shape")),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "name": "Shape",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 1,
+        "character": 1
+      },
+      "required": 2,
+      "provided": 2,
+      "items": [
+        {
+          "name": "SIDES",
+          "kind": "Constant",
+          "provided": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 3,
+            "character": 5
+          }
+        },
+        {
+          "name": "NAME",
+          "kind": "Constant",
+          "provided": true,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 4,
+            "character": 5
+          }
+        },
+        {
+          "name": "area",
+          "kind": "Method",
+          "provided": false,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 6,
+            "character": 5
+          }
+        },
+        {
+          "name": "describe",
+          "kind": "Method",
+          "provided": true,
+          "location": {
+            "file": "src/lib.rs",
+            "line": 8,
+            "character": 5
+          }
+        }
+      ],
+      "implementor_count": 2,
+      "implementors": [
+        {
+          "type_name": "Square",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 16,
+            "character": 16
+          }
+        },
+        {
+          "type_name": "Vec<T>",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 24,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // by line within the trait
+    insta::assert_json_snapshot!(
+        setup
+            .trait_detail(map([("file", json!("src/lib.rs")), ("line", json!(9))]))
+            .await
+            .unwrap()["name"],
+        @r#""Shape""#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .trait_detail(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("Square")),
+            ]))
+            .await
+            .unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no trait found: `Square` in src/lib.rs\n- the trait must be declared in the given file, use `find_symbol` to look up where it is declared",
+      "data": {
+        "request_id": 3
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
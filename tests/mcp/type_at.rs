@@ -0,0 +1,149 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+#[tokio::test]
+async fn test_type_at() {
+    let setup = TestSetup::new().await;
+
+    // wait for the language server to be ready
+    setup
+        .find_symbol_ok(map([("query", json!("MyMainStruct"))]))
+        .await;
+
+    // variable
+    insta::assert_json_snapshot!(
+        setup
+            .type_at(map([
+                ("file", json!("src/lib.rs")),
+                ("line", json!(15)),
+                ("character", json!(9)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "type": "u64",
+      "type_definitions": []
+    }
+    "#,
+    );
+
+    // expression
+    insta::assert_json_snapshot!(
+        setup
+            .type_at(map([
+                ("file", json!("src/lib.rs")),
+                ("line", json!(16)),
+                ("character", json!(23)),
+                ("end_line", json!(16)),
+                ("end_character", json!(46)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "type": "u64",
+      "type_definitions": []
+    }
+    "#,
+    );
+
+    // function name
+    insta::assert_json_snapshot!(
+        setup
+            .type_at(map([
+                ("file", json!("src/lib.rs")),
+                ("line", json!(14)),
+                ("character", json!(8)),
+            ]))
+            .await
+            .unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no type found at src/lib.rs:14:8\n- point at a variable or expression, or pass `end_line` and `end_character` to select an entire expression",
+      "data": {
+        "request_id": 4
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_type_at_definition_and_coercion() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"typed\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        r#"pub struct Meters(pub f64);
+
+fn takes(s: &str) -> usize {
+    s.len()
+}
+
+pub fn f() -> f64 {
+    let m = Meters(1.0);
+    let s = String::new();
+    m.0 + takes(&s) as f64
+}
+"#,
+    )
+    .unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .type_at(map([
+                ("file", json!("src/lib.rs")),
+                ("line", json!(8)),
+                ("character", json!(9)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "type": "Meters",
+      "type_definitions": [
+        {
+          "file": "src/lib.rs",
+          "line": 1,
+          "character": 12
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .type_at(map([
+                ("file", json!("src/lib.rs")),
+                ("line", json!(10)),
+                ("character", json!(17)),
+                ("end_line", json!(10)),
+                ("end_character", json!(19)),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "type": "&String",
+      "coerced_to": "&str",
+      "type_definitions": []
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,214 @@
+use serde_json::json;
+
+use crate::setup::{TestSetup, map};
+
+const LIB: &str = r#"use std::collections::HashMap;
+
+/// A named collection of shapes.
+pub struct Drawing {
+    /// Name shown in the title bar.
+    pub name: String,
+
+    /// Shapes by their ID.
+    pub(crate) shapes: HashMap<u32, Shape>,
+    scale: f64,
+}
+
+pub struct Meters(pub f64, u8);
+
+/// Something that can be drawn.
+pub enum Shape {
+    /// A circle around the origin.
+    Circle { radius: f64 },
+    Line(f64, f64),
+    Empty,
+}
+"#;
+
+#[tokio::test]
+async fn test_type_members() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"drawing\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), LIB).unwrap();
+
+    let setup = TestSetup::new_with_workspaces(&[root.to_str().unwrap()]).await;
+
+    insta::assert_json_snapshot!(
+        setup
+            .type_members(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("Drawing")),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "name": "Drawing",
+      "kind": "Struct",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 3,
+        "character": 1
+      },
+      "members": [
+        {
+          "name": "name",
+          "kind": "Field",
+          "visibility": "pub",
+          "type": "String",
+          "docs": "Name shown in the title bar.",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 5,
+            "character": 5
+          }
+        },
+        {
+          "name": "shapes",
+          "kind": "Field",
+          "visibility": "pub(crate)",
+          "type": "HashMap<u32, Shape>",
+          "docs": "Shapes by their ID.",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 8,
+            "character": 5
+          }
+        },
+        {
+          "name": "scale",
+          "kind": "Field",
+          "type": "f64",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 10,
+            "character": 5
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // by line within the type
+    insta::assert_json_snapshot!(
+        setup
+            .type_members(map([("file", json!("src/lib.rs")), ("line", json!(19))]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "name": "Shape",
+      "kind": "Enum",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 15,
+        "character": 1
+      },
+      "members": [
+        {
+          "name": "Circle",
+          "kind": "EnumMember",
+          "type": "{ radius: f64 }",
+          "docs": "A circle around the origin.",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 17,
+            "character": 5
+          }
+        },
+        {
+          "name": "Line",
+          "kind": "EnumMember",
+          "type": "(f64, f64)",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 19,
+            "character": 5
+          }
+        },
+        {
+          "name": "Empty",
+          "kind": "EnumMember",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 20,
+            "character": 5
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .type_members(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("Meters")),
+            ]))
+            .await
+            .unwrap(),
+        @r#"
+    {
+      "name": "Meters",
+      "kind": "Struct",
+      "location": {
+        "file": "src/lib.rs",
+        "line": 13,
+        "character": 1
+      },
+      "members": [
+        {
+          "name": "0",
+          "kind": "Field",
+          "visibility": "pub",
+          "type": "f64",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 13,
+            "character": 1
+          }
+        },
+        {
+          "name": "1",
+          "kind": "Field",
+          "type": "u8",
+          "location": {
+            "file": "src/lib.rs",
+            "line": 13,
+            "character": 1
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup
+            .type_members(map([
+                ("file", json!("src/lib.rs")),
+                ("name", json!("Missing")),
+            ]))
+            .await
+            .unwrap_err(),
+        @r#"
+    {
+      "code": -32602,
+      "message": "no struct or enum found: `Missing` in src/lib.rs\n- the type must be declared in the given file, use `find_symbol` to look up where it is declared",
+      "data": {
+        "request_id": 4
+      }
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use crate::setup::{TestSetup, map};
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn test_multiple_workspaces() {
+    let setup = TestSetup::new_with_workspaces(&["main_lib", "dependency_lib"]).await;
+
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("MyMainStruct")),
+        ])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "MyMainStruct",
+          "kind": "Struct",
+          "deprecated": false,
+          "location": {
+            "file": "main_lib/src/lib.rs",
+            "line": 39,
+            "character": 19
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    // dependency is a workspace folder, so it is found without searching dependencies
+    insta::assert_json_snapshot!(
+        setup.find_symbol_ok(map([
+            ("query", json!("my_lib_fn")),
+        ])).await,
+        @r#"
+    {
+      "symbols": [
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "dependency_lib/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "main_lib/src/lib.rs",
+            "line": 14,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "main_lib/unused_workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        },
+        {
+          "name": "my_lib_fn",
+          "kind": "Function",
+          "deprecated": false,
+          "location": {
+            "file": "main_lib/workspace_member/src/lib.rs",
+            "line": 1,
+            "character": 8
+          }
+        }
+      ]
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup.symbol_info_ok(map([
+            ("file", json!("dependency_lib/src/lib.rs")),
+            ("name", json!("my_lib_fn")),
+        ])).await,
+        @r#"
+    {
+      "info": [
+        {
+          "token": {
+            "name": "my_lib_fn",
+            "location": {
+              "file": "dependency_lib/src/lib.rs",
+              "line": 1,
+              "character": 8
+            },
+            "token_type": "function",
+            "modifiers": [
+              "declaration",
+              "public"
+            ]
+          },
+          "hover": [
+            {
+              "language": "rust",
+              "value": "dependency_lib"
+            },
+            {
+              "language": "rust",
+              "value": "pub fn my_lib_fn(left: u64, right: u64) -> u64"
+            }
+          ],
+          "declarations": [
+            {
+              "file": "dependency_lib/src/lib.rs",
+              "line": 1,
+              "character": 8
+            }
+          ],
+          "definitions": [
+            {
+              "file": "dependency_lib/src/lib.rs",
+              "line": 1,
+              "character": 8
+            }
+          ],
+          "implementations": [],
+          "type_definitions": [],
+          "references": [
+            {
+              "file": "main_lib/src/lib.rs",
+              "line": 2,
+              "character": 21,
+              "proximity": "workspace"
+            }
+          ]
+        }
+      ]
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_add_remove_workspace_folder() {
+    let setup = TestSetup::new().await;
+    wait_for_lib_fn_files(&setup, &["src/lib.rs"]).await;
+
+    insta::assert_json_snapshot!(
+        setup.add_workspace_folder(&setup.fixture_path("dependency_lib")).await.unwrap(),
+        @r#"
+    {
+      "folders": [
+        {
+          "name": "main_lib",
+          "path": "/fixtures/main_lib"
+        },
+        {
+          "name": "dependency_lib",
+          "path": "/fixtures/dependency_lib"
+        }
+      ]
+    }
+    "#,
+    );
+    wait_for_lib_fn_files(
+        &setup,
+        &["dependency_lib/src/lib.rs", "main_lib/src/lib.rs"],
+    )
+    .await;
+
+    // adding it again is a no-op
+    setup
+        .add_workspace_folder(&setup.fixture_path("dependency_lib"))
+        .await
+        .unwrap();
+
+    insta::assert_json_snapshot!(
+        without_request_id(setup.add_workspace_folder("dependency_lib").await.unwrap_err()),
+        @r#"
+    {
+      "code": -32602,
+      "message": "path must be absolute: dependency_lib"
+    }
+    "#,
+    );
+
+    insta::assert_json_snapshot!(
+        setup.remove_workspace_folder("dependency_lib").await.unwrap(),
+        @r#"
+    {
+      "folders": [
+        {
+          "name": "main_lib",
+          "path": "/fixtures/main_lib"
+        }
+      ]
+    }
+    "#,
+    );
+    wait_for_lib_fn_files(&setup, &["src/lib.rs"]).await;
+
+    insta::assert_json_snapshot!(
+        without_request_id(setup.remove_workspace_folder("main_lib").await.unwrap_err()),
+        @r#"
+    {
+      "code": -32602,
+      "message": "cannot remove the last workspace folder"
+    }
+    "#,
+    );
+    insta::assert_json_snapshot!(
+        without_request_id(setup.remove_workspace_folder("nope").await.unwrap_err()),
+        @r#"
+    {
+      "code": -32602,
+      "message": "unknown workspace folder: nope"
+    }
+    "#,
+    );
+
+    setup.shutdown().await;
+}
+
+/// Remove the request ID from an error, it depends on how often [`wait_for_lib_fn_files`] polled.
+fn without_request_id(mut err: Value) -> Value {
+    err.as_object_mut().unwrap().remove("data");
+    err
+}
+
+/// Wait until the files that define `my_lib_fn` match, the language server re-indexes after workspace changes.
+async fn wait_for_lib_fn_files(setup: &TestSetup, expected: &[&str]) {
+    let mut files = vec![];
+    for _ in 0..100 {
+        files = lib_fn_files(setup).await;
+        if files == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(files, expected);
+}
+
+/// Files that define `my_lib_fn`, ignoring workspace members.
+///
+/// Empty while the language server re-indexes the workspace.
+async fn lib_fn_files(setup: &TestSetup) -> Vec<String> {
+    let Ok(res) = setup
+        .find_symbol(map([("query", json!("my_lib_fn"))]))
+        .await
+    else {
+        return vec![];
+    };
+    let mut files = res["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["location"]["file"].as_str().unwrap().to_owned())
+        .filter(|file| !file.contains("workspace_member"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}